@@ -0,0 +1,199 @@
+//! timings of building, sorting, searching and rescanning trees of generated files, and the memory they take
+//!
+//! `cargo bench --bench tree` runs every benchmark on trees of 10k and 100k
+//! files, `MIDDAY_BENCH_FILES=10000,100000,1000000` sets the sizes and the
+//! arguments not starting with `-` keep the benchmarks whose name holds one
+//! of them, like `cargo bench --bench tree -- scan`. The trees are generated
+//! once in the temporary directory and kept for the next runs.
+//!
+//! The benchmarks have their own small harness rather than criterion: the
+//! time of each one is printed as its fastest and median run, and the memory
+//! is counted by the allocator of the binary, which criterion does not measure.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use midday_commander::fs_node::{create_node_from_path, FsNodeType};
+use midday_commander::search::{spawn_search, NamePattern, SearchEvent, SearchQuery};
+use midday_commander::sort::{SortKey, SortMode};
+use midday_commander::vfs;
+
+/// tree sizes run without `MIDDAY_BENCH_FILES`
+const DEFAULT_FILES: &[usize] = &[10_000, 100_000];
+
+/// files of a generated directory, the others hold directories
+const FILES_PER_DIRECTORY: usize = 100;
+
+/// directories of a generated directory
+const FANOUT: usize = 10;
+
+/// a benchmark stops at this many runs or past this time, whichever comes first
+const RUNS: usize = 10;
+const BUDGET: Duration = Duration::from_secs(10);
+
+/// the system allocator keeping count of the bytes in use
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn main() -> io::Result<()> {
+    let filters: Vec<String> = env::args().skip(1).filter(|arg| !arg.starts_with('-')).collect();
+    let sizes = match env::var("MIDDAY_BENCH_FILES") {
+        Ok(sizes) => sizes.split(',').filter_map(|size| size.trim().replace('_', "").parse().ok()).collect(),
+        Err(_) => DEFAULT_FILES.to_vec(),
+    };
+    for files in sizes {
+        let root = synthetic_tree(files)?;
+        let selected = |name: &str| filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()));
+        let label = |name: &str| format!("{name}/{files}");
+
+        if selected("scan") {
+            bench(&label("scan"), || create_node_from_path(&root).map(|node| node.children.len()))?;
+        }
+        // the other benchmarks work on a tree loaded once
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let tree = create_node_from_path(&root).map_err(io::Error::other)?.into_ref();
+        if selected("memory") {
+            let bytes = ALLOCATED.load(Ordering::Relaxed).saturating_sub(before);
+            let nodes = tree.read().iter_dfs().count().max(1);
+            println!("{:<24} {:>10} KiB  {:>6} bytes per node", label("memory"), bytes / 1024, bytes / nodes);
+        }
+        let directories: Vec<_> = tree.read().iter_filtered(|node| node.node_type == FsNodeType::Directory).collect();
+        for (name, mode) in [
+            ("sort-name", SortMode::new(SortKey::Name)),
+            ("sort-extension", SortMode::new(SortKey::Extension)),
+            ("sort-size", SortMode::new(SortKey::Size)),
+            ("sort-mtime", SortMode::new(SortKey::MTime)),
+        ] {
+            if selected(name) {
+                bench(&label(name), || {
+                    // the reverse order first, so each run has the same work to do
+                    let reversed = SortMode {
+                        descending: !mode.descending,
+                        ..mode
+                    };
+                    tree.write().sort_children(reversed);
+                    tree.write().sort_children(mode);
+                    for directory in &directories {
+                        directory.write().sort_children(reversed);
+                        directory.write().sort_children(mode);
+                    }
+                    Ok::<_, io::Error>(())
+                })?;
+            }
+        }
+        if selected("search") {
+            bench(&label("search"), || search(&root, "*7*.txt"))?;
+        }
+        if selected("rescan") {
+            bench(&label("rescan"), || {
+                let mut changed = tree.write().rescan()?;
+                for directory in &directories {
+                    changed |= directory.write().rescan()?;
+                }
+                Ok::<_, io::Error>(changed)
+            })?;
+        }
+        if selected("walk") {
+            bench(&label("walk"), || Ok::<_, io::Error>(tree.read().iter_dfs().count()))?;
+        }
+    }
+    Ok(())
+}
+
+/// run `work` a few times and print the fastest and the median time
+fn bench<T, E: std::fmt::Display>(name: &str, mut work: impl FnMut() -> Result<T, E>) -> io::Result<()> {
+    let started = Instant::now();
+    let mut times = Vec::with_capacity(RUNS);
+    while times.len() < RUNS && (times.is_empty() || started.elapsed() < BUDGET) {
+        let run = Instant::now();
+        black_box(work().map_err(|err| io::Error::other(format!("{name}: {err}")))?);
+        times.push(run.elapsed());
+    }
+    times.sort();
+    println!("{name:<24} fastest {:>12.3?}  median {:>12.3?}  ({} runs)", times[0], times[times.len() / 2], times.len());
+    Ok(())
+}
+
+/// the number of files the search finds below `root`
+fn search(root: &Path, pattern: &str) -> Result<usize, String> {
+    let query = SearchQuery {
+        start: root.to_path_buf(),
+        name: NamePattern::parse(pattern, false, false)?,
+        content: None,
+        case_sensitive: false,
+        gitignore: false,
+    };
+    let handle = spawn_search(vfs::local(), query);
+    let mut found = 0;
+    for event in handle.events.iter() {
+        match event {
+            SearchEvent::Found(_) => found += 1,
+            SearchEvent::Error(..) => {}
+            SearchEvent::Done { .. } => break,
+        }
+    }
+    Ok(found)
+}
+
+/// a tree of `files` small files in the temporary directory, generated the first time
+///
+/// Each directory holds [`FILES_PER_DIRECTORY`] files of a few sizes and
+/// extensions and [`FANOUT`] directories, down as far as the files need.
+fn synthetic_tree(files: usize) -> io::Result<PathBuf> {
+    let root = env::temp_dir().join(format!("midday-commander-bench-{files}"));
+    let done = root.join(".complete");
+    if done.exists() {
+        return Ok(root);
+    }
+    println!("generating {files} files in {}", root.display());
+    let _ = fs::remove_dir_all(&root);
+    let mut pending = VecDeque::from([root.clone()]);
+    let mut left = files;
+    let mut index = 0;
+    while left > 0 {
+        let Some(directory) = pending.pop_front() else {
+            break;
+        };
+        fs::create_dir_all(&directory)?;
+        for _ in 0..FILES_PER_DIRECTORY.min(left) {
+            let extension = ["txt", "rs", "png", "tar.gz", "md"][index % 5];
+            fs::write(directory.join(format!("file{index}.{extension}")), vec![b'x'; index % 4096])?;
+            index += 1;
+            left -= 1;
+        }
+        for child in 0..FANOUT {
+            pending.push_back(directory.join(format!("dir{child}")));
+        }
+    }
+    fs::write(done, "")?;
+    Ok(root)
+}
@@ -0,0 +1,513 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use ratatui::layout::Rect;
+use ratatui::Frame;
+
+use crate::clipboard;
+use crate::commands::{self, Command, CommandOutcome, Keymap, PromptKind};
+use crate::config::Config;
+use crate::editor;
+use crate::event::AppEvent;
+use crate::fs_node::TreeUpdate;
+use crate::help::{self, Help};
+use crate::mouse::Mouse;
+use crate::panel::PanelManager;
+use crate::search::SearchResults;
+use crate::shell::{self, Shell, ShellAction};
+use crate::ui::chmod_dialog::ChmodResult;
+use crate::ui::chown_dialog::ChownResult;
+use crate::ui::connection_dialog::ConnectionResult;
+use crate::ui::dialog::DialogResult;
+use crate::ui::find_dialog::FindResult;
+use crate::ui::history_dialog::HistoryResult;
+use crate::ui::hotlist_dialog::HotlistResult;
+use crate::ui::menu_bar::{MenuBar, MenuResult};
+use crate::ui::prompt::PromptResult;
+use crate::ui::rename_dialog::RenameResult;
+use crate::ui::theme::Theme;
+use crate::ui::{self, Dialog};
+use crate::watcher::TreeWatcher;
+
+/// what the application does after a screen took an event
+#[derive(Debug)]
+pub enum ScreenAction {
+    /// the event means nothing to the screen
+    Ignored,
+    /// the screen stays open, the outcome is carried out like one of a command
+    Stay(CommandOutcome),
+    /// the screen is done and taken off the stack
+    Close,
+}
+
+/// what the loop does once the application took an event
+#[derive(Debug)]
+pub enum Step {
+    /// wait for the next event
+    Continue,
+    Quit,
+    /// an outcome needing the terminal, like an external editor or a shell line
+    Terminal(CommandOutcome),
+    /// hand the terminal to the subshell, with a command line to run in it
+    Subshell(Option<String>),
+}
+
+/// a full-screen mode shown in place of the panels, like the viewer or the search results
+///
+/// Screens are stacked by the [`App`], the top one is drawn and takes the
+/// events, closing it shows the one below and at last the panels.
+pub trait Screen {
+    fn handle_event(&mut self, event: &AppEvent, manager: &mut PanelManager) -> ScreenAction;
+
+    /// draw the screen over the whole `area`
+    fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme);
+
+    /// take what background work sent since the last draw, open or not
+    fn poll(&mut self) {}
+
+    /// true while background work is running, the screen is then drawn more often
+    fn is_busy(&self) -> bool {
+        false
+    }
+
+    /// the outcome of the answer to a prompt the screen asked, none if it did not ask it
+    fn submit(&mut self, _kind: PromptKind, _input: &str) -> Option<CommandOutcome> {
+        None
+    }
+
+    /// called once the screen is closed, to bring the panels up to date with what it did
+    fn closed(&mut self, _manager: &mut PanelManager) {}
+
+    /// the help topic about the screen, opened by F1
+    fn help_topic(&self) -> &'static str {
+        help::INDEX
+    }
+}
+
+/// the state of the whole application, the panels with what is opened over them
+///
+/// A dialog takes every key, then the top screen does, then the panels with
+/// the command line under them.
+pub struct App {
+    pub manager: PanelManager,
+    pub keymap: Keymap,
+    /// first key of a chord, waiting for the second one
+    pub chord: Option<KeyEvent>,
+    /// last error or notice, cleared by the next command
+    pub message: Option<String>,
+    /// prompt or form a command is waiting for
+    pub dialog: Option<Dialog>,
+    /// command line under the panels and the output of its commands
+    pub shell: Shell,
+    pub mouse: Mouse,
+    /// full-screen modes above the panels, the last one is shown
+    screens: Vec<Box<dyn Screen>>,
+    /// dialogs put aside for the help, with the number of screens under the help
+    suspended: Vec<(usize, Dialog)>,
+}
+
+impl App {
+    pub fn new(manager: PanelManager, keymap: Keymap) -> App {
+        App {
+            manager,
+            keymap,
+            chord: None,
+            message: None,
+            dialog: None,
+            shell: Shell::new(),
+            mouse: Mouse::new(),
+            screens: Vec::new(),
+            suspended: Vec::new(),
+        }
+    }
+
+    /// put text pasted into the terminal in the input of the dialog, or on the command line
+    ///
+    /// It goes in as a single line, a pasted line break runs nothing.
+    pub fn paste(&mut self, text: &str) {
+        let text = clipboard::single_line(text);
+        let on_panels = self.on_panels();
+        match self.dialog.as_mut() {
+            Some(dialog) => dialog.paste(&text),
+            None if on_panels => {
+                self.shell.completer.close();
+                self.shell.input.insert(&text);
+            }
+            None => {}
+        }
+    }
+
+    /// open a screen over the current one
+    pub fn push(&mut self, screen: Box<dyn Screen>) {
+        self.screens.push(screen);
+    }
+
+    /// true if the panels are shown, no screen being open
+    pub fn on_panels(&self) -> bool {
+        self.screens.is_empty()
+    }
+
+    /// let every open screen and the panels take what their background work sent
+    pub fn poll(&mut self) {
+        for screen in self.screens.iter_mut() {
+            screen.poll();
+        }
+        if let Some(error) = self.manager.poll_loading() {
+            self.message = Some(error);
+        }
+    }
+
+    /// true if an open screen or a panel listing a remote directory waits on background work
+    pub fn is_busy(&self) -> bool {
+        self.screens.iter().any(|screen| screen.is_busy()) || self.manager.is_loading()
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, theme: &Theme) {
+        let screen = self.screens.last_mut().map(|screen| screen.as_mut() as &mut dyn Screen);
+        ui::draw(frame, &mut self.manager, screen, theme, self.message.as_deref(), self.dialog.as_mut(), &self.shell);
+    }
+
+    /// hand an event to the top screen, none if the panels are shown
+    ///
+    /// A screen done with is closed here and told so.
+    pub fn screen_event(&mut self, event: &AppEvent) -> Option<ScreenAction> {
+        let screen = self.screens.last_mut()?;
+        let action = screen.handle_event(event, &mut self.manager);
+        if let ScreenAction::Close = action
+            && let Some(mut screen) = self.screens.pop()
+        {
+            screen.closed(&mut self.manager);
+            if self.suspended.last().is_some_and(|(depth, _)| *depth == self.screens.len()) {
+                self.dialog = self.suspended.pop().map(|(_, dialog)| dialog);
+            }
+        }
+        Some(action)
+    }
+
+    /// the help topic about what takes the keys, the dialog, the top screen or the panels
+    pub fn help_topic(&self) -> &'static str {
+        match (&self.dialog, self.screens.last()) {
+            (Some(dialog), _) => dialog.help_topic(),
+            (None, Some(screen)) => screen.help_topic(),
+            (None, None) => help::command_topic(Command::Help),
+        }
+    }
+
+    /// open the help over everything, a dialog open is put aside until the help is closed
+    pub fn open_help(&mut self, topic: &str) {
+        if let Some(dialog) = self.dialog.take() {
+            self.suspended.push((self.screens.len(), dialog));
+        }
+        self.screens.push(Box::new(Help::new(topic, &self.keymap)));
+    }
+
+    /// the outcome of the answer to a prompt, given to the screen that asked it if any
+    pub fn submit(&mut self, kind: PromptKind, input: &str) -> CommandOutcome {
+        let asked = self.screens.last_mut().and_then(|screen| screen.submit(kind, input));
+        asked.unwrap_or_else(|| commands::submit(kind, input, &mut self.manager))
+    }
+
+    /// take what background work sent and close the job dialog of a finished job
+    pub fn update(&mut self) {
+        self.poll();
+        if let Some(Dialog::Job(job)) = self.dialog.as_mut()
+            && let Some(report) = job.poll()
+        {
+            self.dialog = None;
+            match commands::finish_job(report, &mut self.manager) {
+                CommandOutcome::Dialog(modal) => self.dialog = Some(Dialog::Modal(modal)),
+                CommandOutcome::Message(text) => self.message = Some(text),
+                _ => self.message = None,
+            }
+        }
+    }
+
+    /// handle an event of the loop, `area` is the size of the screen
+    pub fn handle(&mut self, event: AppEvent, area: Rect, config: &mut Config, theme: &mut Theme, watcher: Option<&TreeWatcher>) -> Step {
+        let outcome = match event {
+            // jobs are polled and the screen drawn again at the top of the loop
+            AppEvent::Tick | AppEvent::Job | AppEvent::Resize(..) => return Step::Continue,
+            AppEvent::Paste(text) => {
+                self.paste(&text);
+                return Step::Continue;
+            }
+            // background threads only tell what changed, the panels are brought up to date here
+            AppEvent::TreeChanged(changes) => {
+                if let Some(watcher) = watcher
+                    && watcher.apply(&changes)
+                {
+                    self.manager.left.refresh_view();
+                    self.manager.right.refresh_view();
+                }
+                return Step::Continue;
+            }
+            AppEvent::TreeUpdated(updates) => {
+                updates.into_iter().for_each(TreeUpdate::apply);
+                self.manager.left.refresh_view();
+                self.manager.right.refresh_view();
+                return Step::Continue;
+            }
+            AppEvent::Mouse(event) if matches!(self.dialog, Some(Dialog::Menu(_))) => {
+                let (bar, menu_area) = ui::menu_areas(area, self.message.is_some());
+                let Some(Dialog::Menu(menu)) = self.dialog.as_mut() else {
+                    return Step::Continue;
+                };
+                match menu.handle_mouse(&event, bar, menu_area) {
+                    MenuResult::Pending => return Step::Continue,
+                    MenuResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    MenuResult::Run(side, command) => {
+                        self.dialog = None;
+                        commands::dispatch_on(side, command, &mut self.manager)
+                    }
+                }
+            }
+            // other dialogs and full-screen modes are driven by the keyboard alone
+            AppEvent::Mouse(event) if self.dialog.is_none() && self.on_panels() => {
+                match self.mouse.handle(&event, area, self.message.is_some(), !self.shell.visible, &mut self.manager, &self.keymap) {
+                    Some(outcome) => outcome,
+                    None => return Step::Continue,
+                }
+            }
+            // the panels ask for help through their keymap
+            AppEvent::Key(key)
+                if key.kind == KeyEventKind::Press
+                    && key.code == KeyCode::F(1)
+                    && key.modifiers == KeyModifiers::NONE
+                    && (self.dialog.is_some() || !self.on_panels()) =>
+            {
+                let topic = self.help_topic();
+                self.open_help(topic);
+                CommandOutcome::Done
+            }
+            AppEvent::Key(key) if key.kind == KeyEventKind::Press => match self.dialog.as_mut() {
+                Some(Dialog::Prompt(active)) => match active.handle_key(&key) {
+                    PromptResult::Pending => return Step::Continue,
+                    PromptResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    PromptResult::Submit(input) => {
+                        let kind = active.kind;
+                        self.dialog = None;
+                        self.submit(kind, &input)
+                    }
+                },
+                Some(Dialog::Find(find)) => match find.handle_key(&key) {
+                    FindResult::Pending => return Step::Continue,
+                    FindResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    FindResult::Submit(query) => {
+                        self.dialog = None;
+                        let results = SearchResults::start(self.manager.active().vfs(), query);
+                        self.push(Box::new(results));
+                        CommandOutcome::Done
+                    }
+                },
+                Some(Dialog::Rename(rename)) => match rename.handle_key(&key) {
+                    RenameResult::Pending => return Step::Continue,
+                    RenameResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    RenameResult::Apply(renames) => {
+                        self.dialog = None;
+                        commands::apply_renames(&renames, &mut self.manager)
+                    }
+                },
+                Some(Dialog::Chmod(chmod)) => match chmod.handle_key(&key) {
+                    ChmodResult::Pending => return Step::Continue,
+                    ChmodResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    ChmodResult::Apply(targets, options) => {
+                        self.dialog = None;
+                        commands::apply_chmod(&targets, &options, &mut self.manager)
+                    }
+                },
+                Some(Dialog::Chown(chown)) => match chown.handle_key(&key) {
+                    ChownResult::Pending => return Step::Continue,
+                    ChownResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    ChownResult::Apply(targets, options) => {
+                        self.dialog = None;
+                        commands::apply_chown(&targets, &options, &mut self.manager)
+                    }
+                },
+                Some(Dialog::Hotlist(hotlist)) => match hotlist.handle_key(&key) {
+                    HotlistResult::Pending => return Step::Continue,
+                    HotlistResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    HotlistResult::Jump(location) => {
+                        self.dialog = None;
+                        commands::jump_to_location(&location, &mut self.manager)
+                    }
+                },
+                Some(Dialog::Connections(connections)) => match connections.handle_key(&key) {
+                    ConnectionResult::Pending => return Step::Continue,
+                    ConnectionResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    ConnectionResult::Connect(index) => {
+                        self.dialog = None;
+                        commands::connect_saved(index, None, &mut self.manager)
+                    }
+                    ConnectionResult::Edit(index) => {
+                        let form = commands::edit_connection(index, &connections.connections);
+                        self.dialog = None;
+                        form
+                    }
+                },
+                Some(Dialog::Modal(modal)) => match modal.handle_key(&key) {
+                    DialogResult::Pending => return Step::Continue,
+                    DialogResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    DialogResult::Submit(answer) => {
+                        let kind = modal.kind;
+                        self.dialog = None;
+                        commands::answer(kind, &answer, &mut self.manager)
+                    }
+                },
+                Some(Dialog::Job(job)) => {
+                    job.handle_key(&key);
+                    return Step::Continue;
+                }
+                Some(Dialog::Menu(menu)) => match menu.handle_key(&key) {
+                    MenuResult::Pending => return Step::Continue,
+                    MenuResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    MenuResult::Run(side, command) => {
+                        self.dialog = None;
+                        commands::dispatch_on(side, command, &mut self.manager)
+                    }
+                },
+                Some(Dialog::History(history)) => match history.handle_key(&key) {
+                    HistoryResult::Pending => return Step::Continue,
+                    HistoryResult::Cancel => {
+                        self.dialog = None;
+                        return Step::Continue;
+                    }
+                    HistoryResult::Jump(index) => {
+                        self.dialog = None;
+                        commands::jump_in_history(index, &mut self.manager)
+                    }
+                },
+                None => match self.screen_event(&AppEvent::Key(key)) {
+                    Some(ScreenAction::Ignored) => return Step::Continue,
+                    Some(ScreenAction::Stay(outcome)) => outcome,
+                    Some(ScreenAction::Close) => CommandOutcome::Done,
+                    // the key after the start of a chord says which command it is, any other drops it
+                    None if self.chord.is_some() => {
+                        let first = self.chord.take().unwrap_or(key);
+                        match self.keymap.lookup_chord(&first, &key) {
+                            Some(command) => commands::dispatch(command, &mut self.manager),
+                            None => CommandOutcome::Done,
+                        }
+                    }
+                    None => match commands::filter_key(&key, &mut self.manager)
+                        .or_else(|| commands::search_key(&key, &self.keymap, &mut self.manager))
+                        .or_else(|| commands::tree_key(&key, &mut self.manager))
+                    {
+                        Some(outcome) => outcome,
+                        None if config.subshell
+                            && key.code == KeyCode::Char('o')
+                            && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                        {
+                            return Step::Subshell(None);
+                        }
+                        None => match self.shell.handle_key(
+                            &key,
+                            self.keymap.lookup(&key).is_some(),
+                            &commands::command_line_completion(&self.manager),
+                        ) {
+                            ShellAction::Handled => CommandOutcome::Done,
+                            ShellAction::Run(command) if config.subshell && !commands::is_cd(&command) => {
+                                return Step::Subshell(Some(shell::expand_macros(&command, self.manager.active())));
+                            }
+                            ShellAction::Run(command) => commands::run_shell_command(&command, &mut self.shell, &mut self.manager),
+                            ShellAction::Ignored if self.keymap.is_chord_start(&key) => {
+                                self.chord = Some(key);
+                                CommandOutcome::Message(format!("{} and then?", commands::key_name(key.code, key.modifiers)))
+                            }
+                            ShellAction::Ignored => {
+                                let Some(command) = self.keymap.lookup(&key) else {
+                                    return Step::Continue;
+                                };
+                                commands::dispatch(command, &mut self.manager)
+                            }
+                        },
+                    },
+                },
+            },
+            _ => return Step::Continue,
+        };
+        self.carry_out(outcome, config, theme)
+    }
+
+    /// carry out the outcome of a command, the ones needing the terminal are given back
+    pub fn carry_out(&mut self, outcome: CommandOutcome, config: &mut Config, theme: &mut Theme) -> Step {
+        self.message = None;
+        match outcome {
+            CommandOutcome::Done => {}
+            CommandOutcome::Quit => return Step::Quit,
+            CommandOutcome::Message(text) => self.message = Some(text),
+            CommandOutcome::Prompt(next) => self.dialog = Some(Dialog::Prompt(next)),
+            CommandOutcome::Find(find) => self.dialog = Some(Dialog::Find(find)),
+            CommandOutcome::Rename(rename) => self.dialog = Some(Dialog::Rename(rename)),
+            CommandOutcome::Chmod(chmod) => self.dialog = Some(Dialog::Chmod(chmod)),
+            CommandOutcome::Chown(chown) => self.dialog = Some(Dialog::Chown(chown)),
+            CommandOutcome::Hotlist(hotlist) => self.dialog = Some(Dialog::Hotlist(hotlist)),
+            CommandOutcome::Connections(connections) => self.dialog = Some(Dialog::Connections(connections)),
+            CommandOutcome::History(history) => self.dialog = Some(Dialog::History(history)),
+            CommandOutcome::Dialog(modal) => self.dialog = Some(Dialog::Modal(modal)),
+            CommandOutcome::Job(job) => self.dialog = Some(Dialog::Job(job)),
+            CommandOutcome::Menu(menu) => self.dialog = Some(Dialog::Menu(Box::new(MenuBar::new(menu, &self.keymap)))),
+            CommandOutcome::Theme(name) => match Theme::named(&name) {
+                Ok(named) => {
+                    *theme = named;
+                    config.theme = name;
+                }
+                Err(err) => self.message = Some(err.to_string()),
+            },
+            CommandOutcome::Sync(session) => self.push(session),
+            CommandOutcome::DiskUsage(usage) => self.push(usage),
+            CommandOutcome::Help(topic) => self.open_help(topic),
+            // external programs need the terminal, the caller hands it over
+            CommandOutcome::View(opened) => match (&config.viewer, opened.local_path()) {
+                (Some(_), Some(_)) => return Step::Terminal(CommandOutcome::View(opened)),
+                _ => {
+                    let mut opened = opened;
+                    opened.enable_highlighting(&theme.syntax);
+                    self.push(opened);
+                }
+            },
+            outcome @ (CommandOutcome::Run { .. } | CommandOutcome::Escalate(_) | CommandOutcome::Open(_)) => {
+                return Step::Terminal(outcome);
+            }
+            CommandOutcome::Edit(opened) => {
+                match (editor::external_editor(config.editor.as_deref()), opened.local_path()) {
+                    (Some(_), Some(_)) => return Step::Terminal(CommandOutcome::Edit(opened)),
+                    _ => {
+                        let mut opened = opened;
+                        opened.enable_highlighting(&theme.syntax);
+                        self.push(opened);
+                    }
+                }
+            }
+        }
+        Step::Continue
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::filetype;
+use crate::opener::system_opener;
+
+/// what a key asks of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAction {
+    /// Enter
+    Open,
+    /// F3
+    View,
+    /// F4
+    Edit,
+}
+
+/// commands for the files whose name matches one of the patterns, or whose type one of the types
+///
+/// Commands are run by the user's shell with the macros of the command line,
+/// `%f` being the file. The output of a view command is shown in the viewer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Association {
+    /// glob patterns on the file name, like `*.tar.gz`, matched without regard to case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+    /// MIME types like `image/png` or `image/*`, see [`filetype::detect`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edit: Option<String>,
+}
+
+impl Association {
+    fn new(patterns: &[&str], types: &[&str], open: Option<String>, view: Option<&str>) -> Association {
+        Association {
+            patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+            types: types.iter().map(|mime| mime.to_string()).collect(),
+            open,
+            view: view.map(str::to_string),
+            edit: None,
+        }
+    }
+
+    pub fn matches(&self, name: &str, mime: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..MatchOptions::new()
+        };
+        self.patterns
+            .iter()
+            .filter_map(|pattern| Pattern::new(pattern).ok())
+            .any(|pattern| pattern.matches_with(name, options))
+            || self.types.iter().any(|pattern| filetype::mime_matches(pattern, mime))
+    }
+
+    pub fn command(&self, action: FileAction) -> Option<&str> {
+        match action {
+            FileAction::Open => self.open.as_deref(),
+            FileAction::View => self.view.as_deref(),
+            FileAction::Edit => self.edit.as_deref(),
+        }
+    }
+}
+
+/// the association file, like the `mc.ext` of Midnight Commander
+///
+/// The first association matching a file and having a command for the
+/// action wins, files without one go to the viewer and the editor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Associations {
+    #[serde(default, rename = "association")]
+    pub associations: Vec<Association>,
+}
+
+impl Default for Associations {
+    /// archives are listed, images, media and documents go to the desktop
+    fn default() -> Self {
+        let desktop = Some(format!("{} %f", system_opener()));
+        Associations {
+            associations: vec![
+                Association::new(
+                    &["*.tar", "*.tar.gz", "*.tgz", "*.tar.bz2", "*.tbz2", "*.tar.xz", "*.txz", "*.tar.zst"],
+                    &["application/x-tar"],
+                    None,
+                    Some("tar -tvf %f"),
+                ),
+                Association::new(&["*.zip", "*.jar"], &["application/zip"], None, Some("unzip -l %f")),
+                Association::new(&["*.7z"], &["application/x-7z-compressed"], None, Some("7z l %f")),
+                Association::new(&["*.rar"], &["application/vnd.rar"], None, Some("unrar l %f")),
+                Association::new(&["*.deb"], &["application/vnd.debian.binary-package"], None, Some("dpkg-deb -c %f")),
+                Association::new(&["*.rpm"], &["application/x-rpm"], None, Some("rpm -qlp %f")),
+                Association::new(&[], &["image/*", "audio/*", "video/*"], desktop.clone(), None),
+                Association::new(&[], &["application/pdf"], desktop.clone(), Some("pdftotext -layout %f -")),
+                Association::new(&["*.odt", "*.ods", "*.odp", "*.docx", "*.xlsx", "*.pptx", "*.epub"], &[], desktop, None),
+            ],
+        }
+    }
+}
+
+impl Associations {
+    /// `associations.toml` in the directory of the default configuration
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::default_path()?.with_file_name("associations.toml"))
+    }
+
+    /// read the associations of a file, a missing file gives the defaults
+    pub fn load(path: &Path) -> io::Result<Associations> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Associations::default()),
+            Err(err) => return Err(err),
+        };
+        toml::from_str(&text).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err.message()))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+
+    /// command of the first association of a file with one for `action`, by name and type
+    pub fn command(&self, action: FileAction, name: &str, mime: &str) -> Option<&str> {
+        self.associations
+            .iter()
+            .filter(|association| association.matches(name, mime))
+            .find_map(|association| association.command(action))
+    }
+}
@@ -0,0 +1,102 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// programs writing their input to the clipboard, the first one found is used
+///
+/// They cover macOS, Wayland, X with xclip or xsel, and Windows or WSL.
+const COPY_PROGRAMS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// programs printing the clipboard, with no OSC 52 fallback to read it
+const PASTE_PROGRAMS: &[(&str, &[&str])] = &[
+    ("pbpaste", &[]),
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+    ("powershell.exe", &["-NoProfile", "-Command", "Get-Clipboard"]),
+];
+
+/// put `text` in the system clipboard
+///
+/// The clipboard programs of the system are tried first. Without one, the
+/// text is sent to the terminal as an OSC 52 sequence, which most terminals
+/// put in the clipboard, over ssh too.
+pub fn copy(text: &str) -> io::Result<()> {
+    for (program, args) in programs(COPY_PROGRAMS) {
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
+/// the text of the system clipboard
+pub fn paste() -> io::Result<String> {
+    for (program, args) in programs(PASTE_PROGRAMS) {
+        let Ok(output) = Command::new(program).args(args).stdin(Stdio::null()).stderr(Stdio::null()).output() else {
+            continue;
+        };
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "no clipboard program found, like xclip or wl-paste"))
+}
+
+/// the programs of a list that fit the display, Wayland or X, of the session
+fn programs(programs: &'static [(&'static str, &'static [&'static str])]) -> impl Iterator<Item = (&'static str, &'static [&'static str])> {
+    let wayland = env::var_os("WAYLAND_DISPLAY").is_some();
+    let x11 = env::var_os("DISPLAY").is_some();
+    programs.iter().copied().filter(move |(program, _)| match *program {
+        "wl-copy" | "wl-paste" => wayland,
+        "xclip" | "xsel" => x11,
+        _ => true,
+    })
+}
+
+/// text of a paste fit for a single line input, line breaks and tabs become spaces
+pub fn single_line(text: &str) -> String {
+    text.trim_end_matches(['\r', '\n'])
+        .chars()
+        .map(|c| match c {
+            '\r' | '\n' | '\t' => ' ',
+            c => c,
+        })
+        .collect()
+}
+
+/// standard base64 with padding, as OSC 52 wants it
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| group | (*byte as u32) << (16 - 8 * index));
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => encoded.push(ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,2409 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::associations::FileAction;
+use crate::clipboard;
+use crate::compare::{self, CompareMode};
+use crate::complete::Completion;
+use crate::config::FormatConfig;
+use crate::criteria::{CriteriaText, EntryKind, SelectCriteria};
+use crate::connections::{Connection, Connections};
+use crate::filter::NameFilter;
+use crate::format;
+use crate::editor::{Editor, EditorPrompt};
+use crate::escalation::Privileged;
+use crate::fs_node::{create_node_in, FsNode, FsNodeRef, FsNodeType, TreeSender};
+use crate::fsinfo;
+use crate::hash::{self, Checksum, HashAlgorithm};
+use crate::help;
+use crate::hotlist::Hotlist;
+use crate::info::InfoView;
+use crate::job::{Extraction, Job, JobKind, JobReport};
+use crate::journal::Operation;
+use crate::keyring;
+use crate::ops::{self, ArchiveFormat, ChmodOptions, ChownOptions, CompressOptions, ConflictResolution, CopyConflict};
+use crate::panel::{FileClipboard, FileDrop, Layout, Panel, PanelManager, PanelSide};
+use crate::paths::{self, expand_user_path};
+use crate::rename::{self, Rename};
+use crate::shell::{self, Shell};
+use crate::sort::{SortKey, SortMode};
+use crate::sync::{SyncOptions, SyncSession};
+use crate::trash::Trash;
+use crate::usage::DiskUsage;
+use crate::ui::chmod_dialog::ChmodDialog;
+use crate::ui::chown_dialog::ChownDialog;
+use crate::ui::connection_dialog::ConnectionDialog;
+use crate::ui::dialog::{CheckField, DialogAnswer, InputField, ListField, ModalDialog, Widget, CANCEL};
+use crate::ui::find_dialog::FindDialog;
+use crate::ui::history_dialog::HistoryDialog;
+use crate::ui::hotlist_dialog::HotlistDialog;
+use crate::ui::job_dialog::JobDialog;
+use crate::ui::prompt::Prompt;
+use crate::ui::menu_bar::MENUS;
+use crate::ui::rename_dialog::RenameDialog;
+use crate::ui::theme::BUILTIN_THEMES;
+use crate::vfs::{self, PasswordError, SftpUrl, TrashFs, VfsEntry};
+use crate::viewer::{Viewer, ViewerPrompt};
+
+/// every action the user can trigger, whatever the input device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    Help,
+    UserMenu,
+    View,
+    Edit,
+    Open,
+    Copy,
+    Move,
+    Compress,
+    Extract,
+    ExtractHere,
+    Split,
+    Join,
+    Mkdir,
+    Delete,
+    MenuBar,
+    Quit,
+    CursorUp,
+    CursorDown,
+    PageUp,
+    PageDown,
+    CursorTop,
+    CursorBottom,
+    Enter,
+    ParentDirectory,
+    SwitchPanel,
+    ComputeSize,
+    DiskUsage,
+    Reload,
+    ToggleMark,
+    MarkGroup,
+    UnmarkGroup,
+    InvertMarks,
+    SelectByCriteria,
+    SaveSelection,
+    RestoreSelection,
+    Find,
+    IncrementalSearch,
+    QuickSearch,
+    Tree,
+    ToggleHidden,
+    Filter,
+    Layout,
+    NextLayout,
+    Sort,
+    Info,
+    FilesystemInfo,
+    Drive,
+    ExternalPanelize,
+    CompareDirectories,
+    Synchronize,
+    MultiRename,
+    Chmod,
+    Chown,
+    DeletePermanently,
+    Shred,
+    Trash,
+    Restore,
+    Undo,
+    Checksum,
+    WriteChecksums,
+    VerifyChecksums,
+    VerifyCopies,
+    BandwidthLimit,
+    CopyAttributes,
+    Hardlink,
+    Hotlist,
+    ChangeDirectory,
+    CutFiles,
+    CopyFiles,
+    PasteFiles,
+    CopyName,
+    CopyPath,
+    CopyPaths,
+    CopyQuotedPaths,
+    Connections,
+    HistoryBack,
+    HistoryForward,
+    History,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    MoveTabLeft,
+    MoveTabRight,
+    Theme,
+}
+
+impl Command {
+    /// short description shown in menus and help
+    pub fn label(self) -> &'static str {
+        match self {
+            Command::Help => "Help",
+            Command::UserMenu => "User menu",
+            Command::View => "View file",
+            Command::Edit => "Edit file",
+            Command::Open => "Open with the system",
+            Command::Copy => "Copy",
+            Command::Move => "Rename or move",
+            Command::Compress => "Pack into an archive",
+            Command::Extract => "Extract to the other panel",
+            Command::ExtractHere => "Extract here",
+            Command::Split => "Split into parts in the other panel",
+            Command::Join => "Join parts into the other panel",
+            Command::Mkdir => "Create directory",
+            Command::Delete => "Move to the trash",
+            Command::MenuBar => "Menu bar",
+            Command::Quit => "Quit",
+            Command::CursorUp => "Cursor up",
+            Command::CursorDown => "Cursor down",
+            Command::PageUp => "Page up",
+            Command::PageDown => "Page down",
+            Command::CursorTop => "First entry",
+            Command::CursorBottom => "Last entry",
+            Command::Enter => "Enter directory",
+            Command::ParentDirectory => "Parent directory",
+            Command::SwitchPanel => "Switch panel",
+            Command::ComputeSize => "Compute directory size",
+            Command::DiskUsage => "Disk usage analyzer",
+            Command::Reload => "Reread directory",
+            Command::ToggleMark => "Mark or unmark entry",
+            Command::MarkGroup => "Mark by pattern",
+            Command::UnmarkGroup => "Unmark by pattern",
+            Command::InvertMarks => "Invert marks",
+            Command::SelectByCriteria => "Mark by size, date, owner or type",
+            Command::SaveSelection => "Save marks under a name",
+            Command::RestoreSelection => "Restore saved marks",
+            Command::Find => "Find file",
+            Command::IncrementalSearch => "Search names as typed",
+            Command::QuickSearch => "Quick filter",
+            Command::Tree => "Directory tree",
+            Command::ToggleHidden => "Hidden files on or off",
+            Command::Filter => "Filter the listing",
+            Command::Layout => "Listing format",
+            Command::NextLayout => "Next listing format",
+            Command::Sort => "Sort order",
+            Command::Info => "Information panel",
+            Command::FilesystemInfo => "Filesystem information",
+            Command::Drive => "Drive or mount point",
+            Command::ExternalPanelize => "External panelize",
+            Command::CompareDirectories => "Compare directories",
+            Command::Synchronize => "Synchronize directories",
+            Command::MultiRename => "Rename several files",
+            Command::Chmod => "Change permissions",
+            Command::Chown => "Change owner and group",
+            Command::DeletePermanently => "Delete without the trash",
+            Command::Shred => "Shred, overwrite then delete",
+            Command::Trash => "Browse the trash",
+            Command::Restore => "Restore from the trash",
+            Command::Undo => "Undo the last file operation",
+            Command::Checksum => "Show checksums",
+            Command::WriteChecksums => "Write a checksum manifest",
+            Command::VerifyChecksums => "Verify a checksum manifest",
+            Command::VerifyCopies => "Verify copies on or off",
+            Command::BandwidthLimit => "Bandwidth limit of copies",
+            Command::CopyAttributes => "Attributes kept by copies",
+            Command::Hardlink => "Hardlink into the other panel",
+            Command::Hotlist => "Directory hotlist",
+            Command::ChangeDirectory => "Change directory",
+            Command::CutFiles => "Cut files to paste elsewhere",
+            Command::CopyFiles => "Copy files to paste elsewhere",
+            Command::PasteFiles => "Paste cut or copied files here",
+            Command::CopyName => "Copy the name to the clipboard",
+            Command::CopyPath => "Copy the full path to the clipboard",
+            Command::CopyPaths => "Copy the marked paths to the clipboard",
+            Command::CopyQuotedPaths => "Copy the marked paths shell-quoted",
+            Command::Connections => "Remote connections",
+            Command::HistoryBack => "Previous directory",
+            Command::HistoryForward => "Next directory",
+            Command::History => "Directory history",
+            Command::NewTab => "New tab",
+            Command::CloseTab => "Close tab",
+            Command::NextTab => "Next tab",
+            Command::PreviousTab => "Previous tab",
+            Command::MoveTabLeft => "Move tab left",
+            Command::MoveTabRight => "Move tab right",
+            Command::Theme => "Color theme",
+        }
+    }
+}
+
+/// labels of the function-key bar, F1 to F10
+pub const FUNCTION_KEYS: [(u8, &str, Command); 10] = [
+    (1, "Help", Command::Help),
+    (2, "Menu", Command::UserMenu),
+    (3, "View", Command::View),
+    (4, "Edit", Command::Edit),
+    (5, "Copy", Command::Copy),
+    (6, "RenMov", Command::Move),
+    (7, "Mkdir", Command::Mkdir),
+    (8, "Delete", Command::Delete),
+    (9, "PullDn", Command::MenuBar),
+    (10, "Quit", Command::Quit),
+];
+
+/// a key with its modifiers, shift left out of characters
+pub type Key = (KeyCode, KeyModifiers);
+
+/// key bindings of the file manager, single keys and two-key chords like Ctrl+X l
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<Key, Command>,
+    /// commands by the key pressed first and the one pressed after it
+    chords: HashMap<(Key, Key), Command>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+            chords: HashMap::new(),
+        };
+        for (number, _, command) in FUNCTION_KEYS {
+            keymap.bind(KeyCode::F(number), KeyModifiers::NONE, command);
+        }
+        keymap.bind(KeyCode::Up, KeyModifiers::NONE, Command::CursorUp);
+        keymap.bind(KeyCode::Down, KeyModifiers::NONE, Command::CursorDown);
+        keymap.bind(KeyCode::PageUp, KeyModifiers::NONE, Command::PageUp);
+        keymap.bind(KeyCode::PageDown, KeyModifiers::NONE, Command::PageDown);
+        keymap.bind(KeyCode::Home, KeyModifiers::NONE, Command::CursorTop);
+        keymap.bind(KeyCode::End, KeyModifiers::NONE, Command::CursorBottom);
+        keymap.bind(KeyCode::Enter, KeyModifiers::NONE, Command::Enter);
+        keymap.bind(KeyCode::Enter, KeyModifiers::ALT, Command::Open);
+        keymap.bind(KeyCode::Backspace, KeyModifiers::NONE, Command::ParentDirectory);
+        keymap.bind(KeyCode::Tab, KeyModifiers::NONE, Command::SwitchPanel);
+        keymap.bind(KeyCode::Char(' '), KeyModifiers::CONTROL, Command::ComputeSize);
+        keymap.bind(KeyCode::Char('S'), KeyModifiers::ALT, Command::DiskUsage);
+        keymap.bind(KeyCode::Char('r'), KeyModifiers::CONTROL, Command::Reload);
+        keymap.bind(KeyCode::Insert, KeyModifiers::NONE, Command::ToggleMark);
+        keymap.bind(KeyCode::Char(' '), KeyModifiers::NONE, Command::ToggleMark);
+        keymap.bind(KeyCode::Char('+'), KeyModifiers::NONE, Command::MarkGroup);
+        keymap.bind(KeyCode::Char('-'), KeyModifiers::NONE, Command::UnmarkGroup);
+        keymap.bind(KeyCode::Char('*'), KeyModifiers::NONE, Command::InvertMarks);
+        keymap.bind(KeyCode::Char('+'), KeyModifiers::ALT, Command::SelectByCriteria);
+        keymap.bind(KeyCode::Char('?'), KeyModifiers::ALT, Command::Find);
+        keymap.bind(KeyCode::Char('s'), KeyModifiers::CONTROL, Command::IncrementalSearch);
+        keymap.bind(KeyCode::Char('s'), KeyModifiers::ALT, Command::IncrementalSearch);
+        keymap.bind(KeyCode::Char('f'), KeyModifiers::ALT, Command::QuickSearch);
+        keymap.bind(KeyCode::Char('T'), KeyModifiers::ALT, Command::Tree);
+        keymap.bind(KeyCode::Char('.'), KeyModifiers::ALT, Command::ToggleHidden);
+        keymap.bind(KeyCode::Char('!'), KeyModifiers::ALT, Command::Filter);
+        keymap.bind(KeyCode::Char('t'), KeyModifiers::ALT, Command::NextLayout);
+        keymap.bind(KeyCode::Char('i'), KeyModifiers::ALT, Command::Info);
+        keymap.bind(KeyCode::Char('I'), KeyModifiers::ALT, Command::FilesystemInfo);
+        keymap.bind(KeyCode::F(1), KeyModifiers::ALT, Command::Drive);
+        keymap.bind(KeyCode::Char('p'), KeyModifiers::ALT, Command::ExternalPanelize);
+        keymap.bind(KeyCode::Char('d'), KeyModifiers::ALT, Command::CompareDirectories);
+        keymap.bind(KeyCode::Char('D'), KeyModifiers::ALT, Command::Synchronize);
+        keymap.bind(KeyCode::F(6), KeyModifiers::SHIFT, Command::MultiRename);
+        keymap.bind(KeyCode::Char('a'), KeyModifiers::ALT, Command::Compress);
+        keymap.bind(KeyCode::Char('x'), KeyModifiers::ALT, Command::Extract);
+        keymap.bind(KeyCode::Char('X'), KeyModifiers::ALT, Command::ExtractHere);
+        keymap.bind(KeyCode::Char('c'), KeyModifiers::ALT, Command::Chmod);
+        keymap.bind(KeyCode::Char('o'), KeyModifiers::ALT, Command::Chown);
+        keymap.bind(KeyCode::F(8), KeyModifiers::SHIFT, Command::DeletePermanently);
+        keymap.bind(KeyCode::Char('u'), KeyModifiers::ALT, Command::Trash);
+        keymap.bind(KeyCode::Char('r'), KeyModifiers::ALT, Command::Restore);
+        keymap.bind(KeyCode::Char('z'), KeyModifiers::CONTROL, Command::Undo);
+        keymap.bind(KeyCode::Char('#'), KeyModifiers::ALT, Command::Checksum);
+        keymap.bind(KeyCode::Char('K'), KeyModifiers::ALT, Command::WriteChecksums);
+        keymap.bind(KeyCode::Char('k'), KeyModifiers::ALT, Command::VerifyChecksums);
+        keymap.bind(KeyCode::Char('v'), KeyModifiers::ALT, Command::VerifyCopies);
+        keymap.bind(KeyCode::Char('\\'), KeyModifiers::CONTROL, Command::Hotlist);
+        // most terminals send Ctrl+\ as Ctrl+4
+        keymap.bind(KeyCode::Char('4'), KeyModifiers::CONTROL, Command::Hotlist);
+        keymap.bind(KeyCode::Char('g'), KeyModifiers::ALT, Command::ChangeDirectory);
+        keymap.bind(KeyCode::Char('n'), KeyModifiers::ALT, Command::Connections);
+        keymap.bind(KeyCode::Left, KeyModifiers::ALT, Command::HistoryBack);
+        keymap.bind(KeyCode::Right, KeyModifiers::ALT, Command::HistoryForward);
+        keymap.bind(KeyCode::Char('h'), KeyModifiers::ALT, Command::History);
+        keymap.bind(KeyCode::Char('H'), KeyModifiers::ALT, Command::History);
+        keymap.bind(KeyCode::Char('t'), KeyModifiers::CONTROL, Command::NewTab);
+        keymap.bind(KeyCode::Char('w'), KeyModifiers::CONTROL, Command::CloseTab);
+        keymap.bind(KeyCode::PageDown, KeyModifiers::CONTROL, Command::NextTab);
+        keymap.bind(KeyCode::PageUp, KeyModifiers::CONTROL, Command::PreviousTab);
+        keymap.bind(KeyCode::PageUp, KeyModifiers::CONTROL | KeyModifiers::SHIFT, Command::MoveTabLeft);
+        keymap.bind(KeyCode::PageDown, KeyModifiers::CONTROL | KeyModifiers::SHIFT, Command::MoveTabRight);
+        keymap.bind(KeyCode::Char('c'), KeyModifiers::CONTROL, Command::CopyFiles);
+        keymap.bind(KeyCode::Char('v'), KeyModifiers::CONTROL, Command::PasteFiles);
+        let ctrl_x = (KeyCode::Char('x'), KeyModifiers::CONTROL);
+        keymap.bind_chord(ctrl_x, (KeyCode::Char('l'), KeyModifiers::NONE), Command::Hardlink);
+        keymap.bind_chord(ctrl_x, (KeyCode::Char('s'), KeyModifiers::NONE), Command::SaveSelection);
+        keymap.bind_chord(ctrl_x, (KeyCode::Char('x'), KeyModifiers::NONE), Command::CutFiles);
+        keymap.bind_chord(ctrl_x, (KeyCode::Char('n'), KeyModifiers::NONE), Command::CopyName);
+        keymap.bind_chord(ctrl_x, (KeyCode::Char('p'), KeyModifiers::NONE), Command::CopyPath);
+        keymap.bind_chord(ctrl_x, (KeyCode::Char('m'), KeyModifiers::NONE), Command::CopyPaths);
+        keymap.bind_chord(ctrl_x, (KeyCode::Char('q'), KeyModifiers::NONE), Command::CopyQuotedPaths);
+        keymap.bind_chord(ctrl_x, (KeyCode::Char('r'), KeyModifiers::NONE), Command::RestoreSelection);
+        keymap
+    }
+}
+
+impl Keymap {
+    pub fn bind(&mut self, code: KeyCode, modifiers: KeyModifiers, command: Command) {
+        self.bindings.insert((code, modifiers), command);
+    }
+
+    /// run `command` with `then` pressed right after `first`
+    pub fn bind_chord(&mut self, first: Key, then: Key, command: Command) {
+        self.chords.insert((first, then), command);
+    }
+
+    pub fn lookup(&self, key: &KeyEvent) -> Option<Command> {
+        self.bindings.get(&key_of(key)).copied()
+    }
+
+    /// true if the key starts a chord, the next key then says which command
+    pub fn is_chord_start(&self, key: &KeyEvent) -> bool {
+        let key = key_of(key);
+        self.chords.keys().any(|(first, _)| *first == key)
+    }
+
+    /// the command of a chord, `first` being the key that started it
+    pub fn lookup_chord(&self, first: &KeyEvent, then: &KeyEvent) -> Option<Command> {
+        self.chords.get(&(key_of(first), key_of(then))).copied()
+    }
+
+    /// the key shown next to a command in menus, the shortest when several run it
+    pub fn shortcut(&self, command: Command) -> Option<String> {
+        self.bindings()
+            .into_iter()
+            .filter(|(_, bound)| *bound == command)
+            .map(|(name, _)| name)
+            .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+    }
+
+    /// every binding by the name of its keys, sorted for display
+    pub fn bindings(&self) -> Vec<(String, Command)> {
+        let keys = self.bindings.iter().map(|(&(code, modifiers), command)| (key_name(code, modifiers), *command));
+        let chords = self.chords.iter().map(|(&((code, modifiers), (then, then_modifiers)), command)| {
+            (format!("{} {}", key_name(code, modifiers), key_name(then, then_modifiers)), *command)
+        });
+        let mut bindings: Vec<_> = keys.chain(chords).collect();
+        bindings.sort_by(|(a, first), (b, second)| first.label().cmp(second.label()).then_with(|| a.cmp(b)));
+        bindings
+    }
+}
+
+/// a pressed key as bound, shift being implied by a character itself
+fn key_of(key: &KeyEvent) -> Key {
+    let modifiers = match key.code {
+        KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+        _ => key.modifiers,
+    };
+    (key.code, modifiers)
+}
+
+/// a key as written in menus and help, like `Ctrl+PgDn` or `Alt+h`
+pub fn key_name(code: KeyCode, modifiers: KeyModifiers) -> String {
+    let mut name = String::new();
+    for (modifier, prefix) in [
+        (KeyModifiers::CONTROL, "Ctrl+"),
+        (KeyModifiers::ALT, "Alt+"),
+        (KeyModifiers::SHIFT, "Shift+"),
+    ] {
+        if modifiers.contains(modifier) {
+            name.push_str(prefix);
+        }
+    }
+    match code {
+        KeyCode::Char(' ') => name.push_str("Space"),
+        KeyCode::Char(c) => name.push(c),
+        KeyCode::F(number) => name.push_str(&format!("F{number}")),
+        KeyCode::PageUp => name.push_str("PgUp"),
+        KeyCode::PageDown => name.push_str("PgDn"),
+        KeyCode::Insert => name.push_str("Ins"),
+        KeyCode::Delete => name.push_str("Del"),
+        KeyCode::Backspace => name.push_str("Bksp"),
+        code => name.push_str(&code.to_string()),
+    }
+    name
+}
+
+/// what the application should do after a command
+#[derive(Debug)]
+pub enum CommandOutcome {
+    Done,
+    Quit,
+    /// something to tell the user, usually an error
+    Message(String),
+    /// ask the user for a line of text, handed to [`submit`]
+    Prompt(Prompt),
+    /// show a file in the viewer
+    View(Box<Viewer>),
+    /// edit a file in the editor
+    Edit(Box<Editor>),
+    /// run a shell command line in `dir` with the terminal handed over, the panels are reread after
+    Run { command: String, dir: PathBuf },
+    /// open a local file with the default application of the system
+    Open(PathBuf),
+    /// ask what to search for, the search itself runs in the results screen
+    Find(Box<FindDialog>),
+    /// review a synchronization plan and run it
+    Sync(Box<SyncSession>),
+    /// scan a subtree and list where its space goes
+    DiskUsage(Box<DiskUsage>),
+    /// build new names for the marked files and review them
+    Rename(Box<RenameDialog>),
+    /// edit the permissions of the targets
+    Chmod(Box<ChmodDialog>),
+    /// choose a new owner and group for the targets
+    Chown(Box<ChownDialog>),
+    /// pick a bookmarked directory
+    Hotlist(Box<HotlistDialog>),
+    /// pick a saved remote connection
+    Connections(Box<ConnectionDialog>),
+    /// pick a directory the active panel visited
+    History(Box<HistoryDialog>),
+    /// open the pull-down menu of this index in the menu bar
+    Menu(usize),
+    /// ask through a dialog, its answer is handed to [`answer`]
+    Dialog(Box<ModalDialog>),
+    /// follow a copy or a move running in the background, handed to [`finish_job`] once it stops
+    Job(Box<JobDialog>),
+    /// switch to the theme of this name
+    Theme(String),
+    /// run an operation refused for lack of rights again as root, the terminal is handed over
+    Escalate(Privileged),
+    /// open the help on this topic
+    Help(&'static str),
+}
+
+/// the entries a copy or a move takes and where they go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    /// the targets of the active panel to the other one
+    Targets,
+    /// the file clipboard to the active panel
+    Paste,
+    /// the entries dropped with the mouse to the directory they were dropped on
+    Drop,
+}
+
+/// what a prompt asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    MarkGroup,
+    UnmarkGroup,
+    SelectByCriteria,
+    SaveSelection,
+    RestoreSelection,
+    CompareDirectories,
+    Synchronize,
+    /// directory or url the active panel goes to
+    ChangeDirectory,
+    Mkdir,
+    /// pattern restricting the files of the listing
+    Filter,
+    /// layout of the listing, picked in a list
+    Layout,
+    /// key of the sort picked in a list, and how names compare with check boxes
+    Sort,
+    /// details of the filesystem of the panel, only read
+    FilesystemInfo,
+    /// root to go to, picked in a list
+    Drive,
+    /// entry of the user menu to run, picked in a list
+    UserMenu,
+    /// shell command whose output lines are the files to list
+    ExternalPanelize,
+    /// retry the refused operation of the manager as root
+    Escalate,
+    /// confirm quitting, asked when `confirm.exit` is on
+    Quit,
+    /// confirm a deletion, to the trash or for good
+    Delete { trash: bool },
+    /// confirm overwriting the targets before deleting them
+    Shred,
+    /// what to do with targets of a copy or a move that already exist, resuming offered when one looks cut off
+    Overwrite { moving: bool, partial: bool, transfer: Transfer },
+    /// name, compression level and format of the archive to pack the targets into
+    Compress,
+    /// size of the parts to split the targets into
+    Split,
+    /// password of the encrypted zip to extract, into the active panel or the other one
+    ExtractPassword { here: bool },
+    /// what to do with members of an extracted archive that already exist
+    ExtractOverwrite,
+    /// settings of a saved connection, of this index or a new one
+    SaveConnection { index: Option<usize> },
+    /// password of the saved connection of this index, refused without one
+    ConnectionPassword { index: usize },
+    /// progress of a running copy or move, the job answers it
+    Job,
+    /// algorithm of the checksums to show
+    Checksum,
+    /// name of the manifest to write
+    WriteChecksums,
+    /// name of the theme to switch to
+    Theme,
+    /// KiB per second of the next copies and moves
+    BandwidthLimit,
+    /// attributes the next copies carry over, one check box each
+    CopyAttributes,
+    /// handled by the open viewer
+    Viewer(ViewerPrompt),
+    /// handled by the open editor
+    Editor(EditorPrompt),
+}
+
+/// number of rows moved by page up and page down
+const PAGE_SIZE: usize = 20;
+
+/// run a command picked in a menu, on the panel of the menu when it has one
+pub fn dispatch_on(side: Option<PanelSide>, command: Command, manager: &mut PanelManager) -> CommandOutcome {
+    match side {
+        // the info of a panel menu is shown by that panel, about the other one
+        Some(side) if command == Command::Info => manager.active = side.opposite(),
+        Some(side) => manager.active = side,
+        None => {}
+    }
+    dispatch(command, manager)
+}
+
+/// run a command against the panels
+pub fn dispatch(command: Command, manager: &mut PanelManager) -> CommandOutcome {
+    if command == Command::Quit && manager.confirm.exit {
+        let dialog = ModalDialog::confirm(PromptKind::Quit, "Quit", "Quit the file manager?", &["Yes", "No"]);
+        return CommandOutcome::Dialog(Box::new(dialog));
+    }
+    let panel = manager.active_mut();
+    match command {
+        Command::Quit => return CommandOutcome::Quit,
+        Command::CursorUp => panel.move_cursor_up(),
+        Command::CursorDown => panel.move_cursor_down(),
+        Command::PageUp => {
+            for _ in 0..PAGE_SIZE {
+                panel.move_cursor_up();
+            }
+        }
+        Command::PageDown => {
+            for _ in 0..PAGE_SIZE {
+                panel.move_cursor_down();
+            }
+        }
+        Command::CursorTop => panel.move_cursor_top(),
+        Command::CursorBottom => panel.move_cursor_bottom(),
+        Command::Enter => return enter_cursor(manager),
+        Command::ParentDirectory => return outcome(panel.go_parent().map(|_| ())),
+        Command::Reload => match panel.tree.as_mut() {
+            Some(tree) => return outcome(tree.reload()),
+            None => return outcome(panel.reload()),
+        },
+        Command::SwitchPanel => manager.switch_active(),
+        Command::ComputeSize => {
+            if let Some(node) = manager.active().cursor_node()
+                && node.read().is_navigable()
+            {
+                FsNode::compute_size(&node, TreeSender::new(manager.events.clone()));
+            }
+        }
+        Command::DiskUsage => {
+            let usage = DiskUsage::start(panel.vfs(), &panel.current_path(), manager.format.units, manager.gitignore);
+            return CommandOutcome::DiskUsage(Box::new(usage));
+        }
+        Command::ToggleMark => panel.toggle_mark(),
+        Command::MarkGroup => {
+            return CommandOutcome::Prompt(Prompt::new(PromptKind::MarkGroup, "Select", "*"));
+        }
+        Command::UnmarkGroup => {
+            return CommandOutcome::Prompt(Prompt::new(PromptKind::UnmarkGroup, "Unselect", "*"));
+        }
+        Command::InvertMarks => panel.invert_marks(),
+        Command::SelectByCriteria => return criteria_dialog(),
+        Command::SaveSelection => return save_selection_dialog(manager),
+        Command::RestoreSelection => return restore_selection_dialog(manager),
+        // the tree has no listing to filter or search
+        Command::IncrementalSearch | Command::QuickSearch if panel.tree.is_some() => {}
+        Command::IncrementalSearch => panel.start_search(),
+        Command::QuickSearch => panel.start_filter(),
+        Command::Tree => return outcome(panel.toggle_tree()),
+        Command::ToggleHidden => {
+            let show_hidden = !panel.show_hidden;
+            panel.set_show_hidden(show_hidden);
+        }
+        Command::Filter => {
+            let current = panel.name_filter.as_ref().map_or("*", |filter| filter.pattern.as_str());
+            let dialog = ModalDialog::input(PromptKind::Filter, "Filter", "Files matching a glob or a /regex/", current);
+            return CommandOutcome::Dialog(Box::new(dialog));
+        }
+        Command::Layout => {
+            let labels = Layout::ALL.iter().map(|layout| layout.label().to_string()).collect();
+            let current = Layout::ALL.iter().position(|layout| *layout == panel.layout).unwrap_or(0);
+            let dialog = ModalDialog::picker(PromptKind::Layout, "Listing format", labels).with_choice(current);
+            return CommandOutcome::Dialog(Box::new(dialog));
+        }
+        Command::NextLayout => panel.layout = panel.layout.next(),
+        Command::Sort => return sort_dialog(panel.sort_mode),
+        Command::Info => {
+            // the info is shown by the other panel, or turned off from either
+            let panel = match manager.active().info {
+                Some(_) => manager.active_mut(),
+                None => manager.inactive_mut(),
+            };
+            panel.info = match panel.info.take() {
+                Some(_) => None,
+                None => Some(InfoView::default()),
+            };
+        }
+        Command::FilesystemInfo => return filesystem_info(manager),
+        Command::Compress => return compress_dialog(manager),
+        Command::Extract => return extract_archive(manager, false, None),
+        Command::ExtractHere => return extract_archive(manager, true, None),
+        Command::Split => return ask_split(manager),
+        Command::Join => return start_join(manager),
+        Command::ExternalPanelize => {
+            let dialog = ModalDialog::input(
+                PromptKind::ExternalPanelize,
+                "External panelize",
+                "Command listing files",
+                &manager.panelize_command,
+            );
+            return CommandOutcome::Dialog(Box::new(dialog));
+        }
+        Command::Drive => {
+            let roots = fsinfo::roots();
+            let labels = roots.iter().map(|root| root.display().to_string()).collect();
+            let current = roots.iter().rposition(|root| paths::is_within(&panel.root.read().path(), root)).unwrap_or(0);
+            let dialog = ModalDialog::picker(PromptKind::Drive, "Drive or mount point", labels).with_choice(current);
+            return CommandOutcome::Dialog(Box::new(dialog));
+        }
+        Command::CompareDirectories => {
+            let title = "Compare by name, size and time, or content (n/s/c)";
+            return CommandOutcome::Prompt(Prompt::new(PromptKind::CompareDirectories, title, "s"));
+        }
+        Command::Synchronize => {
+            let title = "Sync > < or =, then d delete orphans, n dry run, c compare contents";
+            return CommandOutcome::Prompt(Prompt::new(PromptKind::Synchronize, title, ">"));
+        }
+        Command::Find => {
+            let dialog = FindDialog::new(&panel.current_path(), panel.sort_mode.case_sensitive, manager.gitignore);
+            return CommandOutcome::Find(Box::new(dialog));
+        }
+        Command::View => return view_cursor(manager),
+        Command::Edit => return edit_cursor(manager),
+        Command::Open => return open_cursor(manager),
+        Command::Copy => return transfer_targets(manager, false, Transfer::Targets),
+        Command::Move => return transfer_targets(manager, true, Transfer::Targets),
+        Command::CutFiles => return fill_file_clipboard(manager, true),
+        Command::CopyFiles => return fill_file_clipboard(manager, false),
+        Command::PasteFiles => return paste_file_clipboard(manager),
+        Command::Hardlink => return hardlink_cursor(manager),
+        Command::ChangeDirectory => return change_directory_dialog(manager),
+        Command::CopyName | Command::CopyPath | Command::CopyPaths | Command::CopyQuotedPaths => {
+            return copy_to_clipboard(command, panel);
+        }
+        Command::Mkdir => {
+            let dialog = ModalDialog::input(PromptKind::Mkdir, "Create a new directory", "Name", "");
+            // names are completed against the local directories
+            let completion = match panel.vfs().local_path(&panel.current_path()) {
+                Some(dir) => Completion::Paths(dir),
+                None => Completion::None,
+            };
+            return CommandOutcome::Dialog(Box::new(dialog.with_completion(completion)));
+        }
+        Command::MultiRename => {
+            let sources: Vec<_> = panel.targets().iter().map(|node| node.read().path()).collect();
+            if sources.is_empty() {
+                return CommandOutcome::Done;
+            }
+            return CommandOutcome::Rename(Box::new(RenameDialog::new(panel.vfs(), sources)));
+        }
+        Command::Chmod => {
+            let targets = panel.targets();
+            if targets.is_empty() {
+                return CommandOutcome::Done;
+            }
+            return CommandOutcome::Chmod(Box::new(ChmodDialog::new(targets)));
+        }
+        Command::Chown => {
+            let targets = panel.targets();
+            if targets.is_empty() {
+                return CommandOutcome::Done;
+            }
+            return CommandOutcome::Chown(Box::new(ChownDialog::new(targets)));
+        }
+        Command::Delete | Command::DeletePermanently => return confirm_delete(command, manager),
+        Command::Shred => return confirm_shred(manager),
+        Command::Trash => return toggle_trash(manager),
+        Command::Restore => return restore_targets(manager),
+        Command::Undo => return undo_last_operation(manager),
+        Command::Checksum => {
+            let title = "Checksum with md5, sha1, sha256 or blake3";
+            return CommandOutcome::Prompt(Prompt::new(PromptKind::Checksum, title, "sha256"));
+        }
+        Command::WriteChecksums => {
+            let title = "Write the checksums of the targets to (.md5sum, .sha1sum, .sha256sum or .b3sum)";
+            return CommandOutcome::Prompt(Prompt::new(PromptKind::WriteChecksums, title, "checksums.sha256sum"));
+        }
+        Command::VerifyChecksums => return verify_checksums(manager),
+        Command::HistoryBack | Command::HistoryForward => {
+            let result = panel.history_step(command == Command::HistoryForward);
+            return history_outcome(result);
+        }
+        Command::History => return CommandOutcome::History(Box::new(HistoryDialog::new(&panel.history))),
+        Command::NewTab => {
+            if let Err(err) = manager.new_tab() {
+                return CommandOutcome::Message(err.to_string());
+            }
+        }
+        Command::CloseTab => {
+            if !manager.close_tab() {
+                return CommandOutcome::Message(String::from("the last tab of a panel cannot be closed"));
+            }
+        }
+        Command::NextTab | Command::PreviousTab => manager.cycle_tab(command == Command::NextTab),
+        Command::MoveTabLeft | Command::MoveTabRight => {
+            let (_, tabs) = manager.side_mut(manager.active);
+            tabs.shift(command == Command::MoveTabRight);
+        }
+        Command::Hotlist => {
+            let dialog = HotlistDialog::new(Hotlist::default_path(), &panel.display_location());
+            return CommandOutcome::Hotlist(Box::new(dialog));
+        }
+        Command::Connections => return CommandOutcome::Connections(Box::new(ConnectionDialog::new(Connections::default_path()))),
+        Command::MenuBar => {
+            // the menu of the active side opens first
+            let menu = match manager.active {
+                PanelSide::Left => 0,
+                PanelSide::Right => MENUS.len() - 1,
+            };
+            return CommandOutcome::Menu(menu);
+        }
+        Command::Theme => {
+            let title = format!("Theme, {} or a skin name", BUILTIN_THEMES.join(", "));
+            return CommandOutcome::Prompt(Prompt::new(PromptKind::Theme, &title, ""));
+        }
+        Command::VerifyCopies => {
+            let options = &mut manager.copy_options;
+            options.verify = !options.verify;
+            let state = if options.verify { "verified" } else { "not verified" };
+            return CommandOutcome::Message(format!("copies are {state}"));
+        }
+        Command::BandwidthLimit => {
+            let limit = manager.copy_options.bandwidth_limit.map(|bytes| (bytes / 1024).to_string()).unwrap_or_default();
+            let title = "KiB per second copies and moves may use, empty for no limit";
+            return CommandOutcome::Prompt(Prompt::new(PromptKind::BandwidthLimit, title, &limit));
+        }
+        Command::CopyAttributes => {
+            let options = &manager.copy_options;
+            let mut widgets: Vec<Widget> = [
+                ("Permissions", options.preserve_permissions),
+                ("Modification time", options.preserve_mtime),
+                ("Access time", options.preserve_atime),
+                ("Extended attributes", options.preserve_xattrs),
+                ("POSIX ACLs", options.preserve_acls),
+                ("Owner and group, when running as root", options.preserve_owner),
+                ("Hardlinks between the copied files", options.preserve_hardlinks),
+            ]
+            .into_iter()
+            .map(|(label, checked)| Widget::Check(CheckField::new(label, checked)))
+            .collect();
+            widgets.push(Widget::Buttons(vec![String::from("Ok"), String::from(CANCEL)]));
+            let dialog = ModalDialog::new(PromptKind::CopyAttributes, "Attributes kept by copies", widgets);
+            return CommandOutcome::Dialog(Box::new(dialog));
+        }
+        Command::Help => return CommandOutcome::Help(help::command_topic(Command::Help)),
+        Command::UserMenu => return user_menu(manager),
+    }
+    CommandOutcome::Done
+}
+
+/// finish a command that asked for input
+pub fn submit(kind: PromptKind, input: &str, manager: &mut PanelManager) -> CommandOutcome {
+    match kind {
+        PromptKind::MarkGroup | PromptKind::UnmarkGroup => {
+            let mark = kind == PromptKind::MarkGroup;
+            match manager.active_mut().mark_matching(input, mark, false) {
+                Ok(_) => CommandOutcome::Done,
+                Err(err) => CommandOutcome::Message(format!("invalid pattern: {err}")),
+            }
+        }
+        PromptKind::SaveSelection => match input.trim() {
+            "" => CommandOutcome::Message(String::from("the saved marks need a name")),
+            name => {
+                let marked = manager.active().selection.paths().clone();
+                let count = marked.len();
+                manager.selections.insert(name.to_string(), marked);
+                CommandOutcome::Message(format!("{count} marks saved as {name}"))
+            }
+        },
+        PromptKind::CompareDirectories => {
+            let Some(mode) = CompareMode::parse(input) else {
+                return CommandOutcome::Message(format!("unknown comparison {input}"));
+            };
+            match compare::mark_differences(manager, mode) {
+                Ok(0) => CommandOutcome::Message(String::from("the directories are the same")),
+                Ok(marked) => CommandOutcome::Message(format!("{marked} files differ")),
+                Err(err) => CommandOutcome::Message(err.to_string()),
+            }
+        }
+        PromptKind::Synchronize => {
+            let session = SyncOptions::parse(input)
+                .and_then(|options| SyncSession::for_panels(manager, options).map_err(|err| err.to_string()));
+            match session {
+                Ok(session) => CommandOutcome::Sync(Box::new(session)),
+                Err(err) => CommandOutcome::Message(err),
+            }
+        }
+        PromptKind::ExternalPanelize => external_panelize(input, manager),
+        PromptKind::ExtractPassword { here } => extract_archive(manager, here, Some(input)),
+        PromptKind::ConnectionPassword { index } => connect_saved(index, Some(input), manager),
+        PromptKind::ChangeDirectory => change_directory(input, manager),
+        PromptKind::Mkdir => {
+            if input.trim().is_empty() {
+                return CommandOutcome::Done;
+            }
+            let panel = manager.active_mut();
+            let path = expand_user_path(input);
+            let (vfs, target) = {
+                let root = panel.root.read();
+                (Arc::clone(&root.vfs), root.path().join(&path))
+            };
+            let mut missing: Vec<PathBuf> = target
+                .ancestors()
+                .take_while(|ancestor| vfs.metadata(ancestor).is_err())
+                .map(Path::to_path_buf)
+                .collect();
+            missing.reverse();
+            match ops::make_directory(&panel.root, &path) {
+                Ok(created) => {
+                    manager.journal.record(Operation::Mkdir { vfs, created: missing });
+                    let panel = manager.active_mut();
+                    panel.refresh_view();
+                    let position = created.and_then(|node| panel.position_of(&node.read().path()));
+                    if let Some(position) = position {
+                        panel.cursor = position;
+                    }
+                    CommandOutcome::Done
+                }
+                Err(err) => CommandOutcome::Message(err.to_string()),
+            }
+        }
+        PromptKind::Filter => {
+            let panel = manager.active_mut();
+            match NameFilter::parse(input, panel.sort_mode.case_sensitive) {
+                Ok(filter) => {
+                    panel.set_name_filter(filter);
+                    CommandOutcome::Done
+                }
+                Err(err) => CommandOutcome::Message(format!("invalid filter: {err}")),
+            }
+        }
+        // answered by the buttons of a dialog, see `answer`
+        PromptKind::Quit
+        | PromptKind::Delete { .. }
+        | PromptKind::Shred
+        | PromptKind::Overwrite { .. }
+        | PromptKind::Compress
+        | PromptKind::SelectByCriteria
+        | PromptKind::RestoreSelection
+        | PromptKind::ExtractOverwrite
+        | PromptKind::SaveConnection { .. }
+        | PromptKind::Layout
+        | PromptKind::Sort
+        | PromptKind::FilesystemInfo
+        | PromptKind::Drive
+        | PromptKind::UserMenu
+        | PromptKind::Escalate
+        | PromptKind::CopyAttributes
+        | PromptKind::Job => CommandOutcome::Done,
+        PromptKind::Checksum => {
+            let Some(algorithm) = HashAlgorithm::parse(input) else {
+                return CommandOutcome::Message(format!("unknown checksum {}", input.trim()));
+            };
+            match target_checksums(manager, algorithm) {
+                Ok(checksums) => {
+                    let title = format!("{} checksums", algorithm.name());
+                    CommandOutcome::View(Box::new(Viewer::from_text(&title, &hash::format_manifest(&checksums))))
+                }
+                Err(err) => CommandOutcome::Message(err.to_string()),
+            }
+        }
+        PromptKind::Theme => match input.trim() {
+            "" => CommandOutcome::Done,
+            name => CommandOutcome::Theme(name.to_string()),
+        },
+        PromptKind::Split => {
+            let Some(part_size) = format::parse_size(input) else {
+                return CommandOutcome::Message(format!("{} is not a size", input.trim()));
+            };
+            let job = Job::split(manager.active().targets(), manager.inactive().root.clone(), part_size, manager.events.clone());
+            CommandOutcome::Job(Box::new(JobDialog::new(job, manager.format.units)))
+        }
+        PromptKind::BandwidthLimit => {
+            let limit = match input.trim() {
+                "" | "0" => None,
+                kib => match kib.parse::<u64>() {
+                    Ok(kib) => Some(kib.saturating_mul(1024)),
+                    Err(_) => return CommandOutcome::Message(format!("{kib} is not a number of KiB per second")),
+                },
+            };
+            manager.copy_options.bandwidth_limit = limit;
+            CommandOutcome::Message(match limit {
+                Some(limit) => format!("copies and moves use at most {}/s", format::size(limit, manager.format.units)),
+                None => String::from("copies and moves go as fast as they can"),
+            })
+        }
+        PromptKind::WriteChecksums => {
+            let name = input.trim();
+            if name.is_empty() {
+                return CommandOutcome::Done;
+            }
+            let panel = manager.active();
+            let (vfs, manifest) = (panel.vfs(), panel.current_path().join(name));
+            let algorithm = HashAlgorithm::for_manifest(&manifest).unwrap_or_default();
+            let result = target_checksums(manager, algorithm)
+                .and_then(|checksums| hash::write_manifest(vfs.as_ref(), &manifest, &checksums).map(|_| checksums.len()));
+            let panel = manager.active_mut();
+            panel.selection.clear();
+            let _ = panel.reload();
+            match result {
+                Ok(count) => CommandOutcome::Message(format!("{count} {} checksums written to {name}", algorithm.name())),
+                Err(err) => CommandOutcome::Message(err.to_string()),
+            }
+        }
+        PromptKind::Viewer(_) | PromptKind::Editor(_) => CommandOutcome::Done,
+    }
+}
+
+/// checksums of the files among the targets, directories are left out
+fn target_checksums(manager: &PanelManager, algorithm: HashAlgorithm) -> std::io::Result<Vec<Checksum>> {
+    let mut checksums = Vec::new();
+    for node in manager.active().targets() {
+        let node = node.read();
+        if node.is_navigable() {
+            continue;
+        }
+        checksums.push(Checksum {
+            digest: hash::hash_file(node.vfs.as_ref(), &node.path(), algorithm)?,
+            name: node.name.to_string(),
+        });
+    }
+    Ok(checksums)
+}
+
+/// check the files listed by the manifest under the cursor
+fn verify_checksums(manager: &mut PanelManager) -> CommandOutcome {
+    let Some(node) = manager.active().cursor_node() else {
+        return CommandOutcome::Done;
+    };
+    let node = node.read();
+    match hash::verify_manifest(node.vfs.as_ref(), &node.path()) {
+        Ok(report) if report.is_ok() => CommandOutcome::Message(format!("{}: {} files match", node.name, report.matched)),
+        Ok(report) => {
+            let title = format!("verification of {}", node.name);
+            CommandOutcome::View(Box::new(Viewer::from_text(&title, &report.describe())))
+        }
+        Err(err) => CommandOutcome::Message(err.to_string()),
+    }
+}
+
+/// run a line of the command line in the directory of the active panel
+///
+/// `cd` changes the directory of the panel, a shell would forget it at once.
+pub fn run_shell_command(command: &str, shell: &mut Shell, manager: &mut PanelManager) -> CommandOutcome {
+    let command = command.trim();
+    let panel = manager.active_mut();
+    if is_cd(command) {
+        let target = match command[2..].trim() {
+            "" => String::from("~"),
+            target => target.to_string(),
+        };
+        let path = panel.current_path().join(expand_user_path(&target));
+        return outcome(panel.change_directory(&path));
+    }
+    let Some(dir) = panel.vfs().local_path(&panel.current_path()) else {
+        return CommandOutcome::Message(String::from("commands only run in local directories"));
+    };
+    let expanded = shell::expand_macros(command, panel);
+    let result = shell.run(&expanded, &dir);
+    // the command may have changed the files of both panels
+    let _ = manager.left.reload();
+    let _ = manager.right.reload();
+    match result {
+        Ok(()) => CommandOutcome::Done,
+        Err(err) => CommandOutcome::Message(format!("{}: {err}", shell::user_shell())),
+    }
+}
+
+/// what Tab finishes the command line with, paths from the directory of the active panel when local
+pub fn command_line_completion(manager: &PanelManager) -> Completion {
+    let panel = manager.active();
+    match panel.vfs().local_path(&panel.current_path()) {
+        Some(dir) => Completion::CommandLine(dir),
+        None => Completion::None,
+    }
+}
+
+/// true for a `cd` command, the panel runs it instead of the shell
+pub fn is_cd(command: &str) -> bool {
+    let command = command.trim();
+    command == "cd" || command.starts_with("cd ")
+}
+
+/// buttons of the overwrite dialog, each answers every conflict of the operation
+const OVERWRITE_CHOICES: [(&str, ConflictResolution); 5] = [
+    ("Overwrite", ConflictResolution::OverwriteAll),
+    ("If newer", ConflictResolution::OverwriteAllIfNewer),
+    ("Skip", ConflictResolution::SkipAll),
+    ("Rename", ConflictResolution::RenameAllWithSuffix),
+    ("Append", ConflictResolution::AppendAll),
+];
+
+/// act on the answer of a dialog opened by a command
+pub fn answer(kind: PromptKind, answer: &DialogAnswer, manager: &mut PanelManager) -> CommandOutcome {
+    match kind {
+        PromptKind::Quit => match answer.button {
+            0 => CommandOutcome::Quit,
+            _ => CommandOutcome::Done,
+        },
+        PromptKind::Delete { trash } => match answer.button {
+            0 => delete_targets(manager, trash),
+            _ => CommandOutcome::Done,
+        },
+        // Cancel comes first, for Enter not to shred
+        PromptKind::Shred => match answer.button {
+            1 => {
+                let panel = manager.active();
+                let job = Job::shred(panel.targets(), panel.root.clone(), manager.shred_options, manager.events.clone());
+                CommandOutcome::Job(Box::new(JobDialog::new(job, manager.format.units)))
+            }
+            _ => CommandOutcome::Done,
+        },
+        PromptKind::Overwrite { moving, partial, transfer } => {
+            let resolution = match OVERWRITE_CHOICES.get(answer.button) {
+                Some((_, resolution)) => *resolution,
+                // resume comes after the others when it is offered
+                None if partial && answer.button == OVERWRITE_CHOICES.len() => ConflictResolution::ResumeAll,
+                None => return CommandOutcome::Done,
+            };
+            start_job(manager, moving, transfer, Some(resolution))
+        }
+        PromptKind::CopyAttributes => {
+            if let [permissions, mtime, atime, xattrs, acls, owner, hardlinks] = answer.checks[..] {
+                let options = &mut manager.copy_options;
+                options.preserve_permissions = permissions;
+                options.preserve_mtime = mtime;
+                options.preserve_atime = atime;
+                options.preserve_xattrs = xattrs;
+                options.preserve_acls = acls;
+                options.preserve_owner = owner;
+                options.preserve_hardlinks = hardlinks;
+            }
+            CommandOutcome::Done
+        }
+        PromptKind::Layout => {
+            if let Some(layout) = answer.choice.and_then(|choice| Layout::ALL.get(choice)) {
+                manager.active_mut().layout = *layout;
+            }
+            CommandOutcome::Done
+        }
+        PromptKind::Sort => {
+            let panel = manager.active_mut();
+            let key = answer.choice.and_then(|choice| SortKey::ALL.get(choice)).copied().unwrap_or(panel.sort_mode.key);
+            if let [descending, directories_first, case_sensitive, natural, locale] = answer.checks[..] {
+                panel.set_sort_mode(SortMode {
+                    key,
+                    descending,
+                    directories_first,
+                    case_sensitive,
+                    natural,
+                    locale,
+                });
+            }
+            CommandOutcome::Done
+        }
+        // the roots are listed again, a drive plugged in meanwhile only moves those after it
+        PromptKind::Drive => match answer.choice.and_then(|choice| fsinfo::roots().into_iter().nth(choice)) {
+            Some(root) => outcome(manager.active_mut().change_directory_in(vfs::local(), &root)),
+            None => CommandOutcome::Done,
+        },
+        PromptKind::UserMenu => {
+            let panel = manager.active();
+            let cursor = panel.cursor_node();
+            let cursor = cursor.as_ref().map(|node| node.read());
+            let entries = manager.user_menu.entries_for(cursor.as_deref());
+            let Some(entry) = answer.choice.and_then(|choice| entries.get(choice)) else {
+                return CommandOutcome::Done;
+            };
+            match panel.vfs().local_path(&panel.current_path()) {
+                Some(dir) => CommandOutcome::Run {
+                    command: shell::expand_macros(&entry.command, panel),
+                    dir,
+                },
+                None => CommandOutcome::Message(String::from("the user menu only runs in local directories")),
+            }
+        }
+        PromptKind::ExtractOverwrite => {
+            let extraction = manager.extraction.take();
+            match (OVERWRITE_CHOICES.get(answer.button), extraction) {
+                (Some((_, resolution)), Some(extraction)) => start_extraction(manager, extraction, Some(*resolution)),
+                _ => CommandOutcome::Done,
+            }
+        }
+        PromptKind::Compress => match answer.button {
+            0 => compress_targets(answer, manager),
+            _ => CommandOutcome::Done,
+        },
+        PromptKind::RestoreSelection => match (answer.button, answer.choice) {
+            (0, Some(choice)) => restore_selection(choice, manager),
+            _ => CommandOutcome::Done,
+        },
+        PromptKind::SelectByCriteria => match answer.button {
+            0 | 1 => mark_by_criteria(answer, answer.button == 0, manager),
+            _ => CommandOutcome::Done,
+        },
+        PromptKind::SaveConnection { index } => match answer.button {
+            0 => save_connection(index, answer),
+            _ => CommandOutcome::Done,
+        },
+        PromptKind::Escalate => match (answer.button, manager.refused.take()) {
+            (0, Some(operation)) => CommandOutcome::Escalate(operation),
+            _ => CommandOutcome::Done,
+        },
+        _ => submit(kind, answer.text(), manager),
+    }
+}
+
+/// handle a key while the active panel is filtered
+///
+/// `None` means the key is not part of the quick search, the filter is then
+/// accepted and the key should run its usual command.
+pub fn filter_key(key: &KeyEvent, manager: &mut PanelManager) -> Option<CommandOutcome> {
+    let panel = manager.active_mut();
+    let mut query = panel.filter.as_ref()?.query.clone();
+    match key.code {
+        KeyCode::Esc => panel.cancel_filter(),
+        KeyCode::Enter => panel.accept_filter(),
+        KeyCode::Up => panel.move_cursor_up(),
+        KeyCode::Down => panel.move_cursor_down(),
+        KeyCode::Backspace => {
+            query.pop();
+            panel.set_filter_query(&query);
+        }
+        KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+            query.push(c);
+            panel.set_filter_query(&query);
+        }
+        _ => {
+            panel.accept_filter();
+            return None;
+        }
+    }
+    Some(CommandOutcome::Done)
+}
+
+/// handle a key while the active panel searches names as they are typed
+///
+/// The keys of the search command go to the next match. `None` means the key
+/// is not part of the search, the search then ends and the key should run its
+/// usual command.
+pub fn search_key(key: &KeyEvent, keymap: &Keymap, manager: &mut PanelManager) -> Option<CommandOutcome> {
+    let panel = manager.active_mut();
+    panel.search.as_ref()?;
+    match key.code {
+        KeyCode::Esc | KeyCode::Enter => panel.stop_search(),
+        KeyCode::Backspace => panel.widen_search(),
+        _ if keymap.lookup(key) == Some(Command::IncrementalSearch) => panel.search_next(),
+        KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => panel.extend_search(c),
+        _ => {
+            panel.stop_search();
+            return None;
+        }
+    }
+    Some(CommandOutcome::Done)
+}
+
+/// handle a key while the active panel shows the directory tree
+///
+/// `None` means the key is not a tree key and should run its usual command.
+/// Enter shows the selected directory in the other panel.
+pub fn tree_key(key: &KeyEvent, manager: &mut PanelManager) -> Option<CommandOutcome> {
+    let tree = manager.active_mut().tree.as_mut()?;
+    if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+        return None;
+    }
+    match key.code {
+        KeyCode::Up => tree.move_cursor_up(1),
+        KeyCode::Down => tree.move_cursor_down(1),
+        KeyCode::PageUp => tree.move_cursor_up(PAGE_SIZE),
+        KeyCode::PageDown => tree.move_cursor_down(PAGE_SIZE),
+        KeyCode::Home => tree.cursor = 0,
+        KeyCode::End => tree.move_cursor_bottom(),
+        KeyCode::Right | KeyCode::Char('+') => return Some(outcome(tree.expand())),
+        KeyCode::Left | KeyCode::Char('-') => tree.collapse(),
+        KeyCode::Enter => {
+            let node = tree.selected()?;
+            let (vfs, path) = {
+                let node = node.read();
+                (Arc::clone(&node.vfs), node.path())
+            };
+            return Some(outcome(manager.inactive_mut().change_directory_in(vfs, &path)));
+        }
+        _ => return None,
+    }
+    Some(CommandOutcome::Done)
+}
+
+fn outcome(result: std::io::Result<()>) -> CommandOutcome {
+    match result {
+        Ok(()) => CommandOutcome::Done,
+        Err(err) => CommandOutcome::Message(err.to_string()),
+    }
+}
+
+/// ask where the active panel goes, with the directories it went through behind Up
+fn change_directory_dialog(manager: &PanelManager) -> CommandOutcome {
+    let panel = manager.active();
+    let completion = match panel.vfs().local_path(&panel.current_path()) {
+        Some(base) => Completion::Directories {
+            base,
+            cdpath: manager.cdpath.clone(),
+        },
+        None => Completion::None,
+    };
+    let label = "Path, with ~ and $VARIABLES, or an url like sftp://host/dir";
+    let dialog = ModalDialog::input(PromptKind::ChangeDirectory, "Change directory", label, "")
+        .with_history(panel.history.locations())
+        .with_completion(completion);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// show the directory typed in the change directory dialog, home when none
+///
+/// A path stays on the backend of the panel, an url opens its own.
+fn change_directory(input: &str, manager: &mut PanelManager) -> CommandOutcome {
+    let input = match input.trim() {
+        "" => "~",
+        input => input,
+    };
+    if input.contains("://") {
+        return jump_to_location(input, manager);
+    }
+    let cdpath = manager.cdpath.clone();
+    let panel = manager.active_mut();
+    let base = panel.current_path();
+    // names are looked up in the cdpath on the local disk only
+    let path = match panel.vfs().local_path(&base) {
+        Some(base) => paths::resolve_cd(input, &base, &cdpath),
+        None => base.join(expand_user_path(input)),
+    };
+    outcome(panel.change_directory(&path))
+}
+
+/// give the file under the cursor another name, in the directory of the other panel
+fn hardlink_cursor(manager: &mut PanelManager) -> CommandOutcome {
+    let Some(node) = manager.active().cursor_node() else {
+        return CommandOutcome::Done;
+    };
+    let (vfs, path, name, node_type) = {
+        let node = node.read();
+        (Arc::clone(&node.vfs), node.path(), node.name.clone(), node.node_type.clone())
+    };
+    if node_type != FsNodeType::File {
+        return CommandOutcome::Message(format!("{name} is not a file, only files have hardlinks"));
+    }
+    let dest = manager.inactive().root.clone();
+    let (dest_vfs, link) = {
+        let dest = dest.read();
+        (Arc::clone(&dest.vfs), dest.path().join(&*name))
+    };
+    if !vfs::same_vfs(&vfs, &dest_vfs) {
+        return CommandOutcome::Message(String::from("a hardlink cannot leave the filesystem of its file"));
+    }
+    if let Err(err) = vfs.hard_link(&path, &link) {
+        return CommandOutcome::Message(format!("{}: {err}", vfs.display_path(&link)));
+    }
+    manager.journal.record(Operation::Copy { vfs: Arc::clone(&vfs), created: vec![link.clone()] });
+    // the file counts one more link now
+    let _ = node.write().refresh_metadata();
+    if let Ok(created) = create_node_in(&vfs, &link) {
+        let mut dest = dest.write();
+        dest.remove_node(link, None);
+        dest.add_child(created);
+    }
+    manager.active_mut().refresh_view();
+    manager.inactive_mut().refresh_view();
+    CommandOutcome::Done
+}
+
+/// enter the directory or archive under the cursor, or run the open command associated with the file
+fn enter_cursor(manager: &mut PanelManager) -> CommandOutcome {
+    match manager.active_mut().enter() {
+        Ok(false) => match associated(manager, FileAction::Open) {
+            Some((command, dir)) => CommandOutcome::Run { command, dir },
+            None => CommandOutcome::Done,
+        },
+        Err(err) => {
+            // a directory the user cannot read can still be listed as root
+            let dir = manager.active().cursor_node().and_then(|node| {
+                let node = node.read();
+                node.is_navigable().then(|| node.vfs.local_path(&node.path())).flatten()
+            });
+            match dir {
+                Some(dir) => offer_escalation(manager, &err, Privileged::List { dir }),
+                None => CommandOutcome::Message(err.to_string()),
+            }
+        }
+        Ok(true) => CommandOutcome::Done,
+    }
+}
+
+/// ask to retry an operation that failed for lack of rights as root, or show the error
+///
+/// Only errors of permission are offered a retry, and only when there is a
+/// program to run it with.
+fn offer_escalation(manager: &mut PanelManager, err: &std::io::Error, operation: Privileged) -> CommandOutcome {
+    if err.kind() != std::io::ErrorKind::PermissionDenied || manager.escalation.is_none() {
+        return CommandOutcome::Message(err.to_string());
+    }
+    let question = format!("{err}\n{}", operation.question());
+    manager.refused = Some(operation);
+    let buttons = ["Retry as root", CANCEL];
+    let dialog = ModalDialog::confirm(PromptKind::Escalate, "Permission denied", &question, &buttons);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// local paths of nodes, `None` if one of them is on another backend
+fn local_paths(nodes: &[FsNodeRef]) -> Option<Vec<PathBuf>> {
+    nodes
+        .iter()
+        .map(|node| {
+            let node = node.read();
+            node.vfs.local_path(&node.path())
+        })
+        .collect()
+}
+
+/// the command associated with the file under the cursor, expanded, and the directory to run it in
+///
+/// Only local files have associations, the commands see them in their directory.
+fn associated(manager: &PanelManager, action: FileAction) -> Option<(String, PathBuf)> {
+    let panel = manager.active();
+    let node = panel.cursor_node()?;
+    let dir = panel.vfs().local_path(&panel.current_path())?;
+    let (name, mime) = {
+        let node = node.read();
+        if node.is_navigable() {
+            return None;
+        }
+        (node.name.clone(), node.mime_type())
+    };
+    let command = manager.associations.command(action, &name, mime)?;
+    Some((shell::expand_macros(command, panel), dir))
+}
+
+/// list the files printed by a shell command, one per line, in the active panel
+///
+/// The command runs in the directory of the panel, relative lines are from
+/// it. Lines naming no file are left out.
+fn external_panelize(command: &str, manager: &mut PanelManager) -> CommandOutcome {
+    let command = command.trim();
+    if command.is_empty() {
+        return CommandOutcome::Done;
+    }
+    manager.panelize_command = command.to_string();
+    let panel = manager.active_mut();
+    let start = panel.current_path();
+    let Some(dir) = panel.vfs().local_path(&start) else {
+        return CommandOutcome::Message(String::from("external panelize only runs in local directories"));
+    };
+    let output = match shell::capture_stdout(command, &dir) {
+        Ok((output, _)) => output,
+        Err(err) => return CommandOutcome::Message(format!("{}: {err}", shell::user_shell())),
+    };
+    let vfs = panel.vfs();
+    let mut missing = 0;
+    let mut entries = Vec::new();
+    for line in String::from_utf8_lossy(&output).lines().filter(|line| !line.trim().is_empty()) {
+        // `find .` prints `./name`, the dot is dropped for the names to be relative to the panel
+        let path: PathBuf = start
+            .join(line)
+            .components()
+            .filter(|component| *component != Component::CurDir)
+            .collect();
+        match vfs.metadata(&path) {
+            Ok(entry) => entries.push(entry),
+            Err(_) => missing += 1,
+        }
+    }
+    let found = entries.len();
+    if let Err(err) = panel.panelize(&format!("Panelize {command}"), &start, entries) {
+        return CommandOutcome::Message(err.to_string());
+    }
+    match missing {
+        0 => CommandOutcome::Message(format!("{found} files listed")),
+        missing => CommandOutcome::Message(format!("{found} files listed, lines naming no file left out: {missing}")),
+    }
+}
+
+/// pick an entry of the user menu offered for the file under the cursor
+fn user_menu(manager: &mut PanelManager) -> CommandOutcome {
+    let cursor = manager.active().cursor_node();
+    let cursor = cursor.as_ref().map(|node| node.read());
+    let entries = manager.user_menu.entries_for(cursor.as_deref());
+    if entries.is_empty() {
+        return CommandOutcome::Message(String::from("no entry of the user menu applies here"));
+    }
+    let labels = entries.iter().map(|entry| entry.label.clone()).collect();
+    CommandOutcome::Dialog(Box::new(ModalDialog::picker(PromptKind::UserMenu, "User menu", labels)))
+}
+
+/// open the file under the cursor in the viewer, or the output of its associated view command
+fn view_cursor(manager: &mut PanelManager) -> CommandOutcome {
+    if let Some((command, dir)) = associated(manager, FileAction::View) {
+        return match shell::capture(&command, &dir) {
+            Ok((mut output, status)) => {
+                if !status.success() {
+                    output.push_str(&format!("[{status}]\n"));
+                }
+                CommandOutcome::View(Box::new(Viewer::from_text(&command, &output)))
+            }
+            Err(err) => CommandOutcome::Message(format!("{}: {err}", shell::user_shell())),
+        };
+    }
+    let Some(node) = manager.active().cursor_node() else {
+        return CommandOutcome::Done;
+    };
+    let node = node.read();
+    if node.is_navigable() {
+        return CommandOutcome::Message(format!("{} is a directory", node.name));
+    }
+    match Viewer::open(&node.vfs, &node.path()) {
+        Ok(viewer) => CommandOutcome::View(Box::new(viewer)),
+        Err(err) => CommandOutcome::Message(format!("cannot open {}: {err}", node.name)),
+    }
+}
+
+/// hand the entry under the cursor to the default application of the system
+fn open_cursor(manager: &mut PanelManager) -> CommandOutcome {
+    let Some(node) = manager.active().cursor_node() else {
+        return CommandOutcome::Done;
+    };
+    let node = node.read();
+    match node.vfs.local_path(&node.path()) {
+        Some(path) => CommandOutcome::Open(path),
+        None => CommandOutcome::Message(format!("{} is not a local file, copy it here first", node.name)),
+    }
+}
+
+/// open the file under the cursor in the editor, or run its associated edit command
+fn edit_cursor(manager: &mut PanelManager) -> CommandOutcome {
+    if let Some((command, dir)) = associated(manager, FileAction::Edit) {
+        return CommandOutcome::Run { command, dir };
+    }
+    let Some(node) = manager.active().cursor_node() else {
+        return CommandOutcome::Done;
+    };
+    let node = node.read();
+    if node.is_navigable() {
+        return CommandOutcome::Message(format!("{} is a directory", node.name));
+    }
+    match Editor::open(&node.vfs, &node.path()) {
+        Ok(editor) => CommandOutcome::Edit(Box::new(editor)),
+        Err(err) => CommandOutcome::Message(format!("cannot edit {}: {err}", node.name)),
+    }
+}
+
+/// copy or move entries, asking first what to do with existing ones
+fn transfer_targets(manager: &mut PanelManager, moving: bool, transfer: Transfer) -> CommandOutcome {
+    // a dropped entry lands on a directory, never on a listing
+    let listing = match transfer {
+        Transfer::Targets => manager.inactive().listing.is_some().then_some("other"),
+        Transfer::Paste => manager.active().listing.is_some().then_some("active"),
+        Transfer::Drop => None,
+    };
+    if let Some(panel) = listing {
+        return CommandOutcome::Message(format!("the {panel} panel lists files from anywhere, not a directory"));
+    }
+    let (sources, dest) = transfer_entries(manager, transfer);
+    // a copy made where its sources are, both panels showing the same directory or a paste, goes next to them under a free name
+    if !moving && sources.iter().any(|node| is_in_directory(node, &dest)) {
+        return start_job(manager, moving, transfer, Some(ConflictResolution::RenameAllWithSuffix));
+    }
+    let conflicts = target_conflicts(&sources, &dest, moving);
+    if !conflicts.is_empty() && !manager.confirm.overwrite {
+        return start_job(manager, moving, transfer, Some(ConflictResolution::OverwriteAll));
+    }
+    // a destination shorter than its source is taken for a copy cut off, it can be resumed
+    let partial = conflicts.iter().filter(|conflict| conflict.is_partial()).count();
+    let what = match conflicts.as_slice() {
+        [] => return start_job(manager, moving, transfer, None),
+        [conflict] => format!(
+            "{} exists, {} over {}{}",
+            conflict.dest.name,
+            describe_entry(&conflict.source, &manager.format),
+            describe_entry(&conflict.dest, &manager.format),
+            if partial > 0 { ", it looks cut off" } else { "" }
+        ),
+        conflicts if partial > 0 => format!("{} targets exist, {partial} look cut off", conflicts.len()),
+        conflicts => format!("{} targets exist", conflicts.len()),
+    };
+    // overwriting refuses those, see `CopyConflict::changes_kind`
+    let what = match conflicts.iter().any(CopyConflict::changes_kind) {
+        true => format!("{what}, a directory and a file do not overwrite each other"),
+        false => what,
+    };
+    let mut buttons: Vec<&str> = OVERWRITE_CHOICES.iter().map(|(label, _)| *label).collect();
+    if partial > 0 {
+        buttons.push("Resume");
+    }
+    buttons.push(CANCEL);
+    let title = if moving { "Move" } else { "Copy" };
+    let kind = PromptKind::Overwrite { moving, partial: partial > 0, transfer };
+    let dialog = ModalDialog::confirm(kind, title, &what, &buttons);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// the entries a copy or a move takes and the directory they go to
+fn transfer_entries(manager: &PanelManager, transfer: Transfer) -> (Vec<FsNodeRef>, FsNodeRef) {
+    match (transfer, &manager.file_clipboard, &manager.file_drop) {
+        (Transfer::Targets, ..) => (manager.active().targets(), manager.inactive().root.clone()),
+        (Transfer::Paste, Some(clipboard), _) => (clipboard.nodes.clone(), manager.active().root.clone()),
+        (Transfer::Paste, None, _) => (Vec::new(), manager.active().root.clone()),
+        (Transfer::Drop, _, Some(drop)) => (drop.nodes.clone(), drop.dest.clone()),
+        (Transfer::Drop, _, None) => (Vec::new(), manager.active().root.clone()),
+    }
+}
+
+/// true if `node` is an entry of the directory `dir`
+pub fn is_in_directory(node: &FsNodeRef, dir: &FsNodeRef) -> bool {
+    let (node, dir) = (node.read(), dir.read());
+    vfs::same_vfs(&node.vfs, &dir.vfs) && node.path().parent() == Some(dir.path().as_path())
+}
+
+/// keep the targets of the active panel in the file clipboard, to be copied or moved by a paste
+fn fill_file_clipboard(manager: &mut PanelManager, moving: bool) -> CommandOutcome {
+    let nodes = manager.active().targets();
+    if nodes.is_empty() {
+        return CommandOutcome::Done;
+    }
+    let what = match nodes.as_slice() {
+        [node] => node.read().name.to_string(),
+        nodes => format!("{} entries", nodes.len()),
+    };
+    manager.file_clipboard = Some(FileClipboard { nodes, moving });
+    manager.active_mut().selection.clear();
+    let action = if moving { "cut" } else { "copied" };
+    CommandOutcome::Message(format!("{what} {action}, Ctrl+V pastes into the directory of a panel"))
+}
+
+/// copy or move what the file clipboard holds into the directory of the active panel
+fn paste_file_clipboard(manager: &mut PanelManager) -> CommandOutcome {
+    let Some(clipboard) = &manager.file_clipboard else {
+        return CommandOutcome::Message(String::from("nothing was cut or copied"));
+    };
+    let moving = clipboard.moving;
+    transfer_targets(manager, moving, Transfer::Paste)
+}
+
+/// copy or move entries dropped with the mouse into the directory `dest`
+pub fn drop_files(manager: &mut PanelManager, nodes: Vec<FsNodeRef>, dest: FsNodeRef, moving: bool) -> CommandOutcome {
+    manager.file_drop = Some(FileDrop { nodes, dest });
+    transfer_targets(manager, moving, Transfer::Drop)
+}
+
+/// put the name or the path of the cursor entry in the clipboard, or the paths of the targets
+///
+/// The marked paths go one per line, or on one line quoted for a shell.
+fn copy_to_clipboard(command: Command, panel: &Panel) -> CommandOutcome {
+    let targets = match command {
+        Command::CopyName | Command::CopyPath => panel.cursor_node().into_iter().collect(),
+        _ => panel.targets(),
+    };
+    if targets.is_empty() {
+        return CommandOutcome::Done;
+    }
+    let paths: Vec<String> = targets.iter().map(|node| node.read().path().display().to_string()).collect();
+    let text = match command {
+        Command::CopyName => targets[0].read().name.to_string(),
+        Command::CopyQuotedPaths => paths.iter().map(|path| shell::shell_quote(path)).collect::<Vec<_>>().join(" "),
+        _ => paths.join("\n"),
+    };
+    match (clipboard::copy(&text), paths.len()) {
+        (Err(err), _) => CommandOutcome::Message(format!("clipboard: {err}")),
+        (Ok(()), 1) => CommandOutcome::Message(format!("copied {text} to the clipboard")),
+        (Ok(()), count) => CommandOutcome::Message(format!("copied {count} paths to the clipboard")),
+    }
+}
+
+/// ask for the name to save the marks of the active panel under, the saved names are offered
+fn save_selection_dialog(manager: &PanelManager) -> CommandOutcome {
+    if manager.active().selection.is_empty() {
+        return CommandOutcome::Message(String::from("no entries are marked"));
+    }
+    let names = manager.selections.keys().cloned().collect();
+    let dialog = ModalDialog::input(PromptKind::SaveSelection, "Save marks", "Name of the saved marks", "").with_history(names);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// ask which saved marks to restore
+fn restore_selection_dialog(manager: &PanelManager) -> CommandOutcome {
+    if manager.selections.is_empty() {
+        return CommandOutcome::Message(String::from("no marks were saved"));
+    }
+    let labels = manager
+        .selections
+        .iter()
+        .map(|(name, paths)| format!("{name} ({} entries)", paths.len()))
+        .collect();
+    CommandOutcome::Dialog(Box::new(ModalDialog::picker(PromptKind::RestoreSelection, "Restore marks", labels)))
+}
+
+/// mark again the entries of saved marks `choice` that the active panel lists
+fn restore_selection(choice: usize, manager: &mut PanelManager) -> CommandOutcome {
+    let Some(saved) = manager.selections.values().nth(choice).cloned() else {
+        return CommandOutcome::Done;
+    };
+    let count = saved.len();
+    match manager.active_mut().restore_selection(&saved) {
+        0 => CommandOutcome::Message(format!("none of the {count} saved entries is listed here")),
+        restored if restored < count => CommandOutcome::Message(format!("{restored} of the {count} saved entries marked")),
+        _ => CommandOutcome::Done,
+    }
+}
+
+/// ask how the active panel sorts its entries, starting from `current`
+fn sort_dialog(current: SortMode) -> CommandOutcome {
+    let keys = SortKey::ALL.iter().map(|key| key.label().to_string()).collect();
+    let mut widgets = vec![Widget::List(ListField::new(keys))];
+    widgets.extend(
+        [
+            ("Descending", current.descending),
+            ("Directories first", current.directories_first),
+            ("Case sensitive", current.case_sensitive),
+            ("Numbers by value, file2 before file10", current.natural),
+            ("Collation of the locale", current.locale),
+        ]
+        .into_iter()
+        .map(|(label, checked)| Widget::Check(CheckField::new(label, checked))),
+    );
+    widgets.push(Widget::Buttons(vec![String::from("Ok"), String::from(CANCEL)]));
+    let current_key = SortKey::ALL.iter().position(|key| *key == current.key).unwrap_or(0);
+    let dialog = ModalDialog::new(PromptKind::Sort, "Sort order", widgets).with_choice(current_key);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// ask what the entries to mark have, like `>1G` for the size and `1y` for modified before
+fn criteria_dialog() -> CommandOutcome {
+    let kinds = EntryKind::ALL.iter().map(|kind| kind.label().to_string()).collect();
+    let widgets = vec![
+        Widget::Input(InputField::new("Names, glob patterns separated by spaces", "")),
+        Widget::Input(InputField::new("Size, like >1G, <100K or 10M-1G", "")),
+        Widget::Input(InputField::new("Modified before, like 2024-05-31 or 1y for a year ago", "")),
+        Widget::Input(InputField::new("Modified after, like 2024-05-31 or 30d", "")),
+        Widget::Input(InputField::new("Owner, a user name or id", "")),
+        Widget::List(ListField::new(kinds)),
+        Widget::Buttons(vec![String::from("Mark"), String::from("Unmark"), String::from(CANCEL)]),
+    ];
+    let dialog = ModalDialog::new(PromptKind::SelectByCriteria, "Mark by criteria", widgets);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// mark or unmark the entries of the active panel meeting the criteria of the dialog
+fn mark_by_criteria(answer: &DialogAnswer, mark: bool, manager: &mut PanelManager) -> CommandOutcome {
+    let input = |index: usize| answer.inputs.get(index).map(String::as_str).unwrap_or("");
+    let text = CriteriaText {
+        patterns: input(0),
+        size: input(1),
+        modified_before: input(2),
+        modified_after: input(3),
+        owner: input(4),
+    };
+    let kind = EntryKind::ALL[answer.choice.unwrap_or(0)];
+    match SelectCriteria::parse(&text, kind, SystemTime::now()) {
+        Ok(criteria) => {
+            let matched = manager.active_mut().mark_by_criteria(&criteria, mark);
+            CommandOutcome::Message(format!("{matched} entries {}", if mark { "marked" } else { "unmarked" }))
+        }
+        Err(err) => CommandOutcome::Message(err),
+    }
+}
+
+/// ask how to pack the targets into an archive of the other panel
+fn compress_dialog(manager: &PanelManager) -> CommandOutcome {
+    if manager.inactive().listing.is_some() {
+        return CommandOutcome::Message(String::from("the other panel lists files from anywhere, not a directory"));
+    }
+    let panel = manager.active();
+    // one target names the archive, several are named after their directory
+    let name = match panel.targets().as_slice() {
+        [] => return CommandOutcome::Done,
+        [target] => target.read().name.clone(),
+        _ => panel.root.read().name.clone(),
+    };
+    let formats = ArchiveFormat::ALL.iter().map(|format| format.label().to_string()).collect();
+    let widgets = vec![
+        Widget::Input(InputField::new("Archive name, the extension follows the format", &name)),
+        Widget::Input(InputField::new("Compression level, empty for the default", "")),
+        Widget::List(ListField::new(formats)),
+        Widget::Buttons(vec![String::from("Compress"), String::from(CANCEL)]),
+    ];
+    let dialog = ModalDialog::new(PromptKind::Compress, "Pack into an archive", widgets);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// pack the targets in the background as answered in the compress dialog
+fn compress_targets(answer: &DialogAnswer, manager: &PanelManager) -> CommandOutcome {
+    let format = ArchiveFormat::ALL[answer.choice.unwrap_or(0)];
+    let name = answer.inputs.first().map(|name| name.trim()).unwrap_or("");
+    if name.is_empty() {
+        return CommandOutcome::Message(String::from("the archive needs a name"));
+    }
+    let name = match name.to_lowercase().ends_with(format.extension()) {
+        true => name.to_string(),
+        false => format!("{name}{}", format.extension()),
+    };
+    let levels = format.levels();
+    let level = match answer.inputs.get(1).map(|level| level.trim()).unwrap_or("") {
+        "" => format.default_level(),
+        level => match level.parse() {
+            Ok(level) if levels.contains(&level) => level,
+            _ => {
+                return CommandOutcome::Message(format!(
+                    "the levels of {} go from {} to {}",
+                    format.label(),
+                    levels.start(),
+                    levels.end()
+                ));
+            }
+        },
+    };
+    let dest = manager.inactive().root.clone();
+    let exists = {
+        let dest = dest.read();
+        dest.vfs.metadata(&dest.path().join(&name)).is_ok()
+    };
+    if exists {
+        return CommandOutcome::Message(format!("{name} exists already"));
+    }
+    let options = CompressOptions { format, level };
+    let job = Job::compress(manager.active().targets(), dest, name, options, manager.events.clone());
+    CommandOutcome::Job(Box::new(JobDialog::new(job, manager.format.units)))
+}
+
+/// extract the archive under the cursor into the directory of the other panel, or of the active one
+///
+/// The members go into a directory named after the archive unless they are
+/// a single directory. An encrypted zip asks for its password first.
+fn extract_archive(manager: &mut PanelManager, here: bool, password: Option<&str>) -> CommandOutcome {
+    let dest = match here {
+        true => manager.active(),
+        false => manager.inactive(),
+    };
+    if dest.listing.is_some() {
+        return CommandOutcome::Message(String::from("the panel lists files from anywhere, not a directory"));
+    }
+    let dest = dest.root.clone();
+    let Some(node) = manager.active().cursor_node() else {
+        return CommandOutcome::Done;
+    };
+    let (vfs, path, name) = {
+        let node = node.read();
+        (Arc::clone(&node.vfs), node.path(), node.name.clone())
+    };
+    let archive = match vfs::open_archive_with_password(&vfs, &path, password) {
+        None => return CommandOutcome::Message(format!("{name} is not an archive")),
+        Some(Ok(archive)) => archive,
+        Some(Err(err)) => {
+            let label = match vfs::password_error(&err) {
+                Some(PasswordError::Required) => format!("Password of {name}"),
+                Some(PasswordError::Wrong) => format!("Wrong password, password of {name}"),
+                None => return CommandOutcome::Message(format!("{name}: {err}")),
+            };
+            let widgets = vec![
+                Widget::Input(InputField::password(&label)),
+                Widget::Buttons(vec![String::from("Ok"), String::from(CANCEL)]),
+            ];
+            let dialog = ModalDialog::new(PromptKind::ExtractPassword { here }, "Extract", widgets);
+            return CommandOutcome::Dialog(Box::new(dialog));
+        }
+    };
+    let root = archive.host().map(|(_, root)| root).unwrap_or(path);
+    let members = archive.read_dir(&root).and_then(|members| match members.as_slice() {
+        [member] if member.node_type == FsNodeType::Directory => Ok(members),
+        // the root of the archive is copied as a directory named after it
+        _ => archive.metadata(&root).map(|mut root| {
+            root.name = archive_stem(&name);
+            vec![root]
+        }),
+    });
+    let targets = match members {
+        Ok(members) => members.into_iter().map(|member| FsNode::from_entry(member, Arc::clone(&archive)).into_ref()).collect(),
+        Err(err) => return CommandOutcome::Message(format!("{name}: {err}")),
+    };
+    let extraction = Extraction { targets, dest };
+
+    let existing: Vec<String> = {
+        let dest = extraction.dest.read();
+        extraction
+            .targets
+            .iter()
+            .map(|target| target.read().name.to_string())
+            .filter(|name| dest.vfs.metadata(&dest.path().join(name)).is_ok())
+            .collect()
+    };
+    let what = match existing.as_slice() {
+        [] => return start_extraction(manager, extraction, None),
+        [name] => format!("{name} exists, its files are merged with those of the archive"),
+        existing => format!("{} targets exist", existing.len()),
+    };
+    let mut buttons: Vec<&str> = OVERWRITE_CHOICES.iter().map(|(label, _)| *label).collect();
+    buttons.push(CANCEL);
+    manager.extraction = Some(extraction);
+    let dialog = ModalDialog::confirm(PromptKind::ExtractOverwrite, "Extract", &what, &buttons);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// copy the members of an archive out in the background, `resolution` answers every conflict
+fn start_extraction(manager: &PanelManager, extraction: Extraction, resolution: Option<ConflictResolution>) -> CommandOutcome {
+    let Extraction { targets, dest } = extraction;
+    let job = Job::start(JobKind::Extract, targets, dest, manager.copy_options, resolution, manager.events.clone());
+    CommandOutcome::Job(Box::new(JobDialog::new(job, manager.format.units)))
+}
+
+/// name of an archive without its extension, with the `.tar` of a compressed tar
+fn archive_stem(name: &str) -> String {
+    let stem = Path::new(name).file_stem().map_or(name.into(), |stem| stem.to_string_lossy());
+    let tar = stem.len().checked_sub(4).and_then(|start| stem.get(start..)).is_some_and(|end| end.eq_ignore_ascii_case(".tar"));
+    match tar {
+        true => stem[..stem.len() - 4].to_string(),
+        false => stem.into_owned(),
+    }
+}
+
+/// the sources already present in the directory `dest`
+fn target_conflicts(sources: &[FsNodeRef], dest: &FsNodeRef, moving: bool) -> Vec<CopyConflict> {
+    let (dest_vfs, dest_dir) = {
+        let dest = dest.read();
+        (Arc::clone(&dest.vfs), dest.path())
+    };
+    sources
+        .iter()
+        .filter_map(|node| {
+            let node = node.read();
+            let target = dest_dir.join(&*node.name);
+            // moving a node onto itself is harmless, a copy onto itself would empty it
+            if moving && vfs::same_vfs(&node.vfs, &dest_vfs) && target == node.path() {
+                return None;
+            }
+            Some(CopyConflict {
+                source: node.vfs.metadata(&node.path()).ok()?,
+                dest: dest_vfs.metadata(&target).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// the device, type and space of the filesystem of the active panel in a dialog
+fn filesystem_info(manager: &mut PanelManager) -> CommandOutcome {
+    let units = manager.format.units;
+    let Some(status) = manager.active_mut().fs_status() else {
+        return CommandOutcome::Message(String::from("only local directories have a filesystem to show"));
+    };
+    let mut lines = Vec::new();
+    match &status.mount {
+        Some(mount) => {
+            lines.push(format!("Device:  {}", mount.device));
+            lines.push(format!("Mounted: {}", mount.mount_point.display()));
+            lines.push(format!("Type:    {}", mount.fs_type));
+        }
+        None => lines.push(String::from("The mount is unknown")),
+    }
+    match status.space {
+        Some(space) => {
+            // a filesystem without blocks, like /proc, has no share used
+            let percent = (space.used() as f64 * 100.0 / space.total.max(1) as f64).round();
+            lines.push(format!("Total:   {}", format::size(space.total, units)));
+            lines.push(format!("Used:    {} ({percent}%)", format::size(space.used(), units)));
+            lines.push(format!("Free:    {}", format::size(space.free, units)));
+        }
+        None => lines.push(String::from("The space cannot be read")),
+    }
+    let dialog = ModalDialog::message(PromptKind::FilesystemInfo, "Filesystem information", &lines.join("\n"));
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// size and modification time of one side of a conflict, the size to the byte
+fn describe_entry(entry: &VfsEntry, config: &FormatConfig) -> String {
+    let modified = entry.metadata.modified.map(|time| format::time(time, &config.time_format));
+    format!("{} bytes {}", entry.metadata.size, modified.as_deref().unwrap_or("?"))
+}
+
+/// ask the size of the parts to split the targets into, files only
+fn ask_split(manager: &PanelManager) -> CommandOutcome {
+    let targets = manager.active().targets();
+    if targets.is_empty() {
+        return CommandOutcome::Done;
+    }
+    if let Some(dir) = targets.iter().find(|node| node.read().node_type != FsNodeType::File) {
+        return CommandOutcome::Message(format!("{} is not a file, only files can be split", dir.read().name));
+    }
+    let title = "Size of the parts, like 4095M for FAT32 or 20M for mail";
+    CommandOutcome::Prompt(Prompt::new(PromptKind::Split, title, "4095M"))
+}
+
+/// join the files the targets are parts of in the background, each file once
+fn start_join(manager: &PanelManager) -> CommandOutcome {
+    let mut names = HashSet::new();
+    let targets: Vec<FsNodeRef> = manager
+        .active()
+        .targets()
+        .into_iter()
+        .filter(|node| ops::joined_name(&node.read().name).is_some_and(|name| names.insert(name.to_string())))
+        .collect();
+    if targets.is_empty() {
+        return CommandOutcome::Message(String::from("nothing to join, parts are named like file.001"));
+    }
+    let job = Job::join(targets, manager.inactive().root.clone(), manager.events.clone());
+    CommandOutcome::Job(Box::new(JobDialog::new(job, manager.format.units)))
+}
+
+/// copy or move the targets in the background, `resolution` answers every conflict
+///
+/// A cut and paste empties the file clipboard, its entries are gone from
+/// where they were.
+fn start_job(manager: &mut PanelManager, moving: bool, transfer: Transfer, resolution: Option<ConflictResolution>) -> CommandOutcome {
+    let kind = if moving { JobKind::Move } else { JobKind::Copy };
+    let (targets, dest) = transfer_entries(manager, transfer);
+    match transfer {
+        Transfer::Paste if moving => manager.file_clipboard = None,
+        Transfer::Drop => manager.file_drop = None,
+        _ => {}
+    }
+    let job = Job::start(kind, targets, dest, manager.copy_options, resolution, manager.events.clone());
+    CommandOutcome::Job(Box::new(JobDialog::new(job, manager.format.units)))
+}
+
+/// record what a stopped job did and show the panels it changed, with a message if needed
+///
+/// A job refused for lack of rights offers to do what is left of it as root.
+pub fn finish_job(report: JobReport, manager: &mut PanelManager) -> CommandOutcome {
+    manager.journal.record(report.operation);
+    manager.active_mut().selection.clear();
+    manager.active_mut().refresh_view();
+    manager.inactive_mut().refresh_view();
+    match report.result {
+        Err(err) => {
+            let dest = report.dest.read().vfs.local_path(&report.dest.read().path());
+            match (local_paths(&report.unfinished), dest) {
+                (Some(sources), Some(dest)) if !sources.is_empty() => {
+                    let operation = match report.kind {
+                        JobKind::Copy => Privileged::Copy {
+                            sources,
+                            dest,
+                            resolution: report.resolution,
+                        },
+                        JobKind::Move => Privileged::Move {
+                            sources,
+                            dest,
+                            resolution: report.resolution,
+                        },
+                        // archives are not read or written as root, nor files shredded
+                        JobKind::Compress | JobKind::Extract | JobKind::Shred | JobKind::Split | JobKind::Join => {
+                            return CommandOutcome::Message(err.to_string());
+                        }
+                    };
+                    offer_escalation(manager, &err, operation)
+                }
+                _ => CommandOutcome::Message(err.to_string()),
+            }
+        }
+        Ok(()) if report.files_skipped > 0 || !report.lost.is_empty() || !report.notes.is_empty() => {
+            let mut parts: Vec<String> = report.lost.iter().map(ToString::to_string).collect();
+            if report.files_skipped > 0 {
+                parts.insert(0, format!("{} files done, {} skipped", report.files_copied, report.files_skipped));
+            }
+            parts.extend(report.notes);
+            CommandOutcome::Message(parts.join("; "))
+        }
+        Ok(()) => CommandOutcome::Done,
+    }
+}
+
+/// show a visit picked in the history of the active panel
+pub fn jump_in_history(index: usize, manager: &mut PanelManager) -> CommandOutcome {
+    match manager.active_mut().history_jump(index) {
+        Ok(()) => CommandOutcome::Done,
+        Err(err) => CommandOutcome::Message(format!("{err}, forgotten by the history")),
+    }
+}
+
+fn history_outcome(result: std::io::Result<usize>) -> CommandOutcome {
+    match result {
+        Ok(0) => CommandOutcome::Done,
+        Ok(dropped) => CommandOutcome::Message(format!("{dropped} directories of the history no longer exist")),
+        Err(err) => CommandOutcome::Message(err.to_string()),
+    }
+}
+
+/// show a local path or an url in the active panel
+pub fn jump_to_location(location: &str, manager: &mut PanelManager) -> CommandOutcome {
+    let result = vfs::open_location(location).and_then(|(vfs, path)| manager.active_mut().change_directory_in(vfs, &path));
+    match result {
+        Ok(()) => CommandOutcome::Done,
+        Err(err) => CommandOutcome::Message(format!("{location}: {err}")),
+    }
+}
+
+/// open the saved connection of this index in the active panel, asking for a password the host wants
+pub fn connect_saved(index: usize, password: Option<&str>, manager: &mut PanelManager) -> CommandOutcome {
+    let saved = Connections::default_path().and_then(|path| Connections::load(&path).ok());
+    let Some(connection) = saved.and_then(|saved| saved.connections.into_iter().nth(index)) else {
+        return CommandOutcome::Done;
+    };
+    let result = connection.open(password).and_then(|(vfs, path)| manager.active_mut().change_directory_in(vfs, &path));
+    match result {
+        Ok(()) => CommandOutcome::Done,
+        Err(err) if vfs::authentication_failed(&err) => {
+            let label = match password {
+                Some(_) => format!("Wrong password, password of {}", connection.name),
+                None => format!("Password of {}", connection.name),
+            };
+            let widgets = vec![
+                Widget::Input(InputField::password(&label)),
+                Widget::Buttons(vec![String::from("Connect"), String::from(CANCEL)]),
+            ];
+            let dialog = ModalDialog::new(PromptKind::ConnectionPassword { index }, "Connect", widgets);
+            CommandOutcome::Dialog(Box::new(dialog))
+        }
+        Err(err) => CommandOutcome::Message(format!("{}: {err}", connection.name)),
+    }
+}
+
+/// the form editing the saved connection of this index, or a new one
+pub fn edit_connection(index: Option<usize>, connections: &Connections) -> CommandOutcome {
+    let connection = index.and_then(|index| connections.connections.get(index)).cloned().unwrap_or_default();
+    CommandOutcome::Dialog(Box::new(connection_form(index, &connection, None)))
+}
+
+fn connection_form(index: Option<usize>, connection: &Connection, error: Option<&str>) -> ModalDialog {
+    let password = match connection.keyring {
+        true => "Password or S3 secret key, empty keeps the one in the keyring",
+        false => "Password or S3 secret key, kept in the keyring, optional",
+    };
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    let mut widgets = Vec::new();
+    if let Some(error) = error {
+        widgets.push(Widget::Text(error.to_string()));
+    }
+    widgets.extend([
+        Widget::Input(InputField::new("Name", &connection.name)),
+        Widget::Input(InputField::new("Url, like sftp://user@host/dir or s3://bucket/prefix", &connection.url)),
+        Widget::Input(InputField::password(password)),
+        Widget::Input(InputField::new("S3 endpoint, empty for the config file", &optional(&connection.endpoint))),
+        Widget::Input(InputField::new("S3 region", &optional(&connection.region))),
+        Widget::Input(InputField::new("S3 access key id", &optional(&connection.access_key_id))),
+        Widget::Buttons(vec![String::from("Save"), String::from(CANCEL)]),
+    ]);
+    let title = match index {
+        Some(_) => "Edit connection",
+        None => "New connection",
+    };
+    ModalDialog::new(PromptKind::SaveConnection { index }, title, widgets)
+}
+
+/// save the connection of the form, the password in the keyring, and list the connections again
+fn save_connection(index: Option<usize>, answer: &DialogAnswer) -> CommandOutcome {
+    let Some(path) = Connections::default_path() else {
+        return CommandOutcome::Message(String::from("no configuration directory, connections are not saved"));
+    };
+    let mut connections = match Connections::load(&path) {
+        Ok(connections) => connections,
+        Err(err) => return CommandOutcome::Message(err.to_string()),
+    };
+    let input = |position: usize| answer.inputs.get(position).map(|text| text.trim().to_string()).unwrap_or_default();
+    let optional = |position: usize| Some(input(position)).filter(|text| !text.is_empty());
+    let previous = index.and_then(|index| connections.connections.get(index)).cloned();
+    let mut password = answer.inputs.get(2).cloned().unwrap_or_default();
+    let mut connection = Connection {
+        name: input(0),
+        url: input(1),
+        endpoint: optional(3),
+        region: optional(4),
+        access_key_id: optional(5),
+        ..previous.clone().unwrap_or_default()
+    };
+    // a password typed in the url goes to the keyring with the others
+    if let Some(mut url) = SftpUrl::parse(&connection.url)
+        && let Some(typed) = url.password.take()
+    {
+        if password.is_empty() {
+            password = typed;
+        }
+        connection.url = url.to_string();
+    }
+
+    let taken = connections.position(&connection.name).is_some_and(|position| Some(position) != index);
+    let error = if connection.name.is_empty() {
+        Some(String::from("a connection needs a name"))
+    } else if !Connection::is_remote_url(&connection.url) {
+        Some(String::from("the url must start with sftp:// or s3://"))
+    } else if taken {
+        Some(format!("there is already a connection {}", connection.name))
+    } else {
+        store_secret(&mut connection, previous.as_ref(), &password).err().map(|err| format!("keyring: {err}"))
+    };
+    if let Some(error) = error {
+        return CommandOutcome::Dialog(Box::new(connection_form(index, &connection, Some(&error))));
+    }
+
+    match index.filter(|&index| index < connections.connections.len()) {
+        Some(index) => connections.connections[index] = connection.clone(),
+        None => connections.connections.push(connection.clone()),
+    }
+    let mut dialog = ConnectionDialog::new(Some(path.clone()));
+    if let Err(err) = connections.save(&path) {
+        dialog.set_error(err.to_string());
+    }
+    dialog.connections = connections;
+    dialog.select(&connection.name);
+    CommandOutcome::Connections(Box::new(dialog))
+}
+
+/// keep the password typed for a connection in the keyring, or move the one there to a new name
+fn store_secret(connection: &mut Connection, previous: Option<&Connection>, password: &str) -> std::io::Result<()> {
+    let renamed = previous.filter(|previous| previous.keyring && previous.name != connection.name);
+    if !password.is_empty() {
+        keyring::store(&connection.name, password)?;
+        connection.keyring = true;
+    } else if let Some(previous) = renamed
+        && let Some(secret) = keyring::lookup(&previous.name)?
+    {
+        keyring::store(&connection.name, &secret)?;
+    }
+    if let Some(previous) = renamed {
+        keyring::delete(&previous.name)?;
+    }
+    Ok(())
+}
+
+/// rename the files of the active panel as reviewed in the rename dialog
+pub fn apply_renames(renames: &[Rename], manager: &mut PanelManager) -> CommandOutcome {
+    let panel = manager.active_mut();
+    let vfs = panel.vfs();
+    let result = rename::execute(&vfs, renames);
+    panel.selection.clear();
+    let _ = panel.reload();
+    let renamed = renames.iter().find(|rename| !rename.is_unchanged());
+    if let Some(position) = renamed.and_then(|rename| panel.position_of(&rename.to)) {
+        panel.cursor = position;
+    }
+    if result.is_ok() {
+        manager.journal.record(Operation::Rename {
+            vfs,
+            renames: renames.to_vec(),
+        });
+    }
+    match result {
+        Ok(count) => CommandOutcome::Message(format!("{count} files renamed")),
+        Err(err) => CommandOutcome::Message(format!("nothing renamed: {err}")),
+    }
+}
+
+/// change the permissions of entries of the active panel as chosen in the dialog
+pub fn apply_chmod(targets: &[FsNodeRef], options: &ChmodOptions, manager: &mut PanelManager) -> CommandOutcome {
+    let report = ops::change_mode(targets, options);
+    manager.active_mut().selection.clear();
+    // the entries refused are offered to root, with the mode the others got
+    let vfs = manager.active().vfs();
+    let denied: Vec<PathBuf> = report
+        .errors
+        .iter()
+        .filter(|(_, err)| err.kind() == std::io::ErrorKind::PermissionDenied)
+        .filter_map(|(path, _)| vfs.local_path(path))
+        .collect();
+    match report.errors.first() {
+        Some((_, err)) if !denied.is_empty() => {
+            let err = std::io::Error::new(err.kind(), report.summary());
+            offer_escalation(manager, &err, Privileged::Chmod { paths: denied, mode: options.mode })
+        }
+        _ => CommandOutcome::Message(report.summary()),
+    }
+}
+
+/// change the owner and group of entries of the active panel as chosen in the dialog
+pub fn apply_chown(targets: &[FsNodeRef], options: &ChownOptions, manager: &mut PanelManager) -> CommandOutcome {
+    if options.uid.is_none() && options.gid.is_none() {
+        return CommandOutcome::Done;
+    }
+    let report = ops::change_owner(targets, options);
+    manager.active_mut().selection.clear();
+    CommandOutcome::Message(report.summary())
+}
+
+/// ask before deleting the targets, F8 moves local files to the trash
+fn confirm_delete(command: Command, manager: &mut PanelManager) -> CommandOutcome {
+    let panel = manager.active();
+    let targets = panel.targets();
+    let what = match targets.as_slice() {
+        [] => return CommandOutcome::Done,
+        [node] => node.read().name.to_string(),
+        targets => format!("{} entries", targets.len()),
+    };
+    // only local files can go to the trash, a trashed entry is purged
+    let trash = command == Command::Delete && panel.vfs().scheme() == "file" && Trash::home().is_some();
+    let question = if trash {
+        format!("Move {what} to the trash?")
+    } else {
+        format!("Delete {what} for good?")
+    };
+    if !manager.confirm.delete {
+        return delete_targets(manager, trash);
+    }
+    let dialog = ModalDialog::confirm(PromptKind::Delete { trash }, "Delete", &question, &["Yes", "No"]);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// ask before shredding the targets of the active panel, refused where overwriting misses the old content
+fn confirm_shred(manager: &mut PanelManager) -> CommandOutcome {
+    let panel = manager.active();
+    let targets = panel.targets();
+    let what = match targets.as_slice() {
+        [] => return CommandOutcome::Done,
+        [node] => node.read().name.to_string(),
+        targets => format!("{} entries", targets.len()),
+    };
+    if panel.vfs().local_path(&panel.current_path()).is_none() {
+        return CommandOutcome::Message(String::from("only local files can be shredded"));
+    }
+    let solid = targets.iter().find(|node| ops::is_solid_state(&node.read().path()) == Some(true));
+    if let Some(node) = solid {
+        return CommandOutcome::Message(format!(
+            "{} is on a solid state drive, overwriting it would leave the old content on the drive, delete it instead",
+            node.read().name
+        ));
+    }
+    let options = manager.shred_options;
+    let question = format!(
+        "Overwrite {what} {} times with {} and delete? Nothing brings it back, neither the trash nor undo.\n\n\
+         Copy-on-write filesystems like btrfs or ZFS, snapshots and backups can keep the old content elsewhere.",
+        options.passes.max(1),
+        options.pattern.label()
+    );
+    let dialog = ModalDialog::confirm(PromptKind::Shred, "Shred", &question, &[CANCEL, "Shred"]);
+    CommandOutcome::Dialog(Box::new(dialog))
+}
+
+/// delete the selection, or the cursor entry, of the active panel
+fn delete_targets(manager: &mut PanelManager, to_trash: bool) -> CommandOutcome {
+    let panel = manager.active_mut();
+    let targets = panel.targets();
+    let vfs = panel.vfs();
+    let mut trashed = Vec::new();
+    let mut result = Ok(());
+    let mut unfinished = Vec::new();
+    for (index, node) in targets.iter().enumerate() {
+        let path = node.read().path();
+        let removed = match to_trash {
+            // an entry goes to the trash of its own filesystem
+            true => match path.parent().and_then(Trash::for_directory) {
+                Some(trash) => trash.put(&path).map(|item| {
+                    trashed.push((trash, item));
+                    true
+                }),
+                None => Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no home directory for the trash")),
+            },
+            // the trash purges whole entries itself
+            false if vfs.scheme() == "trash" => vfs.remove(&path).map(|_| true),
+            false => ops::delete_node(node, &mut ops::delete::confirm_all).map(|_| false),
+        };
+        match removed {
+            Ok(true) => {
+                let parent = node.read().parent();
+                if let Some(parent) = parent {
+                    parent.write().remove_node(path, None);
+                }
+            }
+            Ok(false) => {}
+            Err(err) => {
+                result = Err(err);
+                unfinished = targets[index..].to_vec();
+                break;
+            }
+        }
+    }
+    panel.selection.clear();
+    panel.refresh_view();
+    // what the trash refused is not deleted for good behind the user's back
+    let escalated = match to_trash {
+        false if vfs::is_local(&vfs) => local_paths(&unfinished),
+        _ => None,
+    };
+    if to_trash {
+        manager.journal.record(Operation::Trash { items: trashed });
+    }
+    match (result, escalated) {
+        (Err(err), Some(paths)) => offer_escalation(manager, &err, Privileged::Delete { paths }),
+        (result, _) => outcome(result),
+    }
+}
+
+/// reverse the last recorded file operation and show its effect in both panels
+fn undo_last_operation(manager: &mut PanelManager) -> CommandOutcome {
+    let result = manager.journal.undo_last_operation();
+    let _ = manager.left.reload();
+    let _ = manager.right.reload();
+    match result {
+        Ok(Some(undone)) => CommandOutcome::Message(format!("undid the {undone}")),
+        Ok(None) => CommandOutcome::Message(String::from("nothing to undo")),
+        Err(err) => CommandOutcome::Message(format!("undo failed: {err}")),
+    }
+}
+
+/// show the trash in the active panel, or leave it for the home directory
+fn toggle_trash(manager: &mut PanelManager) -> CommandOutcome {
+    let panel = manager.active_mut();
+    if panel.vfs().scheme() == "trash" {
+        let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/"));
+        return outcome(panel.change_directory_in(vfs::local(), &home));
+    }
+    // the trash of a directory on another filesystem is at its top
+    let trash = match panel.vfs().local_path(&panel.current_path()) {
+        Some(dir) => Trash::for_directory(&dir),
+        None => Trash::home(),
+    };
+    let Some(trash) = trash else {
+        return CommandOutcome::Message(String::from("no home directory for the trash"));
+    };
+    outcome(panel.change_directory_in(Arc::new(TrashFs::new(trash)), Path::new("/")))
+}
+
+/// move the trashed targets of the active panel back to where they came from
+fn restore_targets(manager: &mut PanelManager) -> CommandOutcome {
+    let panel = manager.active_mut();
+    if panel.vfs().scheme() != "trash" {
+        return CommandOutcome::Message(String::from("only entries of the trash can be restored"));
+    }
+    // the files directory of the trash shown is its root
+    let Some(trash) = panel.vfs().local_path(Path::new("/")).as_deref().and_then(Path::parent).map(Trash::new) else {
+        return CommandOutcome::Done;
+    };
+    let mut restored = 0;
+    let mut result = Ok(());
+    for node in panel.targets() {
+        let path = node.read().path();
+        // entries inside trashed directories go back with their directory
+        let Some(name) = TrashFs::item_name(&path).filter(|_| path.parent() == Some(Path::new("/"))) else {
+            continue;
+        };
+        match trash.restore(&name) {
+            Ok(_) => restored += 1,
+            Err(err) => {
+                result = Err(err);
+                break;
+            }
+        }
+    }
+    panel.selection.clear();
+    let _ = panel.reload();
+    match result {
+        Ok(()) => CommandOutcome::Message(format!("{restored} entries restored")),
+        Err(err) => CommandOutcome::Message(err.to_string()),
+    }
+}
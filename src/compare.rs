@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::fs_node::{FsNodeRef, FsNodeType};
+use crate::panel::PanelManager;
+use crate::vfs::{VfsEntry, VfsRef};
+
+/// how two files of the same name are told apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareMode {
+    /// only files missing on one side differ
+    Name,
+    /// files differ when their size or modification time do
+    SizeTime,
+    /// files differ when their contents do, whatever their times
+    Content,
+}
+
+impl CompareMode {
+    /// mode from the first letter typed at the compare prompt
+    pub fn parse(input: &str) -> Option<CompareMode> {
+        match input.trim().chars().next()?.to_ascii_lowercase() {
+            'n' => Some(CompareMode::Name),
+            's' | 't' => Some(CompareMode::SizeTime),
+            'c' => Some(CompareMode::Content),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    LeftOnly,
+    RightOnly,
+    LeftNewer,
+    RightNewer,
+    /// contents or types differ with no side more recent
+    Different,
+}
+
+/// an entry that is not the same on both sides
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// path below the compared directories
+    pub relative: PathBuf,
+    pub kind: DiffKind,
+    pub left: Option<VfsEntry>,
+    pub right: Option<VfsEntry>,
+}
+
+/// compare the entries of two directory subtrees
+///
+/// Directories present on both sides are descended into when `recursive`
+/// is set and are otherwise ignored, like symlinks. A directory present on
+/// one side only is a single difference, its content is not listed.
+pub fn compare_nodes(left: &FsNodeRef, right: &FsNodeRef, mode: CompareMode, recursive: bool) -> io::Result<Vec<Difference>> {
+    let side = |node: &FsNodeRef| {
+        let node = node.read();
+        (Arc::clone(&node.vfs), node.path())
+    };
+    let (left_vfs, left_path) = side(left);
+    let (right_vfs, right_path) = side(right);
+    let mut differences = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let left_entries = by_name(left_vfs.read_dir(&left_path.join(&relative))?);
+        let mut right_entries = by_name(right_vfs.read_dir(&right_path.join(&relative))?);
+        for (name, left_entry) in left_entries {
+            let path = relative.join(&name);
+            let Some(right_entry) = right_entries.remove(&name) else {
+                differences.push(Difference {
+                    relative: path,
+                    kind: DiffKind::LeftOnly,
+                    left: Some(left_entry),
+                    right: None,
+                });
+                continue;
+            };
+            let kind = match (&left_entry.node_type, &right_entry.node_type) {
+                (FsNodeType::Directory, FsNodeType::Directory) => {
+                    if recursive {
+                        pending.push(path);
+                    }
+                    continue;
+                }
+                (FsNodeType::Symlink { .. }, FsNodeType::Symlink { .. }) => continue,
+                (FsNodeType::File, FsNodeType::File) => {
+                    match compare_files(&left_vfs, &left_entry, &right_vfs, &right_entry, mode)? {
+                        Some(kind) => kind,
+                        None => continue,
+                    }
+                }
+                _ => DiffKind::Different,
+            };
+            differences.push(Difference {
+                relative: path,
+                kind,
+                left: Some(left_entry),
+                right: Some(right_entry),
+            });
+        }
+        for (name, right_entry) in right_entries {
+            differences.push(Difference {
+                relative: relative.join(name),
+                kind: DiffKind::RightOnly,
+                left: None,
+                right: Some(right_entry),
+            });
+        }
+    }
+    differences.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(differences)
+}
+
+fn by_name(entries: Vec<VfsEntry>) -> BTreeMap<String, VfsEntry> {
+    entries.into_iter().map(|entry| (entry.name.clone(), entry)).collect()
+}
+
+/// how two files of the same name differ, `None` if they do not
+fn compare_files(
+    left_vfs: &VfsRef,
+    left: &VfsEntry,
+    right_vfs: &VfsRef,
+    right: &VfsEntry,
+    mode: CompareMode,
+) -> io::Result<Option<DiffKind>> {
+    let newer = || match (seconds(left.metadata.modified), seconds(right.metadata.modified)) {
+        (Some(left), Some(right)) if left > right => DiffKind::LeftNewer,
+        (Some(left), Some(right)) if left < right => DiffKind::RightNewer,
+        _ => DiffKind::Different,
+    };
+    let differs = match mode {
+        CompareMode::Name => false,
+        CompareMode::SizeTime => {
+            left.metadata.size != right.metadata.size
+                || seconds(left.metadata.modified) != seconds(right.metadata.modified)
+        }
+        CompareMode::Content => {
+            left.metadata.size != right.metadata.size || !same_content(left_vfs, &left.path, right_vfs, &right.path)?
+        }
+    };
+    Ok(differs.then(newer))
+}
+
+/// modification time in whole seconds, some backends keep nothing finer
+fn seconds(time: Option<SystemTime>) -> Option<u64> {
+    time.and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+}
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// compare two files byte for byte, stopping at the first difference
+fn same_content(left_vfs: &VfsRef, left: &Path, right_vfs: &VfsRef, right: &Path) -> io::Result<bool> {
+    let mut left = left_vfs.open_read(left)?;
+    let mut right = right_vfs.open_read(right)?;
+    let mut left_buffer = vec![0; BUFFER_SIZE];
+    let mut right_buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let read = read_full(&mut left, &mut left_buffer)?;
+        if read_full(&mut right, &mut right_buffer)? != read || left_buffer[..read] != right_buffer[..read] {
+            return Ok(false);
+        }
+        if read == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// fill as much of `buffer` as the reader allows, short only at the end
+fn read_full(reader: &mut dyn Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+/// mark the files of both panels that differ from the other side, returns how many were marked
+///
+/// Files missing on the other side are marked, and of two different files
+/// the more recent one, or both when neither is. Earlier marks are replaced.
+pub fn mark_differences(manager: &mut PanelManager, mode: CompareMode) -> io::Result<usize> {
+    let differences = compare_nodes(&manager.left.root, &manager.right.root, mode, false)?;
+    manager.left.selection.clear();
+    manager.right.selection.clear();
+    let mut marked = 0;
+    for difference in differences {
+        let (left, right) = match difference.kind {
+            DiffKind::LeftOnly | DiffKind::LeftNewer => (difference.left, None),
+            DiffKind::RightOnly | DiffKind::RightNewer => (None, difference.right),
+            DiffKind::Different => (difference.left, difference.right),
+        };
+        // only files are marked, as in mc
+        for (panel, entry) in [(&mut manager.left, left), (&mut manager.right, right)] {
+            if let Some(entry) = entry
+                && entry.node_type == FsNodeType::File
+            {
+                panel.selection.insert(entry.path);
+                marked += 1;
+            }
+        }
+    }
+    Ok(marked)
+}
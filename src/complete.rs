@@ -0,0 +1,186 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths::{common_prefix, complete_directory, complete_path};
+use crate::ui::prompt::LineInput;
+
+/// what an input offers to finish the typed text with
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Completion {
+    #[default]
+    None,
+    /// local paths, relative ones from this directory
+    Paths(PathBuf),
+    /// local directories, relative ones from `base` or one of `cdpath`
+    Directories { base: PathBuf, cdpath: Vec<PathBuf> },
+    /// a shell command run in this directory, programs of `$PATH` for its first word and paths after
+    CommandLine(PathBuf),
+}
+
+/// a way to finish the typed text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// the whole text of the input once picked
+    pub text: String,
+    /// what the popup shows of it
+    pub label: String,
+}
+
+/// ways to finish `text`, then the answers of `history` starting with it
+///
+/// A word starting with `$` is finished with the environment variables,
+/// the first word of a command line with the programs of `$PATH`, and the
+/// others with paths. A command line finishes its last word, the other
+/// inputs their whole text.
+pub fn candidates(completion: &Completion, text: &str, history: &[String]) -> Vec<Candidate> {
+    let (head, word) = match completion {
+        Completion::None => return Vec::new(),
+        Completion::CommandLine(_) => text.split_at(text.rfind(char::is_whitespace).map_or(0, |space| space + 1)),
+        _ => ("", text),
+    };
+    let words = match completion {
+        _ if word.starts_with('$') && !word.contains('/') => variables(word),
+        Completion::CommandLine(_) if head.trim().is_empty() && !word.is_empty() && !word.contains('/') => programs(word),
+        Completion::Paths(base) | Completion::CommandLine(base) => complete_path(word, base),
+        Completion::Directories { base, cdpath } => complete_directory(word, base, cdpath),
+        Completion::None => Vec::new(),
+    };
+    let mut candidates: Vec<Candidate> = words
+        .into_iter()
+        .map(|word| Candidate {
+            label: label(&word),
+            text: format!("{head}{word}"),
+        })
+        .collect();
+    // the latest answers first
+    for answer in history.iter().rev() {
+        if answer.starts_with(text) && answer != text && !candidates.iter().any(|candidate| candidate.text == *answer) {
+            candidates.push(Candidate {
+                text: answer.clone(),
+                label: answer.clone(),
+            });
+        }
+    }
+    candidates
+}
+
+/// the name a path ends with, a directory keeps its slash
+fn label(word: &str) -> String {
+    let name = word.trim_end_matches('/').rsplit('/').next().unwrap_or(word);
+    match word.ends_with('/') && !name.is_empty() {
+        true => format!("{name}/"),
+        false => name.to_string(),
+    }
+}
+
+/// environment variables for a word like `$HO` or `${HO`, written the same way
+fn variables(word: &str) -> Vec<String> {
+    let braced = word.starts_with("${");
+    let prefix = word.trim_start_matches('$').trim_start_matches('{');
+    let names: BTreeSet<String> = env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names
+        .into_iter()
+        .map(|name| match braced {
+            true => format!("${{{name}}}"),
+            false => format!("${name}"),
+        })
+        .collect()
+}
+
+/// programs of the directories of `$PATH` whose name starts with `prefix`
+fn programs(prefix: &str) -> Vec<String> {
+    let Some(path) = env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let mut names = BTreeSet::new();
+    for dir in env::split_paths(&path) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if let Ok(name) = entry.file_name().into_string()
+                && name.starts_with(prefix)
+                && is_program(&entry.path())
+            {
+                names.insert(name);
+            }
+        }
+    }
+    names.into_iter().collect()
+}
+
+#[cfg(unix)]
+fn is_program(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_program(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// the candidates of an input, opened by Tab and then cycled by Tab and Shift+Tab
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Completer {
+    pub candidates: Vec<Candidate>,
+    /// candidate put in the input, none while the text it opened on is shown
+    pub selected: Option<usize>,
+    /// text the popup opened on, Esc brings it back
+    typed: String,
+}
+
+impl Completer {
+    pub fn is_open(&self) -> bool {
+        !self.candidates.is_empty()
+    }
+
+    /// step to the next candidate of the open popup, or finish the text as far as every candidate agrees
+    ///
+    /// A single candidate is put in at once, several open the popup.
+    pub fn step(&mut self, completion: &Completion, history: &[String], input: &mut LineInput, forward: bool) {
+        if self.is_open() {
+            let count = self.candidates.len();
+            let next = match (self.selected, forward) {
+                (None, true) => 0,
+                (None, false) => count - 1,
+                (Some(index), true) => (index + 1) % count,
+                (Some(index), false) => (index + count - 1) % count,
+            };
+            self.selected = Some(next);
+            input.set_text(&self.candidates[next].text);
+            return;
+        }
+        let candidates = candidates(completion, &input.text, history);
+        let texts: Vec<String> = candidates.iter().map(|candidate| candidate.text.clone()).collect();
+        let shared = common_prefix(&texts);
+        if shared.len() > input.text.len() {
+            input.set_text(&shared);
+        }
+        if candidates.len() > 1 {
+            self.typed = input.text.clone();
+            self.candidates = candidates;
+            self.selected = None;
+        }
+    }
+
+    /// close the popup, the text stays as it is
+    pub fn close(&mut self) {
+        self.candidates.clear();
+        self.selected = None;
+    }
+
+    /// close the popup and put back the text it opened on
+    pub fn cancel(&mut self, input: &mut LineInput) {
+        if self.selected.is_some() {
+            input.set_text(&self.typed);
+        }
+        self.close();
+    }
+}
@@ -0,0 +1,251 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ops::{CopyOptions, ShredOptions};
+use crate::panel::{Column, Layout};
+use crate::format::{SizeUnits, DEFAULT_TIME_FORMAT};
+use crate::sort::SortMode;
+
+/// user settings, stored as toml
+///
+/// Missing keys take their default value so old files keep loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub panel: PanelConfig,
+    pub confirm: ConfirmConfig,
+    /// name of the color theme
+    pub theme: String,
+    /// command run to edit a file, the built-in editor when unset
+    pub editor: Option<String>,
+    /// command run to view a file, the built-in viewer when unset
+    pub viewer: Option<String>,
+    pub open: OpenConfig,
+    pub format: FormatConfig,
+    /// F8 moves local files to the trash instead of deleting them
+    pub delete_to_trash: bool,
+    /// attributes of the sources given to their copies
+    pub preserve: PreserveConfig,
+    /// re-hash copied files to check them
+    pub verify_copies: bool,
+    /// passes and pattern of the shred command, `random`, `zeros` or `ones`
+    pub shred: ShredOptions,
+    /// most KiB per second a copy or a move job transfers, as fast as it goes when unset
+    pub bandwidth_limit: Option<u64>,
+    /// Ctrl+O and the command line use a live shell, commands are captured otherwise
+    pub subshell: bool,
+    /// clicks, the wheel and drags act on the panels, the terminal's own selection needs shift then
+    pub mouse: bool,
+    /// directories the change directory dialog looks in for a name, before those of `$CDPATH`
+    pub cdpath: Vec<String>,
+    /// disk usage scans and searches leave out what the `.gitignore` files ignore
+    pub gitignore: bool,
+    /// program retrying refused operations as root, like `sudo` or `pkexec`
+    ///
+    /// The first of sudo, doas and pkexec on the PATH when unset, an empty
+    /// program never offers to.
+    pub escalation: Option<String>,
+    /// object storage of the `s3://` locations
+    pub s3: S3Config,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            panel: PanelConfig::default(),
+            confirm: ConfirmConfig::default(),
+            theme: String::from("classic"),
+            editor: None,
+            viewer: None,
+            open: OpenConfig::default(),
+            format: FormatConfig::default(),
+            delete_to_trash: true,
+            preserve: PreserveConfig::default(),
+            verify_copies: false,
+            shred: ShredOptions::default(),
+            bandwidth_limit: None,
+            subshell: true,
+            mouse: true,
+            cdpath: Vec::new(),
+            gitignore: false,
+            escalation: None,
+            s3: S3Config::default(),
+        }
+    }
+}
+
+/// defaults applied to both panels at startup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelConfig {
+    pub sort: SortMode,
+    pub show_hidden: bool,
+    /// columns of the full layout, from `name`, `size`, `human-size`, `modified`, `permissions`, `owner`, `link-target` and `path`
+    pub columns: Vec<Column>,
+    pub layout: Layout,
+    /// columns of names in the brief layout
+    pub brief_columns: usize,
+    /// Nerd Font icons in front of the names, the terminal font needs them
+    pub icons: bool,
+}
+
+impl Default for PanelConfig {
+    fn default() -> Self {
+        PanelConfig {
+            sort: SortMode::default(),
+            show_hidden: true,
+            columns: vec![Column::Name, Column::Size, Column::Modified],
+            layout: Layout::Full,
+            brief_columns: 2,
+            icons: false,
+        }
+    }
+}
+
+/// how sizes and times are written in the listing and the dialogs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    /// `binary` for steps of 1024 bytes, `si` for steps of 1000
+    pub units: SizeUnits,
+    /// strftime-like format of the times, with `%Y %y %m %b %d %e %H %M %S`
+    pub time_format: String,
+    /// the listing writes the times of the last month like `3 h ago`
+    pub relative_times: bool,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        FormatConfig {
+            units: SizeUnits::default(),
+            time_format: String::from(DEFAULT_TIME_FORMAT),
+            relative_times: false,
+        }
+    }
+}
+
+/// how files are handed to the default application of the system
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenConfig {
+    /// program given the file, `xdg-open`, `open` or `explorer` when unset
+    pub command: Option<String>,
+    /// give the terminal to the program, for openers running console programs
+    pub terminal: bool,
+    /// wait for the program to exit and reread the panel, in case it changed the file
+    pub wait: bool,
+}
+
+/// access to S3 compatible object storage, opened as `s3://bucket/prefix`
+///
+/// What is unset comes from the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`,
+/// `AWS_SESSION_TOKEN`, `AWS_REGION` and `AWS_ENDPOINT_URL` variables.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct S3Config {
+    /// like `http://localhost:9000` for a MinIO server, Amazon S3 when unset
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    /// address buckets as `endpoint/bucket` rather than `bucket.endpoint`, the default with an endpoint set
+    pub path_style: Option<bool>,
+}
+
+/// which operations ask before doing anything
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfirmConfig {
+    pub delete: bool,
+    pub overwrite: bool,
+    pub exit: bool,
+}
+
+impl Default for ConfirmConfig {
+    fn default() -> Self {
+        ConfirmConfig {
+            delete: true,
+            overwrite: true,
+            exit: false,
+        }
+    }
+}
+
+/// attributes copies carry over, ownership only when running as root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreserveConfig {
+    pub permissions: bool,
+    pub mtime: bool,
+    pub atime: bool,
+    pub xattrs: bool,
+    pub acls: bool,
+    pub owner: bool,
+    pub hardlinks: bool,
+}
+
+impl Default for PreserveConfig {
+    fn default() -> Self {
+        let options = CopyOptions::default();
+        PreserveConfig::from_options(&options)
+    }
+}
+
+impl PreserveConfig {
+    pub fn from_options(options: &CopyOptions) -> PreserveConfig {
+        PreserveConfig {
+            permissions: options.preserve_permissions,
+            mtime: options.preserve_mtime,
+            atime: options.preserve_atime,
+            xattrs: options.preserve_xattrs,
+            acls: options.preserve_acls,
+            owner: options.preserve_owner,
+            hardlinks: options.preserve_hardlinks,
+        }
+    }
+
+    pub fn apply(&self, options: &mut CopyOptions) {
+        options.preserve_permissions = self.permissions;
+        options.preserve_mtime = self.mtime;
+        options.preserve_atime = self.atime;
+        options.preserve_xattrs = self.xattrs;
+        options.preserve_acls = self.acls;
+        options.preserve_owner = self.owner;
+        options.preserve_hardlinks = self.hardlinks;
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/midday-commander/config.toml`, falling back to `~/.config`
+    pub fn default_path() -> Option<PathBuf> {
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("midday-commander").join("config.toml"))
+    }
+
+    /// read a configuration file, a missing file gives the defaults
+    pub fn load(path: &Path) -> io::Result<Config> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(err),
+        };
+        toml::from_str(&text).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err.message()))
+        })
+    }
+
+    /// write the configuration, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+}
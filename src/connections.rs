@@ -0,0 +1,119 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, S3Config};
+use crate::keyring;
+use crate::vfs::{self, S3Fs, S3Options, SftpFs, SftpOptions, SftpUrl, VfsRef};
+
+/// schemes of the remote backends a connection can be saved for
+pub const SCHEMES: [&str; 2] = ["sftp://", "s3://"];
+
+/// a remote endpoint saved under a name
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Connection {
+    pub name: String,
+    /// like `sftp://user@host:port/dir` or `s3://bucket/prefix`, a password is never kept here
+    pub url: String,
+    /// the password, or the secret key for S3, is in the keyring of the system under the name
+    #[serde(default)]
+    pub keyring: bool,
+    /// connect to an sftp host missing from `~/.ssh/known_hosts`
+    #[serde(default)]
+    pub accept_unknown_host: bool,
+    /// S3 settings of the connection, the AWS environment variables fill in the others
+    ///
+    /// A connection without any uses the s3 section of the config file.
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+}
+
+impl Connection {
+    /// connect, with `password` or the one of the keyring
+    pub fn open(&self, password: Option<&str>) -> io::Result<(VfsRef, PathBuf)> {
+        let secret = match password {
+            Some(password) => Some(password.to_string()),
+            None if self.keyring => keyring::lookup(&self.name)?,
+            None => None,
+        };
+        if self.url.starts_with("sftp://") {
+            let mut url = SftpUrl::parse(&self.url)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid url {}", self.url)))?;
+            url.password = secret.or(url.password);
+            let options = SftpOptions {
+                accept_unknown_host: self.accept_unknown_host,
+                ..SftpOptions::default()
+            };
+            let sftp = SftpFs::connect(&url, &options)?.named(&self.name);
+            return Ok((Arc::new(sftp), url.path));
+        }
+        if let Some(path) = self.url.strip_prefix("s3://") {
+            let own = self.endpoint.is_some() || self.region.is_some() || self.access_key_id.is_some();
+            let mut options = match own {
+                true => S3Options::from_config(&S3Config {
+                    endpoint: self.endpoint.clone(),
+                    region: self.region.clone(),
+                    access_key_id: self.access_key_id.clone(),
+                    ..S3Config::default()
+                }),
+                false => vfs::s3_fs::options().clone(),
+            };
+            if secret.is_some() {
+                options.secret_access_key = secret;
+            }
+            let s3 = S3Fs::connect(options).named(&self.name);
+            return Ok((Arc::new(s3), Path::new("/").join(path)));
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not an sftp or s3 url", self.url),
+        ))
+    }
+
+    /// true for urls of the backends connections are saved for
+    pub fn is_remote_url(url: &str) -> bool {
+        SCHEMES.iter().any(|scheme| url.starts_with(scheme))
+    }
+}
+
+/// the saved connections, stored as toml next to the configuration
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Connections {
+    #[serde(default, rename = "connection")]
+    pub connections: Vec<Connection>,
+}
+
+impl Connections {
+    /// `connections.toml` in the directory of the default configuration
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::default_path()?.with_file_name("connections.toml"))
+    }
+
+    /// read the connections, a missing file gives none
+    pub fn load(path: &Path) -> io::Result<Connections> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Connections::default()),
+            Err(err) => return Err(err),
+        };
+        toml::from_str(&text).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err.message()))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.connections.iter().position(|connection| connection.name == name)
+    }
+}
@@ -0,0 +1,160 @@
+use std::time::SystemTime;
+
+use glob::{MatchOptions, Pattern};
+
+use crate::format::{parse_size, parse_time};
+use crate::fs_node::{FsNode, FsNodeType};
+use crate::ops::chown::system_users;
+
+/// the entries a selection by criteria looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntryKind {
+    #[default]
+    Files,
+    Directories,
+    Symlinks,
+    Any,
+}
+
+impl EntryKind {
+    pub const ALL: [EntryKind; 4] = [EntryKind::Files, EntryKind::Directories, EntryKind::Symlinks, EntryKind::Any];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EntryKind::Files => "Files",
+            EntryKind::Directories => "Directories",
+            EntryKind::Symlinks => "Symlinks",
+            EntryKind::Any => "Files, directories and symlinks",
+        }
+    }
+
+    fn matches(self, node: &FsNode) -> bool {
+        match self {
+            EntryKind::Files => node.node_type == FsNodeType::File,
+            EntryKind::Directories => node.node_type == FsNodeType::Directory,
+            EntryKind::Symlinks => node.is_symlink(),
+            EntryKind::Any => true,
+        }
+    }
+}
+
+/// what the entries selected by criteria have, every criterion set must hold
+#[derive(Debug, Clone, Default)]
+pub struct SelectCriteria {
+    /// glob patterns of the names, one of them must match
+    pub patterns: Vec<Pattern>,
+    /// smallest size allowed, in bytes
+    pub min_size: Option<u64>,
+    /// largest size allowed, in bytes
+    pub max_size: Option<u64>,
+    pub modified_before: Option<SystemTime>,
+    pub modified_after: Option<SystemTime>,
+    /// user id of the owner
+    pub owner: Option<u32>,
+    pub kind: EntryKind,
+}
+
+/// the text of each field of the select by criteria dialog
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CriteriaText<'a> {
+    pub patterns: &'a str,
+    pub size: &'a str,
+    pub modified_before: &'a str,
+    pub modified_after: &'a str,
+    pub owner: &'a str,
+}
+
+impl SelectCriteria {
+    /// the criteria of the dialog fields, an empty field sets none
+    ///
+    /// The patterns are separated by spaces. A size is `>1G`, `<100K`,
+    /// `10M-1G` or a size taken as it is. The times are dates or ages, see
+    /// `format::parse_time`. The owner is a user name or id.
+    pub fn parse(text: &CriteriaText, kind: EntryKind, now: SystemTime) -> Result<SelectCriteria, String> {
+        let patterns = text
+            .patterns
+            .split_whitespace()
+            .map(|pattern| Pattern::new(pattern).map_err(|err| format!("invalid pattern {pattern}: {err}")))
+            .collect::<Result<_, _>>()?;
+        let (min_size, max_size) = parse_size_range(text.size)?;
+        let time = |text: &str| match text.trim() {
+            "" => Ok(None),
+            text => parse_time(text, now).map(Some).ok_or_else(|| format!("{text} is not a date like 2024-05-31 or an age like 90d")),
+        };
+        Ok(SelectCriteria {
+            patterns,
+            min_size,
+            max_size,
+            modified_before: time(text.modified_before)?,
+            modified_after: time(text.modified_after)?,
+            owner: parse_owner(text.owner)?,
+            kind,
+        })
+    }
+
+    /// true if `node` meets every criterion, an unknown size or time meets none
+    pub fn matches(&self, node: &FsNode, case_sensitive: bool) -> bool {
+        let options = MatchOptions {
+            case_sensitive,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        let sized = self.min_size.is_some() || self.max_size.is_some();
+        let dated = self.modified_before.is_some() || self.modified_after.is_some();
+        let modified = node.metadata.as_ref().and_then(|metadata| metadata.modified);
+        self.kind.matches(node)
+            && (self.patterns.is_empty() || self.patterns.iter().any(|pattern| pattern.matches_with(&node.name, options)))
+            && (!sized
+                || node.size().is_some_and(|size| {
+                    self.min_size.is_none_or(|min| size >= min) && self.max_size.is_none_or(|max| size <= max)
+                }))
+            && (!dated
+                || modified.is_some_and(|modified| {
+                    self.modified_before.is_none_or(|before| modified < before)
+                        && self.modified_after.is_none_or(|after| modified > after)
+                }))
+            && self.owner.is_none_or(|owner| node.metadata.as_ref().is_some_and(|metadata| metadata.uid == owner))
+    }
+}
+
+/// the smallest and largest sizes of a range like `>1G`, `<100K` or `10M-1G`
+fn parse_size_range(text: &str) -> Result<(Option<u64>, Option<u64>), String> {
+    match text.trim() {
+        "" => Ok((None, None)),
+        text => size_range(text).ok_or_else(|| format!("{text} is not a size like >1G, <100K or 10M-1G")),
+    }
+}
+
+fn size_range(text: &str) -> Option<(Option<u64>, Option<u64>)> {
+    // an empty file is a size too, the size parser wants at least a byte
+    let size = |text: &str| match text.trim() {
+        "0" => Some(0),
+        text => parse_size(text),
+    };
+    if let Some(min) = text.strip_prefix(">=") {
+        Some((Some(size(min)?), None))
+    } else if let Some(min) = text.strip_prefix('>') {
+        Some((Some(size(min)?.checked_add(1)?), None))
+    } else if let Some(max) = text.strip_prefix("<=") {
+        Some((None, Some(size(max)?)))
+    } else if let Some(max) = text.strip_prefix('<') {
+        Some((None, Some(size(max)?.checked_sub(1)?)))
+    } else if let Some((min, max)) = text.split_once('-') {
+        Some((Some(size(min)?), Some(size(max)?)))
+    } else {
+        let size = size(text)?;
+        Some((Some(size), Some(size)))
+    }
+}
+
+/// the user id of a name or of a number
+fn parse_owner(text: &str) -> Result<Option<u32>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    match system_users().into_iter().find(|user| user.name == text) {
+        Some(user) => Ok(Some(user.id)),
+        None => text.parse().map(Some).map_err(|_| format!("no user is named {text}")),
+    }
+}
@@ -0,0 +1,607 @@
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::commands::PromptKind;
+use crate::highlight::{self, Highlighter};
+use crate::ui::prompt::Prompt;
+use crate::vfs::VfsRef;
+
+pub const TAB_WIDTH: usize = 8;
+
+/// position in the text, `column` counts characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Position {
+    pub row: usize,
+    pub column: usize,
+}
+
+/// one undoable change, what was inserted or removed at `start`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Change {
+    Inserted { start: Position, text: String },
+    Removed { start: Position, text: String },
+}
+
+/// changes undone and redone together, with the cursor from before them
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Edit {
+    changes: Vec<Change>,
+    cursor: Position,
+}
+
+/// what a key did to the editor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorAction {
+    None,
+    Close,
+    Prompt(Prompt),
+    Message(String),
+}
+
+/// input the editor asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorPrompt {
+    Search,
+    ReplaceFind,
+    ReplaceWith,
+}
+
+/// small text editor working on a file of any backend
+pub struct Editor {
+    pub path: PathBuf,
+    pub title: String,
+    vfs: VfsRef,
+    pub lines: Vec<String>,
+    pub cursor: Position,
+    /// first displayed row and column
+    pub top: usize,
+    pub left: usize,
+    /// rows of text of the last render, used for paging
+    pub height: usize,
+    pub width: usize,
+    pub dirty: bool,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    crlf: bool,
+    final_newline: bool,
+    pub search: Option<String>,
+    replace: Option<String>,
+    /// the next close discards unsaved changes
+    confirm_close: bool,
+    pub highlighter: Option<Box<dyn Highlighter>>,
+}
+
+impl std::fmt::Debug for Editor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Editor")
+            .field("path", &self.path)
+            .field("cursor", &self.cursor)
+            .field("dirty", &self.dirty)
+            .finish()
+    }
+}
+
+impl Editor {
+    /// open a text file, files that are not utf-8 are refused
+    pub fn open(vfs: &VfsRef, path: &Path) -> io::Result<Editor> {
+        let mut data = Vec::new();
+        vfs.open_read(path)?.read_to_end(&mut data)?;
+        let text = String::from_utf8(data)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not a utf-8 text file"))?;
+        let crlf = text.contains("\r\n");
+        let final_newline = text.ends_with('\n');
+        let body = text.strip_suffix('\n').unwrap_or(&text);
+        let lines = body
+            .split('\n')
+            .map(|line| if crlf { line.strip_suffix('\r').unwrap_or(line) } else { line }.to_string())
+            .collect();
+        Ok(Editor {
+            path: path.to_path_buf(),
+            title: vfs.display_path(path),
+            vfs: VfsRef::clone(vfs),
+            lines,
+            cursor: Position::default(),
+            top: 0,
+            left: 0,
+            height: 24,
+            width: 80,
+            dirty: false,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            crlf,
+            final_newline,
+            search: None,
+            replace: None,
+            confirm_close: false,
+            highlighter: None,
+        })
+    }
+
+    /// color the text by language if the file type is known
+    pub fn enable_highlighting(&mut self, syntax_theme: &str) {
+        let first_line = self.lines.first().map(String::as_str).unwrap_or_default();
+        self.highlighter = highlight::for_file(&self.path, first_line, syntax_theme);
+    }
+
+    /// path of the edited file on the local filesystem, for external editors
+    pub fn local_path(&self) -> Option<PathBuf> {
+        self.vfs.local_path(&self.path)
+    }
+
+    /// the whole text as it will be saved
+    pub fn text(&self) -> String {
+        let newline = if self.crlf { "\r\n" } else { "\n" };
+        let mut text = self.lines.join(newline);
+        if self.final_newline {
+            text.push_str(newline);
+        }
+        text
+    }
+
+    /// write to a temporary file next to the original and rename it over
+    pub fn save(&mut self) -> io::Result<()> {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let temporary = self.path.with_file_name(format!(".{name}.mdc-save"));
+        let mode = self.vfs.metadata(&self.path).ok().map(|entry| entry.metadata.permissions);
+        let result = (|| {
+            let mut file = self.vfs.open_write(&temporary)?;
+            file.write_all(self.text().as_bytes())?;
+            file.flush()?;
+            drop(file);
+            if let Some(mode) = mode {
+                match self.vfs.set_permissions(&temporary, mode & 0o7777) {
+                    Err(err) if err.kind() != io::ErrorKind::Unsupported => return Err(err),
+                    _ => {}
+                }
+            }
+            self.vfs.rename(&temporary, &self.path)
+        })();
+        if result.is_err() {
+            let _ = self.vfs.remove(&temporary);
+        }
+        result?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> EditorAction {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let closing = matches!(key.code, KeyCode::Esc | KeyCode::F(10));
+        if !closing {
+            self.confirm_close = false;
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::F(10) => {
+                if self.dirty && !self.confirm_close {
+                    self.confirm_close = true;
+                    return EditorAction::Message(String::from(
+                        "unsaved changes, F2 saves them and Esc again discards them",
+                    ));
+                }
+                return EditorAction::Close;
+            }
+            KeyCode::F(2) => return self.save_action(),
+            KeyCode::Char('s') if control => return self.save_action(),
+            KeyCode::F(7) => return self.search_prompt(EditorPrompt::Search),
+            KeyCode::Char('f') if control => return self.search_prompt(EditorPrompt::Search),
+            KeyCode::F(4) => return self.search_prompt(EditorPrompt::ReplaceFind),
+            KeyCode::Char('r') if control => return self.search_prompt(EditorPrompt::ReplaceFind),
+            // shift+F7 as most terminals report it
+            KeyCode::F(17) => return self.search_next(),
+            KeyCode::Char('n') if control => return self.search_next(),
+            KeyCode::Char('z') if control => self.undo(),
+            KeyCode::Char('y') if control => self.redo(),
+            KeyCode::Up => self.move_to_row(self.cursor.row.saturating_sub(1)),
+            KeyCode::Down => self.move_to_row(self.cursor.row + 1),
+            KeyCode::PageUp => self.move_to_row(self.cursor.row.saturating_sub(self.height.max(1))),
+            KeyCode::PageDown => self.move_to_row(self.cursor.row + self.height.max(1)),
+            KeyCode::Home if control => self.cursor = Position::default(),
+            KeyCode::End if control => {
+                let row = self.lines.len() - 1;
+                self.cursor = Position {
+                    row,
+                    column: self.line_len(row),
+                };
+            }
+            KeyCode::Home => self.cursor.column = 0,
+            KeyCode::End => self.cursor.column = self.line_len(self.cursor.row),
+            KeyCode::Left => self.cursor = self.before(self.cursor),
+            KeyCode::Right => self.cursor = self.after(self.cursor),
+            KeyCode::Enter => self.type_text("\n"),
+            KeyCode::Tab => self.type_text("\t"),
+            KeyCode::Backspace if self.cursor != Position::default() => {
+                let start = self.before(self.cursor);
+                self.delete(start, self.cursor);
+            }
+            KeyCode::Delete if self.after(self.cursor) != self.cursor => {
+                let end = self.after(self.cursor);
+                self.delete(self.cursor, end);
+            }
+            KeyCode::Char(c) if !control => self.type_text(&c.to_string()),
+            _ => {}
+        }
+        EditorAction::None
+    }
+
+    fn save_action(&mut self) -> EditorAction {
+        match self.save() {
+            Ok(()) => EditorAction::Message(format!("saved {}", self.title)),
+            Err(err) => EditorAction::Message(format!("cannot save {}: {err}", self.title)),
+        }
+    }
+
+    fn search_prompt(&self, prompt: EditorPrompt) -> EditorAction {
+        let title = if prompt == EditorPrompt::Search { "Search" } else { "Replace" };
+        let previous = self.search.clone().unwrap_or_default();
+        EditorAction::Prompt(Prompt::new(PromptKind::Editor(prompt), title, &previous))
+    }
+
+    /// finish a search or a replace
+    pub fn submit(&mut self, prompt: EditorPrompt, input: &str) -> EditorAction {
+        match prompt {
+            EditorPrompt::Search => {
+                if input.is_empty() {
+                    return EditorAction::None;
+                }
+                self.search = Some(input.to_string());
+                self.search_next()
+            }
+            EditorPrompt::ReplaceFind => {
+                if input.is_empty() {
+                    return EditorAction::None;
+                }
+                self.search = Some(input.to_string());
+                let previous = self.replace.clone().unwrap_or_default();
+                EditorAction::Prompt(Prompt::new(
+                    PromptKind::Editor(EditorPrompt::ReplaceWith),
+                    &format!("Replace {input} with"),
+                    &previous,
+                ))
+            }
+            EditorPrompt::ReplaceWith => {
+                self.replace = Some(input.to_string());
+                let Some(needle) = self.search.clone() else {
+                    return EditorAction::None;
+                };
+                let count = self.replace_all(&needle, input);
+                EditorAction::Message(format!("{count} replaced"))
+            }
+        }
+    }
+
+    /// move the cursor to the next occurrence of the search, wrapping around
+    fn search_next(&mut self) -> EditorAction {
+        let Some(needle) = self.search.clone() else {
+            return EditorAction::None;
+        };
+        let from = self.after(self.cursor);
+        match self.find(&needle, from).or_else(|| self.find(&needle, Position::default())) {
+            Some(found) => {
+                self.cursor = found;
+                EditorAction::None
+            }
+            None => EditorAction::Message(format!("\"{needle}\" not found")),
+        }
+    }
+
+    /// first occurrence of a single-line string at or after `from`
+    pub fn find(&self, needle: &str, from: Position) -> Option<Position> {
+        for row in from.row..self.lines.len() {
+            let line = &self.lines[row];
+            let skip = if row == from.row { byte_offset(line, from.column) } else { 0 };
+            if let Some(index) = line[skip..].find(needle) {
+                return Some(Position {
+                    row,
+                    column: line[..skip + index].chars().count(),
+                });
+            }
+        }
+        None
+    }
+
+    /// replace every occurrence, all of it undone at once
+    pub fn replace_all(&mut self, needle: &str, replacement: &str) -> usize {
+        if needle.is_empty() {
+            return 0;
+        }
+        let count: usize = self.lines.iter().map(|line| line.matches(needle).count()).sum();
+        if count == 0 {
+            return 0;
+        }
+        let before = self.text_between(Position::default(), self.end());
+        let after: Vec<String> = self.lines.iter().map(|line| line.replace(needle, replacement)).collect();
+        let after = after.join("\n");
+        let cursor = self.cursor;
+        self.lines = vec![String::new()];
+        self.insert(Position::default(), &after);
+        // recorded as removing the old text and inserting the new one
+        self.push_edit(Edit {
+            changes: vec![
+                Change::Removed {
+                    start: Position::default(),
+                    text: before,
+                },
+                Change::Inserted {
+                    start: Position::default(),
+                    text: after,
+                },
+            ],
+            cursor,
+        });
+        self.cursor = self.clamp(cursor);
+        count
+    }
+
+    fn end(&self) -> Position {
+        let row = self.lines.len() - 1;
+        Position {
+            row,
+            column: self.line_len(row),
+        }
+    }
+
+    pub fn line_len(&self, row: usize) -> usize {
+        self.lines.get(row).map(|line| line.chars().count()).unwrap_or(0)
+    }
+
+    fn clamp(&self, position: Position) -> Position {
+        let row = position.row.min(self.lines.len() - 1);
+        Position {
+            row,
+            column: position.column.min(self.line_len(row)),
+        }
+    }
+
+    fn move_to_row(&mut self, row: usize) {
+        self.cursor = self.clamp(Position {
+            row,
+            column: self.cursor.column,
+        });
+    }
+
+    fn before(&self, position: Position) -> Position {
+        if position.column > 0 {
+            Position {
+                column: position.column - 1,
+                ..position
+            }
+        } else if position.row > 0 {
+            Position {
+                row: position.row - 1,
+                column: self.line_len(position.row - 1),
+            }
+        } else {
+            position
+        }
+    }
+
+    fn after(&self, position: Position) -> Position {
+        if position.column < self.line_len(position.row) {
+            Position {
+                column: position.column + 1,
+                ..position
+            }
+        } else if position.row + 1 < self.lines.len() {
+            Position {
+                row: position.row + 1,
+                column: 0,
+            }
+        } else {
+            position
+        }
+    }
+
+    /// insert typed text at the cursor, typing runs are undone together
+    fn type_text(&mut self, text: &str) {
+        let start = self.cursor;
+        let end = self.insert(start, text);
+        let merged = match self.undo.last_mut().map(|edit| edit.changes.as_mut_slice()) {
+            Some([Change::Inserted { start: previous, text: typed }])
+                if !text.contains('\n') && !typed.contains('\n') && advance(*previous, typed) == start =>
+            {
+                typed.push_str(text);
+                true
+            }
+            _ => false,
+        };
+        if merged {
+            self.redo.clear();
+            self.dirty = true;
+        } else {
+            self.push_edit(Edit {
+                changes: vec![Change::Inserted {
+                    start,
+                    text: text.to_string(),
+                }],
+                cursor: start,
+            });
+        }
+        self.cursor = end;
+    }
+
+    fn delete(&mut self, start: Position, end: Position) {
+        let cursor = self.cursor;
+        let text = self.remove(start, end);
+        self.push_edit(Edit {
+            changes: vec![Change::Removed { start, text }],
+            cursor,
+        });
+        self.cursor = start;
+    }
+
+    fn push_edit(&mut self, edit: Edit) {
+        self.undo.push(edit);
+        self.redo.clear();
+        self.dirty = true;
+    }
+
+    pub fn undo(&mut self) {
+        let Some(edit) = self.undo.pop() else {
+            return;
+        };
+        for change in edit.changes.iter().rev() {
+            match change {
+                Change::Inserted { start, text } => {
+                    self.remove(*start, advance(*start, text));
+                }
+                Change::Removed { start, text } => {
+                    self.insert(*start, text);
+                }
+            }
+        }
+        self.cursor = self.clamp(edit.cursor);
+        self.redo.push(edit);
+        self.dirty = true;
+    }
+
+    pub fn redo(&mut self) {
+        let Some(edit) = self.redo.pop() else {
+            return;
+        };
+        let mut cursor = edit.cursor;
+        for change in &edit.changes {
+            cursor = match change {
+                Change::Inserted { start, text } => self.insert(*start, text),
+                Change::Removed { start, text } => {
+                    self.remove(*start, advance(*start, text));
+                    *start
+                }
+            };
+        }
+        self.cursor = self.clamp(cursor);
+        self.undo.push(edit);
+        self.dirty = true;
+    }
+
+    /// insert text, returns the position after it
+    fn insert(&mut self, at: Position, text: &str) -> Position {
+        if let Some(highlighter) = self.highlighter.as_mut() {
+            highlighter.invalidate(at.row);
+        }
+        let line = &mut self.lines[at.row];
+        let offset = byte_offset(line, at.column);
+        let tail = line.split_off(offset);
+        let mut parts = text.split('\n');
+        line.push_str(parts.next().unwrap_or_default());
+        let mut row = at.row;
+        for part in parts {
+            row += 1;
+            self.lines.insert(row, part.to_string());
+        }
+        let column = self.line_len(row);
+        self.lines[row].push_str(&tail);
+        Position { row, column }
+    }
+
+    /// remove the text between two positions and return it
+    fn remove(&mut self, start: Position, end: Position) -> String {
+        if let Some(highlighter) = self.highlighter.as_mut() {
+            highlighter.invalidate(start.row);
+        }
+        let removed = self.text_between(start, end);
+        let end_line = self.lines[end.row].clone();
+        let tail = &end_line[byte_offset(&end_line, end.column)..];
+        let line = &mut self.lines[start.row];
+        line.truncate(byte_offset(line, start.column));
+        line.push_str(tail);
+        self.lines.drain(start.row + 1..=end.row);
+        removed
+    }
+
+    fn text_between(&self, start: Position, end: Position) -> String {
+        if start.row == end.row {
+            let line = &self.lines[start.row];
+            return line[byte_offset(line, start.column)..byte_offset(line, end.column)].to_string();
+        }
+        let first = &self.lines[start.row];
+        let last = &self.lines[end.row];
+        let mut parts = vec![&first[byte_offset(first, start.column)..]];
+        parts.extend(self.lines[start.row + 1..end.row].iter().map(String::as_str));
+        parts.push(&last[..byte_offset(last, end.column)]);
+        parts.join("\n")
+    }
+
+    /// screen column of the cursor, tabs expanded
+    pub fn cursor_column(&self) -> usize {
+        display_column(&self.lines[self.cursor.row], self.cursor.column)
+    }
+
+    /// keep the cursor on screen
+    pub fn scroll_to_cursor(&mut self) {
+        let height = self.height.max(1);
+        if self.cursor.row < self.top {
+            self.top = self.cursor.row;
+        } else if self.cursor.row >= self.top + height {
+            self.top = self.cursor.row + 1 - height;
+        }
+        let column = self.cursor_column();
+        let width = self.width.max(1);
+        if column < self.left {
+            self.left = column;
+        } else if column >= self.left + width {
+            self.left = column + 1 - width;
+        }
+    }
+}
+
+/// a line with tabs expanded to spaces
+pub fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - expanded.chars().count() % TAB_WIDTH;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
+
+fn display_column(line: &str, column: usize) -> usize {
+    line.chars().take(column).fold(0, |width, c| {
+        if c == '\t' { width + TAB_WIDTH - width % TAB_WIDTH } else { width + 1 }
+    })
+}
+
+fn byte_offset(line: &str, column: usize) -> usize {
+    line.char_indices().nth(column).map(|(offset, _)| offset).unwrap_or(line.len())
+}
+
+/// position reached after inserting `text` at `start`
+fn advance(start: Position, text: &str) -> Position {
+    match text.rsplit_once('\n') {
+        Some((_, last)) => Position {
+            row: start.row + text.matches('\n').count(),
+            column: last.chars().count(),
+        },
+        None => Position {
+            row: start.row,
+            column: start.column + text.chars().count(),
+        },
+    }
+}
+
+/// expand `$NAME` words of an external command
+pub fn expand_command(command: &str) -> String {
+    command
+        .split_whitespace()
+        .map(|word| match word.strip_prefix('$') {
+            Some(name) => std::env::var(name).unwrap_or_default(),
+            None => word.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// the external editor command, `$EDITOR` style values are expanded
+pub fn external_editor(configured: Option<&str>) -> Option<String> {
+    let command = expand_command(configured?);
+    (!command.trim().is_empty()).then_some(command)
+}
@@ -0,0 +1,154 @@
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::export::ExportNode;
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::FsNodeType;
+use crate::ops::ConflictResolution;
+use crate::shell::shell_quote;
+use crate::vfs::VfsEntry;
+
+/// programs looked for on the PATH when none is configured, in this order
+const KNOWN_PROGRAMS: [&str; 3] = ["sudo", "doas", "pkexec"];
+
+/// the program running refused operations again with the rights of root
+///
+/// It is given the terminal, so it can ask for a password the way it
+/// usually does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Escalation {
+    pub program: String,
+}
+
+impl Escalation {
+    /// the configured program, or the first known one on the PATH when unset
+    ///
+    /// An empty program turns the escalation off.
+    pub fn new(configured: Option<&str>) -> Option<Escalation> {
+        let program = match configured {
+            Some(program) => program.trim().to_string(),
+            None => KNOWN_PROGRAMS.into_iter().find(|program| on_path(program))?.to_string(),
+        };
+        (!program.is_empty()).then_some(Escalation { program })
+    }
+
+    /// the shell command line doing `operation` as root
+    pub fn command_line(&self, operation: &Privileged) -> io::Result<String> {
+        let all = |paths: &[PathBuf]| paths.iter().map(|path| quote(path)).collect::<Vec<_>>().join(" ");
+        let words = match operation {
+            Privileged::Copy { sources, dest, resolution } => format!("cp -a{} -- {} {}", existing_flag(*resolution), all(sources), quote(dest)),
+            Privileged::Move { sources, dest, resolution } => format!("mv{} -- {} {}", existing_flag(*resolution), all(sources), quote(dest)),
+            Privileged::Delete { paths } => format!("rm -rf -- {}", all(paths)),
+            Privileged::Chmod { paths, mode } => format!("chmod {:04o} -- {}", mode & 0o7777, all(paths)),
+            // the listing is read by this program, its export is read back
+            Privileged::List { dir } => format!("{} export --shallow {}", quote(&env::current_exe()?), quote(dir)),
+        };
+        Ok(format!("{} {words}", shell_quote(&self.program)))
+    }
+}
+
+/// an operation refused for lack of rights, as it can be done again as root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Privileged {
+    /// copy the sources into the directory `dest`, existing targets dealt with as `resolution` says
+    Copy { sources: Vec<PathBuf>, dest: PathBuf, resolution: Option<ConflictResolution> },
+    Move { sources: Vec<PathBuf>, dest: PathBuf, resolution: Option<ConflictResolution> },
+    Delete { paths: Vec<PathBuf> },
+    /// give the permission bits `mode` to the paths
+    Chmod { paths: Vec<PathBuf>, mode: u32 },
+    /// read a directory the user cannot list, for the panel to show it
+    List { dir: PathBuf },
+}
+
+impl Privileged {
+    /// the question asked before running the operation as root
+    pub fn question(&self) -> String {
+        let count = |paths: &[PathBuf]| match paths {
+            [path] => path.display().to_string(),
+            paths => format!("{} entries", paths.len()),
+        };
+        let what = match self {
+            Privileged::Copy { sources, dest, resolution } => {
+                format!("copy {} to {}{}", count(sources), dest.display(), existing_note(*resolution))
+            }
+            Privileged::Move { sources, dest, resolution } => {
+                format!("move {} to {}{}", count(sources), dest.display(), existing_note(*resolution))
+            }
+            Privileged::Delete { paths } => format!("delete {}", count(paths)),
+            Privileged::Chmod { paths, .. } => format!("change the permissions of {}", count(paths)),
+            Privileged::List { dir } => format!("read {}", dir.display()),
+        };
+        format!("Retry as root to {what}?")
+    }
+}
+
+/// the entries of a directory listed by `export --shallow`, as the local backend would give them
+pub fn parse_listing(output: &[u8]) -> io::Result<Vec<VfsEntry>> {
+    let node: ExportNode = serde_json::from_slice(output).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(node.children.into_iter().map(entry_of).collect())
+}
+
+fn entry_of(node: ExportNode) -> VfsEntry {
+    let (node_type, type_bits) = match (node.kind.as_str(), node.target) {
+        ("directory", _) => (FsNodeType::Directory, 0o040000),
+        // the user cannot look behind the link to tell if it is broken
+        ("symlink", Some(target)) => (FsNodeType::Symlink { target, broken: false }, 0o120000),
+        _ => (FsNodeType::File, 0o100000),
+    };
+    let mode = node.mode.as_deref().and_then(|mode| u32::from_str_radix(mode, 8).ok()).unwrap_or(0);
+    let modified = node
+        .modified
+        .map(|seconds| std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds.max(0) as u64));
+    VfsEntry {
+        name: node.name,
+        path: node.path,
+        node_type,
+        metadata: FsMetadata {
+            size: node.size.unwrap_or(0),
+            modified,
+            changed: None,
+            permissions: type_bits | mode,
+            uid: node.uid.unwrap_or(0),
+            gid: node.gid.unwrap_or(0),
+            inode: 0,
+            device: 0,
+            nlink: 1,
+            hidden: false,
+        },
+    }
+}
+
+/// the option of `cp` and `mv` keeping to the answer given to existing targets
+///
+/// Only overwriting has one, and replacing older targets with the `-u` of
+/// GNU. The other answers, and replacing older targets elsewhere, leave the
+/// existing targets alone.
+fn existing_flag(resolution: Option<ConflictResolution>) -> &'static str {
+    match resolution {
+        None | Some(ConflictResolution::Overwrite | ConflictResolution::OverwriteAll) => "",
+        Some(ConflictResolution::OverwriteIfNewer | ConflictResolution::OverwriteAllIfNewer) if cfg!(target_os = "linux") => " -u",
+        Some(_) => " -n",
+    }
+}
+
+/// what the question tells of the existing targets, when they are not overwritten
+fn existing_note(resolution: Option<ConflictResolution>) -> &'static str {
+    match existing_flag(resolution) {
+        " -u" => ", replacing only older targets",
+        " -n" => ", leaving existing targets alone",
+        _ => "",
+    }
+}
+
+fn quote(path: &Path) -> String {
+    shell_quote(&path.to_string_lossy())
+}
+
+/// true if an executable called `program` is in one of the directories of the PATH
+fn on_path(program: &str) -> bool {
+    let Some(path) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&path).any(|dir| dir.join(program).is_file())
+}
@@ -0,0 +1,151 @@
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyEvent, MouseEvent};
+
+use crate::fs_node::TreeUpdate;
+use crate::watcher::TreeChange;
+
+/// longest wait for terminal input before looking at the channel again
+const SLICE: Duration = Duration::from_millis(20);
+
+/// something the application reacts to, from the terminal or from background work
+///
+/// Background threads never change what the panels show themselves, they
+/// send what changed and the main loop brings the panels up to date.
+#[derive(Debug)]
+pub enum AppEvent {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    /// the terminal is now this many columns and rows
+    Resize(u16, u16),
+    /// text pasted into the terminal
+    Paste(String),
+    /// watched directories changed on disk, see [`crate::watcher::TreeWatcher::apply`]
+    TreeChanged(Vec<TreeChange>),
+    /// changes of the loaded tree made by a job or a computation, to be applied in order
+    TreeUpdated(Vec<TreeUpdate>),
+    /// a job sent progress or stopped, it is worth polling
+    Job,
+    /// nothing happened in the time given
+    Tick,
+}
+
+/// merges the terminal input and the events of background threads into one stream
+///
+/// The terminal is read on the calling thread, so a program given the
+/// terminal gets its keys. Background threads send through a clone of
+/// [`EventLoop::sender`], a closed application only makes their sends fail.
+pub struct EventLoop {
+    sender: Sender<AppEvent>,
+    events: Receiver<AppEvent>,
+    /// event taken from the channel while gathering tree changes
+    pending: Option<AppEvent>,
+    /// false when every event comes from the channel, like the scripted ones of a test
+    terminal: bool,
+}
+
+impl EventLoop {
+    pub fn new() -> EventLoop {
+        let (sender, events) = mpsc::channel();
+        EventLoop {
+            sender,
+            events,
+            pending: None,
+            terminal: true,
+        }
+    }
+
+    /// a loop that never reads the terminal, events are sent through [`EventLoop::sender`]
+    pub fn headless() -> EventLoop {
+        EventLoop {
+            terminal: false,
+            ..EventLoop::new()
+        }
+    }
+
+    /// the way in for events of background threads
+    pub fn sender(&self) -> Sender<AppEvent> {
+        self.sender.clone()
+    }
+
+    /// pass the changes of a watcher on as [`AppEvent::TreeChanged`]
+    pub fn forward_changes(&self, changes: Receiver<TreeChange>) {
+        let sender = self.sender();
+        thread::spawn(move || {
+            for change in changes {
+                if sender.send(AppEvent::TreeChanged(vec![change])).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// the next event, [`AppEvent::Tick`] when none came within `timeout`
+    ///
+    /// Events already sent by background threads come before terminal input.
+    /// Tree changes waiting together are given as one event, a burst of them
+    /// redraws the screen once.
+    pub fn next(&mut self, timeout: Duration) -> io::Result<AppEvent> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(event) = self.queued() {
+                return Ok(event);
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if !self.terminal {
+                match left.is_zero() {
+                    true => return Ok(AppEvent::Tick),
+                    false => thread::sleep(left.min(SLICE)),
+                }
+            } else if event::poll(left.min(SLICE))? {
+                if let Some(event) = terminal_event(event::read()?) {
+                    return Ok(event);
+                }
+            } else if left.is_zero() {
+                return Ok(AppEvent::Tick);
+            }
+        }
+    }
+
+    /// the first event of the channel, with the tree changes following it
+    fn queued(&mut self) -> Option<AppEvent> {
+        let event = match self.pending.take() {
+            Some(event) => event,
+            None => self.events.try_recv().ok()?,
+        };
+        let AppEvent::TreeChanged(mut changes) = event else {
+            return Some(event);
+        };
+        loop {
+            match self.events.try_recv() {
+                Ok(AppEvent::TreeChanged(more)) => changes.extend(more),
+                Ok(other) => {
+                    self.pending = Some(other);
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        Some(AppEvent::TreeChanged(changes))
+    }
+}
+
+impl Default for EventLoop {
+    fn default() -> EventLoop {
+        EventLoop::new()
+    }
+}
+
+/// the event of the application for one of the terminal, none for those it ignores
+fn terminal_event(event: Event) -> Option<AppEvent> {
+    match event {
+        Event::Key(key) => Some(AppEvent::Key(key)),
+        Event::Mouse(mouse) => Some(AppEvent::Mouse(mouse)),
+        Event::Resize(columns, rows) => Some(AppEvent::Resize(columns, rows)),
+        Event::Paste(text) => Some(AppEvent::Paste(text)),
+        Event::FocusGained | Event::FocusLost => None,
+    }
+}
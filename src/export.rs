@@ -0,0 +1,167 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fs_error::FsError;
+use crate::fs_node::{FsNode, FsNodeType};
+use crate::hash::{self, HashAlgorithm};
+
+/// layout of an exported tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// the nodes nested like the directories, one object each
+    #[default]
+    Json,
+    /// one line per node, depth first, with a header line
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn parse(text: &str) -> Option<ExportFormat> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// checksum of the regular files, none by default as it reads every file
+    pub hash: Option<HashAlgorithm>,
+}
+
+/// a node as written to an export, times in seconds since the epoch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportNode {
+    pub name: String,
+    pub path: PathBuf,
+    /// `file`, `directory` or `symlink`
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<i64>,
+    /// permission bits in octal, like `0644`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// digest of a regular file with the algorithm of the options
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// why the content of a directory could not be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ExportNode>,
+}
+
+impl ExportNode {
+    /// the export of a node and of its loaded descendants
+    ///
+    /// Directories without a computed size get the total of their files,
+    /// files that cannot be read get no hash.
+    pub fn new(node: &FsNode, hash: Option<HashAlgorithm>) -> ExportNode {
+        let mut export = ExportNode::single(node, hash);
+        export.children = node
+            .children
+            .iter()
+            .map(|child| ExportNode::new(&child.read(), hash))
+            .collect();
+        if node.node_type == FsNodeType::Directory && export.size.is_none() {
+            let sizes = export.children.iter().filter(|child| child.kind != "symlink");
+            export.size = Some(sizes.filter_map(|child| child.size).sum());
+        }
+        export
+    }
+
+    /// the export of a node without its children
+    fn single(node: &FsNode, hash: Option<HashAlgorithm>) -> ExportNode {
+        let (kind, target) = match &node.node_type {
+            FsNodeType::File => ("file", None),
+            FsNodeType::Directory => ("directory", None),
+            FsNodeType::Symlink { target, .. } => ("symlink", Some(target.clone())),
+        };
+        let metadata = node.metadata.as_ref();
+        let hash = hash
+            .filter(|_| node.node_type == FsNodeType::File)
+            .and_then(|algorithm| hash::hash_file(node.vfs.as_ref(), &node.path(), algorithm).ok());
+        ExportNode {
+            name: node.name.to_string(),
+            path: node.path(),
+            kind: kind.to_string(),
+            target,
+            size: node.size(),
+            modified: metadata.and_then(|metadata| metadata.modified).map(unix_seconds),
+            mode: metadata.map(|metadata| format!("{:04o}", metadata.permissions & 0o7777)),
+            uid: metadata.map(|metadata| metadata.uid),
+            gid: metadata.map(|metadata| metadata.gid),
+            hash,
+            error: node.error.as_ref().map(FsError::to_string),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// write a node and its loaded descendants in the format of the options
+pub fn export(node: &FsNode, options: ExportOptions, out: &mut dyn Write) -> io::Result<()> {
+    match options.format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &ExportNode::new(node, options.hash))?;
+            writeln!(out)
+        }
+        ExportFormat::Csv => {
+            writeln!(out, "path,type,size,modified,mode,uid,gid,target,hash,error")?;
+            write_csv_rows(out, &ExportNode::new(node, options.hash))
+        }
+    }
+}
+
+/// the line of a node then those of its descendants, depth first
+fn write_csv_rows(out: &mut dyn Write, node: &ExportNode) -> io::Result<()> {
+    let text = |value: Option<String>| value.unwrap_or_default();
+    let fields = [
+        node.path.display().to_string(),
+        node.kind.to_string(),
+        text(node.size.map(|size| size.to_string())),
+        text(node.modified.map(|seconds| seconds.to_string())),
+        text(node.mode.clone()),
+        text(node.uid.map(|uid| uid.to_string())),
+        text(node.gid.map(|gid| gid.to_string())),
+        text(node.target.as_ref().map(|target| target.display().to_string())),
+        text(node.hash.clone()),
+        text(node.error.clone()),
+    ];
+    let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    writeln!(out, "{}", line.join(","))?;
+    for child in &node.children {
+        write_csv_rows(out, child)?;
+    }
+    Ok(())
+}
+
+/// a field quoted when it holds a comma, a quote or a line break, quotes doubled
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    }
+}
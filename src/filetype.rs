@@ -0,0 +1,336 @@
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::vfs::Vfs;
+
+/// bytes read from the start of a file to recognize its type
+pub const SNIFF_LEN: usize = 1024;
+
+pub const DIRECTORY: &str = "inode/directory";
+pub const SYMLINK: &str = "inode/symlink";
+pub const TEXT: &str = "text/plain";
+pub const BINARY: &str = "application/octet-stream";
+
+/// signatures of binary formats: offset, bytes and type
+const MAGIC: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"II*\0", "image/tiff"),
+    (0, b"MM\0*", "image/tiff"),
+    (0, b"8BPS", "image/vnd.adobe.photoshop"),
+    (8, b"WEBP", "image/webp"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xfd7zXZ\0", "application/x-xz"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (0, b"Rar!\x1a\x07", "application/vnd.rar"),
+    (257, b"ustar", "application/x-tar"),
+    (0, b"!<arch>\ndebian", "application/vnd.debian.binary-package"),
+    (0, b"!<arch>\n", "application/x-archive"),
+    (0, b"\xed\xab\xee\xdb", "application/x-rpm"),
+    (0, b"\x7fELF", "application/x-executable"),
+    (0, b"MZ", "application/vnd.microsoft.portable-executable"),
+    (0, b"\0asm", "application/wasm"),
+    (0, b"\xca\xfe\xba\xbe", "application/java-vm"),
+    (0, b"SQLite format 3\0", "application/vnd.sqlite3"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"MThd", "audio/midi"),
+    (8, b"WAVE", "audio/wav"),
+    (8, b"AVI ", "video/x-msvideo"),
+    (0, b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+    (4, b"ftyp", "video/mp4"),
+    (0, b"wOFF", "font/woff"),
+    (0, b"wOF2", "font/woff2"),
+    (0, b"OTTO", "font/otf"),
+    (0, b"\xef\xbb\xbf", TEXT),
+];
+
+/// types by lowercase extension
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("txt", TEXT),
+    ("log", TEXT),
+    ("ini", TEXT),
+    ("conf", TEXT),
+    ("cfg", TEXT),
+    ("md", "text/markdown"),
+    ("rst", "text/x-rst"),
+    ("csv", "text/csv"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("ts", "text/x-typescript"),
+    ("rs", "text/x-rust"),
+    ("c", "text/x-c"),
+    ("h", "text/x-c"),
+    ("cpp", "text/x-c++"),
+    ("hpp", "text/x-c++"),
+    ("go", "text/x-go"),
+    ("java", "text/x-java"),
+    ("py", "text/x-python"),
+    ("rb", "text/x-ruby"),
+    ("sh", "application/x-sh"),
+    ("json", "application/json"),
+    ("toml", "application/toml"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("xml", "application/xml"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("bmp", "image/bmp"),
+    ("webp", "image/webp"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("ico", "image/x-icon"),
+    ("psd", "image/vnd.adobe.photoshop"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("opus", "audio/opus"),
+    ("flac", "audio/flac"),
+    ("wav", "audio/wav"),
+    ("m4a", "audio/mp4"),
+    ("mid", "audio/midi"),
+    ("mp4", "video/mp4"),
+    ("mkv", "video/x-matroska"),
+    ("webm", "video/webm"),
+    ("avi", "video/x-msvideo"),
+    ("mov", "video/quicktime"),
+    ("pdf", "application/pdf"),
+    ("epub", "application/epub+zip"),
+    ("odt", "application/vnd.oasis.opendocument.text"),
+    ("ods", "application/vnd.oasis.opendocument.spreadsheet"),
+    ("odp", "application/vnd.oasis.opendocument.presentation"),
+    ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+    ("zip", "application/zip"),
+    ("jar", "application/java-archive"),
+    ("tar", "application/x-tar"),
+    ("gz", "application/gzip"),
+    ("tgz", "application/gzip"),
+    ("bz2", "application/x-bzip2"),
+    ("xz", "application/x-xz"),
+    ("zst", "application/zstd"),
+    ("7z", "application/x-7z-compressed"),
+    ("rar", "application/vnd.rar"),
+    ("deb", "application/vnd.debian.binary-package"),
+    ("rpm", "application/x-rpm"),
+    ("exe", "application/vnd.microsoft.portable-executable"),
+    ("wasm", "application/wasm"),
+    ("db", "application/vnd.sqlite3"),
+    ("sqlite", "application/vnd.sqlite3"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+];
+
+/// types outside `text/` that are read as text
+const TEXT_APPLICATIONS: &[&str] = &[
+    "application/json",
+    "application/toml",
+    "application/yaml",
+    "application/xml",
+    "application/x-sh",
+    "image/svg+xml",
+];
+
+/// type guessed from the extension of a file name
+pub fn from_name(name: &str) -> Option<&'static str> {
+    let (_, extension) = name.rsplit_once('.')?;
+    let extension = extension.to_ascii_lowercase();
+    EXTENSIONS.iter().find(|(known, _)| *known == extension).map(|(_, mime)| *mime)
+}
+
+/// type of a binary format recognized from the first bytes of a file
+pub fn from_magic(head: &[u8]) -> Option<&'static str> {
+    MAGIC
+        .iter()
+        .find(|(offset, magic, _)| head.get(*offset..offset + magic.len()) == Some(*magic))
+        .map(|(_, _, mime)| *mime)
+}
+
+/// type of a file from its name and first bytes
+///
+/// Signatures win over the extension, except for zip files and text with a
+/// byte order mark whose extension tells more, like a `.docx`. Unknown files
+/// are text or binary depending on their bytes.
+pub fn detect(name: &str, head: &[u8]) -> &'static str {
+    match (from_magic(head), from_name(name)) {
+        (Some("application/zip" | TEXT), Some(by_name)) => by_name,
+        (Some(mime), _) | (None, Some(mime)) => mime,
+        (None, None) if looks_like_text(head) => TEXT,
+        (None, None) => BINARY,
+    }
+}
+
+/// read the bytes [`detect`] looks at
+pub fn read_head(vfs: &dyn Vfs, path: &Path) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    vfs.open_read(path)?.take(SNIFF_LEN as u64).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+/// true for types shown as text
+pub fn is_text(mime: &str) -> bool {
+    mime.starts_with("text/") || TEXT_APPLICATIONS.contains(&mime)
+}
+
+/// true if the bytes have no nul and are mostly printable, as utf-8 or a single-byte charset
+pub fn looks_like_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => return true,
+        // a character cut by the end of the sample
+        Err(err) if err.error_len().is_none() => return true,
+        Err(_) => {}
+    }
+    let printable = head
+        .iter()
+        .filter(|&&byte| byte >= 0x20 || matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    printable * 10 >= head.len() * 9
+}
+
+/// match a type against a pattern like `image/*`, `*` or `text/plain`, without regard to case
+pub fn mime_matches(pattern: &str, mime: &str) -> bool {
+    let (pattern, mime) = (pattern.to_ascii_lowercase(), mime.to_ascii_lowercase());
+    match pattern.strip_suffix("/*") {
+        _ if pattern == "*" => true,
+        Some(kind) => mime.split('/').next() == Some(kind),
+        None => pattern == mime,
+    }
+}
+
+/// family of a file, choosing its color and icon in the panels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Directory,
+    Symlink,
+    BrokenSymlink,
+    Executable,
+    Archive,
+    Image,
+    Audio,
+    Video,
+    Document,
+    Source,
+    Plain,
+}
+
+/// types of compressed files and archives
+const ARCHIVES: &[&str] = &[
+    "application/zip",
+    "application/java-archive",
+    "application/gzip",
+    "application/x-bzip2",
+    "application/x-xz",
+    "application/zstd",
+    "application/x-7z-compressed",
+    "application/vnd.rar",
+    "application/x-tar",
+    "application/x-archive",
+    "application/vnd.debian.binary-package",
+    "application/x-rpm",
+];
+
+/// family of a type, `Plain` for the types without a color of their own
+pub fn kind(mime: &str) -> FileKind {
+    match mime.split_once('/') {
+        _ if ARCHIVES.contains(&mime) => FileKind::Archive,
+        Some(("image", _)) => FileKind::Image,
+        Some(("audio", _)) => FileKind::Audio,
+        Some(("video", _)) => FileKind::Video,
+        Some(("text", subtype)) if subtype.starts_with("x-") || subtype == "javascript" => FileKind::Source,
+        Some(("application", "x-sh")) => FileKind::Source,
+        Some(("application", subtype))
+            if subtype == "pdf" || subtype == "epub+zip" || subtype.starts_with("vnd.oasis") || subtype.starts_with("vnd.openxmlformats") =>
+        {
+            FileKind::Document
+        }
+        _ => FileKind::Plain,
+    }
+}
+
+/// Nerd Font icons by lowercase extension, before those of the families
+const ICONS: &[(&str, char)] = &[
+    ("rs", '\u{e7a8}'),
+    ("py", '\u{e606}'),
+    ("js", '\u{e74e}'),
+    ("ts", '\u{e628}'),
+    ("go", '\u{e626}'),
+    ("c", '\u{e61e}'),
+    ("h", '\u{e61e}'),
+    ("cpp", '\u{e61d}'),
+    ("hpp", '\u{e61d}'),
+    ("java", '\u{e738}'),
+    ("rb", '\u{e791}'),
+    ("html", '\u{e736}'),
+    ("htm", '\u{e736}'),
+    ("css", '\u{e749}'),
+    ("md", '\u{e609}'),
+    ("json", '\u{e60b}'),
+    ("toml", '\u{e615}'),
+    ("yaml", '\u{e615}'),
+    ("yml", '\u{e615}'),
+    ("lock", '\u{f023}'),
+    ("pdf", '\u{f1c1}'),
+];
+
+/// icon of a family when the extension has none
+fn kind_icon(kind: FileKind) -> char {
+    match kind {
+        FileKind::Directory => '\u{f07b}',
+        FileKind::Symlink => '\u{f481}',
+        FileKind::BrokenSymlink => '\u{f127}',
+        FileKind::Executable => '\u{f489}',
+        FileKind::Archive => '\u{f410}',
+        FileKind::Image => '\u{f1c5}',
+        FileKind::Audio => '\u{f1c7}',
+        FileKind::Video => '\u{f1c8}',
+        FileKind::Document => '\u{f15c}',
+        FileKind::Source => '\u{f121}',
+        FileKind::Plain => '\u{f15b}',
+    }
+}
+
+/// what the panels need to decorate a file, worked out once for each node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileClass {
+    pub kind: FileKind,
+    /// lowercase extension of a file, `None` for directories
+    pub extension: Option<String>,
+    /// Nerd Font icon
+    pub icon: char,
+}
+
+impl FileClass {
+    pub fn new(kind: FileKind, name: &str) -> FileClass {
+        let extension = match kind {
+            FileKind::Directory => None,
+            _ => name.rsplit_once('.').filter(|(stem, _)| !stem.is_empty()).map(|(_, extension)| extension.to_ascii_lowercase()),
+        };
+        let icon = match (kind, &extension) {
+            (FileKind::Directory | FileKind::Symlink | FileKind::BrokenSymlink, _) => kind_icon(kind),
+            (_, Some(extension)) => ICONS
+                .iter()
+                .find(|(known, _)| known == extension)
+                .map(|(_, icon)| *icon)
+                .unwrap_or(kind_icon(kind)),
+            (_, None) => kind_icon(kind),
+        };
+        FileClass { kind, extension, icon }
+    }
+}
@@ -0,0 +1,165 @@
+use std::path::PathBuf;
+
+use glob::{MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+
+/// incremental fuzzy filter over the entries of a panel
+///
+/// The listing only shows entries whose name contains the query letters in
+/// order. The query is case sensitive only when it has an uppercase letter.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PanelFilter {
+    pub query: String,
+    /// entry under the cursor when filtering started, restored on cancel
+    pub origin: Option<PathBuf>,
+}
+
+/// points for a letter matched right after the previous one
+const CONSECUTIVE_BONUS: i64 = 8;
+/// points for a letter starting the name or a word in it
+const WORD_START_BONUS: i64 = 6;
+/// points lost for every skipped character between two matched letters
+const GAP_PENALTY: i64 = 1;
+
+impl PanelFilter {
+    pub fn new(origin: Option<PathBuf>) -> PanelFilter {
+        PanelFilter {
+            query: String::new(),
+            origin,
+        }
+    }
+
+    /// score of a name against the query, `None` if it does not match
+    pub fn score(&self, name: &str) -> Option<i64> {
+        fuzzy_match(&self.query, name).map(|(score, _)| score)
+    }
+
+    /// character positions of the name matched by the query, for highlighting
+    pub fn positions(&self, name: &str) -> Vec<usize> {
+        fuzzy_match(&self.query, name).map(|(_, positions)| positions).unwrap_or_default()
+    }
+}
+
+/// incremental search moving the cursor to the names starting with the query
+///
+/// Unlike the quick filter it hides nothing. The query is case sensitive only
+/// when it has an uppercase letter.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PanelSearch {
+    pub query: String,
+}
+
+impl PanelSearch {
+    /// true if `name` starts with `query`
+    pub fn matches(query: &str, name: &str) -> bool {
+        let case_sensitive = query.chars().any(char::is_uppercase);
+        let mut name = name.chars();
+        query.chars().all(|wanted| match name.next() {
+            Some(c) if case_sensitive => c == wanted,
+            Some(c) => c.to_lowercase().eq(wanted.to_lowercase()),
+            None => false,
+        })
+    }
+
+    /// character positions of the name matched by the query, for highlighting
+    pub fn positions(&self, name: &str) -> Vec<usize> {
+        match PanelSearch::matches(&self.query, name) {
+            true => (0..self.query.chars().count()).collect(),
+            false => Vec::new(),
+        }
+    }
+}
+
+/// match the letters of `query` in order in `name`, higher scores are better
+///
+/// Letters are matched greedily from the left, which keeps the score cheap
+/// enough to compute for every entry on every key.
+pub fn fuzzy_match(query: &str, name: &str) -> Option<(i64, Vec<usize>)> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+    let name: Vec<char> = name.chars().collect();
+    let mut positions = Vec::new();
+    let mut score = 0;
+    let mut next = 0;
+    for wanted in query.chars().map(fold) {
+        let position = (next..name.len()).find(|&index| fold(name[index]) == wanted)?;
+        if positions.last().is_some_and(|&last| last + 1 == position) {
+            score += CONSECUTIVE_BONUS;
+        } else if !positions.is_empty() {
+            score -= (position - next) as i64 * GAP_PENALTY;
+        }
+        if is_word_start(&name, position) {
+            score += WORD_START_BONUS;
+        }
+        positions.push(position);
+        next = position + 1;
+    }
+    // shorter names win among equal matches
+    score -= name.len() as i64 / 8;
+    Some((score, positions))
+}
+
+fn is_word_start(name: &[char], index: usize) -> bool {
+    match index.checked_sub(1).map(|previous| name[previous]) {
+        None => true,
+        Some(previous) => {
+            matches!(previous, '.' | '_' | '-' | ' ') || (previous.is_lowercase() && name[index].is_uppercase())
+        }
+    }
+}
+
+/// pattern restricting the files a panel lists, set with the filter dialog
+///
+/// A pattern between slashes, like `/^test_.*\.rs$/`, is a regular
+/// expression searched in the names, anything else a glob pattern matching the
+/// whole name. Directories are always listed so the panel can still move around.
+#[derive(Debug, Clone)]
+pub struct NameFilter {
+    /// the pattern as typed
+    pub pattern: String,
+    pub case_sensitive: bool,
+    matcher: Matcher,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Glob(Pattern, MatchOptions),
+    Regex(Regex),
+}
+
+impl NameFilter {
+    /// read a typed pattern, `None` for an empty one or `*` which list everything
+    pub fn parse(pattern: &str, case_sensitive: bool) -> Result<Option<NameFilter>, String> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() || pattern == "*" {
+            return Ok(None);
+        }
+        let matcher = match pattern.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            Some(expression) => RegexBuilder::new(expression)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map(Matcher::Regex)
+                .map_err(|err| err.to_string())?,
+            None => {
+                let options = MatchOptions {
+                    case_sensitive,
+                    require_literal_separator: true,
+                    require_literal_leading_dot: false,
+                };
+                Matcher::Glob(Pattern::new(pattern).map_err(|err| err.to_string())?, options)
+            }
+        };
+        Ok(Some(NameFilter {
+            pattern: pattern.to_string(),
+            case_sensitive,
+            matcher,
+        }))
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match &self.matcher {
+            Matcher::Glob(pattern, options) => pattern.matches_with(name, *options),
+            Matcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::vfs::archive::{civil_to_unix, unix_time, unix_to_civil};
+
+/// format of the times of the listing and the dialogs
+pub const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// steps between the units of a size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnits {
+    /// KiB, MiB, ... of 1024 bytes, 1024 KiB
+    #[default]
+    Binary,
+    /// kB, MB, ... of 1000 bytes, 1000 kB
+    Si,
+}
+
+impl SizeUnits {
+    fn step(self) -> f64 {
+        match self {
+            SizeUnits::Binary => 1024.0,
+            SizeUnits::Si => 1000.0,
+        }
+    }
+
+    fn names(self) -> [&'static str; 6] {
+        match self {
+            SizeUnits::Binary => ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+            SizeUnits::Si => ["kB", "MB", "GB", "TB", "PB", "EB"],
+        }
+    }
+}
+
+/// a size scaled down to its unit, the index of the unit and the scaled value
+fn scale(bytes: u64, units: SizeUnits) -> Option<(usize, f64)> {
+    let step = units.step();
+    if (bytes as f64) < step {
+        return None;
+    }
+    let mut scaled = bytes as f64 / step;
+    let mut unit = 0;
+    while scaled >= step && unit < units.names().len() - 1 {
+        scaled /= step;
+        unit += 1;
+    }
+    Some((unit, scaled))
+}
+
+/// a size with its unit, like `12.5 MiB` or `512 B`
+pub fn size(bytes: u64, units: SizeUnits) -> String {
+    match scale(bytes, units) {
+        None => format!("{bytes} B"),
+        Some((unit, scaled)) => format!("{scaled:.1} {}", units.names()[unit]),
+    }
+}
+
+/// a size typed by the user, in bytes or with a unit like `700M`, `1.5G` or `1440K`
+///
+/// The units are of 1024, whatever the case or a trailing `B` or `iB`.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (number, unit) = text.split_at(text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len()));
+    let number: f64 = number.parse().ok()?;
+    let unit = unit.trim().to_ascii_lowercase();
+    let unit = unit.strip_suffix("ib").or_else(|| unit.strip_suffix('b')).unwrap_or(&unit);
+    let shift = match unit {
+        "" => 0,
+        "k" => 10,
+        "m" => 20,
+        "g" => 30,
+        "t" => 40,
+        _ => return None,
+    };
+    let bytes = number * (1u64 << shift) as f64;
+    match (1.0..u64::MAX as f64).contains(&bytes) {
+        true => Some(bytes as u64),
+        false => None,
+    }
+}
+
+/// a time typed by the user, a UTC date like `2024-05-31` or how long before `now` like `90d`
+///
+/// The ages are in hours `h`, days `d`, weeks `w`, months `m` of 30 days or
+/// years `y` of 365 days.
+pub fn parse_time(text: &str, now: SystemTime) -> Option<SystemTime> {
+    let text = text.trim();
+    let mut date = text.splitn(3, '-');
+    if let (Some(year), Some(month), Some(day)) = (date.next(), date.next(), date.next()) {
+        let (year, month, day): (i64, u32, u32) = (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+        return match (1..=12).contains(&month) && (1..=31).contains(&day) {
+            true => Some(unix_time(civil_to_unix(year, month, day, 0, 0, 0))),
+            false => None,
+        };
+    }
+    let (number, unit) = text.split_at(text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len()));
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit.trim().to_ascii_lowercase().as_str() {
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        "m" => 30 * 86_400,
+        "y" => 365 * 86_400,
+        _ => return None,
+    };
+    now.checked_sub(Duration::from_secs(number.checked_mul(seconds)?))
+}
+
+/// a size in at most six characters, like `12.5M`, `512` or `87k`
+pub fn short_size(bytes: u64, units: SizeUnits) -> String {
+    let Some((unit, scaled)) = scale(bytes, units) else {
+        return bytes.to_string();
+    };
+    // the first letter of the unit, kilo is written small in SI
+    let letter = &units.names()[unit][..1];
+    match scaled < 10.0 {
+        true => format!("{scaled:.1}{letter}"),
+        false => format!("{scaled:.0}{letter}"),
+    }
+}
+
+/// a time in UTC like strftime, with `%Y %y %m %b %d %e %H %M %S` and `%%`
+///
+/// Other directives are written as they are.
+pub fn time(time: SystemTime, format: &str) -> String {
+    let seconds = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    };
+    let (year, month, day, hour, minute, second) = unix_to_civil(seconds);
+    let mut text = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => text.push_str(&format!("{year:04}")),
+            Some('y') => text.push_str(&format!("{:02}", year.rem_euclid(100))),
+            Some('m') => text.push_str(&format!("{month:02}")),
+            Some('b') => text.push_str(MONTHS[(month as usize).clamp(1, 12) - 1]),
+            Some('d') => text.push_str(&format!("{day:02}")),
+            Some('e') => text.push_str(&format!("{day:>2}")),
+            Some('H') => text.push_str(&format!("{hour:02}")),
+            Some('M') => text.push_str(&format!("{minute:02}")),
+            Some('S') => text.push_str(&format!("{second:02}")),
+            Some('%') => text.push('%'),
+            Some(other) => {
+                text.push('%');
+                text.push(other);
+            }
+            None => text.push('%'),
+        }
+    }
+    text
+}
+
+/// how long ago `time` was from `now` in its largest unit, like `3 h ago`, or how far ahead
+pub fn relative_time(time: SystemTime, now: SystemTime) -> String {
+    let (elapsed, ago) = match now.duration_since(time) {
+        Ok(elapsed) => (elapsed, true),
+        Err(err) => (err.duration(), false),
+    };
+    let seconds = elapsed.as_secs();
+    let amount = match seconds {
+        0..60 => return String::from("just now"),
+        60..3600 => format!("{} min", seconds / 60),
+        3600..86_400 => format!("{} h", seconds / 3600),
+        86_400..2_592_000 => format!("{} days", seconds / 86_400),
+        2_592_000..31_536_000 => format!("{} months", seconds / 2_592_000),
+        _ => format!("{} years", seconds / 31_536_000),
+    };
+    match ago {
+        true => format!("{amount} ago"),
+        false => format!("in {amount}"),
+    }
+}
+
+/// a duration as `m:ss`, or `h:mm:ss` from an hour on
+pub fn duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds / 3600 {
+        0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{hours}:{:02}:{:02}", seconds / 60 % 60, seconds % 60),
+    }
+}
+
+/// permission bits as `rwxr-xr-x`, with the setuid, setgid and sticky bits as `s` and `t`
+pub fn permissions(mode: u32) -> String {
+    let mut text = String::new();
+    // read, write and execute of the owner, the group and the others, with their special bit
+    for (shift, special, set) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = mode >> shift;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => set.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+/// the letter `ls -l` writes for the file type bits of a mode, `None` when they are not set
+pub fn type_letter(mode: u32) -> Option<char> {
+    match mode & 0o170000 {
+        0o040000 => Some('d'),
+        0o120000 => Some('l'),
+        0o060000 => Some('b'),
+        0o020000 => Some('c'),
+        0o010000 => Some('p'),
+        0o140000 => Some('s'),
+        0o100000 => Some('-'),
+        _ => None,
+    }
+}
@@ -0,0 +1,73 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// why a node or its content could not be read, with the path it was for
+///
+/// Unlike [`io::Error`] it can be cloned, so a node keeps the error of its
+/// last read for the panels to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsError {
+    PermissionDenied(PathBuf),
+    NotFound(PathBuf),
+    /// the name cannot be a path on the filesystem, like one holding a nul byte
+    InvalidName(PathBuf),
+    /// any other failure, described by the system
+    Io {
+        path: PathBuf,
+        kind: io::ErrorKind,
+        message: String,
+    },
+}
+
+impl FsError {
+    pub fn new(path: &Path, err: &io::Error) -> FsError {
+        let path = path.to_path_buf();
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => FsError::PermissionDenied(path),
+            io::ErrorKind::NotFound => FsError::NotFound(path),
+            io::ErrorKind::InvalidFilename | io::ErrorKind::InvalidInput => FsError::InvalidName(path),
+            kind => FsError::Io {
+                path,
+                kind,
+                message: err.to_string(),
+            },
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            FsError::PermissionDenied(path) | FsError::NotFound(path) | FsError::InvalidName(path) => path,
+            FsError::Io { path, .. } => path,
+        }
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            FsError::PermissionDenied(_) => io::ErrorKind::PermissionDenied,
+            FsError::NotFound(_) => io::ErrorKind::NotFound,
+            FsError::InvalidName(_) => io::ErrorKind::InvalidFilename,
+            FsError::Io { kind, .. } => *kind,
+        }
+    }
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsError::PermissionDenied(path) => write!(f, "{}: permission denied", path.display()),
+            FsError::NotFound(path) => write!(f, "{}: no such file or directory", path.display()),
+            FsError::InvalidName(path) => write!(f, "{}: invalid name", path.display()),
+            FsError::Io { path, message, .. } => write!(f, "{}: {message}", path.display()),
+        }
+    }
+}
+
+impl Error for FsError {}
+
+impl From<FsError> for io::Error {
+    fn from(err: FsError) -> io::Error {
+        io::Error::new(err.kind(), err)
+    }
+}
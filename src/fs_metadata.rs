@@ -0,0 +1,75 @@
+use std::fs::Metadata;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FsMetadata {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub changed: Option<SystemTime>,
+    pub permissions: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub inode: u64,
+    /// the filesystem holding the inode, 0 when unknown
+    pub device: u64,
+    pub nlink: u64,
+    /// hidden by an attribute rather than by a leading dot, as on Windows
+    pub hidden: bool,
+}
+
+impl FsMetadata {
+    /// read metadata of a path without following symlinks
+    pub fn from_path(path: &Path) -> io::Result<FsMetadata> {
+        let metadata = std::fs::symlink_metadata(path)?;
+        Ok(FsMetadata::from_metadata(&metadata))
+    }
+
+    #[cfg(unix)]
+    pub fn from_metadata(metadata: &Metadata) -> FsMetadata {
+        use std::os::unix::fs::MetadataExt;
+        use std::time::Duration;
+
+        let changed = if metadata.ctime() >= 0 {
+            Some(SystemTime::UNIX_EPOCH + Duration::new(metadata.ctime() as u64, metadata.ctime_nsec() as u32))
+        } else {
+            None
+        };
+
+        FsMetadata {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            changed,
+            permissions: metadata.mode(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            inode: metadata.ino(),
+            device: metadata.dev(),
+            nlink: metadata.nlink(),
+            hidden: false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_metadata(metadata: &Metadata) -> FsMetadata {
+        let permissions = if metadata.permissions().readonly() { 0o444 } else { 0o644 };
+        #[cfg(windows)]
+        let hidden = crate::windows::is_hidden(metadata);
+        #[cfg(not(windows))]
+        let hidden = false;
+
+        FsMetadata {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            changed: metadata.created().ok(),
+            permissions,
+            uid: 0,
+            gid: 0,
+            inode: 0,
+            device: 0,
+            nlink: 1,
+            hidden,
+        }
+    }
+}
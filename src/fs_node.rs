@@ -1,20 +1,77 @@
-use std::cell::RefCell;
-use std::path::PathBuf;
-use std::rc::{Rc, Weak};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, OnceLock, Weak};
+use std::thread::{self, JoinHandle};
 
-#[derive(Debug, PartialEq)]
+use parking_lot::RwLock;
+
+use crate::event::AppEvent;
+use crate::filetype::{self, FileClass, FileKind};
+use crate::fs_error::FsError;
+use crate::fs_metadata::FsMetadata;
+use crate::scan;
+use crate::sort::{SortKey, SortMode};
+use crate::vfs::{self, VfsEntry, VfsRef};
+
+/// shared handle on a node, read from any thread but changed on the main one, see [`TreeUpdate`]
+pub type FsNodeRef = Arc<RwLock<FsNode>>;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum FsNodeType {
     File,
     Directory,
+    Symlink {
+        target: PathBuf,
+        broken: bool,
+    },
+}
+
+/// source of the generations of the nodes, a value is never given twice
+static GENERATIONS: AtomicU64 = AtomicU64::new(0);
+
+fn next_generation() -> u64 {
+    GENERATIONS.fetch_add(1, Ordering::Relaxed)
+}
+
+/// where a node is, a file shares the path of its directory with its siblings
+///
+/// A tree of a million entries would otherwise hold a million whole paths,
+/// most of them repeating the one of their directory.
+#[derive(Debug, Clone)]
+enum Location {
+    /// the node is its name in this directory, the path of its parent
+    In(Arc<Path>),
+    /// the whole path, kept by directories for their children to share and by
+    /// nodes not named after their last component
+    At(Arc<Path>),
 }
 
 #[derive(Debug)]
 pub struct FsNode {
-    pub name: String,
-    pub path: PathBuf,
+    /// the last component of the path, boxed as it never grows
+    ///
+    /// A file takes its path from it, set the path before changing it, see [`FsNode::set_path`].
+    pub name: Box<str>,
+    location: Location,
     pub node_type: FsNodeType,
-    pub parent: Option<Weak<FsNode>>,
-    pub children: Vec<Rc<RefCell<FsNode>>>,
+    pub metadata: Option<FsMetadata>,
+    /// cached total size of a directory subtree, cleared when children change
+    pub computed_size: Option<u64>,
+    pub parent: Option<Weak<RwLock<FsNode>>>,
+    pub children: Vec<FsNodeRef>,
+    /// changes with the children, see [`FsNode::generation`]
+    generation: u64,
+    /// filesystem backend the node lives on
+    pub vfs: VfsRef,
+    /// why the children of a directory could not be read the last time they were asked
+    pub error: Option<FsError>,
+    /// decoration of the node in the panels, cleared with the metadata
+    class: OnceLock<FileClass>,
+    this: Weak<RwLock<FsNode>>,
 }
 
 impl FsNode {
@@ -22,22 +79,358 @@ impl FsNode {
         name: String,
         path: PathBuf,
         fs_node_type: FsNodeType,
-        parent: Option<Weak<FsNode>>,
-        children: Vec<Rc<RefCell<FsNode>>>
+        parent: Option<Weak<RwLock<FsNode>>>,
+        children: Vec<FsNodeRef>
     ) -> FsNode {
+        let metadata = FsMetadata::from_path(&path).ok();
         FsNode {
-            name,
-            path,
+            name: name.into_boxed_str(),
+            location: Location::At(Arc::from(path)),
             node_type: fs_node_type,
+            metadata,
+            computed_size: None,
             parent,
             children,
+            generation: next_generation(),
+            vfs: vfs::local(),
+            error: None,
+            class: OnceLock::new(),
+            this: Weak::new(),
+        }
+    }
+
+    /// node for an entry listed by a filesystem backend
+    pub fn from_entry(entry: VfsEntry, vfs: VfsRef) -> FsNode {
+        FsNode {
+            name: entry.name.into_boxed_str(),
+            location: Location::At(Arc::from(entry.path)),
+            node_type: entry.node_type,
+            metadata: Some(entry.metadata),
+            computed_size: None,
+            parent: None,
+            children: Vec::new(),
+            generation: next_generation(),
+            vfs,
+            error: None,
+            class: OnceLock::new(),
+            this: Weak::new(),
+        }
+    }
+
+    /// share the node, its children get a parent link to it
+    pub fn into_ref(mut self) -> FsNodeRef {
+        let directory = self.shared_path();
+        Arc::new_cyclic(|this| {
+            for child in &self.children {
+                let mut child = child.write();
+                child.parent = Some(this.clone());
+                child.share_directory(&directory);
+            }
+            self.this = this.clone();
+            RwLock::new(self)
+        })
+    }
+
+    /// the whole path of the node, put together from the one of its directory for a file
+    pub fn path(&self) -> PathBuf {
+        match &self.location {
+            Location::In(directory) => directory.join(&*self.name),
+            Location::At(path) => path.to_path_buf(),
+        }
+    }
+
+    /// the path of the node once, shared from then on with the children it gets
+    fn shared_path(&mut self) -> Arc<Path> {
+        let path: Arc<Path> = match &self.location {
+            Location::At(path) => return Arc::clone(path),
+            Location::In(_) => Arc::from(self.path()),
+        };
+        self.location = Location::At(Arc::clone(&path));
+        path
+    }
+
+    /// take the path of `directory` in place of a path of its own, for a file named after its last component
+    fn share_directory(&mut self, directory: &Arc<Path>) {
+        let inside = match &self.location {
+            Location::In(current) => current == directory,
+            Location::At(path) => path.parent() == Some(directory.as_ref()) && path.file_name() == Some(OsStr::new(&*self.name)),
+        };
+        if inside && self.node_type != FsNodeType::Directory && self.children.is_empty() {
+            self.location = Location::In(Arc::clone(directory));
+        }
+    }
+
+    /// a value changing whenever children are added, removed or put in another order
+    ///
+    /// No two nodes ever give the same one, what was worked out from the
+    /// children holds as long as their node gives the generation it had then.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// give the node a new generation, for code changing [`FsNode::children`] itself
+    pub fn children_changed(&mut self) {
+        self.generation = next_generation();
+    }
+
+    /// parent of the node if it is still alive
+    pub fn parent(&self) -> Option<FsNodeRef> {
+        self.parent.as_ref().and_then(Weak::upgrade)
+    }
+
+    /// re-read the metadata of the node from its filesystem
+    pub fn refresh_metadata(&mut self) -> io::Result<()> {
+        self.class = OnceLock::new();
+        match self.vfs.metadata(&self.path()) {
+            Ok(entry) => {
+                self.metadata = Some(entry.metadata);
+                Ok(())
+            }
+            Err(err) => {
+                self.metadata = None;
+                Err(err)
+            }
+        }
+    }
+
+    /// true if the node is a symbolic link, broken or not
+    pub fn is_symlink(&self) -> bool {
+        matches!(self.node_type, FsNodeType::Symlink { .. })
+    }
+
+    /// true if the node is a symbolic link whose target does not exist
+    pub fn is_broken_symlink(&self) -> bool {
+        matches!(self.node_type, FsNodeType::Symlink { broken: true, .. })
+    }
+
+    /// true if the node is hidden, by a leading dot or by its attributes on Windows
+    pub fn is_hidden(&self) -> bool {
+        self.name.starts_with('.') || self.metadata.as_ref().is_some_and(|metadata| metadata.hidden)
+    }
+
+    /// true if the node can be entered by a panel, following the link if needed
+    pub fn is_navigable(&self) -> bool {
+        match &self.node_type {
+            FsNodeType::Directory => true,
+            FsNodeType::File => false,
+            FsNodeType::Symlink { broken: true, .. } => false,
+            FsNodeType::Symlink { .. } => self.vfs.is_dir(&self.path()),
+        }
+    }
+
+    /// true if a file has an execute permission bit set
+    pub fn is_executable(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.permissions & 0o111 != 0)
+    }
+
+    /// family and icon of the node, from its type, permissions and name
+    ///
+    /// Computed on first use and kept until the metadata is read again, the
+    /// content of files is not read.
+    pub fn class(&self) -> &FileClass {
+        self.class.get_or_init(|| {
+            let kind = match &self.node_type {
+                FsNodeType::Directory => FileKind::Directory,
+                FsNodeType::Symlink { broken: true, .. } => FileKind::BrokenSymlink,
+                FsNodeType::Symlink { .. } => FileKind::Symlink,
+                FsNodeType::File if self.is_executable() => FileKind::Executable,
+                FsNodeType::File => filetype::from_name(&self.name).map(filetype::kind).unwrap_or(FileKind::Plain),
+            };
+            FileClass::new(kind, &self.name)
+        })
+    }
+
+    /// type of the node, recognized from the first bytes of a file and its name
+    ///
+    /// Reads the start of the file, an unreadable file is typed by its name alone.
+    pub fn mime_type(&self) -> &'static str {
+        if self.is_navigable() {
+            return filetype::DIRECTORY;
+        }
+        if self.is_broken_symlink() {
+            return filetype::SYMLINK;
         }
+        let head = filetype::read_head(self.vfs.as_ref(), &self.path()).unwrap_or_default();
+        filetype::detect(&self.name, &head)
+    }
+
+    /// replace the children of a directory node with its direct entries on disk
+    pub fn load_children(&mut self) -> io::Result<()> {
+        let entries = self.read_entries()?;
+        self.children.clear();
+        self.computed_size = None;
+        self.children_changed();
+        for entry in entries {
+            let child = FsNode::from_entry(entry, Arc::clone(&self.vfs));
+            self.add_child(child);
+        }
+        Ok(())
+    }
+
+    /// read the directory again and bring the children in line with it
+    ///
+    /// Children still on disk with the same type keep their node, with the
+    /// metadata read again and their own loaded children, so what points at
+    /// them stays valid. New entries get a node and vanished ones are dropped.
+    /// Returns true when something was added, removed or changed.
+    pub fn rescan(&mut self) -> io::Result<bool> {
+        let entries = self.read_entries()?;
+        let mut existing: HashMap<Box<str>, FsNodeRef> = self
+            .children
+            .drain(..)
+            .map(|child| {
+                let name = child.read().name.clone();
+                (name, child)
+            })
+            .collect();
+        let mut changed = false;
+        for entry in entries {
+            let kept = existing
+                .remove(entry.name.as_str())
+                .filter(|child| child.read().node_type == entry.node_type);
+            match kept {
+                Some(child) => {
+                    {
+                        let mut node = child.write();
+                        if node.metadata.as_ref() != Some(&entry.metadata) {
+                            // a directory whose content changed has a new modification time
+                            node.computed_size = None;
+                            node.metadata = Some(entry.metadata);
+                            node.class = OnceLock::new();
+                            changed = true;
+                        }
+                    }
+                    self.children.push(child);
+                }
+                None => {
+                    changed = true;
+                    self.add_child(FsNode::from_entry(entry, Arc::clone(&self.vfs)));
+                }
+            }
+        }
+        for vanished in existing.into_values() {
+            changed = true;
+            vanished.write().parent = None;
+        }
+        if changed {
+            self.computed_size = None;
+        }
+        // the children come back in the order of the disk
+        self.children_changed();
+        Ok(changed)
+    }
+
+    /// the entries of the directory on disk, keeping the error on the node when they cannot be read
+    fn read_entries(&mut self) -> io::Result<Vec<VfsEntry>> {
+        let entries = self.vfs.read_dir(&self.path());
+        self.error = entries.as_ref().err().map(|err| FsError::new(&self.path(), err));
+        entries
     }
 
     /// add child to a node
     pub fn add_child(&mut self, child:FsNode) {
-        let child = Rc::new(RefCell::new(child));
+        let child = child.into_ref();
+        self.add_child_node(child);
+    }
+
+    /// add an already shared node as child
+    pub fn add_child_node(&mut self, child: FsNodeRef) {
+        let directory = self.shared_path();
+        {
+            let mut node = child.write();
+            node.parent = Some(self.this.clone());
+            node.share_directory(&directory);
+        }
         self.children.push(child);
+        self.computed_size = None;
+        self.children_changed();
+    }
+
+    /// change the path of the node and of all its descendants
+    pub fn set_path(&mut self, path: PathBuf) {
+        let old = self.path();
+        self.location = Location::At(Arc::from(path));
+        let top = self.shared_path();
+        self.move_children(&old, &top);
+    }
+
+    /// bring the paths of the loaded descendants from below `old` to below `new`
+    fn move_children(&mut self, old: &Path, new: &Path) {
+        let directory = self.shared_path();
+        for child in &self.children {
+            let mut child = child.write();
+            if let Ok(relative) = child.path().strip_prefix(old) {
+                child.location = Location::At(Arc::from(new.join(relative)));
+                child.share_directory(&directory);
+            }
+            child.move_children(old, new);
+        }
+    }
+
+    /// move the node and all its descendants to another filesystem backend
+    pub fn set_vfs(&mut self, vfs: VfsRef) {
+        for node in self.iter_dfs() {
+            node.write().vfs = Arc::clone(&vfs);
+        }
+        self.vfs = vfs;
+    }
+
+    /// the loaded descendants of the node, each directory followed by its content
+    ///
+    /// The node itself is left out. Children are read when their parent is
+    /// reached, nodes added or removed before that show up or not.
+    pub fn iter_dfs(&self) -> DepthFirst {
+        DepthFirst {
+            stack: self.children.iter().rev().cloned().collect(),
+        }
+    }
+
+    /// the loaded descendants of the node level by level, the children first
+    pub fn iter_bfs(&self) -> BreadthFirst {
+        BreadthFirst {
+            queue: self.children.iter().cloned().collect(),
+        }
+    }
+
+    /// the loaded descendants for which `predicate` holds, in depth-first order
+    ///
+    /// The subtrees of the nodes left out are still walked.
+    pub fn iter_filtered<P: FnMut(&FsNode) -> bool>(&self, mut predicate: P) -> impl Iterator<Item = FsNodeRef> + use<P> {
+        self.iter_dfs().filter(move |node| predicate(&node.read()))
+    }
+
+    /// size of the node, the computed subtree size for directories when known
+    pub fn size(&self) -> Option<u64> {
+        if self.node_type == FsNodeType::Directory {
+            self.computed_size
+        } else {
+            self.metadata.as_ref().map(|metadata| metadata.size)
+        }
+    }
+
+    /// sum the sizes of the subtree on a background thread, `updates` caches the result on the node
+    pub fn compute_size(node: &FsNodeRef, updates: TreeSender) -> JoinHandle<u64> {
+        let node = Arc::clone(node);
+        thread::spawn(move || {
+            let (vfs, path) = {
+                let node = node.read();
+                (Arc::clone(&node.vfs), node.path())
+            };
+            let size = disk_usage(vfs.as_ref(), &path);
+            updates.send(vec![TreeUpdate::Size { node, size }]);
+            size
+        })
+    }
+
+    /// order the direct children of the node without touching the disk
+    pub fn sort_children(&mut self, sort_mode: SortMode) {
+        if sort_mode.key == SortKey::Unsorted && !sort_mode.directories_first {
+            return;
+        }
+        self.children.sort_by(|a, b| sort_mode.compare(&a.read(), &b.read()));
+        self.children_changed();
     }
 
     /// find node amongst the direct children of a node
@@ -45,12 +438,12 @@ impl FsNode {
         &mut self,
         path: PathBuf,
         fs_node_type: Option<FsNodeType>
-    ) -> Option<Rc<RefCell<FsNode>>> {
+    ) -> Option<FsNodeRef> {
         self.children.iter().find_map(|child| {
-            let node = child.borrow();
-            if node.path == path
+            let node = child.read();
+            if node.path() == path
                 && (fs_node_type.is_none() || (fs_node_type.as_ref() == Some(&node.node_type))) {
-                Some(Rc::clone(child))
+                Some(Arc::clone(child))
             }
             else {
                 None
@@ -58,19 +451,281 @@ impl FsNode {
         })
     }
 
+    /// find the loaded node at `path` below this one by walking its components
+    ///
+    /// The node itself is found for its own path, paths outside of it or not
+    /// loaded yet give `None`.
+    pub fn find_descendant(&self, path: &Path) -> Option<FsNodeRef> {
+        let relative = path.strip_prefix(self.path()).ok()?;
+        let mut components = relative.components();
+        let Some(first) = components.next() else {
+            return self.this.upgrade();
+        };
+        let mut node = self.child_named(first.as_os_str())?;
+        for component in components {
+            let child = node.read().child_named(component.as_os_str())?;
+            node = child;
+        }
+        Some(node)
+    }
+
+    fn child_named(&self, name: &OsStr) -> Option<FsNodeRef> {
+        self.children.iter().find(|child| OsStr::new(&*child.read().name) == name).cloned()
+    }
+
+    /// the parents of the node still alive, the closest first
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors { next: self.parent() }
+    }
+
+    /// the top of the tree the node is in, `None` when the node has no parent
+    pub fn root(&self) -> Option<FsNodeRef> {
+        self.ancestors().last()
+    }
+
+    /// the path leading from this node to `other`, going up with `..` where needed
+    ///
+    /// `None` when the nodes are on different backends, their paths share nothing then.
+    pub fn relative_path_to(&self, other: &FsNode) -> Option<PathBuf> {
+        // backends on different hosts or archives show their top differently
+        let top = Path::new("/");
+        if !Arc::ptr_eq(&self.vfs, &other.vfs) && self.vfs.display_path(top) != other.vfs.display_path(top) {
+            return None;
+        }
+        let (from, to) = (self.path(), other.path());
+        let from: Vec<_> = from.components().collect();
+        let to: Vec<_> = to.components().collect();
+        let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+        let mut relative: PathBuf = from[common..].iter().map(|_| Component::ParentDir).collect();
+        relative.extend(&to[common..]);
+        Some(relative)
+    }
+
     /// remove node if there is one and return it
-    pub fn remove_node(&mut self, path: PathBuf, fs_node_type: Option<FsNodeType>) -> Option<Rc<RefCell<FsNode>>> {
+    pub fn remove_node(&mut self, path: PathBuf, fs_node_type: Option<FsNodeType>) -> Option<FsNodeRef> {
         let position = self.children
             .iter()
-            .position(|child|
-                child.borrow().path == path
-                    && (fs_node_type.is_none() || (fs_node_type.as_ref() == Some(&child.borrow().node_type)))
-            );
+            .position(|child| {
+                let child = child.read();
+                child.path() == path
+                    && (fs_node_type.is_none() || (fs_node_type.as_ref() == Some(&child.node_type)))
+            });
 
         if let Some(position) = position {
-            Some(Rc::clone(&self.children.remove(position)))
+            let node = self.children.remove(position);
+            node.write().parent = None;
+            self.computed_size = None;
+            self.children_changed();
+            Some(node)
         } else {
             None
         }
     }
-}
\ No newline at end of file
+}
+
+/// a change of the loaded tree made on a background thread, applied by the main loop
+///
+/// Threads read the nodes the panels show and leave them as they are, they
+/// send these through the event loop instead, see [`TreeSender`]. A panel
+/// never has its entries change while it draws or walks them.
+#[derive(Debug)]
+pub enum TreeUpdate {
+    /// put a node among the children of `directory`, in place of the one with its path
+    Add { directory: FsNodeRef, node: FsNode },
+    /// take the entry of a path out of `directory`
+    Remove { directory: FsNodeRef, path: PathBuf },
+    /// a node moved under `directory`, its loaded subtree goes along
+    Move {
+        node: FsNodeRef,
+        directory: FsNodeRef,
+        name: String,
+        path: PathBuf,
+        metadata: Option<FsMetadata>,
+        /// backend of `directory` when the node comes from another one
+        vfs: Option<VfsRef>,
+    },
+    /// the total size of the subtree of a directory
+    Size { node: FsNodeRef, size: u64 },
+}
+
+impl TreeUpdate {
+    /// make the change, on the thread the panels are drawn on
+    pub fn apply(self) {
+        match self {
+            TreeUpdate::Add { directory, node } => {
+                let mut directory = directory.write();
+                directory.remove_node(node.path(), None);
+                directory.add_child(node);
+            }
+            TreeUpdate::Remove { directory, path } => {
+                directory.write().remove_node(path, None);
+            }
+            TreeUpdate::Move { node, directory, name, path, metadata, vfs } => {
+                let (old_parent, old_path) = {
+                    let node = node.read();
+                    (node.parent(), node.path())
+                };
+                if let Some(old_parent) = old_parent {
+                    old_parent.write().remove_node(old_path, None);
+                }
+                {
+                    let mut node = node.write();
+                    node.set_path(path);
+                    node.name = name.into_boxed_str();
+                    if let Some(vfs) = vfs {
+                        node.set_vfs(vfs);
+                    }
+                    node.metadata = metadata;
+                    node.class = OnceLock::new();
+                }
+                directory.write().add_child_node(node);
+            }
+            TreeUpdate::Size { node, size } => node.write().computed_size = Some(size),
+        }
+    }
+}
+
+/// where background threads send their [`TreeUpdate`]s
+///
+/// The updates go to the event loop, which applies them between two frames.
+/// Without one they are applied at once, as a program of its own has no
+/// panels to keep still.
+#[derive(Debug, Clone, Default)]
+pub struct TreeSender {
+    events: Option<Sender<AppEvent>>,
+}
+
+impl TreeSender {
+    pub fn new(events: Option<Sender<AppEvent>>) -> TreeSender {
+        TreeSender { events }
+    }
+
+    pub fn send(&self, updates: Vec<TreeUpdate>) {
+        if updates.is_empty() {
+            return;
+        }
+        match &self.events {
+            // a closed application has no tree left to update
+            Some(events) => {
+                let _ = events.send(AppEvent::TreeUpdated(updates));
+            }
+            None => updates.into_iter().for_each(TreeUpdate::apply),
+        }
+    }
+}
+
+/// walk up the parents of a node, see [`FsNode::ancestors`]
+pub struct Ancestors {
+    next: Option<FsNodeRef>,
+}
+
+impl Iterator for Ancestors {
+    type Item = FsNodeRef;
+
+    fn next(&mut self) -> Option<FsNodeRef> {
+        let node = self.next.take()?;
+        self.next = node.read().parent();
+        Some(node)
+    }
+}
+
+/// depth-first walk of a loaded tree, see [`FsNode::iter_dfs`]
+pub struct DepthFirst {
+    stack: Vec<FsNodeRef>,
+}
+
+impl Iterator for DepthFirst {
+    type Item = FsNodeRef;
+
+    fn next(&mut self) -> Option<FsNodeRef> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.read().children.iter().rev().cloned());
+        Some(node)
+    }
+}
+
+/// breadth-first walk of a loaded tree, see [`FsNode::iter_bfs`]
+pub struct BreadthFirst {
+    queue: VecDeque<FsNodeRef>,
+}
+
+impl Iterator for BreadthFirst {
+    type Item = FsNodeRef;
+
+    fn next(&mut self) -> Option<FsNodeRef> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.read().children.iter().cloned());
+        Some(node)
+    }
+}
+
+/// detect the type of path without following symlinks
+pub fn node_type_from_path(path: &Path) -> Option<FsNodeType> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    let file_type = metadata.file_type();
+
+    if file_type.is_symlink() {
+        let target = std::fs::read_link(path).unwrap_or_default();
+        let broken = std::fs::metadata(path).is_err();
+        Some(FsNodeType::Symlink { target, broken })
+    } else if file_type.is_dir() {
+        Some(FsNodeType::Directory)
+    } else {
+        Some(FsNodeType::File)
+    }
+}
+
+/// total size of the regular files under a path, unreadable entries are skipped
+pub fn disk_usage(vfs: &dyn vfs::Vfs, path: &Path) -> u64 {
+    vfs.metadata(path)
+        .map(|entry| entry_usage(vfs, &entry))
+        .unwrap_or(0)
+}
+
+fn entry_usage(vfs: &dyn vfs::Vfs, entry: &VfsEntry) -> u64 {
+    match entry.node_type {
+        FsNodeType::File => entry.metadata.size,
+        FsNodeType::Symlink { .. } => 0,
+        FsNodeType::Directory => vfs
+            .read_dir(&entry.path)
+            .map(|entries| entries.iter().map(|entry| entry_usage(vfs, entry)).sum())
+            .unwrap_or(0),
+    }
+}
+
+/// number of regular files under a path, counted like for [`disk_usage`]
+pub fn file_count(vfs: &dyn vfs::Vfs, path: &Path) -> u64 {
+    vfs.metadata(path)
+        .map(|entry| entry_files(vfs, &entry))
+        .unwrap_or(0)
+}
+
+fn entry_files(vfs: &dyn vfs::Vfs, entry: &VfsEntry) -> u64 {
+    match entry.node_type {
+        FsNodeType::File => 1,
+        FsNodeType::Symlink { .. } => 0,
+        FsNodeType::Directory => vfs
+            .read_dir(&entry.path)
+            .map(|entries| entries.iter().map(|entry| entry_files(vfs, entry)).sum())
+            .unwrap_or(0),
+    }
+}
+
+/// build a node and its whole subtree from a local path, symlinks are not followed
+pub fn create_node_from_path(path: &Path) -> Result<FsNode, FsError> {
+    create_node_in(&vfs::local(), path)
+}
+
+/// build a node and its whole subtree from a path of a filesystem backend
+///
+/// Directories are read on several threads, see [`scan::scan`]. Only a path
+/// that cannot be read fails, directories below it that cannot be listed
+/// are left empty with their [`FsNode::error`] set.
+pub fn create_node_in(vfs: &VfsRef, path: &Path) -> Result<FsNode, FsError> {
+    scan::scan(vfs, path, scan::default_threads())
+}
+
+/// build the subtree of a path on a background thread
+pub fn spawn_scan(path: PathBuf) -> JoinHandle<Result<FsNodeRef, FsError>> {
+    thread::spawn(move || create_node_from_path(&path).map(FsNode::into_ref))
+}
@@ -0,0 +1,149 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// a mounted filesystem, as listed by the system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    /// device or source of the filesystem, like `/dev/sda1` or `tmpfs`
+    pub device: String,
+    pub mount_point: PathBuf,
+    /// type of the filesystem, like `ext4`
+    pub fs_type: String,
+}
+
+/// the filesystems mounted on the system, empty where they cannot be listed
+pub fn mounts() -> Vec<Mount> {
+    let Ok(text) = fs::read_to_string("/proc/self/mounts") else {
+        return Vec::new();
+    };
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let device = unescape(fields.next()?);
+            let mount_point = PathBuf::from(unescape(fields.next()?));
+            let fs_type = unescape(fields.next()?);
+            Some(Mount {
+                device,
+                mount_point,
+                fs_type,
+            })
+        })
+        .collect()
+}
+
+/// places a panel can start from, the drives on Windows
+#[cfg(windows)]
+pub fn roots() -> Vec<PathBuf> {
+    crate::windows::drives()
+}
+
+/// places a panel can start from, `/` and the mount points of real devices
+///
+/// Pseudo filesystems like `proc` or `tmpfs` have no device path and are left out.
+#[cfg(not(windows))]
+pub fn roots() -> Vec<PathBuf> {
+    let mut roots = vec![PathBuf::from("/")];
+    for mount in mounts() {
+        if mount.device.starts_with('/') && !roots.contains(&mount.mount_point) {
+            roots.push(mount.mount_point);
+        }
+    }
+    roots
+}
+
+/// the mount holding a local path, the one with the longest mount point above it
+pub fn mount_of(path: &Path) -> Option<Mount> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    mounts()
+        .into_iter()
+        .filter(|mount| path.starts_with(&mount.mount_point))
+        // the last of several mounts on the same point hides the others, and is the last maximum
+        .max_by_key(|mount| mount.mount_point.components().count())
+}
+
+/// size of a filesystem and what is left of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Space {
+    pub total: u64,
+    /// bytes an unprivileged user can still write, the blocks kept for root left out
+    pub free: u64,
+}
+
+impl Space {
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.free)
+    }
+}
+
+/// space of the filesystem holding a local path
+#[cfg(unix)]
+pub fn space(path: &Path) -> io::Result<Space> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: the path is a valid C string and `stat` is a writable statvfs
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let block = stat.f_frsize as u64;
+    Ok(Space {
+        total: stat.f_blocks as u64 * block,
+        free: stat.f_bavail as u64 * block,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn space(_path: &Path) -> io::Result<Space> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "free space is only known on unix"))
+}
+
+/// the filesystem of a local directory and its space, as shown under a panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsStatus {
+    /// directory the status was read for
+    pub path: PathBuf,
+    pub mount: Option<Mount>,
+    pub space: Option<Space>,
+}
+
+impl FsStatus {
+    pub fn read(path: &Path) -> FsStatus {
+        FsStatus {
+            path: path.to_path_buf(),
+            mount: mount_of(path),
+            space: space(path).ok(),
+        }
+    }
+}
+
+/// major and minor numbers of a device id, as Linux packs them
+pub fn device_numbers(device: u64) -> (u64, u64) {
+    let major = ((device >> 8) & 0xfff) | ((device >> 32) & !0xfff);
+    let minor = (device & 0xff) | ((device >> 12) & !0xff);
+    (major, minor)
+}
+
+/// undo the octal escapes of spaces, tabs and backslashes in the mount table
+fn unescape(field: &str) -> String {
+    let mut text = String::new();
+    let mut rest = field;
+    while let Some(index) = rest.find('\\') {
+        text.push_str(&rest[..index]);
+        let code = rest.get(index + 1..index + 4).and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(code) => {
+                text.push(code as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                text.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
@@ -0,0 +1,91 @@
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::vfs::Vfs;
+
+/// files whose rules apply to the directory holding them and below
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// the ignore rules in force in a directory, as git applies them
+///
+/// Rules come from the `.gitignore` and `.ignore` files of the directory and
+/// of its parents up to the root of the repository, then from the
+/// `info/exclude` of the repository and the global excludes file of git.
+/// The rules of a deeper file win over those above it.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    /// matchers of the files found, the one that decides first
+    matchers: Vec<Arc<Gitignore>>,
+}
+
+impl IgnoreRules {
+    /// the rules of `dir` and of the directories holding it
+    pub fn for_directory(vfs: &dyn Vfs, dir: &Path) -> IgnoreRules {
+        let mut matchers = Vec::new();
+        for ancestor in dir.ancestors() {
+            matchers.extend(read_rules(vfs, ancestor, &IGNORE_FILES));
+            if vfs.metadata(&ancestor.join(".git")).is_ok() {
+                matchers.extend(read_rules(vfs, ancestor, &[".git/info/exclude"]));
+                break;
+            }
+        }
+        // the excludes file of the user only names local files
+        if vfs.local_path(dir).is_some() {
+            matchers.push(global());
+        }
+        IgnoreRules { matchers }
+    }
+
+    /// the rules of a subdirectory of the directory of these rules
+    pub fn child(&self, vfs: &dyn Vfs, dir: &Path) -> IgnoreRules {
+        let mut matchers: Vec<_> = read_rules(vfs, dir, &IGNORE_FILES).into_iter().collect();
+        if matchers.is_empty() {
+            return self.clone();
+        }
+        matchers.extend(self.matchers.iter().cloned());
+        IgnoreRules { matchers }
+    }
+
+    /// true if the entry at `path` is left out, the `.git` directory always is
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if is_dir && path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        for matcher in &self.matchers {
+            match matcher.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// the matcher of the ignore files of `dir` there are, none without any rule
+fn read_rules(vfs: &dyn Vfs, dir: &Path, files: &[&str]) -> Option<Arc<Gitignore>> {
+    let mut builder = GitignoreBuilder::new(dir);
+    for file in files {
+        let path = dir.join(file);
+        let mut text = String::new();
+        if vfs.open_read(&path).and_then(|mut reader| reader.read_to_string(&mut text)).is_err() {
+            continue;
+        }
+        for line in text.lines() {
+            // a broken pattern only loses its own line, as with git
+            let _ = builder.add_line(Some(path.clone()), line);
+        }
+    }
+    let matcher = builder.build().ok()?;
+    (!matcher.is_empty()).then(|| Arc::new(matcher))
+}
+
+/// the rules of the global excludes file of git, read once
+fn global() -> Arc<Gitignore> {
+    static GLOBAL: OnceLock<Arc<Gitignore>> = OnceLock::new();
+    Arc::clone(GLOBAL.get_or_init(|| Arc::new(Gitignore::global().0)))
+}
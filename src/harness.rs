@@ -0,0 +1,141 @@
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent};
+use ratatui::backend::TestBackend;
+use ratatui::layout::Rect;
+use ratatui::Terminal;
+
+use crate::app::{App, Step};
+use crate::commands::Keymap;
+use crate::config::Config;
+use crate::event::{AppEvent, EventLoop};
+use crate::panel::{Panel, PanelManager};
+use crate::ui::theme::Theme;
+use crate::ui::Dialog;
+
+/// the application drawn into a buffer and driven by scripted events, for tests
+///
+/// Events go through a headless [`EventLoop`] like the ones of background
+/// threads, so jobs and tree updates are taken in the order of a real
+/// session. Outcomes needing the terminal, like the subshell or an external
+/// editor, are not carried out and given back by [`Harness::send`].
+pub struct Harness {
+    pub app: App,
+    pub config: Config,
+    pub theme: Theme,
+    terminal: Terminal<TestBackend>,
+    events: EventLoop,
+}
+
+impl Harness {
+    /// the panels opened on `left` and `right` in a screen of `width` by `height`
+    pub fn new(left: &Path, right: &Path, width: u16, height: u16) -> io::Result<Harness> {
+        let events = EventLoop::headless();
+        let mut manager = PanelManager::new(Panel::new(left)?, Panel::new(right)?);
+        manager.events = Some(events.sender());
+        let config = Config {
+            subshell: false,
+            ..Config::default()
+        };
+        let mut harness = Harness {
+            app: App::new(manager, Keymap::default()),
+            config,
+            theme: Theme::classic(),
+            terminal: Terminal::new(TestBackend::new(width, height))?,
+            events,
+        };
+        harness.draw()?;
+        Ok(harness)
+    }
+
+    /// handle `event` and what background work sent until now, then draw the screen
+    pub fn send(&mut self, event: AppEvent) -> io::Result<Step> {
+        self.events.sender().send(event).map_err(io::Error::other)?;
+        let mut step = Step::Continue;
+        while let Some(event) = self.next(Duration::ZERO)? {
+            step = self.take(event)?;
+        }
+        Ok(step)
+    }
+
+    pub fn key(&mut self, code: KeyCode) -> io::Result<Step> {
+        self.key_with(code, KeyModifiers::NONE)
+    }
+
+    pub fn key_with(&mut self, code: KeyCode, modifiers: KeyModifiers) -> io::Result<Step> {
+        self.send(AppEvent::Key(KeyEvent::new(code, modifiers)))
+    }
+
+    pub fn mouse(&mut self, event: MouseEvent) -> io::Result<Step> {
+        self.send(AppEvent::Mouse(event))
+    }
+
+    /// type `text` one key at a time
+    pub fn type_text(&mut self, text: &str) -> io::Result<()> {
+        for c in text.chars() {
+            self.key(KeyCode::Char(c))?;
+        }
+        Ok(())
+    }
+
+    /// handle the events of background work until no job runs, at most for `timeout`
+    pub fn settle(&mut self, timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        while matches!(self.app.dialog, Some(Dialog::Job(_))) || self.app.is_busy() {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "background work still runs"));
+            }
+            match self.next(left.min(Duration::from_millis(50)))? {
+                Some(event) => {
+                    self.take(event)?;
+                }
+                None => self.draw()?,
+            }
+        }
+        // updates sent by a job as it ended
+        while let Some(event) = self.next(Duration::ZERO)? {
+            self.take(event)?;
+        }
+        Ok(())
+    }
+
+    /// the rows of the last drawn screen, joined by line breaks
+    pub fn screen(&self) -> String {
+        let buffer = self.terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        let symbols: Vec<&str> = buffer.content.iter().map(|cell| cell.symbol()).collect();
+        symbols.chunks(width.max(1)).map(|row| row.concat().trim_end().to_string()).collect::<Vec<_>>().join("\n")
+    }
+
+    /// true if the last drawn screen shows `text` on one of its rows
+    pub fn contains(&self, text: &str) -> bool {
+        self.screen().lines().any(|row| row.contains(text))
+    }
+
+    /// the next event of the loop, none when only a tick came
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<AppEvent>> {
+        match self.events.next(timeout)? {
+            AppEvent::Tick => Ok(None),
+            event => Ok(Some(event)),
+        }
+    }
+
+    fn take(&mut self, event: AppEvent) -> io::Result<Step> {
+        let size = self.terminal.size()?;
+        let area = Rect::new(0, 0, size.width, size.height);
+        let step = self.app.handle(event, area, &mut self.config, &mut self.theme, None);
+        self.draw()?;
+        Ok(step)
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        self.app.update();
+        let theme = &self.theme;
+        let app = &mut self.app;
+        self.terminal.draw(|frame| app.draw(frame, theme))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::Digest;
+
+use crate::vfs::Vfs;
+
+/// bytes hashed at once
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// checksum algorithms, named like their command line tools
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 4] = [
+        HashAlgorithm::Md5,
+        HashAlgorithm::Sha1,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Blake3,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<HashAlgorithm> {
+        let text = text.trim().to_ascii_lowercase();
+        HashAlgorithm::ALL.into_iter().find(|algorithm| algorithm.name() == text)
+    }
+
+    /// extension of the manifests, `.sha256sum` for `sha256sum` files
+    pub fn manifest_extension(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5sum",
+            HashAlgorithm::Sha1 => "sha1sum",
+            HashAlgorithm::Sha256 => "sha256sum",
+            HashAlgorithm::Blake3 => "b3sum",
+        }
+    }
+
+    /// algorithm of a manifest from its extension
+    pub fn for_manifest(path: &Path) -> Option<HashAlgorithm> {
+        let extension = path.extension()?.to_str()?;
+        HashAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.manifest_extension() == extension)
+    }
+
+    /// length of a digest written in hex
+    fn hex_len(self) -> usize {
+        match self {
+            HashAlgorithm::Md5 => 32,
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 64,
+        }
+    }
+}
+
+/// running digest of one of the algorithms
+pub enum Hasher {
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub fn new(algorithm: HashAlgorithm) -> Hasher {
+        match algorithm {
+            HashAlgorithm::Md5 => Hasher::Md5(md5::Md5::new()),
+            HashAlgorithm::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(hasher) => hasher.update(data),
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    /// the digest in lowercase hex
+    pub fn finish_hex(self) -> String {
+        self.finish().iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+/// lowercase hex digest of everything a reader gives
+pub fn hash_reader(algorithm: HashAlgorithm, reader: &mut dyn Read) -> io::Result<String> {
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finish_hex())
+}
+
+pub fn hash_file(vfs: &dyn Vfs, path: &Path, algorithm: HashAlgorithm) -> io::Result<String> {
+    hash_reader(algorithm, &mut vfs.open_read(path)?)
+}
+
+/// one line of a manifest, a digest and the name it was computed for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub digest: String,
+    pub name: String,
+}
+
+/// manifest text in the `sha256sum` format, `<digest>  <name>` per line
+pub fn format_manifest(checksums: &[Checksum]) -> String {
+    checksums
+        .iter()
+        .map(|checksum| format!("{}  {}\n", checksum.digest, checksum.name))
+        .collect()
+}
+
+/// lines of a manifest, the binary marker `*` is accepted and ignored
+pub fn parse_manifest(text: &str) -> Vec<Checksum> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (digest, name) = line.split_once(' ')?;
+            let name = name.strip_prefix([' ', '*']).unwrap_or(name);
+            Some(Checksum {
+                digest: digest.to_ascii_lowercase(),
+                name: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// write a manifest, replacing an existing one
+pub fn write_manifest(vfs: &dyn Vfs, manifest: &Path, checksums: &[Checksum]) -> io::Result<()> {
+    let mut writer = vfs.open_write(manifest)?;
+    writer.write_all(format_manifest(checksums).as_bytes())?;
+    writer.flush()
+}
+
+/// what checking a manifest found
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub matched: usize,
+    /// files whose digest is no longer the recorded one
+    pub mismatched: Vec<String>,
+    /// files that could not be read, with the reason
+    pub unreadable: Vec<(String, io::Error)>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.mismatched.is_empty() && self.unreadable.is_empty()
+    }
+
+    /// `sha256sum -c` like lines, then a summary
+    pub fn describe(&self) -> String {
+        let mut text = String::new();
+        for name in &self.mismatched {
+            text.push_str(&format!("{name}: FAILED\n"));
+        }
+        for (name, err) in &self.unreadable {
+            text.push_str(&format!("{name}: FAILED open or read ({err})\n"));
+        }
+        text.push_str(&format!(
+            "{} matched, {} mismatched, {} unreadable\n",
+            self.matched,
+            self.mismatched.len(),
+            self.unreadable.len()
+        ));
+        text
+    }
+}
+
+/// re-hash the files a manifest lists, names are relative to its directory
+///
+/// The algorithm comes from the extension of the manifest, or from the
+/// length of its digests.
+pub fn verify_manifest(vfs: &dyn Vfs, manifest: &Path) -> io::Result<VerifyReport> {
+    let mut text = String::new();
+    vfs.open_read(manifest)?.read_to_string(&mut text)?;
+    let checksums = parse_manifest(&text);
+    let algorithm = HashAlgorithm::for_manifest(manifest)
+        .or_else(|| {
+            let len = checksums.first()?.digest.len();
+            HashAlgorithm::ALL.into_iter().find(|algorithm| algorithm.hex_len() == len)
+        })
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a checksum manifest", manifest.display()))
+        })?;
+    let dir = manifest.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("/"));
+
+    let mut report = VerifyReport::default();
+    for checksum in checksums {
+        match hash_file(vfs, &dir.join(&checksum.name), algorithm) {
+            Ok(digest) if digest == checksum.digest => report.matched += 1,
+            Ok(_) => report.mismatched.push(checksum.name),
+            Err(err) => report.unreadable.push((checksum.name, err)),
+        }
+    }
+    Ok(report)
+}
+
+/// check that a copy has the content of its source
+pub fn verify_copy(src_vfs: &dyn Vfs, src: &Path, dest_vfs: &dyn Vfs, dest: &Path) -> io::Result<()> {
+    // blake3 is the fastest of them, any digest catches a bad copy
+    let source = hash_file(src_vfs, src, HashAlgorithm::Blake3)?;
+    if hash_file(dest_vfs, dest, HashAlgorithm::Blake3)? != source {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} differs from its source after the copy", dest.display()),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,614 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::commands::{Command, Keymap};
+use crate::viewer::{Viewer, ViewerAction};
+
+/// topic shown when no other fits, with links to all the others
+pub const INDEX: &str = "index";
+
+/// topic of the key bindings, written from the keymap in use
+pub const KEYS: &str = "keys";
+
+/// a page of the help, links are written `[label](topic)` in its text
+struct Topic {
+    id: &'static str,
+    title: &'static str,
+    text: &'static str,
+}
+
+const TOPICS: &[Topic] = &[
+    Topic {
+        id: INDEX,
+        title: "Contents",
+        text: "Midday Commander shows two panels of files side by side. Most commands act on the
+files of the active panel and send their result to the other one.
+
+  [Using the help](help)
+  [The panels](panels)
+  [Keys of the file manager](keys)
+  [Selecting files](selection)
+  [Copying and moving](copy)
+  [Packing and extracting archives](archives)
+  [Deleting](delete)
+  [Finding files](find)
+  [Renaming many files](rename)
+  [Permissions and owners](permissions)
+  [Directory hotlist](hotlist)
+  [Remote connections](connections)
+  [Directory history](history)
+  [The menu bar](menu)
+  [Comparing and synchronizing](compare)
+  [Disk usage](usage)
+  [The command line](shell)
+  [Dialogs](dialogs)
+  [The viewer](viewer)
+  [The editor](editor)
+",
+    },
+    Topic {
+        id: "help",
+        title: "Using the help",
+        text: "Links are written in brackets. Tab and Shift+Tab go from one link to the next,
+Enter follows the selected link and Backspace comes back to the topic it was
+followed from. The arrows, PgUp and PgDn scroll the text, / searches it.
+
+F1 opened from a dialog or a full-screen mode shows the topic about it, closing
+the help with Esc or F10 comes back where it was opened.
+
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "panels",
+        title: "The panels",
+        text: "Each panel lists a directory, of the local disk, of an archive or of a remote
+host. Enter goes into a directory or opens a file, Backspace goes up, Tab
+switches to the other panel.
+
+Alt+g asks for a directory to go to, with ~ and $VARIABLES expanded and
+Tab finishing the directory names. Up goes through the directories the
+panel went to. A name that is not below the current directory is looked for
+in the directories of cdpath in the config file, then in those of $CDPATH.
+A path stays on the host of the panel, an url like sftp://host/dir or
+file:///home opens its own.
+
+Ctrl+S or Alt+S searches the names as they are typed: the cursor goes to the
+next name starting with the letters, from the top again after the last one,
+and the matched letters are underlined. Ctrl+S again goes to the next match,
+Backspace takes a letter off, Enter or Esc ends the search. Alt+f filters
+instead, leaving only the names holding the letters in order.
+
+Remote hosts are opened as sftp://user@host/path, and S3 compatible object
+storage as s3://bucket/prefix, with the credentials of the s3 section of the
+config file or of the AWS environment variables. The buckets are the
+directories of s3://. Remote directories are read in the background, their
+entries show up as they arrive while the bottom of the frame counts them.
+
+The listing format, the columns shown, the sort order and hidden files are set
+per panel from the Left and Right menus. The sort can read the digits of names
+as numbers, for file2 to come before file10 and version 1.9 before 1.10, and
+compare letters by the collation of the locale, LC_COLLATE or LANG. The sort
+of each panel is kept with the session.
+
+A panel can also show a tree of the directories, or the details of the file
+under the cursor of the other panel.
+
+  [Keys of the file manager](keys)
+  [Selecting files](selection)
+  [Directory hotlist](hotlist)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "selection",
+        title: "Selecting files",
+        text: "Insert or Space selects the file under the cursor and moves down. + selects the
+files matching a pattern, - unselects them and * inverts the selection.
+Commands act on the selected files, or on the file under the cursor when none
+is selected.
+
+Alt++ marks or unmarks by criteria: name patterns, a size like >1G, <100K or
+10M-1G, modified before or after a date like 2024-05-31 or an age like 1y or
+30d, the owner and the type of entry. Every criterion filled must hold, so a
+size of >1G with modified before 1y marks the big files left for a year. A
+directory only has a size once computed with Ctrl+Space.
+
+The bottom of the panel counts the marked entries and their size. Ctrl+X s
+saves the marks under a name and Ctrl+X r puts them back in place of the
+current ones, after a reread or on coming back to the directory. The saved
+marks last until the file manager quits.
+
+Ctrl+X n copies the name of the file under the cursor to the system clipboard
+and Ctrl+X p its full path. Ctrl+X m copies the paths of the marked files, one
+per line, and Ctrl+X q on one line quoted for a shell. The clipboard programs
+of the system are used, pbcopy, wl-copy, xclip, xsel or clip.exe, and without
+them the terminal is asked to keep the text, which works over ssh too.
+
+  [Copying and moving](copy)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "copy",
+        title: "Copying and moving",
+        text: "F5 copies and F6 moves the selected files to the directory of the other panel.
+The job runs in the background with its progress in a dialog, where it can be
+paused, a file skipped or the whole job stopped. + and - change how many bytes
+per second it may use, so a big copy to a remote host or a USB stick leaves
+room for the rest. The limit jobs start with is set from the Options menu, or
+as bandwidth_limit in KiB per second in the config file.
+
+Copies on the local disk are left to the filesystem where it can: on btrfs, XFS
+or APFS the copy shares the blocks of its source until one of them changes,
+and the holes of sparse files like disk images stay holes.
+
+Copies keep the permissions and the modification time of their sources. The
+Options menu has the other attributes they can keep: the access time, the
+extended attributes, the POSIX ACLs of Linux and, when running as root, the
+owner and group. Space checks or clears each one. When the destination refuses
+one of these the copy still goes through, and the end of the job tells which
+attribute was lost on how many entries. Checking the hardlinks makes the names
+of one file among the copied files names of one copy, instead of copies each.
+
+Ctrl+X l makes a hardlink of the file under the cursor in the directory of the
+other panel, both panels being on one filesystem. Undo removes it.
+
+Ctrl+C copies the selected files and Ctrl+X x cuts them, to paste them with
+Ctrl+V into the directory of the active panel, whichever it is by then. The
+paste is a copy or a move like F5 and F6, with the same questions on
+existing targets. Copied files pasted into their own directory get a free
+name next to them. A paste of cut files empties the clipboard, copied ones
+can be pasted again.
+
+The mouse copies by dragging too. A drag starting on a selected file carries
+every selected file, one starting on another file marks a range in its panel
+and carries that file alone once it reaches the other panel. Letting go over
+the other panel copies them into its directory, over a directory entry into
+that one, which lights up while the pointer is on it. Ctrl or Alt held when
+letting go moves them instead.
+
+Splitting, in the File menu, cuts files into parts of a given size like 4095M
+for a FAT32 stick or 20M for mail, named file.001, file.002 and so on in the
+other panel. A file.sha256sum manifest comes with them, with the digest of each
+part and of the whole file. Joining any part of them, or several, puts the file
+back together in the other panel. When a manifest lies next to the parts they
+are checked against it, and a file that does not match is removed.
+
+When a target already exists the overwrite dialog asks whether to replace it,
+skip it, or keep both. Its answer can be used for every conflict of the job.
+A target shorter than its source is taken for a copy that was cut off, like a
+download from a dropped connection. Resume then adds only what it misses, the
+targets that are not shorter are left as they are.
+
+  [Deleting](delete)
+  [Packing and extracting archives](archives)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "archives",
+        title: "Packing and extracting archives",
+        text: "Alt+a packs the selected files into a new archive in the directory of the other
+panel, a zip or a tar compressed with gzip, xz or zstd as picked in the dialog.
+The level goes from the fastest to the smallest, 0 to 9 and 1 to 19 for zstd,
+a zip of level 0 only stores its files. The packing runs as a job like a
+copy, an archive stopped halfway is removed and undo deletes a finished one.
+
+Alt+x extracts the archive under the cursor into the other panel and Alt+X
+next to it. Its files go into a directory named after the archive, unless
+the archive holds a single directory. Existing files are handled as for a
+copy, and an encrypted zip, 7z or rar asks for its password.
+
+Enter goes into zip, tar, 7z and rar archives as into a directory, their
+files can be viewed and copied out but not changed.
+
+Other formats are browsed through the helper commands of extfs.toml, next to
+the configuration file. A helper names the archives it takes by patterns and
+gives a list command printing members like ls -l or tar -tv, a copyout
+command writing member %m of archive %f to file %t, and an optional copyin
+command storing file %t as member %m. Debian and rpm packages, disk images
+and apk files have helpers by default.
+
+  [Copying and moving](copy)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "delete",
+        title: "Deleting",
+        text: "F8 moves the selected files to the trash, where they can be restored from. A
+deletion for good asks again before anything is removed. Files the user has no
+right to delete can be retried as root when a program like sudo is set up.
+
+Shredding, in the File menu, overwrites local files before deleting them, 3
+times with random bytes unless `passes` and `pattern` (random, zeros or ones)
+under `[shred]` in the configuration say otherwise. It runs as a job with its
+progress shown and cannot be undone. Files on a solid state drive are refused,
+the drive writes the new bytes elsewhere and keeps the old ones. Copy-on-write
+filesystems, snapshots and backups can keep them too.
+
+  [Copying and moving](copy)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "find",
+        title: "Finding files",
+        text: "The find dialog looks for files by name below a directory, with their content
+matching a text if one is given. The results come in while the search runs.
+With skipping what .gitignore ignores checked, the files git ignores are left out
+and their directories not searched, `gitignore = true` in the configuration
+checks it by default and has the disk usage analyzer skip them too.
+
+In the results Enter goes to the file in the panel, F3 views it with the match
+shown and a panelize puts every result in the active panel, to work on them
+like on a directory.
+
+The external panelize, Alt+p, lists in the active panel the files a shell
+command prints, one per line, like `find . -name '*.rs' -mtime -1`.
+
+  [The viewer](viewer)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "rename",
+        title: "Renaming many files",
+        text: "The multi-rename dialog renames the selected files after a pattern, with a
+search and replace or a counter. The new names are shown next to the old ones
+before anything is renamed, names made twice are reported as conflicts.
+
+The files are renamed all or none: if one fails the others get their old names
+back. The renames of local files are written down next to the configuration
+before the first one, so those cut off by a crash are undone at the next start.
+
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "permissions",
+        title: "Permissions and owners",
+        text: "The chmod dialog sets the permission bits of the selected files, by checkboxes
+or as an octal number, optionally on everything below the directories. The
+chown dialog picks the user and the group in lists.
+
+  [Deleting](delete)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "hotlist",
+        title: "Directory hotlist",
+        text: "The hotlist keeps the directories and remote locations gone to often, in groups.
+Enter goes to the selected entry, entries are added from the current directory
+of the active panel.
+
+  [Directory history](history)
+  [Remote connections](connections)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "connections",
+        title: "Remote connections",
+        text: "Alt+n lists the sftp hosts and S3 storages saved under a name, Enter opens the
+selected one in the active panel. a adds a connection, e edits the selected one
+and d deletes it. They are kept in connections.toml next to the config file.
+
+A password, or the secret key of an S3 storage, typed in the form goes to the
+keyring of the system through secret-tool, or security on macOS, never to the
+file. The connections marked with * have one there. An sftp host refusing the
+keys and the password asks for one, which is not kept.
+
+The header of a remote panel starts with the name of its connection, or its
+host. A connection that drops is made again by the next operation, Ctrl+R
+rereads the directory. Until then the name is shown like an error.
+
+  [The panels](panels)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "history",
+        title: "Directory history",
+        text: "Each panel remembers the directories it showed. The history dialog lists them
+and goes back to the one picked, the history keys step through it without a
+dialog.
+
+  [Directory hotlist](hotlist)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "menu",
+        title: "The menu bar",
+        text: "F9 opens the menu bar. The Left and Right menus set up one panel, the others
+hold every command with its key. The arrows move between the menus and their
+entries, Enter runs the command and Esc closes the menu.
+
+  [Keys of the file manager](keys)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "compare",
+        title: "Comparing and synchronizing",
+        text: "Comparing the directories selects the files that differ between the panels.
+Synchronizing makes a plan of what to copy or delete to make one side like the
+other, or both alike. The plan can be reviewed, each action changed or left
+out, before it runs.
+
+  [Copying and moving](copy)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "usage",
+        title: "Disk usage",
+        text: "The disk usage analyzer sums the sizes of everything below the directory of the
+active panel, then lists each directory biggest entry first with its share of
+the whole. Enter goes into a directory and Backspace back out, g shows the
+entry in the panel and F8 deletes it for good once confirmed with y.
+
+  [Deleting](delete)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "shell",
+        title: "The command line",
+        text: "What is typed goes to the command line under the panels, Enter runs it in the
+directory of the active panel. Ctrl+O switches to the shell behind the panels
+and back.
+
+Once something is typed, Tab completes it: the first word with the programs of
+$PATH, the others with the files of the directory, a $NAME with the variables
+of the environment, and the whole line with the commands run before. When
+several fit, a list opens that Tab and Shift+Tab or Up and Down go through,
+Esc closes it and brings back what was typed.
+
+  [Keys of the file manager](keys)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "dialogs",
+        title: "Dialogs",
+        text: "Tab and Shift+Tab move between the fields and buttons of a dialog. Enter
+presses the selected button, Esc closes the dialog without doing anything.
+Fields keep the values typed in them before, Up and Down go through them.
+
+In fields taking a path, like the one of the change directory dialog, Tab
+completes the path instead of moving on and opens a list when several fit, gone
+through with Tab and Shift+Tab. Esc closes the list first. The start directory
+of the find dialog is completed with Alt+Tab or Ctrl+Space.
+
+Ctrl+V pastes the system clipboard into a field. Text pasted into the terminal
+goes into the field, or on the command line over the panels. Line breaks of
+the pasted text become spaces.
+
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "viewer",
+        title: "The viewer",
+        text: "F3 shows a file without changing it. F2 wraps long lines, F4 switches to hex,
+F5 goes to a line or an offset, F7 searches and n finds the next match. q, Esc
+or F3 close the viewer.
+
+  [The editor](editor)
+  [Contents](index)
+",
+    },
+    Topic {
+        id: "editor",
+        title: "The editor",
+        text: "F4 edits a file, in the external editor when one is set up. The internal editor
+saves with F2, searches with F7 and replaces with F4, asks before quitting with
+unsaved changes.
+
+  [The viewer](viewer)
+  [Contents](index)
+",
+    },
+];
+
+/// a link of the shown topic, where its label is in the text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub topic: &'static str,
+    pub offset: u64,
+    pub len: usize,
+}
+
+/// the help pages, shown in the viewer with links between them
+#[derive(Debug)]
+pub struct Help {
+    pub viewer: Viewer,
+    pub topic: &'static str,
+    pub links: Vec<Link>,
+    /// link Enter follows, shown like a search match
+    pub selected: Option<usize>,
+    /// topics left by following links, with where they were scrolled to
+    back: Vec<(&'static str, u64)>,
+    /// text of the key bindings
+    keys: String,
+}
+
+impl Help {
+    /// open the help on `topic`, the contents if there is no such topic
+    pub fn new(topic: &str, keymap: &Keymap) -> Help {
+        let mut help = Help {
+            viewer: Viewer::from_text("Help", ""),
+            topic: INDEX,
+            links: Vec::new(),
+            selected: None,
+            back: Vec::new(),
+            keys: keys_text(keymap),
+        };
+        help.show(topic);
+        help
+    }
+
+    /// show a topic from its start
+    fn show(&mut self, topic: &str) {
+        let (id, title, text) = match topic {
+            KEYS => (KEYS, "Keys of the file manager", self.keys.clone()),
+            _ => {
+                let topic = TOPICS.iter().find(|known| known.id == topic).unwrap_or(&TOPICS[0]);
+                (topic.id, topic.title, topic.text.to_string())
+            }
+        };
+        let (text, links) = parse_links(&text);
+        self.viewer = Viewer::from_text(&format!("Help: {title}"), &text);
+        self.viewer.wrap = true;
+        self.topic = id;
+        self.links = links;
+        self.selected = None;
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> ViewerAction {
+        match key.code {
+            KeyCode::Tab => self.select(1),
+            KeyCode::BackTab => self.select(self.links.len().saturating_sub(1)),
+            KeyCode::Enter => match self.selected.and_then(|index| self.links.get(index)) {
+                Some(link) => {
+                    let topic = link.topic;
+                    self.back.push((self.topic, self.viewer.top));
+                    self.show(topic);
+                }
+                None => return self.viewer.handle_key(key),
+            },
+            KeyCode::Backspace => {
+                if let Some((topic, top)) = self.back.pop() {
+                    self.show(topic);
+                    self.viewer.top = top;
+                }
+            }
+            _ => return self.viewer.handle_key(key),
+        }
+        ViewerAction::None
+    }
+
+    /// move the selection `step` links forward, wrapping around, and scroll to it
+    fn select(&mut self, step: usize) {
+        if self.links.is_empty() {
+            return;
+        }
+        let index = match self.selected {
+            Some(index) => (index + step) % self.links.len(),
+            None if step == 1 => 0,
+            None => self.links.len() - 1,
+        };
+        self.selected = Some(index);
+        let link = &self.links[index];
+        self.viewer.found = Some((link.offset, link.len));
+        // rows are read again on the next draw, this only keeps the link on screen
+        let last_shown = self
+            .viewer
+            .rows(self.viewer.height)
+            .ok()
+            .and_then(|rows| rows.last().and_then(|row| row.cells.last().map(|(_, offset)| *offset)));
+        if link.offset < self.viewer.top || last_shown.is_some_and(|last| link.offset > last) {
+            let _ = self.viewer.goto_offset(link.offset);
+        }
+    }
+}
+
+/// the topic about a command, the one of the panels for the others
+pub fn command_topic(command: Command) -> &'static str {
+    match command {
+        Command::Copy | Command::Move | Command::Hardlink | Command::BandwidthLimit | Command::CopyAttributes => "copy",
+        Command::CutFiles | Command::CopyFiles | Command::PasteFiles => "copy",
+        Command::Compress | Command::Extract | Command::ExtractHere => "archives",
+        Command::Split | Command::Join => "copy",
+        Command::Delete | Command::DeletePermanently | Command::Shred | Command::Trash | Command::Restore => "delete",
+        Command::Find | Command::ExternalPanelize => "find",
+        Command::MultiRename => "rename",
+        Command::Chmod | Command::Chown => "permissions",
+        Command::Hotlist => "hotlist",
+        Command::ChangeDirectory => "panels",
+        Command::Connections => "connections",
+        Command::History | Command::HistoryBack | Command::HistoryForward => "history",
+        Command::MenuBar => "menu",
+        Command::CompareDirectories | Command::Synchronize => "compare",
+        Command::DiskUsage => "usage",
+        Command::View => "viewer",
+        Command::Edit => "editor",
+        Command::ToggleMark
+        | Command::MarkGroup
+        | Command::UnmarkGroup
+        | Command::InvertMarks
+        | Command::SelectByCriteria
+        | Command::SaveSelection
+        | Command::RestoreSelection
+        | Command::CopyName
+        | Command::CopyPath
+        | Command::CopyPaths
+        | Command::CopyQuotedPaths => "selection",
+        _ => "panels",
+    }
+}
+
+/// the key bindings grouped by command, each command with a link to its topic
+fn keys_text(keymap: &Keymap) -> String {
+    let mut lines: Vec<(Command, Vec<String>)> = Vec::new();
+    for (name, command) in keymap.bindings() {
+        match lines.last_mut() {
+            Some((last, names)) if *last == command => names.push(name),
+            _ => lines.push((command, vec![name])),
+        }
+    }
+    let mut text = String::from("The keys of the panels, as bound now. F1 in a dialog tells about the dialog.\n\n");
+    for (command, names) in lines {
+        let label = format!("[{}]({})", command.label(), command_topic(command));
+        // the link markup is not shown, the padding is for the label alone
+        let width = 36 + label.len() - command.label().len() - 2;
+        text.push_str(&format!("  {label:<width$}{}\n", names.join(", ")));
+    }
+    text.push_str("\n  [Contents](index)\n");
+    text
+}
+
+/// the text as shown, links written `[label]`, with where each link is
+fn parse_links(source: &str) -> (String, Vec<Link>) {
+    let mut text = String::with_capacity(source.len());
+    let mut links = Vec::new();
+    let mut rest = source;
+    while let Some(open) = rest.find('[') {
+        let (before, from) = rest.split_at(open);
+        text.push_str(before);
+        let link = from.find("](").and_then(|middle| {
+            let close = middle + from[middle..].find(')')?;
+            let topic = &from[middle + 2..close];
+            let known = topic == KEYS || TOPICS.iter().any(|known| known.id == topic);
+            known.then(|| (&from[1..middle], topic_id(topic), close))
+        });
+        match link {
+            Some((label, topic, close)) => {
+                links.push(Link {
+                    topic,
+                    offset: text.len() as u64,
+                    len: label.len() + 2,
+                });
+                text.push_str(&format!("[{label}]"));
+                rest = &from[close + 1..];
+            }
+            None => {
+                text.push('[');
+                rest = &from[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    (text, links)
+}
+
+/// the static id of a known topic
+fn topic_id(topic: &str) -> &'static str {
+    match topic {
+        KEYS => KEYS,
+        _ => TOPICS.iter().find(|known| known.id == topic).map_or(INDEX, |known| known.id),
+    }
+}
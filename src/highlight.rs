@@ -0,0 +1,140 @@
+use std::ops::Range;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use ratatui::style::{Color, Modifier, Style};
+use syntect::highlighting::{FontStyle, HighlightState, Highlighter as ThemeHighlighter, RangedHighlightIterator, ThemeSet};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+
+/// styled byte ranges of one line
+pub type LineStyles = Vec<(Style, Range<usize>)>;
+
+/// colors text by language, one document at a time
+///
+/// Only the requested lines are highlighted. Implementations may keep state
+/// between calls for the same document and are told when lines change.
+pub trait Highlighter: Send {
+    /// styles of `count` lines from `first`, earlier lines give the context
+    fn highlight(&mut self, lines: &[String], first: usize, count: usize) -> Vec<LineStyles>;
+
+    /// lines from `row` on have changed
+    fn invalidate(&mut self, _row: usize) {}
+}
+
+/// syntax theme used when the color theme does not name one
+pub const DEFAULT_SYNTAX_THEME: &str = "base16-ocean.dark";
+
+/// lines between two saved parser states
+const CHECKPOINT_INTERVAL: usize = 64;
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+/// highlighter for a file, chosen by extension and then by its first line (shebang, modeline)
+pub fn for_file(path: &Path, first_line: &str, theme: &str) -> Option<Box<dyn Highlighter>> {
+    let set = syntaxes();
+    let by_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| set.find_syntax_by_extension(name));
+    let by_extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(|extension| set.find_syntax_by_extension(extension));
+    let syntax = by_name
+        .or(by_extension)
+        .or_else(|| set.find_syntax_by_first_line(first_line))?;
+    if syntax.name == "Plain Text" {
+        return None;
+    }
+    let theme = themes()
+        .themes
+        .get(theme)
+        .or_else(|| themes().themes.get(DEFAULT_SYNTAX_THEME))?;
+    Some(Box::new(SyntectHighlighter::new(syntax, theme)))
+}
+
+/// highlighter built on the sublime syntax definitions bundled with syntect
+pub struct SyntectHighlighter {
+    syntax: &'static SyntaxReference,
+    highlighter: ThemeHighlighter<'static>,
+    /// parser states at the start of every `CHECKPOINT_INTERVAL` lines
+    checkpoints: Vec<(ParseState, HighlightState)>,
+}
+
+impl SyntectHighlighter {
+    pub fn new(syntax: &'static SyntaxReference, theme: &'static syntect::highlighting::Theme) -> SyntectHighlighter {
+        let highlighter = ThemeHighlighter::new(theme);
+        let start = (
+            ParseState::new(syntax),
+            HighlightState::new(&highlighter, ScopeStack::new()),
+        );
+        SyntectHighlighter {
+            syntax,
+            highlighter,
+            checkpoints: vec![start],
+        }
+    }
+
+    pub fn syntax_name(&self) -> &str {
+        &self.syntax.name
+    }
+}
+
+impl Highlighter for SyntectHighlighter {
+    fn highlight(&mut self, lines: &[String], first: usize, count: usize) -> Vec<LineStyles> {
+        let end = (first + count).min(lines.len());
+        let checkpoint = (first / CHECKPOINT_INTERVAL).min(self.checkpoints.len() - 1);
+        let (mut parse, mut highlight) = self.checkpoints[checkpoint].clone();
+        let mut styles = Vec::with_capacity(end.saturating_sub(first));
+
+        let mut line_text = String::new();
+        for (row, line) in lines.iter().enumerate().take(end).skip(checkpoint * CHECKPOINT_INTERVAL) {
+            if row % CHECKPOINT_INTERVAL == 0 && row / CHECKPOINT_INTERVAL == self.checkpoints.len() {
+                self.checkpoints.push((parse.clone(), highlight.clone()));
+            }
+            line_text.clear();
+            line_text.push_str(line);
+            line_text.push('\n');
+            let Ok(ops) = parse.parse_line(&line_text, syntaxes()) else {
+                // give up on the rest rather than show wrong colors
+                styles.resize(end - first, Vec::new());
+                return styles;
+            };
+            let ranges: LineStyles = RangedHighlightIterator::new(&mut highlight, &ops, &line_text, &self.highlighter)
+                .filter(|(_, _, range)| range.start < line.len())
+                .map(|(style, _, range)| (convert(style), range.start..range.end.min(line.len())))
+                .collect();
+            if row >= first {
+                styles.push(ranges);
+            }
+        }
+        styles
+    }
+
+    fn invalidate(&mut self, row: usize) {
+        // a checkpoint is the state before its first line, that line may have changed
+        self.checkpoints.truncate((row / CHECKPOINT_INTERVAL + 1).max(1));
+    }
+}
+
+fn convert(style: syntect::highlighting::Style) -> Style {
+    let mut converted = Style::default().fg(Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b));
+    if style.font_style.contains(FontStyle::BOLD) {
+        converted = converted.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        converted = converted.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        converted = converted.add_modifier(Modifier::UNDERLINED);
+    }
+    converted
+}
@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+use crate::vfs::VfsRef;
+
+/// directories remembered per panel
+const HISTORY_SIZE: usize = 100;
+
+/// a directory shown by a panel
+#[derive(Debug, Clone)]
+pub struct Visit {
+    /// where it is as shown to the user, a local path or an url
+    pub location: String,
+    /// backend and path it was shown with, unknown for visits of past sessions
+    pub target: Option<(VfsRef, PathBuf)>,
+}
+
+/// directories a panel went through, with a position for back and forward
+#[derive(Debug, Clone, Default)]
+pub struct DirHistory {
+    visits: Vec<Visit>,
+    position: usize,
+}
+
+impl DirHistory {
+    pub fn new() -> DirHistory {
+        DirHistory::default()
+    }
+
+    /// history of a past session, positioned on its last visit
+    pub fn from_locations(locations: Vec<String>) -> DirHistory {
+        let visits: Vec<Visit> = locations
+            .into_iter()
+            .rev()
+            .take(HISTORY_SIZE)
+            .rev()
+            .map(|location| Visit { location, target: None })
+            .collect();
+        DirHistory {
+            position: visits.len().saturating_sub(1),
+            visits,
+        }
+    }
+
+    pub fn visits(&self) -> &[Visit] {
+        &self.visits
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn locations(&self) -> Vec<String> {
+        self.visits.iter().map(|visit| visit.location.clone()).collect()
+    }
+
+    /// record a directory the panel went to, forgetting the visits ahead
+    pub fn visit(&mut self, location: &str, vfs: VfsRef, path: &Path) {
+        let target = Some((vfs, path.to_path_buf()));
+        if let Some(current) = self.visits.get_mut(self.position)
+            && current.location == location
+        {
+            current.target = target;
+            return;
+        }
+        self.visits.truncate(self.position + 1);
+        if self.visits.len() == HISTORY_SIZE {
+            self.visits.remove(0);
+        }
+        self.visits.push(Visit {
+            location: location.to_string(),
+            target,
+        });
+        self.position = self.visits.len() - 1;
+    }
+
+    /// move one visit back or forward, the visits ahead are kept
+    pub fn step(&mut self, forward: bool) -> Option<Visit> {
+        let next = match forward {
+            true => self.position + 1,
+            false => self.position.checked_sub(1)?,
+        };
+        self.go_to(next)
+    }
+
+    /// move to any visit
+    pub fn go_to(&mut self, index: usize) -> Option<Visit> {
+        let visit = self.visits.get(index)?.clone();
+        self.position = index;
+        Some(visit)
+    }
+
+    /// forget the visit just moved to, it cannot be shown anymore, and go back to `from`
+    pub fn discard(&mut self, from: usize) {
+        let stale = self.position;
+        if stale >= self.visits.len() {
+            return;
+        }
+        self.visits.remove(stale);
+        self.position = match stale < from {
+            true => from - 1,
+            false => from,
+        }
+        .min(self.visits.len().saturating_sub(1));
+    }
+}
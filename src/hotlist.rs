@@ -0,0 +1,98 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// a directory the user wants to come back to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    /// local path or url as accepted by `vfs::open_location`, like `sftp://host/dir`
+    pub location: String,
+}
+
+/// named set of bookmarks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookmarkGroup {
+    pub name: String,
+    #[serde(default, rename = "bookmark")]
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl BookmarkGroup {
+    pub fn new(name: &str) -> BookmarkGroup {
+        BookmarkGroup {
+            name: name.to_string(),
+            bookmarks: Vec::new(),
+        }
+    }
+}
+
+/// the directory hotlist, stored as toml next to the configuration
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Hotlist {
+    #[serde(default, rename = "group")]
+    pub groups: Vec<BookmarkGroup>,
+}
+
+/// group bookmarks go to when the hotlist has none
+pub const DEFAULT_GROUP: &str = "Bookmarks";
+
+impl Hotlist {
+    /// `hotlist.toml` in the directory of the default configuration
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::default_path()?.with_file_name("hotlist.toml"))
+    }
+
+    /// read a hotlist, a missing file gives an empty one
+    pub fn load(path: &Path) -> io::Result<Hotlist> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Hotlist::default()),
+            Err(err) => return Err(err),
+        };
+        toml::from_str(&text).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err.message()))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+
+    /// add a bookmark at the end of a group, creating the group if needed
+    pub fn add(&mut self, group: &str, bookmark: Bookmark) {
+        let index = match self.groups.iter().position(|existing| existing.name == group) {
+            Some(index) => index,
+            None => {
+                self.groups.push(BookmarkGroup::new(group));
+                self.groups.len() - 1
+            }
+        };
+        self.groups[index].bookmarks.push(bookmark);
+    }
+
+    /// true if some bookmark leads to the location
+    pub fn contains(&self, location: &str) -> bool {
+        self.groups
+            .iter()
+            .flat_map(|group| &group.bookmarks)
+            .any(|bookmark| bookmark.location == location)
+    }
+}
+
+/// name given to a new bookmark, the last component of its location
+pub fn bookmark_name(location: &str) -> String {
+    let trimmed = location.trim_end_matches('/');
+    match trimmed.rsplit('/').next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => location.to_string(),
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::{FsNode, FsNodeRef, FsNodeType};
+use crate::fsinfo::{self, Mount};
+
+/// details of one file, shown by a panel in info mode
+///
+/// Local files are asked again for what the backends leave out, like the
+/// allocated size and the device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInfo {
+    pub name: String,
+    /// path as the panels show it, with the url of a remote backend
+    pub location: String,
+    pub node_type: FsNodeType,
+    pub mime: &'static str,
+    /// metadata read by the backend, `None` when it could not be read
+    pub metadata: Option<FsMetadata>,
+    /// size of a file, or of the content of a directory once computed
+    pub size: Option<u64>,
+    /// the file is on the local filesystem, its ids have names here
+    pub local: bool,
+    /// bytes taken on the disk, holes of sparse files left out
+    pub allocated: Option<u64>,
+    pub accessed: Option<SystemTime>,
+    pub device: Option<u64>,
+    /// filesystem holding a local file
+    pub mount: Option<Mount>,
+    /// path of the node, to tell when the cursor moved to another one
+    path: PathBuf,
+}
+
+impl FileInfo {
+    pub fn gather(node: &FsNode) -> FileInfo {
+        let mut info = FileInfo {
+            name: node.name.to_string(),
+            location: node.vfs.display_path(&node.path()),
+            node_type: node.node_type.clone(),
+            mime: node.mime_type(),
+            metadata: node.metadata.clone(),
+            size: node.size(),
+            local: false,
+            allocated: None,
+            accessed: None,
+            device: None,
+            mount: None,
+            path: node.path(),
+        };
+        if let Some(local) = node.vfs.local_path(&node.path()) {
+            info.local = true;
+            if let Ok(metadata) = std::fs::symlink_metadata(&local) {
+                info.accessed = metadata.accessed().ok();
+                local_details(&mut info, &metadata);
+            }
+            info.mount = fsinfo::mount_of(&local);
+        }
+        info
+    }
+
+    /// true if the info still describes the node as it is
+    pub fn describes(&self, node: &FsNode) -> bool {
+        self.path == node.path() && self.metadata == node.metadata && self.size == node.size()
+    }
+}
+
+#[cfg(unix)]
+fn local_details(info: &mut FileInfo, metadata: &std::fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+
+    // blocks are of 512 bytes whatever the block size of the filesystem
+    info.allocated = Some(metadata.blocks() * 512);
+    info.device = Some(metadata.dev());
+}
+
+#[cfg(not(unix))]
+fn local_details(_info: &mut FileInfo, _metadata: &std::fs::Metadata) {}
+
+/// the info mode of a panel, describing the entry under the cursor of the other panel
+#[derive(Debug, Clone, Default)]
+pub struct InfoView {
+    /// details of the last node shown, gathered again when it changes
+    pub shown: Option<FileInfo>,
+}
+
+impl InfoView {
+    /// the details of `node`, read again only if it is not the node shown last or it changed
+    pub fn show(&mut self, node: Option<&FsNodeRef>) -> Option<&FileInfo> {
+        let Some(node) = node else {
+            self.shown = None;
+            return None;
+        };
+        let node = node.read();
+        if !self.shown.as_ref().is_some_and(|shown| shown.describes(&node)) {
+            self.shown = Some(FileInfo::gather(&node));
+        }
+        self.shown.as_ref()
+    }
+}
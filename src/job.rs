@@ -0,0 +1,681 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::event::AppEvent;
+use crate::fs_node::{disk_usage, file_count, FsNode, FsNodeRef, TreeSender, TreeUpdate};
+use crate::journal::{Moved, Operation};
+use crate::ops::{self, CompressOptions, ConflictResolution, ConflictResolver, CopyFlow, CopyOptions, CopyProgress, LostAttribute, ShredOptions};
+use crate::vfs;
+
+/// shortest time between two progress events of a job
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// what a job does with its targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    Copy,
+    Move,
+    /// pack the targets into a new archive
+    Compress,
+    /// copy members of an archive out of it
+    Extract,
+    /// overwrite the targets, then delete them
+    Shred,
+    /// cut files into parts
+    Split,
+    /// put the parts of files back together
+    Join,
+}
+
+impl JobKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            JobKind::Copy => "Copy",
+            JobKind::Move => "Move",
+            JobKind::Compress => "Compress",
+            JobKind::Extract => "Extract",
+            JobKind::Shred => "Shred",
+            JobKind::Split => "Split",
+            JobKind::Join => "Join",
+        }
+    }
+}
+
+/// members of an archive to extract once the user said what to do with those existing
+#[derive(Debug)]
+pub struct Extraction {
+    pub targets: Vec<FsNodeRef>,
+    pub dest: FsNodeRef,
+}
+
+/// what a job did once it stopped, finished or not
+#[derive(Debug)]
+pub struct JobReport {
+    pub kind: JobKind,
+    /// what can be undone of it, also when it failed halfway
+    pub operation: Operation,
+    pub files_copied: u64,
+    pub files_skipped: u64,
+    /// attributes of the sources the copies did not get
+    pub lost: Vec<LostAttribute>,
+    /// what else the user should hear of, one sentence each
+    pub notes: Vec<String>,
+    pub result: io::Result<()>,
+    /// targets left when the job failed, the one that failed first
+    pub unfinished: Vec<FsNodeRef>,
+    /// directory the targets or the archive went to
+    pub dest: FsNodeRef,
+    /// answer to the targets that existed, a retry as root keeps to it
+    pub resolution: Option<ConflictResolution>,
+}
+
+#[derive(Debug)]
+enum JobEvent {
+    Progress(CopyProgress),
+    Done(JobReport),
+}
+
+/// sends the events of a job, waking the event loop of the screen for each
+///
+/// The changes the job makes to the loaded tree go to the event loop too,
+/// the job leaves the nodes of the panels as they are.
+struct JobSender {
+    events: Sender<JobEvent>,
+    wake: Option<Sender<AppEvent>>,
+    tree: TreeSender,
+}
+
+impl JobSender {
+    fn send(&self, event: JobEvent) {
+        let _ = self.events.send(event);
+        if let Some(wake) = &self.wake {
+            let _ = wake.send(AppEvent::Job);
+        }
+    }
+
+    fn update(&self, updates: Vec<TreeUpdate>) {
+        self.tree.send(updates);
+    }
+}
+
+/// requests of the screen, read by the job between two buffers
+#[derive(Debug, Default)]
+struct JobControl {
+    paused: Mutex<bool>,
+    resumed: Condvar,
+    skip: AtomicBool,
+    abort: AtomicBool,
+    /// most bytes per second to copy, 0 for as fast as it goes
+    limit: AtomicU64,
+}
+
+impl JobControl {
+    /// block while paused, then tell the copy how to go on
+    fn flow(&self) -> CopyFlow {
+        let mut paused = self.paused.lock();
+        while *paused {
+            self.resumed.wait(&mut paused);
+        }
+        if self.abort.load(Ordering::Relaxed) {
+            CopyFlow::Abort
+        } else if self.skip.swap(false, Ordering::Relaxed) {
+            CopyFlow::SkipFile
+        } else {
+            CopyFlow::Continue
+        }
+    }
+
+    fn set_paused(&self, paused: bool) {
+        *self.paused.lock() = paused;
+        self.resumed.notify_all();
+    }
+}
+
+/// a copy, a move or a compression of the targets of a panel running on a background thread
+///
+/// The screen polls it for progress, the targets are counted first so the
+/// totals are known before anything is copied.
+#[derive(Debug)]
+pub struct Job {
+    pub kind: JobKind,
+    /// last progress sent, `None` while the targets are counted
+    pub progress: Option<CopyProgress>,
+    control: Arc<JobControl>,
+    events: Receiver<JobEvent>,
+    /// time spent in finished pauses, and the start of the current one
+    paused_for: Duration,
+    paused_at: Option<Instant>,
+}
+
+impl Job {
+    /// copy, move or extract `targets` into the directory node `dest`
+    ///
+    /// `resolution` answers every conflict, they are overwritten without one.
+    /// Each progress is also sent to `wake`, for the screen to poll the job.
+    pub fn start(
+        kind: JobKind,
+        targets: Vec<FsNodeRef>,
+        dest: FsNodeRef,
+        options: CopyOptions,
+        resolution: Option<ConflictResolution>,
+        wake: Option<Sender<AppEvent>>,
+    ) -> Job {
+        let job = Job::spawn(kind, wake, move |control, sender| run(kind, &targets, &dest, &options, resolution, control, sender));
+        job.set_limit(options.bandwidth_limit);
+        job
+    }
+
+    /// pack `targets` into the archive `name` of the directory node `dest`
+    pub fn compress(
+        targets: Vec<FsNodeRef>,
+        dest: FsNodeRef,
+        name: String,
+        options: CompressOptions,
+        wake: Option<Sender<AppEvent>>,
+    ) -> Job {
+        Job::spawn(JobKind::Compress, wake, move |control, sender| {
+            run_compress(&targets, &dest, &name, &options, control, sender)
+        })
+    }
+
+    /// overwrite `targets` of the local directory node `dir` and delete them
+    pub fn shred(targets: Vec<FsNodeRef>, dir: FsNodeRef, options: ShredOptions, wake: Option<Sender<AppEvent>>) -> Job {
+        Job::spawn(JobKind::Shred, wake, move |control, sender| run_shred(&targets, &dir, &options, control, sender))
+    }
+
+    /// cut the files `targets` into parts of `part_size` bytes in the directory node `dest`
+    pub fn split(targets: Vec<FsNodeRef>, dest: FsNodeRef, part_size: u64, wake: Option<Sender<AppEvent>>) -> Job {
+        Job::spawn(JobKind::Split, wake, move |control, sender| run_split(&targets, &dest, part_size, control, sender))
+    }
+
+    /// join the files whose parts `targets` are into the directory node `dest`, a part of each
+    pub fn join(targets: Vec<FsNodeRef>, dest: FsNodeRef, wake: Option<Sender<AppEvent>>) -> Job {
+        Job::spawn(JobKind::Join, wake, move |control, sender| run_join(&targets, &dest, control, sender))
+    }
+
+    /// run `body` on a background thread, its report is the last event
+    fn spawn(
+        kind: JobKind,
+        wake: Option<Sender<AppEvent>>,
+        body: impl FnOnce(&JobControl, &JobSender) -> JobReport + Send + 'static,
+    ) -> Job {
+        let (sender, events) = mpsc::channel();
+        let sender = JobSender {
+            events: sender,
+            tree: TreeSender::new(wake.clone()),
+            wake,
+        };
+        let control = Arc::new(JobControl::default());
+        let shared = Arc::clone(&control);
+        thread::spawn(move || {
+            let report = body(&shared, &sender);
+            sender.send(JobEvent::Done(report));
+        });
+        Job {
+            kind,
+            progress: None,
+            control,
+            events,
+            paused_for: Duration::ZERO,
+            paused_at: None,
+        }
+    }
+
+    /// take the events sent by the background thread, the report once it stopped
+    pub fn poll(&mut self) -> Option<JobReport> {
+        for event in self.events.try_iter() {
+            match event {
+                JobEvent::Progress(progress) => self.progress = Some(progress),
+                JobEvent::Done(report) => return Some(report),
+            }
+        }
+        None
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// hold the job after its current buffer, or let it go on
+    pub fn set_paused(&mut self, paused: bool) {
+        match (self.paused_at, paused) {
+            (None, true) => self.paused_at = Some(Instant::now()),
+            (Some(since), false) => {
+                self.paused_for += since.elapsed();
+                self.paused_at = None;
+            }
+            _ => return,
+        }
+        self.control.set_paused(paused);
+    }
+
+    /// most bytes per second the job copies, none when it goes as fast as it can
+    pub fn limit(&self) -> Option<u64> {
+        Some(self.control.limit.load(Ordering::Relaxed)).filter(|limit| *limit > 0)
+    }
+
+    /// change the bandwidth limit, the job keeps to the new one from its next buffer
+    pub fn set_limit(&self, limit: Option<u64>) {
+        self.control.limit.store(limit.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// give up the file being copied, it is removed
+    pub fn skip_file(&self) {
+        self.control.skip.store(true, Ordering::Relaxed);
+    }
+
+    /// stop the job, what is done so far stays
+    pub fn abort(&mut self) {
+        self.control.abort.store(true, Ordering::Relaxed);
+        self.set_paused(false);
+    }
+
+    /// time spent copying, pauses left out
+    pub fn elapsed(&self) -> Duration {
+        let Some(progress) = &self.progress else {
+            return Duration::ZERO;
+        };
+        let pause = self.paused_at.map(|since| since.elapsed()).unwrap_or_default();
+        progress.started.elapsed().saturating_sub(self.paused_for + pause)
+    }
+
+    /// average bytes per second since the start
+    pub fn speed(&self) -> u64 {
+        let seconds = self.elapsed().as_secs_f64();
+        match &self.progress {
+            Some(progress) if seconds > 0.0 => (progress.bytes_copied as f64 / seconds) as u64,
+            _ => 0,
+        }
+    }
+
+    /// estimated remaining time at the average speed
+    pub fn eta(&self) -> Option<Duration> {
+        let progress = self.progress.as_ref()?;
+        let speed = self.speed();
+        if speed == 0 {
+            return None;
+        }
+        let remaining = progress.total_bytes.saturating_sub(progress.bytes_copied);
+        Some(Duration::from_secs(remaining / speed))
+    }
+}
+
+/// the body of the background thread
+fn run(
+    kind: JobKind,
+    targets: &[FsNodeRef],
+    dest: &FsNodeRef,
+    options: &CopyOptions,
+    resolution: Option<ConflictResolution>,
+    control: &JobControl,
+    sender: &JobSender,
+) -> JobReport {
+    let usage = count(targets);
+    let mut state = CopyProgress::new(usage.iter().map(|(bytes, _)| bytes).sum(), usage.iter().map(|(_, files)| files).sum());
+    sender.send(JobEvent::Progress(state.clone()));
+    let mut progress = throttled(control, sender);
+
+    let mut conflicts = match resolution {
+        Some(resolution) => ConflictResolver::always(resolution),
+        None => ConflictResolver::overwrite(),
+    };
+    let (dest_vfs, dest_dir) = {
+        let dest = dest.read();
+        (Arc::clone(&dest.vfs), dest.path())
+    };
+    let mut created = Vec::new();
+    let mut moves = Vec::new();
+    let mut result = Ok(());
+    let mut unfinished = Vec::new();
+    for (index, (node, &(bytes, files))) in targets.iter().zip(&usage).enumerate() {
+        let (from_vfs, from, name) = {
+            let node = node.read();
+            (Arc::clone(&node.vfs), node.path(), node.name.clone())
+        };
+        // a replaced or appended target cannot be brought back, undo leaves it alone
+        let target = dest_dir.join(&*name);
+        let existed = dest_vfs.metadata(&target).is_ok();
+        let before = (state.bytes_copied, state.files_copied, state.files_skipped);
+        let mut updates = Vec::new();
+        let copied = match kind {
+            JobKind::Move => ops::move_node(node, dest, None, &mut conflicts, &mut state, &mut progress, &mut updates),
+            _ => ops::copy_node_into(node, dest, options, &mut conflicts, &mut state, &mut progress, &mut updates),
+        };
+        // each target shows up in the panels once done with
+        sender.update(updates);
+        // a rename copies nothing, its files are done or skipped at once
+        if kind == JobKind::Move && (state.bytes_copied, state.files_copied, state.files_skipped) == before {
+            match &copied {
+                Ok(Some(_)) => {
+                    state.bytes_copied += bytes;
+                    state.files_copied += files;
+                }
+                Ok(None) => {
+                    state.total_bytes = state.total_bytes.saturating_sub(bytes);
+                    state.total_files = state.total_files.saturating_sub(files);
+                }
+                Err(_) => {}
+            }
+            progress(&state);
+        }
+        let to = match copied {
+            Ok(Some(to)) => to,
+            Ok(None) => continue,
+            Err(err) => {
+                result = Err(err);
+                unfinished = targets[index..].to_vec();
+                break;
+            }
+        };
+        if existed && to == target {
+            continue;
+        }
+        match kind {
+            JobKind::Move if !vfs::same_vfs(&from_vfs, &dest_vfs) || from != to => moves.push(Moved {
+                from_vfs,
+                from,
+                to_vfs: Arc::clone(&dest_vfs),
+                to,
+            }),
+            JobKind::Move => {}
+            _ => created.push(to),
+        }
+    }
+    let operation = match kind {
+        JobKind::Move => Operation::Move { moves },
+        _ => Operation::Copy { vfs: dest_vfs, created },
+    };
+    JobReport {
+        kind,
+        operation,
+        files_copied: state.files_copied,
+        files_skipped: state.files_skipped,
+        lost: state.lost,
+        notes: Vec::new(),
+        result,
+        unfinished,
+        dest: Arc::clone(dest),
+        resolution,
+    }
+}
+
+/// the body of the background thread of a compression
+fn run_compress(
+    targets: &[FsNodeRef],
+    dest: &FsNodeRef,
+    name: &str,
+    options: &CompressOptions,
+    control: &JobControl,
+    sender: &JobSender,
+) -> JobReport {
+    let usage = count(targets);
+    let mut state = CopyProgress::new(usage.iter().map(|(bytes, _)| bytes).sum(), usage.iter().map(|(_, files)| files).sum());
+    sender.send(JobEvent::Progress(state.clone()));
+    let mut progress = throttled(control, sender);
+
+    let (dest_vfs, archive) = {
+        let dest = dest.read();
+        (Arc::clone(&dest.vfs), dest.path().join(name))
+    };
+    let result = ops::compress(targets, &dest_vfs, &archive, options, &mut state, &mut progress)
+        .and_then(|()| dest_vfs.metadata(&archive));
+    let (created, result) = match result {
+        Ok(entry) => {
+            sender.update(vec![TreeUpdate::Add {
+                directory: Arc::clone(dest),
+                node: FsNode::from_entry(entry, Arc::clone(&dest_vfs)),
+            }]);
+            (vec![archive], Ok(()))
+        }
+        Err(err) => (Vec::new(), Err(err)),
+    };
+    JobReport {
+        kind: JobKind::Compress,
+        operation: Operation::Copy { vfs: dest_vfs, created },
+        files_copied: state.files_copied,
+        files_skipped: 0,
+        lost: Vec::new(),
+        notes: Vec::new(),
+        result,
+        unfinished: Vec::new(),
+        dest: Arc::clone(dest),
+        resolution: None,
+    }
+}
+
+/// the body of the background thread of a shred
+fn run_shred(targets: &[FsNodeRef], dir: &FsNodeRef, options: &ShredOptions, control: &JobControl, sender: &JobSender) -> JobReport {
+    let usage = count(targets);
+    let bytes = usage.iter().map(|(bytes, _)| options.written(*bytes)).sum();
+    let mut state = CopyProgress::new(bytes, usage.iter().map(|(_, files)| files).sum());
+    sender.send(JobEvent::Progress(state.clone()));
+    let mut progress = throttled(control, sender);
+
+    let mut result = Ok(());
+    let mut unfinished = Vec::new();
+    for (index, node) in targets.iter().enumerate() {
+        let path = node.read().path();
+        match ops::shred_path(&path, options, &mut state, &mut progress) {
+            Ok(true) => {
+                let parent = node.read().parent();
+                if let Some(parent) = parent {
+                    sender.update(vec![TreeUpdate::Remove { directory: parent, path }]);
+                }
+            }
+            Ok(false) => {}
+            Err(err) => {
+                result = Err(err);
+                unfinished = targets[index..].to_vec();
+                break;
+            }
+        }
+    }
+    JobReport {
+        kind: JobKind::Shred,
+        // nothing of it can be undone
+        operation: Operation::Copy { vfs: vfs::local(), created: Vec::new() },
+        files_copied: state.files_copied,
+        files_skipped: state.files_skipped,
+        lost: Vec::new(),
+        notes: Vec::new(),
+        result,
+        unfinished,
+        dest: Arc::clone(dir),
+        resolution: None,
+    }
+}
+
+/// the body of the background thread of a split
+fn run_split(targets: &[FsNodeRef], dest: &FsNodeRef, part_size: u64, control: &JobControl, sender: &JobSender) -> JobReport {
+    let usage = count(targets);
+    let mut state = CopyProgress::new(usage.iter().map(|(bytes, _)| bytes).sum(), usage.iter().map(|(_, files)| files).sum());
+    sender.send(JobEvent::Progress(state.clone()));
+    let mut progress = throttled(control, sender);
+
+    let (dest_vfs, dest_dir) = {
+        let dest = dest.read();
+        (Arc::clone(&dest.vfs), dest.path())
+    };
+    let mut created = Vec::new();
+    let mut result = Ok(());
+    let mut unfinished = Vec::new();
+    for (index, node) in targets.iter().enumerate() {
+        let (from_vfs, from) = {
+            let node = node.read();
+            (Arc::clone(&node.vfs), node.path())
+        };
+        match ops::split_file(from_vfs.as_ref(), &from, dest_vfs.as_ref(), &dest_dir, part_size, &mut state, &mut progress) {
+            Ok(written) => created.extend(written),
+            Err(err) => {
+                result = Err(err);
+                unfinished = targets[index..].to_vec();
+                break;
+            }
+        }
+    }
+    sender.update(entry_updates(dest, &created));
+    JobReport {
+        kind: JobKind::Split,
+        operation: Operation::Copy { vfs: dest_vfs, created },
+        files_copied: state.files_copied,
+        files_skipped: state.files_skipped,
+        lost: Vec::new(),
+        notes: Vec::new(),
+        result,
+        unfinished,
+        dest: Arc::clone(dest),
+        resolution: None,
+    }
+}
+
+/// the body of the background thread of a join
+fn run_join(targets: &[FsNodeRef], dest: &FsNodeRef, control: &JobControl, sender: &JobSender) -> JobReport {
+    // the parts of each file are counted, not only the one picked
+    let usage: Vec<u64> = targets
+        .iter()
+        .map(|node| {
+            let node = node.read();
+            ops::parts_of(node.vfs.as_ref(), &node.path()).map(|(_, parts)| parts.iter().map(|(_, size)| size).sum()).unwrap_or(0)
+        })
+        .collect();
+    let mut state = CopyProgress::new(usage.iter().sum(), targets.len() as u64);
+    sender.send(JobEvent::Progress(state.clone()));
+    let mut progress = throttled(control, sender);
+
+    let (dest_vfs, dest_dir) = {
+        let dest = dest.read();
+        (Arc::clone(&dest.vfs), dest.path())
+    };
+    let mut created = Vec::new();
+    let mut notes = Vec::new();
+    let mut result = Ok(());
+    let mut unfinished = Vec::new();
+    for (index, node) in targets.iter().enumerate() {
+        let (from_vfs, from) = {
+            let node = node.read();
+            (Arc::clone(&node.vfs), node.path())
+        };
+        match ops::join_parts(from_vfs.as_ref(), &from, dest_vfs.as_ref(), &dest_dir, &mut state, &mut progress) {
+            Ok(Some((joined, manifest))) => {
+                let name = joined.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                notes.push(match manifest {
+                    Some(manifest) => format!("{name} matches {}", manifest.file_name().unwrap_or_default().to_string_lossy()),
+                    None => format!("{name} is not checked, its parts have no manifest"),
+                });
+                created.push(joined);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                result = Err(err);
+                unfinished = targets[index..].to_vec();
+                break;
+            }
+        }
+    }
+    sender.update(entry_updates(dest, &created));
+    JobReport {
+        kind: JobKind::Join,
+        operation: Operation::Copy { vfs: dest_vfs, created },
+        files_copied: state.files_copied,
+        files_skipped: state.files_skipped,
+        lost: Vec::new(),
+        notes,
+        result,
+        unfinished,
+        dest: Arc::clone(dest),
+        resolution: None,
+    }
+}
+
+/// the updates showing the files a job wrote in the directory node `dest`
+fn entry_updates(dest: &FsNodeRef, paths: &[PathBuf]) -> Vec<TreeUpdate> {
+    let vfs = Arc::clone(&dest.read().vfs);
+    paths
+        .iter()
+        .filter_map(|path| vfs.metadata(path).ok())
+        .map(|entry| TreeUpdate::Add {
+            directory: Arc::clone(dest),
+            node: FsNode::from_entry(entry, Arc::clone(&vfs)),
+        })
+        .collect()
+}
+
+/// bytes and files of each target
+fn count(targets: &[FsNodeRef]) -> Vec<(u64, u64)> {
+    targets
+        .iter()
+        .map(|node| {
+            let node = node.read();
+            (disk_usage(node.vfs.as_ref(), &node.path()), file_count(node.vfs.as_ref(), &node.path()))
+        })
+        .collect()
+}
+
+/// the progress callback of a job, sending at most one event per interval and keeping to the bandwidth limit
+fn throttled<'a>(control: &'a JobControl, sender: &'a JobSender) -> impl FnMut(&CopyProgress) -> CopyFlow + 'a {
+    let mut last_sent = Some(Instant::now());
+    let mut pace = Pace::new(0, 0);
+    move |state: &CopyProgress| {
+        // a paused job shows where it stopped
+        let paused = *control.paused.lock();
+        if paused || last_sent.is_none_or(|sent| sent.elapsed() >= PROGRESS_INTERVAL) {
+            sender.send(JobEvent::Progress(state.clone()));
+            last_sent = Some(Instant::now());
+        }
+        pace.wait(control, state.bytes_copied);
+        let flow = control.flow();
+        // the time spent paused is no credit for going faster afterwards
+        if paused {
+            pace = Pace::new(state.bytes_copied, pace.limit);
+        }
+        flow
+    }
+}
+
+/// keeps a job to its bandwidth limit, counted from the last change of the limit
+struct Pace {
+    since: Instant,
+    /// bytes copied when the count started
+    from: u64,
+    /// limit the count is made for, 0 for none
+    limit: u64,
+}
+
+impl Pace {
+    fn new(from: u64, limit: u64) -> Pace {
+        Pace {
+            since: Instant::now(),
+            from,
+            limit,
+        }
+    }
+
+    /// sleep until `copied` bytes are no more than the limit allows
+    fn wait(&mut self, control: &JobControl, copied: u64) {
+        let limit = control.limit.load(Ordering::Relaxed);
+        // a skipped file takes its bytes back, the count starts again too
+        if limit != self.limit || copied < self.from {
+            *self = Pace::new(copied, limit);
+            return;
+        }
+        if limit == 0 {
+            return;
+        }
+        let due = Duration::from_secs_f64((copied - self.from) as f64 / limit as f64);
+        // in short naps, an abort or a new limit is not kept waiting
+        while control.limit.load(Ordering::Relaxed) == limit && !control.abort.load(Ordering::Relaxed) {
+            let left = due.saturating_sub(self.since.elapsed());
+            if left.is_zero() {
+                break;
+            }
+            thread::sleep(left.min(PROGRESS_INTERVAL));
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::io;
+use std::path::PathBuf;
+
+use crate::ops;
+use crate::rename::{self, Rename};
+use crate::trash::{Trash, TrashItem};
+use crate::vfs::VfsRef;
+
+/// what a file operation did, with what is needed to reverse it
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// copies made where nothing existed before
+    Copy { vfs: VfsRef, created: Vec<PathBuf> },
+    /// entries moved from one backend path to another
+    Move { moves: Vec<Moved> },
+    /// names changed in place by the multi-rename
+    Rename { vfs: VfsRef, renames: Vec<Rename> },
+    /// entries moved to the trash, with the trash each one went to
+    Trash { items: Vec<(Trash, TrashItem)> },
+    /// directories created, parents first
+    Mkdir { vfs: VfsRef, created: Vec<PathBuf> },
+}
+
+/// one entry of a move
+#[derive(Debug, Clone)]
+pub struct Moved {
+    pub from_vfs: VfsRef,
+    pub from: PathBuf,
+    pub to_vfs: VfsRef,
+    pub to: PathBuf,
+}
+
+impl Operation {
+    /// short description for messages
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Copy { created, .. } => format!("copy of {} entries", created.len()),
+            Operation::Move { moves } => format!("move of {} entries", moves.len()),
+            Operation::Rename { renames, .. } => format!("rename of {} entries", renames.len()),
+            Operation::Trash { items, .. } => format!("deletion of {} entries", items.len()),
+            Operation::Mkdir { created, .. } => match created.last() {
+                Some(path) => format!("creation of {}", path.display()),
+                None => String::from("creation of nothing"),
+            },
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Operation::Copy { created, .. } | Operation::Mkdir { created, .. } => created.is_empty(),
+            Operation::Move { moves } => moves.is_empty(),
+            Operation::Rename { renames, .. } => renames.iter().all(Rename::is_unchanged),
+            Operation::Trash { items, .. } => items.is_empty(),
+        }
+    }
+
+    /// reverse the operation, stopping at the first failure
+    ///
+    /// Nothing is overwritten: an entry put back where something exists
+    /// again makes the undo fail.
+    fn undo(&self) -> io::Result<()> {
+        match self {
+            Operation::Copy { vfs, created } => {
+                for path in created.iter().rev() {
+                    ops::remove_path(vfs, path)?;
+                }
+            }
+            Operation::Move { moves } => {
+                for moved in moves.iter().rev() {
+                    if moved.from_vfs.metadata(&moved.from).is_ok() {
+                        return Err(already_exists(&moved.from));
+                    }
+                    ops::move_path(&moved.to_vfs, &moved.to, &moved.from_vfs, &moved.from)?;
+                }
+            }
+            Operation::Rename { vfs, renames } => {
+                let reversed: Vec<Rename> = renames
+                    .iter()
+                    .map(|rename| Rename {
+                        from: rename.to.clone(),
+                        to: rename.from.clone(),
+                        conflict: None,
+                    })
+                    .collect();
+                rename::execute(vfs, &reversed)?;
+            }
+            Operation::Trash { items } => {
+                for (trash, item) in items.iter().rev() {
+                    trash.restore(&item.name)?;
+                }
+            }
+            Operation::Mkdir { vfs, created } => {
+                // only empty directories go, whatever was put in them stays
+                for path in created.iter().rev() {
+                    vfs.remove(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn already_exists(path: &std::path::Path) -> io::Error {
+    io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists again", path.display()))
+}
+
+/// operations done since startup, the most recent last
+#[derive(Debug, Default)]
+pub struct Journal {
+    operations: Vec<Operation>,
+}
+
+/// operations remembered, older ones cannot be undone
+const JOURNAL_SIZE: usize = 100;
+
+impl Journal {
+    pub fn new() -> Journal {
+        Journal::default()
+    }
+
+    /// remember an operation, operations that did nothing are ignored
+    pub fn record(&mut self, operation: Operation) {
+        if operation.is_empty() {
+            return;
+        }
+        if self.operations.len() == JOURNAL_SIZE {
+            self.operations.remove(0);
+        }
+        self.operations.push(operation);
+    }
+
+    pub fn last(&self) -> Option<&Operation> {
+        self.operations.last()
+    }
+
+    /// reverse the most recent operation, returns what was undone
+    ///
+    /// A failed undo is dropped from the journal as well, it may have been
+    /// partly done and trying it again would not know where it stopped.
+    pub fn undo_last_operation(&mut self) -> io::Result<Option<String>> {
+        let Some(operation) = self.operations.pop() else {
+            return Ok(None);
+        };
+        operation.undo()?;
+        Ok(Some(operation.describe()))
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// service the secrets are filed under in the keyring
+const SERVICE: &str = "midday-commander";
+
+/// keep `secret` in the keyring of the system under `account`, replacing the one there
+///
+/// The keyring is reached through `secret-tool` of libsecret, or `security`
+/// on macOS.
+pub fn store(account: &str, secret: &str) -> io::Result<()> {
+    if cfg!(target_os = "macos") {
+        // the command goes through stdin of `security -i`, not the command line others can read
+        if secret.contains(['\n', '\r']) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the keyring takes no line breaks in a password"));
+        }
+        let mut child = Command::new("security")
+            .arg("-i")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(missing_tool)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            let line = format!("add-generic-password -U -s {} -a {} -w {}\n", quote(SERVICE), quote(account), quote(secret));
+            stdin.write_all(line.as_bytes())?;
+        }
+        let status = child.wait()?;
+        return check(status.success(), "security could not store the password");
+    }
+    if cfg!(windows) {
+        return Err(crate::vfs::unsupported("keyring"));
+    }
+    let label = format!("Midday Commander: {account}");
+    let mut child = Command::new("secret-tool")
+        .args(["store", "--label", &label, "service", SERVICE, "account", account])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(missing_tool)?;
+    // the secret goes through stdin, not the command line others can read
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(secret.as_bytes())?;
+    }
+    let status = child.wait()?;
+    check(status.success(), "secret-tool could not store the password")
+}
+
+/// the secret of `account`, None if the keyring has none
+pub fn lookup(account: &str) -> io::Result<Option<String>> {
+    let output = if cfg!(target_os = "macos") {
+        Command::new("security")
+            .args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"])
+            .stderr(Stdio::null())
+            .output()
+    } else if cfg!(windows) {
+        return Err(crate::vfs::unsupported("keyring"));
+    } else {
+        Command::new("secret-tool")
+            .args(["lookup", "service", SERVICE, "account", account])
+            .stderr(Stdio::null())
+            .output()
+    }
+    .map_err(missing_tool)?;
+    // both tools fail without printing anything for a missing entry
+    if !output.status.success() || output.stdout.is_empty() {
+        return Ok(None);
+    }
+    let secret = String::from_utf8_lossy(&output.stdout);
+    let secret = match cfg!(target_os = "macos") {
+        true => secret.trim_end_matches('\n'),
+        false => &secret,
+    };
+    Ok(Some(secret.to_string()))
+}
+
+/// forget the secret of `account`, there being none is fine
+pub fn delete(account: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args(["delete-generic-password", "-s", SERVICE, "-a", account]);
+        command
+    } else if cfg!(windows) {
+        return Err(crate::vfs::unsupported("keyring"));
+    } else {
+        let mut command = Command::new("secret-tool");
+        command.args(["clear", "service", SERVICE, "account", account]);
+        command
+    };
+    command.stdout(Stdio::null()).stderr(Stdio::null()).status().map(|_| ()).map_err(missing_tool)
+}
+
+/// a word of a `security -i` command line, quoted for its parser
+fn quote(word: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in word.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn missing_tool(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::NotFound => io::Error::new(io::ErrorKind::NotFound, "no keyring, secret-tool of libsecret is not installed"),
+        _ => err,
+    }
+}
+
+fn check(success: bool, message: &str) -> io::Result<()> {
+    match success {
+        true => Ok(()),
+        false => Err(io::Error::other(message.to_string())),
+    }
+}
@@ -0,0 +1,54 @@
+pub mod app;
+pub mod associations;
+pub mod clipboard;
+pub mod commands;
+pub mod compare;
+pub mod complete;
+pub mod connections;
+pub mod criteria;
+pub mod config;
+pub mod editor;
+pub mod escalation;
+pub mod event;
+pub mod export;
+pub mod filetype;
+pub mod filter;
+pub mod format;
+pub mod fsinfo;
+pub mod fs_error;
+pub mod fs_metadata;
+pub mod fs_node;
+pub mod gitignore;
+pub mod harness;
+pub mod hash;
+pub mod highlight;
+pub mod help;
+pub mod history;
+pub mod hotlist;
+pub mod info;
+pub mod job;
+pub mod journal;
+pub mod keyring;
+pub mod mouse;
+pub mod opener;
+pub mod ops;
+pub mod panel;
+pub mod paths;
+pub mod rename;
+pub mod scan;
+pub mod search;
+pub mod session;
+pub mod shell;
+pub mod sort;
+pub mod subshell;
+pub mod sync;
+pub mod trash;
+pub mod tree;
+pub mod ui;
+pub mod usage;
+pub mod user_menu;
+pub mod vfs;
+pub mod viewer;
+pub mod watcher;
+#[cfg(windows)]
+pub mod windows;
@@ -1,5 +1,492 @@
-mod fs_node;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
 
-fn main() {
-    println!("Hello, world!");
+use crossterm::event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+};
+use midday_commander::app::{App, Step};
+use midday_commander::associations::Associations;
+use midday_commander::commands::{Command, CommandOutcome, Keymap};
+use midday_commander::config::{Config, PreserveConfig};
+use midday_commander::event::EventLoop;
+use midday_commander::export::{self, ExportFormat, ExportOptions};
+use midday_commander::fs_node::{create_node_in, FsNode};
+use midday_commander::hash::HashAlgorithm;
+use midday_commander::opener;
+use midday_commander::panel::PanelManager;
+use midday_commander::paths;
+use midday_commander::rename::{self, RenameJournal};
+use midday_commander::session::Session;
+use midday_commander::shell;
+use midday_commander::sort;
+use midday_commander::subshell::Subshell;
+use midday_commander::editor;
+use midday_commander::escalation::{self, Escalation, Privileged};
+use midday_commander::ui::theme::Theme;
+use midday_commander::vfs::{self, ExtHelpers, S3Options};
+use midday_commander::user_menu::UserMenu;
+use midday_commander::watcher::TreeWatcher;
+use ratatui::layout::Rect;
+
+fn main() -> io::Result<()> {
+    sort::init_collation();
+    let mut argv = std::env::args().skip(1).peekable();
+    if argv.peek().is_some_and(|arg| arg == "export") {
+        return export_tree(argv.skip(1).collect());
+    }
+    let args = parse_args()?;
+    let config_path = args.config.or_else(Config::default_path);
+    let mut config = match &config_path {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    // renames cut off by a crash are put back before the panels list their directories
+    let recovery = RenameJournal::default_dir().map(|dir| rename::recover(&dir)).unwrap_or_default();
+    // an unreadable session is not worth refusing to start
+    let session_path = Session::default_path();
+    let saved = session_path.as_deref().and_then(|path| Session::load(path).ok()).unwrap_or_default();
+    let mut locations = args.locations.iter().map(String::as_str);
+    let (left, left_tabs) = saved.left.open(locations.next(), &config.panel)?;
+    let (right, right_tabs) = saved.right.open(locations.next(), &config.panel)?;
+    let mut manager = PanelManager::new(left, right);
+    manager.left_tabs = left_tabs;
+    manager.right_tabs = right_tabs;
+    config.preserve.apply(&mut manager.copy_options);
+    manager.copy_options.verify = config.verify_copies;
+    manager.shred_options = config.shred;
+    manager.copy_options.bandwidth_limit = config.bandwidth_limit.filter(|kib| *kib > 0).map(|kib| kib.saturating_mul(1024));
+    manager.gitignore = config.gitignore;
+    manager.cdpath = paths::cdpath(&config.cdpath);
+    manager.format = config.format.clone();
+    manager.confirm = config.confirm.clone();
+    manager.escalation = Escalation::new(config.escalation.as_deref());
+    if let Some(path) = Associations::default_path() {
+        manager.associations = Associations::load(&path).unwrap_or_else(|err| {
+            eprintln!("{err}, using the built-in associations");
+            Associations::default()
+        });
+    }
+    S3Options::from_config(&config.s3).install();
+    if let Some(path) = ExtHelpers::default_path() {
+        ExtHelpers::load(&path)
+            .unwrap_or_else(|err| {
+                eprintln!("{err}, using the built-in archive helpers");
+                ExtHelpers::default()
+            })
+            .install();
+    }
+    if let Some(path) = UserMenu::default_path() {
+        manager.user_menu = UserMenu::load(&path).unwrap_or_else(|err| {
+            eprintln!("{err}, using the built-in user menu");
+            UserMenu::default()
+        });
+    }
+    // the file manager still works without live refresh
+    let watcher = TreeWatcher::new().ok();
+    // a broken skin should not keep the file manager from starting
+    let mut theme = Theme::named(&config.theme).unwrap_or_else(|err| {
+        eprintln!("{err}, using the classic theme");
+        Theme::classic()
+    });
+
+    let mut keymap = Keymap::default();
+    if !config.delete_to_trash {
+        keymap.bind(KeyCode::F(8), KeyModifiers::NONE, Command::DeletePermanently);
+    }
+    let mut app = App::new(manager, keymap);
+    app.message = recovery.summary();
+
+    let mut terminal = init_terminal(config.mouse)?;
+    let result = run(&mut terminal, &mut app, &mut config, &mut theme, watcher);
+    restore_terminal();
+
+    // the last panel settings become the defaults of the next session
+    let manager = &app.manager;
+    let active = manager.active();
+    config.panel.sort = active.sort_mode;
+    config.panel.show_hidden = active.show_hidden;
+    config.panel.columns = active.columns.clone();
+    config.panel.icons = active.icons;
+    config.panel.layout = active.layout;
+    config.preserve = PreserveConfig::from_options(&manager.copy_options);
+    config.verify_copies = manager.copy_options.verify;
+    config.bandwidth_limit = manager.copy_options.bandwidth_limit.map(|bytes| (bytes / 1024).max(1));
+    if let Some(path) = &session_path
+        && let Err(err) = Session::capture(manager).save(path)
+    {
+        eprintln!("could not save {}: {err}", path.display());
+    }
+    if let Some(path) = &config_path
+        && let Err(err) = config.save(path)
+    {
+        eprintln!("could not save {}: {err}", path.display());
+    }
+    result
+}
+
+struct Args {
+    config: Option<PathBuf>,
+    /// left and right locations, local paths, sftp or s3 urls
+    locations: Vec<String>,
+}
+
+fn parse_args() -> io::Result<Args> {
+    let mut args = Args {
+        config: None,
+        locations: Vec::new(),
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--config" {
+            let path = iter
+                .next()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "--config needs a file"))?;
+            args.config = Some(PathBuf::from(path));
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            args.config = Some(PathBuf::from(path));
+        } else {
+            args.locations.push(arg);
+        }
+    }
+    Ok(args)
+}
+
+/// `export [--format json|csv] [--hash <algorithm>] [--shallow] [--output <file>] [<location>]`
+///
+/// Scans a directory without starting the file manager and writes the tree
+/// to the output file or stdout, the current directory by default. Shallow
+/// exports only hold the entries of the directory itself.
+fn export_tree(args: Vec<String>) -> io::Result<()> {
+    let invalid = |text: String| io::Error::new(io::ErrorKind::InvalidInput, text);
+    let mut options = ExportOptions::default();
+    let mut output = None;
+    let mut location = None;
+    let mut shallow = false;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| iter.next().ok_or_else(|| invalid(format!("{name} needs a value")));
+        match arg.as_str() {
+            "--format" => {
+                let text = value("--format")?;
+                options.format = ExportFormat::parse(&text).ok_or_else(|| invalid(format!("unknown format {text}, json or csv")))?;
+            }
+            "--hash" => {
+                let text = value("--hash")?;
+                options.hash = Some(HashAlgorithm::parse(&text).ok_or_else(|| invalid(format!("unknown checksum {text}")))?);
+            }
+            "--output" => output = Some(PathBuf::from(value("--output")?)),
+            "--shallow" => shallow = true,
+            _ if location.is_none() && !arg.starts_with("--") => location = Some(arg),
+            _ => return Err(invalid(format!("unexpected argument {arg}"))),
+        }
+    }
+    let (vfs, path) = vfs::open_location(location.as_deref().unwrap_or("."))?;
+    let path = match vfs::is_local(&vfs) {
+        true => paths::canonicalize(&path)?,
+        false => path,
+    };
+    let node = match shallow {
+        true => {
+            let mut node = FsNode::from_entry(vfs.metadata(&path)?, Arc::clone(&vfs));
+            node.load_children()?;
+            node
+        }
+        false => create_node_in(&vfs, &path)?,
+    };
+    match output {
+        Some(output) => {
+            let mut file = io::BufWriter::new(std::fs::File::create(output)?);
+            export::export(&node, options, &mut file)?;
+            file.flush()
+        }
+        None => export::export(&node, options, &mut io::stdout().lock()),
+    }
+}
+
+fn run(
+    terminal: &mut ratatui::DefaultTerminal,
+    app: &mut App,
+    config: &mut Config,
+    theme: &mut Theme,
+    mut watcher: Option<TreeWatcher>,
+) -> io::Result<()> {
+    let mut events = EventLoop::new();
+    if let Some(watcher) = &watcher {
+        events.forward_changes(watcher.subscribe());
+    }
+    app.manager.events = Some(events.sender());
+    // interactive shell behind Ctrl+O, started the first time it is needed
+    let mut subshell: Option<Subshell> = None;
+
+    loop {
+        if let Some(watcher) = watcher.as_mut() {
+            // virtual listings are not directories, there is nothing to watch
+            let watched: Vec<_> = [&app.manager.left, &app.manager.right]
+                .into_iter()
+                .filter(|panel| panel.listing.is_none())
+                .map(|panel| Arc::clone(&panel.root))
+                .collect();
+            let _ = watcher.set_watched(&watched);
+        }
+        app.update();
+        terminal.draw(|frame| app.draw(frame, theme))?;
+
+        // results keep streaming in while a search or a remote listing runs, they are picked up on each tick
+        let timeout = if app.is_busy() { 50 } else { 250 };
+        let event = events.next(Duration::from_millis(timeout))?;
+        let size = terminal.size()?;
+        let mut step = app.handle(event, Rect::new(0, 0, size.width, size.height), config, theme, watcher.as_ref());
+        // what needs the terminal is done here, it may lead to another outcome
+        loop {
+            step = match step {
+                Step::Continue => break,
+                Step::Quit => return Ok(()),
+                Step::Subshell(command) => {
+                    let outcome = live_outcome(enter_subshell(terminal, config.mouse, &mut subshell, &mut app.manager, command.as_deref()));
+                    app.carry_out(outcome, config, theme)
+                }
+                Step::Terminal(outcome) => {
+                    hand_over(terminal, app, config, outcome);
+                    Step::Continue
+                }
+            }
+        }
+    }
+}
+
+/// carry out an outcome giving the terminal to another program
+fn hand_over(terminal: &mut ratatui::DefaultTerminal, app: &mut App, config: &Config, outcome: CommandOutcome) {
+    match outcome {
+        CommandOutcome::View(opened) => {
+            if let (Some(command), Some(path)) = (&config.viewer, opened.local_path())
+                && let Err(err) = run_external(terminal, config.mouse, command, &path)
+            {
+                app.message = Some(format!("{command}: {err}"));
+            }
+        }
+        CommandOutcome::Run { command, dir } => {
+            if let Err(err) = run_shell_line(terminal, config.mouse, &command, &dir) {
+                app.message = Some(format!("{command}: {err}"));
+            }
+            let _ = app.manager.left.reload();
+            let _ = app.manager.right.reload();
+        }
+        CommandOutcome::Escalate(operation) => {
+            let listing = matches!(operation, Privileged::List { .. });
+            if let Err(err) = escalate(terminal, config.mouse, &mut app.manager, &operation) {
+                app.message = Some(err.to_string());
+            }
+            // a listing read as root cannot be read again
+            if !listing {
+                let _ = app.manager.left.reload();
+                let _ = app.manager.right.reload();
+            }
+        }
+        CommandOutcome::Open(path) => {
+            let command = opener::opener(config.open.command.as_deref());
+            let result = match config.open.terminal {
+                true => run_external(terminal, config.mouse, command, &path),
+                false => opener::spawn(command, &path, config.open.wait),
+            };
+            if let Err(err) = result {
+                app.message = Some(format!("{command}: {err}"));
+            }
+            if config.open.terminal || config.open.wait {
+                let _ = app.manager.active_mut().reload();
+            }
+        }
+        CommandOutcome::Edit(opened) => {
+            if let (Some(command), Some(path)) = (editor::external_editor(config.editor.as_deref()), opened.local_path()) {
+                if let Err(err) = run_external(terminal, config.mouse, &command, &path) {
+                    app.message = Some(format!("{command}: {err}"));
+                }
+                let _ = app.manager.active_mut().reload();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// set up the terminal for the panels, reporting the mouse if asked
+fn init_terminal(mouse: bool) -> io::Result<ratatui::DefaultTerminal> {
+    let terminal = ratatui::init();
+    // a paste comes as one event, not as keys that could run commands
+    crossterm::execute!(io::stdout(), EnableBracketedPaste)?;
+    if mouse {
+        crossterm::execute!(io::stdout(), EnableMouseCapture)?;
+    }
+    Ok(terminal)
+}
+
+/// give the terminal back as it was, for good or to a program
+fn restore_terminal() {
+    let _ = crossterm::execute!(io::stdout(), DisableMouseCapture, DisableBracketedPaste);
+    ratatui::restore();
+}
+
+/// run a program on a file with the terminal handed over to it
+fn run_external(terminal: &mut ratatui::DefaultTerminal, mouse: bool, command: &str, path: &Path) -> io::Result<()> {
+    let mut words = command.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+    hand_terminal(terminal, mouse, process::Command::new(program).args(words).arg(path))
+}
+
+/// run a command line with the user's shell in `dir`, the terminal handed over to it
+fn run_shell_line(terminal: &mut ratatui::DefaultTerminal, mouse: bool, command: &str, dir: &Path) -> io::Result<()> {
+    hand_terminal(terminal, mouse, process::Command::new(shell::user_shell()).arg("-c").arg(command).current_dir(dir))
+}
+
+/// run an operation refused for lack of rights again as root
+///
+/// A directory read as root is shown by the active panel like search results.
+fn escalate(terminal: &mut ratatui::DefaultTerminal, mouse: bool, manager: &mut PanelManager, operation: &Privileged) -> io::Result<()> {
+    let Some(escalation) = manager.escalation.clone() else {
+        return Ok(());
+    };
+    let command = escalation.command_line(operation)?;
+    let panel = manager.active();
+    let here = panel.vfs().local_path(&panel.current_path()).unwrap_or_else(|| PathBuf::from("/"));
+    match operation {
+        Privileged::List { dir } => {
+            let output = capture_shell_line(terminal, mouse, &command, &here)?;
+            let entries = escalation::parse_listing(&output)?;
+            let title = format!("{} read as root", dir.display());
+            manager.active_mut().panelize(&title, dir, entries)
+        }
+        _ => run_shell_line(terminal, mouse, &command, &here),
+    }
+}
+
+/// leave the screen to a shell command line and read what it writes, its prompts still reach the user
+fn capture_shell_line(terminal: &mut ratatui::DefaultTerminal, mouse: bool, command: &str, dir: &Path) -> io::Result<Vec<u8>> {
+    restore_terminal();
+    let output = process::Command::new(shell::user_shell())
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .stdout(process::Stdio::piped())
+        .spawn()
+        .and_then(process::Child::wait_with_output);
+    *terminal = init_terminal(mouse)?;
+    terminal.clear()?;
+    let output = output?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("exited with {}", output.status)));
+    }
+    Ok(output.stdout)
+}
+
+/// leave the screen to a program until it exits, a failure is an error
+fn hand_terminal(terminal: &mut ratatui::DefaultTerminal, mouse: bool, program: &mut process::Command) -> io::Result<()> {
+    restore_terminal();
+    let status = program.status();
+    *terminal = init_terminal(mouse)?;
+    terminal.clear()?;
+    let status = status?;
+    if !status.success() {
+        return Err(io::Error::other(format!("exited with {status}")));
+    }
+    Ok(())
+}
+
+/// hand the terminal to the persistent shell until Ctrl+O, `command` is typed into it first
+///
+/// The shell is moved to the directory of the active panel before, and the
+/// panel follows the directory the shell is left in.
+fn enter_subshell(
+    terminal: &mut ratatui::DefaultTerminal,
+    mouse: bool,
+    subshell: &mut Option<Subshell>,
+    manager: &mut PanelManager,
+    command: Option<&str>,
+) -> io::Result<CommandOutcome> {
+    let panel = manager.active();
+    let local = panel.vfs().local_path(&panel.current_path());
+    if command.is_some() && local.is_none() {
+        return Ok(CommandOutcome::Message(String::from("commands only run in local directories")));
+    }
+    let (columns, rows) = crossterm::terminal::size()?;
+    if subshell.as_mut().is_some_and(|live| !live.is_alive()) {
+        *subshell = None;
+    }
+    let live = match subshell {
+        Some(live) => live,
+        None => {
+            let home = std::env::var_os("HOME").map(PathBuf::from);
+            let dir = local.clone().or(home).unwrap_or_else(|| PathBuf::from("/"));
+            subshell.insert(Subshell::spawn(&dir, columns, rows)?)
+        }
+    };
+    // a remote directory has no place in the shell, it stays where it was
+    if let Some(dir) = &local {
+        live.sync_dir(dir)?;
+    }
+    live.resize(columns, rows)?;
+    if let Some(command) = command {
+        live.send_command(command)?;
+    }
+
+    restore_terminal();
+    crossterm::terminal::enable_raw_mode()?;
+    let result = pass_through(live);
+    let _ = crossterm::terminal::disable_raw_mode();
+    *terminal = init_terminal(mouse)?;
+    terminal.clear()?;
+    result?;
+
+    let cwd = live.cwd();
+    let alive = live.is_alive();
+    if !alive {
+        *subshell = None;
+    }
+    if let (Some(cwd), Some(dir)) = (cwd, &local)
+        && &cwd != dir
+    {
+        manager.active_mut().change_directory_in(vfs::local(), &cwd)?;
+    }
+    // whatever ran may have changed the files of both panels
+    let _ = manager.left.reload();
+    let _ = manager.right.reload();
+    match alive {
+        true => Ok(CommandOutcome::Done),
+        false => Ok(CommandOutcome::Message(String::from("the shell exited"))),
+    }
+}
+
+/// copy the shell output to the terminal and the keys to the shell until Ctrl+O
+fn pass_through(live: &mut Subshell) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    loop {
+        let output = live.take_output();
+        if !output.is_empty() {
+            stdout.write_all(&output)?;
+            stdout.flush()?;
+        }
+        if !live.is_alive() {
+            return Ok(());
+        }
+        if !event::poll(Duration::from_millis(20))? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Release => {}
+            Event::Key(key) if key.code == KeyCode::Char('o') && key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(());
+            }
+            Event::Key(key) => live.write_key(&key)?,
+            Event::Paste(text) => live.write(text.as_bytes())?,
+            Event::Resize(columns, rows) => live.resize(columns, rows)?,
+            _ => {}
+        }
+    }
+}
+
+fn live_outcome(result: io::Result<CommandOutcome>) -> CommandOutcome {
+    result.unwrap_or_else(|err| CommandOutcome::Message(format!("shell: {err}")))
 }
@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Rect;
+
+use crate::commands::{self, Command, CommandOutcome, Keymap};
+use crate::fs_node::FsNodeRef;
+use crate::panel::{DropTarget, PanelManager, PanelSide};
+use crate::ui::{self, Hit};
+
+/// longest time between the clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// rows moved by a turn of the wheel
+const WHEEL_STEP: usize = 3;
+
+/// what the left button held down on an entry does as the pointer moves
+#[derive(Debug, Clone)]
+enum Drag {
+    /// marks the entries from the one it went down on to the pointer
+    Marking {
+        side: PanelSide,
+        /// entry the button went down on
+        anchor: usize,
+        /// selection before the drag, the range is added to it
+        base: HashSet<PathBuf>,
+    },
+    /// carries entries to be copied, or moved, into the directory they are dropped on
+    Carrying { side: PanelSide, nodes: Vec<FsNodeRef> },
+}
+
+/// what the mouse did to the main screen, clicks are remembered to spot double clicks
+#[derive(Debug, Clone, Default)]
+pub struct Mouse {
+    last_click: Option<(Instant, Hit)>,
+    drag: Option<Drag>,
+}
+
+impl Mouse {
+    pub fn new() -> Mouse {
+        Mouse::default()
+    }
+
+    /// act on a mouse event over the panels and the key bar, None if it missed them
+    ///
+    /// `area`, `message` and `panels` describe the screen as last drawn, see
+    /// [`ui::hit_test`].
+    pub fn handle(
+        &mut self,
+        event: &MouseEvent,
+        area: Rect,
+        message: bool,
+        panels: bool,
+        manager: &mut PanelManager,
+        keymap: &Keymap,
+    ) -> Option<CommandOutcome> {
+        let hit = ui::hit_test(area, manager, message, panels, event.column, event.row);
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let now = Instant::now();
+                let double = self
+                    .last_click
+                    .is_some_and(|(time, last)| Some(last) == hit && now.duration_since(time) < DOUBLE_CLICK);
+                self.last_click = hit.map(|hit| (now, hit));
+                self.drag = None;
+                match hit? {
+                    Hit::FunctionKey(number) => {
+                        let command = keymap.lookup(&KeyEvent::new(KeyCode::F(number), KeyModifiers::NONE))?;
+                        self.last_click = None;
+                        Some(commands::dispatch(command, manager))
+                    }
+                    Hit::Menu(menu) => {
+                        self.last_click = None;
+                        Some(CommandOutcome::Menu(menu))
+                    }
+                    Hit::Tab(side, index) => {
+                        manager.active = side;
+                        manager.select_tab(side, index);
+                        Some(CommandOutcome::Done)
+                    }
+                    Hit::Panel(side) => {
+                        manager.active = side;
+                        Some(CommandOutcome::Done)
+                    }
+                    Hit::Entry(side, index) => {
+                        manager.active = side;
+                        let panel = manager.panel_mut(side);
+                        panel.cursor = index;
+                        if double {
+                            self.last_click = None;
+                            return Some(match panel.enter() {
+                                Ok(true) => CommandOutcome::Done,
+                                // files are opened in the viewer
+                                Ok(false) => commands::dispatch(Command::View, manager),
+                                Err(err) => CommandOutcome::Message(err.to_string()),
+                            });
+                        }
+                        // going down on a marked entry picks up every marked one
+                        let marked = panel.entry(index).is_some_and(|node| panel.is_selected(&node.read().path()));
+                        self.drag = Some(match marked {
+                            true => Drag::Carrying {
+                                side,
+                                nodes: panel.marked_nodes(),
+                            },
+                            false => Drag::Marking {
+                                side,
+                                anchor: index,
+                                base: panel.selection.paths().clone(),
+                            },
+                        });
+                        Some(CommandOutcome::Done)
+                    }
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let drag = self.drag.as_mut()?;
+                // marking stays in its panel, reaching the other one carries the entry it started on
+                if let Drag::Marking { side, anchor, base } = drag {
+                    let (side, anchor) = (*side, *anchor);
+                    match hit {
+                        Some(Hit::Entry(over, index)) if over == side => {
+                            let panel = manager.panel_mut(side);
+                            let (first, last) = (anchor.min(index), anchor.max(index));
+                            let mut selection = base.clone();
+                            selection.extend(panel.entry_window(first, last + 1 - first).iter().map(|node| node.read().path()));
+                            panel.selection.replace(selection);
+                            panel.cursor = index;
+                            return Some(CommandOutcome::Done);
+                        }
+                        Some(Hit::Entry(over, _) | Hit::Panel(over)) if over != side => {
+                            let panel = manager.panel_mut(side);
+                            panel.selection.replace(std::mem::take(base));
+                            panel.cursor = anchor;
+                            let nodes = panel.entry(anchor).into_iter().collect();
+                            *drag = Drag::Carrying { side, nodes };
+                        }
+                        _ => return None,
+                    }
+                }
+                let Drag::Carrying { side, nodes } = drag else {
+                    return None;
+                };
+                let target = hit.and_then(|hit| drop_target(hit, *side, nodes, manager));
+                for over in [PanelSide::Left, PanelSide::Right] {
+                    manager.panel_mut(over).drop_target = target.filter(|(side, _)| *side == over).map(|(_, target)| target);
+                }
+                Some(CommandOutcome::Done)
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                for side in [PanelSide::Left, PanelSide::Right] {
+                    manager.panel_mut(side).drop_target = None;
+                }
+                let Some(Drag::Carrying { side, nodes }) = self.drag.take() else {
+                    return None;
+                };
+                let (over, target) = drop_target(hit?, side, &nodes, manager)?;
+                let panel = manager.panel(over);
+                let dest = match target {
+                    DropTarget::Directory => panel.root.clone(),
+                    DropTarget::Entry(index) => panel.entry(index)?,
+                };
+                // Ctrl or Alt held on release moves the entries instead
+                let moving = event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+                Some(commands::drop_files(manager, nodes, dest, moving))
+            }
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let (Hit::Entry(side, _) | Hit::Panel(side)) = hit? else {
+                    return None;
+                };
+                // the wheel scrolls the panel under the pointer without focusing it
+                let panel = manager.panel_mut(side);
+                for _ in 0..WHEEL_STEP {
+                    match event.kind {
+                        MouseEventKind::ScrollUp => panel.move_cursor_up(),
+                        _ => panel.move_cursor_down(),
+                    }
+                }
+                Some(CommandOutcome::Done)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// where the entries carried from the panel `from` would be dropped at `hit`
+///
+/// A directory entry takes them unless it is one of them, the other panel
+/// the directory it shows. Nothing goes back to the directory it comes from.
+fn drop_target(hit: Hit, from: PanelSide, nodes: &[FsNodeRef], manager: &PanelManager) -> Option<(PanelSide, DropTarget)> {
+    let (Hit::Entry(side, _) | Hit::Panel(side)) = hit else {
+        return None;
+    };
+    let panel = manager.panel(side);
+    let takes = |dir: &FsNodeRef| !nodes.iter().any(|node| Arc::ptr_eq(node, dir) || commands::is_in_directory(node, dir));
+    if let Hit::Entry(_, index) = hit
+        && let Some(node) = panel.entry(index)
+        && node.read().is_navigable()
+    {
+        return takes(&node).then_some((side, DropTarget::Entry(index)));
+    }
+    let shows_directory = panel.listing.is_none() && panel.tree.is_none() && panel.info.is_none();
+    (side != from && shows_directory && takes(&panel.root)).then_some((side, DropTarget::Directory))
+}
@@ -0,0 +1,48 @@
+use std::io;
+use std::path::Path;
+use std::process::{self, Stdio};
+use std::thread;
+
+/// program opening a file with the default application of the desktop
+pub fn system_opener() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        // `start` would take a quoted path for the window title
+        "explorer"
+    } else {
+        "xdg-open"
+    }
+}
+
+/// the configured opener, or the one of the system
+pub fn opener(command: Option<&str>) -> &str {
+    command.filter(|command| !command.trim().is_empty()).unwrap_or(system_opener())
+}
+
+/// start `command` on a file away from the terminal, its output is discarded
+///
+/// With `wait` the call returns once the program exits and fails like it,
+/// otherwise the program is left running on its own.
+pub fn spawn(command: &str, path: &Path, wait: bool) -> io::Result<()> {
+    let mut words = command.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+    let mut process = process::Command::new(program);
+    process.args(words).arg(path).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    // a group of its own keeps the program alive when the terminal sends signals to ours
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut process, 0);
+    let mut child = process.spawn()?;
+    if wait {
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("exited with {status}")));
+        }
+        return Ok(());
+    }
+    // reaped in the background so it does not linger as a zombie
+    thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::fs_node::{FsNodeRef, FsNodeType};
+use crate::vfs::Vfs;
+
+/// which entries an attribute change applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChangeScope {
+    #[default]
+    All,
+    Files,
+    Directories,
+}
+
+impl ChangeScope {
+    /// the next scope, for toggles cycling through them
+    pub fn next(self) -> ChangeScope {
+        match self {
+            ChangeScope::All => ChangeScope::Files,
+            ChangeScope::Files => ChangeScope::Directories,
+            ChangeScope::Directories => ChangeScope::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeScope::All => "files and directories",
+            ChangeScope::Files => "only files",
+            ChangeScope::Directories => "only directories",
+        }
+    }
+
+    fn includes(self, node_type: &FsNodeType) -> bool {
+        match self {
+            ChangeScope::All => true,
+            ChangeScope::Files => *node_type == FsNodeType::File,
+            ChangeScope::Directories => *node_type == FsNodeType::Directory,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChmodOptions {
+    /// permission bits, including setuid, setgid and sticky
+    pub mode: u32,
+    /// also change everything below directories
+    pub recursive: bool,
+    pub scope: ChangeScope,
+}
+
+/// result of a change applied to many entries, failures do not stop it
+#[derive(Debug, Default)]
+pub struct ChangeReport {
+    pub changed: usize,
+    pub errors: Vec<(PathBuf, io::Error)>,
+}
+
+/// failures named in the summary, the message bar has a single line
+const SHOWN_ERRORS: usize = 3;
+
+impl ChangeReport {
+    /// one line for the message bar, naming the first failures
+    pub fn summary(&self) -> String {
+        if self.errors.is_empty() {
+            return format!("{} entries changed", self.changed);
+        }
+        let failures: Vec<String> = self
+            .errors
+            .iter()
+            .take(SHOWN_ERRORS)
+            .map(|(path, err)| format!("{}: {err}", path.display()))
+            .collect();
+        let more = self.errors.len().saturating_sub(SHOWN_ERRORS);
+        let more = if more > 0 { format!(" and {more} more") } else { String::new() };
+        format!("{} entries changed, {} failed: {}{more}", self.changed, self.errors.len(), failures.join("; "))
+    }
+}
+
+/// change the permissions of nodes, then refresh their cached metadata
+///
+/// Symlinks are skipped, changing them would change their target, and
+/// recursion does not follow them.
+pub fn change_mode(nodes: &[FsNodeRef], options: &ChmodOptions) -> ChangeReport {
+    let mut report = ChangeReport::default();
+    for node in nodes {
+        let (vfs, path) = {
+            let node = node.read();
+            (Arc::clone(&node.vfs), node.path())
+        };
+        chmod_path(vfs.as_ref(), &path, options, &mut report);
+        refresh_loaded(node);
+    }
+    report
+}
+
+fn chmod_path(vfs: &dyn Vfs, path: &Path, options: &ChmodOptions, report: &mut ChangeReport) {
+    let entry = match vfs.metadata(path) {
+        Ok(entry) => entry,
+        Err(err) => return report.errors.push((path.to_path_buf(), err)),
+    };
+    if options.scope.includes(&entry.node_type) && !matches!(entry.node_type, FsNodeType::Symlink { .. }) {
+        match vfs.set_permissions(path, options.mode & 0o7777) {
+            Ok(()) => report.changed += 1,
+            Err(err) => report.errors.push((path.to_path_buf(), err)),
+        }
+    }
+    if options.recursive && entry.node_type == FsNodeType::Directory {
+        match vfs.read_dir(path) {
+            Ok(children) => {
+                for child in children {
+                    chmod_path(vfs, &child.path, options, report);
+                }
+            }
+            Err(err) => report.errors.push((path.to_path_buf(), err)),
+        }
+    }
+}
+
+/// re-read the metadata of a node and of the descendants already in the tree
+pub fn refresh_loaded(node: &FsNodeRef) {
+    let descendants = node.read().iter_dfs();
+    // a node that vanished keeps no metadata, the next reload drops it
+    let _ = node.write().refresh_metadata();
+    for node in descendants {
+        let _ = node.write().refresh_metadata();
+    }
+}
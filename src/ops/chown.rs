@@ -0,0 +1,134 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::fs_node::{FsNodeRef, FsNodeType};
+use crate::ops::chmod::{refresh_loaded, ChangeReport};
+use crate::vfs::Vfs;
+
+/// user or group of the system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub id: u32,
+    pub name: String,
+}
+
+/// users of the system sorted by name
+#[cfg(unix)]
+pub fn system_users() -> Vec<Account> {
+    // the user database is not changed while it is read
+    let mut users: Vec<Account> = unsafe { uzers::all_users() }
+        .map(|user| Account {
+            id: user.uid(),
+            name: user.name().to_string_lossy().into_owned(),
+        })
+        .collect();
+    users.sort_by(|a, b| a.name.cmp(&b.name));
+    users.dedup_by_key(|user| user.id);
+    users
+}
+
+/// groups of the system sorted by name
+#[cfg(unix)]
+pub fn system_groups() -> Vec<Account> {
+    let mut groups: Vec<Account> = unsafe { uzers::all_groups() }
+        .map(|group| Account {
+            id: group.gid(),
+            name: group.name().to_string_lossy().into_owned(),
+        })
+        .collect();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    groups.dedup_by_key(|group| group.id);
+    groups
+}
+
+/// names of ids already looked up, the panels ask for them on every frame
+#[cfg(unix)]
+type NameCache = std::sync::LazyLock<parking_lot::Mutex<std::collections::HashMap<u32, Option<String>>>>;
+
+#[cfg(unix)]
+fn cached_name(cache: &NameCache, id: u32, lookup: impl FnOnce(u32) -> Option<String>) -> Option<String> {
+    cache.lock().entry(id).or_insert_with(|| lookup(id)).clone()
+}
+
+/// name of a user id
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    static NAMES: NameCache = NameCache::new(Default::default);
+    cached_name(&NAMES, uid, |uid| uzers::get_user_by_uid(uid).map(|user| user.name().to_string_lossy().into_owned()))
+}
+
+/// name of a group id
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> Option<String> {
+    static NAMES: NameCache = NameCache::new(Default::default);
+    cached_name(&NAMES, gid, |gid| uzers::get_group_by_gid(gid).map(|group| group.name().to_string_lossy().into_owned()))
+}
+
+#[cfg(not(unix))]
+pub fn user_name(_uid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn group_name(_gid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn system_users() -> Vec<Account> {
+    Vec::new()
+}
+
+#[cfg(not(unix))]
+pub fn system_groups() -> Vec<Account> {
+    Vec::new()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChownOptions {
+    /// new owner, `None` keeps it
+    pub uid: Option<u32>,
+    /// new group, `None` keeps it
+    pub gid: Option<u32>,
+    /// also change everything below directories
+    pub recursive: bool,
+}
+
+/// change the owner and group of nodes, then refresh their cached metadata
+///
+/// Symlinks change owner themselves and recursion does not follow them.
+pub fn change_owner(nodes: &[FsNodeRef], options: &ChownOptions) -> ChangeReport {
+    let mut report = ChangeReport::default();
+    for node in nodes {
+        let (vfs, path) = {
+            let node = node.read();
+            (Arc::clone(&node.vfs), node.path())
+        };
+        chown_path(vfs.as_ref(), &path, options, &mut report);
+        refresh_loaded(node);
+    }
+    report
+}
+
+fn chown_path(vfs: &dyn Vfs, path: &Path, options: &ChownOptions, report: &mut ChangeReport) {
+    match vfs.set_owner(path, options.uid, options.gid) {
+        Ok(()) => report.changed += 1,
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            let err = io::Error::new(err.kind(), format!("{err}, giving files away needs root"));
+            report.errors.push((path.to_path_buf(), err));
+        }
+        Err(err) => report.errors.push((path.to_path_buf(), err)),
+    }
+    if !options.recursive || !vfs.metadata(path).is_ok_and(|entry| entry.node_type == FsNodeType::Directory) {
+        return;
+    }
+    match vfs.read_dir(path) {
+        Ok(children) => {
+            for child in children {
+                chown_path(vfs, &child.path, options, report);
+            }
+        }
+        Err(err) => report.errors.push((path.to_path_buf(), err)),
+    }
+}
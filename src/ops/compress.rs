@@ -0,0 +1,349 @@
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use xz2::write::XzEncoder;
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::{FsNodeRef, FsNodeType};
+use crate::ops::copy::aborted;
+use crate::ops::{CopyFlow, CopyProgress};
+use crate::vfs::archive::unix_to_civil;
+use crate::vfs::{same_vfs, Vfs, VfsEntry, VfsRef};
+
+/// kind of archive written by [`compress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 4] = [ArchiveFormat::Zip, ArchiveFormat::TarGz, ArchiveFormat::TarXz, ArchiveFormat::TarZst];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::TarXz => "tar.xz",
+            ArchiveFormat::TarZst => "tar.zst",
+        }
+    }
+
+    /// end of the file name of the archive, with its dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => ".zip",
+            ArchiveFormat::TarGz => ".tar.gz",
+            ArchiveFormat::TarXz => ".tar.xz",
+            ArchiveFormat::TarZst => ".tar.zst",
+        }
+    }
+
+    /// compression levels accepted, the lowest is the fastest
+    ///
+    /// A zip of level 0 stores its files as they are. The levels of zstd
+    /// above 19 need much memory to read back and are left out.
+    pub fn levels(self) -> RangeInclusive<u32> {
+        match self {
+            ArchiveFormat::TarZst => 1..=19,
+            _ => 0..=9,
+        }
+    }
+
+    pub fn default_level(self) -> u32 {
+        match self {
+            ArchiveFormat::TarZst => 3,
+            _ => 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressOptions {
+    pub format: ArchiveFormat,
+    pub level: u32,
+}
+
+/// pack nodes and what is below them into a new archive at `archive` of `vfs`
+///
+/// Members are named after the path of the nodes below the directory
+/// holding them. Progress goes to `state` like for a copy, a file cannot
+/// be skipped halfway and an abort leaves no archive behind.
+pub fn compress(
+    targets: &[FsNodeRef],
+    vfs: &VfsRef,
+    archive: &Path,
+    options: &CompressOptions,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<()> {
+    let result = write_archive(targets, vfs, archive, options, state, progress);
+    if result.is_err() {
+        let _ = vfs.remove(archive);
+    }
+    result
+}
+
+fn write_archive(
+    targets: &[FsNodeRef],
+    vfs: &VfsRef,
+    archive: &Path,
+    options: &CompressOptions,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<()> {
+    let writer = vfs.open_write(archive)?;
+    let mut packer: Box<dyn Packer> = match options.format {
+        ArchiveFormat::Zip => Box::new(ZipPacker::new(writer, options.level)),
+        ArchiveFormat::TarGz => Box::new(TarPacker::new(Encoder::Gzip(GzEncoder::new(writer, Compression::new(options.level))))),
+        ArchiveFormat::TarXz => Box::new(TarPacker::new(Encoder::Xz(XzEncoder::new(writer, options.level)))),
+        ArchiveFormat::TarZst => Box::new(TarPacker::new(Encoder::Zstd(zstd::Encoder::new(writer, options.level as i32)?))),
+    };
+    for node in targets {
+        let (from_vfs, path, name) = {
+            let node = node.read();
+            (Arc::clone(&node.vfs), node.path(), node.name.clone())
+        };
+        // the archive itself may be below a target
+        let skipped = same_vfs(&from_vfs, vfs).then_some(archive);
+        pack(from_vfs.as_ref(), &path, &name, skipped, packer.as_mut(), state, progress)?;
+    }
+    packer.finish()
+}
+
+/// add a path and what is below it as the member `name`, leaving out `skipped`
+fn pack(
+    vfs: &dyn Vfs,
+    path: &Path,
+    name: &str,
+    skipped: Option<&Path>,
+    packer: &mut dyn Packer,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<()> {
+    if skipped == Some(path) {
+        return Ok(());
+    }
+    let entry = vfs.metadata(path)?;
+    match &entry.node_type {
+        FsNodeType::Symlink { target, .. } => packer.symlink(name, &entry.metadata, target),
+        FsNodeType::Directory => {
+            packer.directory(name, &entry.metadata)?;
+            for child in vfs.read_dir(path)? {
+                pack(vfs, &child.path, &format!("{name}/{}", child.name), skipped, packer, state, progress)?;
+            }
+            Ok(())
+        }
+        FsNodeType::File => {
+            state.current_file = path.to_path_buf();
+            state.file_bytes = 0;
+            state.file_size = entry.metadata.size;
+            if progress(state) == CopyFlow::Abort {
+                return Err(aborted());
+            }
+            let mut reader = Tracked {
+                reader: vfs.open_read(path)?,
+                state: &mut *state,
+                progress: &mut *progress,
+                aborted: false,
+            };
+            let result = packer.file(name, &entry, &mut reader);
+            if reader.aborted {
+                return Err(aborted());
+            }
+            result?;
+            state.files_copied += 1;
+            Ok(())
+        }
+    }
+}
+
+/// a file being packed, counting its bytes into the progress as they are read
+struct Tracked<'a> {
+    reader: Box<dyn Read + Send>,
+    state: &'a mut CopyProgress,
+    progress: &'a mut dyn FnMut(&CopyProgress) -> CopyFlow,
+    aborted: bool,
+}
+
+impl Read for Tracked<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.state.bytes_copied += read as u64;
+        self.state.file_bytes += read as u64;
+        // what was packed of a member stays, only an abort stops it
+        match (self.progress)(self.state) {
+            // an interrupted read would be tried again by the copy of the archive writer
+            CopyFlow::Abort => {
+                self.aborted = true;
+                Err(io::Error::other("operation aborted"))
+            }
+            _ => Ok(read),
+        }
+    }
+}
+
+/// writer of the members of one kind of archive
+trait Packer {
+    fn directory(&mut self, name: &str, metadata: &FsMetadata) -> io::Result<()>;
+
+    fn symlink(&mut self, name: &str, metadata: &FsMetadata, target: &Path) -> io::Result<()>;
+
+    /// a file of `entry.metadata.size` bytes read from `reader`
+    fn file(&mut self, name: &str, entry: &VfsEntry, reader: &mut dyn Read) -> io::Result<()>;
+
+    /// write the end of the archive and flush it
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// seconds since the epoch a member was modified, the epoch if unknown
+fn mtime(metadata: &FsMetadata) -> u64 {
+    metadata
+        .modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// compressor a tar is written through
+enum Encoder {
+    Gzip(GzEncoder<Box<dyn Write + Send>>),
+    Xz(XzEncoder<Box<dyn Write + Send>>),
+    Zstd(zstd::Encoder<'static, Box<dyn Write + Send>>),
+}
+
+impl Encoder {
+    fn finish(self) -> io::Result<()> {
+        let mut writer = match self {
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Xz(encoder) => encoder.finish()?,
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Xz(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Xz(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+struct TarPacker {
+    builder: tar::Builder<Encoder>,
+}
+
+impl TarPacker {
+    fn new(encoder: Encoder) -> TarPacker {
+        TarPacker {
+            builder: tar::Builder::new(encoder),
+        }
+    }
+}
+
+/// a GNU header with the mode, owner and time of a member
+fn tar_header(kind: tar::EntryType, size: u64, metadata: &FsMetadata) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(kind);
+    header.set_size(size);
+    header.set_mode(metadata.permissions & 0o7777);
+    header.set_uid(metadata.uid as u64);
+    header.set_gid(metadata.gid as u64);
+    header.set_mtime(mtime(metadata));
+    header
+}
+
+impl Packer for TarPacker {
+    fn directory(&mut self, name: &str, metadata: &FsMetadata) -> io::Result<()> {
+        let mut header = tar_header(tar::EntryType::Directory, 0, metadata);
+        self.builder.append_data(&mut header, format!("{name}/"), io::empty())
+    }
+
+    fn symlink(&mut self, name: &str, metadata: &FsMetadata, target: &Path) -> io::Result<()> {
+        let mut header = tar_header(tar::EntryType::Symlink, 0, metadata);
+        self.builder.append_link(&mut header, name, target)
+    }
+
+    fn file(&mut self, name: &str, entry: &VfsEntry, reader: &mut dyn Read) -> io::Result<()> {
+        let mut header = tar_header(tar::EntryType::Regular, entry.metadata.size, &entry.metadata);
+        self.builder.append_data(&mut header, name, reader)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.builder.into_inner()?.finish()
+    }
+}
+
+struct ZipPacker {
+    writer: ZipWriter<StreamWriter<Box<dyn Write + Send>>>,
+    level: u32,
+}
+
+impl ZipPacker {
+    fn new(writer: Box<dyn Write + Send>, level: u32) -> ZipPacker {
+        ZipPacker {
+            writer: ZipWriter::new_stream(writer),
+            level,
+        }
+    }
+
+    /// how a member is stored, a date before 1980 cannot be and is left out
+    fn options(&self, metadata: &FsMetadata) -> SimpleFileOptions {
+        let options = match self.level {
+            0 => SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+            level => SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .compression_level(Some(level as i64)),
+        };
+        let options = options.unix_permissions(metadata.permissions & 0o7777).large_file(metadata.size >= u32::MAX as u64);
+        let (year, month, day, hour, minute, second) = unix_to_civil(mtime(metadata) as i64);
+        match DateTime::from_date_and_time(year as u16, month as u8, day as u8, hour as u8, minute as u8, second as u8) {
+            Ok(time) => options.last_modified_time(time),
+            Err(_) => options,
+        }
+    }
+}
+
+impl Packer for ZipPacker {
+    fn directory(&mut self, name: &str, metadata: &FsMetadata) -> io::Result<()> {
+        let options = self.options(metadata);
+        self.writer.add_directory(name, options).map_err(io::Error::other)
+    }
+
+    fn symlink(&mut self, name: &str, metadata: &FsMetadata, target: &Path) -> io::Result<()> {
+        let options = self.options(metadata);
+        self.writer.add_symlink(name, target.to_string_lossy(), options).map_err(io::Error::other)
+    }
+
+    fn file(&mut self, name: &str, entry: &VfsEntry, reader: &mut dyn Read) -> io::Result<()> {
+        let options = self.options(&entry.metadata);
+        self.writer.start_file(name, options).map_err(io::Error::other)?;
+        io::copy(reader, &mut self.writer)?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.writer.finish().map_err(io::Error::other)?.into_inner().flush()
+    }
+}
@@ -0,0 +1,149 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::fs_node::FsNodeType;
+use crate::rename::split_extension;
+use crate::vfs::{Vfs, VfsEntry};
+
+/// what to do with a destination that already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    Overwrite,
+    /// overwrite only when the source is more recent, else skip
+    OverwriteIfNewer,
+    Skip,
+    /// copy under the first free name `name (2).ext`
+    RenameWithSuffix,
+    /// add the source file at the end of the destination file
+    Append,
+    /// take a shorter destination file for a copy cut off and add the rest of the source
+    Resume,
+    OverwriteAll,
+    OverwriteAllIfNewer,
+    SkipAll,
+    RenameAllWithSuffix,
+    AppendAll,
+    ResumeAll,
+    /// stop the whole operation
+    Abort,
+}
+
+impl ConflictResolution {
+    /// the answer for one conflict and whether it also answers the next ones
+    fn split(self) -> Option<(ConflictResolution, bool)> {
+        use ConflictResolution::*;
+        match self {
+            Overwrite | OverwriteIfNewer | Skip | RenameWithSuffix | Append | Resume => Some((self, false)),
+            OverwriteAll => Some((Overwrite, true)),
+            OverwriteAllIfNewer => Some((OverwriteIfNewer, true)),
+            SkipAll => Some((Skip, true)),
+            RenameAllWithSuffix => Some((RenameWithSuffix, true)),
+            AppendAll => Some((Append, true)),
+            ResumeAll => Some((Resume, true)),
+            Abort => None,
+        }
+    }
+}
+
+/// a source about to replace an existing destination, with both sides
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyConflict {
+    pub source: VfsEntry,
+    pub dest: VfsEntry,
+}
+
+impl CopyConflict {
+    /// true if the source was modified after the destination
+    pub fn source_is_newer(&self) -> bool {
+        let seconds = |time: Option<SystemTime>| {
+            time.and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+        };
+        match (seconds(self.source.metadata.modified), seconds(self.dest.metadata.modified)) {
+            (Some(source), Some(dest)) => source > dest,
+            // without times there is no telling, the copy goes ahead
+            _ => true,
+        }
+    }
+
+    /// true if one side is a directory and the other is not
+    ///
+    /// Overwriting would remove a whole tree, or put one where a file was,
+    /// which the answers of the conflict dialog do not tell. Such conflicts
+    /// are refused with [`CopyConflict::kind_refused`] unless skipped or renamed.
+    pub fn changes_kind(&self) -> bool {
+        (self.source.node_type == FsNodeType::Directory) != (self.dest.node_type == FsNodeType::Directory)
+    }
+
+    /// the error of an overwrite refused by [`CopyConflict::changes_kind`]
+    pub fn kind_refused(&self) -> io::Error {
+        let message = match self.dest.node_type == FsNodeType::Directory {
+            true => format!("{} is a directory, it is not replaced by a file", self.dest.path.display()),
+            false => format!("{} is not a directory, it is not replaced by one", self.dest.path.display()),
+        };
+        io::Error::new(io::ErrorKind::AlreadyExists, message)
+    }
+
+    /// true if the destination is a file shorter than the source, like a copy cut off
+    pub fn is_partial(&self) -> bool {
+        self.source.node_type == FsNodeType::File
+            && self.dest.node_type == FsNodeType::File
+            && self.dest.metadata.size < self.source.metadata.size
+    }
+}
+
+/// answers the conflicts of one operation, remembering the "all" answers
+pub struct ConflictResolver<'a> {
+    /// asked for every conflict until an "all" answer, overwrite when unset
+    ask: Option<&'a mut dyn FnMut(&CopyConflict) -> ConflictResolution>,
+    all: Option<ConflictResolution>,
+}
+
+impl<'a> ConflictResolver<'a> {
+    pub fn new(ask: &'a mut dyn FnMut(&CopyConflict) -> ConflictResolution) -> ConflictResolver<'a> {
+        ConflictResolver { ask: Some(ask), all: None }
+    }
+
+    /// resolver replacing every destination without asking
+    pub fn overwrite() -> ConflictResolver<'static> {
+        ConflictResolver { ask: None, all: None }
+    }
+
+    /// resolver giving the same answer to every conflict
+    pub fn always(resolution: ConflictResolution) -> ConflictResolver<'static> {
+        ConflictResolver {
+            ask: None,
+            all: resolution.split().map(|(once, _)| once),
+        }
+    }
+
+    /// the single-conflict answer to use, an abort is an error
+    pub fn resolve(&mut self, conflict: &CopyConflict) -> io::Result<ConflictResolution> {
+        if let Some(all) = self.all {
+            return Ok(all);
+        }
+        let answer = match self.ask.as_mut() {
+            Some(ask) => ask(conflict),
+            None => ConflictResolution::Overwrite,
+        };
+        let (once, all) = answer.split().ok_or_else(|| io::Error::new(io::ErrorKind::Interrupted, "operation aborted"))?;
+        if all {
+            self.all = Some(once);
+        }
+        Ok(once)
+    }
+}
+
+/// first `name (N).ext` next to `path` that does not exist
+pub fn free_name(vfs: &dyn Vfs, path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let (stem, extension) = split_extension(&name);
+    (2..)
+        .map(|number| match extension {
+            "" => path.with_file_name(format!("{stem} ({number})")),
+            extension => path.with_file_name(format!("{stem} ({number}).{extension}")),
+        })
+        .find(|candidate| vfs.metadata(candidate).is_err())
+        .expect("some suffix is free")
+}
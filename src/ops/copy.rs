@@ -0,0 +1,600 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::hash;
+use crate::paths;
+use crate::fs_node::{create_node_in, disk_usage, file_count, FsNode, FsNodeRef, FsNodeType, TreeUpdate};
+use crate::ops::conflict::{free_name, ConflictResolution, ConflictResolver, CopyConflict};
+use crate::ops::delete::remove_tree;
+use crate::ops::fast_copy;
+use crate::vfs::{same_vfs, Vfs, VfsEntry, VfsRef};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyOptions {
+    pub preserve_permissions: bool,
+    pub preserve_mtime: bool,
+    /// access times, read before the copy reads the source
+    pub preserve_atime: bool,
+    /// extended attributes other than the ACLs
+    pub preserve_xattrs: bool,
+    /// POSIX ACLs, kept by Linux as the `system.posix_acl_*` extended attributes
+    pub preserve_acls: bool,
+    /// owner and group, only when running as root
+    pub preserve_owner: bool,
+    /// files linked together in the sources are linked together in the copy
+    pub preserve_hardlinks: bool,
+    /// re-hash every copied file and fail if it differs from its source
+    pub verify: bool,
+    /// most bytes per second a job copies, as fast as it goes when unset
+    pub bandwidth_limit: Option<u64>,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions {
+            preserve_permissions: true,
+            preserve_mtime: true,
+            preserve_atime: false,
+            preserve_xattrs: false,
+            preserve_acls: false,
+            preserve_owner: false,
+            preserve_hardlinks: false,
+            verify: false,
+            bandwidth_limit: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CopyProgress {
+    pub bytes_copied: u64,
+    pub total_bytes: u64,
+    pub current_file: PathBuf,
+    /// bytes of the current file copied so far, out of `file_size`
+    pub file_bytes: u64,
+    pub file_size: u64,
+    pub files_copied: u64,
+    pub total_files: u64,
+    /// files skipped halfway, they are left out of the totals
+    pub files_skipped: u64,
+    /// attributes some copies did not get, one entry per attribute
+    pub lost: Vec<LostAttribute>,
+    /// the copy of each file with several links seen so far, by device and inode
+    pub links: HashMap<(u64, u64), PathBuf>,
+    pub started: Instant,
+}
+
+/// a part of the metadata a copy carries over only when asked to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    AccessTime,
+    Xattrs,
+    Acls,
+    Owner,
+}
+
+impl Attribute {
+    pub fn label(self) -> &'static str {
+        match self {
+            Attribute::AccessTime => "access times",
+            Attribute::Xattrs => "extended attributes",
+            Attribute::Acls => "ACLs",
+            Attribute::Owner => "ownership",
+        }
+    }
+}
+
+/// an attribute the destination refused, and on how many copies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LostAttribute {
+    pub attribute: Attribute,
+    pub files: u64,
+    /// the last copy it was lost on, and why
+    pub path: PathBuf,
+    pub error: String,
+}
+
+impl fmt::Display for LostAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} not kept on {} entries, like {}: {}", self.attribute.label(), self.files, self.path.display(), self.error)
+    }
+}
+
+/// what a progress callback wants the copy to do next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyFlow {
+    Continue,
+    /// give up the current file and remove what was written of it
+    SkipFile,
+    /// stop the whole operation with an interrupted error
+    Abort,
+}
+
+impl CopyProgress {
+    pub fn new(total_bytes: u64, total_files: u64) -> CopyProgress {
+        CopyProgress {
+            bytes_copied: 0,
+            total_bytes,
+            current_file: PathBuf::new(),
+            file_bytes: 0,
+            file_size: 0,
+            files_copied: 0,
+            total_files,
+            files_skipped: 0,
+            lost: Vec::new(),
+            links: HashMap::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// estimated remaining time, from the average speed since the start
+    pub fn eta(&self) -> Option<Duration> {
+        if self.bytes_copied == 0 {
+            return None;
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let remaining = self.total_bytes.saturating_sub(self.bytes_copied) as f64;
+        Some(Duration::from_secs_f64(elapsed * remaining / self.bytes_copied as f64))
+    }
+
+    /// count an attribute refused on `path`, once per path
+    pub fn lose(&mut self, attribute: Attribute, path: &Path, err: &io::Error) {
+        match self.lost.iter_mut().find(|lost| lost.attribute == attribute) {
+            Some(lost) if lost.path == path => {}
+            Some(lost) => {
+                lost.files += 1;
+                lost.path = path.to_path_buf();
+                lost.error = err.to_string();
+            }
+            None => self.lost.push(LostAttribute {
+                attribute,
+                files: 1,
+                path: path.to_path_buf(),
+                error: err.to_string(),
+            }),
+        }
+    }
+}
+
+const BUFFER_SIZE: usize = 128 * 1024;
+
+/// copy a node and its content into `dest_dir` of `dest_vfs`, returning the node of the copy
+///
+/// Existing destinations are resolved by `conflicts`, `None` means the copy
+/// was skipped. Directories present on both sides are merged. Several nodes
+/// can report into one `state`, like for [`copy_path_to`].
+pub fn copy_node(
+    src: &FsNodeRef,
+    dest_vfs: &VfsRef,
+    dest_dir: &Path,
+    options: &CopyOptions,
+    conflicts: &mut ConflictResolver,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<Option<FsNode>> {
+    let (src_vfs, src_path, name) = {
+        let node = src.read();
+        (Arc::clone(&node.vfs), node.path(), node.name.clone())
+    };
+    let target = dest_dir.join(&*name);
+
+    if same_vfs(&src_vfs, dest_vfs) && paths::is_within(dest_dir, &src_path) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cannot copy {} into itself", src_path.display()),
+        ));
+    }
+
+    let Some(target) = copy_path(src_vfs.as_ref(), &src_path, dest_vfs.as_ref(), &target, options, conflicts, state, progress)? else {
+        return Ok(None);
+    };
+
+    create_node_in(dest_vfs, &target).map(Some).map_err(io::Error::from)
+}
+
+/// copy a node into a directory node, returning the path of the copy or `None` if it was skipped
+///
+/// The node of the copy goes to `updates`, to be attached to `dest` by the main loop.
+pub fn copy_node_into(
+    src: &FsNodeRef,
+    dest: &FsNodeRef,
+    options: &CopyOptions,
+    conflicts: &mut ConflictResolver,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+    updates: &mut Vec<TreeUpdate>,
+) -> io::Result<Option<PathBuf>> {
+    let (dest_vfs, dest_dir) = {
+        let dest = dest.read();
+        (Arc::clone(&dest.vfs), dest.path())
+    };
+    let Some(copy) = copy_node(src, &dest_vfs, &dest_dir, options, conflicts, state, progress)? else {
+        return Ok(None);
+    };
+
+    let path = copy.path();
+    updates.push(TreeUpdate::Add {
+        directory: Arc::clone(dest),
+        node: copy,
+    });
+    Ok(Some(path))
+}
+
+/// copy a path to an exact destination path, counting into a shared progress
+///
+/// Several copies can report into one `state` as parts of a bigger operation.
+/// An existing destination is replaced.
+pub fn copy_path_to(
+    src_vfs: &VfsRef,
+    src: &Path,
+    dest_vfs: &VfsRef,
+    dest: &Path,
+    options: &CopyOptions,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<()> {
+    let mut conflicts = ConflictResolver::overwrite();
+    copy_path(src_vfs.as_ref(), src, dest_vfs.as_ref(), dest, options, &mut conflicts, state, progress).map(|_| ())
+}
+
+/// copy a path to an exact destination path without progress reporting
+pub(crate) fn copy_tree(
+    src_vfs: &dyn Vfs,
+    src: &Path,
+    dest_vfs: &dyn Vfs,
+    dest: &Path,
+    options: &CopyOptions,
+) -> io::Result<()> {
+    let mut state = CopyProgress::new(0, 0);
+    let mut conflicts = ConflictResolver::overwrite();
+    copy_path(src_vfs, src, dest_vfs, dest, options, &mut conflicts, &mut state, &mut |_| CopyFlow::Continue).map(|_| ())
+}
+
+/// copy a path and what is below it, returns where the copy went or `None` if skipped
+#[allow(clippy::too_many_arguments)]
+fn copy_path(
+    src_vfs: &dyn Vfs,
+    src: &Path,
+    dest_vfs: &dyn Vfs,
+    dest: &Path,
+    options: &CopyOptions,
+    conflicts: &mut ConflictResolver,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<Option<PathBuf>> {
+    let entry = src_vfs.metadata(src)?;
+    // copying reads the source, which can change its access time
+    let accessed = match options.preserve_atime {
+        true => src_vfs.accessed(src).ok(),
+        false => None,
+    };
+    let mut dest = dest.to_path_buf();
+    let mut merge = false;
+    // bytes already in a destination being resumed
+    let mut resume = None;
+    if let Ok(existing) = dest_vfs.metadata(&dest) {
+        let files = entry.node_type == FsNodeType::File && existing.node_type == FsNodeType::File;
+        if entry.node_type == FsNodeType::Directory && existing.node_type == FsNodeType::Directory {
+            merge = true;
+        } else {
+            let conflict = CopyConflict {
+                source: entry.clone(),
+                dest: existing,
+            };
+            match conflicts.resolve(&conflict)? {
+                ConflictResolution::Skip => return Ok(forget(state, src_vfs, src)),
+                ConflictResolution::OverwriteIfNewer if !conflict.source_is_newer() => return Ok(forget(state, src_vfs, src)),
+                ConflictResolution::RenameWithSuffix => dest = free_name(dest_vfs, &dest),
+                // only a file can be added to a file
+                ConflictResolution::Append if !files => return Ok(forget(state, src_vfs, src)),
+                // what was added before a skip stays, the file cannot be cut back
+                ConflictResolution::Append => {
+                    let size = entry.metadata.size;
+                    return match copy_file(src_vfs, src, size, 0, dest_vfs.open_append(&dest)?, state, progress)? {
+                        CopyFlow::Continue => Ok(Some(dest)),
+                        CopyFlow::SkipFile => Ok(None),
+                        CopyFlow::Abort => Err(aborted()),
+                    };
+                }
+                // a destination as long as the source or longer has nothing to resume
+                ConflictResolution::Resume if !conflict.is_partial() => return Ok(forget(state, src_vfs, src)),
+                ConflictResolution::Resume => resume = Some(conflict.dest.metadata.size),
+                _ if conflict.changes_kind() => return Err(conflict.kind_refused()),
+                // a file is truncated by the copy, anything else has to go first
+                _ if !files => remove_tree(dest_vfs, &dest)?,
+                _ => {}
+            }
+        }
+    }
+
+    // the destination is truncated before the source is read, a copy onto itself would empty it
+    if std::ptr::addr_eq(src_vfs, dest_vfs) && src == dest.as_path() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} cannot be copied onto itself", src.display())));
+    }
+
+    match &entry.node_type {
+        FsNodeType::Symlink { target, .. } => {
+            dest_vfs.symlink(target, &dest)?;
+            return Ok(Some(dest));
+        }
+        FsNodeType::Directory => {
+            if !merge {
+                dest_vfs.mkdir(&dest)?;
+            }
+            for child in src_vfs.read_dir(src)? {
+                let child_dest = dest.join(&child.name);
+                copy_path(src_vfs, &child.path, dest_vfs, &child_dest, options, conflicts, state, progress)?;
+            }
+        }
+        FsNodeType::File => {
+            let size = entry.metadata.size;
+            let link = link_key(options, &entry);
+            // another name of a file copied already becomes another name of its copy
+            if let Some(key) = link
+                && resume.is_none()
+                && let Some(first) = state.links.get(&key).cloned()
+                && link_copy(dest_vfs, &first, &dest)?
+            {
+                begin_file(state, src, size, size);
+                end_file(state, size, CopyFlow::Continue);
+                return match progress(state) {
+                    CopyFlow::Abort => Err(aborted()),
+                    _ => Ok(Some(dest)),
+                };
+            }
+            let local = match (src_vfs.local_path(src), dest_vfs.local_path(&dest), resume) {
+                (Some(from), Some(to), None) => copy_local_file(&from, &to, size, state, progress)?,
+                _ => None,
+            };
+            let flow = match local {
+                Some(flow) => flow,
+                None => {
+                    let writer = match resume {
+                        Some(offset) => dest_vfs.open_write_at(&dest, offset)?,
+                        None => dest_vfs.open_write(&dest)?,
+                    };
+                    copy_file(src_vfs, src, size, resume.unwrap_or(0), writer, state, progress)?
+                }
+            };
+            // a resumed file keeps what it had, it can be resumed again
+            if flow != CopyFlow::Continue && resume.is_none() {
+                let _ = dest_vfs.remove(&dest);
+            }
+            match flow {
+                CopyFlow::Continue => {}
+                CopyFlow::SkipFile => return Ok(None),
+                CopyFlow::Abort => return Err(aborted()),
+            }
+            if options.verify {
+                hash::verify_copy(src_vfs, src, dest_vfs, &dest)?;
+            }
+            if let Some(key) = link {
+                state.links.entry(key).or_insert_with(|| dest.clone());
+            }
+        }
+    }
+
+    keep_attributes(src_vfs, &entry, accessed, dest_vfs, &dest, options, state)?;
+    Ok(Some(dest))
+}
+
+/// give a copy the attributes of its source that `options` asks for
+///
+/// Permissions and mtime the destination cannot store are left out, other
+/// errors setting them fail the copy. The attributes asked for on top of
+/// those never fail it, they are counted as lost in `state` instead.
+fn keep_attributes(
+    src_vfs: &dyn Vfs,
+    entry: &VfsEntry,
+    accessed: Option<SystemTime>,
+    dest_vfs: &dyn Vfs,
+    dest: &Path,
+    options: &CopyOptions,
+    state: &mut CopyProgress,
+) -> io::Result<()> {
+    // a new owner drops the setuid bits, the permissions come later
+    if options.preserve_owner
+        && running_as_root()
+        && let Err(err) = dest_vfs.set_owner(dest, Some(entry.metadata.uid), Some(entry.metadata.gid))
+    {
+        state.lose(Attribute::Owner, dest, &err);
+    }
+    // before the permissions, which could forbid writing them
+    if options.preserve_xattrs || options.preserve_acls {
+        keep_xattrs(src_vfs, &entry.path, dest_vfs, dest, options, state);
+    }
+    // the mtime goes first, restrictive permissions could prevent opening the copy
+    if options.preserve_mtime
+        && let Some(modified) = entry.metadata.modified
+    {
+        ignore_unsupported(dest_vfs.set_modified(dest, modified))?;
+    }
+    if let Some(accessed) = accessed
+        && let Err(err) = dest_vfs.set_accessed(dest, accessed)
+    {
+        state.lose(Attribute::AccessTime, dest, &err);
+    }
+    if options.preserve_permissions {
+        ignore_unsupported(dest_vfs.set_permissions(dest, entry.metadata.permissions & 0o7777))?;
+    }
+    Ok(())
+}
+
+/// copy the extended attributes and the ACLs asked for, counting those refused
+fn keep_xattrs(src_vfs: &dyn Vfs, src: &Path, dest_vfs: &dyn Vfs, dest: &Path, options: &CopyOptions, state: &mut CopyProgress) {
+    let attributes = match src_vfs.xattrs(src) {
+        Ok(attributes) => attributes,
+        // a source without any has none to lose
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => return,
+        Err(err) => {
+            for (attribute, wanted) in [(Attribute::Xattrs, options.preserve_xattrs), (Attribute::Acls, options.preserve_acls)] {
+                if wanted {
+                    state.lose(attribute, dest, &err);
+                }
+            }
+            return;
+        }
+    };
+    for (name, value) in attributes {
+        let (attribute, wanted) = match name.starts_with("system.posix_acl_") {
+            true => (Attribute::Acls, options.preserve_acls),
+            false => (Attribute::Xattrs, options.preserve_xattrs),
+        };
+        if wanted && let Err(err) = dest_vfs.set_xattr(dest, &name, &value) {
+            state.lose(attribute, dest, &err);
+        }
+    }
+}
+
+/// only root can give files away to another owner
+#[cfg(unix)]
+fn running_as_root() -> bool {
+    uzers::get_effective_uid() == 0
+}
+
+#[cfg(not(unix))]
+fn running_as_root() -> bool {
+    false
+}
+
+/// device and inode of a file whose links the copy should keep
+fn link_key(options: &CopyOptions, entry: &VfsEntry) -> Option<(u64, u64)> {
+    let metadata = &entry.metadata;
+    match options.preserve_hardlinks && metadata.nlink > 1 && metadata.inode != 0 {
+        true => Some((metadata.device, metadata.inode)),
+        false => None,
+    }
+}
+
+/// make `dest` another name of the copy `first`, false if it has to be copied instead
+///
+/// That is when the destination has no hardlinks, or `first` is on another
+/// filesystem or was removed since.
+fn link_copy(dest_vfs: &dyn Vfs, first: &Path, dest: &Path) -> io::Result<bool> {
+    let linked = match dest_vfs.hard_link(first, dest) {
+        // a file being overwritten
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            dest_vfs.remove(dest)?;
+            dest_vfs.hard_link(first, dest)
+        }
+        linked => linked,
+    };
+    match linked {
+        Ok(()) => Ok(true),
+        Err(err) if matches!(err.kind(), io::ErrorKind::Unsupported | io::ErrorKind::CrossesDevices | io::ErrorKind::NotFound) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// copy the content of a file of `size` bytes from `offset` on, stopping early when `progress` says so
+///
+/// The bytes before `offset` count as copied. A skipped file is taken out
+/// of the totals of `state`.
+fn copy_file(
+    src_vfs: &dyn Vfs,
+    src: &Path,
+    size: u64,
+    offset: u64,
+    mut writer: Box<dyn Write + Send>,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<CopyFlow> {
+    let mut reader = match offset {
+        0 => src_vfs.open_read(src)?,
+        offset => src_vfs.open_read_at(src, offset)?,
+    };
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    begin_file(state, src, size, offset);
+    let mut flow = progress(state);
+    while flow == CopyFlow::Continue {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        state.bytes_copied += read as u64;
+        state.file_bytes += read as u64;
+        flow = progress(state);
+    }
+    if flow == CopyFlow::Continue {
+        writer.flush()?;
+    }
+    end_file(state, size, flow);
+    Ok(flow)
+}
+
+/// copy a local file with what the kernel and the filesystems offer, `None` when they offer nothing
+///
+/// Reflinks and sparse files are taken care of by [`fast_copy::copy`].
+fn copy_local_file(
+    from: &Path,
+    to: &Path,
+    size: u64,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<Option<CopyFlow>> {
+    begin_file(state, from, size, 0);
+    let flow = progress(state);
+    let flow = match flow {
+        CopyFlow::Continue => {
+            let copied = fast_copy::copy(from, to, &mut |bytes| {
+                state.bytes_copied += bytes;
+                state.file_bytes += bytes;
+                progress(state)
+            })?;
+            match copied {
+                Some(flow) => flow,
+                None => return Ok(None),
+            }
+        }
+        flow => flow,
+    };
+    end_file(state, size, flow);
+    Ok(Some(flow))
+}
+
+/// start counting a file of `size` bytes whose first `offset` bytes are already there
+pub(crate) fn begin_file(state: &mut CopyProgress, src: &Path, size: u64, offset: u64) {
+    state.current_file = src.to_path_buf();
+    state.bytes_copied += offset;
+    state.file_bytes = offset;
+    state.file_size = size;
+}
+
+/// count a file done, or take a skipped one out of the totals
+pub(crate) fn end_file(state: &mut CopyProgress, size: u64, flow: CopyFlow) {
+    match flow {
+        CopyFlow::Continue => state.files_copied += 1,
+        CopyFlow::SkipFile => {
+            state.bytes_copied = state.bytes_copied.saturating_sub(state.file_bytes);
+            state.total_bytes = state.total_bytes.saturating_sub(size);
+            state.total_files = state.total_files.saturating_sub(1);
+            state.files_skipped += 1;
+        }
+        CopyFlow::Abort => {}
+    }
+}
+
+/// take a source skipped because of a conflict out of the totals
+fn forget(state: &mut CopyProgress, src_vfs: &dyn Vfs, src: &Path) -> Option<PathBuf> {
+    state.total_bytes = state.total_bytes.saturating_sub(disk_usage(src_vfs, src));
+    state.total_files = state.total_files.saturating_sub(file_count(src_vfs, src));
+    None
+}
+
+pub(crate) fn aborted() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "operation aborted")
+}
+
+/// attributes the destination cannot store are not an error
+fn ignore_unsupported(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => Ok(()),
+        result => result,
+    }
+}
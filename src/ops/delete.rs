@@ -0,0 +1,137 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::fs_node::{FsNodeRef, FsNodeType};
+use crate::vfs::{Vfs, VfsRef};
+
+/// question asked to the user before removing something
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletePrompt<'a> {
+    File(&'a Path),
+    Directory(&'a Path),
+    /// the entry is write-protected, removing it requires forcing
+    ReadOnly(&'a Path),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteAnswer {
+    Yes,
+    No,
+    /// yes for this prompt and every following prompt of the same kind
+    All,
+    Abort,
+}
+
+/// confirmation callback that accepts everything
+pub fn confirm_all(_: &DeletePrompt) -> DeleteAnswer {
+    DeleteAnswer::All
+}
+
+#[derive(Debug, Default)]
+struct DeleteState {
+    all_files: bool,
+    all_directories: bool,
+    all_read_only: bool,
+}
+
+/// delete a node from its filesystem, returns false if something was skipped
+///
+/// When everything was removed the node is detached from its parent.
+pub fn delete_node(
+    node: &FsNodeRef,
+    confirm: &mut dyn FnMut(&DeletePrompt) -> DeleteAnswer,
+) -> io::Result<bool> {
+    let (vfs, path) = {
+        let node = node.read();
+        (Arc::clone(&node.vfs), node.path())
+    };
+    let mut state = DeleteState::default();
+    let removed = delete_path(vfs.as_ref(), &path, &mut state, confirm)?;
+
+    let parent = node.read().parent();
+    if removed && let Some(parent) = parent {
+        parent.write().remove_node(path, None);
+    }
+    Ok(removed)
+}
+
+/// remove a path of a backend and everything below it without asking
+pub fn remove_path(vfs: &VfsRef, path: &Path) -> io::Result<()> {
+    remove_tree(vfs.as_ref(), path)
+}
+
+/// remove a path and everything below it without asking
+pub(crate) fn remove_tree(vfs: &dyn Vfs, path: &Path) -> io::Result<()> {
+    let mut state = DeleteState {
+        all_files: true,
+        all_directories: true,
+        all_read_only: true,
+    };
+    delete_path(vfs, path, &mut state, &mut confirm_all).map(|_| ())
+}
+
+fn delete_path(
+    vfs: &dyn Vfs,
+    path: &Path,
+    state: &mut DeleteState,
+    confirm: &mut dyn FnMut(&DeletePrompt) -> DeleteAnswer,
+) -> io::Result<bool> {
+    let entry = vfs.metadata(path)?;
+    let is_dir = entry.node_type == FsNodeType::Directory;
+
+    let (prompt, all) = if is_dir {
+        (DeletePrompt::Directory(path), &mut state.all_directories)
+    } else {
+        (DeletePrompt::File(path), &mut state.all_files)
+    };
+    if !ask(prompt, all, confirm)? {
+        return Ok(false);
+    }
+
+    let read_only = entry.metadata.permissions & 0o222 == 0;
+    if read_only && !matches!(entry.node_type, FsNodeType::Symlink { .. }) {
+        if !ask(DeletePrompt::ReadOnly(path), &mut state.all_read_only, confirm)? {
+            return Ok(false);
+        }
+        // backends without permissions have nothing to force
+        match vfs.set_permissions(path, (entry.metadata.permissions & 0o7777) | 0o200) {
+            Err(err) if err.kind() != io::ErrorKind::Unsupported => return Err(err),
+            _ => {}
+        }
+    }
+
+    if !is_dir {
+        vfs.remove(path)?;
+        return Ok(true);
+    }
+
+    let mut complete = true;
+    for child in vfs.read_dir(path)? {
+        complete &= delete_path(vfs, &child.path, state, confirm)?;
+    }
+    if complete {
+        vfs.remove(path)?;
+    }
+    Ok(complete)
+}
+
+/// ask unless "all" was already answered, an abort stops the whole deletion
+fn ask(
+    prompt: DeletePrompt,
+    all: &mut bool,
+    confirm: &mut dyn FnMut(&DeletePrompt) -> DeleteAnswer,
+) -> io::Result<bool> {
+    if *all {
+        return Ok(true);
+    }
+    match confirm(&prompt) {
+        DeleteAnswer::Yes => Ok(true),
+        DeleteAnswer::No => Ok(false),
+        DeleteAnswer::All => {
+            *all = true;
+            Ok(true)
+        }
+        DeleteAnswer::Abort => Err(io::Error::new(io::ErrorKind::Interrupted, "deletion aborted")),
+    }
+}
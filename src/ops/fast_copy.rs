@@ -0,0 +1,178 @@
+use std::io;
+use std::path::Path;
+
+use crate::ops::copy::CopyFlow;
+
+/// bytes handed to the kernel at once, between two progress reports
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const CHUNK: u64 = 4 * 1024 * 1024;
+
+/// copy a local file over a local path the fastest way the filesystems allow
+///
+/// A reflink shares the blocks of the source on btrfs, XFS or APFS. Else
+/// only the data of the source is copied, through `copy_file_range` where
+/// the kernel has it, with the holes of a sparse file left as holes.
+/// `copied` is told each step and says how to go on. Returns `None` on
+/// systems without any of this, before anything is written.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn copy(from: &Path, to: &Path, copied: &mut dyn FnMut(u64) -> CopyFlow) -> io::Result<Option<CopyFlow>> {
+    use std::fs::{File, OpenOptions};
+
+    let source = File::open(from)?;
+    let len = source.metadata()?.len();
+    if clone_new(from, to) {
+        return Ok(Some(copied(len)));
+    }
+    let dest = OpenOptions::new().write(true).create(true).truncate(true).open(to)?;
+    if clone(&source, &dest) {
+        return Ok(Some(copied(len)));
+    }
+    let mut kernel = cfg!(target_os = "linux");
+    let mut buffer = Vec::new();
+    let mut position = 0;
+    while position < len {
+        // a filesystem without holes has its data up to the end
+        let (start, end) = match data_extent(&source, position, len)? {
+            Some(extent) => extent,
+            None => (position, len),
+        };
+        if start > position {
+            let flow = copied(start - position);
+            if flow != CopyFlow::Continue {
+                return Ok(Some(flow));
+            }
+        }
+        let mut offset = start;
+        while offset < end {
+            let want = (end - offset).min(CHUNK);
+            let done = match kernel {
+                true => match range(&source, &dest, offset, want) {
+                    Ok(done) => done,
+                    // an older kernel or copying across filesystems, read and write then
+                    Err(err) if is_unsupported(&err) => {
+                        kernel = false;
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                },
+                false => through_buffer(&source, &dest, offset, want, &mut buffer)?,
+            };
+            // the source got shorter meanwhile
+            if done == 0 {
+                dest.set_len(offset)?;
+                return Ok(Some(CopyFlow::Continue));
+            }
+            offset += done;
+            let flow = copied(done);
+            if flow != CopyFlow::Continue {
+                return Ok(Some(flow));
+            }
+        }
+        position = end;
+    }
+    // a hole at the end is only a length
+    dest.set_len(len)?;
+    Ok(Some(CopyFlow::Continue))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn copy(_from: &Path, _to: &Path, _copied: &mut dyn FnMut(u64) -> CopyFlow) -> io::Result<Option<CopyFlow>> {
+    Ok(None)
+}
+
+/// make the new file `to` a clone of `from`, false if it exists or the filesystem cannot
+#[cfg(target_os = "macos")]
+fn clone_new(from: &Path, to: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let (Ok(from), Ok(to)) = (CString::new(from.as_os_str().as_bytes()), CString::new(to.as_os_str().as_bytes())) else {
+        return false;
+    };
+    // SAFETY: both paths are valid C strings, clonefile refuses an existing destination
+    unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), 0) == 0 }
+}
+
+#[cfg(target_os = "linux")]
+fn clone_new(_from: &Path, _to: &Path) -> bool {
+    false
+}
+
+/// share the blocks of `source` with the open `dest`, false if the filesystem cannot
+#[cfg(target_os = "linux")]
+fn clone(source: &std::fs::File, dest: &std::fs::File) -> bool {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: both descriptors are open for the duration of the call
+    unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) == 0 }
+}
+
+#[cfg(target_os = "macos")]
+fn clone(_source: &std::fs::File, _dest: &std::fs::File) -> bool {
+    false
+}
+
+/// the next stretch of data from `position` on, None when the filesystem does not tell
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn data_extent(source: &std::fs::File, position: u64, len: u64) -> io::Result<Option<(u64, u64)>> {
+    use std::os::fd::AsRawFd;
+
+    let seek = |offset: u64, whence| {
+        // SAFETY: the descriptor is open, seeking it moves nothing else
+        let found = unsafe { libc::lseek(source.as_raw_fd(), offset as libc::off_t, whence) };
+        match found {
+            -1 => Err(io::Error::last_os_error()),
+            found => Ok(found as u64),
+        }
+    };
+    let start = match seek(position, libc::SEEK_DATA) {
+        Ok(start) => start,
+        // only holes are left
+        Err(err) if err.raw_os_error() == Some(libc::ENXIO) => return Ok(Some((len, len))),
+        Err(err) if err.raw_os_error() == Some(libc::EINVAL) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let end = seek(start, libc::SEEK_HOLE)?.min(len);
+    Ok(Some((start.min(len), end)))
+}
+
+/// copy `len` bytes at `offset` inside the kernel, the count copied
+#[cfg(target_os = "linux")]
+fn range(source: &std::fs::File, dest: &std::fs::File, offset: u64, len: u64) -> io::Result<u64> {
+    use std::os::fd::AsRawFd;
+
+    let mut from = offset as libc::loff_t;
+    let mut to = offset as libc::loff_t;
+    // SAFETY: both descriptors are open and the offsets are ours
+    let done = unsafe { libc::copy_file_range(source.as_raw_fd(), &mut from, dest.as_raw_fd(), &mut to, len as usize, 0) };
+    match done {
+        -1 => Err(io::Error::last_os_error()),
+        done => Ok(done as u64),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn range(_source: &std::fs::File, _dest: &std::fs::File, _offset: u64, _len: u64) -> io::Result<u64> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// copy `len` bytes at `offset` through memory, the count copied
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn through_buffer(source: &std::fs::File, dest: &std::fs::File, offset: u64, len: u64, buffer: &mut Vec<u8>) -> io::Result<u64> {
+    use std::os::unix::fs::FileExt;
+
+    buffer.resize(CHUNK as usize, 0);
+    let read = source.read_at(&mut buffer[..len as usize], offset)?;
+    dest.write_all_at(&buffer[..read], offset)?;
+    Ok(read as u64)
+}
+
+/// errors of a kernel copy the filesystems or the kernel cannot make
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn is_unsupported(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::Unsupported
+        || matches!(
+            err.raw_os_error(),
+            Some(libc::EXDEV | libc::ENOSYS | libc::EOPNOTSUPP | libc::EINVAL | libc::EBADF)
+        )
+}
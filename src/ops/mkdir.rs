@@ -0,0 +1,49 @@
+use std::io;
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+use crate::fs_node::{create_node_in, FsNodeRef, FsNodeType};
+
+/// create a directory and its missing parents below a directory node
+///
+/// `path` is relative to `parent` or absolute. When the new directory lies
+/// below `parent`, the child of `parent` leading to it is added to the tree
+/// and returned.
+pub fn make_directory(parent: &FsNodeRef, path: &Path) -> io::Result<Option<FsNodeRef>> {
+    let (vfs, base) = {
+        let parent = parent.read();
+        (Arc::clone(&parent.vfs), parent.path())
+    };
+    let target = base.join(path);
+    if vfs.metadata(&target).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+
+    let mut missing: Vec<&Path> = target.ancestors().take_while(|ancestor| vfs.metadata(ancestor).is_err()).collect();
+    missing.reverse();
+    for directory in missing {
+        vfs.mkdir(directory)?;
+    }
+
+    let Ok(relative) = target.strip_prefix(&base) else {
+        return Ok(None);
+    };
+    let Some(Component::Normal(first)) = relative.components().next() else {
+        return Ok(None);
+    };
+    let child_path = base.join(first);
+    let mut parent = parent.write();
+    if let Some(existing) = parent.find_node(child_path.clone(), Some(FsNodeType::Directory)) {
+        // the new directory went inside an existing child
+        existing.write().computed_size = None;
+        return Ok(Some(existing));
+    }
+    let Ok(child) = create_node_in(&vfs, &child_path) else {
+        return Ok(None);
+    };
+    parent.add_child(child);
+    Ok(parent.children.last().cloned())
+}
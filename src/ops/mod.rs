@@ -0,0 +1,22 @@
+pub mod chmod;
+pub mod chown;
+pub mod compress;
+pub mod conflict;
+pub mod copy;
+pub mod delete;
+pub mod fast_copy;
+pub mod mkdir;
+pub mod move_node;
+pub mod shred;
+pub mod split;
+
+pub use chmod::{change_mode, refresh_loaded, ChangeReport, ChangeScope, ChmodOptions};
+pub use chown::{change_owner, group_name, user_name, ChownOptions};
+pub use compress::{compress, ArchiveFormat, CompressOptions};
+pub use conflict::{free_name, ConflictResolution, ConflictResolver, CopyConflict};
+pub use copy::{copy_node, copy_node_into, copy_path_to, Attribute, CopyFlow, CopyOptions, CopyProgress, LostAttribute};
+pub use delete::{delete_node, remove_path, DeleteAnswer, DeletePrompt};
+pub use mkdir::make_directory;
+pub use move_node::{move_node, move_path};
+pub use shred::{is_solid_state, shred_path, ShredOptions, ShredPattern};
+pub use split::{join_parts, joined_name, part_name, parts_of, split_file};
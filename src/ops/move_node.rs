@@ -0,0 +1,167 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::fs_node::{create_node_in, FsNode, FsNodeRef, FsNodeType, TreeUpdate};
+use crate::ops::conflict::{free_name, ConflictResolution, ConflictResolver, CopyConflict};
+use crate::ops::copy::{copy_path_to, copy_tree, CopyFlow, CopyOptions, CopyProgress};
+use crate::ops::delete::remove_tree;
+use crate::paths;
+use crate::vfs::{same_vfs, Vfs, VfsRef};
+
+/// move or rename a node under `new_parent`
+///
+/// `new_name` renames the node, otherwise it keeps its name. An existing
+/// target is resolved by `conflicts`, returns where the node went or `None`
+/// if it was skipped. A move that has to copy reports into `state`, when
+/// files of it are skipped the source stays whole and the move counts as
+/// skipped. What the in-memory tree needs to stay consistent goes to
+/// `updates`, the node is detached from its current parent by them.
+pub fn move_node(
+    node: &FsNodeRef,
+    new_parent: &FsNodeRef,
+    new_name: Option<&str>,
+    conflicts: &mut ConflictResolver,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+    updates: &mut Vec<TreeUpdate>,
+) -> io::Result<Option<PathBuf>> {
+    let (src_vfs, src_path, name) = {
+        let node = node.read();
+        (
+            Arc::clone(&node.vfs),
+            node.path(),
+            new_name.map(str::to_string).unwrap_or_else(|| node.name.to_string()),
+        )
+    };
+    let (dest_vfs, mut target) = {
+        let new_parent = new_parent.read();
+        (Arc::clone(&new_parent.vfs), new_parent.path().join(&name))
+    };
+    let same_backend = same_vfs(&src_vfs, &dest_vfs);
+
+    if same_backend && target == src_path {
+        return Ok(Some(target));
+    }
+    if same_backend && paths::is_within(&target, &src_path) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cannot move {} into itself", src_path.display()),
+        ));
+    }
+    if let Ok(existing) = dest_vfs.metadata(&target) {
+        let conflict = CopyConflict {
+            source: src_vfs.metadata(&src_path)?,
+            dest: existing,
+        };
+        let files = conflict.source.node_type == FsNodeType::File && conflict.dest.node_type == FsNodeType::File;
+        match conflicts.resolve(&conflict)? {
+            ConflictResolution::Skip => return Ok(None),
+            ConflictResolution::OverwriteIfNewer if !conflict.source_is_newer() => return Ok(None),
+            ConflictResolution::RenameWithSuffix => target = free_name(dest_vfs.as_ref(), &target),
+            ConflictResolution::Append | ConflictResolution::Resume if !files => return Ok(None),
+            ConflictResolution::Resume if !conflict.is_partial() => return Ok(None),
+            resolution @ (ConflictResolution::Append | ConflictResolution::Resume) => {
+                let offset = match resolution {
+                    ConflictResolution::Resume => Some(conflict.dest.metadata.size),
+                    _ => None,
+                };
+                append_file(src_vfs.as_ref(), &src_path, dest_vfs.as_ref(), &target, offset)?;
+                src_vfs.remove(&src_path)?;
+                let old_parent = node.read().parent();
+                if let Some(old_parent) = old_parent {
+                    updates.push(TreeUpdate::Remove {
+                        directory: old_parent,
+                        path: src_path,
+                    });
+                }
+                // the target grew, its node is read again
+                if let Ok(entry) = dest_vfs.metadata(&target) {
+                    updates.push(TreeUpdate::Add {
+                        directory: Arc::clone(new_parent),
+                        node: FsNode::from_entry(entry, Arc::clone(&dest_vfs)),
+                    });
+                }
+                return Ok(Some(target));
+            }
+            _ if conflict.changes_kind() => return Err(conflict.kind_refused()),
+            _ => {
+                remove_tree(dest_vfs.as_ref(), &target)?;
+                updates.push(TreeUpdate::Remove {
+                    directory: Arc::clone(new_parent),
+                    path: target.clone(),
+                });
+            }
+        }
+    }
+
+    if !transfer(&src_vfs, &src_path, &dest_vfs, &target, state, progress)? {
+        if let Ok(copy) = create_node_in(&dest_vfs, &target) {
+            updates.push(TreeUpdate::Add {
+                directory: Arc::clone(new_parent),
+                node: copy,
+            });
+        }
+        return Ok(None);
+    }
+
+    updates.push(TreeUpdate::Move {
+        node: Arc::clone(node),
+        directory: Arc::clone(new_parent),
+        name,
+        metadata: dest_vfs.metadata(&target).ok().map(|entry| entry.metadata),
+        path: target.clone(),
+        vfs: (!same_backend).then_some(dest_vfs),
+    });
+    Ok(Some(target))
+}
+
+/// rename a path, falling back to copy and delete across filesystems and backends
+pub fn move_path(src_vfs: &VfsRef, src: &Path, dest_vfs: &VfsRef, dest: &Path) -> io::Result<()> {
+    if same_vfs(src_vfs, dest_vfs) {
+        match src_vfs.rename(src, dest) {
+            Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {}
+            result => return result,
+        }
+    }
+    copy_and_remove(src_vfs.as_ref(), src, dest_vfs.as_ref(), dest)
+}
+
+/// like [`move_path`] with the progress of a copy, false if files were skipped and the source kept
+fn transfer(
+    src_vfs: &VfsRef,
+    src: &Path,
+    dest_vfs: &VfsRef,
+    dest: &Path,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<bool> {
+    if same_vfs(src_vfs, dest_vfs) {
+        match src_vfs.rename(src, dest) {
+            Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {}
+            result => return result.map(|()| true),
+        }
+    }
+    let skipped = state.files_skipped;
+    copy_path_to(src_vfs, src, dest_vfs, dest, &CopyOptions::default(), state, progress)?;
+    if state.files_skipped != skipped {
+        return Ok(false);
+    }
+    remove_tree(src_vfs.as_ref(), src)?;
+    Ok(true)
+}
+
+/// add a file at the end of another one, or what follows `offset` of it when resuming
+fn append_file(src_vfs: &dyn Vfs, src: &Path, dest_vfs: &dyn Vfs, dest: &Path, offset: Option<u64>) -> io::Result<()> {
+    let (mut reader, mut writer) = match offset {
+        Some(offset) => (src_vfs.open_read_at(src, offset)?, dest_vfs.open_write_at(dest, offset)?),
+        None => (src_vfs.open_read(src)?, dest_vfs.open_append(dest)?),
+    };
+    io::copy(&mut reader, &mut writer)?;
+    writer.flush()
+}
+
+fn copy_and_remove(src_vfs: &dyn Vfs, src: &Path, dest_vfs: &dyn Vfs, dest: &Path) -> io::Result<()> {
+    copy_tree(src_vfs, src, dest_vfs, dest, &CopyOptions::default())?;
+    remove_tree(src_vfs, src)
+}
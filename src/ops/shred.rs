@@ -0,0 +1,191 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ops::copy::{aborted, begin_file, end_file, CopyFlow, CopyProgress};
+
+/// bytes written at once, between two progress reports
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// what the passes of a shred write over a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShredPattern {
+    /// fresh random bytes on every pass
+    #[default]
+    Random,
+    Zeros,
+    /// every bit set
+    Ones,
+}
+
+impl ShredPattern {
+    pub const ALL: [ShredPattern; 3] = [ShredPattern::Random, ShredPattern::Zeros, ShredPattern::Ones];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShredPattern::Random => "random bytes",
+            ShredPattern::Zeros => "zeros",
+            ShredPattern::Ones => "ones",
+        }
+    }
+}
+
+/// how a shred overwrites files before removing them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShredOptions {
+    /// times each file is overwritten, at least once
+    pub passes: u32,
+    pub pattern: ShredPattern,
+}
+
+impl Default for ShredOptions {
+    fn default() -> Self {
+        ShredOptions {
+            passes: 3,
+            pattern: ShredPattern::Random,
+        }
+    }
+}
+
+impl ShredOptions {
+    /// bytes written to shred `size` bytes of files
+    pub fn written(&self, size: u64) -> u64 {
+        size.saturating_mul(self.passes.max(1) as u64)
+    }
+}
+
+/// overwrite a local path and remove it, what is below a directory first
+///
+/// Each pass reaches the disk before the next one starts. Symlinks are
+/// removed without touching their target. A skipped file stays, overwritten
+/// as far as it got, with the directories holding it. Returns false when
+/// something was skipped.
+pub fn shred_path(
+    path: &Path,
+    options: &ShredOptions,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<bool> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        let mut removed = true;
+        for entry in fs::read_dir(path)? {
+            removed &= shred_path(&entry?.path(), options, state, progress)?;
+        }
+        if removed {
+            fs::remove_dir(path)?;
+        }
+        return Ok(removed);
+    }
+    if metadata.is_file() {
+        match shred_file(path, metadata.len(), options, state, progress)? {
+            CopyFlow::Continue => {}
+            CopyFlow::SkipFile => return Ok(false),
+            CopyFlow::Abort => return Err(aborted()),
+        }
+    }
+    remove_hidden(path)?;
+    Ok(true)
+}
+
+/// overwrite the content of a file with every pass, stopping early when `progress` says so
+fn shred_file(
+    path: &Path,
+    size: u64,
+    options: &ShredOptions,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<CopyFlow> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut noise = Noise::new();
+    begin_file(state, path, options.written(size), 0);
+
+    let mut flow = progress(state);
+    for _ in 0..options.passes.max(1) {
+        file.seek(SeekFrom::Start(0))?;
+        let mut left = size;
+        while left > 0 && flow == CopyFlow::Continue {
+            let chunk = left.min(BUFFER_SIZE as u64) as usize;
+            match options.pattern {
+                ShredPattern::Random => noise.fill(&mut buffer[..chunk]),
+                ShredPattern::Zeros => buffer[..chunk].fill(0),
+                ShredPattern::Ones => buffer[..chunk].fill(0xff),
+            }
+            file.write_all(&buffer[..chunk])?;
+            left -= chunk as u64;
+            state.bytes_copied += chunk as u64;
+            state.file_bytes += chunk as u64;
+            flow = progress(state);
+        }
+        if flow != CopyFlow::Continue {
+            break;
+        }
+        // the next pass must not be merged with this one in the cache
+        file.sync_data()?;
+    }
+    end_file(state, options.written(size), flow);
+    Ok(flow)
+}
+
+/// remove a file after renaming it to zeros, its name could tell what it was
+fn remove_hidden(path: &Path) -> io::Result<()> {
+    let length = path.file_name().map(|name| name.len()).unwrap_or(1);
+    let hidden = path.with_file_name("0".repeat(length));
+    match hidden != path && fs::symlink_metadata(&hidden).is_err() && fs::rename(path, &hidden).is_ok() {
+        true => fs::remove_file(&hidden),
+        false => fs::remove_file(path),
+    }
+}
+
+/// random bytes from blake3 in its extendable output mode, keyed once per file
+struct Noise(blake3::OutputReader);
+
+impl Noise {
+    fn new() -> Noise {
+        let mut hasher = blake3::Hasher::new();
+        let mut seed = [0; 32];
+        // the clock and the process id make up for a system without /dev/urandom
+        if let Ok(mut random) = fs::File::open("/dev/urandom") {
+            let _ = random.read_exact(&mut seed);
+        }
+        hasher.update(&seed);
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        hasher.update(&now.as_nanos().to_le_bytes());
+        hasher.update(&std::process::id().to_le_bytes());
+        Noise(hasher.finalize_xof())
+    }
+
+    fn fill(&mut self, buffer: &mut [u8]) {
+        self.0.fill(buffer);
+    }
+}
+
+/// true if a path is on a solid state drive, `None` when the system does not tell
+///
+/// A drive like that writes an overwrite to other blocks, the old content
+/// stays until the drive erases it.
+#[cfg(target_os = "linux")]
+pub fn is_solid_state(path: &Path) -> Option<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = fs::symlink_metadata(path).ok()?.dev();
+    let major = ((device >> 32) & 0xffff_f000) | ((device >> 8) & 0xfff);
+    let minor = ((device >> 12) & 0xffff_ff00) | (device & 0xff);
+    let block = Path::new("/sys/dev/block").join(format!("{major}:{minor}"));
+    // a partition has the queue of its whole disk
+    [block.join("queue/rotational"), block.join("../queue/rotational")]
+        .iter()
+        .find_map(|queue| fs::read_to_string(queue).ok())
+        .map(|rotational| rotational.trim() == "0")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_solid_state(_path: &Path) -> Option<bool> {
+    None
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::hash::{self, Checksum, HashAlgorithm, Hasher};
+use crate::ops::copy::{aborted, begin_file, end_file, CopyFlow, CopyProgress};
+use crate::vfs::Vfs;
+
+/// bytes read at once, between two progress reports
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// algorithm of the manifest written next to the parts
+const MANIFEST_ALGORITHM: HashAlgorithm = HashAlgorithm::Sha256;
+
+/// name of part `index` of the file `name`, counted from 1 like `name.001`
+pub fn part_name(name: &str, index: usize) -> String {
+    format!("{name}.{index:03}")
+}
+
+/// name of the file a part was split from, `None` if it is not named like a part
+pub fn joined_name(part: &str) -> Option<&str> {
+    let (name, number) = part.rsplit_once('.')?;
+    match !name.is_empty() && number.len() >= 3 && number.bytes().all(|byte| byte.is_ascii_digit()) {
+        true => Some(name),
+        false => None,
+    }
+}
+
+/// the parts of a split file with their sizes, from `.001` on until one is missing
+///
+/// `part` is any of them. Returns the name of the joined file too.
+pub fn parts_of(vfs: &dyn Vfs, part: &Path) -> io::Result<(String, Vec<(PathBuf, u64)>)> {
+    let not_a_part = || io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not named like a part", part.display()));
+    let file_name = part.file_name().ok_or_else(not_a_part)?.to_string_lossy();
+    let name = joined_name(&file_name).ok_or_else(not_a_part)?.to_string();
+    let dir = part.parent().unwrap_or(Path::new("/"));
+    let mut parts = Vec::new();
+    while let Ok(entry) = vfs.metadata(&dir.join(part_name(&name, parts.len() + 1))) {
+        parts.push((dir.join(part_name(&name, parts.len() + 1)), entry.metadata.size));
+    }
+    if parts.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is missing", part_name(&name, 1))));
+    }
+    Ok((name, parts))
+}
+
+/// split the file `from` into parts of `part_size` bytes in the directory `dest`
+///
+/// A manifest in the `sha256sum` format comes with the parts, the digest of
+/// each part and of the whole file, for a join to check both. Existing files
+/// are not overwritten. A split that is skipped or fails removes what it
+/// wrote. Returns the paths written, none when skipped.
+pub fn split_file(
+    from_vfs: &dyn Vfs,
+    from: &Path,
+    dest_vfs: &dyn Vfs,
+    dest: &Path,
+    part_size: u64,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<Vec<PathBuf>> {
+    let name = match from.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} cannot be split", from.display()))),
+    };
+    let part_size = part_size.max(1);
+    let size = from_vfs.metadata(from)?.metadata.size;
+    let manifest = dest.join(format!("{name}.{}", MANIFEST_ALGORITHM.manifest_extension()));
+    let parts = (1..=size.div_ceil(part_size).max(1)).map(|index| dest.join(part_name(&name, index as usize)));
+    if let Some(existing) = parts.chain([manifest.clone()]).find(|path| dest_vfs.metadata(path).is_ok()) {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", existing.display())));
+    }
+
+    let mut written = Vec::new();
+    let mut checksums = Vec::new();
+    begin_file(state, from, size, 0);
+    let flow = write_parts(from_vfs, from, dest_vfs, dest, &name, part_size, &mut written, &mut checksums, state, progress)
+        .and_then(|flow| match flow {
+            CopyFlow::Continue => {
+                written.push(manifest.clone());
+                hash::write_manifest(dest_vfs, &manifest, &checksums).map(|()| flow)
+            }
+            flow => Ok(flow),
+        });
+    let flow = match flow {
+        Ok(flow) => flow,
+        Err(err) => {
+            remove_all(dest_vfs, &written);
+            return Err(err);
+        }
+    };
+    end_file(state, size, flow);
+    match flow {
+        CopyFlow::Continue => Ok(written),
+        CopyFlow::SkipFile => {
+            remove_all(dest_vfs, &written);
+            Ok(Vec::new())
+        }
+        CopyFlow::Abort => {
+            remove_all(dest_vfs, &written);
+            Err(aborted())
+        }
+    }
+}
+
+/// write the parts one after the other, with their checksums then the one of the whole file
+#[allow(clippy::too_many_arguments)]
+fn write_parts(
+    from_vfs: &dyn Vfs,
+    from: &Path,
+    dest_vfs: &dyn Vfs,
+    dest: &Path,
+    name: &str,
+    part_size: u64,
+    written: &mut Vec<PathBuf>,
+    checksums: &mut Vec<Checksum>,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<CopyFlow> {
+    let mut reader = from_vfs.open_read(from)?;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut whole = Hasher::new(MANIFEST_ALGORITHM);
+    loop {
+        let mut part = reader.by_ref().take(part_size);
+        let mut read = part.read(&mut buffer)?;
+        // an empty file still has a part, the others have data
+        if read == 0 && !checksums.is_empty() {
+            break;
+        }
+        let part_name = part_name(name, checksums.len() + 1);
+        let path = dest.join(&part_name);
+        let mut writer = dest_vfs.open_write(&path)?;
+        written.push(path);
+        let mut hasher = Hasher::new(MANIFEST_ALGORITHM);
+        while read > 0 {
+            writer.write_all(&buffer[..read])?;
+            hasher.update(&buffer[..read]);
+            whole.update(&buffer[..read]);
+            state.bytes_copied += read as u64;
+            state.file_bytes += read as u64;
+            let flow = progress(state);
+            if flow != CopyFlow::Continue {
+                return Ok(flow);
+            }
+            read = part.read(&mut buffer)?;
+        }
+        writer.flush()?;
+        checksums.push(Checksum {
+            digest: hasher.finish_hex(),
+            name: part_name,
+        });
+    }
+    checksums.push(Checksum {
+        digest: whole.finish_hex(),
+        name: name.to_string(),
+    });
+    Ok(CopyFlow::Continue)
+}
+
+/// join the parts of a split file, `part` being any of them, into the directory `dest`
+///
+/// When a manifest of one of the checksum algorithms lies next to the parts,
+/// each part and the joined file are checked against it, a join that does not
+/// match is removed. An existing file is not overwritten. Returns the joined
+/// file and the manifest it was checked against, `None` when skipped.
+pub fn join_parts(
+    vfs: &dyn Vfs,
+    part: &Path,
+    dest_vfs: &dyn Vfs,
+    dest: &Path,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<Option<(PathBuf, Option<PathBuf>)>> {
+    let (name, parts) = parts_of(vfs, part)?;
+    let dir = part.parent().unwrap_or(Path::new("/"));
+    let joined = dest.join(&name);
+    if dest_vfs.metadata(&joined).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", joined.display())));
+    }
+    let manifest = find_manifest(vfs, dir, &name);
+    let size = parts.iter().map(|(_, size)| size).sum();
+
+    begin_file(state, &joined, size, 0);
+    let flow = match append_parts(vfs, &parts, &name, manifest.as_ref(), dest_vfs, &joined, state, progress) {
+        Ok(flow) => flow,
+        Err(err) => {
+            remove_all(dest_vfs, &[joined]);
+            return Err(err);
+        }
+    };
+    end_file(state, size, flow);
+    match flow {
+        CopyFlow::Continue => Ok(Some((joined, manifest.map(|(path, ..)| path)))),
+        CopyFlow::SkipFile => {
+            remove_all(dest_vfs, &[joined]);
+            Ok(None)
+        }
+        CopyFlow::Abort => {
+            remove_all(dest_vfs, &[joined]);
+            Err(aborted())
+        }
+    }
+}
+
+/// a manifest with the digests by name
+type Manifest = (PathBuf, HashAlgorithm, HashMap<String, String>);
+
+/// the manifest of `name` in `dir`, the first of the algorithms found
+fn find_manifest(vfs: &dyn Vfs, dir: &Path, name: &str) -> Option<Manifest> {
+    HashAlgorithm::ALL.into_iter().find_map(|algorithm| {
+        let path = dir.join(format!("{name}.{}", algorithm.manifest_extension()));
+        let mut text = String::new();
+        vfs.open_read(&path).ok()?.read_to_string(&mut text).ok()?;
+        let digests = hash::parse_manifest(&text)
+            .into_iter()
+            .map(|checksum| (checksum.name, checksum.digest))
+            .collect();
+        Some((path, algorithm, digests))
+    })
+}
+
+/// write the parts one after the other into `joined`, checking them if there is a manifest
+#[allow(clippy::too_many_arguments)]
+fn append_parts(
+    vfs: &dyn Vfs,
+    parts: &[(PathBuf, u64)],
+    name: &str,
+    manifest: Option<&Manifest>,
+    dest_vfs: &dyn Vfs,
+    joined: &Path,
+    state: &mut CopyProgress,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<CopyFlow> {
+    let mismatch = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{what} does not match its checksum"));
+    let algorithm = manifest.map(|(_, algorithm, _)| *algorithm).unwrap_or(MANIFEST_ALGORITHM);
+    // a part the manifest has and the panel not would leave a hole
+    if let Some((_, _, digests)) = manifest {
+        let missing = part_name(name, parts.len() + 1);
+        if digests.contains_key(&missing) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{missing} is missing")));
+        }
+    }
+
+    let mut writer = dest_vfs.open_write(joined)?;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut whole = Hasher::new(algorithm);
+    for (path, _) in parts {
+        let mut reader = vfs.open_read(path)?;
+        let mut hasher = Hasher::new(algorithm);
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..read])?;
+            hasher.update(&buffer[..read]);
+            whole.update(&buffer[..read]);
+            state.bytes_copied += read as u64;
+            state.file_bytes += read as u64;
+            let flow = progress(state);
+            if flow != CopyFlow::Continue {
+                return Ok(flow);
+            }
+        }
+        let part = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        if let Some((_, _, digests)) = manifest
+            && digests.get(&part).is_some_and(|digest| *digest != hasher.finish_hex())
+        {
+            return Err(mismatch(&part));
+        }
+    }
+    writer.flush()?;
+    if let Some((_, _, digests)) = manifest
+        && digests.get(name).is_some_and(|digest| *digest != whole.finish_hex())
+    {
+        return Err(mismatch(name));
+    }
+    Ok(CopyFlow::Continue)
+}
+
+/// remove what a stopped split or join wrote, the last first
+fn remove_all(vfs: &dyn Vfs, paths: &[PathBuf]) {
+    for path in paths.iter().rev() {
+        let _ = vfs.remove(path);
+    }
+}
@@ -0,0 +1,1334 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::associations::Associations;
+use crate::config::{ConfirmConfig, FormatConfig, PanelConfig};
+use crate::escalation::{Escalation, Privileged};
+use crate::event::AppEvent;
+use crate::criteria::SelectCriteria;
+use crate::filter::{NameFilter, PanelFilter, PanelSearch};
+use crate::fs_error::FsError;
+use crate::fs_node::{FsNode, FsNodeRef};
+use crate::fsinfo::FsStatus;
+use crate::history::{DirHistory, Visit};
+use crate::job::Extraction;
+use crate::info::InfoView;
+use crate::journal::Journal;
+use crate::ops::{CopyOptions, ShredOptions};
+use crate::paths;
+use crate::user_menu::UserMenu;
+use crate::sort::SortMode;
+use crate::tree::TreeView;
+use crate::vfs::{self, VfsEntry, VfsRef};
+
+/// column of the file listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Name,
+    /// size in bytes
+    Size,
+    /// size with a unit, like `12.5M`
+    #[serde(rename = "human-size")]
+    HumanSize,
+    /// modification time, written with the time format of the panel
+    #[serde(alias = "mtime")]
+    Modified,
+    /// permissions as `drwxr-xr-x`
+    #[serde(alias = "perm")]
+    Permissions,
+    /// name of the owning user, its id when it has none
+    Owner,
+    /// where a symbolic link points
+    #[serde(rename = "link-target", alias = "link")]
+    LinkTarget,
+    /// directory holding the entry, shown by virtual listings whose entries come from anywhere
+    Path,
+}
+
+impl Column {
+    /// the columns of the long layout
+    pub const ALL: [Column; 6] =
+        [Column::Permissions, Column::Owner, Column::HumanSize, Column::Modified, Column::Name, Column::LinkTarget];
+}
+
+/// a list of files from anywhere shown in place of a directory, like search results
+///
+/// The root of the panel stays the directory the list was made from, it is
+/// where going to the parent leads. The entries keep their own names and
+/// paths, every operation works on the files where they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualListing {
+    /// what the files are, written in the frame of the panel
+    pub title: String,
+}
+
+/// entries cut or copied from a panel, waiting to be pasted into a directory
+#[derive(Debug, Clone)]
+pub struct FileClipboard {
+    pub nodes: Vec<FsNodeRef>,
+    /// the entries move on paste instead of being copied
+    pub moving: bool,
+}
+
+/// entries dropped with the mouse on a directory, kept while asked what to do with existing ones
+#[derive(Debug, Clone)]
+pub struct FileDrop {
+    pub nodes: Vec<FsNodeRef>,
+    pub dest: FsNodeRef,
+}
+
+/// what of a panel entries dragged with the mouse are over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropTarget {
+    /// the directory the panel shows
+    Directory,
+    /// a directory among the entries, by index
+    Entry(usize),
+}
+
+/// a directory listing of a remote backend still coming in
+///
+/// A thread of its own reads the pages, the panel adds them as they arrive.
+/// Dropping the listing stops the thread after the page it is reading.
+#[derive(Debug)]
+pub struct Loading {
+    pages: Receiver<io::Result<Vec<VfsEntry>>>,
+    /// entries arrived so far
+    pub count: usize,
+    /// entry to put the cursor on once it arrives
+    cursor_path: Option<PathBuf>,
+}
+
+impl Loading {
+    fn start(vfs: &VfsRef, path: &Path) -> Loading {
+        let (sender, pages) = mpsc::channel();
+        let vfs = Arc::clone(vfs);
+        let path = path.to_path_buf();
+        thread::spawn(move || {
+            // a panel gone elsewhere dropped the receiver, the listing stops at the next page
+            if let Err(err) = vfs.read_dir_pages(&path, &mut |page| sender.send(Ok(page)).is_ok()) {
+                let _ = sender.send(Err(err));
+            }
+        });
+        Loading {
+            pages,
+            count: 0,
+            cursor_path: None,
+        }
+    }
+}
+
+/// how a panel lays its entries out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// one entry per row with the configured columns
+    #[default]
+    Full,
+    /// names only, side by side in several columns
+    Brief,
+    /// one entry per row with every column, like `ls -l`
+    Long,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 3] = [Layout::Full, Layout::Brief, Layout::Long];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Layout::Full => "Full",
+            Layout::Brief => "Brief",
+            Layout::Long => "Long",
+        }
+    }
+
+    /// the layout after this one, back to the first after the last
+    pub fn next(self) -> Layout {
+        let index = Layout::ALL.iter().position(|layout| *layout == self).unwrap_or(0);
+        Layout::ALL[(index + 1) % Layout::ALL.len()]
+    }
+}
+
+/// the marked paths of a panel, counting its changes
+///
+/// The marked entries are worked out again from the listing only once the
+/// revision moved on, see [`Panel::marked_nodes`]. Reading goes through
+/// the set of paths itself.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    paths: HashSet<PathBuf>,
+    revision: u64,
+}
+
+impl Selection {
+    /// true if the path was not marked
+    pub fn insert(&mut self, path: PathBuf) -> bool {
+        let inserted = self.paths.insert(path);
+        self.revision += inserted as u64;
+        inserted
+    }
+
+    /// true if the path was marked
+    pub fn remove(&mut self, path: &Path) -> bool {
+        let removed = self.paths.remove(path);
+        self.revision += removed as u64;
+        removed
+    }
+
+    pub fn extend(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        self.paths.extend(paths);
+        self.revision += 1;
+    }
+
+    pub fn retain(&mut self, keep: impl FnMut(&PathBuf) -> bool) {
+        let len = self.paths.len();
+        self.paths.retain(keep);
+        self.revision += (self.paths.len() != len) as u64;
+    }
+
+    pub fn clear(&mut self) {
+        if !self.paths.is_empty() {
+            self.paths.clear();
+            self.revision += 1;
+        }
+    }
+
+    /// mark `paths` in place of the marked ones
+    pub fn replace(&mut self, paths: HashSet<PathBuf>) {
+        self.paths = paths;
+        self.revision += 1;
+    }
+
+    pub fn paths(&self) -> &HashSet<PathBuf> {
+        &self.paths
+    }
+
+    /// a value moving on with each change of the marks
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+}
+
+impl std::ops::Deref for Selection {
+    type Target = HashSet<PathBuf>;
+
+    fn deref(&self) -> &HashSet<PathBuf> {
+        &self.paths
+    }
+}
+
+/// the listed entries of a panel, kept from one frame to the next
+///
+/// Hidden files and the filters leave entries out, finding them again for
+/// every frame would walk a directory of half a million entries several
+/// times. The view is made again once the children of the directory
+/// changed, see [`FsNode::generation`], or what leaves entries out did.
+#[derive(Debug, Default)]
+struct EntryView {
+    key: Option<ViewKey>,
+    entries: Vec<FsNodeRef>,
+    /// the marked ones of the entries, with the revision of the selection they were found for
+    marked: Option<(u64, Vec<FsNodeRef>)>,
+}
+
+/// what the entries of an [`EntryView`] were worked out from
+#[derive(Debug, Clone, PartialEq)]
+struct ViewKey {
+    generation: u64,
+    show_hidden: bool,
+    filter: Option<String>,
+    name_filter: Option<(String, bool)>,
+}
+
+#[derive(Debug)]
+pub struct Panel {
+    pub root: FsNodeRef,
+    pub cursor: usize,
+    pub offset: usize,
+    pub sort_mode: SortMode,
+    pub selection: Selection,
+    /// list entries whose name starts with a dot
+    pub show_hidden: bool,
+    /// columns of the full layout
+    pub columns: Vec<Column>,
+    pub layout: Layout,
+    /// columns of names in the brief layout
+    pub brief_columns: usize,
+    /// Nerd Font icons in front of the names
+    pub icons: bool,
+    /// the files listed when they are not the content of a directory
+    pub listing: Option<VirtualListing>,
+    /// quick search narrowing the listing while the user types
+    pub filter: Option<PanelFilter>,
+    /// incremental search moving the cursor while the user types
+    pub search: Option<PanelSearch>,
+    /// where the entries dragged with the mouse would go if let go now
+    pub drop_target: Option<DropTarget>,
+    /// pattern set with the filter dialog, kept from one directory to the next
+    pub name_filter: Option<NameFilter>,
+    /// directory tree shown instead of the listing
+    pub tree: Option<TreeView>,
+    /// details of the entry under the cursor of the other panel shown instead of the listing
+    pub info: Option<InfoView>,
+    /// directories shown so far, for back and forward
+    pub history: DirHistory,
+    /// remote listing still arriving, its entries are added as they come
+    pub loading: Option<Loading>,
+    /// filesystem of the displayed directory, read again after a reload
+    fs_status: Option<FsStatus>,
+    view: Mutex<EntryView>,
+}
+
+impl Panel {
+    /// open a panel on a directory
+    pub fn new(path: &Path) -> io::Result<Panel> {
+        let (root, loading) = load_directory(&vfs::local(), path)?;
+        Panel::with_root(root, loading)
+    }
+
+    /// open a panel on a local path or a remote url
+    pub fn open(location: &str) -> io::Result<Panel> {
+        let (vfs, path) = vfs::open_location(location)?;
+        let (root, loading) = load_directory(&vfs, &path)?;
+        Panel::with_root(root, loading)
+    }
+
+    fn with_root(root: FsNodeRef, loading: Option<Loading>) -> io::Result<Panel> {
+        let mut panel = Panel {
+            root,
+            cursor: 0,
+            offset: 0,
+            sort_mode: SortMode::default(),
+            selection: Selection::default(),
+            show_hidden: true,
+            columns: vec![Column::Name, Column::Size, Column::Modified],
+            layout: Layout::Full,
+            brief_columns: 2,
+            icons: false,
+            listing: None,
+            filter: None,
+            search: None,
+            drop_target: None,
+            name_filter: None,
+            tree: None,
+            info: None,
+            history: DirHistory::new(),
+            loading,
+            fs_status: None,
+            view: Mutex::new(EntryView::default()),
+        };
+        panel.record_visit();
+        panel.apply_sort();
+        // a fresh listing starts at the top, not wherever the unsorted first entry went
+        panel.cursor = 0;
+        Ok(panel)
+    }
+
+    /// path of the directory displayed by the panel
+    pub fn current_path(&self) -> PathBuf {
+        self.root.read().path()
+    }
+
+    /// location of the displayed directory as shown to the user
+    pub fn display_location(&self) -> String {
+        let root = self.root.read();
+        root.vfs.display_path(&root.path())
+    }
+
+    /// filesystem backend of the displayed directory
+    pub fn vfs(&self) -> VfsRef {
+        Arc::clone(&self.root.read().vfs)
+    }
+
+    /// apply the panel defaults of the configuration
+    pub fn configure(&mut self, config: &PanelConfig) {
+        self.show_hidden = config.show_hidden;
+        self.columns = config.columns.clone();
+        self.layout = config.layout;
+        self.brief_columns = config.brief_columns.max(1);
+        self.icons = config.icons;
+        self.set_sort_mode(config.sort);
+        self.cursor = 0;
+    }
+
+    /// a new panel on the same directory with the same settings and history
+    ///
+    /// The selection is not shared, each panel keeps its own.
+    pub fn duplicate(&self) -> io::Result<Panel> {
+        let (root, loading) = load_directory(&self.vfs(), &self.current_path())?;
+        let mut panel = Panel::with_root(root, loading)?;
+        panel.show_hidden = self.show_hidden;
+        panel.columns = self.columns.clone();
+        panel.layout = self.layout;
+        panel.brief_columns = self.brief_columns;
+        panel.icons = self.icons;
+        panel.name_filter = self.name_filter.clone();
+        panel.history = self.history.clone();
+        panel.set_sort_mode(self.sort_mode);
+        panel.cursor = self.cursor;
+        panel.clamp_cursor();
+        Ok(panel)
+    }
+
+    /// filesystem and free space of the displayed directory, `None` off the local filesystem
+    pub fn fs_status(&mut self) -> Option<&FsStatus> {
+        let path = self.vfs().local_path(&self.current_path())?;
+        if self.fs_status.as_ref().is_none_or(|status| status.path != path) {
+            self.fs_status = Some(FsStatus::read(&path));
+        }
+        self.fs_status.as_ref()
+    }
+
+    /// visible children of the displayed directory in display order
+    ///
+    /// This copies the whole listing, the rows shown take [`Panel::entry_window`].
+    pub fn entries(&self) -> Vec<FsNodeRef> {
+        self.with_entries(<[FsNodeRef]>::to_vec)
+    }
+
+    /// number of visible children
+    pub fn entry_count(&self) -> usize {
+        self.with_entries(<[FsNodeRef]>::len)
+    }
+
+    /// visible child at a position of the listing
+    pub fn entry(&self, index: usize) -> Option<FsNodeRef> {
+        self.with_entries(|entries| entries.get(index).cloned())
+    }
+
+    /// up to `count` visible children from the position `first` on, the rows of a window
+    pub fn entry_window(&self, first: usize, count: usize) -> Vec<FsNodeRef> {
+        self.with_entries(|entries| entries.iter().skip(first).take(count).cloned().collect())
+    }
+
+    /// run `read` on the visible children, worked out again first when they changed
+    fn with_entries<T>(&self, read: impl FnOnce(&[FsNodeRef]) -> T) -> T {
+        self.with_view(|view| read(&view.entries))
+    }
+
+    fn with_view<T>(&self, read: impl FnOnce(&mut EntryView) -> T) -> T {
+        let key = ViewKey {
+            generation: self.root.read().generation(),
+            show_hidden: self.show_hidden,
+            filter: self.filter.as_ref().map(|filter| filter.query.clone()),
+            name_filter: self.name_filter.as_ref().map(|filter| (filter.pattern.clone(), filter.case_sensitive)),
+        };
+        let mut view = self.view.lock();
+        if view.key.as_ref() != Some(&key) {
+            view.entries = self.visible_children();
+            view.marked = None;
+            view.key = Some(key);
+        }
+        read(&mut view)
+    }
+
+    fn visible_children(&self) -> Vec<FsNodeRef> {
+        let root = self.root.read();
+        if self.show_hidden && self.filter.is_none() && self.name_filter.is_none() {
+            return root.children.clone();
+        }
+        root.children
+            .iter()
+            .filter(|child| {
+                let child = child.read();
+                (self.show_hidden || !child.is_hidden())
+                    && self.filter.as_ref().is_none_or(|filter| filter.score(&child.name).is_some())
+                    && self.name_filter.as_ref().is_none_or(|filter| child.is_navigable() || filter.matches(&child.name))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// list the dotfiles or hide them, the cursor stays on its entry when still listed
+    pub fn set_show_hidden(&mut self, show_hidden: bool) {
+        self.keep_cursor(|panel| panel.show_hidden = show_hidden);
+    }
+
+    /// restrict the listed files to those matching a pattern, or list them all again
+    pub fn set_name_filter(&mut self, name_filter: Option<NameFilter>) {
+        self.keep_cursor(|panel| panel.name_filter = name_filter);
+    }
+
+    /// change what is listed and put the cursor back on its entry, or clamp it
+    fn keep_cursor(&mut self, change: impl FnOnce(&mut Panel)) {
+        let cursor_path = self.cursor_node().map(|node| node.read().path());
+        change(self);
+        if let Some(position) = cursor_path.and_then(|path| self.position_of(&path)) {
+            self.cursor = position;
+        }
+        self.clamp_cursor();
+    }
+
+    /// columns of the rows of the full and long layouts
+    ///
+    /// Virtual listings show the directory of their entries after the names.
+    pub fn layout_columns(&self) -> Vec<Column> {
+        let mut columns = match self.layout {
+            Layout::Full => self.columns.clone(),
+            Layout::Brief => return vec![Column::Name],
+            Layout::Long => Column::ALL.to_vec(),
+        };
+        if self.listing.is_some() && !columns.contains(&Column::Path) {
+            let after = columns.iter().position(|column| *column == Column::Name).map_or(0, |name| name + 1);
+            columns.insert(after, Column::Path);
+        }
+        columns
+    }
+
+    /// switch between the listing and a tree of the backend around the displayed directory
+    pub fn toggle_tree(&mut self) -> io::Result<()> {
+        if self.tree.take().is_none() {
+            self.tree = Some(TreeView::open(&self.vfs(), &self.current_path())?);
+        }
+        Ok(())
+    }
+
+    /// start narrowing the listing down by typing
+    pub fn start_filter(&mut self) {
+        let origin = self.cursor_node().map(|node| node.read().path());
+        self.filter = Some(PanelFilter::new(origin));
+    }
+
+    /// change the filter query and put the cursor on the best match
+    pub fn set_filter_query(&mut self, query: &str) {
+        let Some(filter) = self.filter.as_mut() else {
+            return;
+        };
+        filter.query = query.to_string();
+        let best = self
+            .entries()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| self.filter.as_ref()?.score(&node.read().name).map(|score| (score, index)))
+            // the first of equally good matches
+            .max_by_key(|&(score, index)| (score, std::cmp::Reverse(index)))
+            .map(|(_, index)| index);
+        self.cursor = best.unwrap_or(0);
+        self.offset = 0;
+    }
+
+    /// show the full listing again with the cursor on the chosen entry
+    pub fn accept_filter(&mut self) {
+        let chosen = self.cursor_node().map(|node| node.read().path());
+        self.close_filter(chosen);
+    }
+
+    /// show the full listing again with the cursor where it was before filtering
+    pub fn cancel_filter(&mut self) {
+        let origin = self.filter.as_ref().and_then(|filter| filter.origin.clone());
+        self.close_filter(origin);
+    }
+
+    fn close_filter(&mut self, cursor_path: Option<PathBuf>) {
+        self.filter = None;
+        self.cursor = cursor_path
+            .and_then(|path| self.position_of(&path))
+            .unwrap_or(0);
+        self.clamp_cursor();
+    }
+
+    /// start moving the cursor to the names typed
+    pub fn start_search(&mut self) {
+        self.search = Some(PanelSearch::default());
+    }
+
+    /// add a letter to the search, the cursor goes to the next name starting with it
+    ///
+    /// The entry under the cursor is tried first, the search wraps around at
+    /// the end of the listing. A letter no name goes on with is not added.
+    pub fn extend_search(&mut self, c: char) {
+        let Some(search) = self.search.as_ref() else {
+            return;
+        };
+        let query = format!("{}{c}", search.query);
+        if let Some(index) = self.next_match(&query, self.cursor) {
+            self.cursor = index;
+            self.search = Some(PanelSearch { query });
+        }
+    }
+
+    /// go to the next name matching the search, from the top after the last one
+    pub fn search_next(&mut self) {
+        let Some(search) = self.search.as_ref() else {
+            return;
+        };
+        if let Some(index) = self.next_match(&search.query, self.cursor + 1) {
+            self.cursor = index;
+        }
+    }
+
+    /// take the last letter off the search, the cursor stays on a name that still matches
+    pub fn widen_search(&mut self) {
+        if let Some(search) = self.search.as_mut() {
+            search.query.pop();
+        }
+    }
+
+    pub fn stop_search(&mut self) {
+        self.search = None;
+    }
+
+    /// position of the first entry starting with `query` from `start` on, wrapping around
+    fn next_match(&self, query: &str, start: usize) -> Option<usize> {
+        self.with_entries(|entries| {
+            let count = entries.len();
+            (0..count)
+                .map(|step| (start + step) % count)
+                .find(|&index| PanelSearch::matches(query, &entries[index].read().name))
+        })
+    }
+
+    /// node under the cursor, if the directory is not empty
+    pub fn cursor_node(&self) -> Option<FsNodeRef> {
+        self.entry(self.cursor)
+    }
+
+    pub fn move_cursor_up(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_cursor_down(&mut self) {
+        let len = self.entry_count();
+        if self.cursor + 1 < len {
+            self.cursor += 1;
+        }
+    }
+
+    pub fn move_cursor_top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_cursor_bottom(&mut self) {
+        self.cursor = self.entry_count().saturating_sub(1);
+    }
+
+    /// display another directory of the same backend, clearing the selection
+    pub fn change_directory(&mut self, path: &Path) -> io::Result<()> {
+        self.change_directory_in(self.vfs(), path)
+    }
+
+    /// display a directory of any backend, clearing the selection
+    pub fn change_directory_in(&mut self, vfs: VfsRef, path: &Path) -> io::Result<()> {
+        self.load(vfs, path)?;
+        self.record_visit();
+        Ok(())
+    }
+
+    fn record_visit(&mut self) {
+        let location = self.display_location();
+        self.history.visit(&location, self.vfs(), &self.current_path());
+    }
+
+    /// continue the history of a past session from the displayed directory
+    pub fn restore_history(&mut self, locations: Vec<String>) {
+        self.history = DirHistory::from_locations(locations);
+        self.record_visit();
+    }
+
+    /// go back or forward in the history, returns how many stale directories were dropped
+    ///
+    /// Directories that went away since they were visited are forgotten and
+    /// the next one in the same direction is shown instead.
+    pub fn history_step(&mut self, forward: bool) -> io::Result<usize> {
+        let mut dropped = 0;
+        loop {
+            let from = self.history.position();
+            let Some(visit) = self.history.step(forward) else {
+                return Ok(dropped);
+            };
+            match self.show_visit(&visit) {
+                Ok(()) => return Ok(dropped),
+                Err(err) if is_stale(&err) => {
+                    self.history.discard(from);
+                    dropped += 1;
+                }
+                Err(err) => {
+                    self.history.go_to(from);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// show a visit picked in the history, a stale one is forgotten
+    pub fn history_jump(&mut self, index: usize) -> io::Result<()> {
+        let from = self.history.position();
+        let Some(visit) = self.history.go_to(index) else {
+            return Ok(());
+        };
+        let result = self.show_visit(&visit);
+        match &result {
+            Ok(()) => {}
+            Err(err) if is_stale(err) => self.history.discard(from),
+            Err(_) => {
+                self.history.go_to(from);
+            }
+        }
+        result
+    }
+
+    fn show_visit(&mut self, visit: &Visit) -> io::Result<()> {
+        let (vfs, path) = match &visit.target {
+            Some((vfs, path)) => (Arc::clone(vfs), path.clone()),
+            None => vfs::open_location(&visit.location)?,
+        };
+        self.load(Arc::clone(&vfs), &path)?;
+        self.history.visit(&visit.location, vfs, &path);
+        Ok(())
+    }
+
+    fn load(&mut self, vfs: VfsRef, path: &Path) -> io::Result<()> {
+        (self.root, self.loading) = load_directory(&vfs, path)?;
+        self.cursor = 0;
+        self.offset = 0;
+        self.selection.clear();
+        self.listing = None;
+        self.filter = None;
+        self.search = None;
+        self.apply_sort();
+        self.cursor = 0;
+        Ok(())
+    }
+
+    /// list entries found anywhere as a virtual listing made from `start`
+    ///
+    /// The directory of each entry is shown in a column of its own. Navigating
+    /// away or going to the parent brings back the real directory listing.
+    pub fn panelize(&mut self, title: &str, start: &Path, entries: Vec<VfsEntry>) -> io::Result<()> {
+        let vfs = self.vfs();
+        let mut root = FsNode::from_entry(vfs.metadata(start)?, Arc::clone(&vfs));
+        for entry in entries {
+            root.add_child(FsNode::from_entry(entry, Arc::clone(&vfs)));
+        }
+        self.root = root.into_ref();
+        self.loading = None;
+        self.cursor = 0;
+        self.offset = 0;
+        self.selection.clear();
+        self.listing = Some(VirtualListing {
+            title: title.to_string(),
+        });
+        self.filter = None;
+        self.search = None;
+        self.apply_sort();
+        self.cursor = 0;
+        Ok(())
+    }
+
+    /// enter the directory under the cursor, following symlinks to directories
+    ///
+    /// Supported archives are entered as directories.
+    pub fn enter(&mut self) -> io::Result<bool> {
+        let Some(node) = self.cursor_node() else {
+            return Ok(false);
+        };
+        let (vfs, path, navigable) = {
+            let node = node.read();
+            (Arc::clone(&node.vfs), node.path(), node.is_navigable())
+        };
+        if navigable {
+            let result = self.change_directory(&path);
+            // the entry keeps why it could not be entered, the listing marks it
+            node.write().error = result.as_ref().err().map(|err| FsError::new(&path, err));
+            result?;
+            return Ok(true);
+        }
+        match vfs::open_archive(&vfs, &path) {
+            Some(archive) => {
+                self.change_directory_in(archive?, &path)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// go to the parent directory and put the cursor on the directory we left
+    ///
+    /// Leaving the root of an archive goes back to the directory holding it.
+    pub fn go_parent(&mut self) -> io::Result<bool> {
+        let current = self.current_path();
+        if self.listing.is_some() {
+            self.change_directory(&current)?;
+            return Ok(true);
+        }
+        let Some(parent) = current.parent() else {
+            return Ok(false);
+        };
+        match self.vfs().host() {
+            Some((host, root)) if root == current => self.change_directory_in(host, parent)?,
+            _ => self.change_directory(parent)?,
+        }
+        self.place_cursor(&current);
+        Ok(true)
+    }
+
+    /// display the directory holding `path` with the cursor on it
+    pub fn reveal(&mut self, path: &Path) -> io::Result<()> {
+        let parent = path.parent().unwrap_or(path);
+        self.change_directory(parent)?;
+        self.place_cursor(path);
+        Ok(())
+    }
+
+    /// put the cursor on an entry, or on it once it arrives while the listing is loading
+    fn place_cursor(&mut self, path: &Path) {
+        match self.position_of(path) {
+            Some(position) => self.cursor = position,
+            None => {
+                if let Some(loading) = self.loading.as_mut() {
+                    loading.cursor_path = Some(path.to_path_buf());
+                }
+            }
+        }
+    }
+
+    /// add the entries of the remote listing that arrived since the last call
+    ///
+    /// Returns the error that stopped the listing, the entries read before it stay.
+    pub fn poll_loading(&mut self) -> Option<io::Error> {
+        let loading = self.loading.as_mut()?;
+        let mut added = false;
+        let mut failed = None;
+        let finished = loop {
+            match loading.pages.try_recv() {
+                Ok(Ok(page)) => {
+                    let mut root = self.root.write();
+                    loading.count += page.len();
+                    for entry in page {
+                        let vfs = Arc::clone(&root.vfs);
+                        root.add_child(FsNode::from_entry(entry, vfs));
+                    }
+                    added = true;
+                }
+                Ok(Err(err)) => {
+                    let mut root = self.root.write();
+                    root.error = Some(FsError::new(&root.path(), &err));
+                    failed = Some(err);
+                    break true;
+                }
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+        if added {
+            let cursor_path = loading.cursor_path.clone();
+            self.apply_sort();
+            if let Some(position) = cursor_path.and_then(|path| self.position_of(&path)) {
+                self.cursor = position;
+                if let Some(loading) = self.loading.as_mut() {
+                    loading.cursor_path = None;
+                }
+            }
+            self.clamp_cursor();
+        }
+        if finished {
+            self.loading = None;
+        }
+        failed
+    }
+
+    /// re-read the displayed directory, keeping the cursor on the same entry when possible
+    pub fn reload(&mut self) -> io::Result<()> {
+        let cursor_path = self.cursor_node().map(|node| node.read().path());
+        if self.listing.is_some() {
+            // search results are not re-run, entries that went away are dropped
+            let vfs = self.vfs();
+            let mut root = self.root.write();
+            root.children.retain(|child| vfs.metadata(&child.read().path()).is_ok());
+            root.children_changed();
+        } else if self.vfs().remote().is_some() {
+            // read again in the background like when it was entered, the cursor finds its entry back
+            let mut loading = Loading::start(&self.vfs(), &self.current_path());
+            loading.cursor_path = cursor_path;
+            {
+                let mut root = self.root.write();
+                root.children.clear();
+                root.children_changed();
+                root.computed_size = None;
+                root.error = None;
+            }
+            self.loading = Some(loading);
+            self.cursor = 0;
+            self.fs_status = None;
+            return Ok(());
+        } else {
+            self.root.write().rescan()?;
+        }
+        self.apply_sort();
+        // marks are checked on the backend of the panel, an archive member is no local path
+        let vfs = self.vfs();
+        self.selection.retain(|path| vfs.metadata(path).is_ok());
+        self.cursor = cursor_path
+            .and_then(|path| self.position_of(&path))
+            .unwrap_or(self.cursor);
+        self.clamp_cursor();
+        self.fs_status = None;
+        Ok(())
+    }
+
+    /// index of a child of the displayed directory
+    pub fn position_of(&self, path: &Path) -> Option<usize> {
+        self.with_entries(|entries| entries.iter().position(|child| child.read().path() == path))
+    }
+
+    /// adjust the scroll offset so the cursor is visible in a list of `height` rows
+    pub fn scroll_to_cursor(&mut self, height: usize) {
+        if height == 0 {
+            return;
+        }
+        // a listing that shrank fills the rows again rather than showing them empty past its end
+        self.offset = self.offset.min(self.entry_count().saturating_sub(height));
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + height {
+            self.offset = self.cursor + 1 - height;
+        }
+    }
+
+    pub fn set_sort_mode(&mut self, sort_mode: SortMode) {
+        self.sort_mode = sort_mode;
+        self.apply_sort();
+    }
+
+    /// add or remove a path from the selection
+    pub fn toggle_selection(&mut self, path: &Path) {
+        if !self.selection.remove(path) {
+            self.selection.insert(path.to_path_buf());
+        }
+    }
+
+    pub fn is_selected(&self, path: &Path) -> bool {
+        self.selection.contains(path)
+    }
+
+    /// mark or unmark the cursor entry and move to the next one
+    pub fn toggle_mark(&mut self) {
+        if let Some(node) = self.cursor_node() {
+            let path = node.read().path();
+            self.toggle_selection(&path);
+        }
+        self.move_cursor_down();
+    }
+
+    /// mark (or unmark) every visible file whose name matches a glob pattern
+    ///
+    /// Directories are only matched when `directories` is set, as in mc.
+    /// Returns the number of entries that matched.
+    pub fn mark_matching(&mut self, pattern: &str, mark: bool, directories: bool) -> Result<usize, glob::PatternError> {
+        let pattern = glob::Pattern::new(pattern)?;
+        let options = glob::MatchOptions {
+            case_sensitive: self.sort_mode.case_sensitive,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        let mut matched = 0;
+        for node in self.entries() {
+            let node = node.read();
+            if (!directories && node.is_navigable()) || !pattern.matches_with(&node.name, options) {
+                continue;
+            }
+            matched += 1;
+            if mark {
+                self.selection.insert(node.path());
+            } else {
+                self.selection.remove(&node.path());
+            }
+        }
+        Ok(matched)
+    }
+
+    /// mark (or unmark) every visible entry meeting the criteria, returns how many did
+    pub fn mark_by_criteria(&mut self, criteria: &SelectCriteria, mark: bool) -> usize {
+        let mut matched = 0;
+        for node in self.entries() {
+            let node = node.read();
+            if !criteria.matches(&node, self.sort_mode.case_sensitive) {
+                continue;
+            }
+            matched += 1;
+            if mark {
+                self.selection.insert(node.path());
+            } else {
+                self.selection.remove(&node.path());
+            }
+        }
+        matched
+    }
+
+    /// mark the listed entries of `saved` in place of the marks, returns how many are listed
+    ///
+    /// The entries are found by path, a reread directory or one left and
+    /// listed again gets its marks back.
+    pub fn restore_selection(&mut self, saved: &HashSet<PathBuf>) -> usize {
+        let marked = self.with_entries(|entries| entries.iter().map(|node| node.read().path()).filter(|path| saved.contains(path)).collect());
+        self.selection.replace(marked);
+        self.selection.len()
+    }
+
+    /// mark the unmarked files and unmark the marked ones, directories are left alone
+    pub fn invert_marks(&mut self) {
+        for node in self.entries() {
+            let node = node.read();
+            if node.is_navigable() {
+                continue;
+            }
+            self.toggle_selection(&node.path());
+        }
+    }
+
+    /// marked entries of the displayed directory in display order
+    pub fn marked_nodes(&self) -> Vec<FsNodeRef> {
+        self.with_marked(<[FsNodeRef]>::to_vec)
+    }
+
+    /// number of marked entries and the size they add up to, directories counting once computed
+    pub fn marked_total(&self) -> (usize, u64) {
+        self.with_marked(|marked| (marked.len(), marked.iter().filter_map(|node| node.read().size()).sum()))
+    }
+
+    /// run `read` on the marked entries, found again only after the listing or the marks changed
+    fn with_marked<T>(&self, read: impl FnOnce(&[FsNodeRef]) -> T) -> T {
+        if self.selection.is_empty() {
+            return read(&[]);
+        }
+        let revision = self.selection.revision();
+        self.with_view(|view| {
+            if view.marked.as_ref().is_none_or(|(seen, _)| *seen != revision) {
+                let marked = view.entries.iter().filter(|node| self.is_selected(&node.read().path())).cloned().collect();
+                view.marked = Some((revision, marked));
+            }
+            read(view.marked.as_ref().map_or(&[], |(_, marked)| marked.as_slice()))
+        })
+    }
+
+    /// nodes an operation applies to, the marked entries or else the cursor entry
+    pub fn targets(&self) -> Vec<FsNodeRef> {
+        let marked = self.marked_nodes();
+        if marked.is_empty() {
+            return self.cursor_node().into_iter().collect();
+        }
+        marked
+    }
+
+    /// re-apply sorting and cursor bounds after the tree changed underneath the panel
+    pub fn refresh_view(&mut self) {
+        self.apply_sort();
+        self.clamp_cursor();
+        self.fs_status = None;
+    }
+
+    fn apply_sort(&mut self) {
+        let cursor_path = self.cursor_node().map(|node| node.read().path());
+        self.root.write().sort_children(self.sort_mode);
+        if let Some(position) = cursor_path.and_then(|path| self.position_of(&path)) {
+            self.cursor = position;
+        }
+    }
+
+    fn clamp_cursor(&mut self) {
+        let len = self.entry_count();
+        if self.cursor >= len {
+            self.cursor = len.saturating_sub(1);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelSide {
+    Left,
+    Right,
+}
+
+impl PanelSide {
+    pub fn opposite(self) -> PanelSide {
+        match self {
+            PanelSide::Left => PanelSide::Right,
+            PanelSide::Right => PanelSide::Left,
+        }
+    }
+}
+
+/// tabs of one side that are not shown
+///
+/// The shown tab lives in the manager as `left` or `right`. The tabs read
+/// `hidden[..index]`, then the shown one, then `hidden[index..]`.
+#[derive(Debug, Default)]
+pub struct PanelTabs {
+    pub hidden: Vec<Panel>,
+    /// position of the shown tab
+    pub index: usize,
+}
+
+impl PanelTabs {
+    pub fn new() -> PanelTabs {
+        PanelTabs::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.hidden.len() + 1
+    }
+
+    /// always false, the shown tab is one
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// the tabs in order, `shown` being the one at `index`
+    pub fn iter<'a>(&'a self, shown: &'a Panel) -> impl Iterator<Item = &'a Panel> {
+        let (before, after) = self.hidden.split_at(self.index);
+        before.iter().chain(std::iter::once(shown)).chain(after)
+    }
+
+    /// show the tab at `target` instead of `shown`
+    pub fn select(&mut self, shown: &mut Panel, target: usize) {
+        if target == self.index || target >= self.len() {
+            return;
+        }
+        let hidden = if target > self.index { target - 1 } else { target };
+        let previous = std::mem::replace(shown, self.hidden.remove(hidden));
+        let back = if self.index < target { self.index } else { self.index - 1 };
+        self.hidden.insert(back, previous);
+        self.index = target;
+    }
+
+    /// add a tab right after the shown one and show it
+    pub fn open(&mut self, shown: &mut Panel, panel: Panel) {
+        let previous = std::mem::replace(shown, panel);
+        self.hidden.insert(self.index, previous);
+        self.index += 1;
+    }
+
+    /// close the shown tab and show the next one, or the previous one for the last tab
+    ///
+    /// Returns false when `shown` is the only tab, it is kept.
+    pub fn close(&mut self, shown: &mut Panel) -> bool {
+        if self.hidden.is_empty() {
+            return false;
+        }
+        if self.index == self.hidden.len() {
+            self.index -= 1;
+        }
+        *shown = self.hidden.remove(self.index);
+        true
+    }
+
+    /// move the shown tab one place left or right, returns false at the end of the strip
+    pub fn shift(&mut self, forward: bool) -> bool {
+        match forward {
+            true if self.index < self.hidden.len() => self.index += 1,
+            false if self.index > 0 => self.index -= 1,
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[derive(Debug)]
+pub struct PanelManager {
+    pub left: Panel,
+    pub right: Panel,
+    pub left_tabs: PanelTabs,
+    pub right_tabs: PanelTabs,
+    pub active: PanelSide,
+    /// file operations done from the panels, for undo
+    pub journal: Journal,
+    /// options of the copies made from the panels
+    pub copy_options: CopyOptions,
+    /// passes and pattern of the shred command
+    pub shred_options: ShredOptions,
+    /// marks saved by name, to be restored later
+    pub selections: BTreeMap<String, HashSet<PathBuf>>,
+    /// entries cut or copied for a paste
+    pub file_clipboard: Option<FileClipboard>,
+    /// entries dropped with the mouse, waiting for the answer about existing targets
+    pub file_drop: Option<FileDrop>,
+    /// commands run on files by Enter, F3 and F4
+    pub associations: Associations,
+    /// scripts offered by F2
+    pub user_menu: UserMenu,
+    /// last command of the external panelize, offered again
+    pub panelize_command: String,
+    /// directories the change directory dialog looks in for a name
+    pub cdpath: Vec<PathBuf>,
+    /// disk usage scans and searches leave out what git ignores
+    pub gitignore: bool,
+    /// how sizes and times are written
+    pub format: FormatConfig,
+    /// which operations ask before doing anything
+    pub confirm: ConfirmConfig,
+    /// program running refused operations as root, none when it is off
+    pub escalation: Option<Escalation>,
+    /// operation refused for lack of rights, waiting for the user to retry it as root
+    pub refused: Option<Privileged>,
+    /// archive members waiting for the overwrite dialog
+    pub extraction: Option<Extraction>,
+    /// wakes the event loop of the screen when background work moves on
+    pub events: Option<Sender<AppEvent>>,
+}
+
+impl PanelManager {
+    pub fn new(left: Panel, right: Panel) -> PanelManager {
+        PanelManager {
+            left,
+            right,
+            left_tabs: PanelTabs::new(),
+            right_tabs: PanelTabs::new(),
+            active: PanelSide::Left,
+            journal: Journal::new(),
+            copy_options: CopyOptions::default(),
+            shred_options: ShredOptions::default(),
+            selections: BTreeMap::new(),
+            file_clipboard: None,
+            file_drop: None,
+            associations: Associations::default(),
+            user_menu: UserMenu::default(),
+            panelize_command: String::new(),
+            cdpath: Vec::new(),
+            gitignore: false,
+            format: FormatConfig::default(),
+            confirm: ConfirmConfig::default(),
+            escalation: None,
+            refused: None,
+            extraction: None,
+            events: None,
+        }
+    }
+
+    pub fn panel(&self, side: PanelSide) -> &Panel {
+        match side {
+            PanelSide::Left => &self.left,
+            PanelSide::Right => &self.right,
+        }
+    }
+
+    pub fn panel_mut(&mut self, side: PanelSide) -> &mut Panel {
+        match side {
+            PanelSide::Left => &mut self.left,
+            PanelSide::Right => &mut self.right,
+        }
+    }
+
+    pub fn active(&self) -> &Panel {
+        self.panel(self.active)
+    }
+
+    pub fn active_mut(&mut self) -> &mut Panel {
+        self.panel_mut(self.active)
+    }
+
+    pub fn inactive(&self) -> &Panel {
+        self.panel(self.active.opposite())
+    }
+
+    pub fn inactive_mut(&mut self) -> &mut Panel {
+        self.panel_mut(self.active.opposite())
+    }
+
+    /// bring in what the remote listings of both panels read meanwhile, with why one stopped
+    pub fn poll_loading(&mut self) -> Option<String> {
+        let left = self.left.poll_loading();
+        let right = self.right.poll_loading();
+        left.or(right).map(|err| format!("listing failed: {err}"))
+    }
+
+    /// true while a panel lists a remote directory
+    pub fn is_loading(&self) -> bool {
+        self.left.loading.is_some() || self.right.loading.is_some()
+    }
+
+    pub fn tabs(&self, side: PanelSide) -> &PanelTabs {
+        match side {
+            PanelSide::Left => &self.left_tabs,
+            PanelSide::Right => &self.right_tabs,
+        }
+    }
+
+    /// the shown panel of a side with the other tabs of that side
+    pub fn side_mut(&mut self, side: PanelSide) -> (&mut Panel, &mut PanelTabs) {
+        match side {
+            PanelSide::Left => (&mut self.left, &mut self.left_tabs),
+            PanelSide::Right => (&mut self.right, &mut self.right_tabs),
+        }
+    }
+
+    /// open a tab on the directory of the active panel
+    pub fn new_tab(&mut self) -> io::Result<()> {
+        let panel = self.active().duplicate()?;
+        let (shown, tabs) = self.side_mut(self.active);
+        tabs.open(shown, panel);
+        Ok(())
+    }
+
+    /// close the active tab, false if it is the last one of its side
+    pub fn close_tab(&mut self) -> bool {
+        let (shown, tabs) = self.side_mut(self.active);
+        let closed = tabs.close(shown);
+        if closed {
+            // the tab was not watched while hidden
+            let _ = shown.reload();
+        }
+        closed
+    }
+
+    /// show the next or previous tab of the active side, wrapping around
+    pub fn cycle_tab(&mut self, forward: bool) {
+        let tabs = self.tabs(self.active);
+        let len = tabs.len();
+        let target = match forward {
+            true => (tabs.index + 1) % len,
+            false => (tabs.index + len - 1) % len,
+        };
+        self.select_tab(self.active, target);
+    }
+
+    /// show a tab of a side
+    pub fn select_tab(&mut self, side: PanelSide, index: usize) {
+        let (shown, tabs) = self.side_mut(side);
+        if index == tabs.index || index >= tabs.len() {
+            return;
+        }
+        tabs.select(shown, index);
+        // the tab was not watched while hidden
+        let _ = shown.reload();
+    }
+
+    /// give the focus to the other panel
+    pub fn switch_active(&mut self) {
+        // a panel in info mode follows the cursor of the other one, it has none of its own
+        if self.inactive().info.is_none() {
+            self.active = self.active.opposite();
+        }
+    }
+}
+
+/// build a directory node with its direct children loaded
+///
+/// The children of a remote directory are read in the background instead,
+/// they come with the listing that brings them in.
+fn load_directory(vfs: &VfsRef, path: &Path) -> io::Result<(FsNodeRef, Option<Loading>)> {
+    let path = if vfs::is_local(vfs) {
+        paths::canonicalize(path)?
+    } else {
+        path.to_path_buf()
+    };
+    let mut node = FsNode::from_entry(vfs.metadata(&path)?, Arc::clone(vfs));
+    if vfs.remote().is_some() {
+        return Ok((node.into_ref(), Some(Loading::start(vfs, &path))));
+    }
+    node.load_children()?;
+    Ok((node.into_ref(), None))
+}
+
+/// true if a directory of the history cannot come back
+fn is_stale(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory)
+}
@@ -0,0 +1,171 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// the absolute path of `path` with symlinks resolved, in the form users write it
+pub fn canonicalize(path: &Path) -> std::io::Result<PathBuf> {
+    let path = fs::canonicalize(path)?;
+    #[cfg(windows)]
+    let path = crate::windows::strip_verbatim(path);
+    Ok(path)
+}
+
+/// true if `path` is `ancestor` or below it, without regard to case on Windows
+pub fn is_within(path: &Path, ancestor: &Path) -> bool {
+    #[cfg(windows)]
+    return crate::windows::is_within(path, ancestor);
+    #[cfg(not(windows))]
+    path.starts_with(ancestor)
+}
+
+/// expand a path typed by the user
+///
+/// A leading `~` is the home directory, `$NAME` and `${NAME}` are replaced
+/// by environment variables. Unknown variables expand to nothing, as in a
+/// shell.
+pub fn expand_user_path(input: &str) -> PathBuf {
+    let input = input.trim();
+    let home = || env::var("HOME").unwrap_or_default();
+    let input = match input.strip_prefix('~') {
+        Some("") => home(),
+        Some(rest) if rest.starts_with('/') => format!("{}{rest}", home()),
+        _ => input.to_string(),
+    };
+    PathBuf::from(expand_variables(&input))
+}
+
+/// ways to finish a path being typed, relative paths start from `base`
+///
+/// Each candidate is the typed text completed with an entry name,
+/// directories end with a slash. Hidden entries only show up once a dot is
+/// typed.
+pub fn complete_path(input: &str, base: &Path) -> Vec<String> {
+    let (dir_text, prefix) = match input.rfind('/') {
+        Some(slash) => input.split_at(slash + 1),
+        None => ("", input),
+    };
+    let Ok(entries) = fs::read_dir(base.join(expand_user_path(dir_text))) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (prefix.is_empty() && name.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir_text}{name}{slash}"))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// ways to finish a directory being typed, from `base` and then from the `cdpath` directories
+///
+/// Only names that are not absolute or relative to `.` are looked up in `cdpath`.
+pub fn complete_directory(input: &str, base: &Path, cdpath: &[PathBuf]) -> Vec<String> {
+    let mut candidates = complete_path(input, base);
+    if !input.starts_with(['/', '~', '$', '.']) {
+        for dir in cdpath {
+            candidates.extend(complete_path(input, dir));
+        }
+    }
+    candidates.retain(|candidate| candidate.ends_with('/'));
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// directory a `cd` to `input` goes to, below `base` or else below the first `cdpath` directory holding it
+///
+/// As in a shell, `cdpath` is only looked at for names that are not
+/// absolute or relative to `.`, and unless `base` has such a directory.
+pub fn resolve_cd(input: &str, base: &Path, cdpath: &[PathBuf]) -> PathBuf {
+    let input = input.trim();
+    let path = expand_user_path(input);
+    let direct = base.join(&path);
+    if path.is_absolute() || input.starts_with('.') || direct.is_dir() {
+        return direct;
+    }
+    cdpath.iter().map(|dir| dir.join(&path)).find(|dir| dir.is_dir()).unwrap_or(direct)
+}
+
+/// directories a `cd` looks in for a name, the configured ones then those of `$CDPATH`
+pub fn cdpath(configured: &[String]) -> Vec<PathBuf> {
+    let from_env = env::var_os("CDPATH").map(|paths| env::split_paths(&paths).collect::<Vec<_>>()).unwrap_or_default();
+    configured
+        .iter()
+        .map(|dir| expand_user_path(dir))
+        .chain(from_env)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect()
+}
+
+/// longest start shared by every candidate
+pub fn common_prefix(candidates: &[String]) -> String {
+    let Some((first, rest)) = candidates.split_first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_str();
+    for candidate in rest {
+        let shared = prefix
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(candidate.len()), |((offset, _), _)| offset);
+        prefix = &prefix[..shared];
+    }
+    prefix.to_string()
+}
+
+/// replace `$NAME` and `${NAME}` with the value of the variable
+pub fn expand_variables(input: &str) -> String {
+    let mut expanded = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+        let mut name = String::new();
+        if chars.next_if_eq(&'{').is_some() {
+            for c in chars.by_ref() {
+                if c == '}' {
+                    break;
+                }
+                name.push(c);
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                name.push(c);
+            }
+        }
+        if name.is_empty() {
+            // a lone dollar is kept
+            expanded.push('$');
+        } else {
+            expanded.push_str(&env::var(&name).unwrap_or_default());
+        }
+    }
+    expanded
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_within_compares_whole_components() {
+        assert!(is_within(Path::new("/home/a/docs"), Path::new("/home/a")));
+        assert!(is_within(Path::new("/home/a"), Path::new("/home/a")));
+        assert!(!is_within(Path::new("/home/ab"), Path::new("/home/a")));
+        assert!(!is_within(Path::new("/home"), Path::new("/home/a")));
+    }
+
+    #[test]
+    fn is_within_keeps_case_apart() {
+        assert!(!is_within(Path::new("/Home/a"), Path::new("/home/a")));
+    }
+}
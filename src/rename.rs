@@ -0,0 +1,447 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::vfs::{self, Vfs, VfsRef};
+
+/// case change applied to a whole new name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaseTransform {
+    #[default]
+    Keep,
+    Lower,
+    Upper,
+    /// first letter of every word in uppercase, the rest in lowercase
+    Title,
+}
+
+impl CaseTransform {
+    /// the next transform, for toggles cycling through them
+    pub fn next(self) -> CaseTransform {
+        match self {
+            CaseTransform::Keep => CaseTransform::Lower,
+            CaseTransform::Lower => CaseTransform::Upper,
+            CaseTransform::Upper => CaseTransform::Title,
+            CaseTransform::Title => CaseTransform::Keep,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CaseTransform::Keep => "unchanged",
+            CaseTransform::Lower => "lower case",
+            CaseTransform::Upper => "UPPER CASE",
+            CaseTransform::Title => "Title Case",
+        }
+    }
+
+    pub fn apply(self, name: &str) -> String {
+        match self {
+            CaseTransform::Keep => name.to_string(),
+            CaseTransform::Lower => name.to_lowercase(),
+            CaseTransform::Upper => name.to_uppercase(),
+            CaseTransform::Title => {
+                let mut title = String::with_capacity(name.len());
+                let mut word_start = true;
+                for c in name.chars() {
+                    if word_start {
+                        title.extend(c.to_uppercase());
+                    } else {
+                        title.extend(c.to_lowercase());
+                    }
+                    word_start = !c.is_alphanumeric();
+                }
+                title
+            }
+        }
+    }
+}
+
+/// how new names are built from old ones
+///
+/// The template may use `[N]` for the name without its extension, `[E]`
+/// for the extension and `[C]` for a counter. The search pattern is then
+/// replaced in the result and the case transform applied last.
+#[derive(Debug, Clone)]
+pub struct RenamePattern {
+    pub template: String,
+    pub search: Option<Regex>,
+    /// replacement of the search matches, `$1` refers to groups
+    pub replace: String,
+    pub case: CaseTransform,
+    pub counter_start: u64,
+    pub counter_step: u64,
+    /// digits of the counter, padded with zeros
+    pub counter_width: usize,
+}
+
+impl Default for RenamePattern {
+    fn default() -> Self {
+        RenamePattern {
+            template: String::from("[N].[E]"),
+            search: None,
+            replace: String::new(),
+            case: CaseTransform::Keep,
+            counter_start: 1,
+            counter_step: 1,
+            counter_width: 1,
+        }
+    }
+}
+
+impl RenamePattern {
+    /// new name of the `index`-th renamed file
+    pub fn apply(&self, name: &str, index: usize) -> String {
+        let (stem, extension) = split_extension(name);
+        let counter = self.counter_start + self.counter_step * index as u64;
+        let mut result = String::new();
+        let mut rest = self.template.as_str();
+        while let Some(open) = rest.find('[') {
+            result.push_str(&rest[..open]);
+            let after = &rest[open..];
+            let Some(close) = after.find(']') else {
+                break;
+            };
+            match &after[1..close] {
+                "N" => result.push_str(stem),
+                // no extension, no dot in front of it
+                "E" if extension.is_empty() && result.ends_with('.') => {
+                    result.pop();
+                }
+                "E" => result.push_str(extension),
+                "C" => result.push_str(&format!("{counter:0width$}", width = self.counter_width)),
+                _ => result.push_str(&after[..=close]),
+            }
+            rest = &after[close + 1..];
+        }
+        result.push_str(rest);
+        if let Some(search) = &self.search {
+            result = search.replace_all(&result, self.replace.as_str()).into_owned();
+        }
+        self.case.apply(&result)
+    }
+}
+
+/// name and extension, the leading dot of hidden files is not an extension
+pub fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => (&name[..dot], &name[dot + 1..]),
+        _ => (name, ""),
+    }
+}
+
+/// one line of the rename preview
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// why the rename cannot be done
+    pub conflict: Option<String>,
+}
+
+impl Rename {
+    pub fn is_unchanged(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+/// new paths of `sources` with the problems found before touching anything
+pub fn preview(vfs: &VfsRef, sources: &[PathBuf], pattern: &RenamePattern) -> Vec<Rename> {
+    let mut renames: Vec<Rename> = sources
+        .iter()
+        .enumerate()
+        .map(|(index, from)| {
+            let name = from.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+            let new_name = pattern.apply(&name, index);
+            // the separator of Windows splits a name there too
+            let separator = new_name.contains('/') || (cfg!(windows) && new_name.contains('\\'));
+            let conflict = if new_name.is_empty() || new_name == "." || new_name == ".." || separator {
+                Some(String::from("invalid name"))
+            } else {
+                None
+            };
+            Rename {
+                from: from.clone(),
+                to: from.with_file_name(&new_name),
+                conflict,
+            }
+        })
+        .collect();
+
+    let moved: HashSet<&PathBuf> = sources.iter().collect();
+    let mut targets: HashMap<PathBuf, usize> = HashMap::new();
+    for rename in &renames {
+        *targets.entry(rename.to.clone()).or_default() += 1;
+    }
+    for rename in &mut renames {
+        if rename.conflict.is_some() || rename.is_unchanged() {
+            continue;
+        }
+        if targets[&rename.to] > 1 {
+            rename.conflict = Some(String::from("same name as another file"));
+        } else if !moved.contains(&rename.to) && vfs.metadata(&rename.to).is_ok() && !is_case_change(vfs, rename) {
+            rename.conflict = Some(String::from("already exists"));
+        }
+    }
+    renames
+}
+
+/// whether the target found is the source itself, as when only the case of
+/// the name changes on a filesystem ignoring it
+///
+/// The listing of the directory tells, it shows the name the file really has.
+fn is_case_change(vfs: &VfsRef, rename: &Rename) -> bool {
+    let lowercase = |path: &Path| path.file_name().map(|name| name.to_string_lossy().to_lowercase());
+    let name = rename.to.file_name();
+    match (lowercase(&rename.from) == lowercase(&rename.to), rename.to.parent()) {
+        (true, Some(parent)) => vfs
+            .read_dir(parent)
+            .is_ok_and(|entries| !entries.iter().any(|entry| Some(entry.name.as_ref()) == name)),
+        _ => false,
+    }
+}
+
+/// rename everything or nothing
+///
+/// Files first get temporary names, so names can be swapped between them.
+/// If any step fails the renames done so far are undone in reverse order.
+/// Local renames are written to a [`RenameJournal`] first, for them to be
+/// undone at the next start if the program does not live to the end.
+pub fn execute(vfs: &VfsRef, renames: &[Rename]) -> io::Result<usize> {
+    if let Some(rename) = renames.iter().find(|rename| rename.conflict.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: {}", rename.to.display(), rename.conflict.as_deref().unwrap_or_default()),
+        ));
+    }
+    let pending: Vec<&Rename> = renames.iter().filter(|rename| !rename.is_unchanged()).collect();
+    let mut temporaries: Vec<PathBuf> = Vec::with_capacity(pending.len());
+    for rename in &pending {
+        let name = rename.from.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        // a name left by an earlier run, or by anyone, is not renamed over
+        let temporary = (0..)
+            .map(|index| rename.from.with_file_name(format!(".{name}.mdc-rename-{index}")))
+            .find(|temporary| !temporaries.contains(temporary) && vfs.metadata(temporary).is_err())
+            .unwrap_or_default();
+        temporaries.push(temporary);
+    }
+    let mut steps: Vec<Step> = pending
+        .iter()
+        .zip(&temporaries)
+        .map(|(rename, temporary)| Step::new(&rename.from, temporary))
+        .collect();
+    steps.extend(pending.iter().zip(&temporaries).map(|(rename, temporary)| Step::new(temporary, &rename.to)));
+
+    // without a place for the journal a crash is not covered, a failure still is
+    let mut journal = match vfs::is_local(vfs) {
+        true => RenameJournal::default_dir().and_then(|dir| RenameJournal::create(&dir, &steps).ok()),
+        false => None,
+    };
+    for (index, step) in steps.iter().enumerate() {
+        // renaming over a file that appeared since the preview would lose it
+        let result = match vfs.metadata(&step.to).is_ok() {
+            true => Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", step.to.display()),
+            )),
+            false => vfs.rename(&step.from, &step.to),
+        };
+        if let Err(err) = result {
+            // a journal left behind has the next start try again
+            if rollback(vfs.as_ref(), &steps[..index])
+                && let Some(journal) = journal
+            {
+                journal.remove();
+            }
+            return Err(err);
+        }
+        if let Some(journal) = &mut journal {
+            journal.step_done();
+        }
+    }
+    if let Some(journal) = journal {
+        journal.remove();
+    }
+    Ok(pending.len())
+}
+
+/// undo steps in reverse order, false if one could not be
+///
+/// A step whose new name is gone has nothing to undo, one whose old name is
+/// taken again is not undone rather than overwrite what took it.
+fn rollback(vfs: &dyn Vfs, done: &[Step]) -> bool {
+    let mut undone = true;
+    for step in done.iter().rev() {
+        if vfs.metadata(&step.to).is_err() {
+            continue;
+        }
+        undone &= vfs.metadata(&step.from).is_err() && vfs.rename(&step.to, &step.from).is_ok();
+    }
+    undone
+}
+
+/// one rename of a path to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Step {
+    from: PathBuf,
+    to: PathBuf,
+}
+
+impl Step {
+    fn new(from: &Path, to: &Path) -> Step {
+        Step {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        }
+    }
+}
+
+/// the steps of a batch of local renames, written before the first one
+///
+/// The plan goes to `renames-<pid>-<time>.toml` next to the config file, and
+/// each step done adds a byte to the `.done` file of the same name. Both are
+/// removed once the batch is done or undone.
+#[derive(Debug)]
+pub struct RenameJournal {
+    plan: PathBuf,
+    done: File,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Plan {
+    steps: Vec<Step>,
+}
+
+impl RenameJournal {
+    /// the directory of the config file
+    pub fn default_dir() -> Option<PathBuf> {
+        Some(Config::default_path()?.parent()?.to_path_buf())
+    }
+
+    /// write the plan and make sure it is on disk
+    fn create(dir: &Path, steps: &[Step]) -> io::Result<RenameJournal> {
+        fs::create_dir_all(dir)?;
+        // the time tells apart the batches of a process reusing the id of a crashed one
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let name = format!("renames-{}-{time}", std::process::id());
+        let plan = dir.join(format!("{name}.toml"));
+        let text = toml::to_string(&Plan { steps: steps.to_vec() }).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let mut file = OpenOptions::new().write(true).create_new(true).open(&plan)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+        let done = OpenOptions::new().create(true).write(true).truncate(true).open(plan.with_extension("done"))?;
+        Ok(RenameJournal { plan, done })
+    }
+
+    fn step_done(&mut self) {
+        // losing the count of a step is made up for by the recovery
+        let _ = self.done.write_all(b".");
+    }
+
+    fn remove(self) {
+        let _ = fs::remove_file(self.plan.with_extension("done"));
+        let _ = fs::remove_file(&self.plan);
+    }
+}
+
+/// what [`recover`] did with the journals left behind
+#[derive(Debug, Default)]
+pub struct Recovery {
+    /// batches put back
+    pub undone: usize,
+    /// journals that could not be read or removed, they stay for the next start
+    pub errors: Vec<String>,
+}
+
+impl Recovery {
+    /// line telling the user about the batches undone and the journals left
+    pub fn summary(&self) -> Option<String> {
+        let undone = (self.undone > 0).then(|| format!("{} interrupted batches of renames were undone", self.undone));
+        let errors = match self.errors.as_slice() {
+            [] => None,
+            [error] => Some(format!("a rename journal was left: {error}")),
+            errors => Some(format!("{} rename journals were left: {}", errors.len(), errors.join(", "))),
+        };
+        match (undone, errors) {
+            (Some(undone), Some(errors)) => Some(format!("{undone}, {errors}")),
+            (undone, errors) => undone.or(errors),
+        }
+    }
+}
+
+/// undo the local renames a previous run left halfway
+///
+/// Journals of a process still running are left alone, it may be renaming
+/// right now, but not those of the id of this one: it has not renamed yet
+/// and the id was reused after a crash. A journal whose steps cannot all be
+/// undone stays for the next start, one that cannot be read does not keep
+/// the others from being undone.
+pub fn recover(dir: &Path) -> Recovery {
+    let mut recovery = Recovery::default();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return recovery,
+        Err(err) => {
+            recovery.errors.push(format!("{}: {err}", dir.display()));
+            return recovery;
+        }
+    };
+    for entry in entries.flatten() {
+        let plan = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(stem) = name.strip_prefix("renames-").and_then(|name| name.strip_suffix(".toml")) else {
+            continue;
+        };
+        let pid = stem.split('-').next().and_then(|pid| pid.parse::<i32>().ok());
+        if pid.is_some_and(|pid| pid as u32 != std::process::id() && is_running(pid)) {
+            continue;
+        }
+        match recover_journal(&plan) {
+            Ok(true) => recovery.undone += 1,
+            Ok(false) => {}
+            // parse errors go on for lines, the message has one
+            Err(err) => recovery.errors.push(format!("{}: {}", plan.display(), err.to_string().lines().next().unwrap_or(""))),
+        }
+    }
+    recovery
+}
+
+/// undo the steps of one journal, true once they all were and the journal is gone
+fn recover_journal(plan: &Path) -> io::Result<bool> {
+    let local = vfs::local();
+    let text = fs::read_to_string(plan)?;
+    let steps = toml::from_str::<Plan>(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?.steps;
+    let mut done = fs::metadata(plan.with_extension("done")).map(|metadata| metadata.len() as usize).unwrap_or(0).min(steps.len());
+    // the step after those counted may have been done without being counted
+    if let Some(step) = steps.get(done)
+        && local.metadata(&step.from).is_err()
+        && local.metadata(&step.to).is_ok()
+    {
+        done += 1;
+    }
+    if !rollback(local.as_ref(), &steps[..done]) {
+        return Ok(false);
+    }
+    let _ = fs::remove_file(plan.with_extension("done"));
+    fs::remove_file(plan)?;
+    Ok(true)
+}
+
+/// true if a process of this id exists
+#[cfg(unix)]
+fn is_running(pid: i32) -> bool {
+    // SAFETY: signal 0 only checks that the process could be signalled
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: i32) -> bool {
+    false
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::fs_error::FsError;
+use crate::fs_node::{FsNode, FsNodeType};
+use crate::gitignore::IgnoreRules;
+use crate::vfs::{VfsEntry, VfsRef};
+
+/// most threads reading directories at once, more only wait on the same disk
+const MAX_THREADS: usize = 8;
+
+/// number of threads a scan uses, one per core up to [`MAX_THREADS`]
+pub fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(MAX_THREADS)
+}
+
+/// entries of a directory, or why they could not be read
+type Listing = Result<Vec<VfsEntry>, FsError>;
+
+/// directories waiting to be read with the ignore rules in force there, and how many are being read
+#[derive(Default)]
+struct Queue {
+    waiting: Vec<(PathBuf, Option<IgnoreRules>)>,
+    reading: usize,
+}
+
+/// build a node and its whole subtree, reading directories on `threads` threads
+///
+/// The threads take directories from a shared queue and put the directories
+/// they find back in it, so a deep branch is shared out like a wide one. The
+/// listings are then put together into nodes in the order they were read,
+/// the tree is the one a walk on one thread would give. Directories that
+/// cannot be read are left empty with their error and symlinks are not
+/// followed, only a `path` that cannot be read fails.
+pub fn scan(vfs: &VfsRef, path: &Path, threads: usize) -> Result<FsNode, FsError> {
+    scan_with(vfs, path, threads, None)
+}
+
+/// build a node and its subtree like [`scan`], without the entries ignored like git does
+///
+/// The rules of `path` and of the directories above it apply from the start,
+/// those found below it from their directory on. Ignored directories are not read.
+pub fn scan_ignoring(vfs: &VfsRef, path: &Path, threads: usize) -> Result<FsNode, FsError> {
+    scan_with(vfs, path, threads, Some(IgnoreRules::for_directory(vfs.as_ref(), path)))
+}
+
+fn scan_with(vfs: &VfsRef, path: &Path, threads: usize, rules: Option<IgnoreRules>) -> Result<FsNode, FsError> {
+    let entry = vfs.metadata(path).map_err(|err| FsError::new(path, &err))?;
+    if entry.node_type != FsNodeType::Directory {
+        return Ok(FsNode::from_entry(entry, Arc::clone(vfs)));
+    }
+    let queue = Mutex::new(Queue {
+        waiting: vec![(entry.path.clone(), rules)],
+        reading: 0,
+    });
+    let changed = Condvar::new();
+    let listings = Mutex::new(HashMap::new());
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| read_directories(vfs, &queue, &changed, &listings));
+        }
+    });
+    Ok(assemble(vfs, entry, &mut listings.into_inner()))
+}
+
+/// read directories of the queue until none is waiting nor being read
+fn read_directories(vfs: &VfsRef, queue: &Mutex<Queue>, changed: &Condvar, listings: &Mutex<HashMap<PathBuf, Listing>>) {
+    loop {
+        let (path, rules) = {
+            let mut queue = queue.lock();
+            loop {
+                if let Some(waiting) = queue.waiting.pop() {
+                    queue.reading += 1;
+                    break waiting;
+                }
+                if queue.reading == 0 {
+                    return;
+                }
+                changed.wait(&mut queue);
+            }
+        };
+        let mut entries = vfs.read_dir(&path).map_err(|err| FsError::new(&path, &err));
+        if let (Some(rules), Ok(entries)) = (&rules, &mut entries) {
+            entries.retain(|entry| !rules.is_ignored(&entry.path, entry.node_type == FsNodeType::Directory));
+        }
+        // the ignore files of the subdirectories are read before the queue is locked
+        let directories: Vec<_> = entries
+            .iter()
+            .flatten()
+            .filter(|entry| entry.node_type == FsNodeType::Directory)
+            .map(|entry| (entry.path.clone(), rules.as_ref().map(|rules| rules.child(vfs.as_ref(), &entry.path))))
+            .collect();
+        let mut locked = queue.lock();
+        locked.waiting.extend(directories);
+        locked.reading -= 1;
+        // the last reader done with an empty queue lets the others stop
+        changed.notify_all();
+        drop(locked);
+        listings.lock().insert(path, entries);
+    }
+}
+
+/// the node of an entry with the listings read for it and its descendants
+fn assemble(vfs: &VfsRef, entry: VfsEntry, listings: &mut HashMap<PathBuf, Listing>) -> FsNode {
+    let mut node = FsNode::from_entry(entry, Arc::clone(vfs));
+    if node.node_type != FsNodeType::Directory {
+        return node;
+    }
+    match listings.remove(&node.path()) {
+        Some(Ok(entries)) => {
+            for entry in entries {
+                node.add_child(assemble(vfs, entry, listings));
+            }
+        }
+        Some(Err(err)) => node.error = Some(err),
+        None => {}
+    }
+    node
+}
@@ -0,0 +1,366 @@
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use regex::Regex;
+use regex::bytes::Regex as BytesRegex;
+
+use crate::fs_node::FsNodeType;
+use crate::gitignore::IgnoreRules;
+use crate::vfs::{VfsEntry, VfsRef};
+
+/// how file names are matched
+#[derive(Debug, Clone)]
+pub enum NamePattern {
+    /// every file
+    Any,
+    Glob(glob::Pattern),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    /// parse the name field of the find dialog, an empty pattern matches everything
+    pub fn parse(pattern: &str, regex: bool, case_sensitive: bool) -> Result<NamePattern, String> {
+        if pattern.is_empty() || (!regex && pattern == "*") {
+            return Ok(NamePattern::Any);
+        }
+        if regex {
+            let pattern = if case_sensitive { pattern.to_string() } else { format!("(?i){pattern}") };
+            return Regex::new(&pattern).map(NamePattern::Regex).map_err(|err| err.to_string());
+        }
+        glob::Pattern::new(pattern).map(NamePattern::Glob).map_err(|err| err.to_string())
+    }
+
+    pub fn matches(&self, name: &str, case_sensitive: bool) -> bool {
+        match self {
+            NamePattern::Any => true,
+            NamePattern::Glob(pattern) => pattern.matches_with(
+                name,
+                glob::MatchOptions {
+                    case_sensitive,
+                    require_literal_separator: true,
+                    require_literal_leading_dot: false,
+                },
+            ),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// build the pattern file contents are matched against
+///
+/// Plain text is matched literally, `whole_word` only accepts matches
+/// between word boundaries.
+pub fn content_pattern(text: &str, regex: bool, case_sensitive: bool, whole_word: bool) -> Result<BytesRegex, String> {
+    let mut pattern = if regex { text.to_string() } else { regex::escape(text) };
+    if whole_word {
+        pattern = format!(r"\b(?:{pattern})\b");
+    }
+    if !case_sensitive {
+        pattern = format!("(?i){pattern}");
+    }
+    BytesRegex::new(&pattern).map_err(|err| err.to_string())
+}
+
+/// bytes looked at to decide whether a file is binary
+const BINARY_PROBE: usize = 8192;
+
+/// longest preview of a matching line, in characters
+const PREVIEW_LEN: usize = 200;
+
+/// what to look for and where
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub start: PathBuf,
+    pub name: NamePattern,
+    /// what the files must contain, binary files are skipped
+    pub content: Option<BytesRegex>,
+    pub case_sensitive: bool,
+    /// leave out what git ignores, see [`IgnoreRules`]
+    pub gitignore: bool,
+}
+
+/// a line of a file matching the content pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    /// line number, counted from 1
+    pub line: u64,
+    /// offset of the match in the file
+    pub offset: u64,
+    pub matched: Vec<u8>,
+    /// the whole line, trimmed and shortened
+    pub preview: String,
+}
+
+/// a file found by a search, once per matching line with a content pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub entry: VfsEntry,
+    pub found: Option<LineMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchEvent {
+    Found(SearchResult),
+    /// a directory that could not be listed or a file that could not be read
+    Error(PathBuf, String),
+    /// the walk is over, `directories` were visited
+    Done { directories: usize, cancelled: bool },
+}
+
+/// a search running on a background thread
+#[derive(Debug)]
+pub struct SearchHandle {
+    pub events: Receiver<SearchEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl SearchHandle {
+    /// stop the walk, already sent results stay available
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for SearchHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// walk the tree below `query.start`, results are streamed as they are found
+///
+/// Symlinks to directories are not followed.
+pub fn spawn_search(vfs: VfsRef, query: SearchQuery) -> SearchHandle {
+    let (sender, events) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancelled = Arc::clone(&cancel);
+    thread::spawn(move || {
+        let directories = walk(&vfs, &query, &sender, &cancelled);
+        let _ = sender.send(SearchEvent::Done {
+            directories,
+            cancelled: cancelled.load(Ordering::Relaxed),
+        });
+    });
+    SearchHandle { events, cancel }
+}
+
+fn walk(vfs: &VfsRef, query: &SearchQuery, sender: &Sender<SearchEvent>, cancel: &AtomicBool) -> usize {
+    let rules = query.gitignore.then(|| IgnoreRules::for_directory(vfs.as_ref(), &query.start));
+    let mut pending = vec![(query.start.clone(), rules)];
+    let mut directories = 0;
+    while let Some((directory, rules)) = pending.pop() {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        directories += 1;
+        let mut entries = match vfs.read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(err) => {
+                let _ = sender.send(SearchEvent::Error(directory, err.to_string()));
+                continue;
+            }
+        };
+        if let Some(rules) = &rules {
+            entries.retain(|entry| !rules.is_ignored(&entry.path, entry.node_type == FsNodeType::Directory));
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        // depth first, in name order
+        for entry in entries.iter().rev() {
+            if entry.node_type == FsNodeType::Directory {
+                let rules = rules.as_ref().map(|rules| rules.child(vfs.as_ref(), &entry.path));
+                pending.push((entry.path.clone(), rules));
+            }
+        }
+        for entry in entries {
+            if !query.name.matches(&entry.name, query.case_sensitive) {
+                continue;
+            }
+            let Some(content) = &query.content else {
+                if sender.send(SearchEvent::Found(SearchResult { entry, found: None })).is_err() {
+                    // nobody is listening anymore
+                    return directories;
+                }
+                continue;
+            };
+            if entry.node_type != FsNodeType::File {
+                continue;
+            }
+            let matches = match find_in_file(vfs, &entry.path, content, cancel) {
+                Ok(matches) => matches,
+                Err(err) => {
+                    let _ = sender.send(SearchEvent::Error(entry.path, err.to_string()));
+                    continue;
+                }
+            };
+            for found in matches {
+                let result = SearchResult {
+                    entry: entry.clone(),
+                    found: Some(found),
+                };
+                if sender.send(SearchEvent::Found(result)).is_err() {
+                    return directories;
+                }
+            }
+        }
+    }
+    directories
+}
+
+/// every line of a text file matching `content`, nothing for binary files
+fn find_in_file(vfs: &VfsRef, path: &Path, content: &BytesRegex, cancel: &AtomicBool) -> io::Result<Vec<LineMatch>> {
+    let mut reader = BufReader::with_capacity(BINARY_PROBE, vfs.open_read(path)?);
+    if reader.fill_buf()?.contains(&0) {
+        return Ok(Vec::new());
+    }
+    let mut matches = Vec::new();
+    let mut line = Vec::new();
+    let mut number = 0;
+    let mut offset = 0;
+    while !cancel.load(Ordering::Relaxed) {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        number += 1;
+        // the first match of the line locates it, the preview shows the rest
+        if let Some(found) = content.find(&line) {
+            let preview: String = String::from_utf8_lossy(&line).trim().chars().take(PREVIEW_LEN).collect();
+            matches.push(LineMatch {
+                line: number,
+                offset: offset + found.start() as u64,
+                matched: found.as_bytes().to_vec(),
+                preview,
+            });
+        }
+        offset += read as u64;
+    }
+    Ok(matches)
+}
+
+/// what a key did to the result list
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchAction {
+    None,
+    Close,
+    /// show the file in the active panel
+    Jump(PathBuf),
+    /// open the file in the viewer, at the match if there is one
+    View(SearchResult),
+    /// replace the active panel listing with the results
+    Panelize,
+}
+
+/// results of a running or finished search, listed full screen
+#[derive(Debug)]
+pub struct SearchResults {
+    pub query: SearchQuery,
+    pub vfs: VfsRef,
+    pub results: Vec<SearchResult>,
+    pub errors: Vec<(PathBuf, String)>,
+    pub cursor: usize,
+    pub offset: usize,
+    /// rows of the list, set by the renderer
+    pub height: usize,
+    /// directories visited, once the walk is over
+    pub finished: Option<usize>,
+    handle: SearchHandle,
+}
+
+impl SearchResults {
+    pub fn start(vfs: VfsRef, query: SearchQuery) -> SearchResults {
+        let handle = spawn_search(Arc::clone(&vfs), query.clone());
+        SearchResults {
+            query,
+            vfs,
+            results: Vec::new(),
+            errors: Vec::new(),
+            cursor: 0,
+            offset: 0,
+            height: 0,
+            finished: None,
+            handle,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.finished.is_none()
+    }
+
+    /// take the events sent by the walk so far
+    pub fn poll(&mut self) {
+        for event in self.handle.events.try_iter() {
+            match event {
+                SearchEvent::Found(result) => self.results.push(result),
+                SearchEvent::Error(path, err) => self.errors.push((path, err)),
+                SearchEvent::Done { directories, .. } => self.finished = Some(directories),
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> SearchAction {
+        let page = self.height.saturating_sub(1).max(1);
+        let last = self.results.len().saturating_sub(1);
+        match key.code {
+            // the first escape only stops a running search
+            KeyCode::Esc if self.is_running() => self.handle.cancel(),
+            KeyCode::Esc | KeyCode::F(10) | KeyCode::Char('q') => return SearchAction::Close,
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor = (self.cursor + 1).min(last),
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(page),
+            KeyCode::PageDown => self.cursor = (self.cursor + page).min(last),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = last,
+            // a matching line is more useful in the viewer than in the panel
+            KeyCode::Enter => match self.results.get(self.cursor) {
+                Some(result) if result.found.is_some() => return SearchAction::View(result.clone()),
+                Some(result) => return SearchAction::Jump(result.entry.path.clone()),
+                None => {}
+            },
+            KeyCode::F(3) => {
+                if let Some(result) = self.results.get(self.cursor) {
+                    return SearchAction::View(result.clone());
+                }
+            }
+            KeyCode::Char('g') => {
+                if let Some(result) = self.results.get(self.cursor) {
+                    return SearchAction::Jump(result.entry.path.clone());
+                }
+            }
+            KeyCode::F(5) | KeyCode::Char('p') if !self.results.is_empty() => return SearchAction::Panelize,
+            _ => {}
+        }
+        SearchAction::None
+    }
+
+    /// adjust the scroll offset so the cursor is visible
+    pub fn scroll_to_cursor(&mut self) {
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.height > 0 && self.cursor >= self.offset + self.height {
+            self.offset = self.cursor + 1 - self.height;
+        }
+    }
+
+    /// found files without the repeats of files matching on several lines
+    pub fn entries(&self) -> Vec<VfsEntry> {
+        let mut entries: Vec<VfsEntry> = Vec::new();
+        for result in &self.results {
+            if entries.last().is_none_or(|last| last.path != result.entry.path) {
+                entries.push(result.entry.clone());
+            }
+        }
+        entries
+    }
+
+    /// path of a result as listed, relative to the start directory
+    pub fn display_path(&self, result: &SearchResult) -> String {
+        let path = result.entry.path.strip_prefix(&self.query.start).unwrap_or(&result.entry.path);
+        path.display().to_string()
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, PanelConfig};
+use crate::panel::{Layout, Panel, PanelManager, PanelSide, PanelTabs};
+use crate::sort::SortMode;
+
+/// a tab as saved between sessions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TabSession {
+    /// local path or url as accepted by `vfs::open_location`
+    pub location: String,
+    #[serde(default)]
+    pub sort: SortMode,
+    #[serde(default)]
+    pub layout: Layout,
+    /// directories visited in the tab, the oldest first
+    #[serde(default)]
+    pub history: Vec<String>,
+}
+
+impl TabSession {
+    pub fn new(panel: &Panel) -> TabSession {
+        TabSession {
+            location: panel.display_location(),
+            sort: panel.sort_mode,
+            layout: panel.layout,
+            history: panel.history.locations(),
+        }
+    }
+}
+
+/// the tabs of one side
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SideSession {
+    #[serde(rename = "tab")]
+    pub tabs: Vec<TabSession>,
+    /// index of the shown tab
+    pub active: usize,
+}
+
+/// the tabs of both sides, stored as toml next to the configuration
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub left: SideSession,
+    pub right: SideSession,
+}
+
+impl Session {
+    /// `session.toml` in the directory of the default configuration
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::default_path()?.with_file_name("session.toml"))
+    }
+
+    /// read a session, a missing file gives an empty one
+    pub fn load(path: &Path) -> io::Result<Session> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Session::default()),
+            Err(err) => return Err(err),
+        };
+        toml::from_str(&text).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err.message()))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+
+    /// the tabs open in the manager
+    pub fn capture(manager: &PanelManager) -> Session {
+        let side = |side: PanelSide| {
+            let tabs = manager.tabs(side);
+            SideSession {
+                tabs: tabs.iter(manager.panel(side)).map(TabSession::new).collect(),
+                active: tabs.index,
+            }
+        };
+        Session {
+            left: side(PanelSide::Left),
+            right: side(PanelSide::Right),
+        }
+    }
+}
+
+impl SideSession {
+    /// open the saved tabs, the shown one first and the others behind it
+    ///
+    /// A `location` given on the command line replaces the shown tab and
+    /// continues its history. Tabs whose directory went away are dropped,
+    /// the current directory is shown when none is left.
+    pub fn open(&self, location: Option<&str>, config: &PanelConfig) -> io::Result<(Panel, PanelTabs)> {
+        let mut panels = Vec::new();
+        let mut active = None;
+        for (index, tab) in self.tabs.iter().enumerate() {
+            let Ok(mut panel) = Panel::open(&tab.location) else {
+                continue;
+            };
+            panel.configure(config);
+            panel.set_sort_mode(tab.sort);
+            panel.layout = tab.layout;
+            panel.restore_history(tab.history.clone());
+            if index <= self.active {
+                active = Some(panels.len());
+            }
+            panels.push(panel);
+        }
+        let index = active.unwrap_or(0);
+        let shown = match location {
+            Some(location) => {
+                let mut panel = Panel::open(location)?;
+                panel.configure(config);
+                if index < panels.len() {
+                    let replaced = panels.remove(index);
+                    panel.set_sort_mode(replaced.sort_mode);
+                    panel.layout = replaced.layout;
+                    panel.restore_history(replaced.history.locations());
+                }
+                panel
+            }
+            None if index < panels.len() => panels.remove(index),
+            None => {
+                let mut panel = Panel::new(&std::env::current_dir()?)?;
+                panel.configure(config);
+                panel
+            }
+        };
+        let tabs = PanelTabs {
+            index: index.min(panels.len()),
+            hidden: panels,
+        };
+        Ok((shown, tabs))
+    }
+}
@@ -0,0 +1,220 @@
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{self, ExitStatus, Stdio};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::complete::{Completer, Completion};
+use crate::panel::Panel;
+use crate::ui::prompt::LineInput;
+
+/// lines of output kept on the subshell screen
+const SCROLLBACK: usize = 10_000;
+
+/// commands remembered for the completion of the command line
+const HISTORY_SIZE: usize = 100;
+
+/// what a key did to the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellAction {
+    /// not a command line key, it runs its usual command
+    Ignored,
+    Handled,
+    Run(String),
+}
+
+/// command line at the bottom of the panels and the output of what it ran
+#[derive(Debug, Default)]
+pub struct Shell {
+    pub input: LineInput,
+    /// output of the commands, each one after a `$ command` line
+    pub output: Vec<String>,
+    /// lines scrolled back from the end of the output
+    pub scroll: usize,
+    /// the subshell screen is shown instead of the panels
+    pub visible: bool,
+    /// commands run so far, the oldest first
+    pub history: Vec<String>,
+    /// popup of the completions of the command line
+    pub completer: Completer,
+}
+
+impl Shell {
+    pub fn new() -> Shell {
+        Shell::default()
+    }
+
+    /// handle a key of the file manager, `bound` tells whether the key has a command
+    ///
+    /// Typing goes to the command line, except for bound characters like
+    /// `+` or `*` while it is empty. Letters and digits always start a command.
+    /// Once something is typed Tab completes it with `completion`, an empty
+    /// line leaves Tab to the panels.
+    pub fn handle_key(&mut self, key: &KeyEvent, bound: bool, completion: &Completion) -> ShellAction {
+        let empty = self.input.text.is_empty();
+        let plain = !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let open = self.completer.is_open();
+        match key.code {
+            KeyCode::Tab if !empty => self.completer.step(completion, &self.history, &mut self.input, true),
+            KeyCode::BackTab if open => self.completer.step(completion, &self.history, &mut self.input, false),
+            KeyCode::Up if open => self.completer.step(completion, &self.history, &mut self.input, false),
+            KeyCode::Down if open => self.completer.step(completion, &self.history, &mut self.input, true),
+            KeyCode::Esc if open => self.completer.cancel(&mut self.input),
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.visible = !self.visible;
+                self.scroll = 0;
+            }
+            KeyCode::PageUp if self.visible => {
+                self.scroll = (self.scroll + 10).min(self.output.len().saturating_sub(1));
+            }
+            KeyCode::PageDown if self.visible => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::Enter if !empty => {
+                let command = std::mem::take(&mut self.input);
+                self.completer.close();
+                self.remember(&command.text);
+                return ShellAction::Run(command.text);
+            }
+            KeyCode::Esc if !empty => self.input = LineInput::default(),
+            KeyCode::Char(c) if plain && (!empty || !bound || c.is_alphanumeric()) => {
+                self.input.handle_key(key);
+                self.completer.close();
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End | KeyCode::Backspace | KeyCode::Delete
+                if !empty =>
+            {
+                self.input.handle_key(key);
+                self.completer.close();
+            }
+            // the panels are hidden, their keys would act out of sight
+            _ if self.visible => {}
+            _ => return ShellAction::Ignored,
+        }
+        ShellAction::Handled
+    }
+
+    /// keep a command for the completion, once and as the latest
+    fn remember(&mut self, command: &str) {
+        let command = command.trim();
+        self.history.retain(|old| old != command);
+        if self.history.len() == HISTORY_SIZE {
+            self.history.remove(0);
+        }
+        self.history.push(command.to_string());
+    }
+
+    /// run a command with the user's shell in `dir`, its output goes to the subshell screen
+    ///
+    /// The command gets no input, interactive programs see the end of file.
+    pub fn run(&mut self, command: &str, dir: &Path) -> io::Result<()> {
+        self.push_line(format!("$ {command}"));
+        self.visible = true;
+        self.scroll = 0;
+
+        let (output, status) = capture(command, dir)?;
+        for line in output.lines() {
+            self.push_line(line.replace('\t', "        "));
+        }
+        if !status.success() {
+            self.push_line(format!("[{status}]"));
+        }
+        Ok(())
+    }
+
+    fn push_line(&mut self, line: String) {
+        if self.output.len() == SCROLLBACK {
+            self.output.remove(0);
+        }
+        self.output.push(line);
+    }
+}
+
+/// run a command with the user's shell in `dir` and collect what it prints
+///
+/// The command gets no input, interactive programs see the end of file.
+pub fn capture(command: &str, dir: &Path) -> io::Result<(String, ExitStatus)> {
+    // stdout and stderr share one pipe to keep their lines in order
+    let (mut reader, writer) = io::pipe()?;
+    let mut child = process::Command::new(user_shell())
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(writer.try_clone()?)
+        .stderr(writer)
+        .spawn()?;
+    let mut output = Vec::new();
+    // the child holds the last writers, the read ends when it exits
+    let read = reader.read_to_end(&mut output);
+    let status = child.wait()?;
+    read?;
+    Ok((String::from_utf8_lossy(&output).into_owned(), status))
+}
+
+/// run a command like [`capture`] but keep only what it prints on its standard output
+///
+/// Its errors are dropped, they would be mistaken for output lines.
+pub fn capture_stdout(command: &str, dir: &Path) -> io::Result<(Vec<u8>, ExitStatus)> {
+    let output = process::Command::new(user_shell())
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    Ok((output.stdout, output.status))
+}
+
+/// the program running commands, `$SHELL` or else `/bin/sh`
+pub fn user_shell() -> String {
+    match std::env::var("SHELL") {
+        Ok(shell) if !shell.is_empty() => shell,
+        _ => String::from("/bin/sh"),
+    }
+}
+
+/// quote a word for a POSIX shell
+pub fn shell_quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./+,:@%".contains(c)) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// replace the macros of a command with what the panel shows, quoted
+///
+/// `%f` is the file under the cursor, `%d` the current directory, `%s` the
+/// marked files or else the cursor file, `%t` the marked files only, and
+/// `%%` a single `%`.
+pub fn expand_macros(command: &str, panel: &Panel) -> String {
+    let mut expanded = String::with_capacity(command.len());
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => {
+                let name = panel.cursor_node().map(|node| node.read().name.clone()).unwrap_or_default();
+                expanded.push_str(&shell_quote(&name));
+            }
+            Some('d') => expanded.push_str(&shell_quote(&panel.current_path().to_string_lossy())),
+            Some('s') => {
+                let names: Vec<String> = panel.targets().iter().map(|node| shell_quote(&node.read().name)).collect();
+                expanded.push_str(&names.join(" "));
+            }
+            Some('t') => {
+                let names: Vec<String> =
+                    panel.marked_nodes().iter().map(|node| shell_quote(&node.read().name)).collect();
+                expanded.push_str(&names.join(" "));
+            }
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
@@ -0,0 +1,208 @@
+use std::cmp::Ordering;
+#[cfg(unix)]
+use std::ffi::CString;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fs_node::FsNode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    #[default]
+    Name,
+    Extension,
+    Size,
+    MTime,
+    /// keep the order in which entries were read from disk
+    Unsorted,
+}
+
+impl SortKey {
+    /// every key, in the order of the sort dialog
+    pub const ALL: [SortKey; 5] = [SortKey::Name, SortKey::Extension, SortKey::Size, SortKey::MTime, SortKey::Unsorted];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "Name",
+            SortKey::Extension => "Extension",
+            SortKey::Size => "Size",
+            SortKey::MTime => "Modification time",
+            SortKey::Unsorted => "Unsorted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SortMode {
+    pub key: SortKey,
+    pub descending: bool,
+    pub directories_first: bool,
+    pub case_sensitive: bool,
+    /// digits compared as numbers, so file2 comes before file10 and 1.9 before 1.10
+    pub natural: bool,
+    /// letters compared by the collation of the locale, which decides about case too
+    pub locale: bool,
+}
+
+impl Default for SortMode {
+    fn default() -> Self {
+        SortMode {
+            key: SortKey::Name,
+            descending: false,
+            directories_first: true,
+            case_sensitive: false,
+            natural: false,
+            locale: false,
+        }
+    }
+}
+
+impl SortMode {
+    pub fn new(key: SortKey) -> SortMode {
+        SortMode {
+            key,
+            ..SortMode::default()
+        }
+    }
+
+    /// order two nodes, directories stay on top regardless of the direction
+    pub fn compare(&self, a: &FsNode, b: &FsNode) -> Ordering {
+        if self.directories_first {
+            let directories = b.is_navigable().cmp(&a.is_navigable());
+            if directories != Ordering::Equal {
+                return directories;
+            }
+        }
+
+        let ordering = match self.key {
+            SortKey::Unsorted => return Ordering::Equal,
+            SortKey::Name => self.compare_names(a, b),
+            SortKey::Extension => self
+                .compare_text(extension(&a.name), extension(&b.name))
+                .then_with(|| self.compare_names(a, b)),
+            SortKey::Size => size(a).cmp(&size(b)).then_with(|| self.compare_names(a, b)),
+            SortKey::MTime => mtime(a).cmp(&mtime(b)).then_with(|| self.compare_names(a, b)),
+        };
+
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    fn compare_names(&self, a: &FsNode, b: &FsNode) -> Ordering {
+        self.compare_text(&a.name, &b.name)
+    }
+
+    /// order of two names, those equal but for case end in byte order
+    fn compare_text(&self, a: &str, b: &str) -> Ordering {
+        let ordering = match self.natural {
+            true => self.compare_natural(a, b),
+            false => self.compare_letters(a, b),
+        };
+        ordering.then_with(|| a.cmp(b))
+    }
+
+    /// runs of digits compared by their value, the text between them as letters
+    fn compare_natural(&self, a: &str, b: &str) -> Ordering {
+        let (mut a, mut b) = (runs(a), runs(b));
+        loop {
+            let (a, b) = match (a.next(), b.next()) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(a), Some(b)) => (a, b),
+            };
+            let ordering = match is_number(a) && is_number(b) {
+                true => compare_numbers(a, b),
+                false => self.compare_letters(a, b),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+    }
+
+    fn compare_letters(&self, a: &str, b: &str) -> Ordering {
+        match (self.locale, self.case_sensitive) {
+            (true, _) => collate(a, b),
+            (false, true) => a.cmp(b),
+            (false, false) => a.to_lowercase().cmp(&b.to_lowercase()),
+        }
+    }
+}
+
+/// the text split where digits start or stop
+fn runs(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let digits = rest.chars().next()?.is_ascii_digit();
+        let end = rest.find(|c: char| c.is_ascii_digit() != digits).unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(end);
+        rest = tail;
+        Some(run)
+    })
+}
+
+fn is_number(run: &str) -> bool {
+    run.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// order of two runs of digits by value however long, fewer leading zeros first
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let (a_digits, b_digits) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+    a_digits
+        .len()
+        .cmp(&b_digits.len())
+        .then_with(|| a_digits.cmp(b_digits))
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+/// take the collation of names from the environment, LC_COLLATE or else LANG
+///
+/// Call it at the start of main, before any thread, as `setlocale` is not
+/// safe to call while others may read the locale. Until then the names
+/// collate in the C locale, byte by byte. Only the collation changes,
+/// numbers and messages stay as they are.
+pub fn init_collation() {
+    #[cfg(unix)]
+    // SAFETY: the locale name is a valid C string, and no other thread runs yet to read the locale
+    unsafe {
+        libc::setlocale(libc::LC_COLLATE, c"".as_ptr());
+    }
+}
+
+/// order of two texts by the collation set by [`init_collation`]
+#[cfg(unix)]
+fn collate(a: &str, b: &str) -> Ordering {
+    match (CString::new(a), CString::new(b)) {
+        // SAFETY: both are valid C strings living through the call, strcoll only reads them and the locale
+        (Ok(a), Ok(b)) => unsafe { libc::strcoll(a.as_ptr(), b.as_ptr()) }.cmp(&0),
+        _ => a.cmp(b),
+    }
+}
+
+/// without the collation of the system, letters are compared regardless of case
+#[cfg(not(unix))]
+fn collate(a: &str, b: &str) -> Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase())
+}
+
+/// extension of a file name, dotfiles without another dot have none
+pub fn extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(0) | None => "",
+        Some(position) => &name[position + 1..],
+    }
+}
+
+fn size(node: &FsNode) -> u64 {
+    node.size().unwrap_or(0)
+}
+
+fn mtime(node: &FsNode) -> Option<std::time::SystemTime> {
+    node.metadata.as_ref().and_then(|metadata| metadata.modified)
+}
@@ -0,0 +1,180 @@
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+
+use crate::shell::{shell_quote, user_shell};
+
+/// bytes read from the shell at once
+const CHUNK_SIZE: usize = 4096;
+
+/// interactive shell living in a pseudo-terminal for the whole session
+///
+/// It keeps its environment and history between visits. Its output is
+/// queued while the panels are shown and written out when the user comes
+/// back to it.
+pub struct Subshell {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    output: Receiver<Vec<u8>>,
+    /// directory last sent with `cd`
+    synced_dir: PathBuf,
+}
+
+impl std::fmt::Debug for Subshell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subshell").field("pid", &self.child.process_id()).field("dir", &self.synced_dir).finish()
+    }
+}
+
+fn pty_error(err: impl std::fmt::Display) -> io::Error {
+    io::Error::other(format!("pseudo-terminal: {err}"))
+}
+
+impl Subshell {
+    /// start the user's shell in `dir` on a terminal of `columns` by `rows`
+    pub fn spawn(dir: &Path, columns: u16, rows: u16) -> io::Result<Subshell> {
+        let pair = native_pty_system().openpty(pty_size(columns, rows)).map_err(pty_error)?;
+        let mut command = CommandBuilder::new(user_shell());
+        command.cwd(dir);
+        let child = pair.slave.spawn_command(command).map_err(pty_error)?;
+        // the shell holds the only slave end, reads end when it exits
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader().map_err(pty_error)?;
+        let writer = pair.master.take_writer().map_err(pty_error)?;
+        let (sender, output) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buffer = vec![0; CHUNK_SIZE];
+            while let Ok(read) = reader.read(&mut buffer) {
+                if read == 0 || sender.send(buffer[..read].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Subshell {
+            master: pair.master,
+            writer,
+            child,
+            output,
+            synced_dir: dir.to_path_buf(),
+        })
+    }
+
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// output written by the shell since the last call
+    pub fn take_output(&self) -> Vec<u8> {
+        self.output.try_iter().flatten().collect()
+    }
+
+    /// send typed text to the shell
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.writer.flush()
+    }
+
+    pub fn write_key(&mut self, key: &KeyEvent) -> io::Result<()> {
+        match key_bytes(key) {
+            Some(bytes) => self.write(&bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// run a line in the shell as if typed
+    pub fn send_command(&mut self, command: &str) -> io::Result<()> {
+        self.write(format!("{command}\r").as_bytes())
+    }
+
+    /// move the shell to the directory of the panel if it is not there yet
+    ///
+    /// The leading space keeps the `cd` out of the history of most shells.
+    pub fn sync_dir(&mut self, dir: &Path) -> io::Result<()> {
+        if self.cwd().as_deref().unwrap_or(&self.synced_dir) == dir {
+            return Ok(());
+        }
+        self.synced_dir = dir.to_path_buf();
+        self.send_command(&format!(" cd {}", shell_quote(&dir.to_string_lossy())))
+    }
+
+    /// directory the shell is in now, known on Linux only
+    pub fn cwd(&self) -> Option<PathBuf> {
+        let pid = self.child.process_id()?;
+        std::fs::read_link(format!("/proc/{pid}/cwd")).ok()
+    }
+
+    pub fn resize(&self, columns: u16, rows: u16) -> io::Result<()> {
+        self.master.resize(pty_size(columns, rows)).map_err(pty_error)
+    }
+}
+
+impl Drop for Subshell {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+fn pty_size(columns: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows,
+        cols: columns,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// the bytes a terminal sends for a key
+fn key_bytes(key: &KeyEvent) -> Option<Vec<u8>> {
+    let sequence: &[u8] = match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) && c.is_ascii_alphabetic() => {
+            return Some(vec![c.to_ascii_lowercase() as u8 & 0x1f]);
+        }
+        KeyCode::Char(c) => {
+            let mut bytes = Vec::new();
+            // alt is sent as an escape before the key
+            if key.modifiers.contains(KeyModifiers::ALT) {
+                bytes.push(0x1b);
+            }
+            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            return Some(bytes);
+        }
+        KeyCode::Enter => b"\r",
+        KeyCode::Tab => b"\t",
+        KeyCode::BackTab => b"\x1b[Z",
+        KeyCode::Backspace => b"\x7f",
+        KeyCode::Esc => b"\x1b",
+        KeyCode::Up => b"\x1b[A",
+        KeyCode::Down => b"\x1b[B",
+        KeyCode::Right => b"\x1b[C",
+        KeyCode::Left => b"\x1b[D",
+        KeyCode::Home => b"\x1b[H",
+        KeyCode::End => b"\x1b[F",
+        KeyCode::PageUp => b"\x1b[5~",
+        KeyCode::PageDown => b"\x1b[6~",
+        KeyCode::Insert => b"\x1b[2~",
+        KeyCode::Delete => b"\x1b[3~",
+        KeyCode::F(number) => match number {
+            1 => b"\x1bOP",
+            2 => b"\x1bOQ",
+            3 => b"\x1bOR",
+            4 => b"\x1bOS",
+            5 => b"\x1b[15~",
+            6 => b"\x1b[17~",
+            7 => b"\x1b[18~",
+            8 => b"\x1b[19~",
+            9 => b"\x1b[20~",
+            10 => b"\x1b[21~",
+            11 => b"\x1b[23~",
+            12 => b"\x1b[24~",
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(sequence.to_vec())
+}
@@ -0,0 +1,425 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::compare::{compare_nodes, CompareMode, DiffKind, Difference};
+use crate::format::SizeUnits;
+use crate::fs_node::{disk_usage, FsNodeRef, FsNodeType};
+use crate::ops::{copy_path_to, remove_path, CopyFlow, CopyOptions, CopyProgress};
+use crate::panel::{PanelManager, PanelSide};
+use crate::vfs::VfsRef;
+
+/// which way files travel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncDirection {
+    LeftToRight,
+    RightToLeft,
+    /// each side gets what it misses and the older of two different files is replaced
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncOptions {
+    pub direction: SyncDirection,
+    pub mode: CompareMode,
+    /// remove entries the source side does not have, one-way only
+    pub delete_orphans: bool,
+    /// report what would be done without touching anything
+    pub dry_run: bool,
+}
+
+impl SyncOptions {
+    /// options typed at the sync prompt
+    ///
+    /// `>` copies left to right, `<` right to left and `=` both ways. The
+    /// letters `d` (delete orphans), `n` (dry run) and `c` (compare contents)
+    /// may follow.
+    pub fn parse(input: &str) -> Result<SyncOptions, String> {
+        let mut options = SyncOptions {
+            direction: SyncDirection::LeftToRight,
+            mode: CompareMode::SizeTime,
+            delete_orphans: false,
+            dry_run: false,
+        };
+        for c in input.chars().filter(|c| !c.is_whitespace()) {
+            match c {
+                '>' => options.direction = SyncDirection::LeftToRight,
+                '<' => options.direction = SyncDirection::RightToLeft,
+                '=' => options.direction = SyncDirection::Both,
+                'd' => options.delete_orphans = true,
+                'n' => options.dry_run = true,
+                'c' => options.mode = CompareMode::Content,
+                _ => return Err(format!("unknown sync option {c}")),
+            }
+        }
+        if options.delete_orphans && options.direction == SyncDirection::Both {
+            return Err(String::from("orphans can only be deleted by a one-way sync"));
+        }
+        Ok(options)
+    }
+}
+
+/// one step of a synchronization
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncAction {
+    /// copy an entry from a side to the same place on the other side
+    Copy {
+        from: PanelSide,
+        relative: PathBuf,
+        /// bytes of the entry, directories count their content
+        size: u64,
+        /// the destination exists and is not older, replacing it needs confirmation
+        conflict: bool,
+    },
+    Delete { side: PanelSide, relative: PathBuf },
+}
+
+impl SyncAction {
+    pub fn relative(&self) -> &Path {
+        match self {
+            SyncAction::Copy { relative, .. } | SyncAction::Delete { relative, .. } => relative,
+        }
+    }
+}
+
+/// everything a synchronization will do, for review before running it
+#[derive(Debug, Clone)]
+pub struct SyncPlan {
+    pub left: (VfsRef, PathBuf),
+    pub right: (VfsRef, PathBuf),
+    pub options: SyncOptions,
+    pub actions: Vec<SyncAction>,
+}
+
+impl SyncPlan {
+    /// compare two directory trees and list the copies and deletions bringing them in line
+    pub fn new(left: &FsNodeRef, right: &FsNodeRef, options: SyncOptions) -> io::Result<SyncPlan> {
+        let side = |node: &FsNodeRef| {
+            let node = node.read();
+            (Arc::clone(&node.vfs), node.path())
+        };
+        let mut plan = SyncPlan {
+            left: side(left),
+            right: side(right),
+            options,
+            actions: Vec::new(),
+        };
+        for difference in compare_nodes(left, right, options.mode, true)? {
+            if let Some(action) = plan.action_for(difference) {
+                plan.actions.push(action);
+            }
+        }
+        Ok(plan)
+    }
+
+    fn action_for(&self, difference: Difference) -> Option<SyncAction> {
+        let copy = |from: PanelSide, conflict: bool| {
+            let (vfs, entry) = match from {
+                PanelSide::Left => (&self.left.0, difference.left.as_ref()?),
+                PanelSide::Right => (&self.right.0, difference.right.as_ref()?),
+            };
+            let size = match entry.node_type {
+                FsNodeType::Directory => disk_usage(vfs.as_ref(), &entry.path),
+                _ => entry.metadata.size,
+            };
+            Some(SyncAction::Copy {
+                from,
+                relative: difference.relative.clone(),
+                size,
+                conflict,
+            })
+        };
+        let delete = |side: PanelSide| {
+            Some(SyncAction::Delete {
+                side,
+                relative: difference.relative.clone(),
+            })
+        };
+        let orphans = self.options.delete_orphans;
+        match (self.options.direction, difference.kind) {
+            (SyncDirection::LeftToRight, DiffKind::LeftOnly) => copy(PanelSide::Left, false),
+            (SyncDirection::LeftToRight, DiffKind::RightOnly) if orphans => delete(PanelSide::Right),
+            (SyncDirection::LeftToRight, DiffKind::LeftNewer) => copy(PanelSide::Left, false),
+            (SyncDirection::LeftToRight, DiffKind::RightNewer | DiffKind::Different) => copy(PanelSide::Left, true),
+            (SyncDirection::RightToLeft, DiffKind::RightOnly) => copy(PanelSide::Right, false),
+            (SyncDirection::RightToLeft, DiffKind::LeftOnly) if orphans => delete(PanelSide::Left),
+            (SyncDirection::RightToLeft, DiffKind::RightNewer) => copy(PanelSide::Right, false),
+            (SyncDirection::RightToLeft, DiffKind::LeftNewer | DiffKind::Different) => copy(PanelSide::Right, true),
+            (SyncDirection::Both, DiffKind::LeftOnly | DiffKind::LeftNewer) => copy(PanelSide::Left, false),
+            (SyncDirection::Both, DiffKind::RightOnly | DiffKind::RightNewer) => copy(PanelSide::Right, false),
+            // no side is more recent, the left one wins if the user agrees
+            (SyncDirection::Both, DiffKind::Different) => copy(PanelSide::Left, true),
+            _ => None,
+        }
+    }
+
+    fn root(&self, side: PanelSide) -> &(VfsRef, PathBuf) {
+        match side {
+            PanelSide::Left => &self.left,
+            PanelSide::Right => &self.right,
+        }
+    }
+
+    /// bytes the copies of the plan will write
+    pub fn total_bytes(&self) -> u64 {
+        self.actions
+            .iter()
+            .map(|action| match action {
+                SyncAction::Copy { size, .. } => *size,
+                SyncAction::Delete { .. } => 0,
+            })
+            .sum()
+    }
+}
+
+/// answer to a conflicting copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictAnswer {
+    Overwrite,
+    Skip,
+    /// overwrite this and every following conflict
+    OverwriteAll,
+    /// skip this and every following conflict
+    SkipAll,
+    Abort,
+}
+
+/// what a synchronization did, or would have done in a dry run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SyncReport {
+    pub copied: usize,
+    pub deleted: usize,
+    pub skipped: usize,
+    pub aborted: bool,
+}
+
+/// run the actions of a plan in order through the ops layer
+///
+/// `ask` decides conflicting copies. Errors stop the synchronization, the
+/// actions done so far stay done.
+pub fn execute(
+    plan: &SyncPlan,
+    ask: &mut dyn FnMut(&SyncAction) -> ConflictAnswer,
+    progress: &mut dyn FnMut(&CopyProgress) -> CopyFlow,
+) -> io::Result<SyncReport> {
+    let mut report = SyncReport::default();
+    // actions are counted by the report rather than files
+    let mut state = CopyProgress::new(plan.total_bytes(), 0);
+    let mut always: Option<bool> = None;
+    for action in &plan.actions {
+        match action {
+            SyncAction::Copy {
+                from,
+                relative,
+                size,
+                conflict,
+            } => {
+                if *conflict {
+                    let overwrite = match always {
+                        Some(overwrite) => overwrite,
+                        None => match ask(action) {
+                            ConflictAnswer::Overwrite => true,
+                            ConflictAnswer::Skip => false,
+                            ConflictAnswer::OverwriteAll => *always.insert(true),
+                            ConflictAnswer::SkipAll => *always.insert(false),
+                            ConflictAnswer::Abort => {
+                                report.aborted = true;
+                                return Ok(report);
+                            }
+                        },
+                    };
+                    if !overwrite {
+                        report.skipped += 1;
+                        state.total_bytes = state.total_bytes.saturating_sub(*size);
+                        continue;
+                    }
+                }
+                let (src_vfs, src_root) = plan.root(*from);
+                let (dest_vfs, dest_root) = plan.root(from.opposite());
+                let (src, dest) = (src_root.join(relative), dest_root.join(relative));
+                if plan.options.dry_run {
+                    state.current_file = src;
+                    state.bytes_copied += size;
+                    progress(&state);
+                } else {
+                    // a file replacing a directory, or the other way round, needs room
+                    if let Ok(existing) = dest_vfs.metadata(&dest)
+                        && (existing.node_type == FsNodeType::Directory
+                            || src_vfs.metadata(&src)?.node_type == FsNodeType::Directory)
+                    {
+                        remove_path(dest_vfs, &dest)?;
+                    }
+                    copy_path_to(src_vfs, &src, dest_vfs, &dest, &CopyOptions::default(), &mut state, progress)?;
+                }
+                report.copied += 1;
+            }
+            SyncAction::Delete { side, relative } => {
+                let (vfs, root) = plan.root(*side);
+                if !plan.options.dry_run {
+                    remove_path(vfs, &root.join(relative))?;
+                }
+                report.deleted += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// news from a synchronization running in the background
+#[derive(Debug, Clone)]
+pub enum SyncEvent {
+    Progress { bytes_copied: u64, total_bytes: u64, current_file: PathBuf },
+    /// waiting for an answer to this conflict
+    Conflict(SyncAction),
+    Done(Result<SyncReport, String>),
+}
+
+/// state of the synchronization screen
+#[derive(Debug)]
+pub enum SyncStage {
+    /// the plan is shown, nothing was done yet
+    Review,
+    Running,
+    Conflict(SyncAction),
+    Finished(Result<SyncReport, String>),
+}
+
+/// what a key did to the synchronization screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncKeyResult {
+    None,
+    Close,
+}
+
+/// a plan under review, then running on a background thread
+#[derive(Debug)]
+pub struct SyncSession {
+    pub plan: Arc<SyncPlan>,
+    pub stage: SyncStage,
+    /// bytes copied and to copy, with the file being copied
+    pub progress: (u64, u64, PathBuf),
+    /// units of the sizes of the progress line
+    pub units: SizeUnits,
+    pub cursor: usize,
+    pub offset: usize,
+    /// rows of the action list, set by the renderer
+    pub height: usize,
+    events: Option<Receiver<SyncEvent>>,
+    answers: Option<Sender<ConflictAnswer>>,
+}
+
+impl SyncSession {
+    pub fn new(plan: SyncPlan) -> SyncSession {
+        let total = plan.total_bytes();
+        SyncSession {
+            plan: Arc::new(plan),
+            stage: SyncStage::Review,
+            progress: (0, total, PathBuf::new()),
+            units: SizeUnits::default(),
+            cursor: 0,
+            offset: 0,
+            height: 0,
+            events: None,
+            answers: None,
+        }
+    }
+
+    /// plan the synchronization of the two panel directories
+    pub fn for_panels(manager: &PanelManager, options: SyncOptions) -> io::Result<SyncSession> {
+        let mut session = SyncPlan::new(&manager.left.root, &manager.right.root, options).map(SyncSession::new)?;
+        session.units = manager.format.units;
+        Ok(session)
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.stage, SyncStage::Running | SyncStage::Conflict(_))
+    }
+
+    fn start(&mut self) {
+        let (event_sender, events) = mpsc::channel();
+        let (answers, answer_receiver) = mpsc::channel();
+        let plan = Arc::clone(&self.plan);
+        thread::spawn(move || {
+            let progress_sender = event_sender.clone();
+            let mut ask = |action: &SyncAction| {
+                let _ = event_sender.send(SyncEvent::Conflict(action.clone()));
+                // the screen went away, stop rather than guess
+                answer_receiver.recv().unwrap_or(ConflictAnswer::Abort)
+            };
+            let mut progress = |state: &CopyProgress| {
+                let _ = progress_sender.send(SyncEvent::Progress {
+                    bytes_copied: state.bytes_copied,
+                    total_bytes: state.total_bytes,
+                    current_file: state.current_file.clone(),
+                });
+                CopyFlow::Continue
+            };
+            let result = execute(&plan, &mut ask, &mut progress).map_err(|err| err.to_string());
+            let _ = progress_sender.send(SyncEvent::Done(result));
+        });
+        self.events = Some(events);
+        self.answers = Some(answers);
+        self.stage = SyncStage::Running;
+    }
+
+    /// take the events sent by the background thread
+    pub fn poll(&mut self) {
+        let Some(events) = &self.events else {
+            return;
+        };
+        for event in events.try_iter() {
+            match event {
+                SyncEvent::Progress {
+                    bytes_copied,
+                    total_bytes,
+                    current_file,
+                } => self.progress = (bytes_copied, total_bytes, current_file),
+                SyncEvent::Conflict(action) => self.stage = SyncStage::Conflict(action),
+                SyncEvent::Done(result) => self.stage = SyncStage::Finished(result),
+            }
+        }
+    }
+
+    fn answer(&mut self, answer: ConflictAnswer) {
+        if let Some(answers) = &self.answers {
+            let _ = answers.send(answer);
+        }
+        self.stage = SyncStage::Running;
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> SyncKeyResult {
+        match (&self.stage, key.code) {
+            (SyncStage::Review, KeyCode::Enter | KeyCode::F(2)) => self.start(),
+            (SyncStage::Review | SyncStage::Finished(_), KeyCode::Esc | KeyCode::F(10)) => return SyncKeyResult::Close,
+            (SyncStage::Finished(_), KeyCode::Enter) => return SyncKeyResult::Close,
+            (SyncStage::Review, KeyCode::Up) => self.cursor = self.cursor.saturating_sub(1),
+            (SyncStage::Review, KeyCode::Down) => {
+                self.cursor = (self.cursor + 1).min(self.plan.actions.len().saturating_sub(1));
+            }
+            (SyncStage::Review, KeyCode::PageUp) => self.cursor = self.cursor.saturating_sub(self.height.max(1)),
+            (SyncStage::Review, KeyCode::PageDown) => {
+                self.cursor = (self.cursor + self.height.max(1)).min(self.plan.actions.len().saturating_sub(1));
+            }
+            (SyncStage::Conflict(_), KeyCode::Char('o')) => self.answer(ConflictAnswer::Overwrite),
+            (SyncStage::Conflict(_), KeyCode::Char('s')) => self.answer(ConflictAnswer::Skip),
+            (SyncStage::Conflict(_), KeyCode::Char('a')) => self.answer(ConflictAnswer::OverwriteAll),
+            (SyncStage::Conflict(_), KeyCode::Char('n')) => self.answer(ConflictAnswer::SkipAll),
+            (SyncStage::Conflict(_), KeyCode::Esc) => self.answer(ConflictAnswer::Abort),
+            _ => {}
+        }
+        SyncKeyResult::None
+    }
+
+    /// adjust the scroll offset so the cursor is visible
+    pub fn scroll_to_cursor(&mut self) {
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.height > 0 && self.cursor >= self.offset + self.height {
+            self.offset = self.cursor + 1 - self.height;
+        }
+    }
+}
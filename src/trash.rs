@@ -0,0 +1,334 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::ops;
+use crate::vfs::archive::{civil_to_unix, unix_time, unix_to_civil};
+use crate::vfs::{self, VfsRef};
+
+/// trash can following the freedesktop.org trash specification
+///
+/// Trashed entries live in `files/` under a unique name, with a
+/// `info/<name>.trashinfo` file recording where they came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trash {
+    pub root: PathBuf,
+}
+
+/// an entry of the trash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashItem {
+    /// name in the `files` directory
+    pub name: String,
+    /// path the entry had before it was trashed
+    pub original: PathBuf,
+    pub deleted: Option<SystemTime>,
+}
+
+impl Trash {
+    pub fn new(root: &Path) -> Trash {
+        Trash {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// the home trash, `$XDG_DATA_HOME/Trash` falling back to `~/.local/share/Trash`
+    pub fn home() -> Option<Trash> {
+        let data = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".local").join("share"),
+        };
+        Some(Trash::new(&data.join("Trash")))
+    }
+
+    /// the trash of the entries of `dir`
+    ///
+    /// Entries on the filesystem of the home trash go there. The others go
+    /// to the trash at the top of their own filesystem, `$topdir/.Trash/$uid`
+    /// when `.Trash` is a sticky directory, `$topdir/.Trash-$uid` otherwise.
+    pub fn for_directory(dir: &Path) -> Option<Trash> {
+        let home = Trash::home()?;
+        #[cfg(unix)]
+        if let Ok(dir) = std::path::absolute(dir)
+            && let Some(device) = device(&dir)
+            && existing_device(&home.root) != Some(device)
+        {
+            return Some(top_trash(&mount_point(&dir, device)));
+        }
+        Some(home)
+    }
+
+    pub fn files_dir(&self) -> PathBuf {
+        self.root.join("files")
+    }
+
+    pub fn info_dir(&self) -> PathBuf {
+        self.root.join("info")
+    }
+
+    fn info_path(&self, name: &str) -> PathBuf {
+        self.info_dir().join(format!("{name}.trashinfo"))
+    }
+
+    /// move a local path to the trash, which must be on the same filesystem
+    pub fn put(&self, path: &Path) -> io::Result<TrashItem> {
+        let original = std::path::absolute(path)?;
+        fs::symlink_metadata(&original)?;
+        // a copy to the trash of another filesystem would be slow and free nothing
+        #[cfg(unix)]
+        if let Some(parent) = original.parent()
+            && device(parent) != existing_device(&self.root)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::CrossesDevices,
+                format!("{} is not on the filesystem of the trash {}", original.display(), self.root.display()),
+            ));
+        }
+        for dir in [self.files_dir(), self.info_dir()] {
+            create_private_dir(&dir)?;
+        }
+        let base = original
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("unnamed"));
+        let deleted = SystemTime::now();
+        let info = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(&original),
+            format_date(deleted)
+        );
+
+        // the info file is created exclusively, which reserves the name
+        let mut number = 1;
+        let name = loop {
+            let name = if number == 1 { base.clone() } else { format!("{base}.{number}") };
+            let taken = self.files_dir().join(&name).symlink_metadata().is_ok();
+            if !taken {
+                match OpenOptions::new().write(true).create_new(true).open(self.info_path(&name)) {
+                    Ok(mut file) => {
+                        file.write_all(info.as_bytes())?;
+                        break name;
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            number += 1;
+        };
+
+        let local = vfs::local();
+        if let Err(err) = ops::move_path(&local, &original, &local, &self.files_dir().join(&name)) {
+            let _ = fs::remove_file(self.info_path(&name));
+            return Err(err);
+        }
+        Ok(TrashItem {
+            name,
+            original,
+            deleted: Some(deleted),
+        })
+    }
+
+    /// entries of the trash sorted by name, broken info files are skipped
+    pub fn items(&self) -> io::Result<Vec<TrashItem>> {
+        let entries = match fs::read_dir(self.info_dir()) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut items = Vec::new();
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let Some(name) = file_name.strip_suffix(".trashinfo") else {
+                continue;
+            };
+            if let Ok(item) = self.item(name) {
+                items.push(item);
+            }
+        }
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(items)
+    }
+
+    /// the trashed entry of a name with its info
+    pub fn item(&self, name: &str) -> io::Result<TrashItem> {
+        let text = fs::read_to_string(self.info_path(name))?;
+        self.files_dir().join(name).symlink_metadata()?;
+        let mut original = None;
+        let mut deleted = None;
+        for line in text.lines() {
+            if let Some(path) = line.strip_prefix("Path=") {
+                original = decode_path(path);
+            } else if let Some(date) = line.strip_prefix("DeletionDate=") {
+                deleted = parse_date(date);
+            }
+        }
+        let original = original.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{name}.trashinfo has no path"))
+        })?;
+        // relative paths are relative to the top of the filesystem holding the trash
+        let top = match self.root.parent() {
+            Some(shared) if shared.file_name().is_some_and(|name| name == ".Trash") => shared.parent(),
+            parent => parent,
+        };
+        let original = match top {
+            Some(top) if original.is_relative() => top.join(original),
+            _ => original,
+        };
+        Ok(TrashItem {
+            name: name.to_string(),
+            original,
+            deleted,
+        })
+    }
+
+    /// move a trashed entry back where it came from, returns that path
+    pub fn restore(&self, name: &str) -> io::Result<PathBuf> {
+        let item = self.item(name)?;
+        if item.original.symlink_metadata().is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", item.original.display()),
+            ));
+        }
+        if let Some(parent) = item.original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let local = vfs::local();
+        ops::move_path(&local, &self.files_dir().join(name), &local, &item.original)?;
+        fs::remove_file(self.info_path(name))?;
+        Ok(item.original)
+    }
+
+    /// remove a trashed entry for good
+    pub fn purge(&self, name: &str) -> io::Result<()> {
+        let local: VfsRef = vfs::local();
+        ops::remove_path(&local, &self.files_dir().join(name))?;
+        fs::remove_file(self.info_path(name))
+    }
+}
+
+#[cfg(unix)]
+fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|metadata| metadata.dev())
+}
+
+/// the device of a path, or of its closest existing ancestor as a trash may not exist yet
+#[cfg(unix)]
+fn existing_device(path: &Path) -> Option<u64> {
+    path.ancestors().find_map(device)
+}
+
+/// the topmost ancestor of `dir` still on `device`
+#[cfg(unix)]
+fn mount_point(dir: &Path, device: u64) -> PathBuf {
+    let mut top = dir;
+    while let Some(parent) = top.parent()
+        && self::device(parent) == Some(device)
+    {
+        top = parent;
+    }
+    top.to_path_buf()
+}
+
+/// the trash of the user at the top of a filesystem
+#[cfg(unix)]
+fn top_trash(top: &Path) -> Trash {
+    use std::os::unix::fs::PermissionsExt;
+    let uid = uzers::get_current_uid();
+    let shared = top.join(".Trash");
+    // a shared trash set up by an administrator is sticky, a symlink could lead anywhere
+    let trusted = fs::symlink_metadata(&shared)
+        .is_ok_and(|metadata| metadata.is_dir() && metadata.permissions().mode() & 0o1000 != 0);
+    match trusted {
+        true => Trash::new(&shared.join(uid.to_string())),
+        false => Trash::new(&top.join(format!(".Trash-{uid}"))),
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    // other users must not see what was thrown away
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    std::ffi::OsString::from_vec(bytes).into()
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// percent-encode a path as the specification asks, slashes are kept
+pub fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path_bytes(path) {
+        if byte.is_ascii_alphanumeric() || b"/-_.!~*'()".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+pub fn decode_path(encoded: &str) -> Option<PathBuf> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut input = encoded.bytes();
+    while let Some(byte) = input.next() {
+        if byte == b'%' {
+            let high = (input.next()? as char).to_digit(16)?;
+            let low = (input.next()? as char).to_digit(16)?;
+            bytes.push((high * 16 + low) as u8);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    Some(path_from_bytes(bytes))
+}
+
+/// `YYYY-MM-DDThh:mm:ss`, in UTC for want of the local time zone
+fn format_date(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    let (year, month, day, hour, minute, second) = unix_to_civil(seconds);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}")
+}
+
+fn parse_date(text: &str) -> Option<SystemTime> {
+    let (date, time) = text.trim().split_once('T')?;
+    let mut date = date.splitn(3, '-');
+    let year = date.next()?.parse().ok()?;
+    let month = date.next()?.parse().ok()?;
+    let day = date.next()?.parse().ok()?;
+    let mut time = time.splitn(3, ':');
+    let hour = time.next()?.parse().ok()?;
+    let minute = time.next()?.parse().ok()?;
+    // fractions of seconds are allowed and ignored
+    let second = time.next()?.get(..2)?.parse().ok()?;
+    Some(unix_time(civil_to_unix(year, month, day, hour, minute, second)))
+}
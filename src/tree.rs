@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::fs_node::{FsNode, FsNodeRef, FsNodeType};
+use crate::sort::SortMode;
+use crate::vfs::VfsRef;
+
+/// directory hierarchy of a backend, shown as a tree in place of a listing
+///
+/// Directories are read the first time they are expanded. Symlinks are not
+/// listed, so the tree never loops.
+#[derive(Debug)]
+pub struct TreeView {
+    pub root: FsNodeRef,
+    /// directories showing their subdirectories
+    pub expanded: HashSet<PathBuf>,
+    /// directories whose children were read
+    loaded: HashSet<PathBuf>,
+    /// index of the selected row
+    pub cursor: usize,
+    pub offset: usize,
+}
+
+/// one line of the flattened tree
+#[derive(Debug, Clone)]
+pub struct TreeRow {
+    pub node: FsNodeRef,
+    pub depth: usize,
+    /// branch characters drawn in front of the name
+    pub prefix: String,
+    pub expanded: bool,
+}
+
+impl TreeView {
+    /// open the tree at the top of the backend with `path` expanded and selected
+    pub fn open(vfs: &VfsRef, path: &Path) -> io::Result<TreeView> {
+        let top = path.ancestors().last().unwrap_or(path);
+        let root = FsNode::from_entry(vfs.metadata(top)?, Arc::clone(vfs)).into_ref();
+        let mut tree = TreeView {
+            root,
+            expanded: HashSet::new(),
+            loaded: HashSet::new(),
+            cursor: 0,
+            offset: 0,
+        };
+        let mut ancestors: Vec<&Path> = path.ancestors().collect();
+        ancestors.reverse();
+        for ancestor in &ancestors {
+            // a missing or unreadable directory stops the expansion where it is
+            if !tree.select(ancestor) || tree.expand().is_err() {
+                break;
+            }
+        }
+        tree.select(path);
+        Ok(tree)
+    }
+
+    /// visible rows, depth first in name order
+    pub fn rows(&self) -> TreeRows<'_> {
+        TreeRows {
+            tree: self,
+            stack: vec![(Arc::clone(&self.root), String::new(), None)],
+        }
+    }
+
+    /// node of the selected row
+    pub fn selected(&self) -> Option<FsNodeRef> {
+        self.rows().nth(self.cursor).map(|row| row.node)
+    }
+
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.selected().map(|node| node.read().path())
+    }
+
+    /// put the cursor on a visible directory, false if it is not shown
+    pub fn select(&mut self, path: &Path) -> bool {
+        match self.rows().position(|row| row.node.read().path() == path) {
+            Some(position) => {
+                self.cursor = position;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// show the subdirectories of the selected directory, reading it if needed
+    pub fn expand(&mut self) -> io::Result<()> {
+        let Some(node) = self.selected() else {
+            return Ok(());
+        };
+        let path = node.read().path();
+        if !self.loaded.contains(&path) {
+            let mut node = node.write();
+            node.rescan()?;
+            node.sort_children(SortMode::default());
+            self.loaded.insert(path.clone());
+        }
+        self.expanded.insert(path);
+        Ok(())
+    }
+
+    /// hide the subdirectories of the selected directory, or select its parent
+    pub fn collapse(&mut self) {
+        let Some(node) = self.selected() else {
+            return;
+        };
+        let path = node.read().path();
+        if !self.expanded.remove(&path)
+            && let Some(parent) = path.parent()
+        {
+            self.select(parent);
+        }
+    }
+
+    /// read the selected directory again, keeping the expansion below it
+    pub fn reload(&mut self) -> io::Result<()> {
+        let Some(node) = self.selected() else {
+            return Ok(());
+        };
+        let path = node.read().path();
+        self.loaded.retain(|loaded| !loaded.starts_with(&path));
+        let mut expanded: Vec<PathBuf> = self.expanded.iter().filter(|expanded| expanded.starts_with(&path)).cloned().collect();
+        // parents come first, their children must be read before they can be selected
+        expanded.sort();
+        for directory in expanded {
+            if self.select(&directory) {
+                self.expand()?;
+            }
+        }
+        self.select(&path);
+        Ok(())
+    }
+
+    pub fn move_cursor_up(&mut self, rows: usize) {
+        self.cursor = self.cursor.saturating_sub(rows);
+    }
+
+    pub fn move_cursor_down(&mut self, rows: usize) {
+        let last = self.rows().count().saturating_sub(1);
+        self.cursor = (self.cursor + rows).min(last);
+    }
+
+    pub fn move_cursor_bottom(&mut self) {
+        self.cursor = self.rows().count().saturating_sub(1);
+    }
+
+    /// adjust the scroll offset so the cursor is visible in `height` rows
+    pub fn scroll_to_cursor(&mut self, height: usize) {
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if height > 0 && self.cursor >= self.offset + height {
+            self.offset = self.cursor + 1 - height;
+        }
+    }
+}
+
+/// iterator flattening the expanded part of a tree into rows
+pub struct TreeRows<'a> {
+    tree: &'a TreeView,
+    /// nodes left to visit with the guides of their parent and whether they are the last child
+    stack: Vec<(FsNodeRef, String, Option<bool>)>,
+}
+
+impl Iterator for TreeRows<'_> {
+    type Item = TreeRow;
+
+    fn next(&mut self) -> Option<TreeRow> {
+        let (node, guides, last) = self.stack.pop()?;
+        let depth = guides.chars().count() / 3 + usize::from(last.is_some());
+        let (prefix, child_guides) = match last {
+            // the root has no branch
+            None => (String::new(), String::new()),
+            Some(true) => (format!("{guides}└─ "), format!("{guides}   ")),
+            Some(false) => (format!("{guides}├─ "), format!("{guides}│  ")),
+        };
+        let expanded = self.tree.expanded.contains(&node.read().path());
+        if expanded {
+            let children: Vec<FsNodeRef> = node
+                .read()
+                .children
+                .iter()
+                .filter(|child| child.read().node_type == FsNodeType::Directory)
+                .cloned()
+                .collect();
+            let count = children.len();
+            for (index, child) in children.into_iter().enumerate().rev() {
+                self.stack.push((child, child_guides.clone(), Some(index + 1 == count)));
+            }
+        }
+        Some(TreeRow {
+            node,
+            depth,
+            prefix,
+            expanded,
+        })
+    }
+}
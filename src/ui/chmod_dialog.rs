@@ -0,0 +1,159 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::fs_node::FsNodeRef;
+use crate::ops::{ChangeScope, ChmodOptions};
+use crate::ui::prompt::LineInput;
+use crate::ui::theme::Theme;
+
+/// what a key did to the permissions dialog
+#[derive(Debug, Clone)]
+pub enum ChmodResult {
+    Pending,
+    Apply(Vec<FsNodeRef>, ChmodOptions),
+    Cancel,
+}
+
+/// permission bit of every checkbox, owner, group and others then the special bits
+const BITS: [u32; 12] = [
+    0o400, 0o200, 0o100, 0o040, 0o020, 0o010, 0o004, 0o002, 0o001, 0o4000, 0o2000, 0o1000,
+];
+
+const OCTAL: usize = 0;
+const FIRST_BIT: usize = 1;
+const RECURSIVE: usize = FIRST_BIT + BITS.len();
+const SCOPE: usize = RECURSIVE + 1;
+const FIELDS: usize = SCOPE + 1;
+
+/// permissions editor for the cursor entry or the marked entries
+#[derive(Debug, Clone)]
+pub struct ChmodDialog {
+    targets: Vec<FsNodeRef>,
+    pub mode: u32,
+    pub octal: LineInput,
+    pub recursive: bool,
+    pub scope: ChangeScope,
+    /// field with the focus
+    pub focus: usize,
+    /// why the octal field is refused
+    pub error: Option<String>,
+}
+
+impl ChmodDialog {
+    /// dialog starting from the permissions of the first target
+    pub fn new(targets: Vec<FsNodeRef>) -> ChmodDialog {
+        let mode = targets
+            .first()
+            .and_then(|node| node.read().metadata.as_ref().map(|metadata| metadata.permissions & 0o7777))
+            .unwrap_or(0o644);
+        ChmodDialog {
+            targets,
+            mode,
+            octal: LineInput::new(&format!("{mode:04o}")),
+            recursive: false,
+            scope: ChangeScope::All,
+            focus: FIRST_BIT,
+            error: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> ChmodResult {
+        match key.code {
+            KeyCode::Esc => return ChmodResult::Cancel,
+            KeyCode::Enter if self.error.is_none() => {
+                let options = ChmodOptions {
+                    mode: self.mode,
+                    recursive: self.recursive,
+                    scope: self.scope,
+                };
+                return ChmodResult::Apply(self.targets.clone(), options);
+            }
+            KeyCode::Tab | KeyCode::Down => self.focus = (self.focus + 1) % FIELDS,
+            KeyCode::BackTab | KeyCode::Up => self.focus = (self.focus + FIELDS - 1) % FIELDS,
+            KeyCode::Char(' ') if self.focus != OCTAL => match self.focus {
+                RECURSIVE => self.recursive = !self.recursive,
+                SCOPE => self.scope = self.scope.next(),
+                bit => {
+                    self.mode ^= BITS[bit - FIRST_BIT];
+                    self.octal.set_text(&format!("{:04o}", self.mode));
+                    self.error = None;
+                }
+            },
+            _ if self.focus == OCTAL && self.octal.handle_key(key) => {
+                match u32::from_str_radix(self.octal.text.trim(), 8) {
+                    Ok(mode) if mode <= 0o7777 => {
+                        self.mode = mode;
+                        self.error = None;
+                    }
+                    _ => self.error = Some(String::from("not an octal mode")),
+                }
+            }
+            _ => {}
+        }
+        ChmodResult::Pending
+    }
+
+    /// render the dialog centered in `area`
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.saturating_sub(4).min(48);
+        let [row] = Layout::vertical([Constraint::Length(13)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(row);
+
+        let title = match self.targets.as_slice() {
+            [node] => format!(" Permissions of {} ", node.read().name),
+            targets => format!(" Permissions of {} entries ", targets.len()),
+        };
+        let block = Block::default().borders(Borders::ALL).title(title).style(theme.dialog.style());
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let focused = |field: usize| if self.focus == field { theme.selected.style() } else { theme.dialog.style() };
+        let checkbox = |index: usize| {
+            let mark = if self.mode & BITS[index] != 0 { 'x' } else { ' ' };
+            Span::styled(format!("[{mark}]"), focused(FIRST_BIT + index))
+        };
+
+        const LABEL: usize = 10;
+        let (octal, cursor) = self.octal.visible(8);
+        let mut lines = vec![
+            Line::from(vec![
+                Span::raw(format!("{:<LABEL$}", "Octal:")),
+                Span::styled(octal, theme.panel.style()),
+            ]),
+            Line::raw(""),
+            Line::raw(format!("{:<LABEL$}read  write  execute", "")),
+        ];
+        for (row, label) in ["Owner", "Group", "Others"].into_iter().enumerate() {
+            let mut spans = vec![Span::raw(format!("{label:<LABEL$}"))];
+            for column in 0..3 {
+                spans.push(checkbox(row * 3 + column));
+                spans.push(Span::raw(if column == 0 { "   " } else { "    " }));
+            }
+            lines.push(Line::from(spans));
+        }
+        let mut special = vec![Span::raw(format!("{:<LABEL$}", "Special"))];
+        for (index, label) in [(9, " setuid "), (10, " setgid "), (11, " sticky")] {
+            special.push(checkbox(index));
+            special.push(Span::raw(label));
+        }
+        lines.push(Line::from(special));
+        lines.push(Line::raw(""));
+        let recursive = if self.recursive { 'x' } else { ' ' };
+        lines.push(Line::styled(format!("[{recursive}] Apply inside directories"), focused(RECURSIVE)));
+        lines.push(Line::from(vec![
+            Span::raw(format!("{:<LABEL$}", "Apply to:")),
+            Span::styled(format!("< {} >", self.scope.label()), focused(SCOPE)),
+        ]));
+        if let Some(error) = &self.error {
+            lines.push(Line::styled(error.as_str(), theme.error.style()));
+        }
+        frame.render_widget(Paragraph::new(lines), inner);
+        if self.focus == OCTAL {
+            frame.set_cursor_position((inner.x + LABEL as u16 + cursor as u16, inner.y));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::fs_node::FsNodeRef;
+use crate::ops::chown::{system_groups, system_users, Account};
+use crate::ops::ChownOptions;
+use crate::ui::theme::Theme;
+
+/// what a key did to the owner dialog
+#[derive(Debug, Clone)]
+pub enum ChownResult {
+    Pending,
+    Apply(Vec<FsNodeRef>, ChownOptions),
+    Cancel,
+}
+
+const USERS: usize = 0;
+const GROUPS: usize = 1;
+const RECURSIVE: usize = 2;
+const FIELDS: usize = 3;
+
+/// list of accounts with a cursor, the first row keeps the current value
+#[derive(Debug, Clone)]
+pub struct AccountList {
+    pub accounts: Vec<Account>,
+    /// 0 is "unchanged", then the accounts
+    pub cursor: usize,
+    pub offset: usize,
+}
+
+impl AccountList {
+    fn new(accounts: Vec<Account>) -> AccountList {
+        AccountList {
+            accounts,
+            cursor: 0,
+            offset: 0,
+        }
+    }
+
+    /// id of the chosen account, `None` to keep the current one
+    pub fn selected(&self) -> Option<u32> {
+        self.cursor.checked_sub(1).map(|index| self.accounts[index].id)
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) {
+        let last = self.accounts.len();
+        match key.code {
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor = (self.cursor + 1).min(last),
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(10),
+            KeyCode::PageDown => self.cursor = (self.cursor + 10).min(last),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = last,
+            // jump to the next name starting with the letter
+            KeyCode::Char(c) => {
+                let found = (self.cursor..last)
+                    .chain(0..self.cursor)
+                    .find(|&index| self.accounts[index].name.starts_with(c));
+                if let Some(index) = found {
+                    self.cursor = index + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, title: &str, focused: bool, theme: &Theme) {
+        let block = Block::default().borders(Borders::ALL).title(format!(" {title} "));
+        let list = block.inner(area);
+        let height = list.height as usize;
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if height > 0 && self.cursor >= self.offset + height {
+            self.offset = self.cursor + 1 - height;
+        }
+        let width = list.width as usize;
+        let lines: Vec<Line> = std::iter::once(String::from("<unchanged>"))
+            .chain(self.accounts.iter().map(|account| format!("{} ({})", account.name, account.id)))
+            .enumerate()
+            .skip(self.offset)
+            .take(height)
+            .map(|(index, text)| {
+                let text = format!("{text:<width$.width$}");
+                match (index == self.cursor, focused) {
+                    (true, true) => Line::styled(text, theme.selected.style()),
+                    (true, false) => Line::styled(text, theme.marked.style()),
+                    _ => Line::raw(text),
+                }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+/// name of an account, its id when it is unknown
+fn account_name(accounts: &[Account], id: u32) -> String {
+    accounts
+        .iter()
+        .find(|account| account.id == id)
+        .map(|account| account.name.clone())
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// owner and group chooser for the cursor entry or the marked entries
+#[derive(Debug, Clone)]
+pub struct ChownDialog {
+    targets: Vec<FsNodeRef>,
+    pub users: AccountList,
+    pub groups: AccountList,
+    pub recursive: bool,
+    /// field with the focus
+    pub focus: usize,
+}
+
+impl ChownDialog {
+    pub fn new(targets: Vec<FsNodeRef>) -> ChownDialog {
+        ChownDialog {
+            targets,
+            users: AccountList::new(system_users()),
+            groups: AccountList::new(system_groups()),
+            recursive: false,
+            focus: USERS,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> ChownResult {
+        match key.code {
+            KeyCode::Esc => return ChownResult::Cancel,
+            KeyCode::Enter => {
+                let options = ChownOptions {
+                    uid: self.users.selected(),
+                    gid: self.groups.selected(),
+                    recursive: self.recursive,
+                };
+                return ChownResult::Apply(self.targets.clone(), options);
+            }
+            KeyCode::Tab | KeyCode::Right => self.focus = (self.focus + 1) % FIELDS,
+            KeyCode::BackTab | KeyCode::Left => self.focus = (self.focus + FIELDS - 1) % FIELDS,
+            KeyCode::Char(' ') if self.focus == RECURSIVE => self.recursive = !self.recursive,
+            _ => match self.focus {
+                USERS => self.users.handle_key(key),
+                GROUPS => self.groups.handle_key(key),
+                _ => {}
+            },
+        }
+        ChownResult::Pending
+    }
+
+    /// render the dialog centered in `area`
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.saturating_sub(4).min(64);
+        let height = area.height.saturating_sub(2).min(20);
+        let [row] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(row);
+
+        let title = match self.targets.as_slice() {
+            [node] => {
+                let node = node.read();
+                let owner = node.metadata.as_ref().map(|metadata| {
+                    format!("{}:{}", account_name(&self.users.accounts, metadata.uid), account_name(&self.groups.accounts, metadata.gid))
+                });
+                format!(" Owner of {} {} ", node.name, owner.unwrap_or_default())
+            }
+            targets => format!(" Owner of {} entries ", targets.len()),
+        };
+        let block = Block::default().borders(Borders::ALL).title(title).style(theme.dialog.style());
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let [lists, recursive] = Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(inner);
+        let [users, groups] = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(lists);
+        self.users.render(frame, users, "User", self.focus == USERS, theme);
+        self.groups.render(frame, groups, "Group", self.focus == GROUPS, theme);
+        let mark = if self.recursive { 'x' } else { ' ' };
+        let style = if self.focus == RECURSIVE { theme.selected.style() } else { theme.dialog.style() };
+        frame.render_widget(Paragraph::new(format!("[{mark}] Apply inside directories")).style(style), recursive);
+    }
+}
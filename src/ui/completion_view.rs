@@ -0,0 +1,56 @@
+use ratatui::layout::Rect;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::complete::Completer;
+use crate::ui::theme::Theme;
+
+/// candidates shown at once, the popup scrolls to the selected one
+const HEIGHT: usize = 8;
+
+/// render the open popup of a completer next to the input row `anchor`
+///
+/// It goes below the input when it fits in the frame, above otherwise.
+pub fn render(frame: &mut Frame, completer: &Completer, anchor: Rect, theme: &Theme) {
+    if !completer.is_open() {
+        return;
+    }
+    let screen = frame.area();
+    let rows = completer.candidates.len().min(HEIGHT);
+    let widest = completer.candidates.iter().map(|candidate| candidate.label.chars().count()).max().unwrap_or(0);
+    let width = (widest as u16 + 2).clamp(12, screen.width.saturating_sub(anchor.x.saturating_sub(screen.x)).max(12));
+    let height = rows as u16 + 2;
+    let below = anchor.bottom() + height <= screen.bottom();
+    let area = Rect {
+        x: anchor.x.min(screen.right().saturating_sub(width)),
+        y: match below {
+            true => anchor.bottom(),
+            false => anchor.y.saturating_sub(height),
+        },
+        width: width.min(screen.width),
+        height: height.min(screen.height),
+    };
+
+    let selected = completer.selected.unwrap_or(0);
+    let offset = selected.saturating_sub(rows.saturating_sub(1));
+    let inner_width = area.width.saturating_sub(2) as usize;
+    let lines: Vec<Line> = completer
+        .candidates
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(rows)
+        .map(|(index, candidate)| {
+            let text = format!("{:<inner_width$.inner_width$}", candidate.label);
+            match completer.selected == Some(index) {
+                true => Line::styled(text, theme.selected.style()),
+                false => Line::raw(text),
+            }
+        })
+        .collect();
+    let title = format!(" {}/{} ", completer.selected.map_or(0, |index| index + 1), completer.candidates.len());
+    let block = Block::default().borders(Borders::ALL).title(title).style(theme.dialog.style());
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
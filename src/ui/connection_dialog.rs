@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::connections::Connections;
+use crate::keyring;
+use crate::ui::theme::Theme;
+
+/// what a key did to the connections dialog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionResult {
+    Pending,
+    /// open the connection of this index in the active panel
+    Connect(usize),
+    /// edit the connection of this index, or a new one
+    Edit(Option<usize>),
+    Cancel,
+}
+
+/// the saved remote connections, deleting one is saved at once
+#[derive(Debug, Clone)]
+pub struct ConnectionDialog {
+    pub connections: Connections,
+    /// file the connections are saved to, nothing is saved without one
+    path: Option<PathBuf>,
+    cursor: usize,
+    offset: usize,
+    /// last error, shown under the list
+    error: Option<String>,
+}
+
+impl ConnectionDialog {
+    /// open the connections of `path`, a broken file opens none and says why
+    pub fn new(path: Option<PathBuf>) -> ConnectionDialog {
+        let (connections, error) = match path.as_deref().map(Connections::load) {
+            Some(Ok(connections)) => (connections, None),
+            Some(Err(err)) => (Connections::default(), Some(err.to_string())),
+            None => (Connections::default(), Some(String::from("no configuration directory, connections are not saved"))),
+        };
+        ConnectionDialog {
+            connections,
+            // a broken file is not overwritten
+            path: path.filter(|_| error.is_none()),
+            cursor: 0,
+            offset: 0,
+            error,
+        }
+    }
+
+    /// put the cursor on the connection of this name
+    pub fn select(&mut self, name: &str) {
+        if let Some(position) = self.connections.position(name) {
+            self.cursor = position;
+        }
+    }
+
+    /// say why something failed under the list
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> ConnectionResult {
+        self.error = None;
+        let count = self.connections.connections.len();
+        let last = count.saturating_sub(1);
+        match key.code {
+            KeyCode::Esc => return ConnectionResult::Cancel,
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor = (self.cursor + 1).min(last),
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(10),
+            KeyCode::PageDown => self.cursor = (self.cursor + 10).min(last),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = last,
+            KeyCode::Enter if self.cursor < count => return ConnectionResult::Connect(self.cursor),
+            KeyCode::Insert | KeyCode::Char('a') => return ConnectionResult::Edit(None),
+            KeyCode::F(4) | KeyCode::Char('e') if self.cursor < count => return ConnectionResult::Edit(Some(self.cursor)),
+            KeyCode::Delete | KeyCode::Char('d') => self.delete_cursor(),
+            _ => {}
+        }
+        ConnectionResult::Pending
+    }
+
+    /// forget the cursor connection, and its password in the keyring
+    fn delete_cursor(&mut self) {
+        if self.cursor >= self.connections.connections.len() {
+            return;
+        }
+        let Some(path) = &self.path else {
+            self.error = Some(String::from("no configuration directory, connections are not saved"));
+            return;
+        };
+        let removed = self.connections.connections.remove(self.cursor);
+        self.cursor = self.cursor.min(self.connections.connections.len().saturating_sub(1));
+        let saved = self.connections.save(path);
+        let forgotten = match removed.keyring {
+            true => keyring::delete(&removed.name),
+            false => Ok(()),
+        };
+        self.error = saved.and(forgotten).err().map(|err| err.to_string());
+    }
+
+    /// render the dialog centered in `area`
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.saturating_sub(4).min(70);
+        let height = area.height.saturating_sub(2).min(18);
+        let [row] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(row);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Connections ")
+            .style(theme.dialog.style());
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        let [list, status, hint] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1), Constraint::Length(1)]).areas(inner);
+
+        let connections = &self.connections.connections;
+        let height = list.height as usize;
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if height > 0 && self.cursor >= self.offset + height {
+            self.offset = self.cursor + 1 - height;
+        }
+        let width = list.width as usize;
+        let lines: Vec<Line> = if connections.is_empty() {
+            vec![Line::raw("no saved connections, press a to add one")]
+        } else {
+            connections
+                .iter()
+                .enumerate()
+                .skip(self.offset)
+                .take(height)
+                .map(|(index, connection)| {
+                    // the keyring holds a password for the marked ones
+                    let mark = if connection.keyring { '*' } else { ' ' };
+                    let text = format!("{mark} {:<20} {}", connection.name, connection.url);
+                    let text = format!("{text:<width$.width$}");
+                    if index == self.cursor {
+                        Line::styled(text, theme.selected.style())
+                    } else {
+                        Line::raw(text)
+                    }
+                })
+                .collect()
+        };
+        frame.render_widget(Paragraph::new(lines), list);
+
+        if let Some(error) = &self.error {
+            frame.render_widget(Paragraph::new(error.as_str()).style(theme.error.style()), status);
+        }
+        let keys = "Enter connect  a add  e edit  d delete  Esc close  * in keyring";
+        frame.render_widget(Paragraph::new(keys), hint);
+    }
+}
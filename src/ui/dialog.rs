@@ -0,0 +1,636 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Gauge, Paragraph, Wrap};
+use ratatui::Frame;
+
+use crate::commands::PromptKind;
+use crate::complete::{Completer, Completion};
+use crate::ui::completion_view;
+use crate::ui::prompt::LineInput;
+use crate::ui::theme::Theme;
+
+/// a button with this label closes the dialog like Esc
+pub const CANCEL: &str = "Cancel";
+
+/// rows of a list before it scrolls
+const LIST_HEIGHT: usize = 10;
+
+/// what a key did to a dialog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DialogResult {
+    Pending,
+    Submit(DialogAnswer),
+    Cancel,
+}
+
+/// the state of a dialog when a button was pressed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialogAnswer {
+    /// index of the pressed button, Enter outside the buttons presses the first one
+    pub button: usize,
+    /// text of each input, in order
+    pub inputs: Vec<String>,
+    /// entry under the cursor of the first list
+    pub choice: Option<usize>,
+    /// state of each check box, in order
+    pub checks: Vec<bool>,
+}
+
+impl DialogAnswer {
+    /// text of the first input
+    pub fn text(&self) -> &str {
+        self.inputs.first().map(String::as_str).unwrap_or("")
+    }
+}
+
+/// single-line input with the previous answers behind Up and Down
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InputField {
+    pub label: String,
+    pub input: LineInput,
+    /// earlier answers, the oldest first
+    pub history: Vec<String>,
+    /// answer of the history shown, None for the text being typed
+    recalled: Option<usize>,
+    /// text typed before going through the history
+    draft: String,
+    pub completion: Completion,
+    /// popup of the completions, opened by Tab
+    completer: Completer,
+    /// shown as stars, for a password
+    pub masked: bool,
+}
+
+impl InputField {
+    pub fn new(label: &str, text: &str) -> InputField {
+        InputField {
+            label: label.to_string(),
+            input: LineInput::new(text),
+            ..InputField::default()
+        }
+    }
+
+    /// an empty input whose text is not shown
+    pub fn password(label: &str) -> InputField {
+        InputField {
+            masked: true,
+            ..InputField::new(label, "")
+        }
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) {
+        match key.code {
+            KeyCode::Tab => self.completer.step(&self.completion, &self.history, &mut self.input, true),
+            KeyCode::BackTab => self.completer.step(&self.completion, &self.history, &mut self.input, false),
+            KeyCode::Char(' ') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.completer.step(&self.completion, &self.history, &mut self.input, true);
+            }
+            KeyCode::Esc => self.completer.cancel(&mut self.input),
+            KeyCode::Up if self.completer.is_open() => self.completer.step(&self.completion, &self.history, &mut self.input, false),
+            KeyCode::Down if self.completer.is_open() => self.completer.step(&self.completion, &self.history, &mut self.input, true),
+            KeyCode::Up => self.recall(true),
+            KeyCode::Down => self.recall(false),
+            _ => {
+                if self.input.handle_key(key) {
+                    self.recalled = None;
+                    self.completer.close();
+                }
+            }
+        }
+    }
+
+    /// true for the keys the input takes from the dialog: Tab when it completes, Shift+Tab and Esc while its popup is open
+    fn takes(&self, key: &KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Tab => self.completion != Completion::None,
+            KeyCode::BackTab | KeyCode::Esc => self.completer.is_open(),
+            _ => false,
+        }
+    }
+
+    /// step to an older or a newer answer of the history
+    fn recall(&mut self, older: bool) {
+        let next = match (self.recalled, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index + 1 < self.history.len() => Some(index + 1),
+            (_, false) => None,
+        };
+        if self.recalled.is_none() {
+            self.draft = self.input.text.clone();
+        }
+        if next.is_none() && self.recalled.is_none() {
+            return;
+        }
+        self.recalled = next;
+        match next {
+            Some(index) => self.input.set_text(&self.history[index]),
+            None => self.input.set_text(&self.draft),
+        }
+    }
+}
+
+/// entries to pick one of
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ListField {
+    pub items: Vec<String>,
+    pub cursor: usize,
+    offset: usize,
+}
+
+impl ListField {
+    pub fn new(items: Vec<String>) -> ListField {
+        ListField {
+            items,
+            ..ListField::default()
+        }
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) {
+        let last = self.items.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor = (self.cursor + 1).min(last),
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(LIST_HEIGHT),
+            KeyCode::PageDown => self.cursor = (self.cursor + LIST_HEIGHT).min(last),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = last,
+            _ => {}
+        }
+    }
+
+    fn height(&self) -> u16 {
+        self.items.len().clamp(1, LIST_HEIGHT) as u16
+    }
+}
+
+/// an option turned on and off with Space
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CheckField {
+    pub label: String,
+    pub checked: bool,
+}
+
+impl CheckField {
+    pub fn new(label: &str, checked: bool) -> CheckField {
+        CheckField {
+            label: label.to_string(),
+            checked,
+        }
+    }
+}
+
+/// a bar filling up as work gets done
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProgressBar {
+    pub label: String,
+    pub done: u64,
+    pub total: u64,
+}
+
+impl ProgressBar {
+    pub fn new(label: &str) -> ProgressBar {
+        ProgressBar {
+            label: label.to_string(),
+            ..ProgressBar::default()
+        }
+    }
+
+    /// part done between 0 and 1, nothing is done of nothing to do
+    pub fn ratio(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => (self.done as f64 / total as f64).min(1.0),
+        }
+    }
+}
+
+/// a part of a dialog, stacked from top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Widget {
+    /// wrapped message
+    Text(String),
+    Input(InputField),
+    List(ListField),
+    Check(CheckField),
+    Progress(ProgressBar),
+    /// a row of buttons, the first letter of each presses it outside inputs
+    Buttons(Vec<String>),
+}
+
+/// what takes the keys in a dialog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stop {
+    Widget(usize),
+    /// a button by widget and position in its row
+    Button(usize, usize),
+}
+
+/// window over the panels built from widgets, Tab and Shift+Tab move the focus
+///
+/// The answer goes back to the command that opened it through `kind`, like
+/// for a [`Prompt`](crate::ui::prompt::Prompt).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModalDialog {
+    pub kind: PromptKind,
+    pub title: String,
+    pub widgets: Vec<Widget>,
+    /// index of the focused stop
+    focus: usize,
+}
+
+impl ModalDialog {
+    /// a dialog focused on its first input, list or button
+    pub fn new(kind: PromptKind, title: &str, widgets: Vec<Widget>) -> ModalDialog {
+        ModalDialog {
+            kind,
+            title: title.to_string(),
+            widgets,
+            focus: 0,
+        }
+    }
+
+    /// a message with an Ok button
+    pub fn message(kind: PromptKind, title: &str, text: &str) -> ModalDialog {
+        ModalDialog::confirm(kind, title, text, &["Ok"])
+    }
+
+    /// a question answered by one of the buttons, like Yes, No and All
+    pub fn confirm(kind: PromptKind, title: &str, text: &str, buttons: &[&str]) -> ModalDialog {
+        let buttons = buttons.iter().map(|button| button.to_string()).collect();
+        ModalDialog::new(kind, title, vec![Widget::Text(text.to_string()), Widget::Buttons(buttons)])
+    }
+
+    /// a line of text to type, read with [`DialogAnswer::text`]
+    pub fn input(kind: PromptKind, title: &str, label: &str, text: &str) -> ModalDialog {
+        let buttons = vec![String::from("Ok"), String::from(CANCEL)];
+        ModalDialog::new(kind, title, vec![Widget::Input(InputField::new(label, text)), Widget::Buttons(buttons)])
+    }
+
+    /// an entry to pick, read with [`DialogAnswer::choice`]
+    pub fn picker(kind: PromptKind, title: &str, items: Vec<String>) -> ModalDialog {
+        let buttons = vec![String::from("Ok"), String::from(CANCEL)];
+        ModalDialog::new(kind, title, vec![Widget::List(ListField::new(items)), Widget::Buttons(buttons)])
+    }
+
+    /// one bar per label above a row of buttons, updated with [`ModalDialog::set_progress`]
+    pub fn progress(kind: PromptKind, title: &str, bars: &[&str], buttons: &[&str]) -> ModalDialog {
+        let mut widgets: Vec<Widget> = bars.iter().map(|label| Widget::Progress(ProgressBar::new(label))).collect();
+        widgets.push(Widget::Buttons(buttons.iter().map(|button| button.to_string()).collect()));
+        ModalDialog::new(kind, title, widgets)
+    }
+
+    /// offer earlier answers in the first input
+    pub fn with_history(mut self, history: Vec<String>) -> ModalDialog {
+        if let Some(field) = self.first_input() {
+            field.history = history;
+        }
+        self
+    }
+
+    /// let Tab finish the text of the first input
+    pub fn with_completion(mut self, completion: Completion) -> ModalDialog {
+        if let Some(field) = self.first_input() {
+            field.completion = completion;
+        }
+        self
+    }
+
+    /// put the cursor of the first list on an entry
+    pub fn with_choice(mut self, choice: usize) -> ModalDialog {
+        let list = self.widgets.iter_mut().find_map(|widget| match widget {
+            Widget::List(list) => Some(list),
+            _ => None,
+        });
+        if let Some(list) = list {
+            list.cursor = choice.min(list.items.len().saturating_sub(1));
+        }
+        self
+    }
+
+    fn first_input(&mut self) -> Option<&mut InputField> {
+        self.widgets.iter_mut().find_map(|widget| match widget {
+            Widget::Input(field) => Some(field),
+            _ => None,
+        })
+    }
+
+    /// set a bar of the dialog, by its position among the bars
+    pub fn set_progress(&mut self, bar: usize, label: &str, done: u64, total: u64) {
+        let mut bars = self.widgets.iter_mut().filter_map(|widget| match widget {
+            Widget::Progress(progress) => Some(progress),
+            _ => None,
+        });
+        if let Some(progress) = bars.nth(bar) {
+            progress.label = label.to_string();
+            progress.done = done;
+            progress.total = total;
+        }
+    }
+
+    fn stops(&self) -> Vec<Stop> {
+        let mut stops = Vec::new();
+        for (index, widget) in self.widgets.iter().enumerate() {
+            match widget {
+                Widget::Input(_) | Widget::List(_) | Widget::Check(_) => stops.push(Stop::Widget(index)),
+                Widget::Buttons(buttons) => stops.extend((0..buttons.len()).map(|button| Stop::Button(index, button))),
+                Widget::Text(_) | Widget::Progress(_) => {}
+            }
+        }
+        stops
+    }
+
+    fn focused(&self) -> Option<Stop> {
+        self.stops().get(self.focus).copied()
+    }
+
+    /// put pasted text in the input with the focus, if an input has it
+    pub fn paste(&mut self, text: &str) {
+        if let Some(Stop::Widget(index)) = self.focused()
+            && let Widget::Input(field) = &mut self.widgets[index]
+        {
+            field.completer.close();
+            field.input.insert(text);
+        }
+    }
+
+    fn move_focus(&mut self, forward: bool) {
+        let count = self.stops().len();
+        if count > 0 {
+            self.focus = match forward {
+                true => (self.focus + 1) % count,
+                false => (self.focus + count - 1) % count,
+            };
+        }
+    }
+
+    /// press a button, counting every button of the dialog in order
+    fn press(&self, button: usize) -> DialogResult {
+        let label = self
+            .widgets
+            .iter()
+            .filter_map(|widget| match widget {
+                Widget::Buttons(buttons) => Some(buttons),
+                _ => None,
+            })
+            .flatten()
+            .nth(button);
+        if label.is_some_and(|label| label == CANCEL) {
+            return DialogResult::Cancel;
+        }
+        let inputs = self
+            .widgets
+            .iter()
+            .filter_map(|widget| match widget {
+                Widget::Input(field) => Some(field.input.text.clone()),
+                _ => None,
+            })
+            .collect();
+        let choice = self.widgets.iter().find_map(|widget| match widget {
+            Widget::List(list) if !list.items.is_empty() => Some(list.cursor),
+            _ => None,
+        });
+        let checks = self
+            .widgets
+            .iter()
+            .filter_map(|widget| match widget {
+                Widget::Check(check) => Some(check.checked),
+                _ => None,
+            })
+            .collect();
+        DialogResult::Submit(DialogAnswer { button, inputs, choice, checks })
+    }
+
+    /// index of the focused button among every button of the dialog
+    fn button_number(&self, widget: usize, button: usize) -> usize {
+        let before: usize = self.widgets[..widget]
+            .iter()
+            .map(|widget| match widget {
+                Widget::Buttons(buttons) => buttons.len(),
+                _ => 0,
+            })
+            .sum();
+        before + button
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> DialogResult {
+        let focused = self.focused();
+        if let Some(Stop::Widget(index)) = focused
+            && let Widget::Input(field) = &mut self.widgets[index]
+            && field.takes(key)
+        {
+            field.handle_key(key);
+            return DialogResult::Pending;
+        }
+        match key.code {
+            KeyCode::Esc => return DialogResult::Cancel,
+            KeyCode::BackTab => self.move_focus(false),
+            KeyCode::Tab => self.move_focus(true),
+            KeyCode::Enter => {
+                return match focused {
+                    Some(Stop::Button(widget, button)) => self.press(self.button_number(widget, button)),
+                    _ => self.press(0),
+                };
+            }
+            _ => match focused {
+                Some(Stop::Widget(index)) => match &mut self.widgets[index] {
+                    Widget::Input(field) => field.handle_key(key),
+                    Widget::List(list) => match key.code {
+                        KeyCode::Char(c) => return self.press_hotkey(c),
+                        _ => list.handle_key(key),
+                    },
+                    Widget::Check(check) => match key.code {
+                        KeyCode::Char(' ') => check.checked = !check.checked,
+                        KeyCode::Char(c) => return self.press_hotkey(c),
+                        KeyCode::Up => self.move_focus(false),
+                        KeyCode::Down => self.move_focus(true),
+                        _ => {}
+                    },
+                    _ => {}
+                },
+                Some(Stop::Button(..)) => match key.code {
+                    KeyCode::Left | KeyCode::Up => self.move_focus(false),
+                    KeyCode::Right | KeyCode::Down => self.move_focus(true),
+                    KeyCode::Char(c) => return self.press_hotkey(c),
+                    _ => {}
+                },
+                None => {
+                    if let KeyCode::Char(c) = key.code {
+                        return self.press_hotkey(c);
+                    }
+                }
+            },
+        }
+        DialogResult::Pending
+    }
+
+    /// press the first button whose label starts with the letter
+    fn press_hotkey(&self, letter: char) -> DialogResult {
+        let letter = letter.to_ascii_lowercase();
+        let button = self
+            .widgets
+            .iter()
+            .filter_map(|widget| match widget {
+                Widget::Buttons(buttons) => Some(buttons),
+                _ => None,
+            })
+            .flatten()
+            .position(|label| label.chars().next().is_some_and(|first| first.to_ascii_lowercase() == letter));
+        match button {
+            Some(button) => self.press(button),
+            None => DialogResult::Pending,
+        }
+    }
+
+    fn widget_height(widget: &Widget, width: u16) -> u16 {
+        match widget {
+            Widget::Text(text) => text
+                .lines()
+                .map(|line| (line.chars().count() as u16).div_ceil(width.max(1)).max(1))
+                .sum::<u16>()
+                .max(1),
+            // the label and the text, the popup of the completions goes over what follows
+            Widget::Input(_) => 2,
+            Widget::List(list) => list.height(),
+            Widget::Check(_) => 1,
+            Widget::Progress(_) => 2,
+            Widget::Buttons(_) => 1,
+        }
+    }
+
+    /// render the dialog centered in `area`
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.saturating_sub(4).min((self.title.chars().count() as u16 + 6).max(60));
+        let inner_width = width.saturating_sub(4);
+        let heights: Vec<u16> = self.widgets.iter().map(|widget| ModalDialog::widget_height(widget, inner_width)).collect();
+        // a blank row between widgets, check boxes stay together
+        let gaps: Vec<u16> = (0..self.widgets.len())
+            .map(|index| match index {
+                0 => 0,
+                _ if matches!((&self.widgets[index - 1], &self.widgets[index]), (Widget::Check(_), Widget::Check(_))) => 0,
+                _ => 1,
+            })
+            .collect();
+        let height = heights.iter().sum::<u16>() + gaps.iter().sum::<u16>() + 2;
+        let [row] = Layout::vertical([Constraint::Length(height.min(area.height))]).flex(Flex::Center).areas(area);
+        let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(row);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", self.title))
+            .style(theme.dialog.style());
+        let inner = block.inner(area);
+        let inner = Rect {
+            x: inner.x + 1,
+            width: inner.width.saturating_sub(2),
+            ..inner
+        };
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let constraints = heights
+            .iter()
+            .zip(&gaps)
+            .flat_map(|(height, gap)| [Constraint::Length(*gap), Constraint::Length(*height)]);
+        let rows = Layout::vertical(constraints).split(inner);
+        let focused = self.focused();
+        let mut popup = None;
+        for (index, widget) in self.widgets.iter_mut().enumerate() {
+            let area = rows[index * 2 + 1];
+            let has_focus = focused == Some(Stop::Widget(index));
+            match widget {
+                Widget::Text(text) => {
+                    frame.render_widget(Paragraph::new(text.as_str()).wrap(Wrap { trim: false }), area);
+                }
+                Widget::Input(field) => {
+                    render_input(frame, area, field, has_focus, theme);
+                    if has_focus {
+                        popup = Some((field.completer.clone(), Rect { y: area.y + 1, height: 1, ..area }));
+                    }
+                }
+                Widget::List(list) => render_list(frame, area, list, has_focus, theme),
+                Widget::Check(check) => {
+                    let mark = match check.checked {
+                        true => 'x',
+                        false => ' ',
+                    };
+                    let text = format!("[{mark}] {}", check.label);
+                    let line = match has_focus {
+                        true => Line::styled(text, theme.selected.style()),
+                        false => Line::raw(text),
+                    };
+                    frame.render_widget(Paragraph::new(line), area);
+                }
+                Widget::Progress(progress) => {
+                    let [label, bar] = Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(area);
+                    frame.render_widget(Paragraph::new(progress.label.as_str()), label);
+                    let gauge = Gauge::default().gauge_style(theme.selected.style()).ratio(progress.ratio());
+                    frame.render_widget(gauge, bar);
+                }
+                Widget::Buttons(buttons) => {
+                    let mut spans = Vec::new();
+                    for (button, label) in buttons.iter().enumerate() {
+                        if button > 0 {
+                            spans.push(Span::raw(" "));
+                        }
+                        let text = format!("[ {label} ]");
+                        spans.push(match focused == Some(Stop::Button(index, button)) {
+                            true => Span::styled(text, theme.selected.style()),
+                            false => Span::raw(text),
+                        });
+                    }
+                    frame.render_widget(Paragraph::new(Line::from(spans)).centered(), area);
+                }
+            }
+        }
+        // over the widgets below the input
+        if let Some((completer, anchor)) = popup {
+            completion_view::render(frame, &completer, anchor, theme);
+        }
+    }
+}
+
+fn render_input(frame: &mut Frame, area: Rect, field: &InputField, focused: bool, theme: &Theme) {
+    let rows = Layout::vertical([Constraint::Length(1), Constraint::Length(1), Constraint::Min(0)]).split(area);
+    frame.render_widget(Paragraph::new(field.label.as_str()), rows[0]);
+    let (text, cursor) = field.input.visible(rows[1].width as usize);
+    let text = match field.masked {
+        true => "*".repeat(text.chars().count()),
+        false => text,
+    };
+    let style = match focused {
+        true => theme.selected.style(),
+        false => theme.panel.style(),
+    };
+    frame.render_widget(Paragraph::new(format!("{text:<width$}", width = rows[1].width as usize)).style(style), rows[1]);
+    if focused {
+        frame.set_cursor_position((rows[1].x + cursor as u16, rows[1].y));
+    }
+}
+
+fn render_list(frame: &mut Frame, area: Rect, list: &mut ListField, focused: bool, theme: &Theme) {
+    let height = area.height as usize;
+    if list.cursor < list.offset {
+        list.offset = list.cursor;
+    } else if height > 0 && list.cursor >= list.offset + height {
+        list.offset = list.cursor + 1 - height;
+    }
+    let width = area.width as usize;
+    let lines: Vec<Line> = list
+        .items
+        .iter()
+        .enumerate()
+        .skip(list.offset)
+        .take(height)
+        .map(|(index, item)| {
+            let text = format!("{item:<width$.width$}");
+            match index == list.cursor {
+                true if focused => Line::styled(text, theme.selected.style()),
+                true => Line::styled(text, theme.marked.style()),
+                false => Line::raw(text),
+            }
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines), area);
+}
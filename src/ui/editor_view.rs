@@ -0,0 +1,128 @@
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use crate::app::{Screen, ScreenAction};
+use crate::commands::{CommandOutcome, PromptKind};
+use crate::editor::{expand_tabs, Editor, EditorAction, TAB_WIDTH};
+use crate::event::AppEvent;
+use crate::highlight::LineStyles;
+use crate::panel::PanelManager;
+use crate::ui::theme::Theme;
+use crate::ui::viewer_view::syntax_style;
+
+/// render the editor over the whole `area`, with the cursor placed in the text
+pub fn render(frame: &mut Frame, area: Rect, editor: &mut Editor, theme: &Theme) {
+    let [status, body, keys] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)]).areas(area);
+    editor.height = body.height as usize;
+    editor.width = body.width as usize;
+    editor.scroll_to_cursor();
+
+    let syntax = match editor.highlighter.as_mut() {
+        Some(highlighter) => highlighter.highlight(&editor.lines, editor.top, editor.height),
+        None => Vec::new(),
+    };
+    let lines: Vec<Line> = editor
+        .lines
+        .iter()
+        .skip(editor.top)
+        .take(editor.height)
+        .enumerate()
+        .map(|(index, line)| match syntax.get(index) {
+            Some(styles) => styled_line(line, styles, editor.left, theme),
+            None => Line::from(expand_tabs(line).chars().skip(editor.left).collect::<String>()),
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).style(theme.panel.style()), body);
+
+    let position = format!("{}:{}", editor.cursor.row + 1, editor.cursor.column + 1);
+    let dirty = if editor.dirty { "*" } else { " " };
+    let width = (status.width as usize).saturating_sub(position.len() + 3);
+    let title = format!("{dirty}{:<width$.width$} {position}", editor.title);
+    frame.render_widget(Paragraph::new(title).style(theme.header.style()), status);
+
+    let hints = [("2", "Save"), ("4", "Replace"), ("7", "Search"), ("10", "Quit")];
+    let spans: Vec<Span> = hints
+        .iter()
+        .flat_map(|(key, label)| {
+            [
+                Span::styled(*key, theme.key_number.style()),
+                Span::styled(format!("{label:<8}"), theme.key_label.style()),
+            ]
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(Line::from(spans)), keys);
+
+    let x = editor.cursor_column().saturating_sub(editor.left) as u16;
+    let y = (editor.cursor.row - editor.top) as u16;
+    frame.set_cursor_position((body.x + x, body.y + y));
+}
+
+/// a line with tabs expanded and syntax colors, scrolled by `left` columns
+fn styled_line<'a>(line: &str, styles: &LineStyles, left: usize, theme: &Theme) -> Line<'a> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut text = String::new();
+    let mut current = None;
+    let mut column = 0;
+    for (position, c) in line.char_indices() {
+        let style = syntax_style(Some(styles), position).map_or(theme.panel.style(), |style| theme.panel.style().patch(style));
+        let cells = if c == '\t' { TAB_WIDTH - column % TAB_WIDTH } else { 1 };
+        for _ in 0..cells {
+            column += 1;
+            if column <= left {
+                continue;
+            }
+            if current != Some(style) && !text.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut text), current.unwrap_or_default()));
+            }
+            current = Some(style);
+            text.push(if c == '\t' { ' ' } else { c });
+        }
+    }
+    if !text.is_empty() {
+        spans.push(Span::styled(text, current.unwrap_or_default()));
+    }
+    Line::from(spans)
+}
+
+impl Screen for Editor {
+    fn handle_event(&mut self, event: &AppEvent, _manager: &mut PanelManager) -> ScreenAction {
+        let AppEvent::Key(key) = event else {
+            return ScreenAction::Ignored;
+        };
+        match self.handle_key(key) {
+            EditorAction::Close => ScreenAction::Close,
+            action => ScreenAction::Stay(outcome(action)),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        render(frame, area, self, theme);
+    }
+
+    fn help_topic(&self) -> &'static str {
+        "editor"
+    }
+
+    fn submit(&mut self, kind: PromptKind, input: &str) -> Option<CommandOutcome> {
+        let PromptKind::Editor(asked) = kind else {
+            return None;
+        };
+        Some(outcome(Editor::submit(self, asked, input)))
+    }
+
+    /// the file may have been saved under the panels
+    fn closed(&mut self, manager: &mut PanelManager) {
+        let _ = manager.active_mut().reload();
+    }
+}
+
+fn outcome(action: EditorAction) -> CommandOutcome {
+    match action {
+        EditorAction::None | EditorAction::Close => CommandOutcome::Done,
+        EditorAction::Prompt(prompt) => CommandOutcome::Prompt(prompt),
+        EditorAction::Message(text) => CommandOutcome::Message(text),
+    }
+}
@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::complete::{Completer, Completion};
+use crate::search::{content_pattern, NamePattern, SearchQuery};
+use crate::ui::completion_view;
+use crate::ui::prompt::LineInput;
+use crate::ui::theme::Theme;
+
+/// what a key did to the find dialog
+#[derive(Debug, Clone)]
+pub enum FindResult {
+    Pending,
+    Submit(SearchQuery),
+    Cancel,
+}
+
+const START: usize = 0;
+const NAME: usize = 1;
+const CONTENT: usize = 2;
+const REGEX: usize = 3;
+const CONTENT_REGEX: usize = 4;
+const WHOLE_WORD: usize = 5;
+const CASE_SENSITIVE: usize = 6;
+const GITIGNORE: usize = 7;
+const FIELDS: usize = 8;
+
+/// form asking where to search and for what
+#[derive(Debug, Clone)]
+pub struct FindDialog {
+    /// directory relative start paths are resolved against
+    base: PathBuf,
+    pub start: LineInput,
+    /// popup of the directories the start can be finished with
+    completer: Completer,
+    pub name: LineInput,
+    pub content: LineInput,
+    /// the file name is a regular expression instead of a glob
+    pub regex: bool,
+    /// the content is a regular expression instead of plain text
+    pub content_regex: bool,
+    pub whole_word: bool,
+    pub case_sensitive: bool,
+    /// skip what the `.gitignore` files ignore
+    pub gitignore: bool,
+    /// field with the focus
+    pub focus: usize,
+    /// why the last submit was refused
+    pub error: Option<String>,
+}
+
+impl FindDialog {
+    pub fn new(base: &Path, case_sensitive: bool, gitignore: bool) -> FindDialog {
+        FindDialog {
+            base: base.to_path_buf(),
+            start: LineInput::new(&base.display().to_string()),
+            completer: Completer::default(),
+            name: LineInput::new("*"),
+            content: LineInput::default(),
+            regex: false,
+            content_regex: false,
+            whole_word: false,
+            case_sensitive,
+            gitignore,
+            focus: NAME,
+            error: None,
+        }
+    }
+
+    /// Tab moves between the fields, Alt+Tab or Ctrl+Space complete the start
+    /// directory and Tab then goes through the candidates.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> FindResult {
+        let completing = self.focus == START && self.completer.is_open();
+        let complete = matches!(key.code, KeyCode::Tab if key.modifiers.contains(KeyModifiers::ALT))
+            || matches!(key.code, KeyCode::Char(' ') if key.modifiers.contains(KeyModifiers::CONTROL));
+        if self.focus == START && (complete || completing) {
+            let completion = Completion::Directories {
+                base: self.base.clone(),
+                cdpath: Vec::new(),
+            };
+            match key.code {
+                _ if complete => {
+                    self.completer.step(&completion, &[], &mut self.start, true);
+                    return FindResult::Pending;
+                }
+                KeyCode::Tab | KeyCode::Down => {
+                    self.completer.step(&completion, &[], &mut self.start, true);
+                    return FindResult::Pending;
+                }
+                KeyCode::BackTab | KeyCode::Up => {
+                    self.completer.step(&completion, &[], &mut self.start, false);
+                    return FindResult::Pending;
+                }
+                KeyCode::Esc => {
+                    self.completer.cancel(&mut self.start);
+                    return FindResult::Pending;
+                }
+                _ => self.completer.close(),
+            }
+        }
+        match key.code {
+            KeyCode::Esc => return FindResult::Cancel,
+            KeyCode::Enter => match self.query() {
+                Ok(query) => return FindResult::Submit(query),
+                Err(err) => self.error = Some(err),
+            },
+            KeyCode::Tab | KeyCode::Down => self.focus = (self.focus + 1) % FIELDS,
+            KeyCode::BackTab | KeyCode::Up => self.focus = (self.focus + FIELDS - 1) % FIELDS,
+            KeyCode::Char(' ') if self.focus >= REGEX => {
+                if let Some(toggle) = self.toggle_mut() {
+                    *toggle = !*toggle;
+                }
+            }
+            _ => {
+                if let Some(input) = self.input_mut() {
+                    input.handle_key(key);
+                }
+            }
+        }
+        FindResult::Pending
+    }
+
+    /// put pasted text in the field with the focus, if it is a text field
+    pub fn paste(&mut self, text: &str) {
+        self.completer.close();
+        if let Some(input) = self.input_mut() {
+            input.insert(text);
+        }
+    }
+
+    fn input_mut(&mut self) -> Option<&mut LineInput> {
+        match self.focus {
+            START => Some(&mut self.start),
+            NAME => Some(&mut self.name),
+            CONTENT => Some(&mut self.content),
+            _ => None,
+        }
+    }
+
+    fn toggle_mut(&mut self) -> Option<&mut bool> {
+        match self.focus {
+            REGEX => Some(&mut self.regex),
+            CONTENT_REGEX => Some(&mut self.content_regex),
+            WHOLE_WORD => Some(&mut self.whole_word),
+            CASE_SENSITIVE => Some(&mut self.case_sensitive),
+            GITIGNORE => Some(&mut self.gitignore),
+            _ => None,
+        }
+    }
+
+    /// the search described by the form
+    pub fn query(&self) -> Result<SearchQuery, String> {
+        let start = self.base.join(self.start.text.trim());
+        let name = NamePattern::parse(self.name.text.trim(), self.regex, self.case_sensitive)?;
+        let content = match self.content.text.as_str() {
+            "" => None,
+            text => Some(content_pattern(text, self.content_regex, self.case_sensitive, self.whole_word)?),
+        };
+        Ok(SearchQuery {
+            start,
+            name,
+            content,
+            case_sensitive: self.case_sensitive,
+            gitignore: self.gitignore,
+        })
+    }
+
+    /// render the dialog centered in `area`
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.saturating_sub(4).min(64);
+        let [row] = Layout::vertical([Constraint::Length(13)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(row);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Find file ")
+            .style(theme.dialog.style());
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        const LABEL: u16 = 12;
+        let field_width = inner.width.saturating_sub(LABEL) as usize;
+        let mut popup = None;
+        let inputs = [("Start at:", &self.start), ("File name:", &self.name), ("Content:", &self.content)];
+        for (index, (label, input)) in inputs.into_iter().enumerate() {
+            let line = Rect {
+                y: inner.y + index as u16,
+                height: 1,
+                ..inner
+            };
+            let (text, cursor) = input.visible(field_width);
+            if index == START && self.focus == START {
+                let anchor = Rect {
+                    x: line.x + LABEL,
+                    ..line
+                };
+                popup = Some(anchor);
+            }
+            let text = Line::from(vec![
+                Span::raw(format!("{label:<width$}", width = LABEL as usize)),
+                Span::styled(text, theme.panel.style()),
+            ]);
+            frame.render_widget(Paragraph::new(text), line);
+            if self.focus == index {
+                frame.set_cursor_position((line.x + LABEL + cursor as u16, line.y));
+            }
+        }
+
+        let toggles = [
+            ("File name is a regular expression", self.regex),
+            ("Content is a regular expression", self.content_regex),
+            ("Whole words", self.whole_word),
+            ("Case sensitive", self.case_sensitive),
+            ("Skip what .gitignore ignores", self.gitignore),
+        ];
+        for (index, (label, checked)) in toggles.into_iter().enumerate() {
+            let focus = REGEX + index;
+            let line = Rect {
+                y: inner.y + focus as u16 + 1,
+                height: 1,
+                ..inner
+            };
+            let mark = if checked { 'x' } else { ' ' };
+            let style = if self.focus == focus { theme.selected.style() } else { theme.dialog.style() };
+            frame.render_widget(Paragraph::new(format!("[{mark}] {label}")).style(style), line);
+        }
+
+        if let Some(error) = &self.error {
+            let line = Rect {
+                y: inner.bottom().saturating_sub(1),
+                height: 1,
+                ..inner
+            };
+            frame.render_widget(Paragraph::new(error.as_str()).style(theme.error.style()), line);
+        }
+        if let Some(anchor) = popup {
+            completion_view::render(frame, &self.completer, anchor, theme);
+        }
+    }
+}
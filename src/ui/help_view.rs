@@ -0,0 +1,60 @@
+use ratatui::layout::Rect;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use crate::app::{Screen, ScreenAction};
+use crate::commands::{CommandOutcome, PromptKind};
+use crate::event::AppEvent;
+use crate::help::Help;
+use crate::panel::PanelManager;
+use crate::ui::theme::Theme;
+use crate::ui::viewer_view;
+use crate::viewer::ViewerAction;
+
+/// render the help in the viewer, with the keys of the help in place of those of the viewer
+pub fn render(frame: &mut Frame, area: Rect, help: &mut Help, theme: &Theme) {
+    viewer_view::render(frame, area, &mut help.viewer, theme);
+    if area.height == 0 {
+        return;
+    }
+    let keys = Rect::new(area.x, area.bottom() - 1, area.width, 1);
+    let hints = [("Tab", "Link"), ("Enter", "Go"), ("Bksp", "Back"), ("1", "Help"), ("7", "Search"), ("10", "Quit")];
+    let spans: Vec<Span> = hints
+        .iter()
+        .flat_map(|(key, label)| {
+            [
+                Span::styled(*key, theme.key_number.style()),
+                Span::styled(format!("{label:<8}"), theme.key_label.style()),
+            ]
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(Line::from(spans)), keys);
+}
+
+impl Screen for Help {
+    fn handle_event(&mut self, event: &AppEvent, _manager: &mut PanelManager) -> ScreenAction {
+        let AppEvent::Key(key) = event else {
+            return ScreenAction::Ignored;
+        };
+        match self.handle_key(key) {
+            ViewerAction::None => ScreenAction::Stay(CommandOutcome::Done),
+            ViewerAction::Close => ScreenAction::Close,
+            ViewerAction::Prompt(prompt) => ScreenAction::Stay(CommandOutcome::Prompt(prompt)),
+            ViewerAction::Message(text) => ScreenAction::Stay(CommandOutcome::Message(text)),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        render(frame, area, self, theme);
+    }
+
+    /// the searches of the viewer are asked for by the help too
+    fn submit(&mut self, kind: PromptKind, input: &str) -> Option<CommandOutcome> {
+        Screen::submit(&mut self.viewer, kind, input)
+    }
+
+    fn help_topic(&self) -> &'static str {
+        "help"
+    }
+}
@@ -0,0 +1,94 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::history::DirHistory;
+use crate::ui::theme::Theme;
+
+/// what a key did to the history list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryResult {
+    Pending,
+    /// show the visit of this index in the active panel
+    Jump(usize),
+    Cancel,
+}
+
+/// directories visited by the active panel, the oldest first
+#[derive(Debug, Clone)]
+pub struct HistoryDialog {
+    pub locations: Vec<String>,
+    /// visit the panel is at
+    current: usize,
+    pub cursor: usize,
+    offset: usize,
+}
+
+impl HistoryDialog {
+    pub fn new(history: &DirHistory) -> HistoryDialog {
+        HistoryDialog {
+            locations: history.locations(),
+            current: history.position(),
+            cursor: history.position(),
+            offset: 0,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> HistoryResult {
+        let last = self.locations.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc => return HistoryResult::Cancel,
+            KeyCode::Enter => return HistoryResult::Jump(self.cursor),
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor = (self.cursor + 1).min(last),
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(10),
+            KeyCode::PageDown => self.cursor = (self.cursor + 10).min(last),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = last,
+            _ => {}
+        }
+        HistoryResult::Pending
+    }
+
+    /// render the list centered in `area`
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.saturating_sub(4).min(70);
+        let height = area.height.saturating_sub(2).min(self.locations.len() as u16 + 2).max(3);
+        let [row] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(row);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" History ")
+            .style(theme.dialog.style());
+        let list = block.inner(area);
+        let height = list.height as usize;
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if height > 0 && self.cursor >= self.offset + height {
+            self.offset = self.cursor + 1 - height;
+        }
+        let width = list.width as usize;
+        let lines: Vec<Line> = self
+            .locations
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(height)
+            .map(|(index, location)| {
+                // the current directory is marked like in a shell's dirs
+                let mark = if index == self.current { '*' } else { ' ' };
+                let text = format!("{mark} {location:<width$.width$}", width = width.saturating_sub(2));
+                if index == self.cursor {
+                    Line::styled(text, theme.selected.style())
+                } else {
+                    Line::raw(text)
+                }
+            })
+            .collect();
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
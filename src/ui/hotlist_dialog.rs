@@ -0,0 +1,321 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::hotlist::{bookmark_name, Bookmark, BookmarkGroup, Hotlist, DEFAULT_GROUP};
+use crate::ui::prompt::LineInput;
+use crate::ui::theme::Theme;
+
+/// what a key did to the hotlist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotlistResult {
+    Pending,
+    /// show this location in the active panel
+    Jump(String),
+    Cancel,
+}
+
+/// a line of the list, by index in the hotlist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Group(usize),
+    Bookmark(usize, usize),
+}
+
+/// name being typed for a new group or a rename
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    NewGroup,
+    Rename(Row),
+}
+
+/// the directory hotlist, every change is saved at once
+#[derive(Debug, Clone)]
+pub struct HotlistDialog {
+    pub hotlist: Hotlist,
+    /// file the hotlist is saved to, nothing is saved without one
+    path: Option<PathBuf>,
+    /// location of the active panel, added by `a`
+    current: String,
+    /// names of the groups whose bookmarks are hidden
+    collapsed: HashSet<String>,
+    cursor: usize,
+    offset: usize,
+    edit: Option<(Edit, LineInput)>,
+    /// last error, shown under the list
+    error: Option<String>,
+}
+
+impl HotlistDialog {
+    /// open the hotlist of `path`, a broken file opens an empty one and says why
+    pub fn new(path: Option<PathBuf>, current: &str) -> HotlistDialog {
+        let (hotlist, error) = match path.as_deref().map(Hotlist::load) {
+            Some(Ok(hotlist)) => (hotlist, None),
+            Some(Err(err)) => (Hotlist::default(), Some(err.to_string())),
+            None => (Hotlist::default(), Some(String::from("no configuration directory, changes are not saved"))),
+        };
+        HotlistDialog {
+            hotlist,
+            // a broken file is not overwritten
+            path: path.filter(|_| error.is_none()),
+            current: current.to_string(),
+            collapsed: HashSet::new(),
+            cursor: 0,
+            offset: 0,
+            edit: None,
+            error,
+        }
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (group_index, group) in self.hotlist.groups.iter().enumerate() {
+            rows.push(Row::Group(group_index));
+            if !self.collapsed.contains(&group.name) {
+                rows.extend((0..group.bookmarks.len()).map(|index| Row::Bookmark(group_index, index)));
+            }
+        }
+        rows
+    }
+
+    fn cursor_row(&self) -> Option<Row> {
+        self.rows().get(self.cursor).copied()
+    }
+
+    /// group of the cursor row
+    fn cursor_group(&self) -> Option<usize> {
+        match self.cursor_row()? {
+            Row::Group(group) | Row::Bookmark(group, _) => Some(group),
+        }
+    }
+
+    fn move_cursor_to(&mut self, row: Row) {
+        if let Some(position) = self.rows().iter().position(|&other| other == row) {
+            self.cursor = position;
+        }
+    }
+
+    fn save(&mut self) {
+        if let Some(path) = &self.path {
+            self.error = self.hotlist.save(path).err().map(|err| err.to_string());
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> HotlistResult {
+        if let Some((edit, input)) = self.edit.as_mut() {
+            match key.code {
+                KeyCode::Esc => self.edit = None,
+                KeyCode::Enter => {
+                    let (edit, name) = (*edit, input.text.trim().to_string());
+                    self.edit = None;
+                    if !name.is_empty() {
+                        self.apply_edit(edit, name);
+                    }
+                }
+                _ => {
+                    input.handle_key(key);
+                }
+            }
+            return HotlistResult::Pending;
+        }
+
+        self.error = None;
+        let last = self.rows().len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc => return HotlistResult::Cancel,
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor = (self.cursor + 1).min(last),
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(10),
+            KeyCode::PageDown => self.cursor = (self.cursor + 10).min(last),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = last,
+            KeyCode::Enter => match self.cursor_row() {
+                Some(Row::Bookmark(group, index)) => {
+                    return HotlistResult::Jump(self.hotlist.groups[group].bookmarks[index].location.clone());
+                }
+                Some(Row::Group(group)) => {
+                    let name = self.hotlist.groups[group].name.clone();
+                    if !self.collapsed.remove(&name) {
+                        self.collapsed.insert(name);
+                    }
+                }
+                None => {}
+            },
+            KeyCode::Insert | KeyCode::Char('a') => self.add_current(),
+            KeyCode::Char('g') => self.edit = Some((Edit::NewGroup, LineInput::default())),
+            KeyCode::Char('r') => {
+                if let Some(row) = self.cursor_row() {
+                    let name = match row {
+                        Row::Group(group) => &self.hotlist.groups[group].name,
+                        Row::Bookmark(group, index) => &self.hotlist.groups[group].bookmarks[index].name,
+                    };
+                    self.edit = Some((Edit::Rename(row), LineInput::new(name)));
+                }
+            }
+            KeyCode::Delete | KeyCode::Char('d') => self.delete_cursor(),
+            KeyCode::Char('m') => self.move_to_next_group(),
+            _ => {}
+        }
+        HotlistResult::Pending
+    }
+
+    /// bookmark the directory of the active panel in the group of the cursor
+    fn add_current(&mut self) {
+        if self.hotlist.contains(&self.current) {
+            self.error = Some(format!("{} is already bookmarked", self.current));
+            return;
+        }
+        let group = match self.cursor_group() {
+            Some(group) => self.hotlist.groups[group].name.clone(),
+            None => String::from(DEFAULT_GROUP),
+        };
+        let bookmark = Bookmark {
+            name: bookmark_name(&self.current),
+            location: self.current.clone(),
+        };
+        self.hotlist.add(&group, bookmark);
+        self.collapsed.remove(&group);
+        let group = self.hotlist.groups.iter().position(|existing| existing.name == group).unwrap_or(0);
+        self.move_cursor_to(Row::Bookmark(group, self.hotlist.groups[group].bookmarks.len() - 1));
+        self.save();
+    }
+
+    fn apply_edit(&mut self, edit: Edit, name: String) {
+        let taken = self.hotlist.groups.iter().any(|group| group.name == name);
+        match edit {
+            Edit::NewGroup | Edit::Rename(Row::Group(_)) if taken => {
+                self.error = Some(format!("there is already a group {name}"));
+                return;
+            }
+            Edit::NewGroup => {
+                self.hotlist.groups.push(BookmarkGroup::new(&name));
+                self.move_cursor_to(Row::Group(self.hotlist.groups.len() - 1));
+            }
+            Edit::Rename(Row::Group(group)) => {
+                let old = std::mem::replace(&mut self.hotlist.groups[group].name, name.clone());
+                if self.collapsed.remove(&old) {
+                    self.collapsed.insert(name);
+                }
+            }
+            Edit::Rename(Row::Bookmark(group, index)) => self.hotlist.groups[group].bookmarks[index].name = name,
+        }
+        self.save();
+    }
+
+    /// remove the cursor bookmark, or its group once it is empty
+    fn delete_cursor(&mut self) {
+        match self.cursor_row() {
+            Some(Row::Bookmark(group, index)) => {
+                self.hotlist.groups[group].bookmarks.remove(index);
+            }
+            Some(Row::Group(group)) if self.hotlist.groups[group].bookmarks.is_empty() => {
+                self.hotlist.groups.remove(group);
+            }
+            Some(Row::Group(group)) => {
+                self.error = Some(format!("{} still has bookmarks", self.hotlist.groups[group].name));
+                return;
+            }
+            None => return,
+        }
+        self.cursor = self.cursor.min(self.rows().len().saturating_sub(1));
+        self.save();
+    }
+
+    /// move the cursor bookmark to the end of the next group
+    fn move_to_next_group(&mut self) {
+        let Some(Row::Bookmark(group, index)) = self.cursor_row() else {
+            return;
+        };
+        if self.hotlist.groups.len() < 2 {
+            self.error = Some(String::from("create another group first with g"));
+            return;
+        }
+        let bookmark = self.hotlist.groups[group].bookmarks.remove(index);
+        let next = (group + 1) % self.hotlist.groups.len();
+        self.hotlist.groups[next].bookmarks.push(bookmark);
+        let name = self.hotlist.groups[next].name.clone();
+        self.collapsed.remove(&name);
+        self.move_cursor_to(Row::Bookmark(next, self.hotlist.groups[next].bookmarks.len() - 1));
+        self.save();
+    }
+
+    /// render the dialog centered in `area`
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.saturating_sub(4).min(70);
+        let height = area.height.saturating_sub(2).min(22);
+        let [row] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(row);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Directory hotlist ")
+            .style(theme.dialog.style());
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        let [list, status, hint] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1), Constraint::Length(1)]).areas(inner);
+
+        let rows = self.rows();
+        let height = list.height as usize;
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if height > 0 && self.cursor >= self.offset + height {
+            self.offset = self.cursor + 1 - height;
+        }
+        let width = list.width as usize;
+        let lines: Vec<Line> = if rows.is_empty() {
+            vec![Line::raw("no bookmarks, press a to add the current directory")]
+        } else {
+            rows.iter()
+                .enumerate()
+                .skip(self.offset)
+                .take(height)
+                .map(|(index, row)| {
+                    let text = match *row {
+                        Row::Group(group) => {
+                            let group = &self.hotlist.groups[group];
+                            let mark = if self.collapsed.contains(&group.name) { '+' } else { '-' };
+                            format!("{mark} {} ({})", group.name, group.bookmarks.len())
+                        }
+                        Row::Bookmark(group, bookmark) => {
+                            let bookmark = &self.hotlist.groups[group].bookmarks[bookmark];
+                            format!("    {:<20} {}", bookmark.name, bookmark.location)
+                        }
+                    };
+                    let text = format!("{text:<width$.width$}");
+                    if index == self.cursor {
+                        Line::styled(text, theme.selected.style())
+                    } else {
+                        Line::raw(text)
+                    }
+                })
+                .collect()
+        };
+        frame.render_widget(Paragraph::new(lines), list);
+
+        match &self.edit {
+            Some((edit, input)) => {
+                let label = match edit {
+                    Edit::NewGroup => "New group: ",
+                    Edit::Rename(_) => "Rename to: ",
+                };
+                let (text, cursor) = input.visible((status.width as usize).saturating_sub(label.len()));
+                frame.render_widget(Paragraph::new(format!("{label}{text}")), status);
+                frame.set_cursor_position((status.x + (label.len() + cursor) as u16, status.y));
+            }
+            None => {
+                if let Some(error) = &self.error {
+                    frame.render_widget(Paragraph::new(error.as_str()).style(theme.error.style()), status);
+                }
+            }
+        }
+        let keys = "Enter go  a add  g group  r rename  d delete  m move  Esc close";
+        frame.render_widget(Paragraph::new(keys), hint);
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::SystemTime;
+
+use ratatui::layout::Rect;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Paragraph, Wrap};
+use ratatui::Frame;
+
+use crate::config::FormatConfig;
+use crate::format;
+use crate::fs_node::FsNodeType;
+use crate::fsinfo::device_numbers;
+use crate::info::FileInfo;
+use crate::ops::{group_name, user_name};
+use crate::ui::theme::Theme;
+
+/// width of the labels in front of the values
+const LABEL_WIDTH: usize = 11;
+
+/// render the details of the entry under the cursor of the other panel in place of a listing
+pub fn render(frame: &mut Frame, area: Rect, info: Option<&FileInfo>, active: bool, theme: &Theme, config: &FormatConfig) {
+    let frame_style = if active {
+        theme.active_frame.style()
+    } else {
+        theme.panel_frame.style()
+    };
+    let block = Block::default()
+        .style(theme.panel.style())
+        .borders(Borders::ALL)
+        .border_type(if active { BorderType::Double } else { BorderType::Plain })
+        .border_style(frame_style)
+        .title(Span::styled(" Info ", frame_style));
+    let lines: Vec<Line> = match info {
+        Some(info) => details(info, config)
+            .into_iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    Span::styled(format!("{label:<LABEL_WIDTH$}"), theme.header.style()),
+                    Span::raw(value),
+                ])
+            })
+            .collect(),
+        None => vec![Line::raw("No file")],
+    };
+    frame.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
+}
+
+/// the lines of the info, a label and its value, skipping what is not known
+fn details(info: &FileInfo, config: &FormatConfig) -> Vec<(&'static str, String)> {
+    let mut lines = vec![("File", info.name.clone()), ("Location", info.location.clone())];
+    let kind = match &info.node_type {
+        FsNodeType::Directory => String::from("directory"),
+        FsNodeType::File => info.mime.to_string(),
+        FsNodeType::Symlink { broken: true, .. } => String::from("broken symbolic link"),
+        FsNodeType::Symlink { .. } => format!("symbolic link to {}", info.mime),
+    };
+    lines.push(("Type", kind));
+    if let FsNodeType::Symlink { target, .. } = &info.node_type {
+        lines.push(("Target", target.display().to_string()));
+    }
+    match info.size {
+        Some(size) => lines.push(("Size", format!("{} ({size} bytes)", format::size(size, config.units)))),
+        None if info.node_type == FsNodeType::Directory => lines.push(("Size", String::from("not computed, Ctrl+Space"))),
+        None => {}
+    }
+    if let Some(allocated) = info.allocated {
+        lines.push(("Allocated", format::size(allocated, config.units)));
+    }
+    let Some(metadata) = &info.metadata else {
+        lines.push(("Metadata", String::from("cannot be read")));
+        return lines;
+    };
+    let mode = metadata.permissions;
+    let letter = format::type_letter(mode).unwrap_or('-');
+    lines.push(("Mode", format!("{letter}{} ({:04o})", format::permissions(mode), mode & 0o7777)));
+    // ids of remote files mean nothing here
+    let user = info.local.then(|| user_name(metadata.uid)).flatten();
+    let group = info.local.then(|| group_name(metadata.gid)).flatten();
+    lines.push((
+        "Owner",
+        format!(
+            "{}:{} ({}:{})",
+            user.unwrap_or_else(|| metadata.uid.to_string()),
+            group.unwrap_or_else(|| metadata.gid.to_string()),
+            metadata.uid,
+            metadata.gid
+        ),
+    ));
+    lines.push(("Links", metadata.nlink.to_string()));
+    if metadata.inode != 0 {
+        lines.push(("Inode", metadata.inode.to_string()));
+    }
+    let now = SystemTime::now();
+    let time = |time: SystemTime| format!("{} ({})", format::time(time, &config.time_format), format::relative_time(time, now));
+    for (label, value) in [("Modified", metadata.modified), ("Changed", metadata.changed), ("Accessed", info.accessed)] {
+        if let Some(value) = value {
+            lines.push((label, time(value)));
+        }
+    }
+    if let Some(device) = info.device {
+        let (major, minor) = device_numbers(device);
+        lines.push(("Device", format!("{major}:{minor}")));
+    }
+    if let Some(mount) = &info.mount {
+        lines.push((
+            "Filesystem",
+            format!("{} on {} ({})", mount.device, mount.mount_point.display(), mount.fs_type),
+        ));
+    }
+    lines
+}
@@ -0,0 +1,122 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::Rect;
+use ratatui::Frame;
+
+use crate::commands::PromptKind;
+use crate::format::{self, SizeUnits};
+use crate::job::{Job, JobReport};
+use crate::ui::dialog::{DialogResult, ModalDialog, ProgressBar, Widget};
+use crate::ui::theme::Theme;
+
+/// buttons of the dialog, the first one reads Resume while paused
+const PAUSE: usize = 0;
+const SKIP: usize = 1;
+const ABORT: usize = 2;
+
+/// bandwidth limits + and - step through, in bytes per second
+const LIMITS: [u64; 6] = [64 << 10, 256 << 10, 1 << 20, 4 << 20, 16 << 20, 64 << 20];
+
+/// progress of a running copy or move, open until the job stops
+#[derive(Debug)]
+pub struct JobDialog {
+    pub job: Job,
+    dialog: ModalDialog,
+    units: SizeUnits,
+}
+
+impl JobDialog {
+    pub fn new(job: Job, units: SizeUnits) -> JobDialog {
+        let widgets = vec![
+            Widget::Text(String::from("Counting files...")),
+            Widget::Progress(ProgressBar::default()),
+            Widget::Progress(ProgressBar::default()),
+            Widget::Text(String::new()),
+            Widget::Text(String::new()),
+            Widget::Buttons(vec![String::from("Pause"), String::from("Skip"), String::from("Abort")]),
+        ];
+        let dialog = ModalDialog::new(PromptKind::Job, job.kind.label(), widgets);
+        let mut job_dialog = JobDialog { job, dialog, units };
+        job_dialog.refresh();
+        job_dialog
+    }
+
+    /// take what the job sent, its report once it stopped
+    pub fn poll(&mut self) -> Option<JobReport> {
+        let report = self.job.poll();
+        self.refresh();
+        report
+    }
+
+    /// Pause or Resume, Skip and Abort, Esc aborts too, + and - change the bandwidth limit
+    pub fn handle_key(&mut self, key: &KeyEvent) {
+        let limit = self.job.limit();
+        match key.code {
+            // past the highest limit there is none
+            KeyCode::Char('+') => {
+                self.job.set_limit(limit.and_then(|limit| LIMITS.into_iter().find(|step| *step > limit)));
+            }
+            KeyCode::Char('-') => {
+                let lower = match limit {
+                    Some(limit) => LIMITS.into_iter().rev().find(|step| *step < limit).unwrap_or(LIMITS[0]),
+                    None => LIMITS[LIMITS.len() - 1],
+                };
+                self.job.set_limit(Some(lower));
+            }
+            _ => self.press(key),
+        }
+        self.refresh();
+    }
+
+    fn press(&mut self, key: &KeyEvent) {
+        match self.dialog.handle_key(key) {
+            DialogResult::Pending => {}
+            DialogResult::Cancel => self.job.abort(),
+            DialogResult::Submit(answer) => match answer.button {
+                PAUSE => self.job.set_paused(!self.job.is_paused()),
+                SKIP => self.job.skip_file(),
+                ABORT => self.job.abort(),
+                _ => {}
+            },
+        }
+    }
+
+    /// write the last progress of the job into the widgets
+    fn refresh(&mut self) {
+        let paused = self.job.is_paused();
+        if let Some(Widget::Buttons(buttons)) = self.dialog.widgets.last_mut() {
+            buttons[PAUSE] = String::from(if paused { "Resume" } else { "Pause" });
+        }
+        let limit = match self.job.limit() {
+            Some(limit) => format!("Limit {}/s, + and - change it", format::size(limit, self.units)),
+            None => String::from("No limit, - sets one"),
+        };
+        self.dialog.widgets[4] = Widget::Text(limit);
+        let Some(progress) = &self.job.progress else {
+            return;
+        };
+        let current = match paused {
+            true => format!("Paused on {}", progress.current_file.display()),
+            false => progress.current_file.display().to_string(),
+        };
+        let size = |bytes| format::size(bytes, self.units);
+        let file = format!("File   {} of {}", size(progress.file_bytes), size(progress.file_size));
+        let total = format!("Total  {} of {}", size(progress.bytes_copied), size(progress.total_bytes));
+        let left = progress.total_files.saturating_sub(progress.files_copied);
+        // `?` until the speed is known
+        let eta = self.job.eta().map(format::duration).unwrap_or_else(|| String::from("?"));
+        let mut stats = format!("{}/s  ETA {eta}  {left} files left", size(self.job.speed()));
+        if progress.files_skipped > 0 {
+            stats.push_str(&format!(", {} skipped", progress.files_skipped));
+        }
+        let (file_bytes, file_size, bytes_copied, total_bytes) =
+            (progress.file_bytes, progress.file_size, progress.bytes_copied, progress.total_bytes);
+        self.dialog.widgets[0] = Widget::Text(current);
+        self.dialog.widgets[3] = Widget::Text(stats);
+        self.dialog.set_progress(0, &file, file_bytes, file_size);
+        self.dialog.set_progress(1, &total, bytes_copied, total_bytes);
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        self.dialog.render(frame, area, theme);
+    }
+}
@@ -0,0 +1,34 @@
+use ratatui::layout::Rect;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use crate::commands::FUNCTION_KEYS;
+use crate::ui::theme::Theme;
+
+/// width of the slot of each key
+fn slot_width(area: Rect) -> usize {
+    (area.width as usize / FUNCTION_KEYS.len()).max(3)
+}
+
+/// number of the function key drawn at `column`
+pub fn key_at(area: Rect, column: u16) -> Option<u8> {
+    let slot = column.checked_sub(area.x)? as usize / slot_width(area);
+    FUNCTION_KEYS.get(slot).map(|(number, _, _)| *number)
+}
+
+/// render the function-key bar, every label gets an equal share of the width
+pub fn render(frame: &mut Frame, area: Rect, theme: &Theme) {
+    let slot = slot_width(area);
+    let mut spans = Vec::new();
+    for (number, label, _) in FUNCTION_KEYS {
+        let number = number.to_string();
+        let width = slot.saturating_sub(number.len());
+        spans.push(Span::styled(number, theme.key_number.style()));
+        spans.push(Span::styled(
+            format!("{label:<width$.width$}"),
+            theme.key_label.style(),
+        ));
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
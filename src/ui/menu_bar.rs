@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::commands::{Command, Keymap};
+use crate::panel::PanelSide;
+use crate::ui::theme::Theme;
+
+/// a pull-down menu of the bar
+#[derive(Debug, Clone, Copy)]
+pub struct Menu {
+    pub title: &'static str,
+    /// panel the commands act on, the active one when None
+    pub side: Option<PanelSide>,
+    pub commands: &'static [Command],
+}
+
+/// commands of the Left and Right menus
+const PANEL_COMMANDS: &[Command] = &[
+    Command::Tree,
+    Command::IncrementalSearch,
+    Command::QuickSearch,
+    Command::Filter,
+    Command::ToggleHidden,
+    Command::Layout,
+    Command::NextLayout,
+    Command::Sort,
+    Command::Info,
+    Command::FilesystemInfo,
+    Command::Drive,
+    Command::ExternalPanelize,
+    Command::Reload,
+    Command::ComputeSize,
+    Command::ParentDirectory,
+    Command::Hotlist,
+    Command::ChangeDirectory,
+    Command::Connections,
+    Command::History,
+    Command::HistoryBack,
+    Command::HistoryForward,
+    Command::NewTab,
+    Command::CloseTab,
+    Command::NextTab,
+    Command::PreviousTab,
+    Command::MoveTabLeft,
+    Command::MoveTabRight,
+];
+
+/// the menus of the bar, from left to right
+pub const MENUS: [Menu; 5] = [
+    Menu {
+        title: "Left",
+        side: Some(PanelSide::Left),
+        commands: PANEL_COMMANDS,
+    },
+    Menu {
+        title: "File",
+        side: None,
+        commands: &[
+            Command::View,
+            Command::Edit,
+            Command::Open,
+            Command::Copy,
+            Command::Move,
+            Command::CutFiles,
+            Command::CopyFiles,
+            Command::PasteFiles,
+            Command::Hardlink,
+            Command::MultiRename,
+            Command::Compress,
+            Command::Extract,
+            Command::ExtractHere,
+            Command::Split,
+            Command::Join,
+            Command::Mkdir,
+            Command::Delete,
+            Command::DeletePermanently,
+            Command::Shred,
+            Command::Chmod,
+            Command::Chown,
+            Command::ToggleMark,
+            Command::MarkGroup,
+            Command::UnmarkGroup,
+            Command::InvertMarks,
+            Command::SelectByCriteria,
+            Command::SaveSelection,
+            Command::RestoreSelection,
+            Command::Undo,
+            Command::Quit,
+        ],
+    },
+    Menu {
+        title: "Command",
+        side: None,
+        commands: &[
+            Command::Find,
+            Command::CompareDirectories,
+            Command::Synchronize,
+            Command::DiskUsage,
+            Command::Checksum,
+            Command::WriteChecksums,
+            Command::VerifyChecksums,
+            Command::Trash,
+            Command::Restore,
+            Command::CopyName,
+            Command::CopyPath,
+            Command::CopyPaths,
+            Command::CopyQuotedPaths,
+            Command::UserMenu,
+        ],
+    },
+    Menu {
+        title: "Options",
+        side: None,
+        commands: &[Command::VerifyCopies, Command::BandwidthLimit, Command::CopyAttributes, Command::Theme],
+    },
+    Menu {
+        title: "Right",
+        side: Some(PanelSide::Right),
+        commands: PANEL_COMMANDS,
+    },
+];
+
+/// column of the first title
+const BAR_MARGIN: u16 = 1;
+
+/// start column and width of each title on the bar, relative to its left edge
+fn title_spans() -> impl Iterator<Item = (u16, u16)> {
+    MENUS.iter().scan(BAR_MARGIN, |start, menu| {
+        // a space on each side of the title
+        let width = menu.title.len() as u16 + 2;
+        let span = (*start, width);
+        *start += width + 1;
+        Some(span)
+    })
+}
+
+/// index of the menu whose title is drawn at `column`
+pub fn title_at(area: Rect, column: u16) -> Option<usize> {
+    let column = column.checked_sub(area.x)?;
+    title_spans().position(|(start, width)| (start..start + width).contains(&column))
+}
+
+/// render the titles of the menus on one line, the open one highlighted
+pub fn render_bar(frame: &mut Frame, area: Rect, open: Option<usize>, theme: &Theme) {
+    let mut spans = vec![Span::raw(" ".repeat(BAR_MARGIN as usize))];
+    for (index, menu) in MENUS.iter().enumerate() {
+        let style = match open == Some(index) {
+            true => theme.selected.style(),
+            false => theme.key_label.style(),
+        };
+        spans.push(Span::styled(format!(" {} ", menu.title), style));
+        spans.push(Span::raw(" "));
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)).style(theme.key_label.style()), area);
+}
+
+/// what a key or a click did to the open menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuResult {
+    Pending,
+    /// run the command, on the given side if the menu has one
+    Run(Option<PanelSide>, Command),
+    Cancel,
+}
+
+/// the bar with one of its menus pulled down
+#[derive(Debug, Clone)]
+pub struct MenuBar {
+    /// index of the open menu in [`MENUS`]
+    pub menu: usize,
+    pub cursor: usize,
+    /// keys shown next to the commands
+    shortcuts: HashMap<Command, String>,
+}
+
+impl MenuBar {
+    pub fn new(menu: usize, keymap: &Keymap) -> MenuBar {
+        let shortcuts = MENUS
+            .iter()
+            .flat_map(|menu| menu.commands)
+            .filter_map(|command| Some((*command, keymap.shortcut(*command)?)))
+            .collect();
+        MenuBar {
+            menu: menu.min(MENUS.len() - 1),
+            cursor: 0,
+            shortcuts,
+        }
+    }
+
+    fn commands(&self) -> &'static [Command] {
+        MENUS[self.menu].commands
+    }
+
+    fn open(&mut self, menu: usize) {
+        self.menu = menu;
+        self.cursor = 0;
+    }
+
+    fn run(&self) -> MenuResult {
+        MenuResult::Run(MENUS[self.menu].side, self.commands()[self.cursor])
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> MenuResult {
+        let last = self.commands().len() - 1;
+        match key.code {
+            KeyCode::Esc | KeyCode::F(9) | KeyCode::F(10) => return MenuResult::Cancel,
+            KeyCode::Enter => return self.run(),
+            KeyCode::Left => self.open((self.menu + MENUS.len() - 1) % MENUS.len()),
+            KeyCode::Right => self.open((self.menu + 1) % MENUS.len()),
+            KeyCode::Up => self.cursor = if self.cursor == 0 { last } else { self.cursor - 1 },
+            KeyCode::Down => self.cursor = if self.cursor == last { 0 } else { self.cursor + 1 },
+            KeyCode::Home | KeyCode::PageUp => self.cursor = 0,
+            KeyCode::End | KeyCode::PageDown => self.cursor = last,
+            _ => {}
+        }
+        MenuResult::Pending
+    }
+
+    /// follow the mouse, `bar` is where the titles are and `area` where the menu is pulled down
+    pub fn handle_mouse(&mut self, event: &MouseEvent, bar: Rect, area: Rect) -> MenuResult {
+        let position = Position::new(event.column, event.row);
+        let list = self.list_area(area);
+        let entry = match list.contains(position) {
+            true => Some((event.row - list.y) as usize),
+            false => None,
+        };
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) if bar.contains(position) => match title_at(bar, event.column) {
+                Some(menu) if menu == self.menu => MenuResult::Cancel,
+                Some(menu) => {
+                    self.open(menu);
+                    MenuResult::Pending
+                }
+                None => MenuResult::Cancel,
+            },
+            MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Up(MouseButton::Left) => match entry {
+                Some(index) => {
+                    self.cursor = index;
+                    self.run()
+                }
+                None if event.kind == MouseEventKind::Up(MouseButton::Left) => MenuResult::Pending,
+                None => MenuResult::Cancel,
+            },
+            MouseEventKind::Moved | MouseEventKind::Drag(_) => {
+                if let Some(index) = entry {
+                    self.cursor = index;
+                }
+                MenuResult::Pending
+            }
+            _ => MenuResult::Pending,
+        }
+    }
+
+    /// the pulled down menu under its title, kept inside `area`
+    fn menu_area(&self, area: Rect) -> Rect {
+        let shortcut_width = self.commands().iter().filter_map(|command| self.shortcuts.get(command)).map(String::len).max();
+        let label_width = self.commands().iter().map(|command| command.label().len()).max().unwrap_or(0);
+        // the labels and shortcuts are padded by a space and apart by two
+        let width = (label_width + shortcut_width.map_or(0, |width| width + 2) + 4) as u16;
+        let width = width.min(area.width);
+        let height = (self.commands().len() as u16 + 2).min(area.height);
+        let (start, _) = title_spans().nth(self.menu).unwrap_or((0, 0));
+        let x = (area.x + start).min(area.right().saturating_sub(width));
+        Rect::new(x, area.y, width, height)
+    }
+
+    fn list_area(&self, area: Rect) -> Rect {
+        Block::default().borders(Borders::ALL).inner(self.menu_area(area))
+    }
+
+    /// render the pulled down menu at the top of `area`, the bar is drawn by [`render_bar`]
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let menu = self.menu_area(area);
+        let block = Block::default().borders(Borders::ALL).style(theme.dialog.style());
+        let width = block.inner(menu).width as usize;
+        let lines: Vec<Line> = self
+            .commands()
+            .iter()
+            .enumerate()
+            .map(|(index, command)| {
+                let shortcut = self.shortcuts.get(command).map(String::as_str).unwrap_or("");
+                let label = width.saturating_sub(shortcut.len() + 2);
+                let text = format!(" {:<label$.label$}{shortcut} ", command.label());
+                match index == self.cursor {
+                    true => Line::styled(text, theme.selected.style()),
+                    false => Line::raw(text),
+                }
+            })
+            .collect();
+        frame.render_widget(Clear, menu);
+        frame.render_widget(Paragraph::new(lines).block(block), menu);
+    }
+}
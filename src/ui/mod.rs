@@ -0,0 +1,277 @@
+mod completion_view;
+mod editor_view;
+mod help_view;
+mod info_view;
+mod key_bar;
+mod panel_view;
+mod search_view;
+mod subshell_view;
+mod sync_view;
+mod tab_strip;
+mod tree_view;
+mod usage_view;
+pub mod chmod_dialog;
+pub mod chown_dialog;
+pub mod connection_dialog;
+pub mod dialog;
+pub mod find_dialog;
+pub mod history_dialog;
+pub mod hotlist_dialog;
+pub mod job_dialog;
+pub mod menu_bar;
+pub mod prompt;
+pub mod rename_dialog;
+pub mod theme;
+pub mod viewer_view;
+
+pub use panel_view::entry_at;
+
+use ratatui::layout::{Constraint, Direction, Layout, Position, Rect};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use crate::app::Screen;
+use crate::commands::PromptKind;
+use crate::job::JobKind;
+use crate::panel::{PanelManager, PanelSide};
+use crate::shell::Shell;
+use crate::ui::chmod_dialog::ChmodDialog;
+use crate::ui::chown_dialog::ChownDialog;
+use crate::ui::connection_dialog::ConnectionDialog;
+use crate::ui::dialog::ModalDialog;
+use crate::ui::find_dialog::FindDialog;
+use crate::ui::history_dialog::HistoryDialog;
+use crate::ui::hotlist_dialog::HotlistDialog;
+use crate::ui::job_dialog::JobDialog;
+use crate::ui::menu_bar::MenuBar;
+use crate::ui::prompt::Prompt;
+use crate::ui::rename_dialog::RenameDialog;
+use crate::ui::theme::Theme;
+
+/// window drawn over the panels that takes every key
+#[derive(Debug)]
+pub enum Dialog {
+    Prompt(Prompt),
+    Find(Box<FindDialog>),
+    Rename(Box<RenameDialog>),
+    Chmod(Box<ChmodDialog>),
+    Chown(Box<ChownDialog>),
+    Hotlist(Box<HotlistDialog>),
+    Connections(Box<ConnectionDialog>),
+    History(Box<HistoryDialog>),
+    Menu(Box<MenuBar>),
+    Modal(Box<ModalDialog>),
+    Job(Box<JobDialog>),
+}
+
+impl Dialog {
+    /// put pasted text in the input with the focus, the dialogs without one ignore it
+    pub fn paste(&mut self, text: &str) {
+        match self {
+            Dialog::Prompt(prompt) => prompt.input.insert(text),
+            Dialog::Find(find) => find.paste(text),
+            Dialog::Rename(rename) => rename.paste(text),
+            Dialog::Modal(modal) => modal.paste(text),
+            _ => {}
+        }
+    }
+
+    /// the help topic about the dialog
+    pub fn help_topic(&self) -> &'static str {
+        match self {
+            Dialog::Find(_) => "find",
+            Dialog::Rename(_) => "rename",
+            Dialog::Chmod(_) | Dialog::Chown(_) => "permissions",
+            Dialog::Hotlist(_) => "hotlist",
+            Dialog::Connections(_) => "connections",
+            Dialog::History(_) => "history",
+            Dialog::Menu(_) => "menu",
+            Dialog::Job(job) if matches!(job.job.kind, JobKind::Compress | JobKind::Extract) => "archives",
+            Dialog::Job(job) if job.job.kind == JobKind::Shred => "delete",
+            Dialog::Job(_) => "copy",
+            Dialog::Modal(modal) => match modal.kind {
+                PromptKind::Delete { .. } | PromptKind::Shred | PromptKind::Escalate => "delete",
+                PromptKind::ChangeDirectory => "panels",
+                PromptKind::SelectByCriteria | PromptKind::SaveSelection | PromptKind::RestoreSelection => "selection",
+                PromptKind::Overwrite { .. } => "copy",
+                PromptKind::Compress | PromptKind::ExtractPassword { .. } | PromptKind::ExtractOverwrite => "archives",
+                PromptKind::SaveConnection { .. } | PromptKind::ConnectionPassword { .. } => "connections",
+                _ => "dialogs",
+            },
+            Dialog::Prompt(_) => "dialogs",
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        match self {
+            Dialog::Prompt(prompt) => prompt.render(frame, area, theme),
+            Dialog::Find(find) => find.render(frame, area, theme),
+            Dialog::Rename(rename) => rename.render(frame, area, theme),
+            Dialog::Chmod(chmod) => chmod.render(frame, area, theme),
+            Dialog::Chown(chown) => chown.render(frame, area, theme),
+            Dialog::Hotlist(hotlist) => hotlist.render(frame, area, theme),
+            Dialog::Connections(connections) => connections.render(frame, area, theme),
+            Dialog::History(history) => history.render(frame, area, theme),
+            Dialog::Menu(menu) => menu.render(frame, area, theme),
+            Dialog::Modal(modal) => modal.render(frame, area, theme),
+            Dialog::Job(job) => job.render(frame, area, theme),
+        }
+    }
+}
+
+/// render the whole file manager screen, `message` is shown above the command line
+///
+/// An open full-screen mode takes the place of the panels, the subshell
+/// screen toggled by the command line takes it when nothing is open.
+pub fn draw(
+    frame: &mut Frame,
+    manager: &mut PanelManager,
+    screen: Option<&mut dyn Screen>,
+    theme: &Theme,
+    message: Option<&str>,
+    dialog: Option<&mut Dialog>,
+    shell: &Shell,
+) {
+    let [bar, panels, message_row, command_line, keys] = screen_rows(frame.area(), message.is_some());
+
+    if let Some(screen) = screen {
+        let area = frame.area();
+        screen.render(frame, area, theme);
+        draw_overlays(frame, area, theme, message, dialog);
+        return;
+    }
+
+    if shell.visible {
+        subshell_view::render(frame, panels, shell, theme);
+    } else {
+        let format = manager.format.clone();
+        for (side, strip, area) in side_areas(panels, manager) {
+            let active = manager.active == side;
+            // a panel in info mode describes the entry under the cursor of the other one
+            let described = match manager.panel(side).info {
+                Some(_) => manager.panel(side.opposite()).cursor_node(),
+                None => None,
+            };
+            let (panel, tabs) = manager.side_mut(side);
+            if let Some(strip) = strip {
+                tab_strip::render(frame, strip, panel, tabs, active, theme);
+            }
+            match panel.info.as_mut() {
+                Some(info) => info_view::render(frame, area, info.show(described.as_ref()), active, theme, &format),
+                None => panel_view::render(frame, area, panel, active, theme, &format),
+            }
+        }
+    }
+
+    if let Some(message) = message {
+        frame.render_widget(Paragraph::new(message).style(theme.error.style()), message_row);
+    }
+    let location = manager.active().display_location();
+    subshell_view::render_command_line(frame, command_line, shell, &location, dialog.is_none(), theme);
+    key_bar::render(frame, keys, theme);
+    let open = match &dialog {
+        Some(Dialog::Menu(menu)) => Some(menu.menu),
+        _ => None,
+    };
+    menu_bar::render_bar(frame, bar, open, theme);
+    draw_overlays(frame, panels, theme, None, dialog);
+}
+
+/// rows of the main screen: menu bar, panels, message line, command line and key bar
+fn screen_rows(area: Rect, message: bool) -> [Rect; 5] {
+    Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(1),
+        Constraint::Length(if message { 1 } else { 0 }),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(area)
+}
+
+/// the menu bar and the area its menus are pulled down in
+pub fn menu_areas(area: Rect, message: bool) -> (Rect, Rect) {
+    let [bar, panels, ..] = screen_rows(area, message);
+    (bar, panels)
+}
+
+/// tab strip and listing of each side, the strip only shows up once a side has a second tab
+fn side_areas(area: Rect, manager: &PanelManager) -> [(PanelSide, Option<Rect>, Rect); 2] {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    [(PanelSide::Left, columns[0]), (PanelSide::Right, columns[1])].map(|(side, area)| {
+        if manager.tabs(side).len() > 1 {
+            let [strip, rest] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+            (side, Some(strip), rest)
+        } else {
+            (side, None, area)
+        }
+    })
+}
+
+/// what the main screen shows at a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hit {
+    /// a tab of the strip of a side, by index
+    Tab(PanelSide, usize),
+    /// an entry of a listing, by index in the panel entries
+    Entry(PanelSide, usize),
+    /// anywhere else on a panel
+    Panel(PanelSide),
+    /// a button of the key bar, by function key number
+    FunctionKey(u8),
+    /// a title of the menu bar, by index in the menus
+    Menu(usize),
+}
+
+/// find what was drawn at `column`, `row` by [`draw`] with the same state
+///
+/// `panels` is false while the subshell screen hides them.
+pub fn hit_test(area: Rect, manager: &PanelManager, message: bool, panels: bool, column: u16, row: u16) -> Option<Hit> {
+    let [bar, listing, _, _, keys] = screen_rows(area, message);
+    let position = Position::new(column, row);
+    if keys.contains(position) {
+        return key_bar::key_at(keys, column).map(Hit::FunctionKey);
+    }
+    if bar.contains(position) {
+        return menu_bar::title_at(bar, column).map(Hit::Menu);
+    }
+    if !panels || !listing.contains(position) {
+        return None;
+    }
+    let (side, strip, area) = side_areas(listing, manager).into_iter().find(|(_, strip, area)| {
+        area.contains(position) || strip.is_some_and(|strip| strip.contains(position))
+    })?;
+    let panel = manager.panel(side);
+    if let Some(strip) = strip
+        && strip.contains(position)
+    {
+        let tab = tab_strip::tab_at(strip, panel, manager.tabs(side), column);
+        return Some(tab.map_or(Hit::Panel(side), |index| Hit::Tab(side, index)));
+    }
+    // the tree has rows of its own, the info none
+    if panel.tree.is_some() || panel.info.is_some() {
+        return Some(Hit::Panel(side));
+    }
+    match entry_at(area, panel, column, row) {
+        Some(index) => Some(Hit::Entry(side, index)),
+        None => Some(Hit::Panel(side)),
+    }
+}
+
+/// message line and dialog drawn over full-screen modes
+fn draw_overlays(frame: &mut Frame, area: Rect, theme: &Theme, message: Option<&str>, dialog: Option<&mut Dialog>) {
+    if let Some(message) = message {
+        let line = Rect {
+            y: area.bottom().saturating_sub(2),
+            height: 1,
+            ..area
+        };
+        frame.render_widget(Paragraph::new(message).style(theme.error.style()), line);
+    }
+    if let Some(dialog) = dialog {
+        dialog.render(frame, area, theme);
+    }
+}
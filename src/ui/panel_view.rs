@@ -0,0 +1,383 @@
+use std::time::{Duration, SystemTime};
+
+use ratatui::layout::{Constraint, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Cell, Row, Table, TableState};
+use ratatui::Frame;
+
+use crate::config::FormatConfig;
+use crate::format;
+use crate::fs_node::{FsNode, FsNodeType};
+use crate::fsinfo::FsStatus;
+use crate::ops::user_name;
+use crate::panel::{Column, DropTarget, Layout, Panel};
+use crate::ui::theme::Theme;
+use crate::ui::tree_view;
+
+/// written after the directories whose content could not be read
+pub const UNREADABLE_MARK: &str = " ⚠";
+
+/// render a panel as a framed file list, `active` panels get a highlighted frame
+pub fn render(frame: &mut Frame, area: Rect, panel: &mut Panel, active: bool, theme: &Theme, format: &FormatConfig) {
+    if let Some(tree) = panel.tree.as_mut() {
+        tree_view::render(frame, area, tree, active, theme);
+        return;
+    }
+    let frame_style = if active {
+        theme.active_frame.style()
+    } else {
+        theme.panel_frame.style()
+    };
+    // a filter set with the dialog stays in sight, files are missing otherwise
+    let location = match &panel.listing {
+        Some(listing) => format!("{}: {}", listing.title, panel.display_location()),
+        None => panel.display_location(),
+    };
+    let title = match &panel.name_filter {
+        Some(filter) => format!(" {location} [{}] ", filter.pattern),
+        None => format!(" {location} "),
+    };
+    // remote panels name their connection first, like an error once it dropped
+    let title = match panel.vfs().remote() {
+        Some(remote) => {
+            let style = match remote.connected {
+                true => frame_style,
+                false => theme.error.style(),
+            };
+            Line::from(vec![Span::styled(format!(" [{}]", remote.label), style), Span::styled(title, frame_style)])
+        }
+        None => Line::from(Span::styled(title, frame_style)),
+    };
+    // entries dragged over the panel would go to the directory it shows
+    let border_style = match panel.drop_target {
+        Some(DropTarget::Directory) => theme.drop_target.style(),
+        _ => frame_style,
+    };
+    let block = Block::default()
+        .style(theme.panel.style())
+        .borders(Borders::ALL)
+        .border_type(if active { BorderType::Double } else { BorderType::Plain })
+        .border_style(border_style)
+        .title(title);
+    // the quick filter or search query sits on the bottom border while typing,
+    // the marks counted on the left of the free space otherwise
+    let marked = match (&panel.filter, &panel.search) {
+        (None, None) => marked_footer(panel, format),
+        _ => None,
+    };
+    let block = match &marked {
+        Some(marked) => block.title_bottom(Line::styled(marked.clone(), theme.marked.style())),
+        None => block,
+    };
+    let room = (area.width as usize).saturating_sub(2 + marked.as_ref().map_or(0, |marked| marked.chars().count()));
+    let block = match (&panel.filter, &panel.search, &panel.loading) {
+        (Some(filter), ..) => block.title_bottom(Span::styled(format!(" Filter: {} ", filter.query), frame_style)),
+        (None, Some(search), _) => block.title_bottom(Span::styled(format!(" Search: {} ", search.query), frame_style)),
+        (None, None, Some(loading)) => {
+            block.title_bottom(Line::styled(format!(" loading… {} entries ", loading.count), frame_style).right_aligned())
+        }
+        (None, None, None) => match panel.fs_status().and_then(|status| footer(status, format)) {
+            Some(footer) if footer.chars().count() <= room => {
+                block.title_bottom(Line::styled(footer, frame_style).right_aligned())
+            }
+            _ => block,
+        },
+    };
+
+    let cursor_style = if active {
+        theme.selected.style()
+    } else {
+        Style::default()
+    };
+    match panel.layout {
+        Layout::Brief => render_brief(frame, area, block, panel, theme, cursor_style),
+        Layout::Full | Layout::Long => render_rows(frame, area, block, panel, theme, cursor_style, format),
+    }
+}
+
+/// the free space of a filesystem and its type, like ` 12.3 GiB free of 100.0 GiB, ext4 `
+fn footer(status: &FsStatus, format: &FormatConfig) -> Option<String> {
+    let space = status.space?;
+    let free = format!("{} free of {}", format::size(space.free, format.units), format::size(space.total, format.units));
+    Some(match &status.mount {
+        Some(mount) => format!(" {free}, {} ", mount.fs_type),
+        None => format!(" {free} "),
+    })
+}
+
+/// the number of marked entries and their size, like ` 3 marked, 1.2 GiB `
+///
+/// Directories add the size computed for them, none before that.
+fn marked_footer(panel: &Panel, format: &FormatConfig) -> Option<String> {
+    if panel.selection.is_empty() {
+        return None;
+    }
+    let (marked, size) = panel.marked_total();
+    Some(format!(" {marked} marked, {} ", format::size(size, format.units)))
+}
+
+/// one entry per row with the columns of the layout
+fn render_rows(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block,
+    panel: &mut Panel,
+    theme: &Theme,
+    cursor_style: Style,
+    format: &FormatConfig,
+) {
+    // one line for the header
+    let height = block.inner(area).height.saturating_sub(1) as usize;
+    panel.scroll_to_cursor(height);
+
+    // only the rows in sight are formatted, however long the listing
+    let columns = panel.layout_columns();
+    let rows: Vec<Row> = panel
+        .entry_window(panel.offset, height)
+        .iter()
+        .enumerate()
+        .map(|(row, node)| {
+            let index = panel.offset + row;
+            let node = node.read();
+            Row::new(columns.iter().map(|column| match (column, matched_positions(&node, panel)) {
+                (Column::Name, Some(positions)) => Cell::from(matched_name(&node, &positions, panel.icons)),
+                _ => Cell::from(display_column(&node, *column, panel, format)),
+            }))
+            .style(entry_style(&node, index, panel, theme))
+        })
+        .collect();
+
+    let header = Row::new(columns.iter().map(|column| column_title(*column)))
+        .style(theme.header.style());
+    let widths: Vec<Constraint> = columns.iter().map(|column| column_width(*column, format)).collect();
+    let table = Table::new(rows, widths)
+    .header(header)
+    .block(block)
+    .row_highlight_style(cursor_style);
+
+    let mut state = TableState::default();
+    if panel.entry_count() > 0 {
+        state.select(Some(panel.cursor - panel.offset));
+    }
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+/// names only, running down the first column and on into the next ones
+fn render_brief(frame: &mut Frame, area: Rect, block: Block, panel: &mut Panel, theme: &Theme, cursor_style: Style) {
+    let height = block.inner(area).height.saturating_sub(1) as usize;
+    let columns = panel.brief_columns.max(1);
+    panel.scroll_to_cursor(height * columns);
+
+    let entries = panel.entry_window(panel.offset, height * columns);
+    let rows: Vec<Row> = (0..height)
+        .map(|row| {
+            Row::new((0..columns).map(|column| {
+                let index = panel.offset + column * height + row;
+                let Some(node) = entries.get(column * height + row) else {
+                    return Cell::default();
+                };
+                let node = node.read();
+                let cell = match matched_positions(&node, panel) {
+                    Some(positions) => Cell::from(matched_name(&node, &positions, panel.icons)),
+                    None => Cell::from(display_name(&node, panel.icons)),
+                };
+                let style = entry_style(&node, index, panel, theme);
+                match index == panel.cursor {
+                    true => cell.style(style.patch(cursor_style)),
+                    false => cell.style(style),
+                }
+            }))
+        })
+        .collect();
+
+    let header = Row::new((0..columns).map(|_| column_title(Column::Name))).style(theme.header.style());
+    let widths = (0..columns).map(|_| Constraint::Ratio(1, columns as u32));
+    let table = Table::new(rows, widths).header(header).block(block);
+    frame.render_widget(table, area);
+}
+
+/// index of the entry drawn at a cell of a panel shown in `area`
+pub fn entry_at(area: Rect, panel: &Panel, column: u16, row: u16) -> Option<usize> {
+    // entries start under the border and the header
+    let first = area.y + 2;
+    if row < first || row + 1 >= area.bottom() {
+        return None;
+    }
+    let mut index = panel.offset + (row - first) as usize;
+    if panel.layout == Layout::Brief {
+        let height = area.height.saturating_sub(3) as usize;
+        let columns = panel.brief_columns.max(1);
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let slot = column.saturating_sub(area.x + 1) as usize * columns / width;
+        index += slot.min(columns - 1) * height;
+    }
+    (index < panel.entry_count()).then_some(index)
+}
+
+/// color of an entry, marked entries stand out whatever their type
+fn entry_style(node: &FsNode, index: usize, panel: &Panel, theme: &Theme) -> Style {
+    if panel.drop_target == Some(DropTarget::Entry(index)) {
+        theme.drop_target.style()
+    } else if panel.is_selected(&node.path()) {
+        theme.marked.style()
+    } else {
+        theme.file_style(node.class())
+    }
+}
+
+fn column_title(column: Column) -> &'static str {
+    match column {
+        Column::Name => "Name",
+        Column::Size | Column::HumanSize => "Size",
+        Column::Modified => "Modify time",
+        Column::Permissions => "Permission",
+        Column::Owner => "Owner",
+        Column::LinkTarget => "Link target",
+        Column::Path => "Path",
+    }
+}
+
+/// longest time of the last month written relative to now, like `29 days ago`
+const RELATIVE_WIDTH: u16 = 11;
+
+fn column_width(column: Column, format: &FormatConfig) -> Constraint {
+    match column {
+        Column::Name => Constraint::Min(10),
+        Column::Size => Constraint::Length(9),
+        Column::HumanSize => Constraint::Length(6),
+        Column::Modified => {
+            // as wide as any time written with the format
+            let width = format::time(SystemTime::UNIX_EPOCH, &format.time_format).chars().count() as u16;
+            Constraint::Length(if format.relative_times { width.max(RELATIVE_WIDTH) } else { width })
+        }
+        Column::Permissions => Constraint::Length(10),
+        Column::Owner => Constraint::Length(8),
+        Column::LinkTarget => Constraint::Min(8),
+        Column::Path => Constraint::Min(10),
+    }
+}
+
+fn display_column(node: &FsNode, column: Column, panel: &Panel, format: &FormatConfig) -> String {
+    match column {
+        Column::Name => display_name(node, panel.icons),
+        Column::Size => display_size(node),
+        Column::HumanSize => display_human_size(node, format),
+        Column::Modified => display_mtime(node, format),
+        Column::Permissions => display_permissions(node),
+        Column::Owner => display_owner(node),
+        Column::LinkTarget => match &node.node_type {
+            FsNodeType::Symlink { target, .. } => target.display().to_string(),
+            _ => String::new(),
+        },
+        Column::Path => node.path().parent().map(|dir| node.vfs.display_path(dir)).unwrap_or_default(),
+    }
+}
+
+/// name with a mark of its type, after the icon of the node if `icons` is set
+fn display_name(node: &FsNode, icons: bool) -> String {
+    let mut name = format!("{}{}", name_prefix(node, icons), node.name);
+    if node.error.is_some() {
+        name.push_str(UNREADABLE_MARK);
+    }
+    name
+}
+
+/// what comes before the name: the icon if `icons` is set, then the mark of the type
+fn name_prefix(node: &FsNode, icons: bool) -> String {
+    let mark = match &node.node_type {
+        FsNodeType::Directory => "/",
+        FsNodeType::File => "",
+        FsNodeType::Symlink { broken: true, .. } => "!",
+        FsNodeType::Symlink { .. } if node.is_navigable() => "~",
+        FsNodeType::Symlink { .. } => "@",
+    };
+    match icons {
+        true => format!("{} {mark}", node.class().icon),
+        false => mark.to_string(),
+    }
+}
+
+/// letters of the name matched by the quick filter or the search, `None` when there is neither
+fn matched_positions(node: &FsNode, panel: &Panel) -> Option<Vec<usize>> {
+    match (&panel.filter, &panel.search) {
+        (Some(filter), _) => Some(filter.positions(&node.name)),
+        (None, Some(search)) => Some(search.positions(&node.name)),
+        (None, None) => None,
+    }
+}
+
+/// display name with the matched letters underlined
+fn matched_name<'a>(node: &FsNode, positions: &[usize], icons: bool) -> Line<'a> {
+    let name = display_name(node, icons);
+    // the icon and the type marker in front of the name are not part of the match
+    let marker = name_prefix(node, icons).chars().count();
+    let matched = Style::default().add_modifier(Modifier::UNDERLINED | Modifier::BOLD);
+    let spans: Vec<Span> = name
+        .chars()
+        .enumerate()
+        .map(|(index, c)| match index.checked_sub(marker) {
+            Some(position) if positions.contains(&position) => Span::styled(c.to_string(), matched),
+            _ => Span::raw(c.to_string()),
+        })
+        .collect();
+    Line::from(spans)
+}
+
+fn display_size(node: &FsNode) -> String {
+    if node.is_symlink() && node.is_navigable() {
+        return String::from("<DIR>");
+    }
+    match node.size() {
+        Some(size) => size.to_string(),
+        None if node.is_navigable() => String::from("<DIR>"),
+        None => String::from("?"),
+    }
+}
+
+/// size with a unit, like `12.5M`, short enough for a narrow column
+fn display_human_size(node: &FsNode, format: &FormatConfig) -> String {
+    match node.size() {
+        Some(size) if !(node.is_symlink() && node.is_navigable()) => format::short_size(size, format.units),
+        _ => display_size(node),
+    }
+}
+
+/// times of the last month are written relative to now when the configuration asks for it
+fn display_mtime(node: &FsNode, format: &FormatConfig) -> String {
+    const MONTH: Duration = Duration::from_secs(30 * 86_400);
+    let Some(modified) = node.metadata.as_ref().and_then(|metadata| metadata.modified) else {
+        return String::new();
+    };
+    let now = SystemTime::now();
+    let recent = now.duration_since(modified).is_ok_and(|elapsed| elapsed < MONTH);
+    match format.relative_times && recent {
+        true => format::relative_time(modified, now),
+        false => format::time(modified, &format.time_format),
+    }
+}
+
+/// mode as `ls -l` writes it, the type letter from the node when the mode has none
+fn display_permissions(node: &FsNode) -> String {
+    let Some(metadata) = &node.metadata else {
+        return String::from("?");
+    };
+    let kind = format::type_letter(metadata.permissions).unwrap_or(match node.node_type {
+        FsNodeType::Directory => 'd',
+        FsNodeType::Symlink { .. } => 'l',
+        FsNodeType::File => '-',
+    });
+    format!("{kind}{}", format::permissions(metadata.permissions))
+}
+
+/// user name of a local file, remote ids mean nothing here and are shown as numbers
+fn display_owner(node: &FsNode) -> String {
+    let Some(metadata) = &node.metadata else {
+        return String::from("?");
+    };
+    let local = node.vfs.local_path(&node.path()).is_some();
+    local
+        .then(|| user_name(metadata.uid))
+        .flatten()
+        .unwrap_or_else(|| metadata.uid.to_string())
+}
@@ -0,0 +1,144 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::clipboard;
+use crate::commands::PromptKind;
+use crate::ui::theme::Theme;
+
+/// what a key did to a prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptResult {
+    Pending,
+    Submit(String),
+    Cancel,
+}
+
+/// editable line of text with a cursor
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LineInput {
+    pub text: String,
+    /// cursor position in characters
+    pub cursor: usize,
+}
+
+impl LineInput {
+    pub fn new(text: &str) -> LineInput {
+        LineInput {
+            text: text.to_string(),
+            cursor: text.chars().count(),
+        }
+    }
+
+    /// apply an editing key, false if the key is not an editing key
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.text.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.chars().count(),
+            KeyCode::Backspace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.text.remove(self.byte_offset());
+                }
+            }
+            KeyCode::Delete => {
+                if self.cursor < self.text.chars().count() {
+                    self.text.remove(self.byte_offset());
+                }
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.text.clear();
+                self.cursor = 0;
+            }
+            // a clipboard that cannot be read pastes nothing
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Ok(text) = clipboard::paste() {
+                    self.insert(&clipboard::single_line(&text));
+                }
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.text.insert(self.byte_offset(), c);
+                self.cursor += 1;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// put `text` in at the cursor, the cursor after it
+    pub fn insert(&mut self, text: &str) {
+        let offset = self.byte_offset();
+        self.text.insert_str(offset, text);
+        self.cursor += text.chars().count();
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = text.chars().count();
+    }
+
+    fn byte_offset(&self) -> usize {
+        self.text
+            .char_indices()
+            .nth(self.cursor)
+            .map(|(offset, _)| offset)
+            .unwrap_or(self.text.len())
+    }
+
+    /// the part of the text fitting in `width` columns and the cursor column in it
+    pub fn visible(&self, width: usize) -> (String, usize) {
+        // keep the cursor visible in long inputs
+        let skip = self.cursor.saturating_sub(width.saturating_sub(1));
+        (self.text.chars().skip(skip).take(width).collect(), self.cursor - skip)
+    }
+}
+
+/// one-line text input shown in a small box over the panels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub title: String,
+    pub input: LineInput,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, title: &str, input: &str) -> Prompt {
+        Prompt {
+            kind,
+            title: title.to_string(),
+            input: LineInput::new(input),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> PromptResult {
+        match key.code {
+            KeyCode::Enter => PromptResult::Submit(self.input.text.clone()),
+            KeyCode::Esc => PromptResult::Cancel,
+            _ => {
+                self.input.handle_key(key);
+                PromptResult::Pending
+            }
+        }
+    }
+
+    /// render the prompt centered in `area`
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.saturating_sub(4).min(60);
+        let [row] = Layout::vertical([Constraint::Length(3)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(row);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", self.title))
+            .style(theme.dialog.style());
+        let inner = block.inner(area);
+        let (text, cursor) = self.input.visible(inner.width as usize);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(text).block(block), area);
+        frame.set_cursor_position((inner.x + cursor as u16, inner.y));
+    }
+}
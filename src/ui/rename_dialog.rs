@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::Frame;
+use regex::Regex;
+
+use crate::rename::{self, CaseTransform, Rename, RenamePattern};
+use crate::ui::prompt::LineInput;
+use crate::ui::theme::Theme;
+use crate::vfs::VfsRef;
+
+/// what a key did to the rename dialog
+#[derive(Debug, Clone)]
+pub enum RenameResult {
+    Pending,
+    Apply(Vec<Rename>),
+    Cancel,
+}
+
+const TEMPLATE: usize = 0;
+const SEARCH: usize = 1;
+const REPLACE: usize = 2;
+const CASE: usize = 3;
+const COUNTER_START: usize = 4;
+const COUNTER_STEP: usize = 5;
+const COUNTER_WIDTH: usize = 6;
+const FIELDS: usize = 7;
+
+/// form building new names for several files, with a preview of every rename
+#[derive(Debug, Clone)]
+pub struct RenameDialog {
+    vfs: VfsRef,
+    sources: Vec<PathBuf>,
+    pub template: LineInput,
+    pub search: LineInput,
+    pub replace: LineInput,
+    pub case: CaseTransform,
+    pub counter_start: LineInput,
+    pub counter_step: LineInput,
+    pub counter_width: LineInput,
+    /// field with the focus
+    pub focus: usize,
+    /// renames of the current pattern, kept from the last valid one
+    pub preview: Vec<Rename>,
+    /// first preview row shown
+    pub offset: usize,
+    /// why the pattern cannot be used, or why applying was refused
+    pub error: Option<String>,
+}
+
+impl RenameDialog {
+    pub fn new(vfs: VfsRef, sources: Vec<PathBuf>) -> RenameDialog {
+        let pattern = RenamePattern::default();
+        let mut dialog = RenameDialog {
+            vfs,
+            sources,
+            template: LineInput::new(&pattern.template),
+            search: LineInput::default(),
+            replace: LineInput::default(),
+            case: pattern.case,
+            counter_start: LineInput::new(&pattern.counter_start.to_string()),
+            counter_step: LineInput::new(&pattern.counter_step.to_string()),
+            counter_width: LineInput::new(&pattern.counter_width.to_string()),
+            focus: TEMPLATE,
+            preview: Vec::new(),
+            offset: 0,
+            error: None,
+        };
+        dialog.update_preview();
+        dialog
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> RenameResult {
+        match key.code {
+            KeyCode::Esc => return RenameResult::Cancel,
+            KeyCode::Enter => {
+                self.update_preview();
+                if self.error.is_none() {
+                    match self.preview.iter().filter(|rename| rename.conflict.is_some()).count() {
+                        0 => return RenameResult::Apply(self.preview.clone()),
+                        conflicts => self.error = Some(format!("{conflicts} names conflict")),
+                    }
+                }
+            }
+            KeyCode::Tab | KeyCode::Down => self.focus = (self.focus + 1) % FIELDS,
+            KeyCode::BackTab | KeyCode::Up => self.focus = (self.focus + FIELDS - 1) % FIELDS,
+            KeyCode::PageUp => self.offset = self.offset.saturating_sub(10),
+            KeyCode::PageDown => self.offset = (self.offset + 10).min(self.preview.len().saturating_sub(1)),
+            KeyCode::Char(' ') if self.focus == CASE => {
+                self.case = self.case.next();
+                self.update_preview();
+            }
+            _ => {
+                if let Some(input) = self.input_mut()
+                    && input.handle_key(key)
+                {
+                    self.update_preview();
+                }
+            }
+        }
+        RenameResult::Pending
+    }
+
+    /// put pasted text in the field with the focus, if it is a text field
+    pub fn paste(&mut self, text: &str) {
+        if let Some(input) = self.input_mut() {
+            input.insert(text);
+            self.update_preview();
+        }
+    }
+
+    fn input_mut(&mut self) -> Option<&mut LineInput> {
+        match self.focus {
+            TEMPLATE => Some(&mut self.template),
+            SEARCH => Some(&mut self.search),
+            REPLACE => Some(&mut self.replace),
+            COUNTER_START => Some(&mut self.counter_start),
+            COUNTER_STEP => Some(&mut self.counter_step),
+            COUNTER_WIDTH => Some(&mut self.counter_width),
+            _ => None,
+        }
+    }
+
+    /// the renaming described by the form
+    pub fn pattern(&self) -> Result<RenamePattern, String> {
+        let search = match self.search.text.as_str() {
+            "" => None,
+            text => Some(Regex::new(text).map_err(|err| err.to_string())?),
+        };
+        let number = |input: &LineInput, label: &str| {
+            input.text.trim().parse::<u64>().map_err(|_| format!("{label} is not a number"))
+        };
+        Ok(RenamePattern {
+            template: self.template.text.clone(),
+            search,
+            replace: self.replace.text.clone(),
+            case: self.case,
+            counter_start: number(&self.counter_start, "counter start")?,
+            counter_step: number(&self.counter_step, "counter step")?,
+            counter_width: number(&self.counter_width, "counter width")? as usize,
+        })
+    }
+
+    fn update_preview(&mut self) {
+        match self.pattern() {
+            Ok(pattern) => {
+                self.preview = rename::preview(&self.vfs, &self.sources, &pattern);
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    /// render the form above the preview table over most of `area`
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let area = Rect {
+            x: area.x + 2,
+            y: area.y + 1,
+            width: area.width.saturating_sub(4),
+            height: area.height.saturating_sub(2),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Multi-rename {} files ", self.sources.len()))
+            .title_bottom(" Enter Rename  Space Case  PgUp/PgDn Scroll  Esc Cancel ")
+            .style(theme.dialog.style());
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        let [form, error, table] =
+            Layout::vertical([Constraint::Length(FIELDS as u16 + 1), Constraint::Length(1), Constraint::Min(1)]).areas(inner);
+
+        const LABEL: u16 = 16;
+        let field_width = form.width.saturating_sub(LABEL) as usize;
+        let case = LineInput::new(&format!("< {} >", self.case.label()));
+        let fields = [
+            ("Template:", &self.template),
+            ("Search regex:", &self.search),
+            ("Replace with:", &self.replace),
+            ("Case:", &case),
+            ("Counter start:", &self.counter_start),
+            ("Counter step:", &self.counter_step),
+            ("Counter digits:", &self.counter_width),
+        ];
+        for (index, (label, input)) in fields.into_iter().enumerate() {
+            let line = Rect {
+                y: form.y + index as u16,
+                height: 1,
+                ..form
+            };
+            let (text, cursor) = input.visible(field_width);
+            let style = if index == CASE && self.focus == CASE { theme.selected.style() } else { theme.panel.style() };
+            let text = Line::from(vec![
+                Span::raw(format!("{label:<width$}", width = LABEL as usize)),
+                Span::styled(text, style),
+            ]);
+            frame.render_widget(Paragraph::new(text), line);
+            if self.focus == index && index != CASE {
+                frame.set_cursor_position((line.x + LABEL + cursor as u16, line.y));
+            }
+        }
+        let help = Rect {
+            y: form.y + FIELDS as u16,
+            height: 1,
+            ..form
+        };
+        frame.render_widget(Paragraph::new("[N] name  [E] extension  [C] counter  $1 group in the replacement"), help);
+
+        if let Some(message) = &self.error {
+            frame.render_widget(Paragraph::new(message.as_str()).style(theme.error.style()), error);
+        }
+
+        let column = (table.width as usize).saturating_sub(3) / 2;
+        let lines: Vec<Line> = self
+            .preview
+            .iter()
+            .skip(self.offset)
+            .take(table.height as usize)
+            .map(|rename| {
+                let name = |path: &PathBuf| path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                let new_name = match &rename.conflict {
+                    Some(conflict) => format!("{} ({conflict})", name(&rename.to)),
+                    None => name(&rename.to),
+                };
+                let text = format!("{:<column$.column$} -> {new_name}", name(&rename.from));
+                match rename.conflict {
+                    Some(_) => Line::styled(text, theme.error.style()),
+                    None if rename.is_unchanged() => Line::raw(text),
+                    None => Line::styled(text, theme.marked.style()),
+                }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), table);
+    }
+}
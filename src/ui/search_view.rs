@@ -0,0 +1,122 @@
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Frame;
+
+use crate::app::{Screen, ScreenAction};
+use crate::commands::CommandOutcome;
+use crate::event::AppEvent;
+use crate::panel::PanelManager;
+use crate::search::{SearchAction, SearchResults};
+use crate::viewer::Viewer;
+use crate::ui::theme::Theme;
+
+/// render the search results over the whole `area`
+pub fn render(frame: &mut Frame, area: Rect, results: &mut SearchResults, theme: &Theme) {
+    let [body, keys] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(area);
+
+    let state = match results.finished {
+        None => String::from("searching"),
+        Some(directories) => format!("{directories} directories searched"),
+    };
+    let errors = match results.errors.len() {
+        0 => String::new(),
+        count => format!(", {count} unreadable"),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.active_frame.style())
+        .title(format!(" Find in {} ", results.vfs.display_path(&results.query.start)))
+        .title_bottom(format!(" {} found, {state}{errors} ", results.results.len()))
+        .style(theme.panel.style());
+    let list = block.inner(body);
+    results.height = list.height as usize;
+    results.scroll_to_cursor();
+
+    let width = list.width as usize;
+    let lines: Vec<Line> = results
+        .results
+        .iter()
+        .enumerate()
+        .skip(results.offset)
+        .take(results.height)
+        .map(|(index, result)| {
+            let text = match &result.found {
+                Some(found) => format!("{}:{}: {}", results.display_path(result), found.line, found.preview),
+                None => results.display_path(result),
+            };
+            let text = format!("{text:<width$.width$}");
+            if index == results.cursor {
+                Line::styled(text, theme.selected.style())
+            } else {
+                Line::raw(text)
+            }
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), body);
+
+    let hints = [("3", "View"), ("g", "Go to"), ("5", "Panelize"), ("Esc", "Stop"), ("10", "Quit")];
+    let spans: Vec<Span> = hints
+        .iter()
+        .flat_map(|(key, label)| {
+            [
+                Span::styled(*key, theme.key_number.style()),
+                Span::styled(format!("{label:<9}"), theme.key_label.style()),
+            ]
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(Line::from(spans)), keys);
+}
+
+impl Screen for SearchResults {
+    fn handle_event(&mut self, event: &AppEvent, manager: &mut PanelManager) -> ScreenAction {
+        let AppEvent::Key(key) = event else {
+            return ScreenAction::Ignored;
+        };
+        let message = |text: String| ScreenAction::Stay(CommandOutcome::Message(text));
+        match self.handle_key(key) {
+            SearchAction::None => ScreenAction::Stay(CommandOutcome::Done),
+            SearchAction::Close => ScreenAction::Close,
+            SearchAction::Jump(path) => match manager.active_mut().reveal(&path) {
+                Ok(()) => ScreenAction::Close,
+                Err(err) => message(format!("{}: {err}", path.display())),
+            },
+            SearchAction::View(result) => {
+                let path = &result.entry.path;
+                let mut viewer = match Viewer::open(&self.vfs, path) {
+                    Ok(viewer) => viewer,
+                    Err(err) => return message(format!("cannot open {}: {err}", path.display())),
+                };
+                if let Some(found) = &result.found
+                    && let Err(err) = viewer.show_match(found.line, found.offset, &found.matched)
+                {
+                    return message(err.to_string());
+                }
+                ScreenAction::Stay(CommandOutcome::View(Box::new(viewer)))
+            }
+            SearchAction::Panelize => {
+                let entries = self.entries();
+                match manager.active_mut().panelize("Search results", &self.query.start, entries) {
+                    Ok(()) => ScreenAction::Close,
+                    Err(err) => message(err.to_string()),
+                }
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        render(frame, area, self, theme);
+    }
+
+    fn help_topic(&self) -> &'static str {
+        "find"
+    }
+
+    fn poll(&mut self) {
+        SearchResults::poll(self);
+    }
+
+    fn is_busy(&self) -> bool {
+        self.is_running()
+    }
+}
@@ -0,0 +1,47 @@
+use ratatui::layout::Rect;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use crate::shell::Shell;
+use crate::ui::completion_view;
+use crate::ui::theme::Theme;
+
+/// render the output of the commands run so far, the end at the bottom
+pub fn render(frame: &mut Frame, area: Rect, shell: &Shell, theme: &Theme) {
+    let height = area.height as usize;
+    let end = shell.output.len().saturating_sub(shell.scroll);
+    let start = end.saturating_sub(height);
+    let lines: Vec<Line> = shell.output[start..end].iter().map(|line| Line::raw(line.as_str())).collect();
+    // short output sits at the bottom, next to the command line like in a terminal
+    let top = area.height.saturating_sub(lines.len() as u16);
+    let body = Rect {
+        y: area.y + top,
+        height: area.height - top,
+        ..area
+    };
+    frame.render_widget(Paragraph::new("").style(theme.panel.style()), area);
+    frame.render_widget(Paragraph::new(lines).style(theme.panel.style()), body);
+}
+
+/// render the command line, the panel location as its prompt
+pub fn render_command_line(frame: &mut Frame, area: Rect, shell: &Shell, location: &str, focused: bool, theme: &Theme) {
+    let prompt = format!("{location}$ ");
+    // a long location leaves at least half of the line to the command
+    let prompt: String = match prompt.chars().count().checked_sub(area.width as usize / 2) {
+        Some(skip) if skip > 0 => prompt.chars().skip(skip).collect(),
+        _ => prompt,
+    };
+    let width = (area.width as usize).saturating_sub(prompt.chars().count());
+    let (text, cursor) = shell.input.visible(width);
+    let x = area.x + prompt.chars().count() as u16 + cursor as u16;
+    frame.render_widget(Paragraph::new(Line::from(vec![Span::raw(prompt.clone()), Span::raw(text)])), area);
+    if focused {
+        frame.set_cursor_position((x, area.y));
+        let anchor = Rect {
+            x: area.x + prompt.chars().count() as u16,
+            ..area
+        };
+        completion_view::render(frame, &shell.completer, anchor, theme);
+    }
+}
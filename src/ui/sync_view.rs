@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Frame;
+
+use crate::app::{Screen, ScreenAction};
+use crate::commands::CommandOutcome;
+use crate::event::AppEvent;
+use crate::format;
+use crate::panel::{PanelManager, PanelSide};
+use crate::sync::{SyncAction, SyncDirection, SyncKeyResult, SyncSession, SyncStage};
+use crate::ui::theme::Theme;
+
+/// render the synchronization plan and its progress over the whole `area`
+pub fn render(frame: &mut Frame, area: Rect, session: &mut SyncSession, theme: &Theme) {
+    let [body, status] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(area);
+    let plan = Arc::clone(&session.plan);
+    let arrow = match plan.options.direction {
+        SyncDirection::LeftToRight => "->",
+        SyncDirection::RightToLeft => "<-",
+        SyncDirection::Both => "<->",
+    };
+    let dry_run = if plan.options.dry_run { " (dry run)" } else { "" };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.active_frame.style())
+        .title(format!(
+            " Synchronize {} {arrow} {}{dry_run} ",
+            plan.left.0.display_path(&plan.left.1),
+            plan.right.0.display_path(&plan.right.1)
+        ))
+        .title_bottom(format!(" {} actions ", plan.actions.len()))
+        .style(theme.panel.style());
+    let list = block.inner(body);
+    session.height = list.height as usize;
+    session.scroll_to_cursor();
+
+    let width = list.width as usize;
+    let reviewing = matches!(session.stage, SyncStage::Review);
+    let lines: Vec<Line> = plan
+        .actions
+        .iter()
+        .enumerate()
+        .skip(session.offset)
+        .take(session.height)
+        .map(|(index, action)| {
+            let text = format!("{:<width$.width$}", describe(action));
+            if reviewing && index == session.cursor {
+                Line::styled(text, theme.selected.style())
+            } else if matches!(action, SyncAction::Copy { conflict: true, .. }) {
+                Line::styled(text, theme.marked.style())
+            } else {
+                Line::raw(text)
+            }
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), body);
+
+    let (copied, total, current) = &session.progress;
+    let line = match &session.stage {
+        SyncStage::Review if plan.actions.is_empty() => Line::raw("Nothing to do, Esc to close"),
+        SyncStage::Review => key_hints(&[("Enter", "Run"), ("Esc", "Cancel")], theme),
+        SyncStage::Running => Line::raw(format!(
+            "{} / {}  {}",
+            format::size(*copied, session.units),
+            format::size(*total, session.units),
+            current.display()
+        )),
+        SyncStage::Conflict(action) => Line::styled(
+            format!(
+                "{} is not older on the other side: (o)verwrite, (s)kip, overwrite (a)ll, skip all (n), Esc abort",
+                action.relative().display()
+            ),
+            theme.error.style(),
+        ),
+        SyncStage::Finished(Ok(report)) => Line::raw(format!(
+            "{} copied, {} deleted, {} skipped{}, Esc to close",
+            report.copied,
+            report.deleted,
+            report.skipped,
+            if report.aborted { ", aborted" } else { "" }
+        )),
+        SyncStage::Finished(Err(err)) => Line::styled(format!("{err}, Esc to close"), theme.error.style()),
+    };
+    frame.render_widget(Paragraph::new(line), status);
+}
+
+/// one line of the plan
+fn describe(action: &SyncAction) -> String {
+    match action {
+        SyncAction::Copy {
+            from, relative, conflict, ..
+        } => {
+            let arrow = if *from == PanelSide::Left { "->" } else { "<-" };
+            let mark = if *conflict { '!' } else { ' ' };
+            format!("{mark} copy   {arrow} {}", relative.display())
+        }
+        SyncAction::Delete { side, relative } => {
+            let side = if *side == PanelSide::Left { "left " } else { "right" };
+            format!("  delete {side} {}", relative.display())
+        }
+    }
+}
+
+fn key_hints<'a>(hints: &[(&'a str, &'a str)], theme: &Theme) -> Line<'a> {
+    let spans: Vec<Span> = hints
+        .iter()
+        .flat_map(|(key, label)| {
+            [
+                Span::styled(*key, theme.key_number.style()),
+                Span::styled(format!("{label:<9}"), theme.key_label.style()),
+            ]
+        })
+        .collect();
+    Line::from(spans)
+}
+
+impl Screen for SyncSession {
+    fn handle_event(&mut self, event: &AppEvent, _manager: &mut PanelManager) -> ScreenAction {
+        let AppEvent::Key(key) = event else {
+            return ScreenAction::Ignored;
+        };
+        match self.handle_key(key) {
+            SyncKeyResult::Close => ScreenAction::Close,
+            SyncKeyResult::None => ScreenAction::Stay(CommandOutcome::Done),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        render(frame, area, self, theme);
+    }
+
+    fn help_topic(&self) -> &'static str {
+        "compare"
+    }
+
+    fn poll(&mut self) {
+        SyncSession::poll(self);
+    }
+
+    fn is_busy(&self) -> bool {
+        self.is_running()
+    }
+
+    /// both sides may have changed
+    fn closed(&mut self, manager: &mut PanelManager) {
+        let _ = manager.left.reload();
+        let _ = manager.right.reload();
+    }
+}
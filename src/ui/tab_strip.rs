@@ -0,0 +1,57 @@
+use ratatui::layout::Rect;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use crate::panel::{Panel, PanelTabs};
+use crate::ui::theme::Theme;
+
+/// longest tab name shown, longer ones are cut
+const NAME_WIDTH: usize = 16;
+
+/// render the tabs of a side on one line, the shown tab highlighted
+pub fn render(frame: &mut Frame, area: Rect, shown: &Panel, tabs: &PanelTabs, active: bool, theme: &Theme) {
+    let current = match active {
+        true => theme.selected.style(),
+        false => theme.active_frame.style(),
+    };
+    let spans: Vec<Span> = tabs
+        .iter(shown)
+        .enumerate()
+        .map(|(index, panel)| {
+            let text = format!(" {} ", tab_name(&panel.display_location()));
+            match index == tabs.index {
+                true => Span::styled(text, current),
+                false => Span::styled(text, theme.panel_frame.style()),
+            }
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(Line::from(spans)).style(theme.panel.style()), area);
+}
+
+/// index of the tab drawn at `column`
+pub fn tab_at(area: Rect, shown: &Panel, tabs: &PanelTabs, column: u16) -> Option<usize> {
+    let mut start = area.x;
+    for (index, panel) in tabs.iter(shown).enumerate() {
+        // a space on each side of the name
+        let end = start + tab_name(&panel.display_location()).chars().count() as u16 + 2;
+        if (start..end).contains(&column) {
+            return Some(index);
+        }
+        start = end;
+    }
+    None
+}
+
+/// last component of a location, cut to fit on the strip
+fn tab_name(location: &str) -> String {
+    let trimmed = location.trim_end_matches('/');
+    let name = match trimmed.rsplit('/').next() {
+        Some(name) if !name.is_empty() => name,
+        _ => location,
+    };
+    match name.chars().count() > NAME_WIDTH {
+        true => format!("{}~", name.chars().take(NAME_WIDTH - 1).collect::<String>()),
+        false => name.to_string(),
+    }
+}
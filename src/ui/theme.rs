@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+use crate::filetype::{FileClass, FileKind};
+use crate::highlight::DEFAULT_SYNTAX_THEME;
+
+/// colors of one role, unset colors keep the terminal default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoleStyle {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+}
+
+impl RoleStyle {
+    pub const fn new(fg: Color, bg: Color) -> RoleStyle {
+        RoleStyle {
+            fg: Some(fg),
+            bg: Some(bg),
+            bold: false,
+        }
+    }
+
+    pub const fn fg(fg: Color) -> RoleStyle {
+        RoleStyle {
+            fg: Some(fg),
+            bg: None,
+            bold: false,
+        }
+    }
+
+    pub const fn bold(self) -> RoleStyle {
+        RoleStyle { bold: true, ..self }
+    }
+
+    pub fn style(&self) -> Style {
+        let mut style = Style::default();
+        if let Some(fg) = self.fg {
+            style = style.fg(fg);
+        }
+        if let Some(bg) = self.bg {
+            style = style.bg(bg);
+        }
+        if self.bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        style
+    }
+}
+
+/// named color roles used by every widget
+///
+/// Skins are toml or json files with the same keys, roles they leave out
+/// come from the classic theme.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    /// text and background of the file lists
+    pub panel: RoleStyle,
+    pub panel_frame: RoleStyle,
+    pub active_frame: RoleStyle,
+    pub header: RoleStyle,
+    /// row under the cursor of the active panel
+    pub selected: RoleStyle,
+    pub marked: RoleStyle,
+    /// directory or panel that entries dragged with the mouse would be dropped on
+    pub drop_target: RoleStyle,
+    pub directory: RoleStyle,
+    pub executable: RoleStyle,
+    pub symlink: RoleStyle,
+    pub broken_symlink: RoleStyle,
+    pub archive: RoleStyle,
+    pub image: RoleStyle,
+    /// audio and video files
+    pub media: RoleStyle,
+    /// colors of files by lowercase extension, before those of their family
+    pub extensions: BTreeMap<String, RoleStyle>,
+    pub dialog: RoleStyle,
+    pub error: RoleStyle,
+    pub key_number: RoleStyle,
+    pub key_label: RoleStyle,
+    /// syntect theme coloring source files in the viewer and the editor
+    pub syntax: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
+    }
+}
+
+/// names of the themes compiled into the program
+pub const BUILTIN_THEMES: [&str; 2] = ["classic", "dark"];
+
+impl Theme {
+    /// white on blue, like the original Midnight Commander
+    pub fn classic() -> Theme {
+        Theme {
+            name: String::from("classic"),
+            panel: RoleStyle::new(Color::Gray, Color::Blue),
+            panel_frame: RoleStyle::fg(Color::Gray),
+            active_frame: RoleStyle::fg(Color::White).bold(),
+            header: RoleStyle::fg(Color::Yellow).bold(),
+            selected: RoleStyle::new(Color::Black, Color::Cyan),
+            marked: RoleStyle::fg(Color::Yellow).bold(),
+            drop_target: RoleStyle::new(Color::Black, Color::LightGreen),
+            directory: RoleStyle::fg(Color::White).bold(),
+            executable: RoleStyle::fg(Color::LightGreen).bold(),
+            symlink: RoleStyle::fg(Color::LightCyan),
+            broken_symlink: RoleStyle::fg(Color::LightRed),
+            archive: RoleStyle::fg(Color::LightMagenta),
+            image: RoleStyle::fg(Color::Magenta),
+            media: RoleStyle::fg(Color::Yellow),
+            extensions: BTreeMap::new(),
+            dialog: RoleStyle::new(Color::Black, Color::Gray),
+            error: RoleStyle::new(Color::White, Color::Red).bold(),
+            key_number: RoleStyle::fg(Color::White),
+            key_label: RoleStyle::new(Color::Black, Color::Cyan),
+            syntax: String::from(DEFAULT_SYNTAX_THEME),
+        }
+    }
+
+    /// terminal background with muted colors
+    pub fn dark() -> Theme {
+        Theme {
+            name: String::from("dark"),
+            panel: RoleStyle::default(),
+            panel_frame: RoleStyle::fg(Color::DarkGray),
+            active_frame: RoleStyle::fg(Color::Yellow).bold(),
+            header: RoleStyle::fg(Color::Gray).bold(),
+            selected: RoleStyle::new(Color::Black, Color::Gray),
+            marked: RoleStyle::fg(Color::Yellow).bold(),
+            drop_target: RoleStyle::new(Color::Black, Color::Green),
+            directory: RoleStyle::fg(Color::LightBlue).bold(),
+            executable: RoleStyle::fg(Color::Green),
+            symlink: RoleStyle::fg(Color::Cyan),
+            broken_symlink: RoleStyle::fg(Color::Red),
+            archive: RoleStyle::fg(Color::Magenta),
+            image: RoleStyle::fg(Color::LightMagenta),
+            media: RoleStyle::fg(Color::Yellow),
+            extensions: BTreeMap::new(),
+            dialog: RoleStyle::new(Color::White, Color::DarkGray),
+            error: RoleStyle::fg(Color::LightRed).bold(),
+            key_number: RoleStyle::fg(Color::Gray),
+            key_label: RoleStyle::new(Color::Black, Color::DarkGray),
+            syntax: String::from("base16-eighties.dark"),
+        }
+    }
+
+    /// color of an entry of the file lists
+    ///
+    /// Directories and links keep their role whatever their name, other files
+    /// take the color of their extension if the theme has one.
+    pub fn file_style(&self, class: &FileClass) -> Style {
+        let by_extension = class.extension.as_ref().and_then(|extension| self.extensions.get(extension));
+        let role = match (class.kind, by_extension) {
+            (FileKind::Directory, _) => self.directory,
+            (FileKind::Symlink, _) => self.symlink,
+            (FileKind::BrokenSymlink, _) => self.broken_symlink,
+            (_, Some(role)) => *role,
+            (FileKind::Executable, None) => self.executable,
+            (FileKind::Archive, None) => self.archive,
+            (FileKind::Image, None) => self.image,
+            (FileKind::Audio | FileKind::Video, None) => self.media,
+            (FileKind::Document | FileKind::Source | FileKind::Plain, None) => return Style::default(),
+        };
+        role.style()
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "classic" => Some(Theme::classic()),
+            "dark" => Some(Theme::dark()),
+            _ => None,
+        }
+    }
+
+    /// a built-in theme or a skin from the skins directory, by name
+    pub fn named(name: &str) -> io::Result<Theme> {
+        if let Some(theme) = Theme::builtin(name) {
+            return Ok(theme);
+        }
+        let dir = skins_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no skins directory"))?;
+        for extension in ["toml", "json"] {
+            let path = dir.join(format!("{name}.{extension}"));
+            if path.exists() {
+                return Theme::load(&path);
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::NotFound, format!("unknown theme {name}")))
+    }
+
+    /// read a skin file, its format is chosen from the extension
+    pub fn load(path: &Path) -> io::Result<Theme> {
+        let text = fs::read_to_string(path)?;
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {message}", path.display()));
+        let mut theme: Theme = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_str(&text).map_err(|err| invalid(err.to_string()))?,
+            _ => toml::from_str(&text).map_err(|err| invalid(err.message().to_string()))?,
+        };
+        if let Some(stem) = path.file_stem()
+            && theme.name == Theme::classic().name
+        {
+            theme.name = stem.to_string_lossy().into_owned();
+        }
+        Ok(theme)
+    }
+}
+
+/// directory holding user skins, next to the configuration file
+pub fn skins_dir() -> Option<PathBuf> {
+    crate::config::Config::default_path()
+        .and_then(|path| path.parent().map(|dir| dir.join("skins")))
+}
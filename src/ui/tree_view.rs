@@ -0,0 +1,55 @@
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Paragraph};
+use ratatui::Frame;
+
+use crate::tree::TreeView;
+use crate::ui::panel_view::UNREADABLE_MARK;
+use crate::ui::theme::Theme;
+
+/// render a directory tree in place of a panel listing
+pub fn render(frame: &mut Frame, area: Rect, tree: &mut TreeView, active: bool, theme: &Theme) {
+    let frame_style = if active {
+        theme.active_frame.style()
+    } else {
+        theme.panel_frame.style()
+    };
+    let block = Block::default()
+        .style(theme.panel.style())
+        .borders(Borders::ALL)
+        .border_type(if active { BorderType::Double } else { BorderType::Plain })
+        .border_style(frame_style)
+        .title(Span::styled(" Tree ", frame_style));
+    let height = block.inner(area).height as usize;
+    tree.scroll_to_cursor(height);
+
+    let cursor_style = if active {
+        theme.selected.style()
+    } else {
+        Style::default()
+    };
+    let lines: Vec<Line> = tree
+        .rows()
+        .enumerate()
+        .skip(tree.offset)
+        .take(height)
+        .map(|(index, row)| {
+            let node = row.node.read();
+            let mut name = if row.depth == 0 {
+                node.vfs.display_path(&node.path())
+            } else {
+                node.name.to_string()
+            };
+            if node.error.is_some() {
+                name.push_str(UNREADABLE_MARK);
+            }
+            let style = if index == tree.cursor { cursor_style } else { theme.directory.style() };
+            Line::from(vec![
+                Span::styled(row.prefix, theme.panel_frame.style()),
+                Span::styled(name, style),
+            ])
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
@@ -0,0 +1,131 @@
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Frame;
+
+use crate::app::{Screen, ScreenAction};
+use crate::commands::CommandOutcome;
+use crate::event::AppEvent;
+use crate::format;
+use crate::fs_node::FsNodeType;
+use crate::panel::PanelManager;
+use crate::ui::theme::Theme;
+use crate::usage::{DiskUsage, UsageAction};
+
+/// cells of the bar showing the share of an entry
+const BAR_WIDTH: usize = 20;
+
+/// render the disk usage of the listed directory over the whole `area`
+pub fn render(frame: &mut Frame, area: Rect, usage: &mut DiskUsage, theme: &Theme) {
+    let [body, status] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(area);
+
+    let location = match &usage.current {
+        Some(current) => usage.vfs.display_path(&current.read().path()),
+        None => usage.vfs.display_path(&usage.start),
+    };
+    let total = usage.total();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.active_frame.style())
+        .title(format!(" Disk usage of {location} "))
+        .title_bottom(format!(" {} in {} entries ", format::size(total, usage.units), usage.entries().len()))
+        .style(theme.panel.style());
+    let list = block.inner(body);
+    usage.height = list.height as usize;
+    usage.scroll_to_cursor();
+
+    let width = list.width as usize;
+    let lines: Vec<Line> = usage
+        .entries()
+        .iter()
+        .enumerate()
+        .skip(usage.offset)
+        .take(usage.height)
+        .map(|(index, node)| {
+            let node = node.read();
+            let size = node.size().unwrap_or(0);
+            let share = if total == 0 { 0.0 } else { size as f64 / total as f64 };
+            let filled = (share * BAR_WIDTH as f64).round() as usize;
+            let name = match node.node_type {
+                FsNodeType::Directory => format!("/{}", node.name),
+                _ => node.name.to_string(),
+            };
+            let text = format!(
+                "{:>10} {:>5.1}% [{}{}] {name}",
+                format::size(size, usage.units),
+                share * 100.0,
+                "#".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled)
+            );
+            let text = format!("{text:<width$.width$}");
+            if index == usage.cursor {
+                Line::styled(text, theme.selected.style())
+            } else {
+                Line::styled(text, theme.file_style(node.class()))
+            }
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), body);
+
+    let line = match (&usage.confirm, &usage.error) {
+        (Some(node), _) => {
+            let node = node.read();
+            let size = format::size(node.size().unwrap_or(0), usage.units);
+            Line::styled(format!("Delete {} and its {size} for good? (y)es, any other key to keep it", node.name), theme.error.style())
+        }
+        (None, Some(err)) => Line::styled(err.clone(), theme.error.style()),
+        (None, None) if usage.is_running() => Line::raw(format!("scanning {}", usage.vfs.display_path(&usage.start))),
+        (None, None) => {
+            let hints = [("Enter", "Open"), ("Bksp", "Up"), ("g", "Go to"), ("8", "Delete"), ("10", "Quit")];
+            let spans: Vec<Span> = hints
+                .iter()
+                .flat_map(|(key, label)| {
+                    [
+                        Span::styled(*key, theme.key_number.style()),
+                        Span::styled(format!("{label:<9}"), theme.key_label.style()),
+                    ]
+                })
+                .collect();
+            Line::from(spans)
+        }
+    };
+    frame.render_widget(Paragraph::new(line), status);
+}
+
+impl Screen for DiskUsage {
+    fn handle_event(&mut self, event: &AppEvent, manager: &mut PanelManager) -> ScreenAction {
+        let AppEvent::Key(key) = event else {
+            return ScreenAction::Ignored;
+        };
+        match self.handle_key(key) {
+            UsageAction::None => ScreenAction::Stay(CommandOutcome::Done),
+            UsageAction::Close => ScreenAction::Close,
+            UsageAction::Jump(path) => match manager.active_mut().reveal(&path) {
+                Ok(()) => ScreenAction::Close,
+                Err(err) => ScreenAction::Stay(CommandOutcome::Message(format!("{}: {err}", path.display()))),
+            },
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        render(frame, area, self, theme);
+    }
+
+    fn help_topic(&self) -> &'static str {
+        "usage"
+    }
+
+    fn poll(&mut self) {
+        DiskUsage::poll(self);
+    }
+
+    fn is_busy(&self) -> bool {
+        self.is_running()
+    }
+
+    /// files may have been deleted
+    fn closed(&mut self, manager: &mut PanelManager) {
+        let _ = manager.left.reload();
+        let _ = manager.right.reload();
+    }
+}
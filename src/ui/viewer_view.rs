@@ -0,0 +1,188 @@
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use crate::app::{Screen, ScreenAction};
+use crate::commands::{CommandOutcome, PromptKind};
+use crate::event::AppEvent;
+use crate::highlight::LineStyles;
+use crate::panel::PanelManager;
+use crate::ui::theme::Theme;
+use crate::viewer::{ViewRow, Viewer, ViewerAction, HEX_WIDTH};
+
+/// render the viewer over the whole `area`, a status line on top and a key hint at the bottom
+pub fn render(frame: &mut Frame, area: Rect, viewer: &mut Viewer, theme: &Theme) {
+    let [status, body, keys] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)]).areas(area);
+    viewer.height = body.height as usize;
+    viewer.width = body.width as usize;
+
+    let rows = match viewer.rows(viewer.height) {
+        Ok(rows) => rows,
+        Err(err) => {
+            frame.render_widget(Paragraph::new(err.to_string()).style(theme.error.style()), body);
+            Vec::new()
+        }
+    };
+    // the visible rows are highlighted on their own, a comment opened above the top is missed
+    let syntax = match viewer.highlighter.as_mut() {
+        Some(highlighter) if !viewer.hex => {
+            let texts: Vec<String> = rows.iter().map(|row| row.cells.iter().map(|(c, _)| c).collect()).collect();
+            highlighter.invalidate(0);
+            highlighter.highlight(&texts, 0, texts.len())
+        }
+        _ => Vec::new(),
+    };
+    let lines: Vec<Line> = rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            if viewer.hex {
+                render_hex_row(row, viewer, theme)
+            } else {
+                render_row(row, viewer, theme, syntax.get(index))
+            }
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).style(theme.panel.style()), body);
+
+    let position = match (viewer.known_len(), viewer.percent()) {
+        (Some(len), Some(percent)) => format!("{}/{} {percent}%", viewer.top, len),
+        _ => format!("{}", viewer.top),
+    };
+    let mode = match (viewer.hex, viewer.wrap) {
+        (true, _) => "hex",
+        (false, true) => "wrap",
+        (false, false) => "nowrap",
+    };
+    let width = (status.width as usize).saturating_sub(position.len() + mode.len() + 2);
+    let title = format!("{:<width$.width$} {mode} {position}", viewer.title);
+    frame.render_widget(Paragraph::new(title).style(theme.header.style()), status);
+
+    let hints = [
+        ("2", "Wrap"),
+        ("3", "Quit"),
+        ("4", if viewer.hex { "Ascii" } else { "Hex" }),
+        ("5", "Goto"),
+        ("7", "Search"),
+        ("n", "Next"),
+    ];
+    let spans: Vec<Span> = hints
+        .iter()
+        .flat_map(|(key, label)| {
+            [
+                Span::styled(*key, theme.key_number.style()),
+                Span::styled(format!("{label:<8}"), theme.key_label.style()),
+            ]
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(Line::from(spans)), keys);
+}
+
+/// offset, hex bytes in two groups of eight and the printable characters
+fn render_hex_row<'a>(row: &ViewRow, viewer: &Viewer, theme: &Theme) -> Line<'a> {
+    let found = viewer.found.map(|(offset, len)| offset..offset + len as u64);
+    let highlight = theme.selected.style().add_modifier(Modifier::BOLD);
+    let normal = theme.panel.style();
+    let style_of = |offset: &u64| {
+        if found.as_ref().is_some_and(|found| found.contains(offset)) { highlight } else { normal }
+    };
+
+    let mut spans = vec![Span::styled(format!("{:08x}  ", row.start), theme.header.style())];
+    for index in 0..HEX_WIDTH as usize {
+        let text = match row.cells.get(index) {
+            Some((byte, _)) => format!("{:02x}", *byte as u32),
+            None => String::from("  "),
+        };
+        let style = row.cells.get(index).map(|(_, offset)| style_of(offset)).unwrap_or(normal);
+        spans.push(Span::styled(text, style));
+        spans.push(Span::styled(if index == 7 { "  " } else { " " }, normal));
+    }
+    spans.push(Span::styled(" ", normal));
+    for (byte, offset) in &row.cells {
+        let c = match *byte as u32 {
+            0x20..=0x7e => *byte,
+            _ => '.',
+        };
+        spans.push(Span::styled(c.to_string(), style_of(offset)));
+    }
+    Line::from(spans)
+}
+
+fn render_row<'a>(row: &ViewRow, viewer: &Viewer, theme: &Theme, syntax: Option<&LineStyles>) -> Line<'a> {
+    let skip = if viewer.wrap { 0 } else { viewer.left };
+    let found = viewer.found.map(|(offset, len)| offset..offset + len as u64);
+    let highlight = theme.selected.style().add_modifier(Modifier::BOLD);
+
+    // group consecutive cells of the same style into spans
+    let mut spans: Vec<Span> = Vec::new();
+    let mut text = String::new();
+    let mut current = None;
+    let mut byte = 0;
+    for (index, (c, offset)) in row.cells.iter().enumerate() {
+        let position = byte;
+        byte += c.len_utf8();
+        if index < skip {
+            continue;
+        }
+        let style = if found.as_ref().is_some_and(|found| found.contains(offset)) {
+            highlight
+        } else {
+            syntax_style(syntax, position).map_or(theme.panel.style(), |style| theme.panel.style().patch(style))
+        };
+        if current != Some(style) && !text.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut text), current.unwrap_or_default()));
+        }
+        current = Some(style);
+        text.push(*c);
+    }
+    if !text.is_empty() {
+        spans.push(Span::styled(text, current.unwrap_or_default()));
+    }
+    Line::from(spans)
+}
+
+/// syntax color of the byte at `position` of a line
+pub fn syntax_style(syntax: Option<&LineStyles>, position: usize) -> Option<Style> {
+    syntax?
+        .iter()
+        .find(|(_, range)| range.contains(&position))
+        .map(|(style, _)| *style)
+}
+
+impl Screen for Viewer {
+    fn handle_event(&mut self, event: &AppEvent, _manager: &mut PanelManager) -> ScreenAction {
+        let AppEvent::Key(key) = event else {
+            return ScreenAction::Ignored;
+        };
+        match self.handle_key(key) {
+            ViewerAction::Close => ScreenAction::Close,
+            action => ScreenAction::Stay(outcome(action)),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        render(frame, area, self, theme);
+    }
+
+    fn help_topic(&self) -> &'static str {
+        "viewer"
+    }
+
+    fn submit(&mut self, kind: PromptKind, input: &str) -> Option<CommandOutcome> {
+        let PromptKind::Viewer(asked) = kind else {
+            return None;
+        };
+        Some(outcome(Viewer::submit(self, asked, input)))
+    }
+}
+
+fn outcome(action: ViewerAction) -> CommandOutcome {
+    match action {
+        ViewerAction::None | ViewerAction::Close => CommandOutcome::Done,
+        ViewerAction::Prompt(prompt) => CommandOutcome::Prompt(prompt),
+        ViewerAction::Message(text) => CommandOutcome::Message(text),
+    }
+}
@@ -0,0 +1,190 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::format::SizeUnits;
+use crate::fs_error::FsError;
+use crate::fs_node::{FsNode, FsNodeRef, FsNodeType};
+use crate::ops;
+use crate::scan;
+use crate::vfs::VfsRef;
+
+/// what a key did to the disk usage analyzer
+#[derive(Debug, Clone, PartialEq)]
+pub enum UsageAction {
+    None,
+    Close,
+    /// show the entry in the active panel
+    Jump(PathBuf),
+}
+
+/// the sizes of a whole subtree, each directory listed biggest entry first like ncdu
+///
+/// The subtree is scanned once on a background thread. Directories then
+/// hold the sum of their content as their computed size, drilling down and
+/// deleting only walk the tree in memory.
+#[derive(Debug)]
+pub struct DiskUsage {
+    pub vfs: VfsRef,
+    pub start: PathBuf,
+    /// the directory listed, none until the scan is over
+    pub current: Option<FsNodeRef>,
+    pub cursor: usize,
+    pub offset: usize,
+    /// rows of the list, set by the renderer
+    pub height: usize,
+    /// entry to delete once the user confirms it
+    pub confirm: Option<FsNodeRef>,
+    /// why the scan or the last deletion failed
+    pub error: Option<String>,
+    pub units: SizeUnits,
+    scan: Option<Receiver<Result<FsNode, FsError>>>,
+}
+
+impl DiskUsage {
+    /// scan `start`, leaving out what git ignores if `gitignore` is set
+    pub fn start(vfs: VfsRef, start: &Path, units: SizeUnits, gitignore: bool) -> DiskUsage {
+        let (sender, scan) = mpsc::channel();
+        let scanned = Arc::clone(&vfs);
+        let path = start.to_path_buf();
+        thread::spawn(move || {
+            let scan = if gitignore { scan::scan_ignoring } else { scan::scan };
+            let _ = sender.send(scan(&scanned, &path, scan::default_threads()));
+        });
+        DiskUsage {
+            vfs,
+            start: start.to_path_buf(),
+            current: None,
+            cursor: 0,
+            offset: 0,
+            height: 0,
+            confirm: None,
+            error: None,
+            units,
+            scan: Some(scan),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    /// take the tree once the scan is over and sum it up
+    pub fn poll(&mut self) {
+        let Some(result) = self.scan.as_ref().and_then(|scan| scan.try_recv().ok()) else {
+            return;
+        };
+        self.scan = None;
+        match result {
+            Ok(root) => {
+                let root = root.into_ref();
+                sum_sizes(&root);
+                self.current = Some(root);
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+
+    /// the entries of the listed directory, biggest first
+    pub fn entries(&self) -> Vec<FsNodeRef> {
+        self.current.as_ref().map(|current| current.read().children.clone()).unwrap_or_default()
+    }
+
+    /// size of the listed directory, what the entries are a part of
+    pub fn total(&self) -> u64 {
+        self.current.as_ref().and_then(|current| current.read().size()).unwrap_or(0)
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> UsageAction {
+        if let Some(node) = self.confirm.take() {
+            if let KeyCode::Char('y') | KeyCode::Char('Y') = key.code {
+                self.delete(&node);
+            }
+            return UsageAction::None;
+        }
+        let page = self.height.saturating_sub(1).max(1);
+        let last = self.entries().len().saturating_sub(1);
+        let selected = self.entries().get(self.cursor).cloned();
+        match key.code {
+            KeyCode::Esc | KeyCode::F(10) | KeyCode::Char('q') => return UsageAction::Close,
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor = (self.cursor + 1).min(last),
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(page),
+            KeyCode::PageDown => self.cursor = (self.cursor + page).min(last),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = last,
+            KeyCode::Enter | KeyCode::Right => {
+                if let Some(node) = selected
+                    && node.read().node_type == FsNodeType::Directory
+                {
+                    self.current = Some(node);
+                    self.cursor = 0;
+                    self.offset = 0;
+                }
+            }
+            KeyCode::Backspace | KeyCode::Left => self.go_up(),
+            KeyCode::F(8) | KeyCode::Delete | KeyCode::Char('d') => self.confirm = selected,
+            KeyCode::Char('g') => {
+                if let Some(node) = selected {
+                    return UsageAction::Jump(node.read().path());
+                }
+            }
+            _ => {}
+        }
+        UsageAction::None
+    }
+
+    /// list the parent of the listed directory with the cursor on where we were, not above the start
+    fn go_up(&mut self) {
+        let Some(current) = self.current.clone() else {
+            return;
+        };
+        let Some(parent) = current.read().parent() else {
+            return;
+        };
+        self.cursor = parent.read().children.iter().position(|child| Arc::ptr_eq(child, &current)).unwrap_or(0);
+        self.current = Some(parent);
+    }
+
+    /// remove an entry from the disk and take its size off the directories holding it
+    fn delete(&mut self, node: &FsNodeRef) {
+        let parent = node.read().parent();
+        self.error = match ops::delete_node(node, &mut ops::delete::confirm_all) {
+            Ok(true) => None,
+            Ok(false) => Some(format!("{} was not removed whole", node.read().path().display())),
+            Err(err) => Some(format!("{}: {err}", node.read().path().display())),
+        };
+        let mut ancestor = parent;
+        while let Some(directory) = ancestor {
+            let size = directory.read().children.iter().filter_map(|child| child.read().size()).sum();
+            directory.write().computed_size = Some(size);
+            ancestor = directory.read().parent();
+        }
+        self.cursor = self.cursor.min(self.entries().len().saturating_sub(1));
+    }
+
+    /// adjust the scroll offset so the cursor is visible
+    pub fn scroll_to_cursor(&mut self) {
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.height > 0 && self.cursor >= self.offset + self.height {
+            self.offset = self.cursor + 1 - self.height;
+        }
+    }
+}
+
+/// set the computed size of every directory of the subtree and sort their children biggest first
+fn sum_sizes(node: &FsNodeRef) -> u64 {
+    let mut node = node.write();
+    if node.node_type != FsNodeType::Directory {
+        return node.size().unwrap_or(0);
+    }
+    let size = node.children.iter().map(sum_sizes).sum();
+    node.children.sort_by_cached_key(|child| std::cmp::Reverse(child.read().size().unwrap_or(0)));
+    node.children_changed();
+    node.computed_size = Some(size);
+    size
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::filetype;
+use crate::fs_node::{FsNode, FsNodeType};
+
+/// an entry of the user menu, a shell script offered for the files meeting its conditions
+///
+/// The script is run by the user's shell in the directory of the panel,
+/// with the macros of the command line: `%f` the file under the cursor, `%d`
+/// the directory, `%s` the selected files or else the cursor file, `%t`
+/// the selected files only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MenuEntry {
+    pub label: String,
+    /// glob patterns on the name of the file under the cursor, matched without regard to case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+    /// `file`, `directory`, `symlink` or MIME types like `image/*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    /// the script, of one or several lines
+    pub command: String,
+}
+
+impl MenuEntry {
+    fn new(label: &str, patterns: &[&str], types: &[&str], command: &str) -> MenuEntry {
+        MenuEntry {
+            label: label.to_string(),
+            patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+            types: types.iter().map(|kind| kind.to_string()).collect(),
+            command: command.to_string(),
+        }
+    }
+
+    /// true if the entry is offered for the file under the cursor, every entry without conditions
+    ///
+    /// Both conditions must hold when both are given.
+    pub fn applies_to(&self, node: &FsNode) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..MatchOptions::new()
+        };
+        let named = self.patterns.is_empty()
+            || self
+                .patterns
+                .iter()
+                .filter_map(|pattern| Pattern::new(pattern).ok())
+                .any(|pattern| pattern.matches_with(&node.name, options));
+        let kind = match node.node_type {
+            FsNodeType::File => "file",
+            FsNodeType::Directory => "directory",
+            FsNodeType::Symlink { .. } => "symlink",
+        };
+        let typed = self.types.is_empty()
+            || self.types.iter().any(|pattern| match pattern.contains('/') {
+                true => !node.is_navigable() && filetype::mime_matches(pattern, node.mime_type()),
+                false => pattern == kind,
+            });
+        named && typed
+    }
+}
+
+/// the user menu of F2, like the `mc.menu` of Midnight Commander
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserMenu {
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<MenuEntry>,
+}
+
+impl Default for UserMenu {
+    fn default() -> Self {
+        UserMenu {
+            entries: vec![
+                MenuEntry::new("Disk usage of the selection", &[], &[], "du -sh -- %s\nprintf 'Press Enter '\nread -r _"),
+                MenuEntry::new("Compress the selection to a tar.gz", &[], &[], "tar -czvf \"$(basename %d).tar.gz\" -- %s"),
+                MenuEntry::new(
+                    "Extract the archive here",
+                    &["*.tar", "*.tar.gz", "*.tgz", "*.tar.bz2", "*.tar.xz", "*.tar.zst"],
+                    &["file"],
+                    "tar -xvf %f",
+                ),
+                MenuEntry::new("Extract the zip here", &["*.zip", "*.jar"], &["file"], "unzip %f"),
+                MenuEntry::new("Make executable", &[], &["file"], "chmod +x -- %f"),
+                MenuEntry::new("Count the lines of the selection", &[], &[], "wc -l -- %s\nprintf 'Press Enter '\nread -r _"),
+            ],
+        }
+    }
+}
+
+impl UserMenu {
+    /// `menu.toml` in the directory of the default configuration
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::default_path()?.with_file_name("menu.toml"))
+    }
+
+    /// read the menu of a file, a missing file gives the default entries
+    pub fn load(path: &Path) -> io::Result<UserMenu> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(UserMenu::default()),
+            Err(err) => return Err(err),
+        };
+        toml::from_str(&text).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err.message()))
+        })
+    }
+
+    /// the entries offered for the file under the cursor, all those without conditions when there is none
+    pub fn entries_for(&self, node: Option<&FsNode>) -> Vec<&MenuEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| match node {
+                Some(node) => entry.applies_to(node),
+                None => entry.patterns.is_empty() && entry.types.is_empty(),
+            })
+            .collect()
+    }
+}
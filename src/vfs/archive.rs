@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::FsNodeType;
+use crate::vfs::VfsEntry;
+
+/// member of an archive as recorded in the index
+#[derive(Debug, Clone)]
+pub struct ArchiveMember {
+    pub node_type: FsNodeType,
+    pub metadata: FsMetadata,
+    /// position of the member in the archive, backend specific
+    pub position: usize,
+}
+
+/// listing of an archive, built once when the archive is opened
+///
+/// Members are addressed by paths below `root`, the path of the archive file
+/// itself, so `/home/me/a.zip/dir/file` is the member `dir/file`.
+#[derive(Debug)]
+pub struct ArchiveIndex {
+    root: PathBuf,
+    root_metadata: FsMetadata,
+    members: BTreeMap<PathBuf, ArchiveMember>,
+    children: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+}
+
+impl ArchiveIndex {
+    pub fn new(root: PathBuf, root_metadata: FsMetadata) -> ArchiveIndex {
+        let mut children = BTreeMap::new();
+        children.insert(PathBuf::new(), BTreeSet::new());
+        ArchiveIndex {
+            root,
+            root_metadata,
+            members: BTreeMap::new(),
+            children,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// record a member, missing parent directories are created implicitly
+    pub fn insert(&mut self, name: &str, member: ArchiveMember) {
+        let Some(relative) = sanitize(name) else {
+            return;
+        };
+        if relative.as_os_str().is_empty() {
+            return;
+        }
+        self.add_parents(&relative, member.metadata.modified);
+        if member.node_type == FsNodeType::Directory {
+            self.children.entry(relative.clone()).or_default();
+        }
+        self.members.insert(relative, member);
+    }
+
+    fn add_parents(&mut self, relative: &Path, modified: Option<SystemTime>) {
+        let mut child = relative.to_path_buf();
+        while let Some(parent) = child.parent().map(Path::to_path_buf) {
+            self.children.entry(parent.clone()).or_default().insert(child.clone());
+            if parent.as_os_str().is_empty() {
+                break;
+            }
+            self.members.entry(parent.clone()).or_insert_with(|| ArchiveMember {
+                node_type: FsNodeType::Directory,
+                metadata: FsMetadata {
+                    size: 0,
+                    modified,
+                    changed: modified,
+                    permissions: 0o40755,
+                    uid: 0,
+                    gid: 0,
+                    inode: 0,
+                    device: 0,
+                    nlink: 1,
+                    hidden: false,
+                },
+                position: usize::MAX,
+            });
+            child = parent;
+        }
+    }
+
+    /// member for a virtual path, the root of the archive has none
+    pub fn member(&self, path: &Path) -> io::Result<&ArchiveMember> {
+        let relative = self.relative(path)?;
+        self.members.get(&relative).ok_or_else(|| not_found(path))
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.relative(path)
+            .map(|relative| relative.as_os_str().is_empty() || self.members.contains_key(&relative))
+            .unwrap_or(false)
+    }
+
+    pub fn metadata(&self, path: &Path) -> io::Result<VfsEntry> {
+        let relative = self.relative(path)?;
+        if relative.as_os_str().is_empty() {
+            return Ok(VfsEntry {
+                name: file_name(&self.root),
+                path: self.root.clone(),
+                node_type: FsNodeType::Directory,
+                metadata: FsMetadata {
+                    permissions: 0o40755,
+                    ..self.root_metadata.clone()
+                },
+            });
+        }
+        let member = self.members.get(&relative).ok_or_else(|| not_found(path))?;
+        Ok(self.entry(&relative, member))
+    }
+
+    pub fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        let relative = self.relative(path)?;
+        let children = self.children.get(&relative).ok_or_else(|| not_found(path))?;
+        Ok(children
+            .iter()
+            .filter_map(|child| self.members.get(child).map(|member| self.entry(child, member)))
+            .collect())
+    }
+
+    fn entry(&self, relative: &Path, member: &ArchiveMember) -> VfsEntry {
+        let path = self.root.join(relative);
+        VfsEntry {
+            name: file_name(&path),
+            path,
+            node_type: member.node_type.clone(),
+            metadata: member.metadata.clone(),
+        }
+    }
+
+    fn relative(&self, path: &Path) -> io::Result<PathBuf> {
+        path.strip_prefix(&self.root)
+            .map(Path::to_path_buf)
+            .map_err(|_| not_found(path))
+    }
+}
+
+/// why the members of an encrypted archive cannot be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordError {
+    Required,
+    Wrong,
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordError::Required => f.write_str("the archive is encrypted, a password is required"),
+            PasswordError::Wrong => f.write_str("wrong password"),
+        }
+    }
+}
+
+impl Error for PasswordError {}
+
+impl From<PasswordError> for io::Error {
+    fn from(err: PasswordError) -> io::Error {
+        io::Error::new(io::ErrorKind::PermissionDenied, err)
+    }
+}
+
+/// the password error an operation failed with, if it did for lack of the right password
+pub fn password_error(err: &io::Error) -> Option<PasswordError> {
+    err.get_ref()?.downcast_ref::<PasswordError>().copied()
+}
+
+/// bytes sent at once by the helper thread of [`stream`]
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// read what `send` produces on a helper thread, in chunks of about [`CHUNK_SIZE`]
+///
+/// A failed send means the reader was dropped, `send` can then stop.
+pub fn stream<F>(send: F) -> Box<dyn Read + Send>
+where
+    F: FnOnce(&SyncSender<io::Result<Vec<u8>>>) -> io::Result<()> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::sync_channel(4);
+    thread::spawn(move || {
+        if let Err(err) = send(&sender) {
+            let _ = sender.send(Err(err));
+        }
+    });
+    Box::new(ChannelReader {
+        receiver,
+        chunk: Vec::new(),
+        offset: 0,
+    })
+}
+
+/// send what `reader` gives in chunks, stopping once nobody reads them
+pub fn send_chunks(reader: &mut dyn Read, sender: &SyncSender<io::Result<Vec<u8>>>) -> io::Result<()> {
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        // the reader was dropped, nobody wants the rest
+        if sender.send(Ok(buffer[..read].to_vec())).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// reading end of a member streamed by a helper thread
+struct ChannelReader {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    offset: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk?;
+                    self.offset = 0;
+                }
+                // the helper thread is done
+                Err(_) => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.offset);
+        buf[..len].copy_from_slice(&self.chunk[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
+/// report writes on archives opened for browsing
+pub fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::ReadOnlyFilesystem, "archives are read-only")
+}
+
+/// seconds since the epoch to a system time, negative values before it
+pub fn unix_time(seconds: i64) -> SystemTime {
+    if seconds >= 0 {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        SystemTime::UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    }
+}
+
+/// seconds since the epoch of a civil date and time (Howard Hinnant's algorithm)
+pub fn civil_to_unix(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    days * 86_400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64
+}
+
+/// time of a DOS date and time as stored by zip and rar, in local time taken as UTC
+pub fn dos_time(time: u32) -> SystemTime {
+    unix_time(civil_to_unix(
+        (time >> 25) as i64 + 1980,
+        (time >> 21) & 0xf,
+        (time >> 16) & 0x1f,
+        (time >> 11) & 0x1f,
+        (time >> 5) & 0x3f,
+        (time & 0x1f) * 2,
+    ))
+}
+
+/// civil date and time of seconds since the epoch, the inverse of [`civil_to_unix`]
+pub fn unix_to_civil(seconds: i64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = seconds.div_euclid(86_400);
+    let day_seconds = seconds.rem_euclid(86_400) as u32;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day, day_seconds / 3600, day_seconds % 3600 / 60, day_seconds % 60)
+}
+
+/// relative member path without `..`, root or prefix components
+fn sanitize(name: &str) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(relative)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned())
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} not found in archive", path.display()))
+}
@@ -0,0 +1,523 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+
+use glob::{MatchOptions, Pattern};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::FsNodeType;
+use crate::shell::{self, shell_quote};
+use crate::vfs::archive::{civil_to_unix, read_only, unix_time, unix_to_civil, ArchiveIndex, ArchiveMember};
+use crate::vfs::{unsupported, Vfs, VfsEntry, VfsRef};
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+/// commands giving access to the members of the archives whose name matches one of the patterns
+///
+/// Commands are run by the user's shell with `%f` the archive, `%m` a member
+/// as the listing names it and `%t` a local file, all quoted, and `%%` a
+/// single `%`. `list` prints a line per member in the format of `ls -l` or
+/// `tar -tv`, `copyout` writes member `%m` to file `%t` and `copyin` stores
+/// file `%t` as member `%m`. Without `copyin` the archive is read-only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtHelper {
+    /// glob patterns on the file name, like `*.deb`, matched without regard to case
+    pub patterns: Vec<String>,
+    pub list: String,
+    pub copyout: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copyin: Option<String>,
+}
+
+impl ExtHelper {
+    fn new(patterns: &[&str], list: &str, copyout: &str) -> ExtHelper {
+        ExtHelper {
+            patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+            list: list.to_string(),
+            copyout: copyout.to_string(),
+            copyin: None,
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..MatchOptions::new()
+        };
+        self.patterns
+            .iter()
+            .filter_map(|pattern| Pattern::new(pattern).ok())
+            .any(|pattern| pattern.matches_with(name, options))
+    }
+}
+
+/// the helper file, like the `extfs.d` of Midnight Commander
+///
+/// Helpers browse the formats the file manager cannot read itself, the
+/// first one matching an archive is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtHelpers {
+    #[serde(default, rename = "helper")]
+    pub helpers: Vec<ExtHelper>,
+}
+
+impl Default for ExtHelpers {
+    /// debian and rpm packages through their tools, disk images and apk through bsdtar
+    fn default() -> Self {
+        ExtHelpers {
+            helpers: vec![
+                ExtHelper::new(
+                    &["*.deb", "*.udeb"],
+                    "dpkg-deb --fsys-tarfile %f | tar -tvf -",
+                    "dpkg-deb --fsys-tarfile %f | tar -xOf - %m > %t",
+                ),
+                ExtHelper::new(&["*.rpm"], "rpm2cpio %f | cpio -itv --quiet", "rpm2cpio %f | cpio -i --quiet --to-stdout %m > %t"),
+                ExtHelper::new(&["*.iso", "*.apk"], "bsdtar -tvf %f", "bsdtar -xOf %f %m > %t"),
+            ],
+        }
+    }
+}
+
+impl ExtHelpers {
+    /// `extfs.toml` in the directory of the default configuration
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::default_path()?.with_file_name("extfs.toml"))
+    }
+
+    /// read the helpers of a file, a missing file gives the defaults
+    pub fn load(path: &Path) -> io::Result<ExtHelpers> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(ExtHelpers::default()),
+            Err(err) => return Err(err),
+        };
+        toml::from_str(&text).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err.message()))
+        })
+    }
+
+    /// make these the helpers [`open`] picks from, only the first call counts
+    pub fn install(self) {
+        let _ = INSTALLED.set(self);
+    }
+
+    /// the first helper for an archive name
+    pub fn helper_for(&self, name: &str) -> Option<&ExtHelper> {
+        self.helpers.iter().find(|helper| helper.matches(name))
+    }
+}
+
+static INSTALLED: OnceLock<ExtHelpers> = OnceLock::new();
+
+/// the helpers in use, the defaults until others are installed
+pub fn helpers() -> &'static ExtHelpers {
+    INSTALLED.get_or_init(ExtHelpers::default)
+}
+
+/// open an archive with the installed helper for its name, if there is one
+pub fn open(host: &VfsRef, path: &Path, name: &str) -> Option<io::Result<ExtFs>> {
+    let helper = helpers().helper_for(name)?;
+    Some(ExtFs::open(VfsRef::clone(host), path, helper.clone()))
+}
+
+/// an archive browsed through the commands of a helper
+#[derive(Debug)]
+pub struct ExtFs {
+    source: Arc<Source>,
+    listing: Arc<RwLock<Listing>>,
+    host: VfsRef,
+}
+
+/// the archive and how to run its helper
+#[derive(Debug)]
+struct Source {
+    helper: ExtHelper,
+    /// local path of the archive file
+    archive: PathBuf,
+    /// path of the archive in its host, the root of the members
+    root: PathBuf,
+    root_metadata: FsMetadata,
+}
+
+#[derive(Debug)]
+struct Listing {
+    index: ArchiveIndex,
+    /// member names as the helper wrote them, by position
+    names: Vec<String>,
+}
+
+impl ExtFs {
+    /// open an archive of a backend that has local paths, running the list command of `helper`
+    pub fn open(host: VfsRef, path: &Path, helper: ExtHelper) -> io::Result<ExtFs> {
+        let archive = host
+            .local_path(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "archive is not on a local filesystem"))?;
+        let source = Source {
+            helper,
+            archive,
+            root: path.to_path_buf(),
+            root_metadata: host.metadata(path)?.metadata,
+        };
+        let listing = source.list()?;
+        Ok(ExtFs {
+            source: Arc::new(source),
+            listing: Arc::new(RwLock::new(listing)),
+            host,
+        })
+    }
+
+    fn member(&self, path: &Path) -> io::Result<ArchiveMember> {
+        self.listing.read().index.member(path).cloned()
+    }
+}
+
+impl Vfs for ExtFs {
+    fn scheme(&self) -> &str {
+        "extfs"
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        self.listing.read().index.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsEntry> {
+        self.listing.read().index.metadata(path)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let member = self.member(path)?;
+        if member.node_type != FsNodeType::File {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, path.display().to_string()));
+        }
+        let name = self.listing.read().names[member.position].clone();
+        let temporary = temporary_path();
+        let copied = self.source.run(&self.source.helper.copyout, &name, &temporary);
+        let file = copied.and_then(|_| File::open(&temporary));
+        // the open file outlives its name
+        let _ = fs::remove_file(&temporary);
+        Ok(Box::new(file?))
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        if self.source.helper.copyin.is_none() {
+            return Err(read_only());
+        }
+        let member = path
+            .strip_prefix(&self.source.root)
+            .map_err(|_| io::Error::new(io::ErrorKind::NotFound, format!("{} not in archive", path.display())))?
+            .to_string_lossy()
+            .into_owned();
+        let temporary = temporary_path();
+        Ok(Box::new(CopyIn {
+            file: Some(File::create(&temporary)?),
+            temporary,
+            member,
+            source: Arc::clone(&self.source),
+            listing: Arc::clone(&self.listing),
+        }))
+    }
+
+    fn remove(&self, _path: &Path) -> io::Result<()> {
+        Err(unsupported("removing"))
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(unsupported("renaming"))
+    }
+
+    /// directories only live in the listing until a file is stored in them
+    fn mkdir(&self, path: &Path) -> io::Result<()> {
+        if self.source.helper.copyin.is_none() {
+            return Err(read_only());
+        }
+        let mut listing = self.listing.write();
+        if listing.index.contains(path) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, path.display().to_string()));
+        }
+        let name = path.strip_prefix(&self.source.root).unwrap_or(path).to_string_lossy().into_owned();
+        let metadata = FsMetadata {
+            size: 0,
+            permissions: 0o40755,
+            ..self.source.root_metadata.clone()
+        };
+        listing.index.insert(&name, ArchiveMember {
+            node_type: FsNodeType::Directory,
+            metadata,
+            position: usize::MAX,
+        });
+        Ok(())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path)
+            .map(|entry| entry.node_type == FsNodeType::Directory)
+            .unwrap_or(false)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        match self.member(path)?.node_type {
+            FsNodeType::Symlink { target, .. } => Ok(target),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "not a symlink")),
+        }
+    }
+
+    fn host(&self) -> Option<(VfsRef, PathBuf)> {
+        Some((VfsRef::clone(&self.host), self.source.root.clone()))
+    }
+}
+
+impl Source {
+    /// list the members with the helper
+    fn list(&self) -> io::Result<Listing> {
+        let command = expand(&self.helper.list, &self.archive, "", Path::new(""));
+        let (output, status) = shell::capture_stdout(&command, self.directory())?;
+        if !status.success() {
+            return Err(io::Error::other(format!("{command} failed with {status}")));
+        }
+        let mut listing = Listing {
+            index: ArchiveIndex::new(self.root.clone(), self.root_metadata.clone()),
+            names: Vec::new(),
+        };
+        for line in String::from_utf8_lossy(&output).lines() {
+            let Some((name, node_type, metadata)) = parse_line(line, &self.root_metadata) else {
+                continue;
+            };
+            listing.index.insert(&name, ArchiveMember {
+                node_type,
+                metadata,
+                position: listing.names.len(),
+            });
+            listing.names.push(name);
+        }
+        Ok(listing)
+    }
+
+    /// run a copy command of the helper, failing with the last line it printed
+    fn run(&self, command: &str, member: &str, file: &Path) -> io::Result<()> {
+        let command = expand(command, &self.archive, member, file);
+        let (output, status) = shell::capture(&command, self.directory())?;
+        match status.success() {
+            true => Ok(()),
+            false => {
+                let reason = output.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("").trim();
+                Err(io::Error::other(format!("{command} failed with {status}: {reason}")))
+            }
+        }
+    }
+
+    /// helpers run next to the archive
+    fn directory(&self) -> &Path {
+        self.archive.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("/"))
+    }
+}
+
+/// a member being written to a local file, stored in the archive when flushed
+struct CopyIn {
+    file: Option<File>,
+    temporary: PathBuf,
+    member: String,
+    source: Arc<Source>,
+    listing: Arc<RwLock<Listing>>,
+}
+
+impl Write for CopyIn {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.file {
+            Some(file) => file.write(buf),
+            None => Err(io::Error::other("member already stored in the archive")),
+        }
+    }
+
+    /// store the member and list the archive again
+    fn flush(&mut self) -> io::Result<()> {
+        let Some(mut file) = self.file.take() else {
+            return Ok(());
+        };
+        file.flush()?;
+        drop(file);
+        let command = self.source.helper.copyin.as_deref().unwrap_or_default();
+        self.source.run(command, &self.member, &self.temporary)?;
+        *self.listing.write() = self.source.list()?;
+        Ok(())
+    }
+}
+
+impl Drop for CopyIn {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.temporary);
+    }
+}
+
+/// a fresh local file name for a member on its way in or out
+fn temporary_path() -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("midday-commander-extfs-{}-{count}", std::process::id()))
+}
+
+/// replace the macros of a helper command, quoted
+fn expand(command: &str, archive: &Path, member: &str, file: &Path) -> String {
+    let mut expanded = String::with_capacity(command.len());
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => expanded.push_str(&shell_quote(&archive.to_string_lossy())),
+            Some('m') => expanded.push_str(&shell_quote(member)),
+            Some('t') => expanded.push_str(&shell_quote(&file.to_string_lossy())),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
+
+/// name, type and metadata of a line of `ls -l` or `tar -tv`
+///
+/// The owner column is `user group` after a link count, or `user/group` as
+/// tar writes it. Devices, fifos and hard links are left out, they cannot
+/// be copied out.
+fn parse_line(line: &str, root_metadata: &FsMetadata) -> Option<(String, FsNodeType, FsMetadata)> {
+    let fields = fields(line);
+    let (_, mode) = fields.first()?;
+    let mut kind = mode.chars();
+    let file_type = match kind.next()? {
+        'd' => 0o40000,
+        '-' => 0o100000,
+        'l' => 0o120000,
+        _ => return None,
+    };
+    let permissions = file_type | permission_bits(kind.as_str())?;
+
+    let (owner, size_at) = match fields.get(1)?.1.split_once('/') {
+        Some((user, group)) => ((user, group), 2),
+        None => ((fields.get(2)?.1, fields.get(3)?.1), 4),
+    };
+    let size = fields.get(size_at)?.1.parse().ok()?;
+    let (modified, date_len) = parse_date(&fields[size_at + 1..])?;
+    let (start, _) = fields.get(size_at + 1 + date_len)?;
+    let mut name = &line[*start..];
+
+    let node_type = match file_type {
+        0o40000 => FsNodeType::Directory,
+        0o120000 => {
+            let (link, target) = name.split_once(" -> ")?;
+            name = link;
+            FsNodeType::Symlink { target: PathBuf::from(target), broken: false }
+        }
+        _ => FsNodeType::File,
+    };
+    let modified = Some(unix_time(modified));
+    let metadata = FsMetadata {
+        size,
+        modified,
+        changed: modified,
+        permissions,
+        uid: owner.0.parse().unwrap_or(root_metadata.uid),
+        gid: owner.1.parse().unwrap_or(root_metadata.gid),
+        inode: 0,
+        device: 0,
+        nlink: 1,
+        hidden: false,
+    };
+    Some((name.to_string(), node_type, metadata))
+}
+
+/// the words of a line with where they start
+fn fields(line: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    for (index, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(begin)) => {
+                fields.push((begin, &line[begin..index]));
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        fields.push((begin, &line[begin..]));
+    }
+    fields
+}
+
+/// mode bits of `rwxr-xr-x`, with the setuid, setgid and sticky letters
+fn permission_bits(letters: &str) -> Option<u32> {
+    let letters: Vec<char> = letters.chars().take(9).collect();
+    if letters.len() != 9 {
+        return None;
+    }
+    let mut mode = 0;
+    for (index, letter) in letters.iter().enumerate() {
+        let bit = 1 << (8 - index);
+        let special = match index {
+            2 => 0o4000,
+            5 => 0o2000,
+            8 => 0o1000,
+            _ => 0,
+        };
+        match letter {
+            'r' | 'w' | 'x' => mode |= bit,
+            's' | 't' => mode |= bit | special,
+            'S' | 'T' => mode |= special,
+            _ => {}
+        }
+    }
+    Some(mode)
+}
+
+/// seconds since the epoch of the date columns and how many columns they take
+///
+/// Dates are `2024-03-01 12:30`, `03-01-2024 12:30`, `Mar 1 2024` or
+/// `Mar 1 12:30` in the current year.
+fn parse_date(fields: &[(usize, &str)]) -> Option<(i64, usize)> {
+    let (_, first) = fields.first()?;
+    let time = |index: usize| fields.get(index).and_then(|(_, field)| parse_time(field));
+    let parts: Vec<&str> = first.split('-').collect();
+    if let [a, b, c] = parts[..] {
+        let (year, month, day) = match a.len() {
+            4 => (a.parse().ok()?, b.parse().ok()?, c.parse().ok()?),
+            _ => (c.parse().ok()?, a.parse().ok()?, b.parse().ok()?),
+        };
+        let (hour, minute, second, len) = match time(1) {
+            Some((hour, minute, second)) => (hour, minute, second, 2),
+            None => (0, 0, 0, 1),
+        };
+        return Some((civil_to_unix(year, month, day, hour, minute, second), len));
+    }
+    let month = MONTHS.iter().position(|month| first.to_lowercase().starts_with(month))? as u32 + 1;
+    let day = fields.get(1)?.1.parse().ok()?;
+    let (_, last) = fields.get(2)?;
+    let seconds = match parse_time(last) {
+        Some((hour, minute, second)) => {
+            let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|now| now.as_secs() as i64).unwrap_or(0);
+            civil_to_unix(unix_to_civil(now).0, month, day, hour, minute, second)
+        }
+        None => civil_to_unix(last.parse().ok()?, month, day, 0, 0, 0),
+    };
+    Some((seconds, 3))
+}
+
+/// hours, minutes and seconds of `12:30` or `12:30:05`
+fn parse_time(field: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = field.split(':');
+    let hour = parts.next()?.parse().ok()?;
+    let minute = parts.next()?.parse().ok()?;
+    let second = parts.next().map(str::parse).transpose().ok()?.unwrap_or(0);
+    parts.next().is_none().then_some((hour, minute, second))
+}
@@ -0,0 +1,266 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::node_type_from_path;
+use crate::vfs::{Vfs, VfsEntry};
+
+/// the filesystem of the machine, through `std::fs`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalFs;
+
+impl Vfs for LocalFs {
+    fn scheme(&self) -> &str {
+        "file"
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(path)?.flatten() {
+            // entries vanishing between the listing and the stat are skipped
+            if let Ok(entry) = self.metadata(&entry.path()) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsEntry> {
+        let metadata = FsMetadata::from_path(path)?;
+        let node_type = node_type_from_path(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.display().to_string()))?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+
+        Ok(VfsEntry {
+            name,
+            path: path.to_path_buf(),
+            node_type,
+            metadata,
+        })
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Ok(Box::new(File::create(path)?))
+    }
+
+    fn open_append(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Ok(Box::new(fs::OpenOptions::new().append(true).open(path)?))
+    }
+
+    fn open_read_at(&self, path: &Path, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(file))
+    }
+
+    fn open_write_at(&self, path: &Path, offset: u64) -> io::Result<Box<dyn Write + Send>> {
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        file.set_len(offset)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(file))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let metadata = fs::symlink_metadata(path)?;
+        if metadata.is_dir() || is_directory_link(&metadata) {
+            fs::remove_dir(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn mkdir(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::read_link(path)
+    }
+
+    #[cfg(unix)]
+    fn symlink(&self, target: &Path, path: &Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(target, path)
+    }
+
+    #[cfg(windows)]
+    fn symlink(&self, target: &Path, path: &Path) -> io::Result<()> {
+        crate::windows::symlink(target, path)
+    }
+
+    fn hard_link(&self, target: &Path, path: &Path) -> io::Result<()> {
+        fs::hard_link(target, path)
+    }
+
+    #[cfg(unix)]
+    fn set_permissions(&self, path: &Path, mode: u32) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+    }
+
+    #[cfg(not(unix))]
+    fn set_permissions(&self, path: &Path, mode: u32) -> io::Result<()> {
+        let mut permissions = fs::metadata(path)?.permissions();
+        permissions.set_readonly(mode & 0o222 == 0);
+        fs::set_permissions(path, permissions)
+    }
+
+    fn set_modified(&self, path: &Path, time: SystemTime) -> io::Result<()> {
+        File::open(path)?.set_modified(time)
+    }
+
+    /// the link itself changes owner, not its target
+    #[cfg(unix)]
+    fn set_owner(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+        std::os::unix::fs::lchown(path, uid, gid)
+    }
+
+    fn accessed(&self, path: &Path) -> io::Result<SystemTime> {
+        fs::symlink_metadata(path)?.accessed()
+    }
+
+    fn set_accessed(&self, path: &Path, time: SystemTime) -> io::Result<()> {
+        File::open(path)?.set_times(fs::FileTimes::new().set_accessed(time))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn xattrs(&self, path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+        xattr::list(path)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn set_xattr(&self, path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        xattr::set(path, name, value)
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(path.to_path_buf())
+    }
+}
+
+/// true if Windows takes the link for a directory, to be removed as one
+#[cfg(windows)]
+fn is_directory_link(metadata: &fs::Metadata) -> bool {
+    crate::windows::is_directory_link(metadata)
+}
+
+#[cfg(not(windows))]
+fn is_directory_link(_: &fs::Metadata) -> bool {
+    false
+}
+
+/// extended attributes of a path itself, never of the target of a link
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod xattr {
+    use std::ffi::{CStr, CString};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub fn list(path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+        let path = c_path(path)?;
+        // SAFETY: a null buffer of size 0 only asks for the size
+        let names = read(|buffer, size| unsafe { list_names(&path, buffer as *mut libc::c_char, size) })?;
+        let mut attributes = Vec::new();
+        for name in names.split(|byte| *byte == 0).filter(|name| !name.is_empty()) {
+            // names that are not text cannot be set again by name
+            let Ok(text) = std::str::from_utf8(name) else {
+                continue;
+            };
+            let name = CString::new(name).map_err(io::Error::other)?;
+            // SAFETY: both strings end with a null and the buffer holds `size` bytes
+            match read(|buffer, size| unsafe { get(&path, &name, buffer, size) }) {
+                Ok(value) => attributes.push((text.to_string(), value)),
+                // removed since the listing
+                Err(err) if err.raw_os_error() == Some(NO_ATTRIBUTE) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(attributes)
+    }
+
+    pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let path = c_path(path)?;
+        let name = CString::new(name).map_err(io::Error::other)?;
+        // SAFETY: both strings end with a null and the value is `value.len()` bytes
+        match unsafe { put(&path, &name, value) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    fn c_path(path: &Path) -> io::Result<CString> {
+        CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)
+    }
+
+    /// call `fill` once for the size then with a buffer that large, again if it grew meanwhile
+    fn read(mut fill: impl FnMut(*mut libc::c_void, usize) -> isize) -> io::Result<Vec<u8>> {
+        loop {
+            let size = match fill(std::ptr::null_mut(), 0) {
+                -1 => return Err(io::Error::last_os_error()),
+                size => size as usize,
+            };
+            let mut buffer = vec![0u8; size];
+            match fill(buffer.as_mut_ptr() as *mut libc::c_void, size) {
+                -1 if io::Error::last_os_error().raw_os_error() == Some(libc::ERANGE) => continue,
+                -1 => return Err(io::Error::last_os_error()),
+                read => {
+                    buffer.truncate(read as usize);
+                    return Ok(buffer);
+                }
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    const NO_ATTRIBUTE: i32 = libc::ENODATA;
+
+    #[cfg(target_os = "macos")]
+    const NO_ATTRIBUTE: i32 = libc::ENOATTR;
+
+    #[cfg(target_os = "linux")]
+    unsafe fn list_names(path: &CStr, buffer: *mut libc::c_char, size: usize) -> isize {
+        unsafe { libc::llistxattr(path.as_ptr(), buffer, size) }
+    }
+
+    #[cfg(target_os = "linux")]
+    unsafe fn get(path: &CStr, name: &CStr, buffer: *mut libc::c_void, size: usize) -> isize {
+        unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), buffer, size) }
+    }
+
+    #[cfg(target_os = "linux")]
+    unsafe fn put(path: &CStr, name: &CStr, value: &[u8]) -> i32 {
+        unsafe { libc::lsetxattr(path.as_ptr(), name.as_ptr(), value.as_ptr() as *const libc::c_void, value.len(), 0) }
+    }
+
+    #[cfg(target_os = "macos")]
+    unsafe fn list_names(path: &CStr, buffer: *mut libc::c_char, size: usize) -> isize {
+        unsafe { libc::listxattr(path.as_ptr(), buffer, size, libc::XATTR_NOFOLLOW) }
+    }
+
+    #[cfg(target_os = "macos")]
+    unsafe fn get(path: &CStr, name: &CStr, buffer: *mut libc::c_void, size: usize) -> isize {
+        unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buffer, size, 0, libc::XATTR_NOFOLLOW) }
+    }
+
+    #[cfg(target_os = "macos")]
+    unsafe fn put(path: &CStr, name: &CStr, value: &[u8]) -> i32 {
+        unsafe { libc::setxattr(path.as_ptr(), name.as_ptr(), value.as_ptr() as *const libc::c_void, value.len(), 0, libc::XATTR_NOFOLLOW) }
+    }
+}
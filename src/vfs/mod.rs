@@ -0,0 +1,258 @@
+pub mod archive;
+pub mod ext_fs;
+pub mod local;
+pub mod rar_fs;
+pub mod s3_fs;
+pub mod sevenz_fs;
+pub mod sftp_fs;
+pub mod tar_fs;
+pub mod trash_fs;
+pub mod zip_fs;
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::FsNodeType;
+use crate::trash::Trash;
+
+pub use archive::{password_error, PasswordError};
+pub use ext_fs::{ExtFs, ExtHelper, ExtHelpers};
+pub use local::LocalFs;
+pub use rar_fs::RarFs;
+pub use s3_fs::{S3Fs, S3Options};
+pub use sevenz_fs::SevenZFs;
+pub use sftp_fs::{authentication_failed, SftpFs, SftpOptions, SftpUrl};
+pub use tar_fs::{TarCompression, TarFs};
+pub use trash_fs::TrashFs;
+pub use zip_fs::ZipFs;
+
+/// shared handle on a filesystem backend
+pub type VfsRef = Arc<dyn Vfs>;
+
+/// one entry of a directory listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct VfsEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub node_type: FsNodeType,
+    pub metadata: FsMetadata,
+}
+
+/// filesystem backend the node tree and the operations are built on
+///
+/// Paths are absolute paths inside the backend. Symlinks are never followed
+/// unless stated otherwise.
+pub trait Vfs: Send + Sync + fmt::Debug {
+    /// name of the backend as used in urls, `file` for the local filesystem
+    fn scheme(&self) -> &str;
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>>;
+
+    /// read a directory in pages handed to `page` as they arrive, it returns false to stop
+    ///
+    /// Remote backends override it so a large directory shows up bit by bit,
+    /// the others give the whole listing as one page.
+    fn read_dir_pages(&self, path: &Path, page: &mut dyn FnMut(Vec<VfsEntry>) -> bool) -> io::Result<()> {
+        page(self.read_dir(path)?);
+        Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsEntry>;
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+
+    /// create or truncate a file
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>>;
+
+    /// remove a file, a symlink or an empty directory
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    fn mkdir(&self, path: &Path) -> io::Result<()>;
+
+    /// true if the path is a directory, following symlinks
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path)
+            .map(|entry| entry.node_type == FsNodeType::Directory)
+            .unwrap_or(false)
+    }
+
+    /// open an existing file to write at its end
+    fn open_append(&self, _path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Err(unsupported("appending"))
+    }
+
+    /// open a file to read from `offset` on, the default reads and drops what comes before
+    fn open_read_at(&self, path: &Path, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+        let mut reader = self.open_read(path)?;
+        io::copy(&mut (&mut reader).take(offset), &mut io::sink())?;
+        Ok(reader)
+    }
+
+    /// open an existing file to write from `offset` on, what followed is cut off
+    fn open_write_at(&self, _path: &Path, _offset: u64) -> io::Result<Box<dyn Write + Send>> {
+        Err(unsupported("writing at an offset"))
+    }
+
+    fn read_link(&self, _path: &Path) -> io::Result<PathBuf> {
+        Err(unsupported("symlinks"))
+    }
+
+    fn symlink(&self, _target: &Path, _path: &Path) -> io::Result<()> {
+        Err(unsupported("symlinks"))
+    }
+
+    /// give the file `target` the other name `path`, both on one filesystem
+    fn hard_link(&self, _target: &Path, _path: &Path) -> io::Result<()> {
+        Err(unsupported("hardlinks"))
+    }
+
+    fn set_permissions(&self, _path: &Path, _mode: u32) -> io::Result<()> {
+        Err(unsupported("permissions"))
+    }
+
+    fn set_modified(&self, _path: &Path, _time: SystemTime) -> io::Result<()> {
+        Err(unsupported("timestamps"))
+    }
+
+    /// change the owner and group, `None` keeps the current one
+    fn set_owner(&self, _path: &Path, _uid: Option<u32>, _gid: Option<u32>) -> io::Result<()> {
+        Err(unsupported("ownership"))
+    }
+
+    /// time of the last access
+    fn accessed(&self, _path: &Path) -> io::Result<SystemTime> {
+        Err(unsupported("access times"))
+    }
+
+    fn set_accessed(&self, _path: &Path, _time: SystemTime) -> io::Result<()> {
+        Err(unsupported("access times"))
+    }
+
+    /// extended attributes by name, the POSIX ACLs among them on Linux
+    fn xattrs(&self, _path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+        Err(unsupported("extended attributes"))
+    }
+
+    fn set_xattr(&self, _path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+        Err(unsupported("extended attributes"))
+    }
+
+    /// how a path of this backend is shown to the user
+    fn display_path(&self, path: &Path) -> String {
+        path.display().to_string()
+    }
+
+    /// path on the local filesystem, for backends that have one
+    fn local_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// backend and path of the file this filesystem is opened from, e.g. an archive
+    ///
+    /// The path is also the root of this filesystem.
+    fn host(&self) -> Option<(VfsRef, PathBuf)> {
+        None
+    }
+
+    /// name and state of the connection of a remote backend, shown in the header of its panels
+    fn remote(&self) -> Option<RemoteStatus> {
+        None
+    }
+}
+
+/// how the connection of a remote backend is doing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteStatus {
+    /// the saved connection the backend was opened from, or its host
+    pub label: String,
+    /// false once the connection dropped and could not be made again
+    pub connected: bool,
+}
+
+/// error returned by backends for what they cannot do
+pub fn unsupported(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, format!("{what} not supported by this filesystem"))
+}
+
+/// the local filesystem backend, shared by every local node
+pub fn local() -> VfsRef {
+    static LOCAL: std::sync::OnceLock<VfsRef> = std::sync::OnceLock::new();
+    Arc::clone(LOCAL.get_or_init(|| Arc::new(LocalFs)))
+}
+
+/// true if both handles point to the same backend instance
+pub fn same_vfs(a: &VfsRef, b: &VfsRef) -> bool {
+    Arc::ptr_eq(a, b)
+}
+
+/// true if the backend is the local filesystem
+pub fn is_local(vfs: &VfsRef) -> bool {
+    vfs.scheme() == "file"
+}
+
+/// open a file as a browsable filesystem if its type is supported, by the backends or by a helper
+pub fn open_archive(vfs: &VfsRef, path: &Path) -> Option<io::Result<VfsRef>> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") || name.ends_with(".jar") {
+        return Some(ZipFs::open(Arc::clone(vfs), path).map(|zip| Arc::new(zip) as VfsRef));
+    }
+    if name.ends_with(".7z") {
+        return Some(SevenZFs::open(Arc::clone(vfs), path).map(|sevenz| Arc::new(sevenz) as VfsRef));
+    }
+    if name.ends_with(".rar") {
+        return Some(RarFs::open(Arc::clone(vfs), path).map(|rar| Arc::new(rar) as VfsRef));
+    }
+    if let Some(compression) = TarCompression::from_name(&name) {
+        return Some(TarFs::open(Arc::clone(vfs), path, compression).map(|tar| Arc::new(tar) as VfsRef));
+    }
+    ext_fs::open(vfs, path, &name).map(|ext| ext.map(|ext| Arc::new(ext) as VfsRef))
+}
+
+/// open an archive to read all its members, an encrypted zip, 7z or rar with `password`
+///
+/// An encrypted archive without a password fails with
+/// [`PasswordError::Required`] rather than when its first member is read.
+pub fn open_archive_with_password(vfs: &VfsRef, path: &Path, password: Option<&str>) -> Option<io::Result<VfsRef>> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    let host = Arc::clone(vfs);
+    let opened = if name.ends_with(".zip") || name.ends_with(".jar") {
+        ZipFs::open_with_password(host, path, password).map(|zip| (zip.is_encrypted(), Arc::new(zip) as VfsRef))
+    } else if name.ends_with(".7z") {
+        SevenZFs::open_with_password(host, path, password).map(|sevenz| (sevenz.is_encrypted(), Arc::new(sevenz) as VfsRef))
+    } else if name.ends_with(".rar") {
+        RarFs::open_with_password(host, path, password).map(|rar| (rar.is_encrypted(), Arc::new(rar) as VfsRef))
+    } else {
+        return open_archive(vfs, path);
+    };
+    Some(opened.and_then(|(encrypted, archive)| match encrypted && password.is_none() {
+        true => Err(PasswordError::Required.into()),
+        false => Ok(archive),
+    }))
+}
+
+/// backend and path for a location typed by the user, a local path or a url
+pub fn open_location(location: &str) -> io::Result<(VfsRef, PathBuf)> {
+    if location.starts_with("sftp://") {
+        let url = SftpUrl::parse(location)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid url {location}")))?;
+        let sftp = SftpFs::connect(&url, &SftpOptions::default())?;
+        return Ok((Arc::new(sftp), url.path));
+    }
+    if let Some(path) = location.strip_prefix("s3://") {
+        let s3 = S3Fs::connect(s3_fs::options().clone());
+        return Ok((Arc::new(s3), Path::new("/").join(path)));
+    }
+    if let Some(path) = location.strip_prefix("trash://") {
+        let trash = Trash::home().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory for the trash"))?;
+        let path = Path::new("/").join(path);
+        return Ok((Arc::new(TrashFs::new(trash)), path));
+    }
+    let path = location.strip_prefix("file://").unwrap_or(location);
+    Ok((local(), PathBuf::from(path)))
+}
@@ -0,0 +1,211 @@
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use unrar::error::{Code, UnrarError};
+use unrar::Archive;
+
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::FsNodeType;
+use crate::vfs::archive::{dos_time, read_only, ArchiveIndex, ArchiveMember, PasswordError};
+use crate::vfs::{Vfs, VfsEntry, VfsRef};
+
+/// file type bits of an attribute that is a unix mode, not dos attributes
+const UNIX_TYPE_MASK: u32 = 0o170000;
+
+/// a rar archive browsed as a read-only directory tree, read through the unrar library
+#[derive(Debug)]
+pub struct RarFs {
+    index: ArchiveIndex,
+    archive: PathBuf,
+    host: VfsRef,
+    password: Option<Vec<u8>>,
+    encrypted: bool,
+}
+
+impl RarFs {
+    /// open a rar file of a backend that has local paths
+    ///
+    /// The members of an archive whose content only is encrypted are listed,
+    /// reading them fails with a [`PasswordError`].
+    pub fn open(host: VfsRef, path: &Path) -> io::Result<RarFs> {
+        RarFs::open_with_password(host, path, None)
+    }
+
+    /// open a rar file, checking the password by reading its first encrypted member
+    pub fn open_with_password(host: VfsRef, path: &Path, password: Option<&str>) -> io::Result<RarFs> {
+        let archive = host
+            .local_path(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "archive is not on a local filesystem"))?;
+        let root_metadata = host.metadata(path)?.metadata;
+        let mut rar = RarFs {
+            index: ArchiveIndex::new(path.to_path_buf(), root_metadata.clone()),
+            archive,
+            host,
+            password: password.map(|password| password.as_bytes().to_vec()),
+            encrypted: false,
+        };
+
+        let mut encrypted = None;
+        let mut symlinks = Vec::new();
+        let listing = rar.unrar().open_for_listing().map_err(|err| rar.io_error(err))?;
+        for (position, header) in listing.enumerate() {
+            let header = header.map_err(|err| rar.io_error(err))?;
+            if header.is_encrypted() {
+                encrypted.get_or_insert(position);
+            }
+            let mode = (header.file_attr & UNIX_TYPE_MASK != 0).then_some(header.file_attr);
+            let default_mode = if header.is_directory() { 0o40755 } else { 0o100644 };
+            let modified = Some(dos_time(header.file_time));
+            let name = header.filename.to_string_lossy().into_owned();
+            let metadata = FsMetadata {
+                size: header.unpacked_size,
+                modified,
+                changed: modified,
+                permissions: mode.unwrap_or(default_mode),
+                uid: root_metadata.uid,
+                gid: root_metadata.gid,
+                inode: 0,
+                device: 0,
+                nlink: 1,
+                hidden: false,
+            };
+            let node_type = if header.is_directory() {
+                FsNodeType::Directory
+            } else if mode.is_some_and(|mode| mode & UNIX_TYPE_MASK == 0o120000) {
+                // the target is the content of the member, recorded once read
+                symlinks.push((position, name, metadata));
+                continue;
+            } else {
+                FsNodeType::File
+            };
+            rar.index.insert(&name, ArchiveMember { node_type, metadata, position });
+        }
+        rar.encrypted = encrypted.is_some();
+
+        if !symlinks.is_empty() {
+            let positions: Vec<usize> = symlinks.iter().map(|(position, ..)| *position).collect();
+            let targets = rar.read_members(&positions)?;
+            for ((position, name, metadata), (_, target)) in symlinks.into_iter().zip(targets) {
+                let target = PathBuf::from(String::from_utf8_lossy(&target).into_owned());
+                rar.index.insert(&name, ArchiveMember {
+                    node_type: FsNodeType::Symlink { target, broken: false },
+                    metadata,
+                    position,
+                });
+            }
+        }
+        if let Some(position) = encrypted
+            && rar.password.is_some()
+        {
+            rar.read_members(&[position])?;
+        }
+        Ok(rar)
+    }
+
+    /// true if some members can only be read with a password
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    fn unrar(&self) -> Archive<'_> {
+        match &self.password {
+            Some(password) => Archive::with_password(&self.archive, password),
+            None => Archive::new(&self.archive),
+        }
+    }
+
+    /// content of the members at `positions`, in archive order, walking the archive once
+    fn read_members(&self, positions: &[usize]) -> io::Result<Vec<(usize, Vec<u8>)>> {
+        let mut contents = Vec::new();
+        let mut archive = self.unrar().open_for_processing().map_err(|err| self.io_error(err))?;
+        let mut position = 0;
+        while contents.len() < positions.len() {
+            let Some(header) = archive.read_header().map_err(|err| self.io_error(err))? else {
+                break;
+            };
+            archive = match positions.contains(&position) {
+                true => {
+                    let (content, next) = header.read().map_err(|err| self.io_error(err))?;
+                    contents.push((position, content));
+                    next
+                }
+                false => header.skip().map_err(|err| self.io_error(err))?,
+            };
+            position += 1;
+        }
+        Ok(contents)
+    }
+
+    /// io error of an unrar error, bad data in an encrypted archive comes from the password
+    fn io_error(&self, err: UnrarError) -> io::Error {
+        match err.code {
+            Code::MissingPassword => PasswordError::Required.into(),
+            Code::BadPassword => PasswordError::Wrong.into(),
+            Code::BadData if self.password.is_some() => PasswordError::Wrong.into(),
+            Code::EOpen => io::Error::new(io::ErrorKind::NotFound, err.to_string()),
+            _ => io::Error::new(io::ErrorKind::InvalidData, err.to_string()),
+        }
+    }
+}
+
+impl Vfs for RarFs {
+    fn scheme(&self) -> &str {
+        "rar"
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        self.index.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsEntry> {
+        self.index.metadata(path)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let member = self.index.member(path)?;
+        if member.node_type != FsNodeType::File {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, path.display().to_string()));
+        }
+        // unrar hands out whole members, so they are decompressed up front
+        let content = self
+            .read_members(&[member.position])?
+            .pop()
+            .map(|(_, content)| content)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "member vanished from archive"))?;
+        Ok(Box::new(Cursor::new(content)))
+    }
+
+    fn open_write(&self, _path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Err(read_only())
+    }
+
+    fn remove(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn mkdir(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.index
+            .metadata(path)
+            .map(|entry| entry.node_type == FsNodeType::Directory)
+            .unwrap_or(false)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        match &self.index.member(path)?.node_type {
+            FsNodeType::Symlink { target, .. } => Ok(target.clone()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "not a symlink")),
+        }
+    }
+
+    fn host(&self) -> Option<(VfsRef, PathBuf)> {
+        Some((VfsRef::clone(&self.host), self.index.root().to_path_buf()))
+    }
+}
@@ -0,0 +1,900 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::S3Config;
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::FsNodeType;
+use crate::vfs::archive::{civil_to_unix, unix_time, unix_to_civil};
+use crate::vfs::{RemoteStatus, Vfs, VfsEntry};
+
+/// bytes sent per part of a multipart upload, S3 wants at least 5 MiB but for the last one
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// biggest object a server-side copy takes in one request
+const COPY_LIMIT: u64 = 5 * 1024 * 1024 * 1024;
+
+/// payload hash of requests whose body is not signed
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// where the objects are and the credentials signing the requests
+#[derive(Clone, PartialEq, Eq)]
+pub struct S3Options {
+    /// scheme and host, without a trailing slash
+    pub endpoint: String,
+    pub region: String,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    pub path_style: bool,
+}
+
+impl fmt::Debug for S3Options {
+    /// the options without the secrets
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Options")
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("access_key_id", &self.access_key_id)
+            .field("path_style", &self.path_style)
+            .finish()
+    }
+}
+
+impl S3Options {
+    /// the settings of the config file, the AWS environment variables for those unset
+    pub fn from_config(config: &S3Config) -> S3Options {
+        let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let region = config
+            .region
+            .clone()
+            .or_else(|| env("AWS_REGION"))
+            .or_else(|| env("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| String::from("us-east-1"));
+        let custom = config.endpoint.clone().or_else(|| env("AWS_ENDPOINT_URL_S3")).or_else(|| env("AWS_ENDPOINT_URL"));
+        let path_style = config.path_style.unwrap_or(custom.is_some());
+        let endpoint = custom.unwrap_or_else(|| format!("https://s3.{region}.amazonaws.com"));
+        S3Options {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            region,
+            access_key_id: config.access_key_id.clone().or_else(|| env("AWS_ACCESS_KEY_ID")),
+            secret_access_key: config.secret_access_key.clone().or_else(|| env("AWS_SECRET_ACCESS_KEY")),
+            session_token: env("AWS_SESSION_TOKEN"),
+            path_style,
+        }
+    }
+
+    /// make these the options of the `s3://` locations, only the first call counts
+    pub fn install(self) {
+        let _ = INSTALLED.set(self);
+    }
+}
+
+static INSTALLED: OnceLock<S3Options> = OnceLock::new();
+
+/// the options of the `s3://` locations, from the environment until others are installed
+pub fn options() -> &'static S3Options {
+    INSTALLED.get_or_init(|| S3Options::from_config(&S3Config::default()))
+}
+
+/// buckets of an S3 compatible object storage as a directory tree
+///
+/// The root lists the buckets, `/bucket/a/b` is the prefix `a/b/` of the
+/// bucket. Directories are the prefixes the keys share, an empty one is an
+/// empty object named like `a/b/`.
+#[derive(Debug)]
+pub struct S3Fs {
+    client: Arc<Client>,
+    /// shown in the header of the panels, the host of the endpoint unless named otherwise
+    label: String,
+}
+
+impl S3Fs {
+    pub fn connect(options: S3Options) -> S3Fs {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(15))
+            .timeout_read(Duration::from_secs(60))
+            .build();
+        let label = options.endpoint.split_once("://").map_or(options.endpoint.as_str(), |(_, host)| host).to_string();
+        S3Fs {
+            client: Arc::new(Client {
+                options,
+                agent,
+                connected: AtomicBool::new(true),
+            }),
+            label,
+        }
+    }
+
+    /// show the panels of this storage under `label`, like the name of a saved connection
+    pub fn named(self, label: &str) -> S3Fs {
+        S3Fs {
+            label: label.to_string(),
+            ..self
+        }
+    }
+
+    /// the objects and the prefixes right below `key` as entries, a page of ListObjectsV2
+    /// at a time until `each` returns false
+    fn list_directory(
+        &self,
+        path: &Path,
+        bucket: &str,
+        key: &str,
+        each: &mut dyn FnMut(Vec<VfsEntry>) -> bool,
+    ) -> io::Result<()> {
+        let prefix = directory_prefix(key);
+        let mut token = None;
+        loop {
+            let page = self.client.list(bucket, &prefix, Some("/"), None, token.as_deref())?;
+            let mut entries = Vec::new();
+            // a server giving keys outside the prefix is not trusted with them
+            for common in &page.prefixes {
+                let name = common.strip_prefix(prefix.as_str()).map(|name| name.trim_end_matches('/'));
+                if let Some(name) = name
+                    && !name.is_empty()
+                {
+                    entries.push(directory_entry(&path.join(name), None));
+                }
+            }
+            for object in &page.objects {
+                if let Some(name) = object.key.strip_prefix(prefix.as_str())
+                    && !name.is_empty()
+                {
+                    entries.push(file_entry(&path.join(name), object.size, object.modified));
+                }
+            }
+            if !each(entries) {
+                return Ok(());
+            }
+            match page.next {
+                Some(next) => token = Some(next),
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// the buckets of the account
+    fn list_buckets(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        let response = self.client.call(self.client.request("GET", None, "", &[], &[]))?;
+        let xml = response.into_string()?;
+        Ok(elements(&xml, "Bucket")
+            .into_iter()
+            .filter_map(|bucket| {
+                let name = element(bucket, "Name")?;
+                let created = element(bucket, "CreationDate").and_then(|date| parse_iso_time(&date));
+                Some(directory_entry(&path.join(name), created))
+            })
+            .collect())
+    }
+}
+
+impl Vfs for S3Fs {
+    fn scheme(&self) -> &str {
+        "s3"
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        let mut entries = Vec::new();
+        self.read_dir_pages(path, &mut |page| {
+            entries.extend(page);
+            true
+        })?;
+        Ok(entries)
+    }
+
+    fn read_dir_pages(&self, path: &Path, page: &mut dyn FnMut(Vec<VfsEntry>) -> bool) -> io::Result<()> {
+        match split(path)? {
+            (None, _) => {
+                page(self.list_buckets(path)?);
+                Ok(())
+            }
+            (Some(bucket), key) => self.list_directory(path, &bucket, &key, page),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsEntry> {
+        match split(path)? {
+            (None, _) => Ok(directory_entry(path, None)),
+            (Some(bucket), key) if key.is_empty() => {
+                self.client.call(self.client.request("HEAD", Some(&bucket), "", &[], &[]))?;
+                Ok(directory_entry(path, None))
+            }
+            (Some(bucket), key) => match self.client.head(&bucket, &key) {
+                Ok((size, modified)) => Ok(file_entry(path, size, modified)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    let page = self.client.list(&bucket, &directory_prefix(&key), None, Some(1), None)?;
+                    match page.objects.is_empty() {
+                        true => Err(io::Error::new(io::ErrorKind::NotFound, format!("{} not found", self.display_path(path)))),
+                        false => Ok(directory_entry(path, None)),
+                    }
+                }
+                Err(err) => Err(err),
+            },
+        }
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let (bucket, key) = object(path)?;
+        let response = self.client.call(self.client.request("GET", Some(&bucket), &key, &[], &[]))?;
+        Ok(Box::new(response.into_reader()))
+    }
+
+    fn open_read_at(&self, path: &Path, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+        let (bucket, key) = object(path)?;
+        let range = [("range", format!("bytes={offset}-"))];
+        let response = self.client.call(self.client.request("GET", Some(&bucket), &key, &[], &range))?;
+        Ok(Box::new(response.into_reader()))
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        let (bucket, key) = object(path)?;
+        Ok(Box::new(Upload {
+            client: Arc::clone(&self.client),
+            bucket,
+            key,
+            buffer: Vec::new(),
+            upload: None,
+            parts: Vec::new(),
+            done: false,
+        }))
+    }
+
+    /// a directory goes with its empty object, the files in it are removed first
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match split(path)? {
+            (None, _) => Err(io::Error::new(io::ErrorKind::PermissionDenied, "the root of the buckets cannot be removed")),
+            (Some(bucket), key) if key.is_empty() => {
+                self.client.call(self.client.request("DELETE", Some(&bucket), "", &[], &[]))?;
+                Ok(())
+            }
+            (Some(bucket), key) => {
+                let key = match self.client.head(&bucket, &key) {
+                    Ok(_) => key,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => directory_prefix(&key),
+                    Err(err) => return Err(err),
+                };
+                self.client.call(self.client.request("DELETE", Some(&bucket), &key, &[], &[]))?;
+                Ok(())
+            }
+        }
+    }
+
+    /// objects are copied on the server then removed, directories are moved file by file
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let (from_bucket, from_key) = object(from)?;
+        let (to_bucket, to_key) = object(to)?;
+        let crosses = || io::Error::new(io::ErrorKind::CrossesDevices, "S3 only renames objects by copying them");
+        match self.client.head(&from_bucket, &from_key) {
+            Ok((size, _)) if size <= COPY_LIMIT => {}
+            Ok(_) => return Err(crosses()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(crosses()),
+            Err(err) => return Err(err),
+        }
+        let source = format!("/{from_bucket}/{}", uri_encode(&from_key, true));
+        let copy = self.client.request("PUT", Some(&to_bucket), &to_key, &[], &[("x-amz-copy-source", source)]);
+        // a copy that fails once started answers 200 with an error document
+        let xml = self.client.call(copy)?.into_string()?;
+        if let Some(err) = error_document(&xml) {
+            return Err(err);
+        }
+        self.client.call(self.client.request("DELETE", Some(&from_bucket), &from_key, &[], &[]))?;
+        Ok(())
+    }
+
+    /// a bucket at the root, an empty object named like a prefix below
+    fn mkdir(&self, path: &Path) -> io::Result<()> {
+        match split(path)? {
+            (None, _) => Err(io::Error::new(io::ErrorKind::AlreadyExists, "the root of the buckets exists")),
+            (Some(bucket), key) if key.is_empty() => {
+                let request = self.client.request("PUT", Some(&bucket), "", &[], &[]);
+                let region = &self.client.options.region;
+                let response = match region.as_str() {
+                    "us-east-1" => request.call(),
+                    _ => request.send_string(&format!(
+                        "<CreateBucketConfiguration><LocationConstraint>{}</LocationConstraint></CreateBucketConfiguration>",
+                        escape(region)
+                    )),
+                };
+                self.client.check(response)?;
+                Ok(())
+            }
+            (Some(bucket), key) => {
+                let request = self.client.request("PUT", Some(&bucket), &directory_prefix(&key), &[], &[]);
+                self.client.check(request.send_bytes(&[]))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn display_path(&self, path: &Path) -> String {
+        format!("s3://{}", path.to_string_lossy().trim_start_matches('/'))
+    }
+
+    fn remote(&self) -> Option<RemoteStatus> {
+        Some(RemoteStatus {
+            label: self.label.clone(),
+            connected: self.client.connected.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// the http side of the backend, shared with the uploads in flight
+struct Client {
+    options: S3Options,
+    agent: ureq::Agent,
+    /// false while the endpoint cannot be reached, every request tries again
+    connected: AtomicBool,
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client").field("options", &self.options).finish()
+    }
+}
+
+/// a page of a listing
+struct Page {
+    objects: Vec<Object>,
+    prefixes: Vec<String>,
+    /// continuation token of the next page, none on the last one
+    next: Option<String>,
+}
+
+struct Object {
+    key: String,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Client {
+    /// a signed request on an object, a bucket without a key, or the service without a bucket
+    fn request(&self, method: &str, bucket: Option<&str>, key: &str, query: &[(&str, String)], headers: &[(&str, String)]) -> ureq::Request {
+        let options = &self.options;
+        let (scheme, host) = options.endpoint.split_once("://").unwrap_or(("https", &options.endpoint));
+        let (host, path) = match bucket {
+            Some(bucket) if options.path_style => (host.to_string(), format!("/{bucket}/{key}")),
+            Some(bucket) => (format!("{bucket}.{host}"), format!("/{key}")),
+            None => (host.to_string(), String::from("/")),
+        };
+        let path = uri_encode(&path, true);
+        let mut query: Vec<(String, String)> = query.iter().map(|(name, value)| (uri_encode(name, false), uri_encode(value, false))).collect();
+        query.sort();
+        let query = query.iter().map(|(name, value)| format!("{name}={value}")).collect::<Vec<_>>().join("&");
+        let url = match query.is_empty() {
+            true => format!("{scheme}://{host}{path}"),
+            false => format!("{scheme}://{host}{path}?{query}"),
+        };
+
+        let mut request = self.agent.request(method, &url);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        let (Some(access_key), Some(secret_key)) = (&options.access_key_id, &options.secret_access_key) else {
+            // anonymous requests for public buckets
+            return request;
+        };
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|now| now.as_secs() as i64).unwrap_or(0);
+        let (year, month, day, hour, minute, second) = unix_to_civil(now);
+        let date = format!("{year:04}{month:02}{day:02}");
+        let timestamp = format!("{date}T{hour:02}{minute:02}{second:02}Z");
+
+        let mut signed: Vec<(String, String)> = headers.iter().map(|(name, value)| (name.to_lowercase(), value.trim().to_string())).collect();
+        signed.push((String::from("host"), host.clone()));
+        signed.push((String::from("x-amz-content-sha256"), String::from(UNSIGNED_PAYLOAD)));
+        signed.push((String::from("x-amz-date"), timestamp.clone()));
+        if let Some(token) = &options.session_token {
+            signed.push((String::from("x-amz-security-token"), token.clone()));
+        }
+        signed.sort();
+        let (canonical_request, signed_headers) = canonical_request(method, &path, &query, &signed, UNSIGNED_PAYLOAD);
+        let scope = scope(&timestamp, &options.region);
+        let signature = signature(secret_key, &options.region, &timestamp, &canonical_request);
+
+        // the headers given are set already
+        for (name, value) in &signed {
+            if name.starts_with("x-amz-") && !headers.iter().any(|(given, _)| given.eq_ignore_ascii_case(name)) {
+                request = request.set(name, value);
+            }
+        }
+        request.set(
+            "authorization",
+            &format!("AWS4-HMAC-SHA256 Credential={access_key}/{scope}, SignedHeaders={signed_headers}, Signature={signature}"),
+        )
+    }
+
+    fn call(&self, request: ureq::Request) -> io::Result<ureq::Response> {
+        self.check(request.call())
+    }
+
+    /// the response of a request that went through, the error the server gave otherwise
+    fn check(&self, response: Result<ureq::Response, ureq::Error>) -> io::Result<ureq::Response> {
+        self.connected.store(!matches!(response, Err(ureq::Error::Transport(_))), Ordering::Relaxed);
+        match response {
+            Ok(response) => Ok(response),
+            Err(ureq::Error::Status(status, response)) => {
+                let xml = response.into_string().unwrap_or_default();
+                Err(error_document(&xml).unwrap_or_else(|| status_error(status, &format!("server answered {status}"))))
+            }
+            Err(ureq::Error::Transport(transport)) => Err(io::Error::new(io::ErrorKind::NotConnected, transport.to_string())),
+        }
+    }
+
+    /// size and modification time of an object
+    fn head(&self, bucket: &str, key: &str) -> io::Result<(u64, Option<SystemTime>)> {
+        let response = self.call(self.request("HEAD", Some(bucket), key, &[], &[]))?;
+        let size = response.header("content-length").and_then(|size| size.parse().ok()).unwrap_or(0);
+        let modified = response.header("last-modified").and_then(parse_http_time);
+        Ok((size, modified))
+    }
+
+    /// a page of the keys starting with `prefix`, those sharing a further `delimiter` folded into prefixes
+    fn list(&self, bucket: &str, prefix: &str, delimiter: Option<&str>, max_keys: Option<usize>, token: Option<&str>) -> io::Result<Page> {
+        let mut query = vec![("list-type", String::from("2")), ("prefix", prefix.to_string())];
+        if let Some(delimiter) = delimiter {
+            query.push(("delimiter", delimiter.to_string()));
+        }
+        if let Some(max_keys) = max_keys {
+            query.push(("max-keys", max_keys.to_string()));
+        }
+        if let Some(token) = token {
+            query.push(("continuation-token", token.to_string()));
+        }
+        let xml = self.call(self.request("GET", Some(bucket), "", &query, &[]))?.into_string()?;
+        let objects = elements(&xml, "Contents")
+            .into_iter()
+            .filter_map(|contents| {
+                Some(Object {
+                    key: element(contents, "Key")?,
+                    size: element(contents, "Size").and_then(|size| size.parse().ok()).unwrap_or(0),
+                    modified: element(contents, "LastModified").and_then(|date| parse_iso_time(&date)),
+                })
+            })
+            .collect();
+        let prefixes = elements(&xml, "CommonPrefixes").into_iter().filter_map(|common| element(common, "Prefix")).collect();
+        let next = match element(&xml, "IsTruncated").as_deref() {
+            Some("true") => element(&xml, "NextContinuationToken"),
+            _ => None,
+        };
+        Ok(Page { objects, prefixes, next })
+    }
+}
+
+/// an object being written, sent in one request or as a multipart upload once it outgrows a part
+///
+/// The object appears when the writer is flushed, dropping it before
+/// abandons the upload.
+struct Upload {
+    client: Arc<Client>,
+    bucket: String,
+    key: String,
+    buffer: Vec<u8>,
+    /// id of the multipart upload, once started
+    upload: Option<String>,
+    /// entity tags of the parts sent
+    parts: Vec<String>,
+    done: bool,
+}
+
+impl Upload {
+    fn send_part(&mut self, data: &[u8]) -> io::Result<()> {
+        let upload = match &self.upload {
+            Some(upload) => upload.clone(),
+            None => {
+                let start = self.client.request("POST", Some(&self.bucket), &self.key, &[("uploads", String::new())], &[]);
+                let xml = self.client.check(start.send_bytes(&[]))?.into_string()?;
+                let upload = element(&xml, "UploadId").ok_or_else(|| io::Error::other("server gave no upload id"))?;
+                self.upload = Some(upload.clone());
+                upload
+            }
+        };
+        let query = [("partNumber", (self.parts.len() + 1).to_string()), ("uploadId", upload)];
+        let part = self.client.request("PUT", Some(&self.bucket), &self.key, &query, &[]);
+        let response = self.client.check(part.send_bytes(data))?;
+        let etag = response.header("etag").ok_or_else(|| io::Error::other("server gave no entity tag for the part"))?;
+        self.parts.push(etag.to_string());
+        Ok(())
+    }
+}
+
+impl Write for Upload {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.done {
+            return Err(io::Error::other("object already uploaded"));
+        }
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= PART_SIZE {
+            let part: Vec<u8> = self.buffer.drain(..PART_SIZE).collect();
+            self.send_part(&part)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// send what is left and complete the object
+    fn flush(&mut self) -> io::Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        let Some(upload) = self.upload.clone() else {
+            let put = self.client.request("PUT", Some(&self.bucket), &self.key, &[], &[]);
+            self.client.check(put.send_bytes(&self.buffer))?;
+            self.done = true;
+            return Ok(());
+        };
+        if !self.buffer.is_empty() {
+            let part = std::mem::take(&mut self.buffer);
+            self.send_part(&part)?;
+        }
+        let parts: String = self
+            .parts
+            .iter()
+            .enumerate()
+            .map(|(index, etag)| format!("<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>", index + 1, escape(etag)))
+            .collect();
+        let complete = self.client.request("POST", Some(&self.bucket), &self.key, &[("uploadId", upload)], &[]);
+        let response = self.client.check(complete.send_string(&format!("<CompleteMultipartUpload>{parts}</CompleteMultipartUpload>")))?;
+        // completing may fail after the server answered 200
+        if let Some(err) = error_document(&response.into_string()?) {
+            return Err(err);
+        }
+        self.done = true;
+        Ok(())
+    }
+}
+
+impl Drop for Upload {
+    fn drop(&mut self) {
+        if let Some(upload) = self.upload.take()
+            && !self.done
+        {
+            let abort = self.client.request("DELETE", Some(&self.bucket), &self.key, &[("uploadId", upload)], &[]);
+            let _ = abort.call();
+        }
+    }
+}
+
+/// bucket and key of a path, the key empty for a bucket and no bucket for the root
+fn split(path: &Path) -> io::Result<(Option<String>, String)> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not an object path", path.display())));
+            }
+        }
+    }
+    if parts.is_empty() {
+        return Ok((None, String::new()));
+    }
+    let bucket = parts.remove(0);
+    Ok((Some(bucket), parts.join("/")))
+}
+
+/// bucket and key of the path of an object
+fn object(path: &Path) -> io::Result<(String, String)> {
+    match split(path)? {
+        (Some(bucket), key) if !key.is_empty() => Ok((bucket, key)),
+        _ => Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a bucket, not an object", path.display()))),
+    }
+}
+
+/// the prefix of the keys below a directory key, empty for the top of a bucket
+fn directory_prefix(key: &str) -> String {
+    match key.is_empty() {
+        true => String::new(),
+        false => format!("{key}/"),
+    }
+}
+
+fn directory_entry(path: &Path, modified: Option<SystemTime>) -> VfsEntry {
+    entry(path, FsNodeType::Directory, 0, modified, 0o40755)
+}
+
+fn file_entry(path: &Path, size: u64, modified: Option<SystemTime>) -> VfsEntry {
+    entry(path, FsNodeType::File, size, modified, 0o100644)
+}
+
+fn entry(path: &Path, node_type: FsNodeType, size: u64, modified: Option<SystemTime>, permissions: u32) -> VfsEntry {
+    VfsEntry {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("/")),
+        path: path.to_path_buf(),
+        node_type,
+        metadata: FsMetadata {
+            size,
+            modified,
+            changed: modified,
+            permissions,
+            uid: 0,
+            gid: 0,
+            inode: 0,
+            device: 0,
+            nlink: 1,
+            hidden: false,
+        },
+    }
+}
+
+/// the canonical request of Signature Version 4 and the names of its signed headers
+///
+/// `path` and `query` are encoded already, the query sorted, and `headers`
+/// are lowercase names with trimmed values in the order of the names.
+fn canonical_request(method: &str, path: &str, query: &str, headers: &[(String, String)], payload_hash: &str) -> (String, String) {
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{name}:{value}\n")).collect();
+    let signed_headers = headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+    let request = format!("{method}\n{path}\n{query}\n{canonical_headers}\n{signed_headers}\n{payload_hash}");
+    (request, signed_headers)
+}
+
+/// the credential scope of a request made at `timestamp`, like `20130524T000000Z`
+fn scope(timestamp: &str, region: &str) -> String {
+    let date = timestamp.split('T').next().unwrap_or(timestamp);
+    format!("{date}/{region}/s3/aws4_request")
+}
+
+/// the signature of a canonical request made at `timestamp`, in hex
+fn signature(secret_key: &str, region: &str, timestamp: &str, canonical_request: &str) -> String {
+    let scope = scope(timestamp, region);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{timestamp}\n{scope}\n{}", hex(&Sha256::digest(canonical_request.as_bytes())));
+    let date = timestamp.split('T').next().unwrap_or(timestamp);
+    let mut key = hmac(format!("AWS4{secret_key}").as_bytes(), date.as_bytes());
+    for part in [region, "s3", "aws4_request"] {
+        key = hmac(&key, part.as_bytes());
+    }
+    hex(&hmac(&key, string_to_sign.as_bytes()))
+}
+
+/// percent-encode all but the unreserved characters, and the slashes if `keep_slash`
+fn uri_encode(text: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// HMAC-SHA256 of RFC 2104, the keyed hash S3 signatures chain
+fn hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; 64];
+    match key.len() > block.len() {
+        true => block[..32].copy_from_slice(&Sha256::digest(key)),
+        false => block[..key.len()].copy_from_slice(key),
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.map(|byte| byte ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|byte| byte ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// the inside of each `<tag>` element of a document, elements of the same tag are not nested in S3 answers
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        found.push(&rest[..end]);
+        rest = &rest[end + close.len()..];
+    }
+    found
+}
+
+/// the text of the first `<tag>` element
+fn element(xml: &str, tag: &str) -> Option<String> {
+    elements(xml, tag).first().map(|text| unescape(text))
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()).and_then(char::from_u32),
+            },
+        };
+        match decoded {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// the error an S3 error document tells, if the document is one
+fn error_document(xml: &str) -> Option<io::Error> {
+    let error = elements(xml, "Error").into_iter().next()?;
+    let code = element(error, "Code").unwrap_or_default();
+    let message = element(error, "Message").unwrap_or_else(|| code.clone());
+    let kind = match code.as_str() {
+        "NoSuchKey" | "NoSuchBucket" | "NoSuchUpload" => io::ErrorKind::NotFound,
+        "AccessDenied" | "InvalidAccessKeyId" | "SignatureDoesNotMatch" | "ExpiredToken" => io::ErrorKind::PermissionDenied,
+        "BucketAlreadyExists" | "BucketAlreadyOwnedByYou" => io::ErrorKind::AlreadyExists,
+        "BucketNotEmpty" => io::ErrorKind::DirectoryNotEmpty,
+        _ => io::ErrorKind::Other,
+    };
+    Some(io::Error::new(kind, format!("{code}: {message}")))
+}
+
+/// error of an answer without a document, like those to HEAD requests
+fn status_error(status: u16, message: &str) -> io::Error {
+    let kind = match status {
+        404 => io::ErrorKind::NotFound,
+        401 | 403 => io::ErrorKind::PermissionDenied,
+        409 => io::ErrorKind::AlreadyExists,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, message.to_string())
+}
+
+/// time of `2024-03-01T12:30:00.000Z`
+fn parse_iso_time(text: &str) -> Option<SystemTime> {
+    let (date, time) = text.split_once('T')?;
+    let mut date = date.split('-').map(str::parse::<u32>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.trim_end_matches('Z').split(':');
+    let hour = time.next()?.parse().ok()?;
+    let minute = time.next()?.parse().ok()?;
+    let second = time.next()?.split('.').next()?.parse().ok()?;
+    Some(unix_time(civil_to_unix(year as i64, month, day, hour, minute, second)))
+}
+
+/// time of `Fri, 01 Mar 2024 12:30:00 GMT`
+fn parse_http_time(text: &str) -> Option<SystemTime> {
+    let mut words = text.split_whitespace().skip(1);
+    let day = words.next()?.parse().ok()?;
+    let month = words.next()?;
+    let month = MONTHS.iter().position(|name| month.starts_with(name))? as u32 + 1;
+    let year = words.next()?.parse().ok()?;
+    let mut time = words.next()?.split(':').map(str::parse::<u32>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    Some(unix_time(civil_to_unix(year, month, day, hour, minute, second)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the hash of an empty body
+    const EMPTY_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    /// the key of the examples of the AWS documentation
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+
+    fn headers(headers: &[(&str, &str)]) -> Vec<(String, String)> {
+        headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        assert_eq!(hex(&hmac(&[0x0b; 20], b"Hi There")), "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+        assert_eq!(
+            hex(&hmac(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // a key longer than a block is hashed first
+        assert_eq!(
+            hex(&hmac(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn uri_encode_keeps_unreserved_characters() {
+        assert_eq!(uri_encode("a-b_c.d~e/f g", true), "a-b_c.d~e/f%20g");
+        assert_eq!(uri_encode("a/b+c=d", false), "a%2Fb%2Bc%3Dd");
+        assert_eq!(uri_encode("é", false), "%C3%A9");
+    }
+
+    #[test]
+    fn signs_the_get_object_example() {
+        let signed = headers(&[
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("range", "bytes=0-9"),
+            ("x-amz-content-sha256", EMPTY_HASH),
+            ("x-amz-date", "20130524T000000Z"),
+        ]);
+        let (request, signed_headers) = canonical_request("GET", "/test.txt", "", &signed, EMPTY_HASH);
+        assert_eq!(signed_headers, "host;range;x-amz-content-sha256;x-amz-date");
+        assert_eq!(hex(&Sha256::digest(request.as_bytes())), "7344ae5b7ee6c3e7e6b0fe0640412a37625d1fbfff95c48bbb2dc43964946972");
+        assert_eq!(scope("20130524T000000Z", "us-east-1"), "20130524/us-east-1/s3/aws4_request");
+        assert_eq!(
+            signature(SECRET_KEY, "us-east-1", "20130524T000000Z", &request),
+            "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41"
+        );
+    }
+
+    #[test]
+    fn signs_the_list_objects_example() {
+        let signed = headers(&[
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("x-amz-content-sha256", EMPTY_HASH),
+            ("x-amz-date", "20130524T000000Z"),
+        ]);
+        let (request, _) = canonical_request("GET", "/", "max-keys=2&prefix=J", &signed, EMPTY_HASH);
+        assert_eq!(
+            signature(SECRET_KEY, "us-east-1", "20130524T000000Z", &request),
+            "34b48302e7b5fa45bde8084f4b7868a86f0a534bc59db6670ed5711ef69dc6f7"
+        );
+    }
+
+    #[test]
+    fn parses_the_times_of_s3() {
+        let time = unix_time(civil_to_unix(2024, 3, 1, 12, 30, 0));
+        assert_eq!(parse_iso_time("2024-03-01T12:30:00.000Z"), Some(time));
+        assert_eq!(parse_iso_time("2024-03-01T12:30:00Z"), Some(time));
+        assert_eq!(parse_http_time("Fri, 01 Mar 2024 12:30:00 GMT"), Some(time));
+        assert_eq!(parse_iso_time("yesterday"), None);
+        assert_eq!(parse_http_time("Fri, 01 Foo 2024 12:30:00 GMT"), None);
+    }
+
+    #[test]
+    fn unescapes_entities() {
+        assert_eq!(unescape("a &amp; b &lt;c&gt; &quot;d&quot; &apos;e&apos;"), "a & b <c> \"d\" 'e'");
+        assert_eq!(unescape("&#233;&#xE9;&#XE9;"), "ééé");
+        // what is not an entity stays as it is
+        assert_eq!(unescape("a & b &unknown; c&"), "a & b &unknown; c&");
+        assert_eq!(unescape(&escape("<a & \"b\">")), "<a & \"b\">");
+    }
+
+    #[test]
+    fn reads_error_documents() {
+        let xml = "<?xml version=\"1.0\"?><Error><Code>NoSuchKey</Code><Message>The key &quot;a&quot; does not exist</Message></Error>";
+        let err = error_document(xml).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "NoSuchKey: The key \"a\" does not exist");
+        let err = error_document("<Error><Code>AccessDenied</Code></Error>").unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(err.to_string(), "AccessDenied: AccessDenied");
+        assert!(error_document("<ListBucketResult></ListBucketResult>").is_none());
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use sevenz_rust::{Archive, BlockDecoder, Password, SevenZMethod, SevenZReader};
+
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::FsNodeType;
+use crate::vfs::archive::{read_only, send_chunks, stream, unix_time, ArchiveIndex, ArchiveMember, PasswordError};
+use crate::vfs::{Vfs, VfsEntry, VfsRef};
+
+/// windows attribute telling the high 16 bits hold a unix mode
+const UNIX_EXTENSION: u32 = 0x8000;
+
+/// seconds between 1601, where windows file times start, and the unix epoch
+const WINDOWS_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+/// a 7z archive browsed as a read-only directory tree
+#[derive(Debug)]
+pub struct SevenZFs {
+    index: ArchiveIndex,
+    archive: PathBuf,
+    listing: Arc<Archive>,
+    host: VfsRef,
+    /// decrypts the encrypted blocks, empty without a password
+    password: Vec<u8>,
+    encrypted: bool,
+}
+
+impl SevenZFs {
+    /// open a 7z file of a backend that has local paths
+    ///
+    /// The members of an archive whose content only is encrypted are listed,
+    /// reading them fails with a [`PasswordError`].
+    pub fn open(host: VfsRef, path: &Path) -> io::Result<SevenZFs> {
+        SevenZFs::open_with_password(host, path, None)
+    }
+
+    /// open a 7z file, checking the password by decoding the first encrypted member
+    pub fn open_with_password(host: VfsRef, path: &Path, password: Option<&str>) -> io::Result<SevenZFs> {
+        let archive = host
+            .local_path(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "archive is not on a local filesystem"))?;
+        let root_metadata = host.metadata(path)?.metadata;
+        let password = password.map(Password::from).unwrap_or_else(Password::empty);
+        let reader = SevenZReader::open(&archive, password.clone()).map_err(|err| io_error(err, password.as_slice()))?;
+        let password = password.to_vec();
+        let listing = Arc::new(reader.archive().clone());
+
+        let mut index = ArchiveIndex::new(path.to_path_buf(), root_metadata.clone());
+        let mut symlinks = BTreeMap::new();
+        for (position, file) in listing.files.iter().enumerate() {
+            if file.is_anti_item {
+                continue;
+            }
+            let mode = (file.has_windows_attributes && file.windows_attributes & UNIX_EXTENSION != 0)
+                .then_some(file.windows_attributes >> 16);
+            let node_type = if file.is_directory {
+                FsNodeType::Directory
+            } else if mode.is_some_and(|mode| mode & 0o170000 == 0o120000) {
+                symlinks.insert(position, file.name.clone());
+                continue;
+            } else {
+                FsNodeType::File
+            };
+            let default_mode = if file.is_directory { 0o40755 } else { 0o100644 };
+            let modified = file.has_last_modified_date.then(|| windows_time(file.last_modified_date.to_raw()));
+            index.insert(&file.name, ArchiveMember {
+                node_type,
+                metadata: member_metadata(&root_metadata, file.size, modified, mode.unwrap_or(default_mode)),
+                position,
+            });
+        }
+
+        let encrypted = listing
+            .folders
+            .iter()
+            .any(|folder| folder.coders.iter().any(|coder| coder.decompression_method_id() == SevenZMethod::ID_AES256SHA256));
+        let mut sevenz = SevenZFs {
+            index,
+            archive,
+            listing,
+            host,
+            password,
+            encrypted,
+        };
+        sevenz.read_symlinks(symlinks, &root_metadata)?;
+        if sevenz.encrypted && !sevenz.password.is_empty() {
+            sevenz.check_password()?;
+        }
+        Ok(sevenz)
+    }
+
+    /// true if some members can only be read with a password
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    /// record the symlinks with their targets, the content of their members
+    fn read_symlinks(&mut self, mut symlinks: BTreeMap<usize, String>, root_metadata: &FsMetadata) -> io::Result<()> {
+        let mut targets = Vec::new();
+        let folders: BTreeSet<_> = symlinks.keys().filter_map(|&position| self.listing.stream_map.file_folder_index[position]).collect();
+        for folder in folders {
+            self.decode(folder, |position, reader| {
+                if symlinks.contains_key(&position) {
+                    let mut target = String::new();
+                    reader.read_to_string(&mut target)?;
+                    targets.push((position, target));
+                }
+                Ok(true)
+            })?;
+        }
+        for (position, target) in targets {
+            let Some(name) = symlinks.remove(&position) else {
+                continue;
+            };
+            let file = &self.listing.files[position];
+            let modified = file.has_last_modified_date.then(|| windows_time(file.last_modified_date.to_raw()));
+            self.index.insert(&name, ArchiveMember {
+                node_type: FsNodeType::Symlink { target: PathBuf::from(target), broken: false },
+                metadata: member_metadata(root_metadata, file.size, modified, file.windows_attributes >> 16),
+                position,
+            });
+        }
+        Ok(())
+    }
+
+    /// decode the first member of an encrypted block, a wrong password garbles it
+    fn check_password(&self) -> io::Result<()> {
+        let Some(folder) = self
+            .listing
+            .folders
+            .iter()
+            .position(|folder| folder.coders.iter().any(|coder| coder.decompression_method_id() == SevenZMethod::ID_AES256SHA256))
+        else {
+            return Ok(());
+        };
+        self.decode(folder, |_, reader| {
+            io::copy(reader, &mut io::sink())?;
+            Ok(false)
+        })
+    }
+
+    /// decode a block, handing each of its members to `each` until it returns false
+    ///
+    /// Blocks are solid, the members before the one wanted are decoded too.
+    fn decode<F>(&self, folder: usize, each: F) -> io::Result<()>
+    where
+        F: FnMut(usize, &mut dyn Read) -> io::Result<bool>,
+    {
+        decode_folder(&self.archive, &self.listing, &self.password, folder, each)
+    }
+}
+
+impl Vfs for SevenZFs {
+    fn scheme(&self) -> &str {
+        "7z"
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        self.index.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsEntry> {
+        self.index.metadata(path)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let member = self.index.member(path)?;
+        if member.node_type != FsNodeType::File {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, path.display().to_string()));
+        }
+        let position = member.position;
+        // empty files have no block
+        let Some(folder) = self.listing.stream_map.file_folder_index[position] else {
+            return Ok(Box::new(Cursor::new(Vec::new())));
+        };
+        let archive = self.archive.clone();
+        let listing = Arc::clone(&self.listing);
+        let password = self.password.clone();
+        Ok(stream(move |sender| {
+            decode_folder(&archive, &listing, &password, folder, |index, reader| match index == position {
+                true => send_chunks(reader, sender).map(|_| false),
+                false => io::copy(reader, &mut io::sink()).map(|_| true),
+            })
+        }))
+    }
+
+    fn open_write(&self, _path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Err(read_only())
+    }
+
+    fn remove(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn mkdir(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.index
+            .metadata(path)
+            .map(|entry| entry.node_type == FsNodeType::Directory)
+            .unwrap_or(false)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        match &self.index.member(path)?.node_type {
+            FsNodeType::Symlink { target, .. } => Ok(target.clone()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "not a symlink")),
+        }
+    }
+
+    fn host(&self) -> Option<(VfsRef, PathBuf)> {
+        Some((VfsRef::clone(&self.host), self.index.root().to_path_buf()))
+    }
+}
+
+/// decode block `folder` of the archive file, `each` gets the position of every member in it
+fn decode_folder<F>(archive: &Path, listing: &Archive, password: &[u8], folder: usize, mut each: F) -> io::Result<()>
+where
+    F: FnMut(usize, &mut dyn Read) -> io::Result<bool>,
+{
+    let mut file = File::open(archive)?;
+    let mut position = listing.stream_map.folder_first_file_index[folder];
+    BlockDecoder::new(folder, listing, password, &mut file)
+        .for_each_entries(&mut |_, reader: &mut dyn Read| {
+            position += 1;
+            each(position - 1, reader).map_err(sevenz_rust::Error::io)
+        })
+        .map(|_| ())
+        .map_err(|err| io_error(err, password))
+}
+
+fn member_metadata(root_metadata: &FsMetadata, size: u64, modified: Option<SystemTime>, permissions: u32) -> FsMetadata {
+    FsMetadata {
+        size,
+        modified: modified.or(root_metadata.modified),
+        changed: modified.or(root_metadata.changed),
+        permissions,
+        uid: root_metadata.uid,
+        gid: root_metadata.gid,
+        inode: 0,
+        device: 0,
+        nlink: 1,
+        hidden: false,
+    }
+}
+
+/// time of a windows file time, in 100 nanoseconds since 1601
+fn windows_time(raw: u64) -> SystemTime {
+    unix_time((raw / 10_000_000) as i64 - WINDOWS_EPOCH_OFFSET)
+}
+
+/// io error of a 7z error, data that does not decode with a password is taken as a wrong password
+///
+/// A password is only asked for an encrypted archive, and the archive gives
+/// no other way to tell it is wrong than the garbage decrypted with it.
+fn io_error(err: sevenz_rust::Error, password: &[u8]) -> io::Error {
+    use sevenz_rust::Error;
+    match err {
+        Error::PasswordRequired => PasswordError::Required.into(),
+        Error::MaybeBadPassword(_) => PasswordError::Wrong.into(),
+        Error::FileOpen(err, _) => err,
+        _ if !password.is_empty() => PasswordError::Wrong.into(),
+        Error::Io(err, _) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err.to_string()),
+    }
+}
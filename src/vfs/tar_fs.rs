@@ -0,0 +1,202 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::SyncSender;
+
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::FsNodeType;
+use crate::vfs::archive::{read_only, send_chunks, stream, unix_time, ArchiveIndex, ArchiveMember};
+use crate::vfs::{Vfs, VfsEntry, VfsRef};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarCompression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl TarCompression {
+    /// compression of a tarball from its file name
+    pub fn from_name(name: &str) -> Option<TarCompression> {
+        let name = name.to_lowercase();
+        if name.ends_with(".tar") {
+            Some(TarCompression::None)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(TarCompression::Gzip)
+        } else if name.ends_with(".tar.bz2") || name.ends_with(".tbz2") || name.ends_with(".tbz") {
+            Some(TarCompression::Bzip2)
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            Some(TarCompression::Xz)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(TarCompression::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+/// a tarball browsed as a read-only directory tree
+#[derive(Debug)]
+pub struct TarFs {
+    index: ArchiveIndex,
+    archive: PathBuf,
+    compression: TarCompression,
+    /// offsets of the member data in an uncompressed tarball
+    offsets: Vec<u64>,
+    host: VfsRef,
+}
+
+impl TarFs {
+    /// open a tarball of a backend that has local paths
+    pub fn open(host: VfsRef, path: &Path, compression: TarCompression) -> io::Result<TarFs> {
+        let archive = host
+            .local_path(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "archive is not on a local filesystem"))?;
+        let root_metadata = host.metadata(path)?.metadata;
+
+        let mut index = ArchiveIndex::new(path.to_path_buf(), root_metadata);
+        let mut offsets = Vec::new();
+        let mut tar = tar::Archive::new(decompress(&archive, compression)?);
+        for (position, entry) in tar.entries()?.enumerate() {
+            let entry = entry?;
+            let header = entry.header();
+            let entry_type = header.entry_type();
+            offsets.push(entry.raw_file_position());
+
+            let node_type = if entry_type.is_dir() {
+                FsNodeType::Directory
+            } else if entry_type.is_symlink() {
+                let target = entry.link_name()?.map(|target| target.into_owned()).unwrap_or_default();
+                FsNodeType::Symlink { target, broken: false }
+            } else if entry_type.is_file() || entry_type.is_contiguous() {
+                FsNodeType::File
+            } else {
+                // hard links, devices and fifos cannot be streamed out
+                continue;
+            };
+            let modified = header.mtime().ok().map(|mtime| unix_time(mtime as i64));
+            let metadata = FsMetadata {
+                size: entry.size(),
+                modified,
+                changed: modified,
+                permissions: header.mode().unwrap_or(0o644) | file_type_bits(&node_type),
+                uid: header.uid().unwrap_or(0) as u32,
+                gid: header.gid().unwrap_or(0) as u32,
+                inode: 0,
+                device: 0,
+                nlink: 1,
+                hidden: false,
+            };
+            let name = entry.path()?.to_string_lossy().into_owned();
+            index.insert(&name, ArchiveMember { node_type, metadata, position });
+        }
+
+        Ok(TarFs {
+            index,
+            archive,
+            compression,
+            offsets,
+            host,
+        })
+    }
+
+    /// stream a member by decompressing the tarball up to it on a helper thread
+    fn stream_member(&self, position: usize) -> io::Result<Box<dyn Read + Send>> {
+        let reader = decompress(&self.archive, self.compression)?;
+        Ok(stream(move |sender| send_member(reader, position, sender)))
+    }
+}
+
+impl Vfs for TarFs {
+    fn scheme(&self) -> &str {
+        "tar"
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        self.index.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsEntry> {
+        self.index.metadata(path)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let member = self.index.member(path)?;
+        if member.node_type != FsNodeType::File {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, path.display().to_string()));
+        }
+        if self.compression == TarCompression::None {
+            let mut file = File::open(&self.archive)?;
+            file.seek(SeekFrom::Start(self.offsets[member.position]))?;
+            return Ok(Box::new(file.take(member.metadata.size)));
+        }
+        self.stream_member(member.position)
+    }
+
+    fn open_write(&self, _path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Err(read_only())
+    }
+
+    fn remove(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn mkdir(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.index
+            .metadata(path)
+            .map(|entry| entry.node_type == FsNodeType::Directory)
+            .unwrap_or(false)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        match &self.index.member(path)?.node_type {
+            FsNodeType::Symlink { target, .. } => Ok(target.clone()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "not a symlink")),
+        }
+    }
+
+    fn host(&self) -> Option<(VfsRef, PathBuf)> {
+        Some((VfsRef::clone(&self.host), self.index.root().to_path_buf()))
+    }
+}
+
+fn decompress(archive: &Path, compression: TarCompression) -> io::Result<Box<dyn Read + Send>> {
+    let file = BufReader::new(File::open(archive)?);
+    Ok(match compression {
+        TarCompression::None => Box::new(file),
+        TarCompression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        TarCompression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+        TarCompression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(file)),
+        TarCompression::Zstd => Box::new(
+            ruzstd::decoding::StreamingDecoder::new(file)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?,
+        ),
+    })
+}
+
+fn send_member(reader: Box<dyn Read + Send>, position: usize, sender: &SyncSender<io::Result<Vec<u8>>>) -> io::Result<()> {
+    let mut tar = tar::Archive::new(reader);
+    let mut entry = tar
+        .entries()?
+        .nth(position)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "member vanished from archive"))??;
+    send_chunks(&mut entry, sender)
+}
+
+fn file_type_bits(node_type: &FsNodeType) -> u32 {
+    match node_type {
+        FsNodeType::Directory => 0o40000,
+        FsNodeType::Symlink { .. } => 0o120000,
+        FsNodeType::File => 0o100000,
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::trash::Trash;
+use crate::vfs::{LocalFs, Vfs, VfsEntry};
+
+/// browsing backend over a trash can, opened as `trash://`
+///
+/// The root lists the trashed entries under their trash name, inside them
+/// the trashed directories can be browsed. Nothing can be written, removing
+/// an entry of the root purges it.
+#[derive(Debug, Clone)]
+pub struct TrashFs {
+    pub trash: Trash,
+}
+
+impl TrashFs {
+    pub fn new(trash: Trash) -> TrashFs {
+        TrashFs { trash }
+    }
+
+    /// name in the trash of the entry a path belongs to, `None` for the root
+    pub fn item_name(path: &Path) -> Option<String> {
+        path.components().find_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+    }
+
+    fn files_path(&self, path: &Path) -> PathBuf {
+        let relative = path.strip_prefix("/").unwrap_or(path);
+        self.trash.files_dir().join(relative)
+    }
+
+    /// an entry of the files directory seen with its path in the trash
+    fn entry(&self, path: &Path) -> io::Result<VfsEntry> {
+        let mut entry = LocalFs.metadata(&self.files_path(path))?;
+        entry.path = path.to_path_buf();
+        if path == Path::new("/") {
+            entry.name = String::from("/");
+        }
+        Ok(entry)
+    }
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::ReadOnlyFilesystem, "the trash is read-only, restore entries first")
+}
+
+impl Vfs for TrashFs {
+    fn scheme(&self) -> &str {
+        "trash"
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        if TrashFs::item_name(path).is_none() {
+            // a trash never used has no directories yet
+            let items = self.trash.items()?;
+            return Ok(items
+                .into_iter()
+                .filter_map(|item| self.entry(&Path::new("/").join(&item.name)).ok())
+                .collect());
+        }
+        let mut entries = LocalFs.read_dir(&self.files_path(path))?;
+        for entry in &mut entries {
+            entry.path = path.join(&entry.name);
+        }
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsEntry> {
+        if TrashFs::item_name(path).is_none() && self.trash.files_dir().symlink_metadata().is_err() {
+            return LocalFs.metadata(Path::new("/")).map(|mut entry| {
+                entry.name = String::from("/");
+                entry
+            });
+        }
+        self.entry(path)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        LocalFs.open_read(&self.files_path(path))
+    }
+
+    fn open_write(&self, _path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Err(read_only())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match TrashFs::item_name(path) {
+            Some(name) if path.strip_prefix("/").unwrap_or(path) == Path::new(&name) => self.trash.purge(&name),
+            _ => Err(read_only()),
+        }
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn mkdir(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        LocalFs.read_link(&self.files_path(path))
+    }
+
+    fn display_path(&self, path: &Path) -> String {
+        format!("trash://{}", path.display())
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.files_path(path))
+    }
+}
@@ -0,0 +1,192 @@
+use std::fs::File;
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use parking_lot::Mutex;
+use zip::read::ZipReadOptions;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::fs_metadata::FsMetadata;
+use crate::fs_node::FsNodeType;
+use crate::vfs::archive::{civil_to_unix, read_only, unix_time, ArchiveIndex, ArchiveMember, PasswordError};
+use crate::vfs::{Vfs, VfsEntry, VfsRef};
+
+/// a zip archive browsed as a read-only directory tree
+#[derive(Debug)]
+pub struct ZipFs {
+    index: ArchiveIndex,
+    archive: Mutex<ZipArchive<File>>,
+    host: VfsRef,
+    /// decrypts the encrypted members
+    password: Option<Vec<u8>>,
+    encrypted: bool,
+}
+
+impl ZipFs {
+    /// open a zip file of a backend that has local paths
+    ///
+    /// The members of an encrypted zip are listed, reading them fails with
+    /// a [`PasswordError`].
+    pub fn open(host: VfsRef, path: &Path) -> io::Result<ZipFs> {
+        ZipFs::open_with_password(host, path, None)
+    }
+
+    /// open a zip file, checking the password against its first encrypted member
+    pub fn open_with_password(host: VfsRef, path: &Path, password: Option<&str>) -> io::Result<ZipFs> {
+        let local = host
+            .local_path(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "archive is not on a local filesystem"))?;
+        let root_metadata = host.metadata(path)?.metadata;
+        let mut archive = ZipArchive::new(File::open(local)?).map_err(io::Error::other)?;
+
+        let password = password.map(|password| password.as_bytes().to_vec());
+
+        let mut index = ArchiveIndex::new(path.to_path_buf(), root_metadata.clone());
+        let mut encrypted = None;
+        for position in 0..archive.len() {
+            // the raw member gives the metadata without decrypting, only a symlink target is read
+            let raw = archive.by_index_raw(position).map_err(io::Error::other)?;
+            let symlink = raw.is_symlink();
+            if raw.encrypted() {
+                encrypted.get_or_insert(position);
+            }
+            drop(raw);
+            let mut file = match symlink {
+                true => read_member(&mut archive, position, password.as_deref())?,
+                false => archive.by_index_raw(position).map_err(io::Error::other)?,
+            };
+            let Ok(name) = file.name().map(|name| name.into_owned()) else {
+                continue;
+            };
+            let modified = file.last_modified().map(|time| {
+                unix_time(civil_to_unix(
+                    time.year() as i64,
+                    time.month() as u32,
+                    time.day() as u32,
+                    time.hour() as u32,
+                    time.minute() as u32,
+                    time.second() as u32,
+                ))
+            });
+
+            let node_type = if file.is_dir() {
+                FsNodeType::Directory
+            } else if file.is_symlink() {
+                let mut target = String::new();
+                file.read_to_string(&mut target)?;
+                FsNodeType::Symlink { target: PathBuf::from(target), broken: false }
+            } else {
+                FsNodeType::File
+            };
+            let default_mode = if file.is_dir() { 0o40755 } else { 0o100644 };
+
+            index.insert(&name, ArchiveMember {
+                node_type,
+                metadata: FsMetadata {
+                    size: file.size(),
+                    modified: modified.or(root_metadata.modified),
+                    changed: modified.or(root_metadata.changed),
+                    permissions: file.unix_mode().unwrap_or(default_mode),
+                    uid: root_metadata.uid,
+                    gid: root_metadata.gid,
+                    inode: 0,
+                    device: 0,
+                    nlink: 1,
+                    hidden: false,
+                },
+                position,
+            });
+        }
+
+        if let Some(position) = encrypted
+            && password.is_some()
+        {
+            read_member(&mut archive, position, password.as_deref())?;
+        }
+
+        Ok(ZipFs {
+            index,
+            archive: Mutex::new(archive),
+            host,
+            password,
+            encrypted: encrypted.is_some(),
+        })
+    }
+
+    /// true if some members can only be read with a password
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+}
+
+impl Vfs for ZipFs {
+    fn scheme(&self) -> &str {
+        "zip"
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsEntry>> {
+        self.index.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsEntry> {
+        self.index.metadata(path)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let member = self.index.member(path)?;
+        if member.node_type != FsNodeType::File {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, path.display().to_string()));
+        }
+        // members borrow the archive, so they are decompressed up front
+        let mut archive = self.archive.lock();
+        let mut file = read_member(&mut archive, member.position, self.password.as_deref())?;
+        let mut content = Vec::with_capacity(member.metadata.size as usize);
+        file.read_to_end(&mut content)?;
+        Ok(Box::new(Cursor::new(content)))
+    }
+
+    fn open_write(&self, _path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Err(read_only())
+    }
+
+    fn remove(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn mkdir(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.index
+            .metadata(path)
+            .map(|entry| entry.node_type == FsNodeType::Directory)
+            .unwrap_or(false)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        match &self.index.member(path)?.node_type {
+            FsNodeType::Symlink { target, .. } => Ok(target.clone()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "not a symlink")),
+        }
+    }
+
+    fn host(&self) -> Option<(VfsRef, PathBuf)> {
+        Some((VfsRef::clone(&self.host), self.index.root().to_path_buf()))
+    }
+}
+
+/// a member of the archive ready to be read, decrypted with `password`
+fn read_member<'a>(archive: &'a mut ZipArchive<File>, position: usize, password: Option<&[u8]>) -> io::Result<zip::read::ZipFile<'a, File>> {
+    match archive.by_index_with_options(position, ZipReadOptions::new().password(password)) {
+        Ok(file) => Ok(file),
+        Err(ZipError::InvalidPassword) => Err(PasswordError::Wrong.into()),
+        Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => Err(PasswordError::Required.into()),
+        Err(err) => Err(io::Error::other(err)),
+    }
+}
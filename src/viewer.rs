@@ -0,0 +1,596 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::commands::PromptKind;
+use crate::filetype;
+use crate::highlight::{self, Highlighter};
+use crate::ui::prompt::Prompt;
+use crate::vfs::VfsRef;
+
+/// bytes read at once when looking for line ends or search matches
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// longest part of a line shown without wrapping, the rest is skipped
+const MAX_LINE: usize = 16 * 1024;
+
+const TAB_WIDTH: usize = 8;
+
+/// bytes of the viewed file, read on demand
+enum Source {
+    /// a local file read with seeks, nothing is kept in memory
+    File { file: File, len: u64 },
+    /// any other stream, kept in memory up to the furthest byte read
+    Stream {
+        reader: Box<dyn Read + Send>,
+        buffer: Vec<u8>,
+        eof: bool,
+    },
+}
+
+impl Source {
+    fn read_at(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        match self {
+            Source::File { file, len: file_len } => {
+                if offset >= *file_len {
+                    return Ok(Vec::new());
+                }
+                file.seek(SeekFrom::Start(offset))?;
+                let mut data = Vec::with_capacity(len);
+                file.take(len as u64).read_to_end(&mut data)?;
+                Ok(data)
+            }
+            Source::Stream { reader, buffer, eof } => {
+                let end = offset.saturating_add(len as u64) as usize;
+                while !*eof && buffer.len() < end {
+                    let mut chunk = vec![0; CHUNK_SIZE];
+                    let read = reader.read(&mut chunk)?;
+                    if read == 0 {
+                        *eof = true;
+                    }
+                    buffer.extend_from_slice(&chunk[..read]);
+                }
+                let start = (offset as usize).min(buffer.len());
+                Ok(buffer[start..end.min(buffer.len())].to_vec())
+            }
+        }
+    }
+
+    /// size of the file, streams are read to the end to know it
+    fn len(&mut self) -> io::Result<u64> {
+        if let Source::Stream { buffer, eof: false, .. } = self {
+            let offset = buffer.len() as u64;
+            self.read_at(offset, usize::MAX / 2)?;
+        }
+        Ok(self.known_len().unwrap_or(0))
+    }
+
+    fn known_len(&self) -> Option<u64> {
+        match self {
+            Source::File { len, .. } => Some(*len),
+            Source::Stream { buffer, eof: true, .. } => Some(buffer.len() as u64),
+            Source::Stream { .. } => None,
+        }
+    }
+}
+
+/// bytes per row of the hex mode
+pub const HEX_WIDTH: u64 = 16;
+
+/// one screen row, every displayed cell with the offset of the byte it comes from
+///
+/// In hex mode every cell is one byte, stored as the char of the same value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewRow {
+    pub start: u64,
+    pub cells: Vec<(char, u64)>,
+}
+
+/// what a key did to the viewer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViewerAction {
+    None,
+    Close,
+    Prompt(Prompt),
+    Message(String),
+}
+
+/// input the viewer asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewerPrompt {
+    Goto,
+    Search,
+}
+
+/// read-only pager over a file of any backend
+pub struct Viewer {
+    pub path: PathBuf,
+    pub title: String,
+    source: Source,
+    /// offset of the first displayed row
+    pub top: u64,
+    pub wrap: bool,
+    /// show offsets, hex bytes and ascii instead of text
+    pub hex: bool,
+    /// columns scrolled to the right when not wrapping
+    pub left: usize,
+    /// rows of text of the last render, used for paging
+    pub height: usize,
+    pub width: usize,
+    /// last search, repeated with `n`
+    pub search: Option<Vec<u8>>,
+    /// offset and length of the last match
+    pub found: Option<(u64, usize)>,
+    pub highlighter: Option<Box<dyn Highlighter>>,
+}
+
+impl std::fmt::Debug for Viewer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Viewer").field("path", &self.path).field("top", &self.top).finish()
+    }
+}
+
+impl Viewer {
+    /// open a file, local files are read in place and others streamed
+    pub fn open(vfs: &VfsRef, path: &Path) -> io::Result<Viewer> {
+        let source = match vfs.local_path(path) {
+            Some(local) => {
+                let file = File::open(local)?;
+                let len = file.metadata()?.len();
+                Source::File { file, len }
+            }
+            None => Source::Stream {
+                reader: vfs.open_read(path)?,
+                buffer: Vec::new(),
+                eof: false,
+            },
+        };
+        let mut viewer = Viewer::open_source(path.to_path_buf(), vfs.display_path(path), source);
+        // binary files start in hex mode
+        let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        let head = viewer.source.read_at(0, filetype::SNIFF_LEN)?;
+        viewer.hex = !filetype::is_text(filetype::detect(&name, &head));
+        Ok(viewer)
+    }
+
+    fn open_source(path: PathBuf, title: String, source: Source) -> Viewer {
+        Viewer {
+            path,
+            title,
+            source,
+            top: 0,
+            wrap: true,
+            hex: false,
+            left: 0,
+            height: 24,
+            width: 80,
+            search: None,
+            found: None,
+            highlighter: None,
+        }
+    }
+
+    /// show text made by the application, like a report
+    pub fn from_text(title: &str, text: &str) -> Viewer {
+        let mut viewer = Viewer::open_source(PathBuf::from(title), title.to_string(), Source::Stream {
+            reader: Box::new(io::empty()),
+            buffer: text.as_bytes().to_vec(),
+            eof: true,
+        });
+        viewer.wrap = false;
+        viewer
+    }
+
+    /// color the text by language if the file type is known
+    pub fn enable_highlighting(&mut self, syntax_theme: &str) {
+        let first_line = self
+            .source
+            .read_at(0, 256)
+            .map(|data| {
+                let line = data.split(|&byte| byte == b'\n').next().unwrap_or_default();
+                String::from_utf8_lossy(line).into_owned()
+            })
+            .unwrap_or_default();
+        self.highlighter = highlight::for_file(&self.path, &first_line, syntax_theme);
+    }
+
+    /// path of the viewed file on the local filesystem, if it is a local file
+    pub fn local_path(&self) -> Option<PathBuf> {
+        match self.source {
+            Source::File { .. } => Some(self.path.clone()),
+            Source::Stream { .. } => None,
+        }
+    }
+
+    /// size of the file if already known
+    pub fn known_len(&self) -> Option<u64> {
+        self.source.known_len()
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> ViewerAction {
+        let result = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::F(3) | KeyCode::F(10) => return ViewerAction::Close,
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_up(1),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => self.scroll_down(1),
+            KeyCode::PageUp | KeyCode::Char('b') => self.scroll_up(self.height.saturating_sub(1).max(1)),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll_down(self.height.saturating_sub(1).max(1)),
+            KeyCode::Home | KeyCode::Char('g') => {
+                self.top = 0;
+                Ok(())
+            }
+            KeyCode::End | KeyCode::Char('G') => self.scroll_to_end(),
+            KeyCode::Left if !self.wrap && !self.hex => {
+                self.left = self.left.saturating_sub(1);
+                Ok(())
+            }
+            KeyCode::Right if !self.wrap && !self.hex => {
+                self.left += 1;
+                Ok(())
+            }
+            KeyCode::F(2) | KeyCode::Char('w') if !self.hex => self.toggle_wrap(),
+            KeyCode::F(4) | KeyCode::Char('h') => self.toggle_hex(),
+            KeyCode::F(5) | KeyCode::Char(':') => {
+                return ViewerAction::Prompt(Prompt::new(
+                    PromptKind::Viewer(ViewerPrompt::Goto),
+                    "Goto line, or offset with @ or 0x",
+                    "",
+                ));
+            }
+            KeyCode::F(7) | KeyCode::Char('/') => {
+                let previous = match &self.search {
+                    Some(search) if self.hex => format_hex(search),
+                    Some(search) => String::from_utf8_lossy(search).into_owned(),
+                    None => String::new(),
+                };
+                let title = if self.hex { "Search hex bytes" } else { "Search" };
+                return ViewerAction::Prompt(Prompt::new(PromptKind::Viewer(ViewerPrompt::Search), title, &previous));
+            }
+            KeyCode::Char('n') => return self.search_next(),
+            _ => Ok(()),
+        };
+        match result {
+            Ok(()) => ViewerAction::None,
+            Err(err) => ViewerAction::Message(err.to_string()),
+        }
+    }
+
+    /// finish a goto or a search
+    pub fn submit(&mut self, prompt: ViewerPrompt, input: &str) -> ViewerAction {
+        match prompt {
+            ViewerPrompt::Goto => match self.goto(input) {
+                Ok(()) => ViewerAction::None,
+                Err(err) => ViewerAction::Message(err.to_string()),
+            },
+            ViewerPrompt::Search => {
+                if input.is_empty() {
+                    return ViewerAction::None;
+                }
+                let needle = if self.hex {
+                    match parse_hex(input) {
+                        Some(bytes) => bytes,
+                        None => return ViewerAction::Message(format!("invalid hex bytes {input}")),
+                    }
+                } else {
+                    input.as_bytes().to_vec()
+                };
+                self.search = Some(needle);
+                self.found = None;
+                self.search_next()
+            }
+        }
+    }
+
+    /// `123` goes to a line, or an offset in hex mode, `@123` or `0x7b` to a byte offset
+    pub fn goto(&mut self, input: &str) -> io::Result<()> {
+        let input = input.trim();
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid position {input}"));
+        if let Some(hex) = input.strip_prefix("0x") {
+            let offset = u64::from_str_radix(hex, 16).map_err(|_| invalid())?;
+            return self.goto_offset(offset);
+        }
+        if let Some(decimal) = input.strip_prefix('@') {
+            let offset = decimal.parse().map_err(|_| invalid())?;
+            return self.goto_offset(offset);
+        }
+        let number: u64 = input.parse().map_err(|_| invalid())?;
+        // hex mode has no lines to speak of
+        if self.hex {
+            return self.goto_offset(number);
+        }
+        self.goto_line(number)
+    }
+
+    pub fn goto_offset(&mut self, offset: u64) -> io::Result<()> {
+        self.top = self.row_start(offset)?;
+        Ok(())
+    }
+
+    /// jump to a line, counted from 1
+    pub fn goto_line(&mut self, line: u64) -> io::Result<()> {
+        let mut position = 0;
+        for _ in 1..line {
+            match self.next_line_start(position)? {
+                Some(next) => position = next,
+                None => break,
+            }
+        }
+        self.top = self.row_start(position)?;
+        Ok(())
+    }
+
+    /// show a match found outside the viewer, `n` then looks for the same bytes
+    pub fn show_match(&mut self, line: u64, offset: u64, matched: &[u8]) -> io::Result<()> {
+        self.search = Some(matched.to_vec());
+        self.found = Some((offset, matched.len()));
+        self.goto_line(line)
+    }
+
+    fn search_next(&mut self) -> ViewerAction {
+        let Some(needle) = self.search.clone() else {
+            return ViewerAction::None;
+        };
+        let from = match self.found {
+            Some((offset, _)) => offset + 1,
+            None => self.top,
+        };
+        match self.find(&needle, from) {
+            Ok(Some(offset)) => {
+                self.found = Some((offset, needle.len()));
+                match self.row_start(offset) {
+                    Ok(start) => {
+                        self.top = start;
+                        ViewerAction::None
+                    }
+                    Err(err) => ViewerAction::Message(err.to_string()),
+                }
+            }
+            Ok(None) => {
+                self.found = None;
+                ViewerAction::Message(format!("\"{}\" not found", String::from_utf8_lossy(&needle)))
+            }
+            Err(err) => ViewerAction::Message(err.to_string()),
+        }
+    }
+
+    /// offset of the next occurrence of `needle` at or after `from`
+    pub fn find(&mut self, needle: &[u8], from: u64) -> io::Result<Option<u64>> {
+        if needle.is_empty() {
+            return Ok(None);
+        }
+        let mut offset = from;
+        loop {
+            let chunk = self.source.read_at(offset, CHUNK_SIZE + needle.len() - 1)?;
+            if chunk.len() < needle.len() {
+                return Ok(None);
+            }
+            if let Some(position) = chunk.windows(needle.len()).position(|window| window == needle) {
+                return Ok(Some(offset + position as u64));
+            }
+            offset += (chunk.len() - needle.len() + 1) as u64;
+        }
+    }
+
+    pub fn toggle_wrap(&mut self) -> io::Result<()> {
+        self.wrap = !self.wrap;
+        self.left = 0;
+        // rows of the wrapped mode may start inside a line
+        self.top = self.line_start(self.top)?;
+        Ok(())
+    }
+
+    /// switch between text and hex, keeping the position
+    pub fn toggle_hex(&mut self) -> io::Result<()> {
+        self.hex = !self.hex;
+        self.left = 0;
+        self.top = self.row_start(self.top)?;
+        Ok(())
+    }
+
+    /// start of the row holding `offset` in the current mode, lines are not split
+    fn row_start(&mut self, offset: u64) -> io::Result<u64> {
+        if self.hex {
+            return Ok(offset - offset % HEX_WIDTH);
+        }
+        self.line_start(offset)
+    }
+
+    /// start of the line holding `offset`
+    fn line_start(&mut self, offset: u64) -> io::Result<u64> {
+        let mut end = offset;
+        while end > 0 {
+            let start = end.saturating_sub(CHUNK_SIZE as u64);
+            let chunk = self.source.read_at(start, (end - start) as usize)?;
+            if let Some(position) = chunk.iter().rposition(|&byte| byte == b'\n') {
+                return Ok(start + position as u64 + 1);
+            }
+            end = start;
+        }
+        Ok(0)
+    }
+
+    /// start of the line after the one holding `offset`, none on the last line
+    fn next_line_start(&mut self, offset: u64) -> io::Result<Option<u64>> {
+        let mut position = offset;
+        loop {
+            let chunk = self.source.read_at(position, CHUNK_SIZE)?;
+            if chunk.is_empty() {
+                return Ok(None);
+            }
+            if let Some(index) = chunk.iter().position(|&byte| byte == b'\n') {
+                let next = position + index as u64 + 1;
+                return Ok(self.has_data(next)?.then_some(next));
+            }
+            position += chunk.len() as u64;
+        }
+    }
+
+    fn has_data(&mut self, offset: u64) -> io::Result<bool> {
+        Ok(!self.source.read_at(offset, 1)?.is_empty())
+    }
+
+    /// the row starting at `start` and where the next one begins
+    pub fn row(&mut self, start: u64) -> io::Result<Option<(ViewRow, Option<u64>)>> {
+        if self.hex {
+            return self.hex_row(start);
+        }
+        let width = if self.wrap { self.width.max(1) } else { MAX_LINE };
+        // a character is at most four bytes, tabs take one
+        let data = self.source.read_at(start, (width * 4).min(MAX_LINE))?;
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let mut cells = Vec::new();
+        let mut index = 0;
+        let mut newline = false;
+        while index < data.len() && cells.len() < width {
+            let (c, len) = decode_char(&data[index..]);
+            let offset = start + index as u64;
+            match c {
+                '\n' => {
+                    newline = true;
+                    index += 1;
+                    break;
+                }
+                '\t' => {
+                    let spaces = TAB_WIDTH - cells.len() % TAB_WIDTH;
+                    cells.extend(std::iter::repeat_n((' ', offset), spaces));
+                }
+                '\r' => {}
+                c if c.is_control() => cells.push(('.', offset)),
+                c => cells.push((c, offset)),
+            }
+            index += len;
+        }
+        // a line exactly as wide as the screen does not get an empty row after it
+        if !newline && data.get(index) == Some(&b'\n') {
+            newline = true;
+            index += 1;
+        }
+        let row = ViewRow { start, cells };
+        let end = start + index as u64;
+        let next = if newline || self.wrap {
+            self.has_data(end)?.then_some(end)
+        } else {
+            self.next_line_start(end.saturating_sub(1))?
+        };
+        Ok(Some((row, next)))
+    }
+
+    fn hex_row(&mut self, start: u64) -> io::Result<Option<(ViewRow, Option<u64>)>> {
+        let data = self.source.read_at(start, HEX_WIDTH as usize)?;
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let cells = data
+            .iter()
+            .zip(start..)
+            .map(|(&byte, offset)| (char::from(byte), offset))
+            .collect();
+        let end = start + data.len() as u64;
+        let next = self.has_data(end)?.then_some(end);
+        Ok(Some((ViewRow { start, cells }, next)))
+    }
+
+    /// the rows of the screen, from the top
+    pub fn rows(&mut self, height: usize) -> io::Result<Vec<ViewRow>> {
+        let mut rows = Vec::with_capacity(height);
+        let mut position = Some(self.top);
+        while rows.len() < height
+            && let Some(start) = position
+        {
+            match self.row(start)? {
+                Some((row, next)) => {
+                    rows.push(row);
+                    position = next;
+                }
+                None => break,
+            }
+        }
+        Ok(rows)
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) -> io::Result<()> {
+        for _ in 0..rows {
+            match self.row(self.top)? {
+                Some((_, Some(next))) => self.top = next,
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) -> io::Result<()> {
+        for _ in 0..rows {
+            if self.top == 0 {
+                break;
+            }
+            self.top = self.previous_row(self.top)?;
+        }
+        Ok(())
+    }
+
+    /// start of the row before the one at `offset`
+    fn previous_row(&mut self, offset: u64) -> io::Result<u64> {
+        if self.hex {
+            return self.row_start(offset - 1);
+        }
+        let line = self.line_start(offset - 1)?;
+        if !self.wrap {
+            return Ok(line);
+        }
+        // walk the wrapped rows of the previous line up to `offset`
+        let mut start = line;
+        while let Some((_, Some(next))) = self.row(start)? {
+            if next >= offset {
+                break;
+            }
+            start = next;
+        }
+        Ok(start)
+    }
+
+    /// show the last page of the file
+    pub fn scroll_to_end(&mut self) -> io::Result<()> {
+        self.top = self.source.len()?;
+        self.scroll_up(self.height.max(1))
+    }
+
+    /// position of the top row in percent of the file
+    pub fn percent(&self) -> Option<u64> {
+        let len = self.known_len()?;
+        Some((self.top * 100).checked_div(len).unwrap_or(100))
+    }
+}
+
+/// hex bytes like `de ad be ef` or `deadbeef`
+pub fn parse_hex(input: &str) -> Option<Vec<u8>> {
+    let digits: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+        .collect()
+}
+
+fn format_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>().join(" ")
+}
+
+/// first character of some bytes and its length, invalid bytes give a replacement character
+fn decode_char(bytes: &[u8]) -> (char, usize) {
+    let len = match bytes[0] {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return (char::REPLACEMENT_CHARACTER, 1),
+    };
+    match bytes.get(..len).and_then(|bytes| std::str::from_utf8(bytes).ok()) {
+        Some(text) => (text.chars().next().unwrap_or(char::REPLACEMENT_CHARACTER), len),
+        None => (char::REPLACEMENT_CHARACTER, 1),
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Weak};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::{Mutex, RwLock};
+
+use crate::fs_node::{node_type_from_path, FsNode, FsNodeRef};
+use crate::vfs;
+
+/// external change of an entry of a watched directory, seen on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeChange {
+    Added { directory: PathBuf, path: PathBuf },
+    Removed { directory: PathBuf, path: PathBuf },
+    Modified { directory: PathBuf, path: PathBuf },
+}
+
+impl TreeChange {
+    /// watched directory in which the change happened
+    pub fn directory(&self) -> &Path {
+        match self {
+            TreeChange::Added { directory, .. }
+            | TreeChange::Removed { directory, .. }
+            | TreeChange::Modified { directory, .. } => directory,
+        }
+    }
+
+    /// entry that changed
+    pub fn path(&self) -> &Path {
+        match self {
+            TreeChange::Added { path, .. } | TreeChange::Removed { path, .. } | TreeChange::Modified { path, .. } => path,
+        }
+    }
+}
+
+type WatchedNodes = Arc<Mutex<HashMap<PathBuf, Weak<RwLock<FsNode>>>>>;
+type Subscribers = Arc<Mutex<Vec<Sender<TreeChange>>>>;
+
+/// keeps loaded directory nodes in sync with the disk
+///
+/// The changes are found on the thread of the watcher and applied to the
+/// nodes by [`TreeWatcher::apply`], on the thread that draws them.
+pub struct TreeWatcher {
+    watcher: RecommendedWatcher,
+    watched: WatchedNodes,
+    subscribers: Subscribers,
+}
+
+impl TreeWatcher {
+    pub fn new() -> notify::Result<TreeWatcher> {
+        let watched: WatchedNodes = Arc::default();
+        let subscribers: Subscribers = Arc::default();
+
+        let handler_watched = Arc::clone(&watched);
+        let handler_subscribers = Arc::clone(&subscribers);
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                for change in changes(&handler_watched, &event) {
+                    handler_subscribers
+                        .lock()
+                        .retain(|subscriber| subscriber.send(change.clone()).is_ok());
+                }
+            }
+        })?;
+
+        Ok(TreeWatcher {
+            watcher,
+            watched,
+            subscribers,
+        })
+    }
+
+    /// receive every change seen in the watched directories
+    pub fn subscribe(&self) -> Receiver<TreeChange> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().push(sender);
+        receiver
+    }
+
+    /// watch the direct entries of a directory node
+    pub fn watch(&mut self, node: &FsNodeRef) -> notify::Result<()> {
+        let (path, local) = {
+            let node = node.read();
+            (node.path(), vfs::is_local(&node.vfs))
+        };
+        // only the local filesystem can be watched
+        if !local || self.watched.lock().contains_key(&path) {
+            return Ok(());
+        }
+        self.watcher.watch(&path, RecursiveMode::NonRecursive)?;
+        self.watched.lock().insert(path, Arc::downgrade(node));
+        Ok(())
+    }
+
+    pub fn unwatch(&mut self, path: &Path) -> notify::Result<()> {
+        if self.watched.lock().remove(path).is_some() {
+            self.watcher.unwatch(path)?;
+        }
+        Ok(())
+    }
+
+    /// watch exactly the given nodes, dropping every other watch
+    pub fn set_watched(&mut self, nodes: &[FsNodeRef]) -> notify::Result<()> {
+        let stale: Vec<PathBuf> = self
+            .watched
+            .lock()
+            .iter()
+            .filter(|(_, watched)| match watched.upgrade() {
+                Some(watched) => !nodes.iter().any(|node| Arc::ptr_eq(node, &watched)),
+                None => true,
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in stale {
+            self.unwatch(&path)?;
+        }
+        for node in nodes {
+            self.watch(node)?;
+        }
+        Ok(())
+    }
+
+    /// bring the children of the watched directories in line with the changes
+    ///
+    /// Returns true if a node changed. Each entry is looked at on disk again,
+    /// what it became since the change was seen is what counts.
+    pub fn apply(&self, changes: &[TreeChange]) -> bool {
+        let mut changed = false;
+        for change in changes {
+            let node = self.watched.lock().get(change.directory()).and_then(Weak::upgrade);
+            if let Some(node) = node {
+                changed |= sync_child(&node, change.path());
+            }
+        }
+        changed
+    }
+}
+
+/// the changes an event of the watcher makes to the watched directories
+fn changes(watched: &WatchedNodes, event: &Event) -> Vec<TreeChange> {
+    let mut changes = Vec::new();
+    // reading a directory produces access events, they never change the tree
+    if matches!(event.kind, EventKind::Access(_)) {
+        return changes;
+    }
+    for path in &event.paths {
+        let Some(directory) = path.parent() else {
+            continue;
+        };
+        if !watched.lock().contains_key(directory) {
+            continue;
+        }
+        let (directory, path) = (directory.to_path_buf(), path.clone());
+        changes.push(match (event.kind, path.symlink_metadata().is_ok()) {
+            (_, false) => TreeChange::Removed { directory, path },
+            (EventKind::Create(_), true) => TreeChange::Added { directory, path },
+            (_, true) => TreeChange::Modified { directory, path },
+        });
+    }
+    changes
+}
+
+/// bring a child of a directory node in line with the disk, true if it changed
+fn sync_child(directory: &FsNodeRef, path: &Path) -> bool {
+    let mut directory = directory.write();
+    let existing = directory.find_node(path.to_path_buf(), None);
+
+    match (existing, node_type_from_path(path)) {
+        (Some(_), None) => {
+            directory.remove_node(path.to_path_buf(), None);
+            true
+        }
+        (Some(child), Some(node_type)) => {
+            let mut child = child.write();
+            child.node_type = node_type;
+            let _ = child.refresh_metadata();
+            true
+        }
+        (None, Some(node_type)) => {
+            let Some(name) = path.file_name() else {
+                return false;
+            };
+            let name = name.to_string_lossy().into_owned();
+            directory.add_child(FsNode::new(name, path.to_path_buf(), node_type, None, Vec::new()));
+            true
+        }
+        (None, None) => false,
+    }
+}
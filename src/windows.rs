@@ -0,0 +1,115 @@
+use std::ffi::OsStr;
+use std::fs::{self, Metadata};
+use std::io;
+use std::os::windows::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+
+/// longest path the programs not asking for long paths can use
+pub const MAX_PATH: usize = 260;
+
+/// the roots of the drives of the machine, `C:\` and the like
+pub fn drives() -> Vec<PathBuf> {
+    (b'A'..=b'Z')
+        .map(|letter| PathBuf::from(format!("{}:\\", letter as char)))
+        .filter(|root| fs::metadata(root).is_ok())
+        .collect()
+}
+
+/// true if the entry has the hidden or the system attribute, which Explorer hides both
+pub fn is_hidden(metadata: &Metadata) -> bool {
+    metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+}
+
+/// true if the entry links to a directory, a directory symlink or a junction
+///
+/// Both are directories to Windows: they are removed with `remove_dir`, which
+/// leaves their target alone, and they are never walked into.
+pub fn is_directory_link(metadata: &Metadata) -> bool {
+    metadata.file_type().is_symlink_dir()
+}
+
+/// make a link at `path`, a directory symlink when `target` is a directory
+///
+/// A relative target is from the directory of the link, as it is read.
+pub fn symlink(target: &Path, path: &Path) -> io::Result<()> {
+    let resolved = match path.parent() {
+        Some(parent) => parent.join(target),
+        None => target.to_path_buf(),
+    };
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, path)
+    } else {
+        std::os::windows::fs::symlink_file(target, path)
+    }
+}
+
+/// a path as users write it, without the `\\?\` prefix `canonicalize` gives
+///
+/// The prefix is kept on paths too long to work without it.
+pub fn strip_verbatim(path: PathBuf) -> PathBuf {
+    let text = path.to_string_lossy();
+    let plain = if let Some(share) = text.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{share}")
+    } else if let Some(rest) = text.strip_prefix(r"\\?\")
+        && rest.as_bytes().get(1) == Some(&b':')
+    {
+        rest.to_string()
+    } else {
+        return path;
+    };
+    if plain.len() < MAX_PATH { PathBuf::from(plain) } else { path }
+}
+
+/// true if `path` is `ancestor` or below it, names compared without regard to case
+pub fn is_within(path: &Path, ancestor: &Path) -> bool {
+    let mut components = path.components();
+    ancestor
+        .components()
+        .all(|part| components.next().is_some_and(|other| fold(other.as_os_str()) == fold(part.as_os_str())))
+}
+
+/// a name in upper case, as the filesystem compares it
+fn fold(name: &OsStr) -> String {
+    name.to_string_lossy().to_uppercase()
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_the_verbatim_prefix_of_drives_and_shares() {
+        assert_eq!(strip_verbatim(PathBuf::from(r"\\?\C:\Users\a")), PathBuf::from(r"C:\Users\a"));
+        assert_eq!(strip_verbatim(PathBuf::from(r"\\?\UNC\server\share\a")), PathBuf::from(r"\\server\share\a"));
+        assert_eq!(strip_verbatim(PathBuf::from(r"C:\Users\a")), PathBuf::from(r"C:\Users\a"));
+        // a volume has no other way to be written
+        let volume = PathBuf::from(r"\\?\Volume{0a1b2c3d-0000-0000-0000-000000000000}\a");
+        assert_eq!(strip_verbatim(volume.clone()), volume);
+    }
+
+    #[test]
+    fn keeps_the_verbatim_prefix_of_long_paths() {
+        let long = PathBuf::from(format!(r"\\?\C:\{}", "a".repeat(MAX_PATH)));
+        assert_eq!(strip_verbatim(long.clone()), long);
+        let fits = format!(r"C:\{}", "a".repeat(MAX_PATH - 4));
+        assert_eq!(strip_verbatim(PathBuf::from(format!(r"\\?\{fits}"))), PathBuf::from(fits));
+    }
+
+    #[test]
+    fn compares_components_without_regard_to_case() {
+        assert!(is_within(Path::new(r"C:\Users\A\docs"), Path::new(r"c:\users\a")));
+        assert!(is_within(Path::new(r"C:\Users\A"), Path::new(r"C:\USERS\A")));
+        assert!(!is_within(Path::new(r"C:\Users\Ab"), Path::new(r"C:\Users\A")));
+        assert!(!is_within(Path::new(r"C:\Users"), Path::new(r"C:\Users\A")));
+    }
+
+    #[test]
+    fn tells_drives_and_shares_apart() {
+        assert!(!is_within(Path::new(r"D:\Users\a"), Path::new(r"C:\Users")));
+        assert!(is_within(Path::new(r"\\Server\Share\a"), Path::new(r"\\server\share")));
+        assert!(!is_within(Path::new(r"\\server\other\a"), Path::new(r"\\server\share")));
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crossterm::event::KeyCode;
+use midday_commander::harness::Harness;
+
+/// longest wait for a job of a test
+const SETTLE: Duration = Duration::from_secs(10);
+
+/// a directory of its own for each test, with a `src` and a `dest` directory in it
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(name: &str) -> Scratch {
+        let root = std::env::temp_dir().join(format!("midday-commander-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("dest")).unwrap();
+        Scratch(root)
+    }
+
+    fn src(&self) -> PathBuf {
+        self.0.join("src")
+    }
+
+    fn dest(&self) -> PathBuf {
+        self.0.join("dest")
+    }
+
+    fn harness(&self) -> Harness {
+        Harness::new(&self.src(), &self.dest(), 80, 24).unwrap()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn copies_marked_files_to_the_other_panel() {
+    let scratch = Scratch::new("copy");
+    fs::write(scratch.src().join("a.txt"), "a").unwrap();
+    fs::write(scratch.src().join("b.txt"), "b").unwrap();
+    fs::write(scratch.src().join("c.txt"), "c").unwrap();
+    let mut harness = scratch.harness();
+
+    // mark a.txt and c.txt, the cursor goes down after each mark
+    harness.key(KeyCode::Insert).unwrap();
+    harness.key(KeyCode::Down).unwrap();
+    harness.key(KeyCode::Insert).unwrap();
+    harness.key(KeyCode::F(5)).unwrap();
+    harness.settle(SETTLE).unwrap();
+
+    assert_eq!(fs::read_to_string(scratch.dest().join("a.txt")).unwrap(), "a");
+    assert_eq!(fs::read_to_string(scratch.dest().join("c.txt")).unwrap(), "c");
+    assert!(!scratch.dest().join("b.txt").exists());
+    let screen = harness.screen();
+    assert_eq!(screen.matches("a.txt").count(), 2, "{screen}");
+    assert_eq!(screen.matches("b.txt").count(), 1, "{screen}");
+}
+
+#[test]
+fn overwrites_an_existing_file_once_confirmed() {
+    let scratch = Scratch::new("overwrite");
+    fs::write(scratch.src().join("a.txt"), "new").unwrap();
+    fs::write(scratch.dest().join("a.txt"), "old").unwrap();
+    let mut harness = scratch.harness();
+
+    harness.key(KeyCode::F(5)).unwrap();
+    assert!(harness.contains("a.txt exists"), "{}", harness.screen());
+    assert_eq!(fs::read_to_string(scratch.dest().join("a.txt")).unwrap(), "old");
+
+    harness.key(KeyCode::Enter).unwrap();
+    harness.settle(SETTLE).unwrap();
+    assert_eq!(fs::read_to_string(scratch.dest().join("a.txt")).unwrap(), "new");
+    assert!(!harness.contains("exists"), "{}", harness.screen());
+}
+
+#[test]
+fn makes_a_directory_and_shows_it() {
+    let scratch = Scratch::new("mkdir");
+    let mut harness = scratch.harness();
+
+    harness.key(KeyCode::F(7)).unwrap();
+    harness.type_text("docs").unwrap();
+    harness.key(KeyCode::Enter).unwrap();
+
+    assert!(scratch.src().join("docs").is_dir());
+    assert!(harness.contains("docs"), "{}", harness.screen());
+}
+
+#[test]
+fn copies_into_the_same_directory_under_a_free_name() {
+    let scratch = Scratch::new("self-copy");
+    fs::write(scratch.src().join("a.txt"), "precious data").unwrap();
+    let mut harness = Harness::new(&scratch.src(), &scratch.src(), 80, 24).unwrap();
+
+    harness.key(KeyCode::F(5)).unwrap();
+    harness.settle(SETTLE).unwrap();
+
+    assert_eq!(fs::read_to_string(scratch.src().join("a.txt")).unwrap(), "precious data");
+    assert_eq!(fs::read_to_string(scratch.src().join("a (2).txt")).unwrap(), "precious data");
+}
+
+#[test]
+fn refuses_to_replace_a_directory_by_a_file() {
+    let scratch = Scratch::new("kind");
+    fs::write(scratch.src().join("a"), "file").unwrap();
+    fs::create_dir(scratch.dest().join("a")).unwrap();
+    fs::write(scratch.dest().join("a").join("inner.txt"), "kept").unwrap();
+    let mut harness = scratch.harness();
+
+    harness.key(KeyCode::F(5)).unwrap();
+    assert!(harness.contains("overwrite each other"), "{}", harness.screen());
+    harness.key(KeyCode::Enter).unwrap();
+    harness.settle(SETTLE).unwrap();
+
+    assert_eq!(fs::read_to_string(scratch.dest().join("a").join("inner.txt")).unwrap(), "kept");
+}